@@ -0,0 +1,20 @@
+/**
+ * penrose :: sending commands to a running window manager
+ *
+ * A tiny command line client for RemoteCommand so that window placement can be driven from
+ * scripts and menus:
+ *
+ *   remote_command move-window 0x1c00003 100 100 800 600
+ *   remote_command tile-window 0x1c00003
+ *
+ * The commands need to be enabled using Config::remote_commands before penrose will run them.
+ */
+use penrose::{contrib::extensions::RemoteCommand, Result, XcbConnection};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cmd: RemoteCommand = args.join(" ").parse()?;
+    let conn = XcbConnection::new()?;
+
+    cmd.send(&conn)
+}
@@ -7,22 +7,27 @@
 //! internal tests and reducing boilerplate.
 pub use crate::{
     core::{
-        bindings::{KeyBindings, KeyCode, KeyEventHandler, MouseBindings},
+        bindings::{KeyBindings, KeyCode, KeyCodeValue, KeyEventHandler, MouseBindings},
         client::Client,
         config::Config,
         data_types::{Region, ResizeAction},
         helpers::index_selectors,
-        layout::{Layout, LayoutConf},
+        input::{InputDevice, PointerConfig},
+        layout::{Layout, LayoutConf, Spacing},
         ring::{InsertPoint, Selector},
         screen::Screen,
         workspace::Workspace,
-        xconnection::{ClientMessage, Prop, Result, XConn, XEvent, Xid},
+        xconnection::{Atom, ClientAttr, ClientMessage, Prop, Result, XConn, XError, XEvent, Xid},
     },
     draw::Color,
     logging_error_handler, Backward, Forward, Less, More, PenroseError, WindowManager,
 };
 
-pub use std::{cell::Cell, collections::HashMap, fmt};
+pub use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+};
 
 pub type TestWM = WindowManager<TestXConn>;
 pub type TestKeyBindings = KeyBindings<TestXConn>;
@@ -74,6 +79,7 @@ pub fn row_layout(
     clients: &[&Client],
     _focused: Option<Xid>,
     monitor_region: &Region,
+    spacing: &Spacing,
     _max_main: u32,
     _ratio: f32,
 ) -> Vec<ResizeAction> {
@@ -81,7 +87,7 @@ pub fn row_layout(
         .as_rows(clients.len() as u32)
         .iter()
         .zip(clients)
-        .map(|(r, c)| (c.id(), Some(*r)))
+        .map(|(r, c)| (c.id(), Some(spacing.pad(r))))
         .collect()
 }
 
@@ -202,8 +208,201 @@ __impl_stub_xcon! {
     }
 }
 
+// A helper for tests that need the X server to report specific properties for some clients. Calls
+// that change the state of the X server are recorded in the same way as for RecordingXConn.
+pub struct ConfigurableXConn {
+    screens: Vec<Screen>,
+    atoms: RefCell<Vec<String>>,
+    props: RefCell<HashMap<(Xid, String), Prop>>,
+    geometries: HashMap<Xid, Region>,
+    pointer_devices: Vec<InputDevice>,
+    failing_devices: Vec<u16>,
+    keycodes: RefCell<HashMap<String, KeyCodeValue>>,
+    events: RefCell<Vec<XEvent>>,
+    calls: RefCell<Vec<RecordedCall>>,
+}
+
+impl fmt::Debug for ConfigurableXConn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigurableXConn")
+            .field("screens", &self.screens)
+            .field("props", &self.props.borrow())
+            .finish()
+    }
+}
+
+impl Default for ConfigurableXConn {
+    fn default() -> Self {
+        Self::new(vec![Screen::new(Region::new(0, 0, 1000, 800), 0)])
+    }
+}
+
+impl ConfigurableXConn {
+    pub fn new(screens: Vec<Screen>) -> Self {
+        Self {
+            screens,
+            atoms: RefCell::new(Vec::new()),
+            props: RefCell::new(HashMap::new()),
+            geometries: HashMap::new(),
+            pointer_devices: Vec::new(),
+            failing_devices: Vec::new(),
+            keycodes: RefCell::new(HashMap::new()),
+            events: RefCell::new(Vec::new()),
+            calls: RefCell::new(Vec::new()),
+        }
+    }
+
+    // Return 'events' from wait_for_event followed by EXIT_CODE key presses
+    pub fn with_events(self, events: Vec<XEvent>) -> Self {
+        self.events.replace(events);
+        self
+    }
+
+    // Report the given pointer devices as attached, failing to configure any of them whose ID is
+    // in 'failing'
+    pub fn with_pointer_devices(mut self, devices: Vec<InputDevice>, failing: Vec<u16>) -> Self {
+        self.pointer_devices = devices;
+        self.failing_devices = failing;
+        self
+    }
+
+    pub fn with_prop(self, id: Xid, atom: Atom, prop: Prop) -> Self {
+        self.set_prop(id, atom, prop);
+        self
+    }
+
+    pub fn with_geometry(mut self, id: Xid, r: Region) -> Self {
+        self.geometries.insert(id, r);
+        self
+    }
+
+    // Change the value reported for a client property from now on
+    pub fn set_prop(&self, id: Xid, atom: Atom, prop: Prop) {
+        let key = (id, atom.as_ref().to_string());
+        self.props.borrow_mut().insert(key, prop);
+    }
+
+    // Change the keyboard mapping reported from now on
+    pub fn set_keycodes(&self, keycodes: HashMap<String, KeyCodeValue>) {
+        self.keycodes.replace(keycodes);
+    }
+
+    pub fn clear(&self) {
+        self.calls.borrow_mut().clear();
+    }
+
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.replace(Vec::new())
+    }
+
+    // The names of the methods that have been called, clearing the recorded calls
+    pub fn methods(&self) -> Vec<String> {
+        self.calls().into_iter().map(|(m, _)| m).collect()
+    }
+
+    fn add_call(&self, method: &str, args: Vec<String>) {
+        self.calls.borrow_mut().push((method.to_string(), args));
+    }
+}
+
+__impl_stub_xcon! {
+    for ConfigurableXConn;
+
+    atom_queries: {
+        fn mock_atom_id(&self, name: &str) -> Result<Xid> {
+            let mut atoms = self.atoms.borrow_mut();
+            let ix = match atoms.iter().position(|a| a == name) {
+                Some(ix) => ix,
+                None => {
+                    atoms.push(name.to_string());
+                    atoms.len() - 1
+                }
+            };
+            Ok(ix as Xid + 1)
+        }
+
+        fn mock_atom_name(&self, atom: Xid) -> Result<String> {
+            let atoms = self.atoms.borrow();
+            match (atom as usize).checked_sub(1).and_then(|ix| atoms.get(ix)) {
+                Some(name) => Ok(name.clone()),
+                None => Err(XError::Raw(format!("unknown atom: {}", atom))),
+            }
+        }
+    }
+    client_properties: {
+        fn mock_get_prop(&self, id: Xid, name: &str) -> Result<Prop> {
+            match self.props.borrow().get(&(id, name.to_string())) {
+                Some(prop) => Ok(prop.clone()),
+                None => Err(XError::MissingProperty(name.into(), id)),
+            }
+        }
+
+        fn mock_change_prop(&self, id: Xid, name: &str, val: Prop) -> Result<()> {
+            self.add_call("change_prop", strings!(id, name, val));
+            Ok(())
+        }
+    }
+    client_handler: {
+        fn mock_kill_client(&self, id: Xid) -> Result<()> {
+            self.add_call("kill_client", strings!(id));
+            Ok(())
+        }
+    }
+    client_config: {
+        fn mock_position_client(&self, id: Xid, r: Region, border: u32, stack_above: bool) -> Result<()> {
+            self.add_call("position_client", strings!(id, r, border, stack_above));
+            Ok(())
+        }
+
+        fn mock_raise_client(&self, id: Xid) -> Result<()> {
+            self.add_call("raise_client", strings!(id));
+            Ok(())
+        }
+    }
+    event_handler: {
+        fn mock_wait_for_event(&self) -> Result<XEvent> {
+            let mut events = self.events.borrow_mut();
+            if events.is_empty() {
+                return Ok(XEvent::KeyPress(EXIT_CODE));
+            }
+            Ok(events.remove(0))
+        }
+
+        fn mock_send_client_event(&self, msg: ClientMessage) -> Result<()> {
+            self.add_call("send_client_event", strings!(msg));
+            Ok(())
+        }
+    }
+    state: {
+        fn mock_current_screens(&self) -> Result<Vec<Screen>> {
+            Ok(self.screens.clone())
+        }
+
+        fn mock_client_geometry(&self, id: Xid) -> Result<Region> {
+            Ok(self.geometries.get(&id).copied().unwrap_or_default())
+        }
+    }
+    conn: {
+        fn mock_keycodes(&self) -> Result<HashMap<String, KeyCodeValue>> {
+            Ok(self.keycodes.borrow().clone())
+        }
+
+        fn mock_pointer_devices(&self) -> Result<Vec<InputDevice>> {
+            Ok(self.pointer_devices.clone())
+        }
+
+        fn mock_configure_pointer_device(&self, device: &InputDevice, _: &PointerConfig) -> Result<()> {
+            if self.failing_devices.contains(&device.id) {
+                return Err(XError::Raw("mocked".into()));
+            }
+            self.add_call("configure_pointer_device", strings!(device.id));
+            Ok(())
+        }
+    }
+}
+
 // A helper for checking that calls to the X server are triggered correctly
-pub struct RecordingXConn(Cell<Vec<(String, Vec<String>)>>);
+pub struct RecordingXConn(Cell<Vec<(String, Vec<String>)>>, Cell<Vec<InputDevice>>);
 
 // Tag for a call through to the X server: maps method -> stringified args
 pub type RecordedCall = (String, Vec<String>);
@@ -216,7 +415,12 @@ impl fmt::Debug for RecordingXConn {
 
 impl RecordingXConn {
     pub fn init() -> Self {
-        Self(Cell::new(Vec::new()))
+        Self(Cell::new(Vec::new()), Cell::new(Vec::new()))
+    }
+
+    // Set the keyboards that will be reported as attached from now on
+    pub fn set_keyboards(&self, keyboards: Vec<InputDevice>) {
+        self.1.set(keyboards);
     }
 
     pub fn clear(&self) {
@@ -246,6 +450,11 @@ __impl_stub_xcon! {
         }
     }
     client_properties: {
+        fn mock_get_prop(&self, id: Xid, name: &str) -> Result<Prop> {
+            self.add_call("get_prop", strings!(id, name));
+            Err(XError::Raw("mocked".into()))
+        }
+
         fn mock_change_prop(&self, id: Xid, name: &str, val: Prop) -> Result<()> {
             self.add_call("change_prop", strings!(id, name, val));
             Ok(())
@@ -255,6 +464,11 @@ __impl_stub_xcon! {
             self.add_call("delete_prop", strings!(id, name));
             Ok(())
         }
+
+        fn mock_client_name(&self, id: Xid) -> Result<String> {
+            self.add_call("client_name", strings!(id));
+            Ok(format!("client {}", id))
+        }
     }
     client_handler: {
         fn mock_focus_client(&self, id: Xid) -> Result<()> {
@@ -262,6 +476,29 @@ __impl_stub_xcon! {
             Ok(())
         }
 
+        fn mock_kill_client(&self, id: Xid) -> Result<()> {
+            self.add_call("kill_client", strings!(id));
+            Ok(())
+        }
+
+        fn mock_create_input_window(&self, r: Region) -> Result<Xid> {
+            self.add_call("create_input_window", strings!(r));
+            let calls = self.0.take();
+            let id = 1000 + calls.len() as Xid;
+            self.0.set(calls);
+            Ok(id)
+        }
+
+        fn mock_destroy_client(&self, id: Xid) -> Result<()> {
+            self.add_call("destroy_client", strings!(id));
+            Ok(())
+        }
+
+        fn mock_map_client(&self, id: Xid) -> Result<()> {
+            self.add_call("map_client", strings!(id));
+            Ok(())
+        }
+
         fn mock_map_client_if_needed(&self, win: Option<&mut Client>) -> Result<()> {
             self.add_call("map_client_if_needed", strings!(win));
             Ok(())
@@ -273,11 +510,26 @@ __impl_stub_xcon! {
         }
     }
     client_config: {
+        fn mock_set_client_attributes(&self, id: Xid, data: &[ClientAttr]) -> Result<()> {
+            self.add_call("set_client_attributes", strings!(id, data));
+            Ok(())
+        }
+
         fn mock_set_client_border_color(&self, id: Xid, color: Color) -> Result<()> {
             self.add_call("set_client_border_color", strings!(id, color));
             Ok(())
         }
 
+        fn mock_set_window_background(&self, id: Xid, color: Color) -> Result<()> {
+            self.add_call("set_window_background", strings!(id, color));
+            Ok(())
+        }
+
+        fn mock_set_client_border_colors(&self, id: Xid, outer: Color, inner: Color, inner_px: u32) -> Result<()> {
+            self.add_call("set_client_border_colors", strings!(id, outer, inner, inner_px));
+            Ok(())
+        }
+
         fn mock_position_client(&self, id: Xid, r: Region, border: u32, stack_above: bool) -> Result<()> {
             self.add_call("position_client", strings!(id, r, border, stack_above));
             Ok(())
@@ -302,11 +554,77 @@ __impl_stub_xcon! {
                 Screen::new(Region::new(1366, 0, 1366, 768), 0),
             ])
         }
+
+        fn mock_warp_cursor(&self, win_id: Option<Xid>, _: &Screen) -> Result<()> {
+            self.add_call("warp_cursor", strings!(win_id));
+            Ok(())
+        }
     }
     conn: {
         fn mock_is_managed_client(&self, c: &Client) -> bool {
             self.add_call("is_managed_client", strings!(c.id()));
             true
         }
+
+        fn mock_grab_keys(
+            &self,
+            key_bindings: &KeyBindings<Self>,
+            mouse_bindings: &MouseBindings<Self>,
+        ) -> Result<()> {
+            let mut codes: Vec<String> = key_bindings.keys().map(|k| k.code.to_string()).collect();
+            codes.sort();
+            codes.push(format!("{} mouse bindings", mouse_bindings.len()));
+            self.add_call("grab_keys", codes);
+            Ok(())
+        }
+
+        fn mock_ungrab_keys(&self) -> Result<()> {
+            self.add_call("ungrab_keys", vec![]);
+            Ok(())
+        }
+
+        fn mock_keyboard_devices(&self) -> Result<Vec<InputDevice>> {
+            self.add_call("keyboard_devices", vec![]);
+            let keyboards = self.1.take();
+            self.1.set(keyboards.clone());
+            Ok(keyboards)
+        }
+
+        fn mock_grab_keyboard(&self) -> Result<()> {
+            self.add_call("grab_keyboard", vec![]);
+            Ok(())
+        }
+
+        fn mock_ungrab_keyboard(&self) -> Result<()> {
+            self.add_call("ungrab_keyboard", vec![]);
+            Ok(())
+        }
+
+        fn mock_grab_pointer(&self) -> Result<()> {
+            self.add_call("grab_pointer", vec![]);
+            Ok(())
+        }
+
+        fn mock_ungrab_pointer(&self) -> Result<()> {
+            self.add_call("ungrab_pointer", vec![]);
+            Ok(())
+        }
+
+        fn mock_refresh_keymap(&self) -> Result<()> {
+            self.add_call("refresh_keymap", vec![]);
+            Ok(())
+        }
+
+        fn mock_pointer_devices(&self) -> Result<Vec<InputDevice>> {
+            Ok(vec![
+                InputDevice { id: 11, name: "SynPS/2 Synaptics TouchPad".into() },
+                InputDevice { id: 12, name: "Logitech USB Receiver".into() },
+            ])
+        }
+
+        fn mock_configure_pointer_device(&self, device: &InputDevice, _: &PointerConfig) -> Result<()> {
+            self.add_call("configure_pointer_device", strings!(device.id));
+            Ok(())
+        }
     }
 }
@@ -1,19 +1,28 @@
 //! Additional helper functions and actions for use with penrose.
 use crate::{
+    contrib::{
+        extensions::dmenu::{DMenu, DMenuConfig, MenuMatch},
+        frecency::Frecency,
+    },
     core::{
-        bindings::KeyEventHandler,
+        bindings::{KeyEventHandler, MouseButton, MouseEvent, MouseEventHandler},
         client::Client,
-        data_types::RelativePosition,
-        helpers::{spawn, spawn_for_output},
+        data_types::{Change, Region, RelativePosition},
+        helpers::spawn_for_output,
         layout::Layout,
-        manager::WindowManager,
+        manager::{InteractiveSelection, WindowManager},
+        process::spawn,
         ring::Selector,
         workspace::Workspace,
-        xconnection::XConn,
+        xconnection::{XConn, Xid},
+        xdg::{self, DesktopEntry},
     },
+    draw::{KeyPressDraw, PMenu, PMenuMatch, WindowHints},
     Result,
 };
 
+use std::{collections::BTreeSet, env, fs, os::unix::fs::PermissionsExt};
+
 /**
  * Jump to, or create, a [Workspace]
  *
@@ -61,11 +70,405 @@ pub fn focus_or_spawn<X: XConn>(
             let workspace = client.workspace();
             wm.focus_workspace(&Selector::Index(workspace))
         } else {
-            spawn(&command)
+            wm.spawn(&command)
+        }
+    })
+}
+
+/**
+ * Grow or shrink the main area of the active layout by `step` using the scroll wheel.
+ *
+ * Scrolling up grows the main area and scrolling down shrinks it, giving finer control than the
+ * key bindings for [update_main_ratio][1]. Bind this to both [ScrollUp][2] and [ScrollDown][3]
+ * with a modifier so that it can be used with the pointer anywhere on the screen:
+ *
+ * ```no_run
+ * # #[macro_use] extern crate penrose;
+ * # use penrose::{core::{bindings::MouseEvent, xconnection::XConn}, contrib::actions::scroll_main_ratio, WindowManager};
+ * # fn example<X: XConn + 'static>() -> penrose::core::bindings::MouseBindings<X> {
+ * gen_mousebindings! {
+ *     Press ScrollUp + [Meta] => scroll_main_ratio(0.01),
+ *     Press ScrollDown + [Meta] => scroll_main_ratio(0.01)
+ * }
+ * # }
+ * # fn main() {}
+ * ```
+ *
+ * [1]: crate::core::manager::WindowManager::update_main_ratio
+ * [2]: crate::core::bindings::MouseButton::ScrollUp
+ * [3]: crate::core::bindings::MouseButton::ScrollDown
+ */
+pub fn scroll_main_ratio<X: XConn>(step: f32) -> MouseEventHandler<X> {
+    Box::new(
+        move |wm: &mut WindowManager<X>, e: &MouseEvent| match e.state.button {
+            MouseButton::ScrollUp => wm.update_main_ratio_by(Change::More, step),
+            MouseButton::ScrollDown => wm.update_main_ratio_by(Change::Less, step),
+            _ => Ok(()),
+        },
+    )
+}
+
+/**
+ * Pick one of the minimized [clients][Client] on the active [Workspace] to restore using
+ * [DMenu], with the most recently minimized client listed first.
+ *
+ * See [minimize_focused][1] for minimizing clients.
+ *
+ * [1]: crate::core::manager::WindowManager::minimize_focused
+ */
+pub fn restore_minimized_with_dmenu<X: XConn>(config: DMenuConfig) -> KeyEventHandler<X> {
+    Box::new(move |wm: &mut WindowManager<X>| {
+        let ids: Vec<Xid> = wm
+            .minimized_clients(wm.active_workspace_index())
+            .into_iter()
+            .rev()
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let titles: Vec<String> = ids
+            .iter()
+            .map(|&id| match wm.client(&Selector::WinId(id)) {
+                Some(c) => c.wm_name().to_string(),
+                None => id.to_string(),
+            })
+            .collect();
+
+        let menu = DMenu::new("restore:", titles, config.clone());
+        match menu.run(wm.active_screen_index())? {
+            MenuMatch::Line(i, _) => wm.restore_minimized(ids[i]),
+            _ => Ok(()),
+        }
+    })
+}
+
+/**
+ * Launch a program picked from the executables found on your `$PATH` using a [PMenu].
+ *
+ * If nothing matches what has been typed when Return is pressed, the input is run as a command
+ * instead so that arguments can be passed to the program being launched.
+ *
+ * Everything that is launched is recorded in 'history', with the programs and commands that are
+ * launched most frequently and recently listed first. Commands that were typed in are offered
+ * again alongside the programs on `$PATH`. Pressing Delete removes the selected item from the
+ * history and reopens the menu.
+ *
+ * ```no_run
+ * # use penrose::__test_helpers::*;
+ * use penrose::{
+ *     contrib::{actions::launch_with_pmenu, frecency::Frecency},
+ *     draw::{KeyPressDraw, PMenu},
+ * };
+ *
+ * # fn example<X: XConn, D: KeyPressDraw + 'static>(menu: PMenu<D>) -> KeyEventHandler<X> {
+ * // history is kept in ~/.local/share/penrose/run_history
+ * launch_with_pmenu(menu, Frecency::named("run_history"))
+ * # }
+ * ```
+ */
+pub fn launch_with_pmenu<X, D>(mut menu: PMenu<D>, mut history: Frecency) -> KeyEventHandler<X>
+where
+    X: XConn,
+    D: KeyPressDraw + 'static,
+{
+    Box::new(move |wm: &mut WindowManager<X>| loop {
+        let mut programs: Vec<String> = executables_in_path()
+            .into_iter()
+            .chain(history.items().map(String::from))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        history.sort_by_score(&mut programs, |s| s.as_str());
+
+        match menu.get_selection_from_input("run:", programs, wm.active_screen_index())? {
+            PMenuMatch::Line(_, cmd) | PMenuMatch::UserInput(cmd) => {
+                wm.spawn(cmd.as_str())?;
+                return history.record(cmd);
+            }
+            PMenuMatch::Delete(_, cmd) => history.remove(&cmd)?,
+            PMenuMatch::NoMatch => return Ok(()),
         }
     })
 }
 
+/**
+ * Launch an installed application picked from its desktop entry using a [PMenu], in the style of
+ * the `drun` mode of rofi.
+ *
+ * Applications are found using [application_entries][xdg::application_entries] and are listed by
+ * name (and generic name), with those picked most frequently and recently first according to
+ * 'history'. As with [launch_with_pmenu], if nothing matches what has been typed when Return is
+ * pressed then the input is run as a command and pressing Delete removes the selected
+ * application from the history.
+ *
+ * ```no_run
+ * # use penrose::__test_helpers::*;
+ * use penrose::{
+ *     contrib::{actions::launch_desktop_entry_with_pmenu, frecency::Frecency},
+ *     draw::{KeyPressDraw, PMenu},
+ * };
+ *
+ * # fn example<X: XConn, D: KeyPressDraw + 'static>(menu: PMenu<D>) -> KeyEventHandler<X> {
+ * // history is kept in ~/.local/share/penrose/drun_history
+ * launch_desktop_entry_with_pmenu(menu, Frecency::named("drun_history"))
+ * # }
+ * ```
+ */
+pub fn launch_desktop_entry_with_pmenu<X, D>(
+    mut menu: PMenu<D>,
+    mut history: Frecency,
+) -> KeyEventHandler<X>
+where
+    X: XConn,
+    D: KeyPressDraw + 'static,
+{
+    Box::new(move |wm: &mut WindowManager<X>| loop {
+        let mut entries = xdg::application_entries();
+        entries.sort_by_key(|e| e.name.to_lowercase());
+        history.sort_by_score(&mut entries, |e| &e.id);
+
+        let labels = entries.iter().map(DesktopEntry::label).collect();
+        match menu.get_selection_from_input("drun:", labels, wm.active_screen_index())? {
+            PMenuMatch::Line(i, _) => {
+                let entry = &entries[i];
+                if let Some(program) = entry.spawn() {
+                    wm.spawn_in_context(program)?;
+                    history.record(entry.id.clone())?;
+                }
+                return Ok(());
+            }
+            PMenuMatch::UserInput(cmd) => return wm.spawn(cmd),
+            PMenuMatch::Delete(i, _) => history.remove(&entries[i].id)?,
+            PMenuMatch::NoMatch => return Ok(()),
+        }
+    })
+}
+
+/**
+ * Pick any [Client] using a [PMenu] and focus it, switching to its [Workspace] first.
+ *
+ * Clients are listed by workspace name and title. Picking a client that has been minimized will
+ * restore it.
+ */
+pub fn switch_client_with_pmenu<X, D>(mut menu: PMenu<D>) -> KeyEventHandler<X>
+where
+    X: XConn,
+    D: KeyPressDraw + 'static,
+{
+    Box::new(move |wm: &mut WindowManager<X>| {
+        let (clients, titles): (Vec<(Xid, usize, bool)>, Vec<String>) = wm
+            .all_clients(&Selector::Any)
+            .iter()
+            .map(|c| {
+                let title = match wm.workspace(&Selector::Index(c.workspace())) {
+                    Some(ws) => format!("{}: {}", ws.name(), c.wm_name()),
+                    None => c.wm_name().to_string(),
+                };
+                ((c.id(), c.workspace(), c.is_minimized()), title)
+            })
+            .unzip();
+
+        let (id, wix, minimized) =
+            match menu.get_selection_from_input("window:", titles, wm.active_screen_index())? {
+                PMenuMatch::Line(i, _) => clients[i],
+                _ => return Ok(()),
+            };
+
+        wm.focus_workspace(&Selector::Index(wix))?;
+        if minimized {
+            wm.restore_minimized(id)
+        } else {
+            wm.focus_client(&Selector::WinId(id)).map(|_| ())
+        }
+    })
+}
+
+/**
+ * Show a badge labelled with a key over each visible [Client] and focus the client whose key is
+ * then pressed.
+ *
+ * This is a much quicker way to get to a specific client on a busy workspace than cycling focus.
+ * Clients on all screens are labelled, working through the screens in order, and pressing Escape
+ * or any key without a badge leaves focus where it was.
+ */
+pub fn goto_window_overlay<X, D>(mut hints: WindowHints<D>) -> KeyEventHandler<X>
+where
+    X: XConn,
+    D: KeyPressDraw + 'static,
+{
+    Box::new(move |wm: &mut WindowManager<X>| {
+        let mut targets: Vec<(Xid, Region)> = vec![];
+        for i in 0..wm.n_screens() {
+            let wix = match wm.screen(&Selector::Index(i)) {
+                Some(s) => s.wix,
+                None => continue,
+            };
+            let ids = match wm.workspace(&Selector::Index(wix)) {
+                Some(ws) => ws.client_ids(),
+                None => continue,
+            };
+            for id in ids {
+                if matches!(wm.client(&Selector::WinId(id)), Some(c) if c.mapped) {
+                    targets.push((id, wm.conn().client_geometry(id)?));
+                }
+            }
+        }
+
+        match hints.select(&targets)? {
+            Some(id) => wm.focus_client(&Selector::WinId(id)).map(|_| ()),
+            None => Ok(()),
+        }
+    })
+}
+
+/**
+ * Show every client on the active workspace at once in a zoomed out grid and focus the one that
+ * is picked, in the style of the "expose" overview found in other desktops.
+ *
+ * Each client is labelled with a badge showing its title and a key to press. Pick a client by
+ * pressing its key or by clicking on it: Escape or clicking with any other button leaves focus
+ * where it was. Either way the workspace's layout is then applied again and any floating clients
+ * are put back where they were. Minimized clients are not shown.
+ */
+pub fn expose<X, D>(mut hints: WindowHints<D>) -> KeyEventHandler<X>
+where
+    X: XConn,
+    D: KeyPressDraw + 'static,
+{
+    Box::new(move |wm: &mut WindowManager<X>| {
+        let screen_index = wm.active_screen_index();
+        let screen = match wm.screen_size(screen_index) {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let clients: Vec<(Xid, bool, String)> = wm
+            .active_workspace()
+            .client_ids()
+            .into_iter()
+            .filter_map(|id| wm.client(&Selector::WinId(id)))
+            .filter(|c| !c.is_minimized())
+            .take(hints.keys().len())
+            .map(|c| (c.id(), c.floating, c.wm_name().to_string()))
+            .collect();
+        if clients.is_empty() {
+            return Ok(());
+        }
+
+        // floating clients are not positioned by the layout so they need to be put back by hand
+        let mut floating = vec![];
+        for &(id, is_floating, _) in clients.iter() {
+            if is_floating {
+                floating.push((id, wm.conn().client_geometry(id)?));
+            }
+        }
+
+        let targets: Vec<(Xid, Region)> = clients
+            .iter()
+            .map(|&(id, _, _)| id)
+            .zip(expose_grid(screen, clients.len()))
+            .collect();
+        for &(id, r) in targets.iter() {
+            wm.show_client(id)?;
+            wm.position_client(id, r, true)?;
+        }
+
+        let focused = wm.focused_client_id();
+        let titles: Vec<String> = clients.into_iter().map(|(_, _, title)| title).collect();
+        let keys: Vec<String> = hints.keys()[..targets.len()]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let key_names: Vec<&str> = keys.iter().map(|k| k.as_str()).collect();
+
+        let picked = match hints.show(&targets, &titles) {
+            Ok(_) => wm.select_interactive(&key_names),
+            Err(e) => Err(e.into()),
+        };
+        hints.hide()?;
+        for (id, r) in floating {
+            wm.position_client(id, r, false)?;
+        }
+        wm.layout_screen(screen_index)?;
+
+        let chosen = match picked? {
+            Some(InteractiveSelection::KeyPressed(k)) => {
+                keys.iter().position(|key| *key == k).map(|i| targets[i].0)
+            }
+            Some(InteractiveSelection::Clicked(p)) => targets
+                .iter()
+                .find(|(_, r)| r.contains_point(&p))
+                .map(|&(id, _)| id),
+            None => None,
+        };
+
+        // the pointer may have ended up over a different client while they were moved around
+        match chosen.or(focused) {
+            Some(id) => wm.focus_client(&Selector::WinId(id)).map(|_| ()),
+            None => Ok(()),
+        }
+    })
+}
+
+// The cells of a grid covering 'screen' for showing 'n' clients, filling rows from the top with
+// any partial final row centered. Cells are inset from each other so that the clients appear to
+// be zoomed out.
+fn expose_grid(screen: Region, n: usize) -> Vec<Region> {
+    if n == 0 {
+        return vec![];
+    }
+
+    let cols = (1..=n).find(|c| c * c >= n).unwrap_or(n);
+    let rows = (1..=n).find(|r| r * cols >= n).unwrap_or(n);
+    let (x, y, w, h) = screen.values();
+    let (cw, ch) = (w / cols as u32, h / rows as u32);
+    let gap = cw.min(ch) / 20;
+
+    (0..n)
+        .map(|i| {
+            let (row, col) = (i / cols, i % cols);
+            let in_row = if row == rows - 1 {
+                n - row * cols
+            } else {
+                cols
+            };
+            let offset = (cols - in_row) as u32 * cw / 2;
+
+            Region::new(
+                x + offset + col as u32 * cw + gap,
+                y + row as u32 * ch + gap,
+                cw - 2 * gap,
+                ch - 2 * gap,
+            )
+        })
+        .collect()
+}
+
+// The names of the executable files in each directory on $PATH, sorted and without duplicates
+fn executables_in_path() -> Vec<String> {
+    let path = env::var("PATH").unwrap_or_default();
+    let mut programs = BTreeSet::new();
+
+    for entry in env::split_paths(&path)
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+    {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let is_executable = match entry.metadata() {
+            Ok(m) => m.is_file() && m.permissions().mode() & 0o111 != 0,
+            Err(_) => false,
+        };
+        if is_executable {
+            programs.insert(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    programs.into_iter().collect()
+}
+
 /**
  * Detect the current monitor set up and arrange the monitors if needed using [xrandr][1].
  *
@@ -110,3 +513,35 @@ pub fn update_monitors_via_xrandr(
         _ => Ok(()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regions(rs: &[(u32, u32, u32, u32)]) -> Vec<Region> {
+        rs.iter()
+            .map(|&(x, y, w, h)| Region::new(x, y, w, h))
+            .collect()
+    }
+
+    test_cases! {
+        expose_grid;
+        args: (n: usize, expected: &[(u32, u32, u32, u32)]);
+
+        case: no_clients => (0, &[]);
+        case: single_client => (1, &[(40, 40, 920, 720)]);
+        case: partial_last_row_is_centered => (
+            3,
+            &[(20, 20, 460, 360), (520, 20, 460, 360), (270, 420, 460, 360)]
+        );
+        case: full_grid => (
+            4,
+            &[(20, 20, 460, 360), (520, 20, 460, 360), (20, 420, 460, 360), (520, 420, 460, 360)]
+        );
+
+        body: {
+            let screen = Region::new(0, 0, 1000, 800);
+            assert_eq!(expose_grid(screen, n), regions(expected));
+        }
+    }
+}
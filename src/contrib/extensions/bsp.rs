@@ -0,0 +1,489 @@
+//! A binary space partitioning layout in the style of bspwm
+use crate::{
+    core::{
+        bindings::KeyEventHandler,
+        data_types::Region,
+        hooks::Hook,
+        layout::{Layout, Spacing},
+        manager::WindowManager,
+        ring::Selector,
+        xconnection::{XConn, Xid},
+    },
+    Result,
+};
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// The direction in which a region is divided in two
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Split {
+    /// One region above the other
+    Horizontal,
+    /// One region beside the other
+    Vertical,
+}
+
+impl Split {
+    fn flipped(self) -> Self {
+        match self {
+            Self::Horizontal => Self::Vertical,
+            Self::Vertical => Self::Horizontal,
+        }
+    }
+}
+
+/// Tile clients by repeatedly splitting the focused region in two, as done by bspwm.
+///
+/// Each new client splits the region of the client that was focused before it appeared, with
+/// the direction of each split alternating between [Vertical][Split::Vertical] and
+/// [Horizontal][Split::Horizontal] as you go down the tree unless a direction has been
+/// [preselected][Bsp::preselect]. When a client is removed its sibling takes over the space that
+/// they shared. The tree for a workspace can be [rotated][Bsp::rotate] by 90 degrees or
+/// [balanced][Bsp::balance] so that all clients get an equal share of the screen.
+///
+/// Layout functions do not hold any state of their own so the tree for each workspace is kept by
+/// the [Hook] returned from [get_hook][Bsp::get_hook], which positions the clients after the
+/// floating [Layout] returned by [layout][Bsp::layout] has been applied. Both need to be passed
+/// to your [WindowManager], using the same `spacing` that it has been configured with.
+///
+/// # Example
+/// ```no_run
+/// # use penrose::core::{hooks::Hooks, layout::{Layout, Spacing}, xconnection::XConn};
+/// use penrose::contrib::extensions::{bsp::Split, Bsp};
+///
+/// # fn example<X: XConn + 'static>() -> penrose::Result<()> {
+/// let bsp = Bsp::new("[bsp]", Spacing::new(2, 5));
+///
+/// let layouts: Vec<Layout> = vec![bsp.layout()];
+/// let hooks: Hooks<X> = vec![bsp.get_hook()];
+///
+/// // each of these can be used as the action for a key binding
+/// let rotate = bsp.rotate::<X>();
+/// let balance = bsp.balance::<X>();
+/// let split_right = bsp.preselect::<X>(Split::Vertical);
+/// let split_below = bsp.preselect::<X>(Split::Horizontal);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bsp {
+    state: Rc<RefCell<BspState>>,
+}
+
+impl Bsp {
+    /// Create a new Bsp for workspaces using a layout with the symbol `layout_symbol`, leaving
+    /// `spacing` around each client.
+    pub fn new(layout_symbol: impl Into<String>, spacing: Spacing) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(BspState {
+                symbol: layout_symbol.into(),
+                spacing,
+                trees: HashMap::new(),
+                last_focused: HashMap::new(),
+                preselected: None,
+            })),
+        }
+    }
+
+    /// The [Layout] to add to your layouts for the tree to be used
+    pub fn layout(&self) -> Layout {
+        Layout::floating(self.state.borrow().symbol.clone())
+    }
+
+    /// Construct the associated [Hook] for adding to the [WindowManager].
+    ///
+    /// NOTE: If the hook is not registered, clients on workspaces using the [layout][Bsp::layout]
+    ///       will not be positioned.
+    pub fn get_hook(&self) -> Box<Self> {
+        Box::new(self.clone())
+    }
+
+    /// Rotate the tree for the active workspace clockwise by 90 degrees
+    pub fn rotate<X: XConn>(&self) -> KeyEventHandler<X> {
+        self.modify_active_tree(Node::rotate)
+    }
+
+    /// Resize every split for the active workspace so that all clients are given the same space
+    pub fn balance<X: XConn>(&self) -> KeyEventHandler<X> {
+        self.modify_active_tree(|n| {
+            n.balance();
+        })
+    }
+
+    /// Split the focused region in the direction of `split` when the next client is added,
+    /// rather than alternating. Calling this again with the same direction before a client is
+    /// added cancels the preselection.
+    pub fn preselect<X: XConn>(&self, split: Split) -> KeyEventHandler<X> {
+        let state = Rc::clone(&self.state);
+        Box::new(move |_: &mut WindowManager<X>| {
+            let mut state = state.borrow_mut();
+            state.preselected = match state.preselected {
+                Some(s) if s == split => None,
+                _ => Some(split),
+            };
+
+            Ok(())
+        })
+    }
+
+    fn modify_active_tree<X, F>(&self, f: F) -> KeyEventHandler<X>
+    where
+        X: XConn,
+        F: Fn(&mut Node) + 'static,
+    {
+        let state = Rc::clone(&self.state);
+        Box::new(move |wm: &mut WindowManager<X>| {
+            let wix = wm.active_workspace_index();
+            match state.borrow_mut().trees.get_mut(&wix) {
+                Some(tree) => f(tree),
+                None => return Ok(()),
+            };
+
+            wm.layout_screen(wm.active_screen_index())
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BspState {
+    symbol: String,
+    spacing: Spacing,
+    trees: HashMap<usize, Node>,
+    last_focused: HashMap<usize, Xid>,
+    preselected: Option<Split>,
+}
+
+impl BspState {
+    // Bring the tree for a workspace up to date with its tiled clients
+    fn sync(&mut self, wix: usize, ids: &[Xid], focused: Option<Xid>) {
+        let mut tree = self
+            .trees
+            .remove(&wix)
+            .and_then(|t| t.retain(&|id| ids.contains(&id)));
+
+        for &id in ids {
+            tree = Some(match tree {
+                None => Node::Leaf(id),
+                Some(t) if t.contains(id) => t,
+                Some(mut t) => {
+                    let target = self
+                        .last_focused
+                        .get(&wix)
+                        .copied()
+                        .filter(|&f| t.contains(f))
+                        .unwrap_or_else(|| t.last_leaf());
+                    t.insert(target, id, self.preselected.take(), None);
+                    t
+                }
+            });
+        }
+
+        if let Some(tree) = tree {
+            self.trees.insert(wix, tree);
+        }
+        match focused {
+            Some(id) => self.last_focused.insert(wix, id),
+            None => self.last_focused.remove(&wix),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Leaf(Xid),
+    Split {
+        split: Split,
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn contains(&self, id: Xid) -> bool {
+        match self {
+            Self::Leaf(l) => *l == id,
+            Self::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    fn last_leaf(&self) -> Xid {
+        match self {
+            Self::Leaf(l) => *l,
+            Self::Split { second, .. } => second.last_leaf(),
+        }
+    }
+
+    // Replace the leaf for 'target' with a split holding it and 'id'. Unless 'split' is given the
+    // new split is in the opposite direction to that of its parent.
+    fn insert(
+        &mut self,
+        target: Xid,
+        id: Xid,
+        split: Option<Split>,
+        parent: Option<Split>,
+    ) -> bool {
+        match self {
+            Self::Leaf(l) if *l == target => {
+                let alternating = parent.map(Split::flipped).unwrap_or(Split::Vertical);
+                *self = Self::Split {
+                    split: split.unwrap_or(alternating),
+                    ratio: 0.5,
+                    first: Box::new(Self::Leaf(target)),
+                    second: Box::new(Self::Leaf(id)),
+                };
+                true
+            }
+            Self::Leaf(_) => false,
+            Self::Split {
+                split: s,
+                first,
+                second,
+                ..
+            } => {
+                let parent = Some(*s);
+                first.insert(target, id, split, parent) || second.insert(target, id, split, parent)
+            }
+        }
+    }
+
+    // Drop leaves that do not match 'keep', with the sibling of each removed leaf taking the place
+    // of their parent. Returns None if no leaves are left.
+    fn retain(self, keep: &dyn Fn(Xid) -> bool) -> Option<Self> {
+        match self {
+            Self::Leaf(id) if keep(id) => Some(self),
+            Self::Leaf(_) => None,
+            Self::Split {
+                split,
+                ratio,
+                first,
+                second,
+            } => match (first.retain(keep), second.retain(keep)) {
+                (Some(first), Some(second)) => Some(Self::Split {
+                    split,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(n), None) | (None, Some(n)) => Some(n),
+                (None, None) => None,
+            },
+        }
+    }
+
+    // Turning the tree clockwise moves what was on the left to the top and what was on the top
+    // to the right.
+    fn rotate(&mut self) {
+        if let Self::Split {
+            split,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            if *split == Split::Horizontal {
+                std::mem::swap(first, second);
+                *ratio = 1.0 - *ratio;
+            }
+            *split = split.flipped();
+            first.rotate();
+            second.rotate();
+        }
+    }
+
+    // Set each ratio so that every leaf gets the same share of the space along that split,
+    // returning the number of leaves below this node.
+    fn balance(&mut self) -> usize {
+        match self {
+            Self::Leaf(_) => 1,
+            Self::Split {
+                ratio,
+                first,
+                second,
+                ..
+            } => {
+                let (n_first, n_second) = (first.balance(), second.balance());
+                *ratio = n_first as f32 / (n_first + n_second) as f32;
+                n_first + n_second
+            }
+        }
+    }
+
+    fn regions(&self, r: Region, acc: &mut Vec<(Xid, Region)>) {
+        match self {
+            Self::Leaf(id) => acc.push((*id, r)),
+            Self::Split {
+                split,
+                ratio,
+                first,
+                second,
+            } => {
+                let (_, _, w, h) = r.values();
+                let split = match split {
+                    Split::Vertical => r.split_at_width((w as f32 * ratio) as u32),
+                    Split::Horizontal => r.split_at_height((h as f32 * ratio) as u32),
+                };
+                if let Ok((r1, r2)) = split {
+                    first.regions(r1, acc);
+                    second.regions(r2, acc);
+                }
+            }
+        }
+    }
+}
+
+impl<X: XConn> Hook<X> for Bsp {
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        screen_index: usize,
+    ) -> Result<()> {
+        let (ids, focused, has_fullscreen) = match wm.workspace(&Selector::Index(workspace_index)) {
+            Some(ws) if ws.layout_symbol() == self.state.borrow().symbol => {
+                let clients: Vec<_> = ws
+                    .client_ids()
+                    .into_iter()
+                    .filter_map(|id| wm.client(&Selector::WinId(id)))
+                    .filter(|c| !c.floating)
+                    .collect();
+                let has_fullscreen = clients.iter().any(|c| c.fullscreen);
+                let ids: Vec<Xid> = clients.iter().map(|c| c.id()).collect();
+                (ids, ws.focused_client(), has_fullscreen)
+            }
+            _ => return Ok(()),
+        };
+        let region = match wm.screen_size(screen_index) {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+
+        let mut state = self.state.borrow_mut();
+        state.sync(workspace_index, &ids, focused);
+        if has_fullscreen {
+            return Ok(()); // positioning the fullscreen client would shrink it back into the tree
+        }
+
+        let mut regions = Vec::with_capacity(ids.len());
+        if let Some(tree) = state.trees.get(&workspace_index) {
+            tree.regions(region, &mut regions);
+        }
+        for (id, r) in regions {
+            wm.position_client(id, state.spacing.pad(&r), false)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> BspState {
+        BspState {
+            symbol: "[bsp]".into(),
+            spacing: Spacing::default(),
+            trees: HashMap::new(),
+            last_focused: HashMap::new(),
+            preselected: None,
+        }
+    }
+
+    fn regions(state: &BspState) -> Vec<(Xid, Region)> {
+        let mut regions = vec![];
+        state.trees[&0].regions(Region::new(0, 0, 1200, 600), &mut regions);
+        regions
+    }
+
+    #[test]
+    fn new_clients_split_the_focused_region_alternately() {
+        let mut s = state();
+        s.sync(0, &[1], Some(1));
+        s.sync(0, &[1, 2], Some(2));
+        s.sync(0, &[1, 2, 3], Some(3));
+
+        assert_eq!(
+            regions(&s),
+            vec![
+                (1, Region::new(0, 0, 600, 600)),
+                (2, Region::new(600, 0, 600, 300)),
+                (3, Region::new(600, 300, 600, 300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn preselected_splits_are_used_once() {
+        let mut s = state();
+        s.preselected = Some(Split::Horizontal);
+        s.sync(0, &[1], Some(1));
+        s.sync(0, &[1, 2], Some(1));
+        s.sync(0, &[1, 2, 3], Some(3));
+
+        assert_eq!(s.preselected, None);
+        assert_eq!(
+            regions(&s),
+            vec![
+                (1, Region::new(0, 0, 600, 300)),
+                (3, Region::new(600, 0, 600, 300)),
+                (2, Region::new(0, 300, 1200, 300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn removing_a_client_gives_its_space_to_its_sibling() {
+        let mut s = state();
+        s.sync(0, &[1], Some(1));
+        s.sync(0, &[1, 2], Some(2));
+        s.sync(0, &[1, 2, 3], Some(3));
+        s.sync(0, &[1, 3], Some(3));
+
+        assert_eq!(
+            regions(&s),
+            vec![
+                (1, Region::new(0, 0, 600, 600)),
+                (3, Region::new(600, 0, 600, 600)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotating_turns_the_tree_clockwise() {
+        let mut s = state();
+        s.sync(0, &[1], Some(1));
+        s.sync(0, &[1, 2], Some(2));
+        s.trees.get_mut(&0).unwrap().rotate();
+
+        assert_eq!(
+            regions(&s),
+            vec![
+                (1, Region::new(0, 0, 1200, 300)),
+                (2, Region::new(0, 300, 1200, 300)),
+            ]
+        );
+
+        s.trees.get_mut(&0).unwrap().rotate();
+        assert_eq!(
+            regions(&s),
+            vec![
+                (2, Region::new(0, 0, 600, 600)),
+                (1, Region::new(600, 0, 600, 600)),
+            ]
+        );
+    }
+
+    #[test]
+    fn balancing_gives_each_client_the_same_space() {
+        let mut s = state();
+        for n in 1..=4 {
+            let ids: Vec<Xid> = (1..=n).collect();
+            s.sync(0, &ids, Some(n));
+        }
+        s.trees.get_mut(&0).unwrap().balance();
+
+        let areas: Vec<u32> = regions(&s).iter().map(|(_, r)| r.w * r.h).collect();
+        assert_eq!(areas, vec![180000; 4]);
+    }
+}
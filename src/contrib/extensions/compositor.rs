@@ -0,0 +1,223 @@
+//! Run and manage an external compositor such as picom alongside penrose
+use crate::{
+    core::{
+        bindings::KeyEventHandler, hooks::Hook, manager::WindowManager, ring::Selector,
+        xconnection::XConn,
+    },
+    Result,
+};
+
+use std::{
+    cell::RefCell,
+    fmt,
+    process::{Child, Command, Stdio},
+    rc::Rc,
+};
+
+/// Start an external compositor when penrose starts and stop it again when penrose exits.
+///
+/// The [get_hook][Compositor::get_hook] method must be called to pass the associated [Hook] to
+/// your [WindowManager] before calling init. By default the compositor is stopped whenever a
+/// fullscreen client is visible on any screen (to reduce input latency for games and video) and
+/// restarted once there are no longer any visible fullscreen clients. The compositor can also be
+/// toggled on and off manually using [toggle][Compositor::toggle] and is stopped while
+/// [game mode][WindowManager::toggle_game_mode] is active.
+///
+/// # Example
+/// ```no_run
+/// # use penrose::core::{bindings::KeyEventHandler, hooks::Hooks, xconnection::XConn};
+/// use penrose::contrib::extensions::Compositor;
+///
+/// # fn example<X: XConn + 'static>() {
+/// let compositor = Compositor::new("picom --experimental-backends");
+/// let hooks: Hooks<X> = vec![compositor.get_hook()];
+/// let toggle: KeyEventHandler<X> = compositor.toggle();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Compositor {
+    state: Rc<RefCell<CompositorState>>,
+}
+
+impl fmt::Debug for Compositor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Compositor")
+            .field("prog", &state.prog)
+            .field("enabled", &state.enabled)
+            .field("suspend_for_fullscreen", &state.suspend_for_fullscreen)
+            .field("running", &state.child.is_some())
+            .finish()
+    }
+}
+
+impl Compositor {
+    /// Create a new Compositor that will run 'prog' (including any command line arguments).
+    pub fn new(prog: impl Into<String>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(CompositorState {
+                prog: prog.into(),
+                child: None,
+                enabled: true,
+                suspend_for_fullscreen: true,
+                fullscreen_visible: false,
+                game_mode: false,
+            })),
+        }
+    }
+
+    /// Set whether or not the compositor should be stopped while a fullscreen client is visible.
+    ///
+    /// Defaults to true.
+    pub fn suspend_for_fullscreen(self, suspend: bool) -> Self {
+        self.state.borrow_mut().suspend_for_fullscreen = suspend;
+        self
+    }
+
+    /// Construct the associated [Hook] for adding to the [WindowManager].
+    ///
+    /// NOTE: If the hook is not registered, the compositor will never be started.
+    pub fn get_hook(&self) -> Box<Self> {
+        Box::new(self.clone())
+    }
+
+    /// Whether or not the compositor process is currently running
+    pub fn is_running(&self) -> bool {
+        self.state.borrow().child.is_some()
+    }
+
+    /// Enable / disable the compositor.
+    ///
+    /// While disabled the compositor will not be restarted when fullscreen clients are closed.
+    pub fn toggle<X: XConn>(&self) -> KeyEventHandler<X> {
+        let state = Rc::clone(&self.state);
+        Box::new(move |_: &mut WindowManager<X>| {
+            let mut state = state.borrow_mut();
+            state.enabled = !state.enabled;
+            state.sync()
+        })
+    }
+}
+
+impl<X: XConn> Hook<X> for Compositor {
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        self.event_handled(wm)
+    }
+
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        let visible: Vec<usize> = (0..wm.n_screens())
+            .flat_map(|i| wm.screen(&Selector::Index(i)).map(|s| s.wix))
+            .collect();
+        let fullscreen_visible = !wm
+            .all_clients(&Selector::Condition(&|c| {
+                c.is_fullscreen() && visible.contains(&c.workspace())
+            }))
+            .is_empty();
+
+        let mut state = self.state.borrow_mut();
+        state.fullscreen_visible = fullscreen_visible;
+        state.game_mode = wm.game_mode();
+        state.sync()
+    }
+}
+
+struct CompositorState {
+    prog: String,
+    child: Option<Child>,
+    enabled: bool,
+    suspend_for_fullscreen: bool,
+    fullscreen_visible: bool,
+    game_mode: bool,
+}
+
+impl CompositorState {
+    fn should_run(&self) -> bool {
+        self.enabled && !self.game_mode && !(self.suspend_for_fullscreen && self.fullscreen_visible)
+    }
+
+    // Start or stop the compositor process so that it matches our current state
+    fn sync(&mut self) -> Result<()> {
+        self.reap_exited();
+
+        match (self.should_run(), self.child.is_some()) {
+            (true, false) => self.start(),
+            (false, true) => {
+                self.stop();
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // If the compositor died on its own then we disable it rather than repeatedly restarting it
+    fn reap_exited(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            if let Ok(Some(status)) = child.try_wait() {
+                warn!(prog = %self.prog, %status, "compositor exited: disabling");
+                self.child = None;
+                self.enabled = false;
+            }
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        let parts: Vec<&str> = self.prog.split_whitespace().collect();
+        if parts.is_empty() {
+            return Ok(());
+        }
+
+        info!(prog = %self.prog, "starting compositor");
+        let child = Command::new(parts[0])
+            .args(&parts[1..])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        self.child = Some(child);
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            info!(prog = %self.prog, "stopping compositor");
+            if let Err(e) = child.kill().and_then(|_| child.wait()) {
+                error!(prog = %self.prog, "unable to stop compositor: {}", e);
+            }
+        }
+    }
+}
+
+impl Drop for CompositorState {
+    fn drop(&mut self) {
+        self.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        should_run;
+        args: (enabled: bool, suspend: bool, fullscreen: bool, game_mode: bool, expected: bool);
+
+        case: enabled => (true, true, false, false, true);
+        case: disabled => (false, true, false, false, false);
+        case: fullscreen_visible => (true, true, true, false, false);
+        case: fullscreen_without_suspend => (true, false, true, false, true);
+        case: game_mode => (true, true, false, true, false);
+
+        body: {
+            let state = CompositorState {
+                prog: "picom".into(),
+                child: None,
+                enabled,
+                suspend_for_fullscreen: suspend,
+                fullscreen_visible: fullscreen,
+                game_mode,
+            };
+
+            assert_eq!(state.should_run(), expected);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Expose penrose on the D-Bus session bus for use by external tools and status bars
+use crate::{
+    core::{
+        bindings::KeyEventHandler,
+        hooks::Hook,
+        manager::WindowManager,
+        ring::Selector,
+        xconnection::{Atom, ClientEventMask, ClientMessage, XConn, Xid},
+    },
+    Result,
+};
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex,
+    },
+};
+
+use zbus::{blocking::Connection, dbus_interface, SignalContext};
+
+/// The well known name that penrose requests on the session bus
+pub const DBUS_NAME: &str = "org.penrose.WindowManager";
+
+/// The object path that the [DBUS_INTERFACE] is served at
+pub const DBUS_PATH: &str = "/org/penrose/WindowManager";
+
+/// The name of the interface providing the penrose signals and methods
+pub const DBUS_INTERFACE: &str = "org.penrose.WindowManager";
+
+/// A method call received over D-Bus that needs to be run by the [WindowManager].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DbusCall {
+    SwitchWorkspace(usize),
+    RunAction(String),
+}
+
+// Method calls are handled on a thread owned by zbus so they are passed back to the hook (and
+// penrose is woken up to run them) rather than being run directly.
+struct Interface {
+    calls: Mutex<Sender<DbusCall>>,
+    wake: Mutex<Box<dyn FnMut() -> Result<()> + Send>>,
+}
+
+impl Interface {
+    fn send(&self, call: DbusCall) -> zbus::fdo::Result<()> {
+        let failed = |e: String| zbus::fdo::Error::Failed(e);
+
+        self.calls
+            .lock()
+            .map_err(|e| failed(e.to_string()))?
+            .send(call)
+            .map_err(|e| failed(e.to_string()))?;
+
+        let mut wake = self.wake.lock().map_err(|e| failed(e.to_string()))?;
+        (*wake)().map_err(|e| failed(e.to_string()))
+    }
+}
+
+#[dbus_interface(name = "org.penrose.WindowManager")]
+impl Interface {
+    /// Focus the workspace with the given index
+    fn switch_workspace(&self, index: u32) -> zbus::fdo::Result<()> {
+        self.send(DbusCall::SwitchWorkspace(index as usize))
+    }
+
+    /// Run the action that was registered under the given name
+    fn run_action(&self, name: String) -> zbus::fdo::Result<()> {
+        self.send(DbusCall::RunAction(name))
+    }
+
+    /// The focused workspace changed from 'previous' to 'current'
+    #[dbus_interface(signal)]
+    async fn workspace_changed(
+        ctxt: &SignalContext<'_>,
+        previous: u32,
+        current: u32,
+    ) -> zbus::Result<()>;
+
+    /// The layout of a workspace changed to the one with the given symbol
+    #[dbus_interface(signal)]
+    async fn layout_changed(
+        ctxt: &SignalContext<'_>,
+        workspace: u32,
+        symbol: String,
+    ) -> zbus::Result<()>;
+
+    /// A new client was focused
+    #[dbus_interface(signal)]
+    async fn focus_changed(ctxt: &SignalContext<'_>, id: u32, title: String) -> zbus::Result<()>;
+}
+
+/// Serve the `org.penrose.WindowManager` interface on the D-Bus session bus.
+///
+/// This allows external tools and status bars to follow what penrose is doing and to control it
+/// without needing to parse X properties. The interface is served at [DBUS_PATH] under the name
+/// [DBUS_NAME] and provides:
+///
+/// ```text
+/// signal WorkspaceChanged(previous: u32, current: u32)
+/// signal LayoutChanged(workspace: u32, symbol: String)
+/// signal FocusChanged(id: u32, title: String)
+/// method SwitchWorkspace(index: u32)
+/// method RunAction(name: String)
+/// ```
+///
+/// Actions that can be run using `RunAction` are registered by name when creating the hook and
+/// are any of the [KeyEventHandler]s that you would otherwise bind to keys. Method calls arrive
+/// on a background thread so, like [Logind][1], a second connection to the X server needs to be
+/// provided to wake up penrose so that they can be run.
+///
+/// Only available with the `dbus` feature enabled.
+///
+/// # Example
+/// ```no_run
+/// # #[macro_use] extern crate penrose;
+/// # use penrose::core::{hooks::Hooks, xconnection::XConn};
+/// use penrose::contrib::extensions::DbusInterface;
+/// use std::collections::HashMap;
+///
+/// # fn main() {}
+/// # fn example<X: XConn + 'static, W: XConn + Send + 'static>(second_conn: W) -> penrose::Result<Hooks<X>> {
+/// let mut actions = HashMap::new();
+/// actions.insert("terminal".to_string(), run_external!("st"));
+///
+/// let hooks: Hooks<X> = vec![Box::new(DbusInterface::new(second_conn, actions)?)];
+/// # Ok(hooks)
+/// # }
+/// ```
+///
+/// [1]: crate::contrib::extensions::Logind
+pub struct DbusInterface<X: XConn> {
+    conn: Connection,
+    calls: Receiver<DbusCall>,
+    actions: HashMap<String, KeyEventHandler<X>>,
+}
+
+impl<X: XConn> fmt::Debug for DbusInterface<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbusInterface")
+            .field("conn", &self.conn.unique_name())
+            .field("actions", &self.actions.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<X: XConn> DbusInterface<X> {
+    /// Connect to the session bus and start serving the penrose interface, running the named
+    /// 'actions' when requested. 'wake_conn' should be a second connection to the X server
+    /// that penrose is running on.
+    ///
+    /// Fails if the session bus is not available or if [DBUS_NAME] is already taken.
+    pub fn new<W>(wake_conn: W, actions: HashMap<String, KeyEventHandler<X>>) -> Result<Self>
+    where
+        W: XConn + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        let wake = move || {
+            let msg = ClientMessage::new(
+                wake_conn.root(),
+                ClientEventMask::SubstructureNotify,
+                Atom::PenroseDbusCall.as_ref(),
+                [0u32; 5].into(),
+            );
+            Ok(wake_conn.send_client_event(msg)?)
+        };
+        let iface = Interface {
+            calls: Mutex::new(tx),
+            wake: Mutex::new(Box::new(wake)),
+        };
+
+        let conn = zbus::blocking::ConnectionBuilder::session()?
+            .name(DBUS_NAME)?
+            .serve_at(DBUS_PATH, iface)?
+            .build()?;
+
+        Ok(Self {
+            conn,
+            calls: rx,
+            actions,
+        })
+    }
+
+    fn emit<B>(&self, signal: &str, body: &B) -> Result<()>
+    where
+        B: zbus::export::serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        Ok(self
+            .conn
+            .emit_signal(None::<()>, DBUS_PATH, DBUS_INTERFACE, signal, body)?)
+    }
+
+    fn run_call(&mut self, wm: &mut WindowManager<X>, call: DbusCall) -> Result<()> {
+        match call {
+            DbusCall::SwitchWorkspace(ix) => wm.focus_workspace(&Selector::Index(ix)),
+            DbusCall::RunAction(name) => match self.actions.get_mut(&name) {
+                Some(action) => action(wm),
+                None => {
+                    warn!(%name, "unknown action requested over dbus");
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+impl<X: XConn> Hook<X> for DbusInterface<X> {
+    fn workspace_change(
+        &mut self,
+        _: &mut WindowManager<X>,
+        previous_workspace: usize,
+        new_workspace: usize,
+    ) -> Result<()> {
+        let body = (previous_workspace as u32, new_workspace as u32);
+        self.emit("WorkspaceChanged", &body)
+    }
+
+    fn layout_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        _: usize,
+    ) -> Result<()> {
+        let symbol = match wm.workspace(&Selector::Index(workspace_index)) {
+            Some(ws) => ws.layout_symbol().to_string(),
+            None => return Ok(()),
+        };
+
+        self.emit("LayoutChanged", &(workspace_index as u32, symbol))
+    }
+
+    fn focus_change(&mut self, wm: &mut WindowManager<X>, id: Xid) -> Result<()> {
+        let title = match wm.client(&Selector::WinId(id)) {
+            Some(c) => c.wm_name().to_string(),
+            None => String::new(),
+        };
+
+        self.emit("FocusChanged", &(id, title))
+    }
+
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        let calls: Vec<DbusCall> = self.calls.try_iter().collect();
+        for call in calls {
+            self.run_call(wm, call)?;
+        }
+
+        Ok(())
+    }
+}
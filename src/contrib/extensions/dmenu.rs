@@ -164,8 +164,10 @@ impl DMenu {
 
         let mut raw = String::new();
         proc.stdout
+            .take()
             .ok_or_else(|| PenroseError::SpawnProc("failed to spawn dmenu".into()))?
             .read_to_string(&mut raw)?;
+        proc.wait()?;
         let choice = raw.trim();
 
         if choice.is_empty() {
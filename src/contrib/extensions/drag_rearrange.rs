@@ -0,0 +1,218 @@
+//! Rearrange tiled clients by dragging them with the mouse
+use crate::{
+    core::{
+        bindings::{MouseEvent, MouseEventHandler, MouseEventKind},
+        data_types::Region,
+        manager::WindowManager,
+        xconnection::{Atom, Prop, XConn, Xid},
+    },
+    draw::{overlay::OverlayWindow, Color, Draw, DrawContext, TextStyle},
+    Result,
+};
+
+use std::{cell::RefCell, fmt, rc::Rc};
+
+/// Swap tiled clients by dragging one on top of another while holding a mouse binding.
+///
+/// The client under the pointer when the drag starts is swapped with the tiled client under the
+/// pointer when the drag ends. While dragging, the border of the client that will be swapped with
+/// is set to the `highlight` color. Floating clients are ignored.
+///
+/// A translucent preview of where the dragged client will end up can also be drawn over the
+/// target client by calling [with_preview][DragRearrange::with_preview].
+///
+/// The [handler][DragRearrange::handler] needs to be bound to the press, motion and release
+/// events of the same mouse state.
+///
+/// # Example
+/// ```no_run
+/// # #[macro_use] extern crate penrose;
+/// # use penrose::core::{bindings::MouseBindings, xconnection::XConn};
+/// use penrose::contrib::extensions::DragRearrange;
+///
+/// # fn example<X: XConn + 'static>() -> penrose::Result<()> {
+/// let drag = DragRearrange::new(0xfabd2f);
+///
+/// let mouse_bindings: MouseBindings<X> = gen_mousebindings! {
+///     Press Left + [Meta] => drag.handler(),
+///     Motion Left + [Meta] => drag.handler(),
+///     Release Left + [Meta] => drag.handler()
+/// };
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DragRearrange {
+    state: Rc<RefCell<DragState>>,
+}
+
+impl DragRearrange {
+    /// Create a new DragRearrange using 'highlight' as the border color of the drop target.
+    pub fn new(highlight: impl Into<Color>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(DragState {
+                highlight: highlight.into(),
+                source: None,
+                target: None,
+                preview: None,
+            })),
+        }
+    }
+
+    /// Also draw a rectangle in the highlight color over the client that will be swapped with,
+    /// using an unmanaged window with the given `opacity` (from 0.0 to 1.0).
+    ///
+    /// The preview is only translucent when a compositor that supports
+    /// `_NET_WM_WINDOW_OPACITY` is running.
+    pub fn with_preview<D: Draw + 'static>(self, drw: D, opacity: f64) -> Self {
+        {
+            let mut state = self.state.borrow_mut();
+            let preview = DropPreview::new(drw, state.highlight, opacity);
+            state.preview = Some(Box::new(preview));
+        }
+
+        self
+    }
+
+    /// A [MouseEventHandler] for starting, tracking and completing a drag.
+    ///
+    /// All handlers returned by the same DragRearrange share their state.
+    pub fn handler<X: XConn>(&self) -> MouseEventHandler<X> {
+        let state = Rc::clone(&self.state);
+        Box::new(move |wm: &mut WindowManager<X>, e: &MouseEvent| {
+            let mut state = state.borrow_mut();
+            match e.kind {
+                MouseEventKind::Press => state.start(wm, e),
+                MouseEventKind::Motion => state.update(wm, e),
+                MouseEventKind::Release => state.finish(wm, e),
+            }
+        })
+    }
+}
+
+// Shows where the dragged client will be placed when the drag is completed
+trait Preview: fmt::Debug {
+    fn show(&mut self, r: Region) -> Result<()>;
+    fn hide(&mut self) -> Result<()>;
+}
+
+struct DropPreview<D: Draw> {
+    overlay: OverlayWindow<D>,
+    opacity: u32,
+}
+
+impl<D: Draw> fmt::Debug for DropPreview<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DropPreview")
+            .field("overlay", &self.overlay)
+            .field("opacity", &self.opacity)
+            .finish()
+    }
+}
+
+impl<D: Draw> DropPreview<D> {
+    fn new(drw: D, color: Color, opacity: f64) -> Self {
+        // Nothing is written in the preview so the font is never used
+        let style = TextStyle {
+            font: "mono".to_string(),
+            point_size: 10,
+            fg: color,
+            bg: Some(color),
+            padding: (0.0, 0.0),
+        };
+
+        Self {
+            overlay: OverlayWindow::new(
+                drw,
+                &style,
+                "penrose-drag-preview",
+                Atom::NetWindowTypeDnd,
+            ),
+            opacity: (opacity.clamp(0.0, 1.0) * u32::MAX as f64) as u32,
+        }
+    }
+}
+
+impl<D: Draw> Preview for DropPreview<D> {
+    fn show(&mut self, r: Region) -> Result<()> {
+        let ctx = self.overlay.show_at(r)?;
+        ctx.flush();
+
+        if let Some(id) = self.overlay.win() {
+            let opacity = Prop::Cardinal(self.opacity);
+            self.overlay
+                .drw()
+                .change_prop(id, Atom::NetWmWindowOpacity.as_ref(), opacity)?;
+        }
+
+        Ok(self.overlay.flush()?)
+    }
+
+    fn hide(&mut self) -> Result<()> {
+        Ok(self.overlay.hide()?)
+    }
+}
+
+#[derive(Debug)]
+struct DragState {
+    highlight: Color,
+    source: Option<Xid>,
+    target: Option<Xid>,
+    preview: Option<Box<dyn Preview>>,
+}
+
+impl DragState {
+    fn start<X: XConn>(&mut self, wm: &mut WindowManager<X>, e: &MouseEvent) -> Result<()> {
+        self.clear_target(wm)?;
+        self.source = wm.tiled_client_at_point(e.rpt);
+
+        Ok(())
+    }
+
+    fn update<X: XConn>(&mut self, wm: &mut WindowManager<X>, e: &MouseEvent) -> Result<()> {
+        let source = match self.source {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let target = wm.tiled_client_at_point(e.rpt).filter(|&id| id != source);
+        if target == self.target {
+            return Ok(());
+        }
+
+        self.clear_target(wm)?;
+        if let Some(id) = target {
+            wm.conn().set_client_border_color(id, self.highlight)?;
+            if let Some(preview) = self.preview.as_mut() {
+                preview.show(wm.conn().client_geometry(id)?)?;
+            }
+            self.target = Some(id);
+        }
+
+        Ok(())
+    }
+
+    fn finish<X: XConn>(&mut self, wm: &mut WindowManager<X>, e: &MouseEvent) -> Result<()> {
+        self.update(wm, e)?;
+        let target = self.target;
+        self.clear_target(wm)?;
+
+        match (self.source.take(), target) {
+            (Some(source), Some(target)) => wm.swap_clients(source, target),
+            _ => Ok(()),
+        }
+    }
+
+    fn clear_target<X: XConn>(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        let id = match self.target.take() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        if let Some(preview) = self.preview.as_mut() {
+            preview.hide()?;
+        }
+
+        wm.reset_client_border(id)
+    }
+}
@@ -0,0 +1,249 @@
+//! Forward touchpad swipe gestures reported by libinput to penrose
+use crate::{
+    contrib::extensions::remote::require_remote_command,
+    core::{
+        bindings::{Gesture, SwipeDirection},
+        config::Config,
+        xconnection::{Atom, ClientEventMask, ClientMessage, XConn},
+    },
+    Result,
+};
+
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Read multi-finger swipes from `libinput debug-events` and send them to penrose so that they
+/// can trigger [gesture bindings][crate::core::manager::WindowManager::set_gesture_bindings].
+///
+/// The libinput X driver does not pass swipe gestures on to X clients, so `libinput` is run as a
+/// sub-process in a background thread that sends a `_PENROSE_GESTURE` client message to the root
+/// window for each completed swipe. As the [XConn] used by the [WindowManager][1] can not be
+/// shared with that thread, a second connection needs to be provided. Reading from `libinput`
+/// requires the user running penrose to be a member of the `input` group.
+///
+/// Any other program can trigger gesture bindings by sending the same message: the data is
+/// generated by [Gesture::as_data]. For that reason these messages are ignored unless "gesture"
+/// is included in [remote_commands][2], and [spawn][LibinputGestures::spawn] fails if it is not.
+///
+/// # Example
+/// ```no_run
+/// # use penrose::core::xconnection::XConn;
+/// use penrose::{contrib::extensions::LibinputGestures, core::config::Config};
+///
+/// # fn example<X: XConn + Send + 'static>(second_conn: X) -> penrose::Result<()> {
+/// let config = Config::default()
+///     .builder()
+///     .remote_commands(vec!["gesture"])
+///     .build()
+///     .unwrap();
+///
+/// LibinputGestures::new().spawn(&config, second_conn)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [1]: crate::core::manager::WindowManager
+/// [2]: crate::core::config::Config::remote_commands
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibinputGestures {
+    device: Option<String>,
+}
+
+impl LibinputGestures {
+    /// Create a new LibinputGestures that reads from all input devices.
+    ///
+    /// "gesture" needs to be included in [remote_commands][1] for the gestures to be used.
+    ///
+    /// [1]: crate::core::config::Config::remote_commands
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only read gestures from the device at 'path' (e.g. `/dev/input/event9`)
+    pub fn device(mut self, path: impl Into<String>) -> Self {
+        self.device = Some(path.into());
+        self
+    }
+
+    /// Start `libinput debug-events` and forward completed swipes using 'conn'.
+    ///
+    /// Fails if "gesture" is not included in the [remote_commands][1] of 'config' or if
+    /// `libinput` could not be started. Errors after that point are logged and stop the
+    /// background thread.
+    ///
+    /// [1]: crate::core::config::Config::remote_commands
+    pub fn spawn<X>(self, config: &Config, conn: X) -> Result<()>
+    where
+        X: XConn + Send + 'static,
+    {
+        require_remote_command(config, "gesture", "LibinputGestures")?;
+
+        let mut cmd = Command::new("libinput");
+        cmd.arg("debug-events");
+        if let Some(device) = &self.device {
+            cmd.args(["--device", device]);
+        }
+
+        let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::null()).spawn()?;
+        let stdout = match child.stdout.take() {
+            Some(stdout) => stdout,
+            None => return Err(perror!("unable to read output from libinput")),
+        };
+
+        thread::spawn(move || {
+            let mut tracker = SwipeTracker::default();
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        error!("unable to read output from libinput: {}", e);
+                        break;
+                    }
+                };
+
+                if let Some(gesture) = tracker.process_line(&line) {
+                    trace!(?gesture, "sending gesture to penrose");
+                    if let Err(e) = send_gesture(&conn, gesture) {
+                        error!("unable to send gesture to penrose: {}", e);
+                        break;
+                    }
+                }
+            }
+
+            // libinput is left running if we stopped reading for any reason other than it exiting
+            let _ = child.kill();
+            let _ = child.wait();
+        });
+
+        Ok(())
+    }
+}
+
+fn send_gesture<X: XConn>(conn: &X, gesture: Gesture) -> Result<()> {
+    let msg = ClientMessage::new(
+        conn.root(),
+        ClientEventMask::SubstructureNotify,
+        Atom::PenroseGesture.as_ref(),
+        gesture.as_data().into(),
+    );
+
+    Ok(conn.send_client_event(msg)?)
+}
+
+// Accumulates the movement reported in GESTURE_SWIPE_UPDATE lines so that the overall direction
+// is known when the swipe ends. Lines look like:
+//   -event9   GESTURE_SWIPE_BEGIN     +1.234s	3
+//    event9   GESTURE_SWIPE_UPDATE    +1.250s	3  5.12/-0.34 ( 7.80/-0.52 unaccelerated)
+//    event9   GESTURE_SWIPE_END       +1.400s	3 cancelled
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct SwipeTracker {
+    dx: f64,
+    dy: f64,
+}
+
+impl SwipeTracker {
+    fn process_line(&mut self, line: &str) -> Option<Gesture> {
+        let mut fields = line.split_whitespace().skip(1);
+        let kind = fields.next()?;
+        let _time = fields.next()?;
+        let fingers: u8 = fields.next()?.parse().ok()?;
+        let rest: Vec<&str> = fields.collect();
+
+        match kind {
+            "GESTURE_SWIPE_BEGIN" => {
+                *self = Self::default();
+                None
+            }
+
+            "GESTURE_SWIPE_UPDATE" => {
+                let delta: String = rest
+                    .iter()
+                    .copied()
+                    .take_while(|s| !s.starts_with('('))
+                    .collect();
+                let mut parts = delta.splitn(2, '/').map(|s| s.parse::<f64>());
+                if let (Some(Ok(dx)), Some(Ok(dy))) = (parts.next(), parts.next()) {
+                    self.dx += dx;
+                    self.dy += dy;
+                }
+                None
+            }
+
+            "GESTURE_SWIPE_END" => {
+                let (dx, dy) = (self.dx, self.dy);
+                *self = Self::default();
+                if rest.contains(&"cancelled") {
+                    return None;
+                }
+
+                SwipeDirection::from_delta(dx, dy).map(|d| Gesture::new(fingers, d))
+            }
+
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        swipe_tracker;
+        args: (lines: &[&str], expected: Option<Gesture>);
+
+        case: three_finger_left => (
+            &[
+                "-event9   GESTURE_SWIPE_BEGIN     +1.234s\t3",
+                " event9   GESTURE_SWIPE_UPDATE    +1.250s\t3 -5.12/ 0.34 (-7.80/ 0.52 unaccelerated)",
+                " event9   GESTURE_SWIPE_UPDATE    +1.260s\t3 -9.00/-1.00 (-13.1/-1.50 unaccelerated)",
+                " event9   GESTURE_SWIPE_END       +1.400s\t3",
+            ],
+            Some(Gesture::new(3, SwipeDirection::Left))
+        );
+        case: four_finger_down => (
+            &[
+                "-event9   GESTURE_SWIPE_BEGIN     +1.234s\t4",
+                " event9   GESTURE_SWIPE_UPDATE    +1.250s\t4  1.00/12.50 ( 1.50/18.00 unaccelerated)",
+                " event9   GESTURE_SWIPE_END       +1.400s\t4",
+            ],
+            Some(Gesture::new(4, SwipeDirection::Down))
+        );
+        case: cancelled => (
+            &[
+                "-event9   GESTURE_SWIPE_BEGIN     +1.234s\t3",
+                " event9   GESTURE_SWIPE_UPDATE    +1.250s\t3  0.00/-8.00 ( 0.00/-9.00 unaccelerated)",
+                " event9   GESTURE_SWIPE_END       +1.400s\t3 cancelled",
+            ],
+            None
+        );
+        case: no_movement => (
+            &[
+                "-event9   GESTURE_SWIPE_BEGIN     +1.234s\t3",
+                " event9   GESTURE_SWIPE_END       +1.400s\t3",
+            ],
+            None
+        );
+        case: pinch_ignored => (
+            &[
+                "-event9   GESTURE_PINCH_BEGIN     +1.234s\t2",
+                " event9   GESTURE_PINCH_UPDATE    +1.250s\t2  3.00/ 0.00 ( 4.00/ 0.00 unaccelerated)  1.10 @ 0.00",
+                " event9   GESTURE_PINCH_END       +1.400s\t2",
+            ],
+            None
+        );
+
+        body: {
+            let mut tracker = SwipeTracker::default();
+            let gestures: Vec<Gesture> = lines
+                .iter()
+                .flat_map(|l| tracker.process_line(l))
+                .collect();
+
+            assert_eq!(gestures.last().copied(), expected);
+        }
+    }
+}
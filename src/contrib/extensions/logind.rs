@@ -0,0 +1,325 @@
+//! Forward lid, sleep and resume events from systemd-logind to penrose
+use crate::{
+    contrib::extensions::remote::require_remote_command,
+    core::{
+        config::Config,
+        helpers::spawn_for_output_with_args,
+        power::PowerEvent,
+        xconnection::{Atom, ClientEventMask, ClientMessage, XConn},
+    },
+    Result,
+};
+
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{channel, Sender},
+    thread,
+    time::Duration,
+};
+
+/// Watch systemd-logind for the lid being closed or opened and the system going to sleep or
+/// waking up, sending each change to penrose as a [PowerEvent] so that it can be handled by
+/// [hooks][crate::core::hooks::Hook::lid_state_changed].
+///
+/// Sleep and resume are read from the `PrepareForSleep` signal using `gdbus monitor` and the lid
+/// state is polled using `busctl`, both on background threads that send a `_PENROSE_POWER_EVENT`
+/// client message to the root window for each change. As the [XConn] used by the
+/// [WindowManager][1] can not be shared with those threads, a second connection needs to be
+/// provided. Any program is able to send these messages so they are ignored unless
+/// "power-event" is included in [remote_commands][2], and [spawn][Logind::spawn] fails if it is
+/// not.
+///
+/// By default logind still acts on the lid switch and power button itself. Calling
+/// [handle_lid_switch][Logind::handle_lid_switch] or [handle_power_key][Logind::handle_power_key]
+/// takes an inhibitor lock (using `systemd-inhibit`) that stops it from doing so for as long as
+/// penrose is running. With the power key inhibited, pressing the power button is only seen as
+/// the `XF86PowerOff` key so it can be bound to an action (such as a power menu) like any other
+/// key binding.
+///
+/// # Example
+/// ```no_run
+/// # use penrose::core::xconnection::XConn;
+/// use penrose::{contrib::extensions::Logind, core::config::Config};
+///
+/// # fn example<X: XConn + Send + 'static>(second_conn: X) -> penrose::Result<()> {
+/// let config = Config::default()
+///     .builder()
+///     .remote_commands(vec!["power-event"])
+///     .build()
+///     .unwrap();
+///
+/// Logind::new().handle_power_key().spawn(&config, second_conn)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [1]: crate::core::manager::WindowManager
+/// [2]: crate::core::config::Config::remote_commands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Logind {
+    lid_poll_interval: Option<Duration>,
+    handle_lid_switch: bool,
+    handle_power_key: bool,
+}
+
+impl Default for Logind {
+    fn default() -> Self {
+        Self {
+            lid_poll_interval: Some(Duration::from_secs(2)),
+            handle_lid_switch: false,
+            handle_power_key: false,
+        }
+    }
+}
+
+impl Logind {
+    /// Create a new Logind that watches for sleep and resume and checks the lid every 2 seconds.
+    ///
+    /// "power-event" needs to be included in [remote_commands][1] for the events to be used.
+    ///
+    /// [1]: crate::core::config::Config::remote_commands
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the lid state every 'interval' or not at all if 'interval' is None
+    pub fn lid_poll_interval(mut self, interval: Option<Duration>) -> Self {
+        self.lid_poll_interval = interval;
+        self
+    }
+
+    /// Stop logind from suspending the system when the lid is closed
+    pub fn handle_lid_switch(mut self) -> Self {
+        self.handle_lid_switch = true;
+        self
+    }
+
+    /// Stop logind from shutting down the system when the power button is pressed
+    pub fn handle_power_key(mut self) -> Self {
+        self.handle_power_key = true;
+        self
+    }
+
+    /// Start watching logind and forward power events using 'conn'.
+    ///
+    /// Fails if "power-event" is not included in the [remote_commands][1] of 'config' or if
+    /// `gdbus` or `systemd-inhibit` could not be started. Errors after that point are logged and
+    /// stop the background thread that hit them.
+    ///
+    /// [1]: crate::core::config::Config::remote_commands
+    pub fn spawn<X>(self, config: &Config, conn: X) -> Result<()>
+    where
+        X: XConn + Send + 'static,
+    {
+        require_remote_command(config, "power-event", "Logind")?;
+
+        let inhibitor = self.inhibit()?;
+        let (tx, rx) = channel();
+
+        watch_sleep(tx.clone())?;
+        if let Some(interval) = self.lid_poll_interval {
+            thread::spawn(move || poll_lid(interval, tx));
+        }
+
+        thread::spawn(move || {
+            for event in rx {
+                trace!(?event, "sending power event to penrose");
+                if let Err(e) = send_power_event(&conn, event) {
+                    error!("unable to send power event to penrose: {}", e);
+                    break;
+                }
+            }
+
+            // closing stdin stops systemd-inhibit, releasing the lock
+            if let Some(mut child) = inhibitor {
+                drop(child.stdin.take());
+                let _ = child.wait();
+            }
+        });
+
+        Ok(())
+    }
+
+    // Take a logind inhibitor lock that is held until the returned child's stdin is closed
+    fn inhibit(&self) -> Result<Option<Child>> {
+        let what = match (self.handle_lid_switch, self.handle_power_key) {
+            (false, false) => return Ok(None),
+            (true, false) => "handle-lid-switch",
+            (false, true) => "handle-power-key",
+            (true, true) => "handle-lid-switch:handle-power-key",
+        };
+
+        let what = format!("--what={}", what);
+        let child = Command::new("systemd-inhibit")
+            .args([
+                what.as_str(),
+                "--who=penrose",
+                "--why=penrose is handling power events",
+                "--mode=block",
+                "cat",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+
+        Ok(Some(child))
+    }
+}
+
+fn send_power_event<X: XConn>(conn: &X, event: PowerEvent) -> Result<()> {
+    let msg = ClientMessage::new(
+        conn.root(),
+        ClientEventMask::SubstructureNotify,
+        Atom::PenrosePowerEvent.as_ref(),
+        event.as_data().into(),
+    );
+
+    Ok(conn.send_client_event(msg)?)
+}
+
+fn watch_sleep(tx: Sender<PowerEvent>) -> Result<()> {
+    let mut child = Command::new("gdbus")
+        .args([
+            "monitor",
+            "--system",
+            "--dest",
+            "org.freedesktop.login1",
+            "--object-path",
+            "/org/freedesktop/login1",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = match child.stdout.take() {
+        Some(stdout) => stdout,
+        None => return Err(perror!("unable to read output from gdbus")),
+    };
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    error!("unable to read output from gdbus: {}", e);
+                    break;
+                }
+            };
+
+            if let Some(event) = parse_sleep_signal(&line) {
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        }
+
+        // gdbus is left running if we stopped reading for any reason other than it exiting
+        let _ = child.kill();
+        let _ = child.wait();
+    });
+
+    Ok(())
+}
+
+fn poll_lid(interval: Duration, tx: Sender<PowerEvent>) {
+    let mut closed = None;
+
+    loop {
+        let raw = match spawn_for_output_with_args(
+            "busctl",
+            &[
+                "get-property",
+                "org.freedesktop.login1",
+                "/org/freedesktop/login1",
+                "org.freedesktop.login1.Manager",
+                "LidClosed",
+            ],
+        ) {
+            Ok(raw) => raw,
+            Err(e) => {
+                error!("unable to read lid state from logind: {}", e);
+                return;
+            }
+        };
+
+        // only changes are sent: the state that we start in is not an event
+        if let Some(current) = parse_lid_closed(&raw) {
+            let event = match closed.replace(current) {
+                Some(false) if current => Some(PowerEvent::LidClosed),
+                Some(true) if !current => Some(PowerEvent::LidOpened),
+                _ => None,
+            };
+            if let Some(event) = event {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+// Lines from `gdbus monitor` for the signal look like:
+//   /org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)
+fn parse_sleep_signal(line: &str) -> Option<PowerEvent> {
+    let args = line
+        .split("org.freedesktop.login1.Manager.PrepareForSleep")
+        .nth(1)?;
+
+    match args.trim() {
+        "(true,)" => Some(PowerEvent::PrepareForSleep),
+        "(false,)" => Some(PowerEvent::Resumed),
+        _ => None,
+    }
+}
+
+// `busctl get-property` prints the type of a property followed by its value: "b true"
+fn parse_lid_closed(raw: &str) -> Option<bool> {
+    match raw.trim() {
+        "b true" => Some(true),
+        "b false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        parse_sleep_signal;
+        args: (line: &str, expected: Option<PowerEvent>);
+
+        case: going_to_sleep => (
+            "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)",
+            Some(PowerEvent::PrepareForSleep)
+        );
+        case: waking_up => (
+            "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)",
+            Some(PowerEvent::Resumed)
+        );
+        case: other_signal => (
+            "/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', objectpath '/org/freedesktop/login1/session/_33')",
+            None
+        );
+        case: monitor_header => ("Monitoring signals on object /org/freedesktop/login1 owned by org.freedesktop.login1", None);
+
+        body: {
+            assert_eq!(parse_sleep_signal(line), expected);
+        }
+    }
+
+    test_cases! {
+        parse_lid_closed;
+        args: (raw: &str, expected: Option<bool>);
+
+        case: closed => ("b true\n", Some(true));
+        case: open => ("b false\n", Some(false));
+        case: unexpected => ("", None);
+
+        body: {
+            assert_eq!(parse_lid_closed(raw), expected);
+        }
+    }
+}
@@ -1,15 +1,45 @@
 //! Functionality extensions for penrose
 //!
 //! Most of these extension work by spawning and / or managing external programs as a sub-process.
+pub mod bsp;
+pub mod compositor;
+#[cfg(feature = "dbus")]
+pub mod dbus;
 pub mod dmenu;
+pub mod drag_rearrange;
+pub mod gestures;
+pub mod logind;
 pub mod notify_send;
+pub mod remote;
 pub mod scratchpad;
 
+#[doc(inline)]
+pub use bsp::Bsp;
+
+#[doc(inline)]
+pub use compositor::Compositor;
+
+#[cfg(feature = "dbus")]
+#[doc(inline)]
+pub use dbus::DbusInterface;
+
 #[doc(inline)]
 pub use dmenu::*;
 
+#[doc(inline)]
+pub use drag_rearrange::DragRearrange;
+
+#[doc(inline)]
+pub use gestures::LibinputGestures;
+
+#[doc(inline)]
+pub use logind::Logind;
+
 #[doc(inline)]
 pub use notify_send::*;
 
+#[doc(inline)]
+pub use remote::RemoteCommand;
+
 #[doc(inline)]
 pub use scratchpad::Scratchpad;
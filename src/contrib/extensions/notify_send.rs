@@ -1,5 +1,5 @@
 //! A simple wrapper around notify-send to allow for generating notifications
-use crate::{core::helpers::spawn_with_args, ErrorHandler, PenroseError, Result};
+use crate::{core::process::spawn_with_args, ErrorHandler, PenroseError, Result};
 
 use std::fmt;
 
@@ -0,0 +1,160 @@
+//! Controlling window placement from outside of penrose
+use crate::{
+    core::{
+        config::Config,
+        data_types::Region,
+        xconnection::{Atom, ClientEventMask, ClientMessage, XConn, Xid},
+    },
+    PenroseError, Result,
+};
+
+use std::str::FromStr;
+
+/// A request for penrose to move a window, sent from another program as a client message to the
+/// root window.
+///
+/// Commands can be parsed from strings so that they can be passed directly from the command line
+/// by a small helper program (see the `remote_command` example) and then run from scripts or menus:
+///
+/// ```text
+/// move-window <id> <x> <y> <w> <h>   float the window and move it to the given position
+/// tile-window <id>                   return a floating window to the tiled layout
+/// ```
+///
+/// Window IDs may be given in decimal or in hex with a leading `0x` (as shown by `xwininfo`).
+///
+/// Any program that can connect to your X server is able to send these commands, so penrose
+/// only runs those listed in [remote_commands][1] (none of them by default).
+///
+/// # Example
+/// ```no_run
+/// # use penrose::core::xconnection::XConn;
+/// use penrose::contrib::extensions::RemoteCommand;
+///
+/// # fn example<X: XConn>(second_conn: X) -> penrose::Result<()> {
+/// let cmd: RemoteCommand = "move-window 0x1c00003 100 100 800 600".parse()?;
+/// cmd.send(&second_conn)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [1]: crate::core::config::Config::remote_commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteCommand {
+    /// Make the window floating and move it to the given region in absolute coordinates
+    MoveWindow(Xid, Region),
+    /// Return a floating window to the tiled layout
+    TileWindow(Xid),
+}
+
+impl RemoteCommand {
+    /// The client message that penrose handles to run this command.
+    ///
+    /// Penrose only receives client messages sent to the 'root' window, so the target window is
+    /// passed as the first data value rather than as the destination of the message.
+    pub fn as_client_message(&self, root: Xid) -> ClientMessage {
+        let (atom, data) = match *self {
+            Self::MoveWindow(id, r) => (Atom::PenroseMoveWindow, [id, r.x, r.y, r.w, r.h]),
+            Self::TileWindow(id) => (Atom::PenroseTileWindow, [id, 0, 0, 0, 0]),
+        };
+
+        ClientMessage::new(
+            root,
+            ClientEventMask::SubstructureNotify,
+            atom.as_ref(),
+            data.into(),
+        )
+    }
+
+    /// Send this command to penrose using 'conn'.
+    ///
+    /// As the [XConn] used by the [WindowManager][1] is not available to other programs, this
+    /// should be a separate connection to the same X server.
+    ///
+    /// [1]: crate::core::manager::WindowManager
+    pub fn send<X: XConn>(&self, conn: &X) -> Result<()> {
+        Ok(conn.send_client_event(self.as_client_message(conn.root()))?)
+    }
+}
+
+impl FromStr for RemoteCommand {
+    type Err = PenroseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let nums = |args: &[&str]| -> Result<Vec<u32>> {
+            args.iter()
+                .map(|a| match a.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => a.parse(),
+                })
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|e| perror!("invalid argument in '{}': {}", s, e))
+        };
+
+        match parts.split_first() {
+            Some((&"move-window", args)) if args.len() == 5 => {
+                let n = nums(args)?;
+                Ok(Self::MoveWindow(n[0], Region::new(n[1], n[2], n[3], n[4])))
+            }
+            Some((&"tile-window", args)) if args.len() == 1 => Ok(Self::TileWindow(nums(args)?[0])),
+            _ => Err(perror!(
+                "unknown command '{}': expected 'move-window <id> <x> <y> <w> <h>' or 'tile-window <id>'",
+                s
+            )),
+        }
+    }
+}
+
+// Extensions that send client messages to penrose refuse to start if their messages would be
+// ignored, rather than silently doing nothing
+pub(super) fn require_remote_command(config: &Config, name: &str, extension: &str) -> Result<()> {
+    if config.remote_commands().iter().any(|c| c == name) {
+        Ok(())
+    } else {
+        Err(perror!(
+            "{} requires \"{}\" to be included in Config::remote_commands",
+            extension,
+            name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        parse;
+        args: (s: &str, expected: Option<RemoteCommand>);
+
+        case: move_window => (
+            "move-window 42 10 20 300 400",
+            Some(RemoteCommand::MoveWindow(42, Region::new(10, 20, 300, 400)))
+        );
+        case: move_window_hex_id => (
+            "move-window 0x1c00003 0 0 10 10",
+            Some(RemoteCommand::MoveWindow(0x1c00003, Region::new(0, 0, 10, 10)))
+        );
+        case: tile_window => ("tile-window 42", Some(RemoteCommand::TileWindow(42)));
+        case: missing_args => ("move-window 42 10 20", None);
+        case: invalid_number => ("tile-window forty-two", None);
+        case: unknown_command => ("close-window 42", None);
+
+        body: {
+            assert_eq!(s.parse::<RemoteCommand>().ok(), expected);
+        }
+    }
+
+    #[test]
+    fn extensions_require_their_remote_command() {
+        let config = Config::default()
+            .builder()
+            .remote_commands(vec!["gesture"])
+            .build()
+            .unwrap();
+
+        assert!(require_remote_command(&config, "gesture", "LibinputGestures").is_ok());
+        assert!(require_remote_command(&config, "power-event", "Logind").is_err());
+    }
+}
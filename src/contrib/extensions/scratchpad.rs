@@ -3,7 +3,6 @@ use crate::{
     core::{
         bindings::KeyEventHandler,
         data_types::Region,
-        helpers::spawn,
         hooks::Hook,
         manager::WindowManager,
         ring::Selector,
@@ -101,7 +100,7 @@ impl Scratchpad {
             None => {
                 self.pending.replace(true);
                 self.visible.replace(false);
-                return spawn(&self.prog); // caught by new_client
+                return wm.spawn(&self.prog); // caught by new_client
             }
         };
 
@@ -138,7 +137,11 @@ impl Scratchpad {
 
 impl<X: XConn> Hook<X> for Scratchpad {
     fn new_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> Result<()> {
-        let c = wm.client_mut(&Selector::WinId(id)).unwrap();
+        let c = match wm.client_mut(&Selector::WinId(id)) {
+            Some(c) => c,
+            None => return Ok(()), // removed by an earlier hook
+        };
+
         if *self.pending.borrow() && self.client.borrow().is_none() {
             self.pending.replace(false);
             self.client.replace(Some(c.id()));
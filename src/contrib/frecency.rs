@@ -0,0 +1,290 @@
+//! Ranking launcher items by how frequently and how recently they have been picked
+//!
+//! A [Frecency] history is used by [launch_with_pmenu][1] and [launch_desktop_entry_with_pmenu][2]
+//! to list the programs and applications that you use most often first, in the same way as the
+//! `run` and `drun` modes of rofi.
+//!
+//! [1]: crate::contrib::actions::launch_with_pmenu
+//! [2]: crate::contrib::actions::launch_desktop_entry_with_pmenu
+use crate::{core::xdg, Result};
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Usage {
+    count: u32,
+    last_used: u64,
+}
+
+impl Usage {
+    // Uses within the last hour count for much more than those from weeks ago
+    fn score(&self, now: u64) -> f64 {
+        let weight = match now.saturating_sub(self.last_used) {
+            age if age < HOUR => 4.0,
+            age if age < DAY => 2.0,
+            age if age < WEEK => 0.5,
+            _ => 0.25,
+        };
+
+        self.count as f64 * weight
+    }
+}
+
+/// A history of picked items (such as launched programs), scored by how often and how recently
+/// each one was picked.
+///
+/// If the history was [loaded][Frecency::load] from a file, it is written back to that file each
+/// time that an item is [recorded][Frecency::record]. The file contains a line for each item
+/// giving the number of times it has been picked, when it was last picked (in seconds since the
+/// Unix epoch) and the item itself, separated by tabs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frecency {
+    path: Option<PathBuf>,
+    entries: HashMap<String, Usage>,
+}
+
+impl Frecency {
+    /// Create a new, empty history that is only kept in memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the history stored in 'path', starting from an empty history if the file does not
+    /// exist yet. Lines of the file that can not be parsed are ignored.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(s) => parse_history(&s),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path: Some(path),
+            entries,
+        }
+    }
+
+    /// Load the history called 'name' from the `penrose` directory in the XDG
+    /// [data home][xdg::data_home] (`~/.local/share/penrose/<name>` by default).
+    ///
+    /// The history is only kept in memory if the data home can not be determined.
+    pub fn named(name: &str) -> Self {
+        match xdg::data_home() {
+            Some(dir) => Self::load(dir.join("penrose").join(name)),
+            None => Self::new(),
+        }
+    }
+
+    /// The file that this history is stored in, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Record that 'item' has just been picked, saving the updated history if it has a file.
+    pub fn record(&mut self, item: impl Into<String>) -> Result<()> {
+        self.record_at(item.into(), now());
+        self.save()
+    }
+
+    /// Forget that 'item' has ever been picked, saving the updated history if it has a file.
+    pub fn remove(&mut self, item: &str) -> Result<()> {
+        if self.entries.remove(item).is_some() {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// The items that have been picked at least once, in no particular order
+    pub fn items(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|s| s.as_str())
+    }
+
+    /// The current score of 'item': higher scores should be listed first and items that have
+    /// never been picked have a score of 0.
+    pub fn score(&self, item: &str) -> f64 {
+        self.score_at(item, now())
+    }
+
+    /// Sort 'items' so that those with the highest score come first, using 'key' to get the
+    /// name that each item is recorded under.
+    ///
+    /// The sort is stable, so items with equal scores (such as those that have never been
+    /// picked) keep their existing order.
+    pub fn sort_by_score<T, F>(&self, items: &mut [T], key: F)
+    where
+        F: Fn(&T) -> &str,
+    {
+        let now = now();
+        items.sort_by(|a, b| {
+            self.score_at(key(b), now)
+                .partial_cmp(&self.score_at(key(a), now))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    fn record_at(&mut self, item: String, now: u64) {
+        let usage = self.entries.entry(item).or_insert(Usage {
+            count: 0,
+            last_used: now,
+        });
+        usage.count += 1;
+        usage.last_used = now;
+    }
+
+    fn score_at(&self, item: &str, now: u64) -> f64 {
+        self.entries.get(item).map_or(0.0, |u| u.score(now))
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write to a temporary file first so that the history is not lost if penrose dies part
+        // way through writing it out
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, format_history(&self.entries))?;
+        fs::rename(&tmp, path)?;
+
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn parse_history(s: &str) -> HashMap<String, Usage> {
+    s.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let count = fields.next()?.parse().ok()?;
+            let last_used = fields.next()?.parse().ok()?;
+            let item = fields.next().filter(|item| !item.is_empty())?;
+
+            Some((item.to_string(), Usage { count, last_used }))
+        })
+        .collect()
+}
+
+fn format_history(entries: &HashMap<String, Usage>) -> String {
+    let mut items: Vec<(&String, &Usage)> = entries.iter().collect();
+    items.sort_by_key(|(item, _)| *item);
+
+    items
+        .into_iter()
+        .map(|(item, u)| format!("{}\t{}\t{}\n", u.count, u.last_used, item))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_000_000_000;
+
+    test_cases! {
+        usage_score;
+        args: (count: u32, age: u64, expected: f64);
+
+        case: within_the_hour => (3, 10, 12.0);
+        case: within_the_day => (3, 2 * HOUR, 6.0);
+        case: within_the_week => (3, 2 * DAY, 1.5);
+        case: older => (4, 2 * WEEK, 1.0);
+
+        body: {
+            let usage = Usage { count, last_used: NOW - age };
+            assert_eq!(usage.score(NOW), expected);
+        }
+    }
+
+    #[test]
+    fn recent_items_can_outrank_frequent_ones() {
+        let mut history = Frecency::new();
+        for _ in 0..5 {
+            history.record_at("old".into(), NOW - 2 * WEEK);
+        }
+        history.record_at("new".into(), NOW);
+
+        assert!(history.score_at("new", NOW) > history.score_at("old", NOW));
+        assert_eq!(history.score_at("unknown", NOW), 0.0);
+    }
+
+    #[test]
+    fn sorting_keeps_the_order_of_unpicked_items() {
+        let mut history = Frecency::new();
+        history.record("c".to_string()).unwrap();
+        let mut items = vec!["a", "b", "c", "d"];
+
+        history.sort_by_score(&mut items, |s| s);
+
+        assert_eq!(items, vec!["c", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn history_round_trips_through_its_file_format() {
+        let mut history = Frecency::new();
+        history.record_at("firefox.desktop".into(), NOW);
+        history.record_at("firefox.desktop".into(), NOW + 1);
+        history.record_at("st -e htop".into(), NOW);
+
+        let s = format_history(&history.entries);
+
+        assert_eq!(
+            s,
+            "2\t1000000001\tfirefox.desktop\n1\t1000000000\tst -e htop\n"
+        );
+        assert_eq!(parse_history(&s), history.entries);
+    }
+
+    #[test]
+    fn malformed_lines_are_ignored() {
+        let entries = parse_history("nope\n1\t2\tok\n3\tx\tbad\n4\t5\t\n");
+
+        assert_eq!(entries.keys().collect::<Vec<_>>(), vec!["ok"]);
+    }
+
+    #[test]
+    fn removed_items_are_forgotten() {
+        let mut history = Frecency::new();
+        history.record("a").unwrap();
+        history.record("b").unwrap();
+
+        history.remove("a").unwrap();
+        history.remove("unknown").unwrap();
+
+        assert_eq!(history.items().collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(history.score("a"), 0.0);
+    }
+
+    #[test]
+    fn recording_saves_the_history() {
+        let path = std::env::temp_dir()
+            .join(format!("penrose-frecency-{}", std::process::id()))
+            .join("history");
+
+        let mut history = Frecency::load(&path);
+        history.record("foo").unwrap();
+        let loaded = Frecency::load(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded, history);
+    }
+}
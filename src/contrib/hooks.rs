@@ -3,9 +3,9 @@ use crate::{
     contrib::actions::update_monitors_via_xrandr,
     core::{
         data_types::RelativePosition,
-        helpers::spawn,
         hooks::Hook,
         manager::WindowManager,
+        process::spawn_with_args,
         ring::Selector,
         xconnection::{XConn, Xid},
     },
@@ -33,8 +33,10 @@ impl ActiveClientAsRootName {
 
 impl<X: XConn> Hook<X> for ActiveClientAsRootName {
     fn new_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> Result<()> {
-        let c = wm.client(&Selector::WinId(id)).unwrap();
-        wm.set_root_window_name(c.wm_name())
+        match wm.client(&Selector::WinId(id)) {
+            Some(c) => wm.set_root_window_name(c.wm_name()),
+            None => Ok(()), // removed by an earlier hook
+        }
     }
 }
 
@@ -64,9 +66,10 @@ impl<X: XConn> Hook<X> for LayoutSymbolAsRootName {
  * Whenever a focus moves to the workspace 'name' and the workspace is empty,
  * set a specific layout and spawn a set of default clients.
  *
- * The layout is set first and then clients are spawned in the order they are defined using the
- * penrose::core::helpers::spawn function. This means that the final client will have focus and the
- * the clients will be arranged based on the order they are spawned.
+ * The layout is set first and then clients are spawned in the order they are defined using
+ * [WindowManager::spawn] so that they can see which workspace they were started for. This means
+ * that the final client will have focus and the the clients will be arranged based on the order
+ * they are spawned.
  */
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct DefaultWorkspace {
@@ -96,7 +99,7 @@ impl<X: XConn> Hook<X> for DefaultWorkspace {
             if ws.name() == self.name && ws.is_empty() {
                 // can fail if the layout symbol is wrong
                 ws.try_set_layout(&self.layout);
-                return self.defaults.iter().try_for_each(spawn);
+                return self.defaults.iter().try_for_each(|cmd| wm.spawn(cmd));
             }
         }
 
@@ -193,7 +196,11 @@ impl<X: XConn> Hook<X> for ClientSpawnRules {
     /// This sets the client workspace to the desired value which is then picked up and
     /// trigers the spawn on that workspace in WindowManager.handle_map_request
     fn new_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> Result<()> {
-        let c = wm.client_mut(&Selector::WinId(id)).unwrap();
+        let c = match wm.client_mut(&Selector::WinId(id)) {
+            Some(c) => c,
+            None => return Ok(()), // removed by an earlier hook
+        };
+
         if let Some(wix) = self.class_rules.get(c.wm_class()) {
             c.set_workspace(*wix);
         } else if let Some(wix) = self.name_rules.get(c.wm_name()) {
@@ -236,6 +243,39 @@ where
     }
 }
 
+/// Turn off a laptop's built in display using xrandr when the lid is closed and turn it back on
+/// when the lid is opened.
+///
+/// The workspaces that were shown on the display are moved to the remaining screens once the
+/// change in outputs has been picked up from RandR. Lid events need to be sent to penrose by
+/// something such as the [Logind][1] extension.
+///
+/// [1]: crate::contrib::extensions::Logind
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisableOutputOnLidClose {
+    output: String,
+}
+
+impl DisableOutputOnLidClose {
+    /// Create a new DisableOutputOnLidClose for the named xrandr output (e.g. `eDP-1`) that is
+    /// pre-boxed for adding to your workspace hooks.
+    pub fn new(output: impl Into<String>) -> Box<Self> {
+        Box::new(Self {
+            output: output.into(),
+        })
+    }
+}
+
+impl<X> Hook<X> for DisableOutputOnLidClose
+where
+    X: XConn,
+{
+    fn lid_state_changed(&mut self, _: &mut WindowManager<X>, closed: bool) -> Result<()> {
+        let state = if closed { "--off" } else { "--auto" };
+        spawn_with_args("xrandr", &["--output", &self.output, state])
+    }
+}
+
 /// Attempt to find and manage any existing normal clients that were running when penrose
 /// started.
 ///
@@ -2,8 +2,12 @@
 use crate::core::{
     client::Client,
     data_types::{Region, ResizeAction},
+    layout::Spacing,
     xconnection::Xid,
 };
+use crate::draw::TAB_BAR_HEIGHT;
+
+use std::cmp;
 
 /**
  * A layout that aims to mimic the feel of having multiple pieces of paper fanned out on a desk,
@@ -18,12 +22,13 @@ pub fn paper(
     clients: &[&Client],
     focused: Option<Xid>,
     monitor_region: &Region,
+    spacing: &Spacing,
     _: u32,
     ratio: f32,
 ) -> Vec<ResizeAction> {
     let n = clients.len();
     if n == 1 {
-        return vec![(clients[0].id(), Some(*monitor_region))];
+        return vec![(clients[0].id(), Some(spacing.pad(monitor_region)))];
     }
 
     let (mx, my, mw, mh) = monitor_region.values();
@@ -40,14 +45,145 @@ pub fn paper(
             let cid = c.id();
             if cid == fid {
                 after_focused = true;
-                (cid, Some(Region::new(mx + i as u32 * step, my, cw, mh)))
+                let r = Region::new(mx + i as u32 * step, my, cw, mh);
+                (cid, Some(spacing.pad(&r)))
             } else {
                 let mut x = mx + i as u32 * step;
                 if after_focused {
                     x += cw - step
                 };
-                (cid, Some(Region::new(x, my, step, mh)))
+                (cid, Some(spacing.pad(&Region::new(x, my, step, mh))))
+            }
+        })
+        .collect()
+}
+
+/**
+ * A horizontally scrolling layout in the style of PaperWM: each client is given a full height
+ * column ratio% of the screen wide and the columns are laid out on a strip that can be wider
+ * than the screen, with changes in focus scrolling the screen along the strip.
+ *
+ * The focused column is centred on the screen unless that would scroll past either end of the
+ * strip. Columns that are only partly on screen are shrunk to the part that is visible and
+ * columns that are entirely off screen are unmapped, so this layout should be used with
+ * [follow_focus][crate::core::layout::LayoutConf::follow_focus] set in order for the strip to
+ * scroll as focus moves. As layout functions do not hold any state of their own, all columns
+ * share the same width: resizing the focused column is done through the main ratio bindings
+ * (which resize every column) and n_main is ignored.
+ */
+pub fn scrolling(
+    clients: &[&Client],
+    focused: Option<Xid>,
+    monitor_region: &Region,
+    spacing: &Spacing,
+    _: u32,
+    ratio: f32,
+) -> Vec<ResizeAction> {
+    let (mx, my, mw, mh) = monitor_region.values();
+    let min_w = 0.2; // anything narrower than this leaves columns that are too small to use
+    let cw = (mw as f32 * if ratio > min_w { ratio } else { min_w }) as i64;
+    let (n, mw) = (clients.len() as i64, mw as i64);
+
+    let fi = focused
+        .and_then(|id| clients.iter().position(|c| c.id() == id))
+        .unwrap_or(0) as i64;
+    let max_offset = cmp::max(n * cw - mw, 0);
+    let offset = cmp::min(cmp::max(fi * cw + cw / 2 - mw / 2, 0), max_offset);
+    let min_visible = 2 * (spacing.border_px + spacing.gap_px) as i64;
+
+    clients
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let left = cmp::max(i as i64 * cw - offset, 0);
+            let right = cmp::min((i as i64 + 1) * cw - offset, mw);
+            if right - left <= min_visible {
+                return (c.id(), None);
             }
+
+            let r = Region::new(mx + left as u32, my, (right - left) as u32, mh);
+            (c.id(), Some(spacing.pad(&r)))
+        })
+        .collect()
+}
+
+/**
+ * A tabbed layout in the style of i3's tabbed containers: the focused client takes up all of the
+ * screen below a strip of [TAB_BAR_HEIGHT] pixels and all other clients are unmapped.
+ *
+ * The strip is left free for a [TabBar][crate::draw::TabBar] showing a tab for each client,
+ * which needs the layout to have [follow_focus][crate::core::layout::LayoutConf::follow_focus]
+ * set so that changing focus shows the newly focused client. n_main and ratio are ignored.
+ */
+pub fn tabbed(
+    clients: &[&Client],
+    focused: Option<Xid>,
+    monitor_region: &Region,
+    spacing: &Spacing,
+    _: u32,
+    _: f32,
+) -> Vec<ResizeAction> {
+    let (mx, my, mw, mh) = monitor_region.values();
+    let tab_h = cmp::min(TAB_BAR_HEIGHT, mh / 2);
+    let r = spacing.pad(&Region::new(mx, my + tab_h, mw, mh - tab_h));
+    let fid = focused
+        .filter(|&id| clients.iter().any(|c| c.id() == id))
+        .or_else(|| clients.first().map(|c| c.id()));
+
+    clients
+        .iter()
+        .map(|c| {
+            let cid = c.id();
+            (cid, if Some(cid) == fid { Some(r) } else { None })
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(n: u32, focused: Xid) -> Vec<Option<(u32, u32)>> {
+        let clients: Vec<Client> = (1..=n).map(|id| Client::detached(id, 0)).collect();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let r = Region::new(0, 0, 1000, 500);
+
+        scrolling(&refs, Some(focused), &r, &Spacing::default(), 1, 0.5)
+            .into_iter()
+            .map(|(_, r)| r.map(|r| (r.x, r.w)))
+            .collect()
+    }
+
+    test_cases! {
+        scrolling;
+        args: (n: u32, focused: Xid, expected: Vec<Option<(u32, u32)>>);
+
+        case: fits_on_screen => (2, 2, vec![Some((0, 500)), Some((500, 500))]);
+        case: start_of_strip => (
+            4, 1, vec![Some((0, 500)), Some((500, 500)), None, None]
+        );
+        case: focused_is_centred => (
+            4, 2, vec![Some((0, 250)), Some((250, 500)), Some((750, 250)), None]
+        );
+        case: end_of_strip => (
+            4, 4, vec![None, None, Some((0, 500)), Some((500, 500))]
+        );
+
+        body: {
+            assert_eq!(columns(n, focused), expected);
+        }
+    }
+
+    #[test]
+    fn tabbed_leaves_room_for_the_tab_bar() {
+        let clients: Vec<Client> = (1..=3).map(|id| Client::detached(id, 0)).collect();
+        let refs: Vec<&Client> = clients.iter().collect();
+        let r = Region::new(0, 0, 1000, 500);
+        let below_tabs = Region::new(0, TAB_BAR_HEIGHT, 1000, 500 - TAB_BAR_HEIGHT);
+
+        assert_eq!(
+            tabbed(&refs, Some(2), &r, &Spacing::default(), 1, 0.5),
+            vec![(1, None), (2, Some(below_tabs)), (3, None)]
+        );
+    }
+}
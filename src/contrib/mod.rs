@@ -2,5 +2,6 @@
 
 pub mod actions;
 pub mod extensions;
+pub mod frecency;
 pub mod hooks;
 pub mod layouts;
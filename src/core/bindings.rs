@@ -1,13 +1,17 @@
-//! Setting up and responding to user defined key/mouse bindings
+//! Setting up and responding to user defined key, mouse, pointer and touchpad gesture bindings
 use crate::{
-    core::{data_types::Point, manager::WindowManager, xconnection::Xid},
+    core::{
+        data_types::{Point, Region},
+        manager::WindowManager,
+        xconnection::{XConn, Xid},
+    },
     PenroseError, Result,
 };
 
 #[cfg(feature = "keysyms")]
 use penrose_keysyms::XKeySym;
 
-use std::{collections::HashMap, convert::TryFrom};
+use std::{cell::RefCell, collections::HashMap, convert::TryFrom, fmt, rc::Rc, time::Duration};
 
 use strum::EnumIter;
 
@@ -23,6 +27,22 @@ pub type KeyBindings<X> = HashMap<KeyCode, KeyEventHandler<X>>;
 /// User defined mouse bindings
 pub type MouseBindings<X> = HashMap<(MouseEventKind, MouseState), MouseEventHandler<X>>;
 
+/// An action to be run in response to a touchpad gesture
+pub type GestureEventHandler<X> = KeyEventHandler<X>;
+
+/// User defined touchpad gesture bindings
+pub type GestureBindings<X> = HashMap<Gesture, GestureEventHandler<X>>;
+
+/// An action to be run when the pointer rests in a [HotCorner]
+pub type HotCornerHandler<X> = KeyEventHandler<X>;
+
+/// User defined bindings for mouse buttons pressed (or scrolled) over the desktop or along the
+/// edges of a screen, running the same actions as key bindings.
+pub type PointerBindings<X> = HashMap<(PointerTarget, MouseState), KeyEventHandler<X>>;
+
+// The default width and height of each hot corner in pixels
+pub(crate) const DEFAULT_HOT_CORNER_SIZE: u32 = 2;
+
 pub(crate) type CodeMap = HashMap<String, u8>;
 
 /// Abstraction layer for working with key presses
@@ -102,6 +122,338 @@ impl KeyCode {
     }
 }
 
+/// A problem found with a user key binding while parsing it, see [KeyBindingReport]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KeyBindingIssue {
+    /// The key name in the binding is not known for the current keyboard layout so the binding
+    /// was dropped
+    UnknownKey(String),
+    /// The same binding was given more than once: only the last action given is used
+    Duplicate(String),
+    /// Two different bindings resolve to the same key code and modifiers: the later 'binding'
+    /// replaces the earlier 'replaced'
+    Conflict {
+        /// The binding that is used
+        binding: String,
+        /// The earlier binding that is no longer used
+        replaced: String,
+        /// The key code and modifiers that both bindings resolve to
+        key_code: KeyCode,
+    },
+}
+
+impl fmt::Display for KeyBindingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownKey(b) => write!(f, "'{}' uses an unknown key name", b),
+            Self::Duplicate(b) => write!(f, "'{}' is bound more than once", b),
+            Self::Conflict {
+                binding, replaced, ..
+            } => write!(
+                f,
+                "'{}' replaces '{}' as they are the same key",
+                binding, replaced
+            ),
+        }
+    }
+}
+
+/// The key bindings that were parsed by [gen_keybindings_with_report][1] along with any
+/// problems that were found with them.
+///
+/// Bindings are listed in the order they were given with any that were replaced or dropped
+/// removed. The [Display] implementation pretty prints the full report and the report is
+/// included in [WindowManager::snapshot][2] once it has been passed to
+/// [set_key_binding_report][3].
+///
+/// [1]: crate::gen_keybindings_with_report
+/// [2]: crate::core::manager::WindowManager::snapshot
+/// [3]: crate::core::manager::WindowManager::set_key_binding_report
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyBindingReport {
+    bindings: Vec<(String, KeyCode)>,
+    issues: Vec<KeyBindingIssue>,
+}
+
+impl KeyBindingReport {
+    /// Create a new empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the result of parsing 'binding', returning the [KeyCode] that should be bound if
+    /// it could be parsed.
+    pub fn record(
+        &mut self,
+        binding: impl Into<String>,
+        parsed: Option<KeyCode>,
+    ) -> Option<KeyCode> {
+        let binding = binding.into();
+        let key_code = match parsed {
+            Some(key_code) => key_code,
+            None => {
+                self.issues.push(KeyBindingIssue::UnknownKey(binding));
+                return None;
+            }
+        };
+
+        if let Some(ix) = self.bindings.iter().position(|(_, k)| *k == key_code) {
+            let (replaced, _) = self.bindings.remove(ix);
+            self.issues.push(if replaced == binding {
+                KeyBindingIssue::Duplicate(binding.clone())
+            } else {
+                KeyBindingIssue::Conflict {
+                    binding: binding.clone(),
+                    replaced,
+                    key_code,
+                }
+            });
+        }
+        self.bindings.push((binding, key_code));
+
+        Some(key_code)
+    }
+
+    /// The bindings that are in use and the key codes they resolved to
+    pub fn bindings(&self) -> &[(String, KeyCode)] {
+        &self.bindings
+    }
+
+    /// Any problems found while parsing the bindings
+    pub fn issues(&self) -> &[KeyBindingIssue] {
+        &self.issues
+    }
+
+    /// The bindings that were dropped due to having an unknown key name
+    pub fn unknown_keys(&self) -> Vec<&str> {
+        self.issues
+            .iter()
+            .flat_map(|i| match i {
+                KeyBindingIssue::UnknownKey(b) => Some(b.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Log each of the issues in this report as a warning
+    pub fn log_issues(&self) {
+        for issue in self.issues.iter() {
+            warn!(%issue, "problem found in key bindings");
+        }
+    }
+}
+
+impl fmt::Display for KeyBindingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .bindings
+            .iter()
+            .map(|(b, _)| b.len())
+            .max()
+            .unwrap_or(0);
+        for (binding, k) in self.bindings.iter() {
+            writeln!(
+                f,
+                "{:width$}  mask={:#06x} code={}",
+                binding,
+                k.mask,
+                k.code,
+                width = width
+            )?;
+        }
+
+        if !self.issues.is_empty() {
+            writeln!(f, "\n{} issue(s):", self.issues.len())?;
+            for issue in self.issues.iter() {
+                writeln!(f, "  {}", issue)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A set of [KeyBindings] that are made available for a single key press after a prefix binding,
+/// allowing for emacs style key sequences such as `M-w` followed by `k`.
+///
+/// The [handler][KeyChord::handler] should be bound to the prefix key. Once it has been pressed,
+/// the bindings in the chord take priority over your top level key bindings until one of them is
+/// pressed. Pressing a top level binding that is not part of the chord cancels the chord and runs
+/// the top level binding as normal, and the chord is cancelled once its timeout (if there is one)
+/// expires. Keys that are not bound in the chord or at the top level are not grabbed by penrose,
+/// so they are passed through to the focused client without cancelling the chord. Chords may be
+/// nested by binding the handler of another chord within a chord.
+///
+/// The hints for the active chord are passed to the
+/// [key_chord_changed][crate::core::hooks::Hook::key_chord_changed] hook so that they can be shown
+/// on screen: see [KeyChordHint][crate::draw::widget::KeyChordHint].
+///
+/// # Example
+/// ```no_run
+/// # #[macro_use] extern crate penrose;
+/// # use penrose::core::{bindings::KeyBindings, ring::Selector, xconnection::XConn};
+/// use penrose::core::bindings::KeyChord;
+/// use std::time::Duration;
+///
+/// # fn example<X: XConn + 'static>() {
+/// let window_chord: KeyChord<X> = KeyChord::new(gen_keybindings! {
+///     "k" => run_internal!(kill_client);
+///     "f" => run_internal!(toggle_client_fullscreen, &Selector::Focused);
+///     "t" => run_external!("alacritty");
+/// })
+/// .timeout(Duration::from_secs(2))
+/// .hints(vec!["k: kill", "f: fullscreen", "t: terminal"]);
+///
+/// let key_bindings: KeyBindings<X> = gen_keybindings! {
+///     "M-w" => window_chord.handler();
+/// };
+/// # }
+/// ```
+pub struct KeyChord<X: XConn> {
+    bindings: Rc<RefCell<KeyBindings<X>>>,
+    hints: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+impl<X: XConn> fmt::Debug for KeyChord<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyChord")
+            .field("bindings", &self.key_codes())
+            .field("hints", &self.hints)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl<X: XConn> Clone for KeyChord<X> {
+    fn clone(&self) -> Self {
+        Self {
+            bindings: Rc::clone(&self.bindings),
+            hints: self.hints.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<X: XConn> KeyChord<X> {
+    /// Create a new KeyChord with no timeout that makes 'bindings' available once entered
+    pub fn new(bindings: KeyBindings<X>) -> Self {
+        Self {
+            bindings: Rc::new(RefCell::new(bindings)),
+            hints: vec![],
+            timeout: None,
+        }
+    }
+
+    /// Cancel the chord if none of its bindings are pressed within 'timeout'
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Descriptions of the available continuations to show while the chord is active
+    pub fn hints(mut self, hints: Vec<impl Into<String>>) -> Self {
+        self.hints = hints.into_iter().map(|h| h.into()).collect();
+        self
+    }
+
+    /// The descriptions of the available continuations for this chord
+    pub fn get_hints(&self) -> &[String] {
+        &self.hints
+    }
+
+    /// The timeout for this chord, if one has been set
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// A [KeyEventHandler] that enters this chord when run.
+    ///
+    /// All handlers returned by the same KeyChord share their bindings.
+    pub fn handler(&self) -> KeyEventHandler<X>
+    where
+        X: 'static,
+    {
+        let chord = self.clone();
+        Box::new(move |wm: &mut WindowManager<X>| wm.enter_key_chord(chord.clone()))
+    }
+
+    /// The [KeyCode]s bound within this chord
+    pub fn key_codes(&self) -> Vec<KeyCode> {
+        self.bindings.borrow().keys().copied().collect()
+    }
+
+    // Run the binding for 'k' if there is one, returning whether or not it was bound
+    pub(crate) fn run(&self, wm: &mut WindowManager<X>, k: KeyCode) -> Option<Result<()>> {
+        self.bindings
+            .borrow_mut()
+            .get_mut(&k)
+            .map(|action| action(wm))
+    }
+}
+
+// Key bindings are resolved to key codes once, using the keyboard mapping at the point that they
+// were created. If the mapping later changes (e.g. after running setxkbmap) the key names that
+// were bound move to different key codes: this maps between the two so that bindings can be left
+// keyed by their original codes while the keys being grabbed follow the key names.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct KeyRemap {
+    to_current: HashMap<KeyCodeValue, KeyCodeValue>,
+    to_original: HashMap<KeyCodeValue, KeyCodeValue>,
+}
+
+impl KeyRemap {
+    // Each original key code follows the names that were bound to it. Where the names for a key
+    // code have been split across several keys, the key code is left alone if any of them are
+    // still in place and otherwise moves to the key holding the most of them.
+    pub(crate) fn between(original: &CodeMap, current: &CodeMap) -> Self {
+        let mut candidates: HashMap<KeyCodeValue, HashMap<KeyCodeValue, usize>> = HashMap::new();
+        for (name, &orig) in original.iter() {
+            if let Some(&cur) = current.get(name) {
+                *candidates.entry(orig).or_default().entry(cur).or_default() += 1;
+            }
+        }
+
+        let mut remap = Self::default();
+        for (orig, targets) in candidates {
+            if targets.contains_key(&orig) {
+                continue;
+            }
+            let target = targets
+                .into_iter()
+                .max_by(|(a, n), (b, m)| n.cmp(m).then(b.cmp(a)))
+                .map(|(code, _)| code);
+            if let Some(cur) = target {
+                remap.to_current.insert(orig, cur);
+                remap.to_original.insert(cur, orig);
+            }
+        }
+
+        remap
+    }
+
+    // The key to grab for a binding made with the original mapping. Bindings for keys whose
+    // code has been taken over by another binding without moving themselves can not be grabbed.
+    pub(crate) fn current(&self, k: KeyCode) -> Option<KeyCode> {
+        match self.to_current.get(&k.code) {
+            Some(&code) => Some(KeyCode { code, ..k }),
+            None if self.to_original.contains_key(&k.code) => None,
+            None => Some(k),
+        }
+    }
+
+    // The binding that a key press using the current mapping was made for
+    pub(crate) fn original(&self, k: KeyCode) -> Option<KeyCode> {
+        match self.to_original.get(&k.code) {
+            Some(&code) => Some(KeyCode { code, ..k }),
+            None if self.to_current.contains_key(&k.code) => None,
+            None => Some(k),
+        }
+    }
+}
+
 /// Known mouse buttons for binding actions
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -130,6 +482,20 @@ impl From<MouseButton> for u8 {
     }
 }
 
+/// The ways that a [MouseButton] can be clicked on a [StatusBar][1] widget
+///
+/// [1]: crate::draw::StatusBar
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ClickKind {
+    /// The button was pressed and released once
+    Single,
+    /// The button was clicked a second time shortly after a single click in the same place
+    Double,
+    /// The button was held down for a while before being released
+    Hold,
+}
+
 /// Known modifier keys for bindings
 #[derive(Debug, EnumIter, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -224,3 +590,335 @@ impl MouseEvent {
         }
     }
 }
+
+/// The direction of a touchpad swipe gesture
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SwipeDirection {
+    /// Fingers moved towards the top of the touchpad
+    Up,
+    /// Fingers moved towards the bottom of the touchpad
+    Down,
+    /// Fingers moved towards the left of the touchpad
+    Left,
+    /// Fingers moved towards the right of the touchpad
+    Right,
+}
+
+impl SwipeDirection {
+    /// The dominant direction of a swipe that moved a total of (dx, dy).
+    ///
+    /// Returns None if there was no movement.
+    pub fn from_delta(dx: f64, dy: f64) -> Option<Self> {
+        if dx == 0.0 && dy == 0.0 {
+            None
+        } else if dx.abs() > dy.abs() {
+            Some(if dx > 0.0 { Self::Right } else { Self::Left })
+        } else {
+            Some(if dy > 0.0 { Self::Down } else { Self::Up })
+        }
+    }
+}
+
+/// A completed multi-finger swipe on a touchpad
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Gesture {
+    /// The number of fingers used
+    pub fingers: u8,
+    /// The overall direction of the swipe
+    pub direction: SwipeDirection,
+}
+
+impl Gesture {
+    /// Construct a new [Gesture]
+    pub fn new(fingers: u8, direction: SwipeDirection) -> Self {
+        Self { fingers, direction }
+    }
+
+    /// The data sent in a `_PENROSE_GESTURE` client message to trigger this gesture
+    pub fn as_data(&self) -> [u32; 5] {
+        let direction = match self.direction {
+            SwipeDirection::Up => 0,
+            SwipeDirection::Down => 1,
+            SwipeDirection::Left => 2,
+            SwipeDirection::Right => 3,
+        };
+
+        [self.fingers as u32, direction, 0, 0, 0]
+    }
+
+    /// Parse the data from a `_PENROSE_GESTURE` client message
+    pub fn from_data(data: &[u32]) -> Option<Self> {
+        let direction = match data.get(1)? {
+            0 => SwipeDirection::Up,
+            1 => SwipeDirection::Down,
+            2 => SwipeDirection::Left,
+            3 => SwipeDirection::Right,
+            _ => return None,
+        };
+
+        Some(Self::new(*data.first()? as u8, direction))
+    }
+}
+
+/// A corner or edge of a screen that can trigger an action when the pointer rests in it
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HotCorner {
+    /// The top left corner of the screen
+    TopLeft,
+    /// The top right corner of the screen
+    TopRight,
+    /// The bottom left corner of the screen
+    BottomLeft,
+    /// The bottom right corner of the screen
+    BottomRight,
+    /// The top edge of the screen, between the corners
+    Top,
+    /// The bottom edge of the screen, between the corners
+    Bottom,
+    /// The left edge of the screen, between the corners
+    Left,
+    /// The right edge of the screen, between the corners
+    Right,
+}
+
+impl HotCorner {
+    /// The part of 'screen' that triggers this corner when corners are 'size' pixels square.
+    ///
+    /// Edges are a single pixel thick and run between the corners.
+    pub fn region(&self, screen: &Region, size: u32) -> Region {
+        let (x, y, w, h) = screen.values();
+        let (far_x, far_y) = (x + w.saturating_sub(size), y + h.saturating_sub(size));
+        let (edge_w, edge_h) = (w.saturating_sub(2 * size), h.saturating_sub(2 * size));
+
+        match self {
+            Self::TopLeft => Region::new(x, y, size, size),
+            Self::TopRight => Region::new(far_x, y, size, size),
+            Self::BottomLeft => Region::new(x, far_y, size, size),
+            Self::BottomRight => Region::new(far_x, far_y, size, size),
+            Self::Top => Region::new(x + size, y, edge_w, 1),
+            Self::Bottom => Region::new(x + size, y + h.saturating_sub(1), edge_w, 1),
+            Self::Left => Region::new(x, y + size, 1, edge_h),
+            Self::Right => Region::new(x + w.saturating_sub(1), y + size, 1, edge_h),
+        }
+    }
+}
+
+/// Actions to run when the pointer rests in a corner or along an edge of any screen for longer
+/// than a given delay.
+///
+/// Each bound corner is covered by a small input only window on every screen that is raised
+/// above the clients on that screen whenever it is laid out. Screens showing a fullscreen client
+/// are skipped so that corners can not be triggered while playing games or watching videos.
+/// Pass your HotCorners to
+/// [set_hot_corners][crate::core::manager::WindowManager::set_hot_corners] to enable them.
+///
+/// # Example
+/// ```no_run
+/// # #[macro_use] extern crate penrose;
+/// # use penrose::core::{manager::WindowManager, xconnection::XConn};
+/// use penrose::core::bindings::{HotCorner, HotCornerHandler, HotCorners};
+/// use std::time::Duration;
+///
+/// # fn example<X: XConn + 'static>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+/// let hot_corners = HotCorners::new(map! {
+///     HotCorner::TopLeft => Box::new(|wm: &mut WindowManager<X>| wm.toggle_workspace())
+///         as HotCornerHandler<X>,
+///     HotCorner::BottomRight => Box::new(|_: &mut WindowManager<X>| spawn!("slock")),
+/// })
+/// .delay(Duration::from_millis(500));
+///
+/// wm.set_hot_corners(hot_corners)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct HotCorners<X: XConn> {
+    bindings: HashMap<HotCorner, HotCornerHandler<X>>,
+    delay: Duration,
+    size: u32,
+}
+
+impl<X: XConn> fmt::Debug for HotCorners<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HotCorners")
+            .field("corners", &self.corners())
+            .field("delay", &self.delay)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl<X: XConn> HotCorners<X> {
+    /// Create a new set of HotCorners that trigger after the pointer has rested in a 2 pixel
+    /// corner for 300ms.
+    pub fn new(bindings: HashMap<HotCorner, HotCornerHandler<X>>) -> Self {
+        Self {
+            bindings,
+            delay: Duration::from_millis(300),
+            size: DEFAULT_HOT_CORNER_SIZE,
+        }
+    }
+
+    /// Set how long the pointer needs to stay in a corner before its action is run
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Set the width and height of each corner in pixels
+    pub fn size(mut self, size: u32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// How long the pointer needs to stay in a corner before its action is run
+    pub fn get_delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// The width and height of each corner in pixels
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+
+    /// The corners that have an action bound
+    pub fn corners(&self) -> Vec<HotCorner> {
+        self.bindings.keys().copied().collect()
+    }
+
+    // Run the action bound to 'corner' if there is one
+    pub(crate) fn run(&mut self, wm: &mut WindowManager<X>, corner: HotCorner) -> Result<()> {
+        match self.bindings.get_mut(&corner) {
+            Some(action) => action(wm),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Where the pointer needs to be for one of your [PointerBindings] to be triggered.
+///
+/// Edges are covered by the same input windows as [HotCorners] (and are the same size), so
+/// clicking or scrolling along an edge works even when a client is underneath it.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PointerTarget {
+    /// Over the desktop (the root window) rather than a client
+    Desktop,
+    /// In a corner or along an edge of any screen
+    Edge(HotCorner),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_map(pairs: &[(&str, u8)]) -> CodeMap {
+        pairs.iter().map(|&(n, c)| (n.to_string(), c)).collect()
+    }
+
+    #[test]
+    fn key_binding_reports_track_conflicts_and_unknown_keys() {
+        let k = |code| KeyCode { mask: 64, code };
+        let mut report = KeyBindingReport::new();
+
+        assert_eq!(report.record("M-j", Some(k(44))), Some(k(44)));
+        assert_eq!(report.record("M-k", Some(k(45))), Some(k(45)));
+        assert_eq!(report.record("M-nope", None), None);
+        assert_eq!(report.record("M-k", Some(k(45))), Some(k(45)));
+        assert_eq!(report.record("M-J", Some(k(44))), Some(k(44)));
+
+        assert_eq!(
+            report.bindings(),
+            &[("M-k".to_string(), k(45)), ("M-J".to_string(), k(44))]
+        );
+        assert_eq!(
+            report.issues(),
+            &[
+                KeyBindingIssue::UnknownKey("M-nope".into()),
+                KeyBindingIssue::Duplicate("M-k".into()),
+                KeyBindingIssue::Conflict {
+                    binding: "M-J".into(),
+                    replaced: "M-j".into(),
+                    key_code: k(44),
+                },
+            ]
+        );
+        assert_eq!(report.unknown_keys(), vec!["M-nope"]);
+    }
+
+    #[test]
+    fn key_binding_reports_are_pretty_printed() {
+        let mut report = KeyBindingReport::new();
+        report.record("M-Return", Some(KeyCode { mask: 64, code: 36 }));
+        report.record("M-j", Some(KeyCode { mask: 64, code: 44 }));
+        report.record("M-nope", None);
+
+        let expected = "\
+M-Return  mask=0x0040 code=36
+M-j       mask=0x0040 code=44
+
+1 issue(s):
+  'M-nope' uses an unknown key name
+";
+        assert_eq!(report.to_string(), expected);
+    }
+
+    #[test]
+    fn key_remap_follows_moved_key_names() {
+        // QWERTY -> QWERTZ swaps 'y' and 'z'
+        let original = code_map(&[("y", 29), ("Y", 29), ("z", 52), ("Z", 52), ("j", 44)]);
+        let current = code_map(&[("z", 29), ("Z", 29), ("y", 52), ("Y", 52), ("j", 44)]);
+        let remap = KeyRemap::between(&original, &current);
+
+        let k = |code| KeyCode { mask: 64, code };
+        assert_eq!(remap.current(k(29)), Some(k(52)));
+        assert_eq!(remap.current(k(52)), Some(k(29)));
+        assert_eq!(remap.current(k(44)), Some(k(44)));
+        assert_eq!(remap.original(k(52)), Some(k(29)));
+        assert_eq!(remap.original(k(44)), Some(k(44)));
+    }
+
+    #[test]
+    fn key_remap_leaves_partially_moved_keys_in_place() {
+        let original = code_map(&[("1", 10), ("exclam", 10), ("a", 38)]);
+        let current = code_map(&[("1", 10), ("exclam", 61), ("q", 38)]);
+        let remap = KeyRemap::between(&original, &current);
+
+        assert_eq!(remap, KeyRemap::default());
+    }
+
+    #[test]
+    fn key_remap_hides_keys_that_now_hold_a_moved_binding() {
+        // 'a' has moved onto the key that was bound as 'q' and 'q' no longer exists
+        let original = code_map(&[("a", 38), ("q", 24)]);
+        let current = code_map(&[("a", 24)]);
+        let remap = KeyRemap::between(&original, &current);
+
+        let k = |code| KeyCode { mask: 0, code };
+        assert_eq!(remap.current(k(38)), Some(k(24)));
+        assert_eq!(remap.current(k(24)), None);
+        assert_eq!(remap.original(k(24)), Some(k(38)));
+        assert_eq!(remap.original(k(38)), None);
+    }
+
+    test_cases! {
+        hot_corner_region;
+        args: (corner: HotCorner, expected: Region);
+
+        case: top_left => (HotCorner::TopLeft, Region::new(100, 0, 5, 5));
+        case: top_right => (HotCorner::TopRight, Region::new(895, 0, 5, 5));
+        case: bottom_left => (HotCorner::BottomLeft, Region::new(100, 595, 5, 5));
+        case: bottom_right => (HotCorner::BottomRight, Region::new(895, 595, 5, 5));
+        case: top => (HotCorner::Top, Region::new(105, 0, 790, 1));
+        case: bottom => (HotCorner::Bottom, Region::new(105, 599, 790, 1));
+        case: left => (HotCorner::Left, Region::new(100, 5, 1, 590));
+        case: right => (HotCorner::Right, Region::new(899, 5, 1, 590));
+
+        body: {
+            let screen = Region::new(100, 0, 800, 600);
+            assert_eq!(corner.region(&screen, 5), expected);
+        }
+    }
+}
@@ -1,6 +1,56 @@
 //! Metadata around X clients and manipulating them
 use crate::core::xconnection::{Atom, Prop, WmHints, WmNormalHints, XClientProperties, Xid};
 
+/// The [weight][Client::weight] that clients start with
+pub const DEFAULT_CLIENT_WEIGHT: u32 = 100;
+
+// Clients serialized before weights were tracked are treated as having the default weight
+#[cfg(feature = "serde")]
+fn default_weight() -> u32 {
+    DEFAULT_CLIENT_WEIGHT
+}
+
+/// How a client expects to be given input focus, following the input models described in
+/// section 4.1.7 of the ICCCM.
+///
+/// The model is determined by the input field of a client's WM_HINTS and whether or not it lists
+/// `WM_TAKE_FOCUS` in its WM_PROTOCOLS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputModel {
+    /// The client never expects keyboard input and should not be given input focus
+    NoInput,
+    /// The client expects the window manager to set input focus on it
+    Passive,
+    /// The client expects the window manager to set input focus on it and is also sent
+    /// `WM_TAKE_FOCUS` so that it can move focus to one of its subwindows
+    LocallyActive,
+    /// The client is sent `WM_TAKE_FOCUS` and sets input focus itself
+    GloballyActive,
+}
+
+impl InputModel {
+    /// Determine the input model from the input hint of a client and whether or not it supports
+    /// the `WM_TAKE_FOCUS` protocol
+    pub fn new(accepts_input: bool, takes_focus: bool) -> Self {
+        match (accepts_input, takes_focus) {
+            (false, false) => Self::NoInput,
+            (true, false) => Self::Passive,
+            (true, true) => Self::LocallyActive,
+            (false, true) => Self::GloballyActive,
+        }
+    }
+
+    /// Whether or not the window manager should set input focus on the client
+    pub fn accepts_input(&self) -> bool {
+        matches!(self, Self::Passive | Self::LocallyActive)
+    }
+
+    /// Whether or not the client should be sent `WM_TAKE_FOCUS` when it is focused
+    pub fn takes_focus(&self) -> bool {
+        matches!(self, Self::LocallyActive | Self::GloballyActive)
+    }
+}
+
 /**
  * Meta-data around a client window that we are handling.
  *
@@ -12,17 +62,36 @@ use crate::core::xconnection::{Atom, Prop, WmHints, WmNormalHints, XClientProper
 pub struct Client {
     pub(crate) id: Xid,
     pub(crate) workspace: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) extra_tags: Vec<usize>,
     pub(crate) wm_name: String,
     pub(crate) wm_class: Vec<String>, // should always be two elements but that's not enforced?
     pub(crate) wm_type: Vec<String>,  // Can't use Atom as it could be something arbitrary
     pub(crate) wm_protocols: Vec<String>, // Can't use Atom as it could be something arbitrary
+    pub(crate) wm_role: Option<String>,
     pub(crate) wm_hints: Option<WmHints>,
     pub(crate) wm_normal_hints: Option<WmNormalHints>,
+    pub(crate) pid: Option<u32>,
+    pub(crate) transient_for: Option<Xid>,
+    #[cfg_attr(feature = "serde", serde(default = "default_weight"))]
+    pub(crate) weight: u32,
     // state flags
     pub(crate) accepts_focus: bool,
     pub(crate) floating: bool,
     pub(crate) fullscreen: bool,
     pub(crate) mapped: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) maximized_horz: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) maximized_vert: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) minimized: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) no_swallow: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) sticky: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) terminal: bool,
     pub(crate) urgent: bool,
     pub(crate) wm_managed: bool,
 }
@@ -63,20 +132,66 @@ impl Client {
             Ok(Prop::Atom(protocols)) => protocols,
             _ => vec![],
         };
+        let wm_role = match conn.get_prop(id, Atom::WmWindowRole.as_ref()) {
+            Ok(Prop::UTF8String(strs)) => strs.into_iter().next(),
+            _ => None,
+        };
+        let pid = match conn.get_prop(id, Atom::NetWmPid.as_ref()) {
+            Ok(Prop::Cardinal(pid)) => Some(pid),
+            _ => None,
+        };
+        let transient_for = match conn.get_prop(id, Atom::WmTransientFor.as_ref()) {
+            Ok(Prop::Window(ids)) => ids.into_iter().next(),
+            _ => None,
+        };
 
         Self {
-            id,
-            workspace,
             wm_name,
             wm_class,
             wm_type,
             wm_protocols,
+            wm_role,
             wm_hints,
             wm_normal_hints,
+            pid,
+            transient_for,
             floating,
             accepts_focus,
+            ..Self::detached(id, workspace)
+        }
+    }
+
+    /// A client on a specific workspace that has not had any of its properties read from the X
+    /// server.
+    ///
+    /// Layouts and workspaces only make use of the state held by the client itself, so detached
+    /// clients can be used to run them without a connection to a display server (for example, when
+    /// testing a layout function).
+    pub fn detached(id: Xid, workspace: usize) -> Self {
+        Self {
+            id,
+            workspace,
+            extra_tags: vec![],
+            wm_name: "unknown".into(),
+            wm_class: vec![],
+            wm_type: vec![Atom::NetWindowTypeNormal.as_ref().to_string()],
+            wm_protocols: vec![],
+            wm_role: None,
+            wm_hints: None,
+            wm_normal_hints: None,
+            pid: None,
+            transient_for: None,
+            weight: DEFAULT_CLIENT_WEIGHT,
+            floating: false,
+            accepts_focus: true,
             fullscreen: false,
             mapped: false,
+            maximized_horz: false,
+            maximized_vert: false,
+            minimized: false,
+            no_swallow: false,
+            sticky: false,
+            terminal: false,
             urgent: false,
             wm_managed: true,
         }
@@ -97,19 +212,111 @@ impl Client {
         &self.wm_name
     }
 
+    /// The WM_WINDOW_ROLE property of this client (if it is set)
+    pub fn wm_role(&self) -> Option<&str> {
+        self.wm_role.as_deref()
+    }
+
+    /// The _NET_WM_PID property of this client (if it is set)
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// The window that this client is a transient for (e.g. the parent of a dialog), as given by
+    /// WM_TRANSIENT_FOR
+    pub fn transient_for(&self) -> Option<Xid> {
+        self.transient_for
+    }
+
     /// Whether or not this client is currently fullscreen
     pub fn is_fullscreen(&self) -> bool {
         self.fullscreen
     }
 
+    /// Whether or not this client has been maximized to fill the width of its screen
+    pub fn is_maximized_horz(&self) -> bool {
+        self.maximized_horz
+    }
+
+    /// Whether or not this client has been maximized to fill the height of its screen
+    pub fn is_maximized_vert(&self) -> bool {
+        self.maximized_vert
+    }
+
+    /// Whether or not this client is currently minimized
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Whether or not this client is shown on every workspace of its screen
+    pub fn is_sticky(&self) -> bool {
+        self.sticky
+    }
+
+    /// Whether or not this client has currently set its urgency hint
+    pub fn is_urgent(&self) -> bool {
+        self.urgent
+    }
+
+    /// The ICCCM [InputModel] of this client
+    pub fn input_model(&self) -> InputModel {
+        let take_focus = Atom::WmTakeFocus.as_ref();
+        let takes_focus = self.wm_protocols.iter().any(|p| p == take_focus);
+
+        InputModel::new(self.accepts_focus, takes_focus)
+    }
+
+    /// Whether or not this client supported being asked to close itself the last time that its
+    /// protocols were read
+    pub fn accepts_close_request(&self) -> bool {
+        let delete = Atom::WmDeleteWindow.as_ref();
+        self.wm_protocols.iter().any(|p| p == delete)
+    }
+
+    /// Whether or not this client is a dock, such as a status bar
+    pub fn is_dock(&self) -> bool {
+        let dock = Atom::NetWindowTypeDock.as_ref();
+        self.wm_type.iter().any(|t| t == dock)
+    }
+
     /// The current workspace index that this client is showing on
     pub fn workspace(&self) -> usize {
         self.workspace
     }
 
+    /// The share of space that this client is given relative to the other clients it is tiled
+    /// alongside, by layouts that support resizing individual clients.
+    ///
+    /// Clients start with a weight of [DEFAULT_CLIENT_WEIGHT] and are reset to it when the window
+    /// manager moves them to a different workspace.
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Set the weight of this client, see [weight][Client::weight]. Weights are at least 1.
+    pub fn set_weight(&mut self, weight: u32) {
+        self.weight = weight.max(1);
+    }
+
     /// Mark this window as being on a new workspace
     pub fn set_workspace(&mut self, workspace: usize) {
-        self.workspace = workspace
+        self.workspace = workspace;
+        self.extra_tags.retain(|&t| t != workspace);
+    }
+
+    /// The tags carried by this client when running in [tag mode][1]: the index of its workspace
+    /// followed by any additional tags it has been given.
+    ///
+    /// [1]: crate::core::config::Config::tag_mode
+    pub fn tags(&self) -> Vec<usize> {
+        let mut tags = vec![self.workspace];
+        tags.extend(&self.extra_tags);
+        tags
+    }
+
+    /// Whether or not this client carries the given tag
+    pub fn has_tag(&self, tag: usize) -> bool {
+        self.workspace == tag || self.extra_tags.contains(&tag)
     }
 
     /// Set the floating state of this client
@@ -121,6 +328,21 @@ impl Client {
         self.wm_name = name.into()
     }
 
+    // The _NET_WM_STATE atoms for the parts of this client's state that we track
+    pub(crate) fn net_wm_state(&self) -> Vec<String> {
+        [
+            (self.urgent, Atom::NetWmStateDemandsAttention),
+            (self.fullscreen, Atom::NetWmStateFullscreen),
+            (self.maximized_horz, Atom::NetWmStateMaximizedHorz),
+            (self.maximized_vert, Atom::NetWmStateMaximizedVert),
+            (self.sticky, Atom::NetWmStateSticky),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, a)| a.as_ref().to_string())
+        .collect()
+    }
+
     /// The WM_CLASS of the window that this Client is tracking
     pub fn class(&self) -> &str {
         self.wm_class.get(0).map(AsRef::as_ref).unwrap_or("unknown")
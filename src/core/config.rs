@@ -1,10 +1,18 @@
 //! User facing configuration of the penrose [WindowManager][crate::core::manager::WindowManager].
 use crate::{
-    core::layout::{side_stack, Layout, LayoutConf},
-    draw::{Color, DrawError},
+    core::{
+        input::{KeyboardConfig, PointerConfig},
+        layout::{side_stack, Layout, LayoutConf},
+        rules::Rule,
+    },
+    draw::{Background, Color, DrawError, Position},
 };
 
-use std::convert::TryInto;
+use std::{collections::HashMap, convert::TryInto};
+
+// The names of the commands that can be given in Config::remote_commands
+pub(crate) const REMOTE_COMMANDS: &[&str] =
+    &["gesture", "move-window", "power-event", "tile-window"];
 
 __with_builder_and_getters! {
     /// The main user facing configuration details.
@@ -22,6 +30,7 @@ __with_builder_and_getters! {
     /// assert_eq!(config.focused_border(), &Color::try_from("#cc241d").unwrap());
     /// ```
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "serde", serde(default))]
     #[derive(Clone, Debug, PartialEq)]
     Config;
 
@@ -65,6 +74,33 @@ __with_builder_and_getters! {
     /// You must provide at least one workspace per screen
     VecImplInto workspaces: String; => vec!["1", "2", "3", "4", "5", "6", "7", "8", "9"];
 
+    /// treat workspaces as dwm/awesome style tags rather than fixed workspaces.
+    ///
+    /// In tag mode a client can carry more than one tag and each screen can view more than one
+    /// tag at a time: see [toggle_tag_on_client][1] and [view_tags][2]. The layout of the first
+    /// tag being viewed on a screen is used to position all of the clients that it is showing.
+    ///
+    /// [1]: crate::core::manager::WindowManager::toggle_tag_on_client
+    /// [2]: crate::core::manager::WindowManager::view_tags
+    Concrete tag_mode: bool; => false;
+
+    /// label each workspace with the most common WM_CLASS of the clients it contains.
+    ///
+    /// Labels are appended to the workspace name when setting _NET_DESKTOP_NAMES and running
+    /// the `workspaces_updated` hook (used by the bar workspace widget), so workspace "1"
+    /// containing mostly terminals will be displayed as "1 term" given a
+    /// [workspace_class_names][1] entry mapping the terminal class to "term".
+    ///
+    /// [1]: Config::workspace_class_names
+    Concrete auto_name_workspaces: bool; => false;
+
+    /// labels (names or icons) to use for client classes when [auto_name_workspaces][1] is set.
+    ///
+    /// Classes without an entry are used as the label directly.
+    ///
+    /// [1]: Config::auto_name_workspaces
+    Concrete workspace_class_names: HashMap<String, String>; => HashMap::new();
+
     /// the window classes that will always be considered floating
     VecImplInto floating_classes: String; => vec!["dmenu", "dunst"];
 
@@ -78,22 +114,205 @@ __with_builder_and_getters! {
             Layout::floating("[----]"),
         ];
 
+    /// the maximum number of clients that will be added to a workspace when they are first mapped.
+    ///
+    /// Once a workspace is full, new clients that would have been placed on it are sent to the
+    /// [overflow_workspace][Config::overflow_workspace] instead. Clients can still be moved to a
+    /// full workspace manually.
+    Concrete max_clients_per_workspace: Option<usize>; => None;
+    /// the index of the workspace that new clients are sent to when their workspace is full.
+    ///
+    /// If this is `None` then the client cap is not applied. Adding and removing workspaces keeps
+    /// this pointing at the same workspace, clearing it if that workspace is removed.
+    Concrete overflow_workspace: Option<usize>; => Some(8);
+
+    /// the [Rule]s used to modify how newly mapped clients are handled
+    Concrete rules: Vec<Rule>; => vec![];
+
+    /// the [PointerConfig]s applied to matching input devices on startup and when devices are
+    /// added or removed (only supported by the `x11rb` backend)
+    Concrete pointer_config: Vec<PointerConfig>; => vec![];
+
+    /// the [KeyboardConfig] applied on startup and when devices are added or removed (device
+    /// hotplug is only detected by the `x11rb` backend)
+    Concrete keyboard_config: Option<KeyboardConfig>; => None;
+
+    /// the names of the client messages that other programs are allowed to send to the root
+    /// window in order to control penrose:
+    ///   - "move-window" and "tile-window": the [RemoteCommand][1]s
+    ///   - "gesture": running gesture bindings (needed by [LibinputGestures][2])
+    ///   - "power-event": lid and sleep events (needed by [Logind][3])
+    ///
+    /// Any program that is able to connect to your X server can send these messages, so none of
+    /// them are allowed by default and only the ones that you make use of should be listed.
+    /// Messages that are not listed here are logged and then ignored.
+    ///
+    /// [1]: crate::contrib::extensions::RemoteCommand
+    /// [2]: crate::contrib::extensions::LibinputGestures
+    /// [3]: crate::contrib::extensions::Logind
+    VecImplInto remote_commands: String; => Vec::<String>::new();
+
+    /// where the pointer is moved to when focusing a different [Workspace][1] on the same screen.
+    ///
+    /// [1]: crate::core::workspace::Workspace
+    Concrete warp_on_workspace_change: PointerWarp; => PointerWarp::Never;
+    /// where the pointer is moved to when focusing a different screen.
+    Concrete warp_on_screen_change: PointerWarp; => PointerWarp::ToScreen;
+    /// where the pointer is moved to when focusing or moving a different client on the same
+    /// screen.
+    Concrete warp_on_client_change: PointerWarp; => PointerWarp::ToClient;
+
     /// the focused border color as a hex literal
     ImplTry DrawError; focused_border: Color; => "#cc241d";
     /// the unfocused border color as a hex literal
     ImplTry DrawError; unfocused_border: Color; => "#3c3836";
+    /// the border color for clients that have set their urgency hint as a hex literal
+    ImplTry DrawError; urgent_border: Color; => "#d79921";
     /// the border width of each window in pixels
     Concrete border_px: u32; => 2;
+    /// the width in pixels of a second, inner line drawn alongside each window inside of its
+    /// border: 0 to draw solid borders.
+    ///
+    /// When set, the focused, unfocused and urgent border colors are used for the outer part of
+    /// the border with the inner line being drawn using [inner_border][Config::inner_border].
+    Concrete inner_border_px: u32; => 0;
+    /// the color of the inner line of two-tone borders as a hex literal
+    ImplTry DrawError; inner_border: Color; => "#282828";
+    /// the background of the root window while each workspace is shown, in the same order as
+    /// [workspaces][Config::workspaces]: the root window is left as it is for workspaces that
+    /// do not have a background.
+    ///
+    /// Colors are set directly by the [WindowManager][1] while images need to be drawn by the
+    /// [Wallpapers][2] hook. Backgrounds can be changed at runtime using
+    /// [set_workspace_background][3].
+    ///
+    /// [1]: crate::core::manager::WindowManager
+    /// [2]: crate::draw::Wallpapers
+    /// [3]: crate::core::manager::WindowManager::set_workspace_background
+    Concrete workspace_backgrounds: Vec<Background>; => vec![];
     /// the gap between tiled windows in pixels
     Concrete gap_px: u32; => 5;
+    /// whether or not border widths, gaps and the space reserved for the status bar should be
+    /// scaled by the DPI of the screen they are shown on.
+    ///
+    /// When enabled, the sizes given here are treated as being for a 96 DPI screen. The DPI of
+    /// each screen is taken from its physical size as reported by RandR, falling back to the
+    /// value of `Xft.dpi` in your X resources if that is not known.
+    Concrete scale_by_dpi: bool; => false;
     /// the percentage of the screen to grow the main region by when incrementing
     Concrete main_ratio_step: f32; => 0.05;
-    /// whether or not space should be reserved for a status bar
+    /// whether or not clients that look like fullscreen video or games should automatically be
+    /// made fullscreen (without borders or gaps).
+    ///
+    /// Clients are detected either by setting `_NET_WM_BYPASS_COMPOSITOR` or by requesting to be
+    /// resized to cover the entire screen. Only floating clients are made fullscreen when they
+    /// ask to cover the screen.
+    Concrete auto_fullscreen: bool; => false;
+    /// whether or not the programs found in the XDG autostart directories should be started along
+    /// with any [autostart][crate::core::manager::WindowManager::autostart] programs.
+    ///
+    /// Entries are skipped if they are marked as `Hidden` or their `OnlyShowIn` and `NotShowIn`
+    /// keys exclude the desktops listed in `XDG_CURRENT_DESKTOP` (or "Penrose" if that is not
+    /// set). See [xdg][crate::core::xdg] for details.
+    Concrete xdg_autostart: bool; => false;
+    /// whether or not space should be reserved for the internal status bar.
+    ///
+    /// When this is `false` (e.g. when using an external bar such as polybar) only the space
+    /// requested by dock windows through `_NET_WM_STRUT_PARTIAL` is reserved.
+    ///
+    /// This, along with [bar_hidden_screens][Config::bar_hidden_screens],
+    /// [top_bar][Config::top_bar] and [bar_height][Config::bar_height], is ignored if any
+    /// [bars][Config::bars] have been given.
     Concrete show_bar: bool; => true;
+    /// the indices of screens on which no space should be reserved for the internal status bar
+    Concrete bar_hidden_screens: Vec<usize>; => vec![];
     /// whether or not the reserved space for a status bar is at the top of the sceen
     Concrete top_bar: bool; => true;
     /// the height of the space to be reserved for a status bar in pixels
     Concrete bar_height: u32; => 18;
+    /// the space to reserve for each of the internal status bars when using more than one of
+    /// them (e.g. one at the top and one at the bottom of the screen) or when different screens
+    /// show different bars.
+    ///
+    /// Space is reserved for each bar independently: bars at the same edge of a screen are
+    /// stacked on top of one another.
+    Concrete bars: Vec<BarConfig>; => vec![];
+}
+
+/// The space to reserve on each screen for one of the internal status bars.
+///
+/// The same height, position and screens should be used when creating the
+/// [StatusBar][crate::draw::StatusBar] itself, which can be done using
+/// [StatusBar::try_new_from_config][crate::draw::StatusBar::try_new_from_config].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BarConfig {
+    height: u32,
+    position: Position,
+    screens: Option<Vec<usize>>,
+    hidden_screens: Vec<usize>,
+}
+
+impl BarConfig {
+    /// Reserve 'height' pixels at the given edge of every screen
+    pub fn new(height: u32, position: Position) -> Self {
+        Self {
+            height,
+            position,
+            screens: None,
+            hidden_screens: vec![],
+        }
+    }
+
+    /// Only reserve space for this bar on the screens with the given indices
+    pub fn screens(mut self, screens: Vec<usize>) -> Self {
+        self.screens = Some(screens);
+        self
+    }
+
+    /// Don't reserve space for this bar on the screens with the given indices
+    pub fn hidden_screens(mut self, screens: Vec<usize>) -> Self {
+        self.hidden_screens = screens;
+        self
+    }
+
+    /// The height of this bar in pixels (before any scaling by DPI)
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The edge of the screen that this bar is shown at
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Whether or not this bar is shown on the screen with the given index
+    pub fn is_shown_on(&self, screen: usize) -> bool {
+        let included = match self.screens {
+            Some(ref screens) => screens.contains(&screen),
+            None => true,
+        };
+
+        included && !self.hidden_screens.contains(&screen)
+    }
+}
+
+/// Where the pointer is moved to after changing focus using the [WindowManager][1].
+///
+/// Moving the pointer stops focus from being taken straight back by the client under the pointer
+/// when using focus-follows-mouse.
+///
+/// [1]: crate::core::manager::WindowManager
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerWarp {
+    /// Leave the pointer where it is
+    Never,
+    /// Move the pointer to the centre of the focused screen
+    ToScreen,
+    /// Move the pointer to the centre of the focused client (or of the focused screen if there
+    /// is no focused client)
+    ToClient,
 }
 
 impl Config {
@@ -101,6 +320,27 @@ impl Config {
     pub fn ws_range(&self) -> std::ops::Range<usize> {
         1..(self.workspaces.len() + 1)
     }
+
+    /// The bars that space needs to be reserved for: either those given in
+    /// [bars][Config::bars] or the single bar described by [show_bar][Config::show_bar],
+    /// [top_bar][Config::top_bar], [bar_height][Config::bar_height] and
+    /// [bar_hidden_screens][Config::bar_hidden_screens].
+    pub fn bar_configs(&self) -> Vec<BarConfig> {
+        if !self.bars.is_empty() {
+            return self.bars.clone();
+        } else if !self.show_bar {
+            return vec![];
+        }
+
+        let position = if self.top_bar {
+            Position::Top
+        } else {
+            Position::Bottom
+        };
+
+        vec![BarConfig::new(self.bar_height, position)
+            .hidden_screens(self.bar_hidden_screens.clone())]
+    }
 }
 
 impl ConfigBuilder {
@@ -117,6 +357,38 @@ impl ConfigBuilder {
             return Err("main_ratio_step must be in the range 0.0 -> 1.0".into());
         }
 
+        if let Some(max) = self.inner.max_clients_per_workspace {
+            if max == 0 {
+                return Err("max_clients_per_workspace must be greater than 0".into());
+            }
+            let n_workspaces = self.inner.workspaces.len();
+            if matches!(self.inner.overflow_workspace, Some(ix) if ix >= n_workspaces) {
+                return Err("overflow_workspace must be a valid workspace index".into());
+            }
+        }
+
+        if self.inner.inner_border_px > self.inner.border_px {
+            return Err("inner_border_px must not be larger than border_px".into());
+        }
+
+        let unknown_command = |c: &String| !REMOTE_COMMANDS.contains(&c.as_str());
+        if let Some(c) = self
+            .inner
+            .remote_commands
+            .iter()
+            .find(|c| unknown_command(c))
+        {
+            return Err(format!("unknown remote command: {}", c));
+        }
+
+        let invalid_accel = |c: &PointerConfig| {
+            let speed = c.get_accel_speed();
+            matches!(speed, Some(s) if !(-1.0..=1.0).contains(&s))
+        };
+        if self.inner.pointer_config.iter().any(invalid_accel) {
+            return Err("pointer accel_speed must be in the range -1.0 -> 1.0".into());
+        }
+
         Ok(())
     }
 }
@@ -203,6 +203,65 @@ impl Region {
         (self.x..(self.x + self.w)).contains(&p.x) && (self.y..(self.y + self.h)).contains(&p.y)
     }
 
+    /// Find the index of the [Region] in `candidates` that is the nearest neighbour of this one
+    /// in the direction given by `position`.
+    ///
+    /// Only regions lying entirely to that side of this one are considered. Regions that overlap
+    /// with this one along the other axis are preferred over those that are diagonally offset,
+    /// then the closest region is chosen, with ties broken by how well aligned they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::{Region, RelativePosition};
+    ///
+    /// let main = Region::new(0, 0, 50, 100);
+    /// let stack = vec![Region::new(50, 0, 50, 50), Region::new(50, 50, 50, 50)];
+    ///
+    /// assert_eq!(main.nearest_in_direction(&stack, RelativePosition::Right), Some(0));
+    /// assert_eq!(main.nearest_in_direction(&stack, RelativePosition::Left), None);
+    /// assert_eq!(stack[0].nearest_in_direction(&stack, RelativePosition::Below), Some(1));
+    /// ```
+    pub fn nearest_in_direction(
+        &self,
+        candidates: &[Region],
+        position: RelativePosition,
+    ) -> Option<usize> {
+        let (x, y, w, h) = self.values();
+        let (x1, y1, x2, y2) = (x as i64, y as i64, (x + w) as i64, (y + h) as i64);
+        let (cx, cy) = (x1 + x2, y1 + y2); // doubled to avoid rounding
+
+        candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| {
+                let (rx, ry, rw, rh) = r.values();
+                let (rx1, ry1, rx2, ry2) =
+                    (rx as i64, ry as i64, (rx + rw) as i64, (ry + rh) as i64);
+                let (rcx, rcy) = (rx1 + rx2, ry1 + ry2);
+
+                let (distance, overlaps, offset) = match position {
+                    RelativePosition::Left if rx2 <= x1 => {
+                        (x1 - rx2, ry1 < y2 && y1 < ry2, rcy - cy)
+                    }
+                    RelativePosition::Right if rx1 >= x2 => {
+                        (rx1 - x2, ry1 < y2 && y1 < ry2, rcy - cy)
+                    }
+                    RelativePosition::Above if ry2 <= y1 => {
+                        (y1 - ry2, rx1 < x2 && x1 < rx2, rcx - cx)
+                    }
+                    RelativePosition::Below if ry1 >= y2 => {
+                        (ry1 - y2, rx1 < x2 && x1 < rx2, rcx - cx)
+                    }
+                    _ => return None,
+                };
+
+                Some(((!overlaps, distance, offset.abs()), i))
+            })
+            .min()
+            .map(|(_, i)| i)
+    }
+
     /// Center this region inside of `enclosing`.
     ///
     /// # Errors
@@ -289,6 +348,64 @@ impl Region {
             .collect()
     }
 
+    /// Split this `Region` into rows with heights in proportion to `weights`.
+    ///
+    /// Rows are the same as those given by [as_rows][Region::as_rows] when all of the weights
+    /// are equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::Region;
+    ///
+    /// let r = Region::new(0, 0, 100, 100);
+    ///
+    /// let regions = r.as_weighted_rows(&[1, 3]);
+    ///
+    /// assert_eq!(regions.len(), 2);
+    /// assert_eq!(regions[0], Region::new(0, 0, 100, 25));
+    /// assert_eq!(regions[1], Region::new(0, 25, 100, 75));
+    /// ```
+    pub fn as_weighted_rows(&self, weights: &[u32]) -> Vec<Region> {
+        if weights.windows(2).all(|w| w[0] == w[1]) {
+            return self.as_rows(weights.len() as u32);
+        }
+
+        weighted_offsets(self.h, weights)
+            .windows(2)
+            .map(|o| Region::new(self.x, self.y + o[0], self.w, o[1] - o[0]))
+            .collect()
+    }
+
+    /// Split this `Region` into columns with widths in proportion to `weights`.
+    ///
+    /// Columns are the same as those given by [as_columns][Region::as_columns] when all of the
+    /// weights are equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use penrose::core::data_types::Region;
+    ///
+    /// let r = Region::new(0, 0, 100, 100);
+    ///
+    /// let regions = r.as_weighted_columns(&[1, 3]);
+    ///
+    /// assert_eq!(regions.len(), 2);
+    /// assert_eq!(regions[0], Region::new(0, 0, 25, 100));
+    /// assert_eq!(regions[1], Region::new(25, 0, 75, 100));
+    /// ```
+    pub fn as_weighted_columns(&self, weights: &[u32]) -> Vec<Region> {
+        if weights.windows(2).all(|w| w[0] == w[1]) {
+            return self.as_columns(weights.len() as u32);
+        }
+
+        weighted_offsets(self.w, weights)
+            .windows(2)
+            .map(|o| Region::new(self.x + o[0], self.y, o[1] - o[0], self.h))
+            .collect()
+    }
+
     /// Divides this region into two columns where the first has the given width.
     ///
     /// # Errors
@@ -369,3 +486,18 @@ impl Region {
         }
     }
 }
+
+// The start of each of a set of weighted spans covering 'total' pixels followed by the end of the
+// final span. Weights of zero are treated as one.
+fn weighted_offsets(total: u32, weights: &[u32]) -> Vec<u32> {
+    let weights: Vec<u64> = weights.iter().map(|&w| w.max(1) as u64).collect();
+    let sum: u64 = weights.iter().sum();
+    let mut acc = 0;
+
+    std::iter::once(0)
+        .chain(weights.iter().map(|w| {
+            acc += w;
+            (total as u64 * acc / sum) as u32
+        }))
+        .collect()
+}
@@ -6,55 +6,15 @@ use crate::{
 
 use std::{
     io::Read,
-    process::{Command, Stdio},
+    process::{Child, Command, Stdio},
 };
 
-/// Run an external command
-///
-/// This redirects the process stdout and stderr to /dev/null.
-pub fn spawn<S: Into<String>>(cmd: S) -> Result<()> {
-    let s = cmd.into();
-    let parts: Vec<&str> = s.split_whitespace().collect();
-    let result = if parts.len() > 1 {
-        Command::new(parts[0])
-            .args(&parts[1..])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-    } else {
-        Command::new(parts[0])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-    };
-
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.into()),
-    }
-}
-
-/// Run an external command with the specified command line arguments
-///
-/// This redirects the process stdout and stderr to /dev/null.
-pub fn spawn_with_args<S: Into<String>>(cmd: S, args: &[&str]) -> Result<()> {
-    let result = Command::new(cmd.into())
-        .args(args)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn();
-
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.into()),
-    }
-}
+// Kept here so that existing configs importing from helpers continue to work
+pub use crate::core::process::{spawn, spawn_with_args};
 
 /// Run an external command and return its output.
 ///
-/// NOTE: std::process::Command::output will not work within penrose due to the
-/// way that signal handling is set up. Use this function if you need to access the
-/// output of a process that you spawn.
+/// Unlike [spawn], this blocks until the process has exited.
 pub fn spawn_for_output<S: Into<String>>(cmd: S) -> Result<String> {
     let cmd = cmd.into();
     info!(?cmd, "spawning subprocess for output");
@@ -68,20 +28,12 @@ pub fn spawn_for_output<S: Into<String>>(cmd: S) -> Result<String> {
         Command::new(parts[0]).stdout(Stdio::piped()).spawn()
     };
 
-    let child = result?;
-    let mut buff = String::new();
-    Ok(child
-        .stdout
-        .ok_or(PenroseError::SpawnProc(cmd))?
-        .read_to_string(&mut buff)
-        .map(|_| buff)?)
+    read_output(cmd, result?)
 }
 
 /// Run an external command with arguments and return its output.
 ///
-/// NOTE: std::process::Command::output will not work within penrose due to the
-/// way that signal handling is set up. Use this function if you need to access the
-/// output of a process that you spawn.
+/// Unlike [spawn_with_args], this blocks until the process has exited.
 pub fn spawn_for_output_with_args<S: Into<String>>(cmd: S, args: &[&str]) -> Result<String> {
     let cmd = cmd.into();
 
@@ -92,12 +44,20 @@ pub fn spawn_for_output_with_args<S: Into<String>>(cmd: S, args: &[&str]) -> Res
         .spawn()?;
 
     info!(?cmd, ?args, "reading output");
+    read_output(cmd, child)
+}
+
+// Read all of stdout and then reap the child so that it is not left as a zombie
+fn read_output(cmd: String, mut child: Child) -> Result<String> {
     let mut buff = String::new();
-    Ok(child
+    child
         .stdout
+        .take()
         .ok_or(PenroseError::SpawnProc(cmd))?
-        .read_to_string(&mut buff)
-        .map(|_| buff)?)
+        .read_to_string(&mut buff)?;
+    child.wait()?;
+
+    Ok(buff)
 }
 
 /// Run the xmodmap command to dump the system keymap table.
@@ -133,6 +93,26 @@ pub fn keycodes_from_xmodmap() -> CodeMap {
     }
 }
 
+/// Run the xmodmap command to dump the system keymap table, returning an error rather than
+/// panicking if it could not be read.
+///
+/// Any lines that are not of the form `keycode <code> = <names ...>` are skipped.
+pub fn try_keycodes_from_xmodmap() -> Result<CodeMap> {
+    Ok(parse_keycodes(&spawn_for_output("xmodmap -pke")?))
+}
+
+fn parse_keycodes(raw: &str) -> CodeMap {
+    raw.lines()
+        .flat_map(|l| {
+            let mut words = l.split_whitespace(); // keycode <code> = <names ...>
+            let key_code: Option<u8> = words.nth(1).and_then(|w| w.parse().ok());
+            key_code
+                .into_iter()
+                .flat_map(move |code| words.clone().skip(1).map(move |name| (name.into(), code)))
+        })
+        .collect()
+}
+
 /// Create a Vec of index selectors for the given input slice
 pub fn index_selectors<'a, T>(len: usize) -> Vec<Selector<'a, T>> {
     (0..len).map(Selector::Index).collect()
@@ -118,7 +118,9 @@
 //! [4]: crate::core::client::Client
 use crate::{
     core::{
-        data_types::Region,
+        bindings::{ClickKind, MouseButton},
+        data_types::{Point, Region},
+        input::LockState,
         manager::WindowManager,
         xconnection::{XConn, Xid},
     },
@@ -147,12 +149,35 @@ pub enum HookName {
     ScreenUpdated,
     RanderNotify,
     FocusChange(u32),
+    ClientUrgencyChanged(Xid, bool),
     EventHandled,
+    ErrorRaised(String),
+    InputDevicesChanged,
+    LockStateChanged(LockState),
+    KeyChordChanged(Vec<String>),
+    WorkspaceOverflow(Xid, usize, usize),
+    MainRatioChanged(usize),
+    RootWindowClicked(MouseButton, Point),
+    RootWindowReleased(MouseButton, Point),
+    WindowClicked(Xid, MouseButton, Point),
+    WindowReleased(Xid, MouseButton, Point),
+    Resumed,
+    Suspending,
+    LidStateChanged(bool),
+    BarVisibilityChanged(bool),
 }
 
 /// Utility type for defining hooks in your penrose configuration.
 pub type Hooks<X> = Vec<Box<dyn Hook<X>>>;
 
+/// An action run once when penrose starts up or shuts down.
+///
+/// See [startup_hook][1] and [shutdown_hook][2].
+///
+/// [1]: crate::core::manager::WindowManager::startup_hook
+/// [2]: crate::core::manager::WindowManager::shutdown_hook
+pub type LifecycleHook<X> = Box<dyn FnMut(&mut WindowManager<X>) -> Result<()>>;
+
 /// User defined functionality triggered by [WindowManager] actions.
 ///
 /// impls of [Hook] can be registered to receive events during [WindowManager] operation. Each hook
@@ -273,6 +298,8 @@ pub trait Hook<X: XConn> {
     ///
     /// Called whenever something updates the WM_NAME or _NET_WM_NAME property on a window.
     /// `is_root == true` indicates that this is the root window that is being modified.
+    /// Changes to clients on workspaces that are not currently shown are only reported once
+    /// their workspace is next displayed.
     ///
     /// # Example Uses
     ///
@@ -398,6 +425,30 @@ pub trait Hook<X: XConn> {
         Ok(())
     }
 
+    /// # Trigger Point
+    ///
+    /// Called when a new client is sent to the overflow [Workspace][1] because the workspace it
+    /// would otherwise have been added to already holds
+    /// [max_clients_per_workspace][2] clients. This is called before the client is added to the
+    /// overflow workspace.
+    ///
+    /// # Example Uses
+    ///
+    /// Letting the user know where the new client has ended up, e.g. by sending a notification.
+    ///
+    /// [1]: crate::core::workspace::Workspace
+    /// [2]: crate::core::config::Config::max_clients_per_workspace
+    #[allow(unused_variables)]
+    fn workspace_overflow(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        full_workspace: usize,
+        overflow_workspace: usize,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     /// # Trigger Point
     ///
     /// Called after focus moves to a new [Screen][1].
@@ -471,6 +522,29 @@ pub trait Hook<X: XConn> {
         Ok(())
     }
 
+    /// # Trigger Point
+    ///
+    /// Called whenever a [Client][1] sets or clears its urgency hint, either via the `WM_HINTS`
+    /// property or a `_NET_WM_STATE_DEMANDS_ATTENTION` client message. Urgency is cleared
+    /// automatically when the client gains focus.
+    ///
+    /// # Example Uses
+    ///
+    /// Highlighting workspaces that contain urgent clients in a status bar (as is done by the
+    /// [Workspaces][2] widget) or sending a notification to the user.
+    ///
+    /// [1]: crate::core::client::Client
+    /// [2]: crate::draw::widget::bar::Workspaces
+    #[allow(unused_variables)]
+    fn client_urgency_changed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        urgent: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     /// # Trigger Point
     ///
     /// Called at the bottom of the main [WindowManager] event loop after each [XEvent][1] is handled.
@@ -485,4 +559,291 @@ pub trait Hook<X: XConn> {
     fn event_handled(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
         Ok(())
     }
+
+    /// # Trigger Point
+    ///
+    /// Called when an input device (keyboard, mouse, touchpad etc) is added, removed, enabled or
+    /// disabled, after all key and mouse bindings have been grabbed again.
+    ///
+    /// # Example Uses
+    ///
+    /// Reapplying per-device settings such as pointer acceleration or keyboard repeat rates to
+    /// newly connected devices.
+    ///
+    /// Device hotplug is only detected by the `x11rb` backend: the X Input bindings for the `xcb`
+    /// backend do not build.
+    #[allow(unused_variables)]
+    fn input_devices_changed(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when the system has resumed from suspend, after the connected [Screens][1] have
+    /// been detected again and all key and mouse bindings have been grabbed again.
+    ///
+    /// Resuming is detected from the next event that arrives after waking up by comparing the
+    /// wall clock against a monotonic clock (which does not advance while suspended). Sleeps that
+    /// are too short to be noticed this way are still picked up if a [PowerEvent][2] is received.
+    ///
+    /// # Example Uses
+    ///
+    /// Locking the screen or refreshing widgets that show time sensitive state such as network
+    /// or battery status.
+    ///
+    /// [1]: crate::core::screen::Screen
+    /// [2]: crate::core::power::PowerEvent
+    #[allow(unused_variables)]
+    fn resumed(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when the system is about to be suspended or hibernated, as reported by a
+    /// [PowerEvent][1] (see the [Logind][2] extension).
+    ///
+    /// # Example Uses
+    ///
+    /// Locking the screen before going to sleep so that it is already locked on wake up.
+    ///
+    /// [1]: crate::core::power::PowerEvent
+    /// [2]: crate::contrib::extensions::Logind
+    #[allow(unused_variables)]
+    fn suspending(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when the laptop lid is closed or opened, as reported by a [PowerEvent][1] (see the
+    /// [Logind][2] extension).
+    ///
+    /// # Example Uses
+    ///
+    /// Turning off the built in display when the lid is closed so that its workspaces move to
+    /// the remaining screens, as done by [DisableOutputOnLidClose][3].
+    ///
+    /// [1]: crate::core::power::PowerEvent
+    /// [2]: crate::contrib::extensions::Logind
+    /// [3]: crate::contrib::hooks::DisableOutputOnLidClose
+    #[allow(unused_variables)]
+    fn lid_state_changed(&mut self, wm: &mut WindowManager<X>, closed: bool) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when the status bars are shown or hidden using [set_bar_visible][1] or
+    /// [toggle_bar][2], after the visible workspaces have been laid out again to use the space
+    /// that is now available to them.
+    ///
+    /// # Example Uses
+    ///
+    /// Mapping or unmapping status bar windows to match, as done by [StatusBar][3].
+    ///
+    /// [1]: crate::core::manager::WindowManager::set_bar_visible
+    /// [2]: crate::core::manager::WindowManager::toggle_bar
+    /// [3]: crate::draw::StatusBar
+    #[allow(unused_variables)]
+    fn bar_visibility_changed(&mut self, wm: &mut WindowManager<X>, visible: bool) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when Caps Lock or Num Lock is toggled on any keyboard.
+    ///
+    /// # Example Uses
+    ///
+    /// Showing the current lock state in a status bar. The state at startup can be found using
+    /// [XConn::lock_state][1].
+    ///
+    /// [1]: crate::core::xconnection::XConn::lock_state
+    #[allow(unused_variables)]
+    fn lock_state_changed(&mut self, wm: &mut WindowManager<X>, state: LockState) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when a [KeyChord][1] is entered, with the hints for its available continuations,
+    /// and again with no hints once it has been completed, cancelled or timed out.
+    ///
+    /// # Example Uses
+    ///
+    /// Showing the key presses that are available while a chord is active.
+    ///
+    /// [1]: crate::core::bindings::KeyChord
+    #[allow(unused_variables)]
+    fn key_chord_changed(&mut self, wm: &mut WindowManager<X>, hints: &[&str]) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called after the main ratio of the active layout on a [Workspace][1] has been changed using
+    /// [update_main_ratio][2], with the new ratio.
+    ///
+    /// # Example Uses
+    ///
+    /// Giving visual feedback on the new size of the main area, as done by [RatioOverlay][3].
+    ///
+    /// [1]: crate::core::workspace::Workspace
+    /// [2]: crate::core::manager::WindowManager::update_main_ratio
+    /// [3]: crate::draw::RatioOverlay
+    #[allow(unused_variables)]
+    fn main_ratio_changed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        ratio: f32,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when a mouse button is pressed over the desktop (the root window with no client
+    /// under the pointer) and the press is not part of a grabbed mouse binding, with the button
+    /// that was pressed and the absolute position of the pointer.
+    ///
+    /// Button presses on the root window are not selected by default: hooks using this trigger
+    /// need to set [RootEventMaskWithClicks][2] on the root window in their `startup` hook.
+    ///
+    /// # Example Uses
+    ///
+    /// Showing a menu of programs to launch, as done by [RootMenu][1].
+    ///
+    /// [1]: crate::draw::RootMenu
+    /// [2]: crate::core::xconnection::ClientAttr::RootEventMaskWithClicks
+    #[allow(unused_variables)]
+    fn root_window_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        point: Point,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when a mouse button is released over the desktop after a press that triggered
+    /// [root_window_clicked][Hook::root_window_clicked], with the button that was released and
+    /// the absolute position of the pointer. As with presses, [RootEventMaskWithClicks][2] needs
+    /// to be set on the root window for releases to be reported.
+    ///
+    /// # Example Uses
+    ///
+    /// Telling apart single clicks, double clicks and press-and-hold on the desktop, in the same
+    /// way that [StatusBar][1] does for its own windows.
+    ///
+    /// [1]: crate::draw::StatusBar
+    /// [2]: crate::core::xconnection::ClientAttr::RootEventMaskWithClicks
+    #[allow(unused_variables)]
+    fn root_window_released(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        point: Point,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when a mouse button is pressed over a window that is not a [Client][1] and has had
+    /// [ButtonEventMask][2] set using the [WindowManager]'s connection, with the ID of the window,
+    /// the button that was pressed and the absolute position of the pointer. Presses that are
+    /// part of a grabbed mouse binding are not reported.
+    ///
+    /// Unlike clicks on the root window, these can be selected by penrose without stopping other
+    /// programs from handling clicks on the desktop.
+    ///
+    /// # Example Uses
+    ///
+    /// Handling clicks on windows that are drawn by penrose, such as a [StatusBar][3] or a
+    /// [TabBar][4].
+    ///
+    /// [1]: crate::core::client::Client
+    /// [2]: crate::core::xconnection::ClientAttr::ButtonEventMask
+    /// [3]: crate::draw::StatusBar
+    /// [4]: crate::draw::TabBar
+    #[allow(unused_variables)]
+    fn window_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        button: MouseButton,
+        point: Point,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when a mouse button is released after a press that triggered
+    /// [window_clicked][Hook::window_clicked], with the ID of the window that was pressed, the
+    /// button that was released and the absolute position of the pointer.
+    ///
+    /// # Example Uses
+    ///
+    /// Telling apart single clicks, double clicks and press-and-hold, as done by [StatusBar][1].
+    ///
+    /// [1]: crate::draw::StatusBar
+    #[allow(unused_variables)]
+    fn window_released(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        button: MouseButton,
+        point: Point,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called by a [StatusBar][1] for the widget under the pointer once a click on the bar has
+    /// been released and classified as a [ClickKind], with the point given relative to the top
+    /// left corner of the widget. The first click of a double click is also reported as a single
+    /// click.
+    ///
+    /// # Example Uses
+    ///
+    /// Focusing a workspace when it is clicked in a [Workspaces][2] widget and moving the focused
+    /// client there when it is held instead.
+    ///
+    /// [1]: crate::draw::StatusBar
+    /// [2]: crate::draw::widget::Workspaces
+    #[allow(unused_variables)]
+    fn bar_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        point: Point,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// # Trigger Point
+    ///
+    /// Called when an error is returned while handling an [XEvent][1] or running a key / mouse
+    /// binding, after the error has been passed to the [ErrorHandler][2] for the
+    /// [WindowManager]. Errors returned from this hook are passed to the error handler but do not
+    /// trigger this hook again.
+    ///
+    /// # Example Uses
+    ///
+    /// Displaying the most recent error in a status bar so that failures are visible without
+    /// needing to check the logs.
+    ///
+    /// [1]: crate::core::xconnection::XEvent
+    /// [2]: crate::ErrorHandler
+    #[allow(unused_variables)]
+    fn error_raised(&mut self, wm: &mut WindowManager<X>, msg: &str) -> Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,270 @@
+//! Configuration of keyboard and pointer input devices
+//!
+//! [PointerConfig]s are applied through the X Input extension to every pointer device whose name
+//! matches when penrose starts and again whenever input devices are added or removed, replacing
+//! the need for running `xinput` scripts. Settings are applied using the properties exposed by
+//! the `libinput` X driver.
+//!
+//! Pointer configuration and device hotplug are only supported by the `x11rb` backend: the X Input
+//! bindings for the `xcb` backend do not build so it reports an error when listing or configuring
+//! devices and never reports devices being added or removed.
+//!
+//! A [KeyboardConfig] sets the XKB layout and key repeat settings at the same points, using
+//! `setxkbmap` and `xset` so that newly connected keyboards pick up the same settings.
+//!
+//! The current [LockState] of the keyboard is tracked through XKB indicator events and passed to
+//! the [lock_state_changed][crate::core::hooks::Hook::lock_state_changed] hook.
+//!
+//! # Example
+//! ```
+//! use penrose::{
+//!     core::input::{KeyboardConfig, PointerConfig},
+//!     Config,
+//! };
+//!
+//! let pointer_config = vec![
+//!     PointerConfig::new("Touchpad")
+//!         .natural_scrolling(true)
+//!         .accel_speed(0.4),
+//!     PointerConfig::new("Logitech G502").button_map(vec![1, 2, 3, 4, 5, 6, 7, 9, 8]),
+//! ];
+//!
+//! let keyboard_config = KeyboardConfig::new()
+//!     .layout("gb")
+//!     .options(vec!["caps:escape"])
+//!     .repeat(250, 40);
+//!
+//! let config = Config::default()
+//!     .builder()
+//!     .pointer_config(pointer_config)
+//!     .keyboard_config(Some(keyboard_config))
+//!     .build()
+//!     .unwrap();
+//! ```
+
+/// The libinput X driver property for pointer acceleration
+pub const ACCEL_SPEED_PROP: &str = "libinput Accel Speed";
+/// The libinput X driver property for natural scrolling
+pub const NATURAL_SCROLLING_PROP: &str = "libinput Natural Scrolling Enabled";
+/// The XKB indicator name for Caps Lock
+pub const CAPS_LOCK_INDICATOR: &str = "Caps Lock";
+/// The XKB indicator name for Num Lock
+pub const NUM_LOCK_INDICATOR: &str = "Num Lock";
+
+/// A pointer device known to the X server
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InputDevice {
+    /// The X Input extension ID of this device
+    pub id: u16,
+    /// The name of this device as reported by the X server
+    pub name: String,
+}
+
+/// The state of the lock keys on the keyboard, as shown by their XKB indicators
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LockState {
+    /// Whether or not Caps Lock is active
+    pub caps_lock: bool,
+    /// Whether or not Num Lock is active
+    pub num_lock: bool,
+}
+
+/// Pointer settings to apply to all input devices with a name containing `device`
+///
+/// Any settings that are left unset will not be modified.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointerConfig {
+    device: String,
+    accel_speed: Option<f32>,
+    natural_scrolling: Option<bool>,
+    button_map: Option<Vec<u8>>,
+}
+
+impl PointerConfig {
+    /// Create a new PointerConfig for all devices with a name containing 'device'
+    pub fn new(device: impl Into<String>) -> Self {
+        Self {
+            device: device.into(),
+            accel_speed: None,
+            natural_scrolling: None,
+            button_map: None,
+        }
+    }
+
+    /// Set the pointer acceleration in the range -1.0 (slowest) to 1.0 (fastest)
+    pub fn accel_speed(mut self, speed: f32) -> Self {
+        self.accel_speed = Some(speed);
+        self
+    }
+
+    /// Enable or disable natural (reversed) scrolling
+    pub fn natural_scrolling(mut self, enabled: bool) -> Self {
+        self.natural_scrolling = Some(enabled);
+        self
+    }
+
+    /// Remap the physical buttons of the device: the button at index `i` of 'map' is the logical
+    /// button that will be reported when physical button `i + 1` is pressed.
+    pub fn button_map(mut self, map: Vec<u8>) -> Self {
+        self.button_map = Some(map);
+        self
+    }
+
+    /// Check whether or not this config applies to the given [InputDevice]
+    pub fn matches(&self, device: &InputDevice) -> bool {
+        device.name.contains(self.device.as_str())
+    }
+
+    /// The requested pointer acceleration, if it has been set
+    pub fn get_accel_speed(&self) -> Option<f32> {
+        self.accel_speed
+    }
+
+    /// Whether natural scrolling should be enabled, if it has been set
+    pub fn get_natural_scrolling(&self) -> Option<bool> {
+        self.natural_scrolling
+    }
+
+    /// The requested button mapping, if it has been set
+    pub fn get_button_map(&self) -> Option<&[u8]> {
+        self.button_map.as_deref()
+    }
+}
+
+/// XKB layout and key repeat settings applied to all keyboards
+///
+/// Any settings that are left unset will not be modified.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyboardConfig {
+    layout: Option<String>,
+    variant: Option<String>,
+    options: Vec<String>,
+    repeat: Option<(u32, u32)>,
+}
+
+impl KeyboardConfig {
+    /// Create a new KeyboardConfig that leaves all settings unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the XKB layout (or comma separated list of layouts) to use
+    pub fn layout(mut self, layout: impl Into<String>) -> Self {
+        self.layout = Some(layout.into());
+        self
+    }
+
+    /// Set the XKB layout variant (or comma separated list of variants) to use
+    pub fn variant(mut self, variant: impl Into<String>) -> Self {
+        self.variant = Some(variant.into());
+        self
+    }
+
+    /// Set the XKB options to use, replacing any that are currently set
+    pub fn options(mut self, options: Vec<impl Into<String>>) -> Self {
+        self.options = options.into_iter().map(|o| o.into()).collect();
+        self
+    }
+
+    /// Set the delay in milliseconds before a held key starts repeating and the number of
+    /// repeats per second
+    pub fn repeat(mut self, delay: u32, rate: u32) -> Self {
+        self.repeat = Some((delay, rate));
+        self
+    }
+
+    /// The external programs (and their arguments) that need to be run to apply this config
+    pub fn commands(&self) -> Vec<(&'static str, Vec<String>)> {
+        let mut commands = vec![];
+
+        if self.layout.is_some() || self.variant.is_some() || !self.options.is_empty() {
+            let mut args = vec![];
+            if let Some(layout) = &self.layout {
+                args.extend(vec!["-layout".into(), layout.clone()]);
+            }
+            if let Some(variant) = &self.variant {
+                args.extend(vec!["-variant".into(), variant.clone()]);
+            }
+            if !self.options.is_empty() {
+                // An empty -option clears any existing options before adding ours
+                args.extend(vec!["-option".into(), "".into()]);
+                args.extend(
+                    self.options
+                        .iter()
+                        .flat_map(|o| vec!["-option".into(), o.clone()]),
+                );
+            }
+            commands.push(("setxkbmap", args));
+        }
+
+        if let Some((delay, rate)) = self.repeat {
+            let args = vec![
+                "r".into(),
+                "rate".into(),
+                delay.to_string(),
+                rate.to_string(),
+            ];
+            commands.push(("xset", args));
+        }
+
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        matches;
+        args: (device: &str, expected: bool);
+
+        case: exact => ("SynPS/2 Synaptics TouchPad", true);
+        case: substring => ("TouchPad", true);
+        case: case_sensitive => ("touchpad", false);
+        case: mismatch => ("Logitech", false);
+
+        body: {
+            let dev = InputDevice {
+                id: 12,
+                name: "SynPS/2 Synaptics TouchPad".into(),
+            };
+
+            assert_eq!(PointerConfig::new(device).matches(&dev), expected);
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    test_cases! {
+        keyboard_commands;
+        args: (conf: KeyboardConfig, expected: Vec<(&'static str, Vec<String>)>);
+
+        case: empty => (KeyboardConfig::new(), vec![]);
+        case: layout => (
+            KeyboardConfig::new().layout("us,gr").variant(",polytonic"),
+            vec![("setxkbmap", args(&["-layout", "us,gr", "-variant", ",polytonic"]))]
+        );
+        case: options => (
+            KeyboardConfig::new().options(vec!["caps:escape", "compose:ralt"]),
+            vec![(
+                "setxkbmap",
+                args(&["-option", "", "-option", "caps:escape", "-option", "compose:ralt"])
+            )]
+        );
+        case: repeat => (
+            KeyboardConfig::new().repeat(250, 40),
+            vec![("xset", args(&["r", "rate", "250", "40"]))]
+        );
+
+        body: {
+            assert_eq!(conf.commands(), expected);
+        }
+    }
+}
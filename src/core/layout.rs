@@ -5,9 +5,9 @@
 //! when there is a need to re-layout a given screen and will always be given a full list of
 //! [Clients][1] that the [WindowManager][2] considers tiled. There are no restrictions as to
 //! whether or not windows may overlap or that they provide a total covering of the available
-//! screen space. Gaps and borders will be added to the [Regions][3] that are specified by layout
-//! functions by eating into the regions specified, so there is no need to account for this when
-//! writing a layout function.
+//! screen space. The [Regions][3] returned by layout functions are applied exactly as they are
+//! given, so layouts are responsible for leaving room for the gaps and borders described by the
+//! [Spacing][6] that they are passed.
 //!
 //! # Writing a simple layout function
 //!
@@ -17,6 +17,7 @@
 //! use penrose::core::{
 //!     client::Client,
 //!     data_types::{Change, Region, ResizeAction},
+//!     layout::Spacing,
 //!     xconnection::Xid,
 //! };
 //!
@@ -24,6 +25,7 @@
 //!     clients: &[&Client],
 //!     _focused: Option<Xid>,
 //!     monitor_region: &Region,
+//!     spacing: &Spacing,
 //!     _max_main: u32,
 //!     _ratio: f32,
 //! ) -> Vec<ResizeAction> {
@@ -31,7 +33,7 @@
 //!         .as_rows(clients.len() as u32)
 //!         .iter()
 //!         .zip(clients)
-//!         .map(|(r, c)| (c.id(), Some(*r)))
+//!         .map(|(r, c)| (c.id(), Some(spacing.pad(r))))
 //!         .collect()
 //! }
 //! ```
@@ -40,7 +42,8 @@
 //! (the total available space on the current screen) into evenly sized rows. (There are a number of
 //! utility methods on `Region` to aid in writing layout functions.) We then pair each client with
 //! `Some(region)` to indicate that this is where the client should be placed by the
-//! `WindowManager`. If we provide `None` for any of the clients, that client will then instead be
+//! `WindowManager`, using [Spacing::pad] to leave room for the client's border and the configured
+//! gap around it. If we provide `None` for any of the clients, that client will then instead be
 //! hidden.
 //!
 //! *Note, windows are positioned and mapped in order, meaning that later clients will overlap
@@ -54,6 +57,7 @@
 //! [3]: crate::core::data_types::Region
 //! [4]: crate::core::data_types::Region::as_rows
 //! [5]: crate::core::layout::side_stack
+//! [6]: crate::core::layout::Spacing
 use crate::core::{
     client::Client,
     data_types::{Change, Region, ResizeAction},
@@ -90,21 +94,49 @@ impl Default for LayoutConf {
     }
 }
 
+/// The border and gap that should be left around each client positioned by a layout function.
+///
+/// The gap will be zero for layouts that have [gapless][LayoutConf::gapless] set.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Spacing {
+    /// The width of the border drawn around each client in pixels
+    pub border_px: u32,
+    /// The gap to leave between each client and its neighbours (or the screen edge) in pixels
+    pub gap_px: u32,
+}
+
+impl Spacing {
+    /// Create a new Spacing
+    pub fn new(border_px: u32, gap_px: u32) -> Self {
+        Self { border_px, gap_px }
+    }
+
+    /// Shrink 'region' so that a client placed in it, along with its border, leaves a gap of
+    /// `gap_px` on each side.
+    pub fn pad(&self, region: &Region) -> Region {
+        let padding = 2 * (self.border_px + self.gap_px);
+        let (x, y, w, h) = region.values();
+        Region::new(x + self.gap_px, y + self.gap_px, w - padding, h - padding)
+    }
+}
+
 /// A function that can be used to position Clients on a Workspace.
 ///
 /// Will be called with the current client list, the active client ID (if there is one), the size
-/// of the screen that the workspace is shown on and the current values of n_main and ratio for
-/// this layout.
-pub type LayoutFunc = fn(&[&Client], Option<Xid>, &Region, u32, f32) -> Vec<ResizeAction>;
+/// of the screen that the workspace is shown on, the [Spacing] to leave around each client and
+/// the current values of n_main and ratio for this layout. The regions returned are used as the
+/// final client positions.
+pub type LayoutFunc = fn(&[&Client], Option<Xid>, &Region, &Spacing, u32, f32) -> Vec<ResizeAction>;
 
 /// Responsible for arranging Clients within a Workspace.
 ///
 /// A Layout is primarily a function that will be passed an array of Clients to apply resize actions
 /// to. Only clients that should be tiled for the current monitor will be passed so no checks are
 /// required to see if each client should be handled. The region passed to the layout function
-/// represents the current screen dimensions that can be utilised and gaps/borders will be added to
-/// each client by the WindowManager itself so there is no need to handle that in the layouts
-/// themselves.
+/// represents the current screen dimensions that can be utilised and the regions it returns are
+/// applied verbatim, so layouts need to leave room for the gaps and borders described by the
+/// [Spacing] they are given.
 ///
 /// Layouts are expected to have a "main area" that holds the clients with primary focus and any
 /// number of secondary areas for the remaining clients to be tiled.
@@ -147,7 +179,14 @@ impl fmt::Debug for Layout {
 }
 
 /// A no-op floating layout that simply satisfies the type required for Layout
-pub fn floating(_: &[&Client], _: Option<Xid>, _: &Region, _: u32, _: f32) -> Vec<ResizeAction> {
+pub fn floating(
+    _: &[&Client],
+    _: Option<Xid>,
+    _: &Region,
+    _: &Spacing,
+    _: u32,
+    _: f32,
+) -> Vec<ResizeAction> {
     vec![]
 }
 
@@ -198,8 +237,30 @@ impl Layout {
         clients: &[&Client],
         focused: Option<Xid>,
         r: &Region,
+        spacing: &Spacing,
     ) -> Vec<ResizeAction> {
-        (self.f.expect("missing layout function"))(clients, focused, r, self.max_main, self.ratio)
+        let spacing = if self.conf.gapless {
+            Spacing::new(spacing.border_px, 0)
+        } else {
+            *spacing
+        };
+        match self.f {
+            Some(f) => f(clients, focused, r, &spacing, self.max_main, self.ratio),
+            None => {
+                warn!(symbol = %self.symbol, "layout function has not been restored: not arranging clients");
+                vec![]
+            }
+        }
+    }
+
+    /// The current maximum number of clients in the main area
+    pub fn max_main(&self) -> u32 {
+        self.max_main
+    }
+
+    /// The current proportion of the screen taken up by the main area
+    pub fn ratio(&self) -> f32 {
+        self.ratio
     }
 
     /// Increase/decrease the number of clients in the main area by 1
@@ -260,6 +321,7 @@ pub(crate) fn mock_layout(
     clients: &[&Client],
     _: Option<Xid>,
     region: &Region,
+    spacing: &Spacing,
     _: u32,
     _: f32,
 ) -> Vec<ResizeAction> {
@@ -269,90 +331,112 @@ pub(crate) fn mock_layout(
         .map(|(i, c)| {
             let (x, y, w, h) = region.values();
             let _k = i as u32;
-            (c.id(), Some(Region::new(x + _k, y + _k, w - _k, h - _k)))
+            let r = Region::new(x + _k, y + _k, w - _k, h - _k);
+            (c.id(), Some(spacing.pad(&r)))
         })
         .collect()
 }
 
 /// A simple layout that places the main region on the left and tiles remaining
 /// windows in a single column to the right.
+///
+/// Clients sharing a column are sized by their [weight][Client::weight].
 pub fn side_stack(
     clients: &[&Client],
     _: Option<Xid>,
     monitor_region: &Region,
+    spacing: &Spacing,
     max_main: u32,
     ratio: f32,
 ) -> Vec<ResizeAction> {
     let n = clients.len() as u32;
+    let weights: Vec<u32> = clients.iter().map(|c| c.weight()).collect();
 
     if n <= max_main || max_main == 0 {
         return monitor_region
-            .as_rows(n)
+            .as_weighted_rows(&weights)
             .iter()
             .zip(clients)
-            .map(|(r, c)| (c.id(), Some(*r)))
+            .map(|(r, c)| (c.id(), Some(spacing.pad(r))))
             .collect();
     }
 
-    let split = ((monitor_region.w as f32) * ratio) as u32;
-    let (main, stack) = monitor_region.split_at_width(split).unwrap();
+    let split = (((monitor_region.w as f32) * ratio) as u32).min(monitor_region.w);
+    let (main, stack) = match monitor_region.split_at_width(split) {
+        Ok(regions) => regions,
+        Err(_) => return vec![],
+    };
 
-    main.as_rows(max_main)
+    let (main_weights, stack_weights) = weights.split_at(max_main as usize);
+    main.as_weighted_rows(main_weights)
         .into_iter()
-        .chain(stack.as_rows(n.saturating_sub(max_main)))
+        .chain(stack.as_weighted_rows(stack_weights))
         .zip(clients)
-        .map(|(r, c)| (c.id(), Some(r)))
+        .map(|(r, c)| (c.id(), Some(spacing.pad(&r))))
         .collect()
 }
 
 /// A simple layout that places the main region at the top of the screen and tiles
 /// remaining windows in a single row underneath.
+///
+/// Clients sharing a row are sized by their [weight][Client::weight].
 pub fn bottom_stack(
     clients: &[&Client],
     _: Option<Xid>,
     monitor_region: &Region,
+    spacing: &Spacing,
     max_main: u32,
     ratio: f32,
 ) -> Vec<ResizeAction> {
     let n = clients.len() as u32;
+    let weights: Vec<u32> = clients.iter().map(|c| c.weight()).collect();
 
     if n <= max_main || max_main == 0 {
         return monitor_region
-            .as_columns(n)
+            .as_weighted_columns(&weights)
             .iter()
             .zip(clients)
-            .map(|(r, c)| (c.id(), Some(*r)))
+            .map(|(r, c)| (c.id(), Some(spacing.pad(r))))
             .collect();
     }
 
-    let split = ((monitor_region.h as f32) * ratio) as u32;
-    let (main, stack) = monitor_region.split_at_height(split).unwrap();
+    let split = (((monitor_region.h as f32) * ratio) as u32).min(monitor_region.h);
+    let (main, stack) = match monitor_region.split_at_height(split) {
+        Ok(regions) => regions,
+        Err(_) => return vec![],
+    };
 
-    main.as_columns(max_main)
+    let (main_weights, stack_weights) = weights.split_at(max_main as usize);
+    main.as_weighted_columns(main_weights)
         .into_iter()
-        .chain(stack.as_columns(n.saturating_sub(max_main)))
+        .chain(stack.as_weighted_columns(stack_weights))
         .zip(clients)
-        .map(|(r, c)| (c.id(), Some(r)))
+        .map(|(r, c)| (c.id(), Some(spacing.pad(&r))))
         .collect()
 }
 
-/// A simple monolve layout that places uses the maximum available space for the focused client and
+/// A simple monocle layout that uses the maximum available space for the focused client and
 /// unmaps all other windows.
+///
+/// The number of clients hidden by this layout is available through
+/// [WindowManager::hidden_client_count][crate::core::manager::WindowManager::hidden_client_count]
+/// and is shown alongside the layout symbol by the
+/// [CurrentLayout][crate::draw::widget::CurrentLayout] status bar widget.
 pub fn monocle(
     clients: &[&Client],
     focused: Option<Xid>,
     monitor_region: &Region,
+    spacing: &Spacing,
     _: u32,
     _: f32,
 ) -> Vec<ResizeAction> {
     if let Some(fid) = focused {
-        let (mx, my, mw, mh) = monitor_region.values();
         clients
             .iter()
             .map(|c| {
                 let cid = c.id();
                 if cid == fid {
-                    (cid, Some(Region::new(mx, my, mw, mh)))
+                    (cid, Some(spacing.pad(monitor_region)))
                 } else {
                     (cid, None)
                 }
@@ -362,3 +446,53 @@ pub fn monocle(
         Vec::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pad_region_centered() {
+        let r = Region::new(0, 0, 200, 100);
+        assert_eq!(Spacing::new(3, 10).pad(&r), Region::new(10, 10, 174, 74));
+        assert_eq!(Spacing::new(3, 0).pad(&r), Region::new(0, 0, 194, 94));
+    }
+
+    #[test]
+    fn gapless_layouts_are_given_no_gap() {
+        let client = Client::detached(1, 0);
+        let r = Region::new(0, 0, 200, 100);
+        let spacing = Spacing::new(3, 10);
+        let conf = LayoutConf {
+            gapless: true,
+            ..Default::default()
+        };
+
+        let gapless = Layout::new("t", conf, monocle, 1, 0.6);
+        let actions = gapless.arrange(&[&client], Some(1), &r, &spacing);
+        assert_eq!(actions, vec![(1, Some(Region::new(0, 0, 194, 94)))]);
+
+        let gapped = Layout::new("t", LayoutConf::default(), monocle, 1, 0.6);
+        let actions = gapped.arrange(&[&client], Some(1), &r, &spacing);
+        assert_eq!(actions, vec![(1, Some(Region::new(10, 10, 174, 74)))]);
+    }
+
+    #[test]
+    fn side_stack_sizes_stacked_clients_by_weight() {
+        let mut clients: Vec<Client> = (1..=3).map(|id| Client::detached(id, 0)).collect();
+        clients[2].set_weight(300);
+        let clients: Vec<&Client> = clients.iter().collect();
+        let r = Region::new(0, 0, 200, 400);
+
+        let actions = side_stack(&clients, None, &r, &Spacing::default(), 1, 0.5);
+
+        assert_eq!(
+            actions,
+            vec![
+                (1, Some(Region::new(0, 0, 100, 400))),
+                (2, Some(Region::new(100, 0, 100, 100))),
+                (3, Some(Region::new(100, 100, 100, 300))),
+            ]
+        );
+    }
+}
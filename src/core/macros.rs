@@ -2,7 +2,9 @@
 
 /// kick off an external program as part of a key/mouse binding.
 ///
-/// NOTE: this explicitly redirects stderr to /dev/null
+/// The program is run using [WindowManager::spawn][crate::core::manager::WindowManager::spawn] so
+/// it is detached from penrose, has its stdout and stderr redirected to /dev/null and is told which
+/// workspace and screen were active when it was started.
 ///
 /// ```no_run
 /// # #[macro_use] extern crate penrose;
@@ -15,9 +17,8 @@
 #[macro_export]
 macro_rules! run_external {
     ($cmd:tt) => {{
-        Box::new(move |_: &mut $crate::core::manager::WindowManager<_>| {
-            $crate::core::helpers::spawn($cmd)
-        }) as $crate::core::bindings::KeyEventHandler<_>
+        Box::new(move |wm: &mut $crate::core::manager::WindowManager<_>| wm.spawn($cmd))
+            as $crate::core::bindings::KeyEventHandler<_>
     }};
 }
 
@@ -46,15 +47,17 @@ macro_rules! run_internal {
     };
 }
 
-/// Helper for spawning external processes and ignoring the output
+/// Helper for spawning detached external processes and ignoring the output
+///
+/// See [process][crate::core::process] for details of how the process is run.
 #[macro_export]
 macro_rules! spawn {
     { $cmd:expr } => {
-        $crate::core::helpers::spawn($cmd)
+        $crate::core::process::spawn($cmd)
     };
 
     { $cmd:expr, $($arg:expr),+ } => {
-        $crate::core::helpers::spawn_with_args($cmd, &[$($arg),+])
+        $crate::core::process::spawn_with_args($cmd, &[$($arg),+])
     };
 }
 
@@ -191,17 +194,62 @@ macro_rules! map {
 /// the [WindowManager][2] method can be passed by reference using `REF` or by value using `VAL`.
 /// Any additional arguments can be passed explicitly if they are required by the method.
 ///
+/// # Problems with bindings
+///
+/// Bindings with an unknown key name cause a panic. Bindings that are given more than once, or
+/// that resolve to the same key as an earlier binding, replace the earlier binding and are logged
+/// as a warning. Use [gen_keybindings_with_report] to handle these yourself.
+///
 /// [1]: crate::core::bindings::KeyEventHandler
 /// [2]: crate::core::manager::WindowManager
 #[macro_export]
 macro_rules! gen_keybindings {
+    { $($tokens:tt)* } => {
+        {
+            let (map, report) = gen_keybindings_with_report! { $($tokens)* };
+            report.log_issues();
+            if let Some(binding) = report.unknown_keys().first() {
+                panic!("invalid key binding: {}", binding);
+            }
+            map
+        }
+    };
+}
+
+/// Generate user keybindings in the same way as [gen_keybindings] along with a
+/// [KeyBindingReport][1] describing the problems found with them.
+///
+/// Bindings with unknown key names are dropped rather than causing a panic, and bindings that
+/// are replaced by later ones are listed in the report rather than being logged. The report can
+/// be passed to [set_key_binding_report][2] so that it is included in state snapshots.
+///
+/// ```no_run
+/// # #[macro_use] extern crate penrose;
+/// # use penrose::__test_helpers::*;
+/// # fn example() -> TestKeyBindings {
+/// let (key_bindings, report) = gen_keybindings_with_report! {
+///     "M-Return" => run_external!("alacritty");
+///     "M-S-q" => run_internal!(kill_client);
+/// };
+///
+/// if !report.issues().is_empty() {
+///     eprintln!("{}", report);
+/// }
+/// # key_bindings }
+/// ```
+///
+/// [1]: crate::core::bindings::KeyBindingReport
+/// [2]: crate::core::manager::WindowManager::set_key_binding_report
+#[macro_export]
+macro_rules! gen_keybindings_with_report {
     { $($tokens:tt)* } => {
         {
             let mut map = ::std::collections::HashMap::new();
+            let mut report = $crate::core::bindings::KeyBindingReport::new();
             let codes = $crate::core::helpers::keycodes_from_xmodmap();
             let parse = $crate::xcb::helpers::parse_key_binding;
-            __private!(@parsekey map, codes, parse, [], [], $($tokens)*);
-            map
+            __private!(@parsekey map, report, codes, parse, [], [], $($tokens)*);
+            (map, report)
         }
     };
 }
@@ -237,6 +285,93 @@ macro_rules! gen_mousebindings {
     };
 }
 
+/// Make creating all of the touchpad gesture bindings less verbose
+///
+/// Each binding is the number of fingers and [SwipeDirection][1] of the swipe followed by the
+/// [GestureEventHandler][2] to run.
+///
+/// [1]: crate::core::bindings::SwipeDirection
+/// [2]: crate::core::bindings::GestureEventHandler
+#[macro_export]
+macro_rules! gen_gesturebindings {
+    {
+        $($fingers:literal $direction:ident => $action:expr),+
+    } => {
+        {
+            // HashMap<Gesture, GestureEventHandler>
+            let mut _map = ::std::collections::HashMap::new();
+
+            $(
+                let gesture = $crate::core::bindings::Gesture::new(
+                    $fingers,
+                    $crate::core::bindings::SwipeDirection::$direction
+                );
+
+                _map.insert(
+                    gesture,
+                    Box::new($action) as $crate::core::bindings::GestureEventHandler<_>
+                );
+            )+
+
+            _map
+        }
+    };
+}
+
+/// Make creating all of the pointer bindings less verbose
+///
+/// Each binding is a [PointerTarget][1] (either `Desktop` or `Edge(<HotCorner>)`) followed by the
+/// [MouseButton][2] and any modifiers that need to be held, in the same way as
+/// [gen_mousebindings], and then the [KeyEventHandler][3] to run.
+///
+/// ```no_run
+/// # #[macro_use] extern crate penrose;
+/// # use penrose::__test_helpers::*;
+/// # fn example<X: XConn + 'static>(mut wm: WindowManager<X>) -> penrose::Result<()> {
+/// wm.set_pointer_bindings(gen_pointerbindings! {
+///     Desktop ScrollUp + [] => run_internal!(cycle_workspace, Backward),
+///     Desktop ScrollDown + [] => run_internal!(cycle_workspace, Forward),
+///     Edge(Left) Middle + [Meta] => run_internal!(toggle_workspace)
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [1]: crate::core::bindings::PointerTarget
+/// [2]: crate::core::bindings::MouseButton
+/// [3]: crate::core::bindings::KeyEventHandler
+#[macro_export]
+macro_rules! gen_pointerbindings {
+    {
+        $($target:ident $(($edge:ident))? $button:ident + [$($modifier:ident),*] => $action:expr),+
+    } => {
+        {
+            // HashMap<(PointerTarget, MouseState), KeyEventHandler>
+            let mut _map = ::std::collections::HashMap::new();
+
+            $(
+                #[allow(unused_mut)]
+                let mut modifiers = Vec::new();
+                $(modifiers.push($crate::core::bindings::ModifierKey::$modifier);)*
+
+                let state = $crate::core::bindings::MouseState::new(
+                    $crate::core::bindings::MouseButton::$button,
+                    modifiers
+                );
+
+                let target = $crate::core::bindings::PointerTarget::$target
+                    $(($crate::core::bindings::HotCorner::$edge))?;
+                _map.insert(
+                    (target, state),
+                    Box::new($action) as $crate::core::bindings::KeyEventHandler<_>
+                );
+            )+
+
+            _map
+        }
+    };
+}
+
 /// Quickly create a simple string error
 #[macro_export]
 macro_rules! perror {
@@ -459,7 +594,7 @@ macro_rules! __private {
      *  @parsekey :: handle each of the valid cases in an invocation of gen_keybindings
      */
 
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $report:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
         map: { $($str:expr),+ } to $to:expr => {
             $( $binding:expr => $method:ident ( $($params:tt)* ); )+
@@ -471,20 +606,19 @@ macro_rules! __private {
             $(
                 for (name, arg) in keynames.iter().zip($to.into_iter()) {
                     let binding = format!($binding, name);
-                    match $parse(binding.clone(), &$codes) {
-                        None => panic!("invalid key binding: {}", binding),
-                        Some(key_code) => $map.insert(
+                    if let Some(key_code) = $report.record(binding.clone(), $parse(binding, &$codes)) {
+                        $map.insert(
                             key_code,
                             run_internal!(
                                 $method,
                                 __private!(@parsemapparams arg; []; $($params,)*)
                             )
-                        ),
-                    };
+                        );
+                    }
                 }
             )+
 
-            __private!(@parsekey $map, $codes, $parse,
+            __private!(@parsekey $map, $report, $codes, $parse,
                 [ $($patt,)* ], [ $(($($template),+; $($name),+),)* ($($binding),+; $($str),+) ],
                 $($tail)*
             );
@@ -492,23 +626,22 @@ macro_rules! __private {
     };
 
     // parse a single simple key binding (validated if $validate is true)
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $report:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
         $binding:expr => $action:expr;
         $($tail:tt)*
     } => {
-        match $parse($binding.to_string(), &$codes) {
-            None => panic!("invalid key binding: {}", $binding),
-            Some(key_code) => $map.insert(key_code, $action),
-        };
-        __private!(@parsekey $map, $codes, $parse,
+        if let Some(key_code) = $report.record($binding, $parse($binding.to_string(), &$codes)) {
+            $map.insert(key_code, $action);
+        }
+        __private!(@parsekey $map, $report, $codes, $parse,
             [ $binding, $($patt,)* ], [ $(($($template),+; $($name),+)),* ],
             $($tail)*
         );
     };
 
     // TODO: remove this target in 0.2.2
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $report:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
         $(refmap)? $(map)? [ $from:expr ] in { $($binding:expr => $method:ident [ $to:expr ];)+ };
         $($tail:tt)*
@@ -521,7 +654,7 @@ macro_rules! __private {
     };
 
     // base case (should be out of tokens)
-    {   @parsekey $map:expr, $codes:expr, $parse:expr,
+    {   @parsekey $map:expr, $report:expr, $codes:expr, $parse:expr,
         [ $($patt:expr,)* ], [ $(($($template:expr),+; $($name:expr),+)),* ],
         $($tail:tt)*
     } => {
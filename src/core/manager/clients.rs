@@ -1,17 +1,13 @@
 //! State and management of clients being managed by Penrose.
 use crate::{
     core::{
-        client::Client,
-        data_types::Region,
+        client::{Client, InputModel, DEFAULT_CLIENT_WEIGHT},
+        data_types::{Change, Region, RelativePosition},
         hooks::HookName,
-        layout::LayoutConf,
-        manager::{event::EventAction, util::pad_region},
+        manager::event::EventAction,
         ring::Selector,
         workspace::ArrangeActions,
-        xconnection::{
-            Atom, ClientMessageKind, Prop, XClientConfig, XClientHandler, XClientProperties,
-            XEventHandler, XState, Xid,
-        },
+        xconnection::{WmHintsFlags, XClientConfig, XClientHandler, XClientProperties, XConn, Xid},
     },
     draw::Color,
     Result,
@@ -26,15 +22,86 @@ pub(super) struct Clients {
     focused_client_id: Option<Xid>,
     focused_border: Color,
     unfocused_border: Color,
+    #[cfg_attr(feature = "serde", serde(default = "default_urgent_border"))]
+    urgent_border: Color,
+    // the color and width of the inner line of two-tone borders if they are enabled
+    inner_border: Option<(Color, u32)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    urgent_clients: Vec<Xid>, // ordered from least to most recently urgent
+    #[cfg_attr(feature = "serde", serde(default))]
+    border_overrides: HashMap<Xid, Color>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    focused_border_overrides: HashMap<Xid, Color>,
+    // clients that were made fullscreen automatically along with their previous geometry
+    #[cfg_attr(feature = "serde", serde(default))]
+    auto_fullscreen: HashMap<Xid, Option<Region>>,
+    // clients that are maximized along at least one axis along with their previous geometry
+    #[cfg_attr(feature = "serde", serde(default))]
+    unmaximized: HashMap<Xid, Region>,
+    // the number of UnmapNotify events we are expecting for each client as a result of our
+    // own unmap requests (hiding workspaces etc)
+    #[cfg_attr(feature = "serde", serde(default))]
+    expected_unmaps: HashMap<Xid, usize>,
+    // clients ordered from most to least recently focused
+    #[cfg_attr(feature = "serde", serde(default))]
+    focus_history: Vec<Xid>,
+    // the regions assigned to tiled clients the last time that their layout was applied
+    #[cfg_attr(feature = "serde", serde(default))]
+    tiled_regions: HashMap<Xid, Region>,
+    // terminals that are hidden while a client they started takes their place, keyed by the
+    // id of that client
+    #[cfg_attr(feature = "serde", serde(default))]
+    swallowed: HashMap<Xid, Client>,
+    // work for clients on hidden workspaces that is held back until they are next shown
+    #[cfg_attr(feature = "serde", serde(default))]
+    deferred: HashMap<Xid, Deferred>,
+}
+
+// Property reads and configure requests for a client that is not currently visible. Sessions
+// with dozens of windows spread over hidden workspaces would otherwise pay for all of them up
+// front rather than when (and if) the client is actually shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(super) struct Deferred {
+    // the client was mapped while hidden and has not yet been placed
+    pub setup: bool,
+    // a rule asked for the client to be made fullscreen when it was mapped
+    pub fullscreen: bool,
+    // the client has changed its name
+    pub name: bool,
+    // the most recent position requested by a floating client
+    pub region: Option<Region>,
+}
+
+// Matches the default in Config so that state serialized before urgency was tracked still loads
+#[cfg(feature = "serde")]
+fn default_urgent_border() -> Color {
+    Color::from(0xd79921ff)
 }
 
 impl Clients {
-    pub fn new(focused_border: impl Into<Color>, unfocused_border: impl Into<Color>) -> Self {
+    pub fn new(
+        focused_border: impl Into<Color>,
+        unfocused_border: impl Into<Color>,
+        urgent_border: impl Into<Color>,
+    ) -> Self {
         Self {
             inner: HashMap::new(),
             focused_client_id: None,
             focused_border: focused_border.into(),
             unfocused_border: unfocused_border.into(),
+            urgent_border: urgent_border.into(),
+            inner_border: None,
+            urgent_clients: Vec::new(),
+            border_overrides: HashMap::new(),
+            focused_border_overrides: HashMap::new(),
+            auto_fullscreen: HashMap::new(),
+            unmaximized: HashMap::new(),
+            expected_unmaps: HashMap::new(),
+            focus_history: Vec::new(),
+            tiled_regions: HashMap::new(),
+            swallowed: HashMap::new(),
+            deferred: HashMap::new(),
         }
     }
 
@@ -105,6 +172,8 @@ impl Clients {
     {
         let prev = self.focused_client_id;
         self.focused_client_id = Some(id);
+        self.focus_history.retain(|&f| f != id);
+        self.focus_history.insert(0, id);
 
         if let Some(prev_id) = prev {
             if id != prev_id {
@@ -115,7 +184,95 @@ impl Clients {
         prev
     }
 
-    #[allow(dead_code)]
+    // The nearest of 'candidates' to 'id' in the given direction, based on where each of them was
+    // placed by the last layout that was applied. Floating clients are never included.
+    pub fn tiled_neighbour(
+        &self,
+        id: Xid,
+        candidates: &[Xid],
+        position: RelativePosition,
+    ) -> Option<Xid> {
+        let is_tiled = |id: &Xid| matches!(self.get(*id), Some(c) if !c.floating);
+        let region = self.tiled_regions.get(&id).filter(|_| is_tiled(&id))?;
+        let (ids, regions): (Vec<Xid>, Vec<Region>) = candidates
+            .iter()
+            .filter(|&&c| c != id && is_tiled(&c))
+            .flat_map(|c| self.tiled_regions.get(c).map(|r| (*c, *r)))
+            .unzip();
+
+        region
+            .nearest_in_direction(&regions, position)
+            .map(|i| ids[i])
+    }
+
+    // Set the weight of 'id' so that it grows or shrinks by roughly 'px' at the expense of the
+    // tiled clients in 'candidates' that share its column (or failing that, its row) in the last
+    // applied layout. Returns false if there were no clients to share space with.
+    pub fn resize_tiled(&mut self, id: Xid, candidates: &[Xid], change: Change, px: u32) -> bool {
+        let is_tiled = |id: &Xid| matches!(self.get(*id), Some(c) if !c.floating);
+        let r = match self.tiled_regions.get(&id).filter(|_| is_tiled(&id)) {
+            Some(r) => *r,
+            None => return false,
+        };
+        let others: Vec<(Xid, Region)> = candidates
+            .iter()
+            .filter(|&&c| c != id && is_tiled(&c))
+            .flat_map(|c| self.tiled_regions.get(c).map(|r| (*c, *r)))
+            .collect();
+
+        let column: Vec<(Xid, u32)> = others
+            .iter()
+            .filter(|(_, o)| o.x == r.x && o.w == r.w)
+            .map(|(c, o)| (*c, o.h))
+            .collect();
+        let (siblings, size) = if !column.is_empty() {
+            (column, r.h)
+        } else {
+            let row: Vec<(Xid, u32)> = others
+                .iter()
+                .filter(|(_, o)| o.y == r.y && o.h == r.h)
+                .map(|(c, o)| (*c, o.w))
+                .collect();
+            (row, r.w)
+        };
+        if siblings.is_empty() {
+            return false;
+        }
+
+        let total = size + siblings.iter().map(|(_, s)| s).sum::<u32>();
+        let others_weight = siblings
+            .iter()
+            .flat_map(|(c, _)| self.get(*c).map(|c| c.weight()))
+            .sum();
+        let new_size = match change {
+            Change::More => size.saturating_add(px),
+            Change::Less => size.saturating_sub(px),
+        };
+        let weight = weight_for_size(new_size, total, others_weight, siblings.len() as u32);
+        self.modify(id, |c| c.set_weight(weight));
+
+        true
+    }
+
+    pub fn focus_history(&self) -> &[Xid] {
+        &self.focus_history
+    }
+
+    // Replace the focus history with a previous snapshot, keeping the currently focused client
+    // as the most recent entry
+    pub fn restore_focus_history(&mut self, history: Vec<Xid>) {
+        let inner = &self.inner;
+        self.focus_history = history
+            .into_iter()
+            .filter(|id| inner.contains_key(id))
+            .collect();
+
+        if let Some(id) = self.focused_client_id {
+            self.focus_history.retain(|&f| f != id);
+            self.focus_history.insert(0, id);
+        }
+    }
+
     pub fn clear_focused(&mut self) {
         self.focused_client_id = None
     }
@@ -128,10 +285,121 @@ impl Clients {
         if self.focused_client_id == Some(id) {
             self.focused_client_id = None;
         }
+        self.urgent_clients.retain(|&u| u != id);
+        self.border_overrides.remove(&id);
+        self.focused_border_overrides.remove(&id);
+        self.auto_fullscreen.remove(&id);
+        self.unmaximized.remove(&id);
+        self.expected_unmaps.remove(&id);
+        self.focus_history.retain(|&f| f != id);
+        self.tiled_regions.remove(&id);
+        self.swallowed.retain(|_, t| t.id != id);
+        self.deferred.remove(&id);
 
         self.inner.remove(&id)
     }
 
+    // Stop tracking 'terminal' as a visible client and hold on to it until 'child' is removed
+    pub fn swallow(&mut self, terminal: Xid, child: Xid) {
+        if let Some(t) = self.remove(terminal) {
+            self.swallowed.insert(child, t);
+        }
+    }
+
+    // The terminal swallowed by 'child' (if there is one) so that it can be restored
+    pub fn take_swallowed(&mut self, child: Xid) -> Option<Client> {
+        self.swallowed.remove(&child)
+    }
+
+    pub fn is_swallowed(&self, id: Xid) -> bool {
+        self.swallowed.values().any(|t| t.id == id)
+    }
+
+    // Hold back work for a known client until it is next shown
+    pub fn defer(&mut self, id: Xid, f: impl FnOnce(&mut Deferred)) {
+        if self.inner.contains_key(&id) {
+            f(self.deferred.entry(id).or_default());
+        }
+    }
+
+    pub fn take_deferred(&mut self, id: Xid) -> Option<Deferred> {
+        self.deferred.remove(&id)
+    }
+
+    // Record that a client was made fullscreen automatically so that it can be restored later
+    pub fn mark_auto_fullscreen(&mut self, id: Xid, prev: Option<Region>) {
+        self.auto_fullscreen.insert(id, prev);
+    }
+
+    pub fn is_auto_fullscreen(&self, id: Xid) -> bool {
+        self.auto_fullscreen.contains_key(&id)
+    }
+
+    // The geometry of an automatically fullscreened client from before it was made fullscreen
+    pub fn take_auto_fullscreen(&mut self, id: Xid) -> Option<Region> {
+        self.auto_fullscreen.remove(&id).flatten()
+    }
+
+    // Record the geometry of a client from before it was maximized, keeping the original if it
+    // is already maximized along the other axis
+    pub fn mark_unmaximized(&mut self, id: Xid, prev: Region) -> Region {
+        *self.unmaximized.entry(id).or_insert(prev)
+    }
+
+    // The geometry of a client from before it was maximized along either axis
+    pub fn take_unmaximized(&mut self, id: Xid) -> Option<Region> {
+        self.unmaximized.remove(&id)
+    }
+
+    // Use the given border color in place of the default unfocused border for a client
+    pub fn set_unfocused_border(&mut self, id: Xid, color: Color) {
+        self.border_overrides.insert(id, color);
+    }
+
+    fn unfocused_border_for(&self, id: Xid) -> Color {
+        self.border_overrides
+            .get(&id)
+            .copied()
+            .unwrap_or(self.unfocused_border)
+    }
+
+    // Use the given border color in place of the default focused border for a client
+    pub fn set_focused_border(&mut self, id: Xid, color: Color) {
+        self.focused_border_overrides.insert(id, color);
+    }
+
+    // Draw borders using two colors, with an inner line 'px' wide alongside each client. Borders
+    // are solid if 'px' is 0.
+    pub fn set_inner_border(&mut self, color: impl Into<Color>, px: u32) {
+        self.inner_border = if px > 0 {
+            Some((color.into(), px))
+        } else {
+            None
+        };
+    }
+
+    // Two-tone borders need to be redrawn whenever a client changes size
+    pub fn has_inner_border(&self) -> bool {
+        self.inner_border.is_some()
+    }
+
+    fn set_border_color<X>(&self, id: Xid, color: Color, conn: &X) -> Result<()>
+    where
+        X: XClientConfig,
+    {
+        match self.inner_border {
+            Some((inner, px)) => Ok(conn.set_client_border_colors(id, color, inner, px)?),
+            None => Ok(conn.set_client_border_color(id, color)?),
+        }
+    }
+
+    fn focused_border_for(&self, id: Xid) -> Color {
+        self.focused_border_overrides
+            .get(&id)
+            .copied()
+            .unwrap_or(self.focused_border)
+    }
+
     pub fn get(&self, id: Xid) -> Option<&Client> {
         self.inner.get(&id)
     }
@@ -140,8 +408,38 @@ impl Clients {
         self.inner.get_mut(&id)
     }
 
+    // Weights only make sense relative to the other clients on a workspace so they are reset
+    // when a client moves to a different one
     pub fn set_client_workspace(&mut self, id: Xid, wix: usize) {
-        self.inner.entry(id).and_modify(|c| c.set_workspace(wix));
+        self.inner.entry(id).and_modify(|c| {
+            c.set_workspace(wix);
+            c.set_weight(DEFAULT_CLIENT_WEIGHT);
+        });
+    }
+
+    // Update the workspace index of every client after workspaces have been added or removed,
+    // returning the clients that were moved along with their new workspace index.
+    pub fn reindex_workspaces(&mut self, f: impl Fn(usize) -> usize) -> Vec<(Xid, usize)> {
+        self.inner
+            .values_mut()
+            .filter_map(|c| {
+                let tags: Vec<usize> = c.extra_tags.iter().map(|&t| f(t)).collect();
+                c.extra_tags.clear();
+                for t in tags {
+                    if !c.extra_tags.contains(&t) {
+                        c.extra_tags.push(t);
+                    }
+                }
+
+                let wix = f(c.workspace());
+                if wix != c.workspace() {
+                    c.set_workspace(wix);
+                    Some((c.id(), wix))
+                } else {
+                    None
+                }
+            })
+            .collect()
     }
 
     pub fn map_if_needed<X>(&mut self, id: Xid, conn: &X) -> Result<()>
@@ -155,9 +453,33 @@ impl Clients {
     where
         X: XClientHandler,
     {
+        if matches!(self.inner.get(&id), Some(c) if c.mapped) {
+            self.expect_unmap(id);
+        }
         Ok(conn.unmap_client_if_needed(self.inner.get_mut(&id))?)
     }
 
+    // Record that we are about to unmap this client ourselves so that the resulting
+    // UnmapNotify is not mistaken for the client withdrawing itself
+    pub fn expect_unmap(&mut self, id: Xid) {
+        *self.expected_unmaps.entry(id).or_insert(0) += 1;
+    }
+
+    // Returns true if this unmap was one we requested, consuming the expectation
+    pub fn take_expected_unmap(&mut self, id: Xid) -> bool {
+        match self.expected_unmaps.get_mut(&id) {
+            Some(n) if *n > 1 => {
+                *n -= 1;
+                true
+            }
+            Some(_) => {
+                self.expected_unmaps.remove(&id);
+                true
+            }
+            None => false,
+        }
+    }
+
     // The index of the [Workspace] holding the requested X window ID. This can return None if
     // the id does not map to a [WindowManager] managed [Client] which happens if the window
     // is unmanaged (e.g. a dock or toolbar) or if a client [Hook] has requested ownership
@@ -166,8 +488,24 @@ impl Clients {
         self.inner.get(&id).map(|c| c.workspace())
     }
 
+    // Minimized clients are not included as they are not shown until they are restored
     pub fn clients_for_workspace(&self, wix: usize) -> Vec<&Client> {
-        self.matching_clients(&Selector::Condition(&|c: &Client| c.workspace == wix))
+        self.matching_clients(&Selector::Condition(&|c: &Client| {
+            c.workspace == wix && !c.minimized
+        }))
+    }
+
+    // The clients that have declared themselves to be transient for 'id', in ID order
+    pub fn transients_for(&self, id: Xid) -> Vec<Xid> {
+        let mut ids: Vec<Xid> = self
+            .inner
+            .values()
+            .filter(|c| c.transient_for == Some(id) && c.id != id)
+            .map(|c| c.id)
+            .collect();
+        ids.sort_unstable();
+
+        ids
     }
 
     pub fn all_known_ids(&self) -> Vec<Xid> {
@@ -178,29 +516,28 @@ impl Clients {
         self.inner.entry(id).and_modify(f);
     }
 
-    // Set X focus to the requested client if it accepts focus, otherwise send a
-    // 'take focus' event for the client to process
-    pub fn set_x_focus<X>(&self, id: Xid, accepts_focus: bool, conn: &X) -> Result<()>
+    // Focus the requested client following its ICCCM input model: X focus is set if the client
+    // accepts input and a 'take focus' event is sent if the client supports WM_TAKE_FOCUS. The
+    // client is marked as the active window regardless of how it handles input.
+    pub fn set_x_focus<X>(&self, id: Xid, model: InputModel, conn: &X) -> Result<()>
     where
-        X: XState + XEventHandler + XClientConfig + XClientHandler + XClientProperties,
+        X: XConn,
     {
-        trace!(id, accepts_focus, "setting focus");
-        if accepts_focus {
+        trace!(id, ?model, "setting focus");
+        if model.accepts_input() {
             if let Err(e) = conn.focus_client(id) {
                 warn!("unable to focus client {}: {}", id, e);
             }
-            conn.change_prop(
-                conn.root(),
-                Atom::NetActiveWindow.as_ref(),
-                Prop::Window(vec![id]),
-            )?;
-            let fb = self.focused_border;
-            if let Err(e) = conn.set_client_border_color(id, fb) {
-                warn!("unable to set client border color for {}: {}", id, e);
-            }
-        } else {
-            let msg = ClientMessageKind::TakeFocus(id).as_message(conn)?;
-            conn.send_client_event(msg)?;
+        }
+
+        if model.takes_focus() {
+            conn.send_take_focus(id)?;
+        }
+
+        conn.set_active_client(Some(id))?;
+        let fb = self.focused_border_for(id);
+        if let Err(e) = self.set_border_color(id, fb, conn) {
+            warn!("unable to set client border color for {}: {}", id, e);
         }
 
         // TODO: should this be running the FocusChange hook?
@@ -209,14 +546,14 @@ impl Clients {
 
     pub fn focus_in<X>(&self, id: Xid, conn: &X) -> Result<()>
     where
-        X: XState + XEventHandler + XClientConfig + XClientHandler + XClientProperties,
+        X: XConn,
     {
-        let accepts_focus = match self.inner.get(&id) {
-            Some(client) => client.accepts_focus,
-            None => conn.client_accepts_focus(id),
+        let model = match self.inner.get(&id) {
+            Some(client) => client.input_model(),
+            None => InputModel::new(conn.client_accepts_focus(id), conn.client_takes_focus(id)),
         };
 
-        self.set_x_focus(id, accepts_focus, conn)
+        self.set_x_focus(id, model, conn)
     }
 
     // The given X window ID lost focus according to the X server
@@ -229,12 +566,100 @@ impl Clients {
             self.focused_client_id = None;
         }
 
-        if self.inner.contains_key(&id) {
-            let ub = self.unfocused_border;
+        if let Some(c) = self.inner.get(&id) {
+            let ub = if c.urgent {
+                self.urgent_border
+            } else {
+                self.unfocused_border_for(id)
+            };
             // The target window may have lost focus because it has just been closed and
             // we have not yet updated our state.
-            conn.set_client_border_color(id, ub).unwrap_or(());
+            self.set_border_color(id, ub, conn).unwrap_or(());
+        }
+    }
+
+    // Set the border of a client back to the color matching its current state, undoing any
+    // temporary highlighting
+    pub fn reset_border<X>(&self, id: Xid, conn: &X) -> Result<()>
+    where
+        X: XClientConfig,
+    {
+        let color = match self.inner.get(&id) {
+            Some(_) if self.focused_client_id == Some(id) => self.focused_border_for(id),
+            Some(c) if c.urgent => self.urgent_border,
+            Some(_) => self.unfocused_border_for(id),
+            None => return Ok(()),
+        };
+
+        self.set_border_color(id, color, conn)
+    }
+
+    // The most recently urgent client that has not yet been focused (if there is one)
+    pub fn most_recent_urgent(&self) -> Option<Xid> {
+        self.urgent_clients.last().copied()
+    }
+
+    // Set the urgency state of a known client, returning the hook that needs to run if the state
+    // has changed. Focused clients are never marked as urgent.
+    pub fn set_urgent<X>(&mut self, id: Xid, urgent: bool, conn: &X) -> Option<EventAction>
+    where
+        X: XClientConfig,
+    {
+        let urgent = urgent && self.focused_client_id != Some(id);
+        let c = self.inner.get_mut(&id)?;
+        if c.urgent == urgent {
+            return None;
         }
+
+        c.urgent = urgent;
+        self.urgent_clients.retain(|&u| u != id);
+        let color = if urgent {
+            self.urgent_clients.push(id);
+            self.urgent_border
+        } else if self.focused_client_id == Some(id) {
+            self.focused_border_for(id)
+        } else {
+            self.unfocused_border_for(id)
+        };
+
+        if let Err(e) = self.set_border_color(id, color, conn) {
+            warn!("unable to set client border color for {}: {}", id, e);
+        }
+
+        Some(EventAction::RunHook(HookName::ClientUrgencyChanged(
+            id, urgent,
+        )))
+    }
+
+    // Let the X server know the current fullscreen, maximized, sticky and urgency state of a client
+    pub fn update_states<X>(&self, id: Xid, conn: &X) -> Result<()>
+    where
+        X: XClientProperties,
+    {
+        if let Some(c) = self.get(id) {
+            conn.update_client_states(c)?;
+        }
+
+        Ok(())
+    }
+
+    // Re-read the WM_HINTS and WM_PROTOCOLS properties for a client, returning whether or not the
+    // urgency hint is set
+    pub fn update_hints<X>(&mut self, id: Xid, conn: &X) -> Option<bool>
+    where
+        X: XClientProperties,
+    {
+        let c = self.inner.get_mut(&id)?;
+        c.wm_hints = conn.client_hints(id);
+        c.wm_protocols = conn.client_protocols(id);
+        c.accepts_focus = match &c.wm_hints {
+            Some(hints) => hints.accepts_input,
+            None => true,
+        };
+
+        c.wm_hints
+            .as_ref()
+            .map(|h| h.flags.contains(WmHintsFlags::URGENCY_HINT))
     }
 
     // The given window ID has had its EWMH name updated by something
@@ -262,9 +687,7 @@ impl Clients {
     pub fn apply_arrange_actions<X>(
         &mut self,
         actions: ArrangeActions,
-        lc: &LayoutConf,
         border_px: u32,
-        gap_px: u32,
         conn: &X,
     ) -> Result<()>
     where
@@ -274,11 +697,12 @@ impl Clients {
         for (id, region) in actions.actions {
             trace!(id, ?region, "positioning client");
             if let Some(region) = region {
-                let reg = pad_region(&region, lc.gapless, gap_px, border_px);
-                conn.position_client(id, reg, border_px, false)?;
+                conn.position_client(id, region, border_px, false)?;
                 self.map_if_needed(id, conn)?;
+                self.tiled_regions.insert(id, region);
             } else {
                 self.unmap_if_needed(id, conn)?;
+                self.tiled_regions.remove(&id);
             }
         }
 
@@ -309,8 +733,6 @@ impl Clients {
             }
         };
 
-        conn.toggle_client_fullscreen(id, client_currently_fullscreen)?;
-
         for &i in workspace_clients.iter() {
             if client_currently_fullscreen {
                 if i == id {
@@ -331,6 +753,8 @@ impl Clients {
             }
         }
 
+        self.update_states(id, conn)?;
+
         Ok(if client_currently_fullscreen {
             vec![EventAction::LayoutWorkspace(wix)]
         } else {
@@ -339,22 +763,83 @@ impl Clients {
     }
 }
 
+// The weight a client needs to take up 'size' of 'total' pixels when sharing them with
+// 'n_others' clients whose weights add up to 'others_weight'. Every client keeps at least a pixel.
+fn weight_for_size(size: u32, total: u32, others_weight: u32, n_others: u32) -> u32 {
+    let size = size
+        .max(1)
+        .min(total.saturating_sub(n_others.max(1)).max(1));
+    let rest = total.saturating_sub(size).max(1);
+
+    (size as u64 * others_weight as u64 / rest as u64).min(u32::MAX as u64) as u32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::xconnection::{self, *};
     use std::cell::Cell;
 
+    test_cases! {
+        weight_for_size;
+        args: (size: u32, total: u32, others_weight: u32, n_others: u32, expected: u32);
+
+        case: equal_share => (200, 400, 100, 1, 100);
+        case: three_quarters => (300, 400, 100, 1, 300);
+        case: shared_with_two => (200, 400, 200, 2, 200);
+        case: no_smaller_than_a_pixel => (0, 400, 100, 1, 0);
+        case: leaves_a_pixel_for_others => (500, 400, 100, 2, 19900);
+
+        body: {
+            assert_eq!(weight_for_size(size, total, others_weight, n_others), expected);
+        }
+    }
+
     #[test]
     fn client_lost_focus_on_focused_clears_focused_client_id() {
         let conn = MockXConn::new(vec![], vec![], vec![]);
-        let mut clients = Clients::new(0xffffff, 0x000000);
+        let mut clients = Clients::new(0xffffff, 0x000000, 0xff0000);
 
         clients.focused_client_id = Some(42);
         clients.client_lost_focus(42, &conn);
         assert!(clients.focused_client_id.is_none());
     }
 
+    #[test]
+    fn set_urgent_tracks_most_recent() {
+        let conn = MockXConn::new(vec![], vec![], vec![]);
+        let mut clients = Clients::new(0xffffff, 0x000000, 0xff0000);
+        for id in 1..4 {
+            clients.insert(id, Client::new(&conn, id, 0, &[]));
+        }
+
+        assert_eq!(
+            clients.set_urgent(1, true, &conn),
+            Some(EventAction::RunHook(HookName::ClientUrgencyChanged(
+                1, true
+            )))
+        );
+        assert!(clients.set_urgent(2, true, &conn).is_some());
+        assert!(clients.set_urgent(1, true, &conn).is_none());
+        assert_eq!(clients.most_recent_urgent(), Some(2));
+
+        clients.remove(2);
+        assert_eq!(clients.most_recent_urgent(), Some(1));
+        assert!(clients.set_urgent(1, false, &conn).is_some());
+        assert_eq!(clients.most_recent_urgent(), None);
+    }
+
+    #[test]
+    fn focused_clients_are_never_urgent() {
+        let conn = MockXConn::new(vec![], vec![], vec![]);
+        let mut clients = Clients::new(0xffffff, 0x000000, 0xff0000);
+        clients.insert(1, Client::new(&conn, 1, 0, &[]));
+        clients.set_focused(1, &conn);
+
+        assert!(clients.set_urgent(1, true, &conn).is_none());
+        assert!(!clients.get(1).unwrap().is_urgent());
+    }
+
     struct RecordingXConn {
         positions: Cell<Vec<(Xid, Region)>>,
         maps: Cell<Vec<Xid>>,
@@ -437,6 +922,18 @@ mod tests {
                 focused_client_id: None,
                 focused_border: 0xffffff.into(),
                 unfocused_border: 0x000000.into(),
+                urgent_border: 0xff0000.into(),
+                inner_border: None,
+                urgent_clients: vec![],
+                border_overrides: HashMap::new(),
+                focused_border_overrides: HashMap::new(),
+                auto_fullscreen: HashMap::new(),
+                unmaximized: HashMap::new(),
+                expected_unmaps: HashMap::new(),
+                focus_history: vec![],
+                tiled_regions: HashMap::new(),
+                swallowed: HashMap::new(),
+                deferred: HashMap::new(),
             };
 
             let r = Region::new(0, 0, 1000, 800);
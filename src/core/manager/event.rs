@@ -1,16 +1,17 @@
 /// This is where event parsing is handled and conversion of things like ICCCM and EWMH
 /// messages to penrose actions is done.
 use crate::core::{
-    bindings::{KeyCode, MouseEvent},
+    bindings::{Gesture, HotCorner, KeyCode, MouseEvent},
     data_types::{Point, Region},
     hooks::HookName,
     manager::{clients::Clients, WindowManager},
+    power::PowerEvent,
     xconnection::{
         Atom, ClientMessage, ConfigureEvent, PointerChange, PropertyEvent, XConn, XEvent, Xid,
     },
 };
 
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 pub(super) struct WmState<'a, X>
 where
@@ -18,6 +19,9 @@ where
 {
     conn: &'a X,
     clients: &'a Clients,
+    hot_corner_windows: &'a HashMap<Xid, (usize, HotCorner)>,
+    remote_commands: &'a [String],
+    suspending: bool,
 }
 
 impl<'a, X> WmState<'a, X>
@@ -28,6 +32,9 @@ where
         Self {
             conn: &manager.conn,
             clients: &manager.clients,
+            hot_corner_windows: manager.hot_corners.windows(),
+            remote_commands: manager.config.remote_commands(),
+            suspending: manager.suspending,
         }
     }
 }
@@ -48,34 +55,72 @@ pub enum EventAction {
     ClientFocusLost(Xid),
     /// An X window gained focus
     ClientFocusGained(Xid),
+    /// An X window had its _NET_WM_BYPASS_COMPOSITOR property changed
+    ClientBypassCompositorChanged(Xid),
+    /// An X window had its WM_HINTS or WM_PROTOCOLS property changed
+    ClientHintsChanged(Xid),
     /// An X window had its WM_NAME or _NET_WM_NAME property changed
     ClientNameChanged(Xid, bool),
     /// Move the given client to the workspace at the given index
     ClientToWorkspace(Xid, usize),
+    /// A client has changed size so its two-tone border needs to be redrawn
+    RedrawClientBorder(Xid),
+    /// An X window had its _NET_WM_STRUT or _NET_WM_STRUT_PARTIAL property changed
+    ClientStrutChanged(Xid),
     /// An X window was destroyed
     DestroyClient(Xid),
     /// Screens should be redetected
     DetectScreens,
+    /// A client should be made floating and moved to the given region
+    FloatClientAt(Xid, Region),
     /// A client should have focus
     FocusIn(Xid),
+    /// The pointer has entered a hot corner
+    HotCornerEntered(HotCorner),
+    /// The pointer has left a hot corner
+    HotCornerLeft,
+    /// Input devices have been added or removed so bindings need to be grabbed again
+    InputDevicesChanged,
+    /// The keyboard mapping has changed so the keymap needs refreshing and bindings need to be
+    /// grabbed again
+    KeyboardMappingChanged,
     /// The workspace on each screen should be layed out again
     LayoutVisible,
     /// Layout the workspace at the given index
     LayoutWorkspace(usize),
     /// A new X window needs to be mapped
     MapWindow(Xid),
+    /// A known X window that had withdrawn itself has requested to be mapped again
+    RemapWindow(Xid),
     /// A client is requesting to be moved: honoured if the client is floating
     MoveClientIfFloating(Xid, Region),
+    /// The system has resumed from suspend so screens need to be detected and bindings need to be
+    /// grabbed again
+    Resumed,
+    /// The system is about to be suspended
+    Suspending,
     /// The named hook should now be run
     RunHook(HookName),
+    /// A touchpad gesture was reported
+    RunGestureBinding(Gesture),
+    /// A key was released while the keyboard was grabbed
+    KeyReleased(KeyCode),
     /// A grabbed keybinding was triggered
     RunKeyBinding(KeyCode),
     /// A grabbed mouse state was triggered
     RunMouseBinding(MouseEvent),
+    /// A floating client should be returned to the tiled layout
+    TileClient(Xid),
     /// The active client should be set to this id
     SetActiveClient(Xid),
     /// The active workspace should be set to this index
     SetActiveWorkspace(usize),
+    /// A client should have its horizontal and vertical maximized states set
+    SetClientMaximized(Xid, bool, bool),
+    /// A client should have its sticky state set
+    SetClientSticky(Xid, bool),
+    /// A client should have its urgency state set
+    SetClientUrgent(Xid, bool),
     /// The active screen should be set based on point location
     SetScreenFromPoint(Option<Point>),
     /// An X window should be set fullscreen
@@ -95,11 +140,11 @@ where
         XEvent::Destroy(id) => vec![EventAction::DestroyClient(id)],
         XEvent::Expose(_) => vec![], // FIXME: work out if this needs handling in the WindowManager
         XEvent::FocusIn(id) => vec![EventAction::FocusIn(id)],
+        XEvent::InputDevicesChanged => vec![EventAction::InputDevicesChanged],
         XEvent::KeyPress(code) => vec![EventAction::RunKeyBinding(code)],
-        XEvent::Leave(p) => vec![
-            EventAction::ClientFocusLost(p.id),
-            EventAction::SetScreenFromPoint(Some(p.abs)),
-        ],
+        XEvent::KeyRelease(code) => vec![EventAction::KeyReleased(code)],
+        XEvent::KeyboardMappingChanged => vec![EventAction::KeyboardMappingChanged],
+        XEvent::LockStateChanged(s) => vec![EventAction::RunHook(HookName::LockStateChanged(s))],
         XEvent::MouseEvent(evt) => vec![EventAction::RunMouseBinding(evt)],
         XEvent::RandrNotify => vec![EventAction::DetectScreens],
         XEvent::ScreenChange => vec![EventAction::SetScreenFromPoint(None)],
@@ -107,9 +152,10 @@ where
 
         // Require processing based on current WindowManager state
         XEvent::ClientMessage(msg) => process_client_message(state, msg),
-        XEvent::ConfigureNotify(evt) => process_configure_notify(evt),
+        XEvent::ConfigureNotify(evt) => process_configure_notify(state, evt),
         XEvent::ConfigureRequest(evt) => process_configure_request(evt),
         XEvent::Enter(p) => process_enter_notify(state, p),
+        XEvent::Leave(p) => process_leave_notify(state, p),
         XEvent::MapRequest(id, override_redirect) => {
             process_map_request(state, id, override_redirect)
         }
@@ -124,33 +170,131 @@ where
     let data = msg.data();
     trace!(id = msg.id, dtype = ?msg.dtype, ?data, "got client message");
 
-    let is_fullscreen = |data: &[u32]| {
-        data.iter()
-            .map(|&a| state.conn.atom_name(a))
-            .flatten()
-            .any(|s| s == Atom::NetWmStateFullscreen.as_ref())
-    };
-
     match Atom::from_str(&msg.dtype) {
         Ok(Atom::NetActiveWindow) => vec![EventAction::SetActiveClient(msg.id)],
         Ok(Atom::NetCurrentDesktop) => vec![EventAction::SetActiveWorkspace(data.as_usize()[0])],
         Ok(Atom::NetWmDesktop) => vec![EventAction::ClientToWorkspace(msg.id, data.as_usize()[0])],
-        Ok(Atom::NetWmState) if is_fullscreen(&data.as_u32()[1..3]) => {
-            // _NET_WM_STATE_ADD == 1, _NET_WM_STATE_TOGGLE == 2
-            let should_fullscreen = [1, 2].contains(&data.as_usize()[0]);
-            vec![EventAction::ToggleClientFullScreen(
-                msg.id,
-                should_fullscreen,
-            )]
+        Ok(Atom::PenroseGesture) if remote_command_allowed(&state, "gesture") => {
+            match Gesture::from_data(&data.as_u32()) {
+                Some(g) => vec![EventAction::RunGestureBinding(g)],
+                None => vec![],
+            }
+        }
+        Ok(Atom::PenroseMoveWindow) if remote_command_allowed(&state, "move-window") => {
+            let d = data.as_u32();
+            let r = Region::new(d[1], d[2], d[3], d[4]);
+            vec![EventAction::FloatClientAt(d[0], r)]
         }
+        Ok(Atom::PenroseTileWindow) if remote_command_allowed(&state, "tile-window") => {
+            vec![EventAction::TileClient(data.as_u32()[0])]
+        }
+        Ok(Atom::PenrosePowerEvent) if remote_command_allowed(&state, "power-event") => {
+            process_power_event(state, &data.as_u32())
+        }
+        Ok(Atom::NetWmState) => process_net_wm_state(state, msg.id, &data.as_u32()),
+        _ => vec![],
+    }
+}
 
+// Remote commands can be sent by any X client so only those enabled in the config are run
+fn remote_command_allowed<X>(state: &WmState<'_, X>, name: &str) -> bool
+where
+    X: XConn,
+{
+    let allowed = state.remote_commands.iter().any(|c| c == name);
+    if !allowed {
+        warn!(
+            name,
+            "ignoring remote command that is not enabled in the config"
+        );
+    }
+
+    allowed
+}
+
+fn process_power_event<X>(state: WmState<'_, X>, data: &[u32]) -> Vec<EventAction>
+where
+    X: XConn,
+{
+    match PowerEvent::from_data(data) {
+        Some(PowerEvent::LidClosed) => vec![EventAction::RunHook(HookName::LidStateChanged(true))],
+        Some(PowerEvent::LidOpened) => vec![EventAction::RunHook(HookName::LidStateChanged(false))],
+        Some(PowerEvent::PrepareForSleep) => vec![EventAction::Suspending],
+        // the resume will already have been handled if it was spotted from the system clock
+        Some(PowerEvent::Resumed) if state.suspending => vec![EventAction::Resumed],
         _ => vec![],
     }
 }
 
-fn process_configure_notify(evt: ConfigureEvent) -> Vec<EventAction> {
+// A single _NET_WM_STATE message can change two properties at once so each of them needs
+// to be handled in turn
+fn process_net_wm_state<X>(state: WmState<'_, X>, id: Xid, data: &[u32]) -> Vec<EventAction>
+where
+    X: XConn,
+{
+    // _NET_WM_STATE_REMOVE == 0, _NET_WM_STATE_ADD == 1, _NET_WM_STATE_TOGGLE == 2
+    let should_set = |currently_set: bool| match data[0] {
+        0 => false,
+        1 => true,
+        _ => !currently_set,
+    };
+    let client = state.clients.get(id);
+    let atoms: Vec<Atom> = data[1..3]
+        .iter()
+        .filter(|&&a| a != 0)
+        .flat_map(|&a| state.conn.atom_name(a))
+        .flat_map(|name| Atom::from_str(&name))
+        .collect();
+
+    // Maximizing along both axes is requested as a single message so the two axes are set
+    // together rather than each undoing the other
+    let horz = atoms.contains(&Atom::NetWmStateMaximizedHorz);
+    let vert = atoms.contains(&Atom::NetWmStateMaximizedVert);
+    let maximized = if horz || vert {
+        let (h, v) = client.map_or((false, false), |c| (c.maximized_horz, c.maximized_vert));
+        let h = if horz { should_set(h) } else { h };
+        let v = if vert { should_set(v) } else { v };
+        Some(EventAction::SetClientMaximized(id, h, v))
+    } else {
+        None
+    };
+
+    // Urgency is handled first so that it is settled before anything else in the same message
+    // can move focus to the client
+    let (urgency, others): (Vec<Atom>, Vec<Atom>) = atoms
+        .into_iter()
+        .partition(|&a| a == Atom::NetWmStateDemandsAttention);
+
+    urgency
+        .into_iter()
+        .chain(others)
+        .flat_map(|atom| match atom {
+            Atom::NetWmStateFullscreen => {
+                let should_fullscreen = [1, 2].contains(&data[0]);
+                Some(EventAction::ToggleClientFullScreen(id, should_fullscreen))
+            }
+            Atom::NetWmStateDemandsAttention => {
+                let urgent = should_set(matches!(client, Some(c) if c.urgent));
+                Some(EventAction::SetClientUrgent(id, urgent))
+            }
+            Atom::NetWmStateSticky => {
+                let sticky = should_set(matches!(client, Some(c) if c.sticky));
+                Some(EventAction::SetClientSticky(id, sticky))
+            }
+            _ => None,
+        })
+        .chain(maximized)
+        .collect()
+}
+
+fn process_configure_notify<X>(state: WmState<'_, X>, evt: ConfigureEvent) -> Vec<EventAction>
+where
+    X: XConn,
+{
     if evt.is_root {
         vec![EventAction::DetectScreens]
+    } else if state.clients.has_inner_border() && state.clients.is_known(evt.id) {
+        vec![EventAction::RedrawClientBorder(evt.id)]
     } else {
         vec![]
     }
@@ -168,6 +312,10 @@ fn process_enter_notify<X>(state: WmState<'_, X>, p: PointerChange) -> Vec<Event
 where
     X: XConn,
 {
+    if let Some(&(_, corner)) = state.hot_corner_windows.get(&p.id) {
+        return vec![EventAction::HotCornerEntered(corner)];
+    }
+
     let mut actions = vec![
         EventAction::ClientFocusGained(p.id),
         EventAction::SetScreenFromPoint(Some(p.abs)),
@@ -182,9 +330,26 @@ where
     actions
 }
 
-// Processing around map_request is currently copied from dwm:
-//   - if override_redirect is set we completely ignore the window
-//   - if the client is in the client_map (i.e. we are already managing this client) then ignore
+fn process_leave_notify<X>(state: WmState<'_, X>, p: PointerChange) -> Vec<EventAction>
+where
+    X: XConn,
+{
+    if state.hot_corner_windows.contains_key(&p.id) {
+        return vec![EventAction::HotCornerLeft];
+    }
+
+    vec![
+        EventAction::ClientFocusLost(p.id),
+        EventAction::SetScreenFromPoint(Some(p.abs)),
+    ]
+}
+
+// Processing around map_request is based on dwm:
+//   - if override_redirect is set we completely ignore the window: popups, tooltips and menus
+//     map themselves and are not ours to manage
+//   - if the client is in the client_map (i.e. we are already managing this client) then it has
+//     withdrawn itself and is asking to be shown again
+//   - otherwise we register the new client and then map it ourselves
 fn process_map_request<X>(
     state: WmState<'_, X>,
     id: Xid,
@@ -193,8 +358,10 @@ fn process_map_request<X>(
 where
     X: XConn,
 {
-    if override_redirect || state.clients.is_known(id) {
+    if override_redirect {
         vec![]
+    } else if state.clients.is_known(id) {
+        vec![EventAction::RemapWindow(id)]
     } else {
         vec![EventAction::MapWindow(id)]
     }
@@ -205,6 +372,15 @@ fn process_property_notify(evt: PropertyEvent) -> Vec<EventAction> {
         Ok(a) if a == Atom::WmName || a == Atom::NetWmName => {
             vec![EventAction::ClientNameChanged(evt.id, evt.is_root)]
         }
+        Ok(Atom::WmHints) | Ok(Atom::WmProtocols) if !evt.is_root => {
+            vec![EventAction::ClientHintsChanged(evt.id)]
+        }
+        Ok(Atom::NetWmStrut) | Ok(Atom::NetWmStrutPartial) if !evt.is_root => {
+            vec![EventAction::ClientStrutChanged(evt.id)]
+        }
+        Ok(Atom::NetWmBypassCompositor) if !evt.is_root => {
+            vec![EventAction::ClientBypassCompositorChanged(evt.id)]
+        }
         // TODO: handle other property changes and possibly allow users to process
         //       unknown events?
         _ => vec![EventAction::UnknownPropertyChange(
@@ -0,0 +1,281 @@
+//! State and management of hot corners and pointer bindings for the desktop and screen edges.
+use crate::{
+    core::{
+        bindings::{
+            HotCorner, HotCorners, PointerBindings, PointerTarget, DEFAULT_HOT_CORNER_SIZE,
+        },
+        manager::screens::Screens,
+        xconnection::{XConn, Xid},
+    },
+    Result,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// Hot corners and edge pointer bindings share the same input windows
+pub(super) struct HotCornerState<X: XConn> {
+    hot_corners: Option<HotCorners<X>>,
+    // None while the bindings have been taken to run one of them
+    pointer_bindings: Option<PointerBindings<X>>,
+    // the input windows covering each hot corner and edge along with the index of their screen
+    windows: HashMap<Xid, (usize, HotCorner)>,
+    // the hot corner containing the pointer and the point at which its action should run
+    pending: Option<(HotCorner, Instant)>,
+}
+
+impl<X: XConn> HotCornerState<X> {
+    pub fn new() -> Self {
+        Self {
+            hot_corners: None,
+            pointer_bindings: Some(HashMap::new()),
+            windows: HashMap::new(),
+            pending: None,
+        }
+    }
+
+    pub fn set_hot_corners(&mut self, hot_corners: HotCorners<X>) {
+        self.hot_corners = Some(hot_corners);
+    }
+
+    // Actions are run with mutable access to the WindowManager so they need to be moved out
+    // while they run: see restore_hot_corners
+    pub fn take_hot_corners(&mut self) -> Option<HotCorners<X>> {
+        self.hot_corners.take()
+    }
+
+    // Put back hot corners that were taken to run an action, unless the action replaced them
+    pub fn restore_hot_corners(&mut self, hot_corners: HotCorners<X>) {
+        if self.hot_corners.is_none() {
+            self.hot_corners = Some(hot_corners);
+        }
+    }
+
+    pub fn set_pointer_bindings(&mut self, bindings: PointerBindings<X>) {
+        self.pointer_bindings = Some(bindings);
+    }
+
+    // As with hot corners, pointer bindings are moved out while one of them is running
+    pub fn take_pointer_bindings(&mut self) -> PointerBindings<X> {
+        self.pointer_bindings.take().unwrap_or_default()
+    }
+
+    // Put back pointer bindings that were taken to run a handler, unless the handler replaced
+    // them. Returns whether or not the handler replaced them.
+    pub fn restore_pointer_bindings(&mut self, bindings: PointerBindings<X>) -> bool {
+        if self.pointer_bindings.is_some() {
+            return true;
+        }
+        self.pointer_bindings = Some(bindings);
+
+        false
+    }
+
+    // The corner or edge covered by the input window 'id' if it is one of ours
+    pub fn edge_for(&self, id: Xid) -> Option<HotCorner> {
+        self.windows.get(&id).map(|&(_, corner)| corner)
+    }
+
+    pub fn windows(&self) -> &HashMap<Xid, (usize, HotCorner)> {
+        &self.windows
+    }
+
+    pub fn is_input_window(&self, id: Xid) -> bool {
+        self.windows.contains_key(&id)
+    }
+
+    // The pointer has entered 'corner': returns the corner if its action should be run now,
+    // otherwise the action is left pending until the configured delay has passed
+    pub fn enter(&mut self, corner: HotCorner, now: Instant) -> Option<HotCorner> {
+        let delay = self.hot_corners.as_ref()?.get_delay();
+
+        if delay == Duration::from_millis(0) {
+            Some(corner)
+        } else {
+            self.pending = Some((corner, now + delay));
+            None
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.pending = None;
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, deadline)| deadline)
+    }
+
+    // The pending hot corner if its delay has passed
+    pub fn take_due(&mut self, now: Instant) -> Option<HotCorner> {
+        match self.pending {
+            Some((corner, deadline)) if deadline <= now => {
+                self.pending = None;
+                Some(corner)
+            }
+            _ => None,
+        }
+    }
+
+    // Cover each hot corner and each edge with a pointer binding on every screen with an input
+    // window, replacing any existing windows
+    pub fn create_windows(&mut self, conn: &X, screens: &Screens) -> Result<()> {
+        for (id, _) in self.windows.drain() {
+            conn.destroy_client(id)?;
+        }
+        self.pending = None;
+
+        let (mut corners, size) = match &self.hot_corners {
+            Some(hot_corners) => (hot_corners.corners(), hot_corners.get_size()),
+            None => (vec![], DEFAULT_HOT_CORNER_SIZE),
+        };
+        for (target, _) in self.pointer_bindings.iter().flat_map(|b| b.keys()) {
+            if let PointerTarget::Edge(corner) = *target {
+                if !corners.contains(&corner) {
+                    corners.push(corner);
+                }
+            }
+        }
+
+        for i in 0..screens.n_screens() {
+            let r = match screens.get(i) {
+                Some(s) => s.region(false),
+                None => continue,
+            };
+
+            for &corner in corners.iter() {
+                let id = conn.create_input_window(corner.region(&r, size))?;
+                conn.map_client(id)?;
+                self.windows.insert(id, (i, corner));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn raise_windows(&self, conn: &X, screen_index: usize) -> Result<()> {
+        for (&id, &(i, _)) in self.windows.iter() {
+            if i == screen_index {
+                conn.raise_client(id)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        __test_helpers::*,
+        core::{bindings::HotCornerHandler, manager::WindowManager},
+    };
+
+    fn hot_corners(delay: u64) -> HotCornerState<TestXConn> {
+        let mut bindings: HashMap<HotCorner, HotCornerHandler<TestXConn>> = HashMap::new();
+        bindings.insert(
+            HotCorner::TopLeft,
+            Box::new(|_: &mut WindowManager<TestXConn>| Ok(())),
+        );
+
+        let mut state = HotCornerState::new();
+        state.set_hot_corners(HotCorners::new(bindings).delay(Duration::from_millis(delay)));
+        state
+    }
+
+    #[test]
+    fn entering_without_hot_corners_does_nothing() {
+        let mut state: HotCornerState<TestXConn> = HotCornerState::new();
+
+        assert_eq!(state.enter(HotCorner::TopLeft, Instant::now()), None);
+        assert_eq!(state.deadline(), None);
+    }
+
+    #[test]
+    fn entering_with_no_delay_runs_immediately() {
+        let mut state = hot_corners(0);
+
+        assert_eq!(
+            state.enter(HotCorner::TopLeft, Instant::now()),
+            Some(HotCorner::TopLeft)
+        );
+        assert_eq!(state.deadline(), None);
+    }
+
+    #[test]
+    fn pending_corners_are_due_after_their_delay() {
+        let mut state = hot_corners(300);
+        let now = Instant::now();
+
+        assert_eq!(state.enter(HotCorner::TopLeft, now), None);
+        assert_eq!(state.take_due(now), None);
+        assert_eq!(
+            state.take_due(now + Duration::from_millis(300)),
+            Some(HotCorner::TopLeft)
+        );
+        assert_eq!(state.deadline(), None);
+    }
+
+    #[test]
+    fn leaving_cancels_pending_corners() {
+        let mut state = hot_corners(300);
+        let now = Instant::now();
+
+        state.enter(HotCorner::TopLeft, now);
+        state.leave();
+
+        assert_eq!(state.take_due(now + Duration::from_secs(1)), None);
+    }
+
+    fn n_pointer_bindings(state: &HotCornerState<TestXConn>) -> Option<usize> {
+        state.pointer_bindings.as_ref().map(|b| b.len())
+    }
+
+    #[test]
+    fn pointer_bindings_set_while_running_replace_the_existing_ones() {
+        let mut state: HotCornerState<TestXConn> = HotCornerState::new();
+        state.set_pointer_bindings(gen_pointerbindings! {
+            Desktop Left + [] => |_: &mut WindowManager<TestXConn>| Ok(())
+        });
+
+        let bindings = state.take_pointer_bindings();
+        state.set_pointer_bindings(gen_pointerbindings! {
+            Edge(Left) Right + [] => |_: &mut WindowManager<TestXConn>| Ok(())
+        });
+        assert!(state.restore_pointer_bindings(bindings));
+        assert_eq!(n_pointer_bindings(&state), Some(1));
+
+        let bindings = state.take_pointer_bindings();
+        assert!(!state.restore_pointer_bindings(bindings));
+        assert_eq!(n_pointer_bindings(&state), Some(1));
+    }
+
+    #[test]
+    fn pointer_bindings_removed_while_running_stay_removed() {
+        let mut state: HotCornerState<TestXConn> = HotCornerState::new();
+        state.set_pointer_bindings(gen_pointerbindings! {
+            Desktop Left + [] => |_: &mut WindowManager<TestXConn>| Ok(()),
+            Edge(Left) Right + [] => |_: &mut WindowManager<TestXConn>| Ok(())
+        });
+
+        // the running Desktop binding removes itself
+        let bindings = state.take_pointer_bindings();
+        state.set_pointer_bindings(gen_pointerbindings! {
+            Edge(Left) Right + [] => |_: &mut WindowManager<TestXConn>| Ok(())
+        });
+        assert!(state.restore_pointer_bindings(bindings));
+        let targets: Vec<PointerTarget> = state
+            .pointer_bindings
+            .iter()
+            .flat_map(|b| b.keys().map(|(t, _)| *t))
+            .collect();
+        assert_eq!(targets, vec![PointerTarget::Edge(HotCorner::Left)]);
+
+        // removing every binding is also kept
+        let bindings = state.take_pointer_bindings();
+        state.set_pointer_bindings(HashMap::new());
+        assert!(state.restore_pointer_bindings(bindings));
+        assert_eq!(n_pointer_bindings(&state), Some(0));
+    }
+}
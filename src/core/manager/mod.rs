@@ -1,41 +1,85 @@
 //! The main user API and control logic for Penrose.
 use crate::{
     core::{
-        bindings::{KeyBindings, KeyCode, MouseBindings, MouseEvent},
-        client::Client,
-        config::Config,
-        data_types::{Change, Point, Region},
-        hooks::{HookName, Hooks},
+        bindings::{
+            Gesture, GestureBindings, HotCorner, HotCorners, KeyBindingReport, KeyBindings,
+            KeyChord, KeyCode, KeyCodeValue, KeyEventHandler, KeyRemap, MouseBindings, MouseButton,
+            MouseEvent, MouseEventKind, PointerBindings, PointerTarget,
+        },
+        client::{Client, DEFAULT_CLIENT_WEIGHT},
+        config::{Config, PointerWarp},
+        data_types::{Change, Point, Region, RelativePosition},
+        hooks::{HookName, Hooks, LifecycleHook},
+        layout::Spacing,
+        process::{self, spawn_with_args, Spawn},
         ring::{Direction, InsertPoint, Selector},
+        rules::{matching_actions, RuleAction},
         screen::Screen,
+        state::{ClientState, ScreenState, State, WorkspaceState},
         workspace::Workspace,
-        xconnection::{Atom, ClientMessageKind, WindowState, XConn, Xid},
+        xconnection::{ClientAttr, WindowState, WmHintsFlags, XConn, XEvent, Xid},
+        xdg,
     },
+    draw::{Background, Color},
     ErrorHandler, PenroseError, Result,
 };
-use nix::sys::signal::{signal, SigHandler, Signal};
-use std::{cell::Cell, fmt};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    process::Child,
+    time::{Duration, Instant, SystemTime},
+};
 use tracing::Level;
 
 #[cfg(feature = "serde")]
 use crate::core::{helpers::logging_error_handler, layout::LayoutFunc};
 
-#[cfg(feature = "serde")]
-use std::collections::HashMap;
-
 mod clients;
 mod event;
+mod hot_corners;
 mod screens;
+mod tags;
 mod util;
 mod workspaces;
 
 use clients::Clients;
 use event::EventAction;
+use hot_corners::HotCornerState;
 use screens::Screens;
 use workspaces::Workspaces;
 
 use event::{process_next_event, WmState};
 
+// X11 ShiftMask: shift is not required to be held during a cycle through recently focused
+// clients so that it can be used to reverse direction
+const SHIFT_MASK: u16 = 1;
+
+// How far the wall clock needs to jump ahead of the monotonic clock between events for us to
+// assume that the system has been suspended: small adjustments from NTP are ignored
+const RESUME_CLOCK_JUMP: Duration = Duration::from_secs(5);
+
+// An in progress alt-tab style cycle through recently focused clients
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MruCycle {
+    // the focus history when the cycle started, restored once the cycle is complete
+    history: Vec<Xid>,
+    candidates: Vec<Xid>,
+    index: usize,
+    // the modifiers that need to be released to complete the cycle
+    mask: u16,
+}
+
+/// What the user picked while [select_interactive][WindowManager::select_interactive] was
+/// waiting for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InteractiveSelection {
+    /// The left mouse button was clicked at this absolute position
+    Clicked(Point),
+    /// The key with this name (as given to `select_interactive`) was pressed
+    KeyPressed(String),
+}
+
 #[cfg(feature = "serde")]
 fn default_hooks<X: XConn>() -> Cell<Hooks<X>> {
     Cell::new(Vec::new())
@@ -74,6 +118,56 @@ pub struct WindowManager<X: XConn> {
     pub(super) error_handler: ErrorHandler,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub(super) hydrated: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    game_mode: bool,
+    // the key binding that enabled game mode: left grabbed so that it can be used to disable it
+    #[cfg_attr(feature = "serde", serde(skip))]
+    game_mode_key: Option<KeyCode>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    running_key: Option<KeyCode>,
+    // the key names held by each key code when the key bindings were grabbed and how those key
+    // codes have moved since then
+    #[cfg_attr(feature = "serde", serde(skip))]
+    original_keycodes: HashMap<String, KeyCodeValue>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    key_remap: KeyRemap,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    key_binding_report: Option<KeyBindingReport>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "HashMap::new"))]
+    gesture_bindings: GestureBindings<X>,
+    // the active key chord and the point at which it times out
+    #[cfg_attr(feature = "serde", serde(skip, default = "Option::default"))]
+    key_chord: Option<(KeyChord<X>, Option<Instant>)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    key_chord_changed: bool,
+    #[cfg_attr(feature = "serde", serde(skip, default = "HotCornerState::new"))]
+    hot_corners: HotCornerState<X>,
+    // the IDs of the keyboards that were attached when we last checked
+    #[cfg_attr(feature = "serde", serde(skip))]
+    known_keyboards: Vec<u16>,
+    // the point at which a hook has asked for the event loop to wake up
+    #[cfg_attr(feature = "serde", serde(skip))]
+    wakeup: Option<Instant>,
+    // the monotonic and wall clock times at which we last checked for a resume from suspend
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clock_check: Option<(Instant, SystemTime)>,
+    // set when we are told that the system is about to sleep and cleared once it has resumed
+    #[cfg_attr(feature = "serde", serde(skip))]
+    suspending: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    mru_cycle: Option<MruCycle>,
+    // the color that the root window was last set to
+    #[cfg_attr(feature = "serde", serde(skip))]
+    root_background: Option<Color>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "Vec::new"))]
+    startup_hooks: Vec<LifecycleHook<X>>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "Vec::new"))]
+    shutdown_hooks: Vec<LifecycleHook<X>>,
+    // programs to start once penrose is running and the processes that were started for them
+    #[cfg_attr(feature = "serde", serde(skip))]
+    autostart: Vec<Spawn>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    autostarted: Vec<Child>,
 }
 
 impl<X: XConn> fmt::Debug for WindowManager<X> {
@@ -87,6 +181,7 @@ impl<X: XConn> fmt::Debug for WindowManager<X> {
             .field("hooks", &stringify!(self.hooks))
             .field("previous_workspace", &self.previous_workspace)
             .field("running", &self.running)
+            .field("game_mode", &self.game_mode)
             .finish()
     }
 }
@@ -102,13 +197,23 @@ impl<X: XConn> WindowManager<X> {
             config
                 .workspaces
                 .iter()
-                .map(|name| Workspace::new(name, layouts.to_vec()))
+                .enumerate()
+                .map(|(i, name)| {
+                    let mut ws = Workspace::new(name, layouts.to_vec());
+                    ws.set_background(config.workspace_backgrounds.get(i).cloned());
+                    ws
+                })
                 .collect(),
             config.main_ratio_step,
         );
 
-        let screens = Screens::new(config.bar_height, config.top_bar);
-        let clients = Clients::new(config.focused_border, config.unfocused_border);
+        let screens = Screens::new(config.bar_configs(), config.scale_by_dpi);
+        let mut clients = Clients::new(
+            config.focused_border,
+            config.unfocused_border,
+            config.urgent_border,
+        );
+        clients.set_inner_border(config.inner_border, config.inner_border_px);
 
         Self {
             conn,
@@ -121,6 +226,26 @@ impl<X: XConn> WindowManager<X> {
             running: false,
             hydrated: true,
             error_handler,
+            game_mode: false,
+            game_mode_key: None,
+            running_key: None,
+            original_keycodes: HashMap::new(),
+            key_remap: KeyRemap::default(),
+            key_binding_report: None,
+            gesture_bindings: HashMap::new(),
+            key_chord: None,
+            key_chord_changed: false,
+            hot_corners: HotCornerState::new(),
+            known_keyboards: Vec::new(),
+            wakeup: None,
+            clock_check: None,
+            suspending: false,
+            mru_cycle: None,
+            root_background: None,
+            startup_hooks: Vec::new(),
+            shutdown_hooks: Vec::new(),
+            autostart: Vec::new(),
+            autostarted: Vec::new(),
         }
     }
 
@@ -196,7 +321,9 @@ impl<X: XConn> WindowManager<X> {
     #[tracing::instrument(level = "debug", err, skip(self))]
     pub fn init(&mut self) -> Result<()> {
         if !self.hydrated {
-            panic!("Need to call 'hydrate_and_init' when restoring from serialised state")
+            return Err(PenroseError::HydrationState(
+                "Need to call 'hydrate_and_init' when restoring from serialised state".into(),
+            ));
         }
 
         trace!("Initialising XConn");
@@ -207,6 +334,7 @@ impl<X: XConn> WindowManager<X> {
 
         trace!("Setting EWMH properties");
         self.conn.set_wm_properties(&self.config.workspaces)?;
+        self.update_root_background()?;
 
         trace!("Forcing cursor to first screen");
         Ok(self.conn.warp_cursor(None, &self.screens.inner[0])?)
@@ -218,11 +346,22 @@ impl<X: XConn> WindowManager<X> {
         for mut c in self.conn.active_managed_clients(classes)?.into_iter() {
             let id = c.id();
             self.add_client_to_workspace(c.workspace(), id)?;
+            if c.mapped {
+                self.clients.expect_unmap(id);
+            }
             self.conn.unmap_client_if_needed(Some(&mut c))?;
             self.clients.insert(id, c);
             self.conn.mark_new_client(id)?;
         }
 
+        for id in self.conn.active_clients()? {
+            if let Some(strut) = self.conn.client_strut(id) {
+                self.screens.set_strut(id, strut);
+                self.conn
+                    .set_client_attributes(id, &[ClientAttr::ClientEventMaskNoCrossing])?;
+            }
+        }
+
         if let Some(id) = self.workspaces.focused_client(0) {
             self.update_focus(id)?;
         }
@@ -232,6 +371,14 @@ impl<X: XConn> WindowManager<X> {
         Ok(())
     }
 
+    // Pass an error to the user provided error handler and then let any hooks know that
+    // something went wrong so that it can be surfaced (e.g. in a status bar)
+    fn handle_error(&mut self, e: PenroseError) {
+        let msg = e.to_string();
+        (self.error_handler)(e);
+        self.run_hook(HookName::ErrorRaised(msg));
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     fn run_hook(&mut self, hook_name: HookName) {
         use HookName::*;
@@ -278,7 +425,30 @@ impl<X: XConn> WindowManager<X> {
             }
             RanderNotify => run_hooks!(randr_notify, self,),
             FocusChange(root) => run_hooks!(focus_change, self, root),
+            ClientUrgencyChanged(id, urgent) => {
+                run_hooks!(client_urgency_changed, self, id, urgent)
+            }
             EventHandled => run_hooks!(event_handled, self,),
+            ErrorRaised(msg) => run_hooks!(error_raised, self, &msg),
+            InputDevicesChanged => run_hooks!(input_devices_changed, self,),
+            LockStateChanged(state) => run_hooks!(lock_state_changed, self, state),
+            KeyChordChanged(hints) => run_hooks!(key_chord_changed, self, str_slice!(hints)),
+            WorkspaceOverflow(id, full, overflow) => {
+                run_hooks!(workspace_overflow, self, id, full, overflow)
+            }
+            MainRatioChanged(wix) => {
+                if let Ok(ratio) = self.workspaces.get_workspace(wix).map(|ws| ws.main_ratio()) {
+                    run_hooks!(main_ratio_changed, self, wix, ratio)
+                }
+            }
+            RootWindowClicked(button, p) => run_hooks!(root_window_clicked, self, button, p),
+            RootWindowReleased(button, p) => run_hooks!(root_window_released, self, button, p),
+            WindowClicked(id, button, p) => run_hooks!(window_clicked, self, id, button, p),
+            WindowReleased(id, button, p) => run_hooks!(window_released, self, id, button, p),
+            Resumed => run_hooks!(resumed, self,),
+            Suspending => run_hooks!(suspending, self,),
+            LidStateChanged(closed) => run_hooks!(lid_state_changed, self, closed),
+            BarVisibilityChanged(visible) => run_hooks!(bar_visibility_changed, self, visible),
         }
     }
 
@@ -302,34 +472,102 @@ impl<X: XConn> WindowManager<X> {
         use EventAction::*;
 
         match action {
+            // focus does not follow the mouse while in game mode
+            ClientFocusGained(_) | ClientFocusLost(_) if self.game_mode => (),
+            // the pointer should not change focus part way through cycling recent clients
+            ClientFocusGained(_) | ClientFocusLost(_) if self.mru_cycle.is_some() => (),
             ClientFocusGained(id) => self.update_focus(id)?,
             ClientFocusLost(id) => self.clients.client_lost_focus(id, &self.conn),
+            ClientBypassCompositorChanged(id) => {
+                let bypass = self.conn.client_requests_compositor_bypass(id);
+                self.set_auto_fullscreen(id, bypass)?
+            }
+            ClientHintsChanged(id) => self.handle_hints_change(id)?,
+            // Names of hidden clients are re-read when they are next shown
+            ClientNameChanged(id, false)
+                if !tags::client_is_viewed(&self.clients, &self.screens, id) =>
+            {
+                self.clients.defer(id, |d| d.name = true)
+            }
             ClientNameChanged(id, is_root) => {
                 let action = self.clients.client_name_changed(id, is_root, &self.conn)?;
                 self.handle_event_action(action, None, None)?
             }
             ClientToWorkspace(id, wix) => self.move_client_to_workspace(id, wix)?,
+            RedrawClientBorder(id) => self.clients.reset_border(id, &self.conn)?,
             DestroyClient(id) => self.remove_client(id)?,
             DetectScreens => {
                 self.run_hook(HookName::RanderNotify);
                 self.detect_screens()?
             }
-            FocusIn(id) => self.clients.focus_in(id, &self.conn)?,
+            FocusIn(id) => self.handle_focus_in(id)?,
             LayoutVisible => self.layout_visible()?,
             LayoutWorkspace(wix) => self.apply_layout(wix)?,
             MapWindow(id) => self.handle_map_request(id)?,
+            RemapWindow(id) => self.handle_remap_request(id)?,
             MoveClientIfFloating(id, r) => self.handle_move_if_floating(id, r)?,
+            Resumed => {
+                info!("resumed from suspend: checking screens and grabbing bindings again");
+                self.suspending = false;
+                self.detect_screens()?;
+                if let (Some(kb), Some(mb)) = (key_bindings, mouse_bindings) {
+                    self.update_grabs(kb, mb)?;
+                }
+                self.run_hook(HookName::Resumed);
+            }
+            Suspending => {
+                self.suspending = true;
+                self.run_hook(HookName::Suspending);
+            }
+            ClientStrutChanged(id) => self.update_strut(id)?,
+            FloatClientAt(id, r) => self.float_client_at(id, r)?,
+            TileClient(id) => self.tile_client(id)?,
             RunHook(hook_name) => self.run_hook(hook_name),
-            RunKeyBinding(e) => match key_bindings {
-                Some(kb) => self.run_key_binding(e, kb),
-                None => return Err(perror!("keybindings can only be triggered from X events")),
+            KeyReleased(_) => self.handle_key_release()?,
+            InputDevicesChanged => {
+                if let (Some(kb), Some(mb)) = (key_bindings, mouse_bindings) {
+                    self.update_grabs(kb, mb)?;
+                }
+                if self.detect_new_keyboards() {
+                    self.configure_keyboards();
+                }
+                self.configure_pointer_devices();
+                self.run_hook(HookName::InputDevicesChanged);
+            }
+            // NOTE: keyboard config is not re-applied here as running setxkbmap would trigger
+            //       another mapping change
+            KeyboardMappingChanged => {
+                self.conn.refresh_keymap()?;
+                let current = self.conn.keycodes()?;
+                self.key_remap = KeyRemap::between(&self.original_keycodes, &current);
+                if let (Some(kb), Some(mb)) = (key_bindings, mouse_bindings) {
+                    self.update_grabs(kb, mb)?;
+                }
+            }
+            RunGestureBinding(g) => self.run_gesture_binding(g),
+            HotCornerEntered(corner) => {
+                if let Some(corner) = self.hot_corners.enter(corner, Instant::now()) {
+                    self.run_hot_corner(corner);
+                }
+            }
+            HotCornerLeft => self.hot_corners.leave(),
+            RunKeyBinding(e) => match (key_bindings, self.key_remap.original(e)) {
+                (Some(kb), Some(k)) => self.run_key_binding(k, kb),
+                (Some(_), None) => trace!(?e, "key is no longer bound after a mapping change"),
+                (None, _) => {
+                    return Err(perror!("keybindings can only be triggered from X events"))
+                }
             },
             RunMouseBinding(e) => match mouse_bindings {
                 Some(mb) => self.run_mouse_binding(e, mb),
                 None => return Err(perror!("mousebindings can only be triggered from X events")),
             },
+            SetActiveClient(id) if self.game_mode => self.set_urgent(id, true)?,
             SetActiveClient(id) => self.set_active_client(id)?,
             SetActiveWorkspace(wix) => self.focus_workspace(&Selector::Index(wix))?,
+            SetClientMaximized(id, horz, vert) => self.set_maximized(id, horz, vert)?,
+            SetClientSticky(id, sticky) => self.set_sticky(id, sticky)?,
+            SetClientUrgent(id, urgent) => self.set_urgent(id, urgent)?,
             SetScreenFromPoint(p) => self.set_screen_from_point(p)?,
             ToggleClientFullScreen(id, should_fullscreen) => {
                 self.set_fullscreen(id, should_fullscreen)?;
@@ -362,31 +600,55 @@ impl<X: XConn> WindowManager<X> {
         mut mouse_bindings: MouseBindings<X>,
     ) -> Result<()> {
         if self.running {
-            panic!("Attempt to call grab_keys_and_run while already running");
+            return Err(PenroseError::Raw(
+                "Attempt to call grab_keys_and_run while already running".into(),
+            ));
         }
         if !self.hydrated {
-            panic!("'hydrate_and_init' must be called before 'grab_keys_and_run' when restoring from serialised state")
-        }
-
-        // ignore SIGCHILD and allow child / inherited processes to be inherited by pid1
-        trace!("registering SIGCHILD signal handler");
-        if let Err(e) = unsafe { signal(Signal::SIGCHLD, SigHandler::SigIgn) } {
-            panic!("unable to set signal handler: {}", e);
+            return Err(PenroseError::HydrationState(
+                "'hydrate_and_init' must be called before 'grab_keys_and_run' when restoring from serialised state".into(),
+            ));
         }
 
         trace!("grabbing key and mouse bindings");
+        self.original_keycodes = self.conn.keycodes().unwrap_or_else(|e| {
+            warn!(
+                "unable to read key names: bindings will not follow keymap changes: {}",
+                e
+            );
+            HashMap::new()
+        });
         self.conn.grab_keys(&key_bindings, &mouse_bindings)?;
 
+        trace!("applying input device config");
+        self.detect_new_keyboards();
+        self.configure_keyboards();
+        self.configure_pointer_devices();
+
         trace!("forcing focus to first workspace");
         self.focus_workspace(&Selector::Index(0))?;
 
         self.run_hook(HookName::Startup);
+        self.run_startup_hooks();
         self.running = true;
+        let mut grabs_for_game_mode = false;
 
         trace!("entering main event loop");
+        self.clock_check = Some((Instant::now(), SystemTime::now()));
         while self.running {
-            match self.conn.wait_for_event() {
-                Ok(event) => {
+            let next = self.next_event();
+            if self.resumed_from_suspend() {
+                if let Err(e) = self.handle_event_action(
+                    EventAction::Resumed,
+                    Some(&mut key_bindings),
+                    Some(&mut mouse_bindings),
+                ) {
+                    self.handle_error(e);
+                }
+            }
+
+            match next {
+                Ok(Some(event)) => {
                     let span = span!(target: "penrose", Level::DEBUG, "XEvent", %event);
                     let _enter = span.enter();
                     trace!(details = ?event, "event details");
@@ -398,21 +660,86 @@ impl<X: XConn> WindowManager<X> {
                             Some(&mut key_bindings),
                             Some(&mut mouse_bindings),
                         ) {
-                            (self.error_handler)(e);
+                            self.handle_error(e);
                         }
                     }
+                }
+
+                Ok(None) => self.handle_timeouts(),
 
-                    self.run_hook(HookName::EventHandled);
-                    self.conn.flush();
+                Err(e) => {
+                    self.handle_error(PenroseError::X(e));
+                    continue;
                 }
+            }
 
-                Err(e) => (self.error_handler)(PenroseError::X(e)),
+            if self.game_mode != grabs_for_game_mode || self.key_chord_changed {
+                grabs_for_game_mode = self.game_mode;
+                self.key_chord_changed = false;
+                if let Err(e) = self.update_grabs(&key_bindings, &mouse_bindings) {
+                    self.handle_error(e);
+                }
             }
+
+            self.reap_autostarted();
+            self.run_hook(HookName::EventHandled);
+            self.conn.flush();
         }
 
         Ok(())
     }
 
+    // Wait for the next XEvent, returning None if a pending key chord, hot corner or wakeup times
+    // out before one arrives
+    fn next_event(&self) -> crate::core::xconnection::Result<Option<XEvent>> {
+        let deadline = match self.next_deadline() {
+            Some(deadline) => deadline,
+            None => return self.conn.wait_for_event().map(Some),
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_millis(0) {
+            return Ok(None);
+        }
+
+        self.conn.wait_for_event_timeout(remaining)
+    }
+
+    // Check whether the system has been suspended since the last time that we checked
+    fn resumed_from_suspend(&mut self) -> bool {
+        let now = (Instant::now(), SystemTime::now());
+        let suspended = match self.clock_check.replace(now) {
+            Some(last) => util::time_suspended(last, now),
+            None => return false,
+        };
+
+        suspended >= RESUME_CLOCK_JUMP
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        let chord = self.key_chord.as_ref().and_then(|&(_, deadline)| deadline);
+        let corner = self.hot_corners.deadline();
+
+        chord.into_iter().chain(corner).chain(self.wakeup).min()
+    }
+
+    fn handle_timeouts(&mut self) {
+        let now = Instant::now();
+
+        if matches!(self.key_chord, Some((_, Some(d))) if d <= now) {
+            trace!("key chord timed out");
+            self.end_key_chord();
+        }
+
+        if let Some(corner) = self.hot_corners.take_due(now) {
+            self.run_hot_corner(corner);
+        }
+
+        if matches!(self.wakeup, Some(w) if w <= now) {
+            self.wakeup = None;
+        }
+    }
+
     /*
      * Top Level EventAction handlers
      */
@@ -439,8 +766,7 @@ impl<X: XConn> WindowManager<X> {
                     if let Err(e) = self.conn.focus_client(root) {
                         warn!("unable to focus root window: {}", e);
                     }
-                    let active_window = Atom::NetActiveWindow.as_ref();
-                    self.conn.delete_prop(root, active_window)?;
+                    self.conn.set_active_client(None)?;
                     self.run_hook(HookName::FocusChange(root));
                     return Ok(());
                 }
@@ -448,16 +774,22 @@ impl<X: XConn> WindowManager<X> {
         };
 
         let prev = self.clients.set_focused(target, &self.conn);
+        self.set_urgent(target, false)?;
 
-        let (wix, accepts_focus) = {
-            // Safe to unwrap because we make sure this is a known client above
-            let c = self.clients.get(target).unwrap();
-            (c.workspace(), c.accepts_focus)
+        let (wix, model) = match self.clients.get(target) {
+            Some(c) => (c.workspace(), c.input_model()),
+            None => return Err(PenroseError::UnknownClient(target)),
         };
 
-        self.focus_screen(&Selector::Condition(&|s| s.wix == wix));
-        self.clients
-            .set_x_focus(target, accepts_focus, &self.conn)?;
+        self.set_active_screen(&Selector::Condition(&|s| s.wix == wix));
+        self.clients.set_x_focus(target, model, &self.conn)?;
+
+        // Dialogs should never end up hidden behind the window they belong to
+        for t in self.clients.transients_for(target) {
+            if tags::client_is_viewed(&self.clients, &self.screens, t) {
+                self.conn.raise_client(t)?;
+            }
+        }
 
         if let Some(ws) = self.workspaces.get_mut(wix) {
             ws.focus_client(target);
@@ -473,15 +805,36 @@ impl<X: XConn> WindowManager<X> {
         Ok(())
     }
 
-    // The given window ID has been destroyed so remove our internal state referencing it.
+    // The given window ID has been destroyed or withdrawn so remove our internal state referencing it.
     #[tracing::instrument(level = "trace", err, skip(self))]
     fn remove_client(&mut self, id: Xid) -> Result<()> {
+        if self.screens.remove_strut(id) {
+            self.layout_visible()?;
+        }
+
         if let Some(client) = self.clients.remove(id) {
             let wix = client.workspace();
+            let terminal = self.restore_swallowed(id, wix);
             self.workspaces.remove_client(wix, id);
+            self.update_workspace_labels()?;
 
-            if self.screens.visible_workspaces().contains(&wix) {
-                self.apply_layout(wix)?;
+            let visible = if self.config.tag_mode {
+                let viewed = self.screens.all_viewed_tags();
+                client.tags().iter().any(|t| viewed.contains(t))
+            } else {
+                self.screens.visible_workspaces().contains(&wix)
+            };
+
+            if visible {
+                if self.config.tag_mode {
+                    self.layout_visible()?;
+                } else {
+                    self.apply_layout(wix)?;
+                }
+                if let Some(terminal) = terminal {
+                    self.clients.map_if_needed(terminal, &self.conn)?;
+                    self.update_focus(terminal)?;
+                }
             }
 
             self.update_known_x_clients()?;
@@ -493,6 +846,51 @@ impl<X: XConn> WindowManager<X> {
         Ok(())
     }
 
+    // The terminal (and its workspace) that started the process owning a newly mapped client,
+    // if that terminal should be swallowed by it. See RuleAction::Terminal.
+    fn swallowing_terminal_for(&self, id: Xid) -> Option<(Xid, usize)> {
+        let pid = match self.clients.get(id) {
+            Some(c) if !c.terminal && !c.no_swallow => c.pid?,
+            _ => return None,
+        };
+
+        self.clients
+            .matching_clients(&Selector::Condition(&|c: &Client| {
+                c.terminal && c.wm_managed
+            }))
+            .into_iter()
+            .find(|t| matches!(t.pid, Some(tpid) if process::is_descendant_of(pid, tpid)))
+            .map(|t| (t.id(), t.workspace()))
+    }
+
+    // Hide 'terminal' and give its position in the layout to 'child' until 'child' is removed
+    fn swallow_terminal(&mut self, terminal: Xid, child: Xid, wix: usize) -> Result<()> {
+        debug!(terminal, child, "swallowing terminal");
+        self.clients.modify(child, |c| c.set_workspace(wix));
+        self.workspaces.replace_client(wix, terminal, child);
+        self.conn.set_client_workspace(child, wix)?;
+        self.clients.unmap_if_needed(terminal, &self.conn)?;
+        self.clients.swallow(terminal, child);
+        self.update_workspace_labels()?;
+        self.run_hook(HookName::ClientAddedToWorkspace(child, wix));
+
+        Ok(())
+    }
+
+    // Put back the terminal swallowed by 'child' (if there is one) in the position held by
+    // 'child' on workspace 'wix'. Workspace labels are updated by the caller once 'child' has
+    // been removed.
+    fn restore_swallowed(&mut self, child: Xid, wix: usize) -> Option<Xid> {
+        let mut terminal = self.clients.take_swallowed(child)?;
+        let id = terminal.id();
+        debug!(terminal = id, child, "restoring swallowed terminal");
+        terminal.set_workspace(wix);
+        self.workspaces.replace_client(wix, child, id);
+        self.clients.insert(id, terminal);
+
+        Some(id)
+    }
+
     #[tracing::instrument(level = "trace", err, skip(self))]
     fn move_client_to_workspace(&mut self, id: Xid, wix: usize) -> Result<()> {
         let current_wix = match self.clients.workspace_index_for_client(id) {
@@ -505,7 +903,7 @@ impl<X: XConn> WindowManager<X> {
             self.add_client_to_workspace(wix, id)?;
             self.clients.set_client_workspace(id, wix);
 
-            if self.screens.visible_workspaces().contains(&wix) {
+            if tags::client_is_viewed(&self.clients, &self.screens, id) {
                 let s = self.screens.focused();
                 self.conn.warp_cursor(Some(id), s)?;
             } else {
@@ -513,6 +911,12 @@ impl<X: XConn> WindowManager<X> {
             }
 
             self.layout_visible()?;
+
+            // Transient clients follow their parent (the parent is already on 'wix' by this point
+            // so cycles in WM_TRANSIENT_FOR can't recurse forever)
+            for t in self.clients.transients_for(id) {
+                self.move_client_to_workspace(t, wix)?;
+            }
         }
 
         Ok(())
@@ -534,7 +938,27 @@ impl<X: XConn> WindowManager<X> {
             .screens
             .update_known_screens(&self.conn, self.workspaces.len())?;
 
-        self.handle_event_actions(actions)
+        self.handle_event_actions(actions)?;
+        self.hot_corners.create_windows(&self.conn, &self.screens)
+    }
+
+    // The workspace that a new client should be placed on instead of 'wix' if it is already full
+    fn overflow_workspace_for(&self, wix: usize) -> Option<usize> {
+        let max = self.config.max_clients_per_workspace?;
+        let overflow = self.config.overflow_workspace?;
+
+        if wix == overflow || self.workspaces.get(wix)?.len() < max {
+            return None;
+        }
+        if overflow >= self.workspaces.len() {
+            warn!(
+                overflow,
+                "overflow workspace is invalid: not applying client cap"
+            );
+            return None;
+        }
+
+        Some(overflow)
     }
 
     // Map a new client window.
@@ -542,25 +966,56 @@ impl<X: XConn> WindowManager<X> {
     fn handle_map_request(&mut self, id: Xid) -> Result<()> {
         trace!(id, "handling map request");
         let classes = str_slice!(self.config.floating_classes);
-        let client = Client::new(&self.conn, id, self.screens.active_ws_index(), classes);
+        let mut client = Client::new(&self.conn, id, self.screens.active_ws_index(), classes);
         let is_managed_type = self.conn.is_managed_client(&client);
         trace!(id, ?client.wm_name, ?client.wm_class, ?client.wm_type, "client details");
 
-        // Run hooks to allow them to modify the client
-        self.clients.insert(id, client);
-        self.run_hook(HookName::NewClient(id));
+        // Dialogs open alongside the window they belong to rather than wherever focus currently is
+        let parent_wix = client
+            .transient_for
+            .and_then(|p| self.clients.workspace_index_for_client(p));
+        if let Some(wix) = parent_wix {
+            client.set_workspace(wix);
+        }
 
-        let details = self
-            .clients
-            .get(id)
-            .map(|c| (c.workspace(), c.wm_hints.clone(), c.wm_managed, c.floating));
+        let rule_actions = matching_actions(&self.config.rules, &client);
+        if rule_actions.contains(&RuleAction::Ignore) {
+            debug!(
+                id,
+                "client matched by an ignore rule: mapping without managing"
+            );
+            return Ok(self.conn.map_client(id)?);
+        }
 
-        if details.is_none() {
-            debug!(id, "Client was removed from the client map by a hook");
-            return Ok(());
+        let mut fullscreen = false;
+        for action in rule_actions.into_iter() {
+            trace!(id, ?action, "applying matching rule action");
+            match action {
+                RuleAction::Ignore => (),
+                RuleAction::ToWorkspace(wix) if wix < self.workspaces.len() => {
+                    client.set_workspace(wix)
+                }
+                RuleAction::ToWorkspace(wix) => warn!(id, wix, "rule target workspace is invalid"),
+                RuleAction::Float => client.set_floating(true),
+                RuleAction::Fullscreen => fullscreen = true,
+                RuleAction::Border(color) => self.clients.set_unfocused_border(id, color),
+                RuleAction::FocusedBorder(color) => self.clients.set_focused_border(id, color),
+                RuleAction::Terminal => client.terminal = true,
+                RuleAction::NoSwallow => client.no_swallow = true,
+            }
         }
 
-        let (wix, wm_hints, wm_managed, floating) = details.unwrap();
+        // Run hooks to allow them to modify the client
+        self.clients.insert(id, client);
+        self.run_hook(HookName::NewClient(id));
+
+        let (mut wix, wm_hints, wm_managed, floating) = match self.clients.get(id) {
+            Some(c) => (c.workspace(), c.wm_hints.clone(), c.wm_managed, c.floating),
+            None => {
+                debug!(id, "Client was removed from the client map by a hook");
+                return Ok(());
+            }
+        };
 
         if let Some(ref wmh) = wm_hints {
             if wmh.initial_state == WindowState::Withdrawn {
@@ -570,28 +1025,71 @@ impl<X: XConn> WindowManager<X> {
         }
 
         if !is_managed_type {
-            return Ok(self.conn.map_client(id)?);
+            self.conn.map_client(id)?;
+            // Docks may change the space they reserve while they are mapped (e.g. when a bar
+            // is reconfigured) so we need to see changes to their properties.
+            if matches!(self.clients.get(id), Some(c) if c.is_dock()) {
+                self.conn
+                    .set_client_attributes(id, &[ClientAttr::ClientEventMaskNoCrossing])?;
+            }
+            return self.update_strut(id);
         }
 
-        if wm_managed {
-            self.add_client_to_workspace(wix, id)?;
-        }
+        let swallowing = match (wm_managed, floating) {
+            (true, false) => self.swallowing_terminal_for(id),
+            _ => None,
+        };
 
-        if floating {
-            if let Some((_, s)) = self.screens.indexed_screen_for_workspace(wix) {
-                util::position_floating_client(
-                    &self.conn,
+        if let Some((terminal, terminal_wix)) = swallowing {
+            self.swallow_terminal(terminal, id, terminal_wix)?;
+            wix = terminal_wix;
+        } else if wm_managed {
+            if let Some(overflow) = self.overflow_workspace_for(wix) {
+                debug!(
                     id,
-                    s.region(self.config.show_bar),
-                    self.config.border_px,
-                )?
+                    wix, overflow, "workspace is full: sending client to overflow"
+                );
+                if let Some(c) = self.clients.get_mut(id) {
+                    c.set_workspace(overflow);
+                }
+                self.run_hook(HookName::WorkspaceOverflow(id, wix, overflow));
+                wix = overflow;
             }
+            self.add_client_to_workspace(wix, id)?;
         }
 
         self.conn.mark_new_client(id)?;
-        self.update_focus(id)?;
+        // Set up front so that border rules apply to clients that are not given focus
+        self.clients.reset_border(id, &self.conn)?;
+        // Clients can be urgent from the moment they are mapped (e.g. a terminal that rang its
+        // bell while starting up) rather than only after their hints change. This needs to be
+        // set before focusing the client so that taking focus clears it again.
+        if matches!(wm_hints, Some(ref h) if h.flags.contains(WmHintsFlags::URGENCY_HINT)) {
+            self.set_urgent(id, true)?;
+        }
+
+        // Clients placed on a workspace that is not being shown are left unmapped, so they can
+        // only take focus once that workspace is next displayed
+        if self.screens.visible_workspaces().contains(&wix) {
+            self.update_focus(id)?;
+        }
         self.update_known_x_clients()?;
 
+        if !tags::client_is_viewed(&self.clients, &self.screens, id) {
+            trace!(
+                id,
+                wix,
+                "client is hidden: deferring setup until it is shown"
+            );
+            self.clients.defer(id, |d| {
+                d.setup = true;
+                d.fullscreen = fullscreen;
+            });
+            return Ok(());
+        }
+
+        self.place_new_client(id, floating)?;
+
         if wix == self.screens.active_ws_index() {
             self.apply_layout(wix)?;
             self.clients.map_if_needed(id, &self.conn)?;
@@ -599,14 +1097,142 @@ impl<X: XConn> WindowManager<X> {
             self.conn.warp_cursor(Some(id), s)?;
         }
 
+        self.fullscreen_new_client(id, fullscreen)
+    }
+
+    // Position a newly mapped floating client and apply any state that it asked for before it
+    // was mapped.
+    fn place_new_client(&mut self, id: Xid, floating: bool) -> Result<()> {
+        if floating {
+            let parent = self
+                .clients
+                .get(id)
+                .and_then(|c| c.transient_for)
+                .filter(|&p| self.clients.is_known(p));
+            let border_px = self.border_px_for(id);
+            let screen = self
+                .clients
+                .workspace_index_for_client(id)
+                .and_then(|wix| self.screens.indexed_screen_for_workspace(wix));
+            match (parent, screen) {
+                (None, Some((_, s))) => {
+                    util::position_floating_client(&self.conn, id, s.region(true), border_px)?
+                }
+                // Parents can be on a workspace that is not currently shown so we fall back to
+                // keeping dialogs on the focused screen
+                (Some(p), s) => {
+                    let r = s
+                        .map_or_else(|| self.screens.focused(), |(_, s)| s)
+                        .region(true);
+                    util::position_transient_client(&self.conn, id, p, r, border_px)?
+                }
+                (None, None) => (),
+            }
+        }
+
+        // Clients can also ask to be shown on all workspaces before they are mapped
+        if self.conn.client_requests_sticky(id) {
+            self.set_sticky(id, true)?;
+        }
+
+        // Floating clients (e.g. file managers and browsers) can restore being maximized
+        let (horz, vert) = self.conn.client_requests_maximized(id);
+        if horz || vert {
+            self.set_maximized(id, horz, vert)?;
+        }
+
+        Ok(())
+    }
+
+    // Make a newly mapped client fullscreen if a rule asked for it, or if it wants to bypass the
+    // compositor and auto_fullscreen is set.
+    fn fullscreen_new_client(&mut self, id: Xid, fullscreen: bool) -> Result<()> {
+        if fullscreen {
+            self.set_fullscreen(id, true)
+        } else if self.config.auto_fullscreen && self.conn.client_requests_compositor_bypass(id) {
+            self.set_auto_fullscreen(id, true)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Carry out any work that was held back for the given clients while they were hidden. This
+    // needs to happen before they are mapped so that they are never shown out of place.
+    fn run_deferred(&mut self, ids: &[Xid]) -> Result<()> {
+        for &id in ids {
+            let work = match self.clients.take_deferred(id) {
+                Some(work) => work,
+                None => continue,
+            };
+            trace!(id, ?work, "running deferred client work");
+
+            if work.setup {
+                let floating = matches!(self.clients.get(id), Some(c) if c.floating);
+                self.place_new_client(id, floating)?;
+                self.fullscreen_new_client(id, work.fullscreen)?;
+            }
+            if let Some(r) = work.region {
+                self.handle_move_if_floating(id, r)?;
+            }
+            if work.name {
+                let action = self.clients.client_name_changed(id, false, &self.conn)?;
+                self.handle_event_action(action, None, None)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // A client that we are already tracking unmapped itself (e.g. minimising to a system tray)
+    // and is now asking to be shown again. Clients on hidden workspaces will be mapped when
+    // their workspace is next displayed.
+    fn handle_remap_request(&mut self, id: Xid) -> Result<()> {
+        let wix = match self.clients.get(id) {
+            Some(c) if c.minimized => return self.restore_minimized(id),
+            Some(c) if c.wm_managed => c.workspace(),
+            Some(_) => return Ok(self.conn.map_client(id)?),
+            None => return Ok(()),
+        };
+
+        if self.screens.visible_workspaces().contains(&wix) {
+            self.conn.map_client(id)?;
+            self.clients.modify(id, |c| c.mapped = true);
+            self.apply_layout(wix)?;
+        } else {
+            self.clients.modify(id, |c| c.mapped = false);
+        }
+
         Ok(())
     }
 
     fn handle_move_if_floating(&mut self, id: Xid, r: Region) -> Result<()> {
+        // Hidden floating clients are moved when they are next shown: only the most recent
+        // request matters by then
+        let floating = matches!(self.clients.get(id), Some(c) if c.floating);
+        if floating && !tags::client_is_viewed(&self.clients, &self.screens, id) {
+            self.clients.defer(id, |d| d.region = Some(r));
+            return Ok(());
+        }
+
+        // Floating clients that ask to cover their entire screen are treated as wanting to be
+        // fullscreen. Tiled clients regularly request their full screen size (e.g. when first
+        // mapped) without wanting anything of the sort.
+        let floating_or_auto = match self.clients.get(id) {
+            Some(c) => c.floating || self.clients.is_auto_fullscreen(id),
+            None => false,
+        };
+        let wix = self.clients.workspace_index_for_client(id);
+        if let Some((_, s)) = wix.and_then(|wix| self.screens.indexed_screen_for_workspace(wix)) {
+            if floating_or_auto {
+                let covers_screen = r == s.region(false);
+                self.set_auto_fullscreen(id, covers_screen)?;
+            }
+        }
+
         if let Some(client) = self.clients.get(id) {
             if client.floating {
                 debug!(id, region = ?r, "repositioning floating window");
-                let bpx = self.config.border_px;
+                let bpx = self.border_px_for(id);
                 self.conn.position_client(id, r, bpx, true)?;
             }
         }
@@ -618,57 +1244,368 @@ impl<X: XConn> WindowManager<X> {
         Ok(())
     }
 
+    // Automatically make a client fullscreen (raising it above any floating clients) or restore
+    // a client that was previously made fullscreen automatically. Clients that were made
+    // fullscreen explicitly are left alone.
+    fn set_auto_fullscreen(&mut self, id: Xid, should_fullscreen: bool) -> Result<()> {
+        if !self.config.auto_fullscreen {
+            return Ok(());
+        }
+
+        let currently_fullscreen = match self.clients.get(id) {
+            Some(c) => c.fullscreen,
+            None => return Ok(()),
+        };
+
+        if should_fullscreen && !currently_fullscreen {
+            let prev = self.conn.client_geometry(id).ok();
+            self.set_fullscreen(id, true)?;
+            if matches!(self.clients.get(id), Some(c) if c.fullscreen) {
+                debug!(id, "automatically making client fullscreen");
+                self.clients.mark_auto_fullscreen(id, prev);
+                self.conn.raise_client(id)?;
+            }
+        } else if !should_fullscreen && self.clients.is_auto_fullscreen(id) {
+            debug!(id, "restoring automatically fullscreened client");
+            self.set_fullscreen(id, false)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_hints_change(&mut self, id: Xid) -> Result<()> {
+        match self.clients.update_hints(id, &self.conn) {
+            Some(urgent) => self.set_urgent(id, urgent),
+            None => Ok(()),
+        }
+    }
+
+    fn set_urgent(&mut self, id: Xid, urgent: bool) -> Result<()> {
+        match self.clients.set_urgent(id, urgent, &self.conn) {
+            Some(action) => {
+                self.clients.update_states(id, &self.conn)?;
+                self.handle_event_action(action, None, None)
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Unmaps that we requested ourselves (hiding a workspace, scratchpads etc) leave the client
+    // under management. Anything else means that the client has withdrawn itself (closed to a
+    // tray, iconified etc) so we stop managing it until it requests to be mapped again.
     fn handle_unmap_notify(&mut self, id: Xid) -> Result<()> {
+        if self.clients.take_expected_unmap(id) || self.clients.is_swallowed(id) {
+            return Ok(());
+        }
+
+        if self.clients.is_known(id) {
+            self.remove_client(id)?;
+        } else if self.screens.remove_strut(id) {
+            self.layout_visible()?;
+        }
+
         Ok(self.conn.set_client_state(id, WindowState::Withdrawn)?)
     }
 
+    // Dock windows may reserve space at the edges of the root window which we need to leave
+    // clear when laying out clients
+    fn update_strut(&mut self, id: Xid) -> Result<()> {
+        let changed = match self.conn.client_strut(id) {
+            Some(strut) => self.screens.set_strut(id, strut),
+            None => self.screens.remove_strut(id),
+        };
+
+        if changed {
+            self.layout_visible()?;
+        }
+
+        Ok(())
+    }
+
     // NOTE: This defers control of the [WindowManager] to the user's key-binding action
     //       which can lead to arbitrary calls to public methods on the [WindowManager]
     //       including mutable methods.
     #[tracing::instrument(level = "debug", skip(self, k, bindings), fields(k.code, k.mask))]
     fn run_key_binding(&mut self, k: KeyCode, bindings: &mut KeyBindings<X>) {
-        if let Some(action) = bindings.get_mut(&k) {
-            // ignoring Child handlers and SIGCHILD
-            if let Err(e) = action(self) {
-                (self.error_handler)(e);
+        if let Some((chord, deadline)) = self.key_chord.clone() {
+            self.end_key_chord();
+            let timed_out = matches!(deadline, Some(d) if Instant::now() >= d);
+            if !timed_out {
+                // Bindings within the chord may enter another chord so it must be ended first
+                if let Some(res) = chord.run(self, k) {
+                    if let Err(e) = res {
+                        self.handle_error(e);
+                    }
+                    return;
+                }
             }
         }
-    }
 
-    // NOTE: This defers control of the [WindowManager] to the user's mouse-binding action
-    //       which can lead to arbitrary calls to public methods on the [WindowManager]
-    //       including mutable methods.
-    #[tracing::instrument(level = "debug", skip(self, e, bindings), fields(?e.state, ?e.kind))]
-    fn run_mouse_binding(&mut self, e: MouseEvent, bindings: &mut MouseBindings<X>) {
-        if let Some(action) = bindings.get_mut(&(e.kind, e.state.clone())) {
+        if let Some(action) = bindings.get_mut(&k) {
+            self.running_key = Some(k);
             // ignoring Child handlers and SIGCHILD
-            if let Err(e) = action(self, &e) {
-                (self.error_handler)(e);
+            let res = action(self);
+            self.running_key = None;
+            if let Err(e) = res {
+                self.handle_error(e);
             }
         }
     }
 
-    fn set_active_client(&mut self, id: Xid) -> Result<()> {
-        self.focus_client(&Selector::WinId(id))
-            .map_err(|_| PenroseError::UnknownClient(id))
-            .map(|_| ())
-    }
-
-    // Set the active [Screen] based on an (x, y) [Point]. If point is None then we set
-    // based on the current cursor position instead.
-    fn set_screen_from_point(&mut self, point: Option<Point>) -> Result<()> {
-        let point = match point {
-            Some(p) => p,
-            None => self.conn.cursor_position()?,
+    // Release all bindings other than the one used to toggle game mode while it is active and
+    // restore them once it is disabled again. This is also used to ensure that newly connected
+    // input devices pick up our grabs.
+    fn update_grabs(
+        &mut self,
+        key_bindings: &KeyBindings<X>,
+        mouse_bindings: &MouseBindings<X>,
+    ) -> Result<()> {
+        self.conn.ungrab_keys()?;
+
+        let no_mouse_bindings = HashMap::new();
+        let (codes, mouse_bindings): (Vec<KeyCode>, _) = match (self.game_mode_key, &self.key_chord)
+        {
+            (Some(k), _) if self.game_mode => (vec![k], &no_mouse_bindings),
+            // Top level bindings are left grabbed so that they can be used to cancel the chord
+            (_, Some((chord, _))) => (
+                key_bindings
+                    .keys()
+                    .copied()
+                    .chain(chord.key_codes())
+                    .collect(),
+                mouse_bindings,
+            ),
+            _ => (key_bindings.keys().copied().collect(), mouse_bindings),
         };
 
-        self.focus_screen(&Selector::Condition(&|s: &Screen| s.contains(point)));
-        Ok(())
+        // Bindings are grabbed on the keys currently holding the key names they were bound to
+        let to_grab: KeyBindings<X> = codes
+            .into_iter()
+            .flat_map(|k| self.key_remap.current(k))
+            .map(|k| (k, Box::new(|_: &mut Self| Ok(())) as KeyEventHandler<X>))
+            .collect();
+
+        Ok(self.conn.grab_keys(&to_grab, mouse_bindings)?)
     }
 
-    // Toggle the given client fullscreen. This has knock on effects for other windows and can
-    // be triggered by user key bindings as well as applications requesting full screen as well.
-    // TODO: should something going fullscreen also hide unmaged windows?
+    // Update the keyboards we know about, returning whether or not any of them are new. The
+    // keyboard config only needs re-applying when a keyboard is attached rather than whenever
+    // any input device is added or removed.
+    fn detect_new_keyboards(&mut self) -> bool {
+        if self.config.keyboard_config.is_none() {
+            return false;
+        }
+
+        let ids: Vec<u16> = match self.conn.keyboard_devices() {
+            Ok(keyboards) => keyboards.iter().map(|k| k.id).collect(),
+            Err(e) => {
+                warn!(
+                    "unable to list keyboards, re-applying keyboard config: {}",
+                    e
+                );
+                return true;
+            }
+        };
+
+        let added = ids.iter().any(|id| !self.known_keyboards.contains(id));
+        self.known_keyboards = ids;
+
+        added
+    }
+
+    // Apply any user keyboard layout and repeat config. Failures are reported individually so
+    // that one failing command does not prevent the others from running.
+    fn configure_keyboards(&mut self) {
+        let commands = match &self.config.keyboard_config {
+            Some(conf) => conf.commands(),
+            None => return,
+        };
+
+        for (cmd, args) in commands {
+            debug!(cmd, ?args, "configuring keyboards");
+            let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+            if let Err(e) = spawn_with_args(cmd, &args) {
+                self.handle_error(e);
+            }
+        }
+    }
+
+    // Apply any user pointer config to the matching input devices that are currently attached.
+    // Failures are reported for each device so that the remaining devices are still configured.
+    fn configure_pointer_devices(&mut self) {
+        if self.config.pointer_config.is_empty() {
+            return;
+        }
+
+        let devices = match self.conn.pointer_devices() {
+            Ok(devices) => devices,
+            Err(e) => return self.handle_error(e.into()),
+        };
+
+        let mut errors = vec![];
+        for device in devices.iter() {
+            for conf in self
+                .config
+                .pointer_config
+                .iter()
+                .filter(|c| c.matches(device))
+            {
+                debug!(id = device.id, name = %device.name, "configuring pointer device");
+                if let Err(e) = self.conn.configure_pointer_device(device, conf) {
+                    errors.push(e);
+                }
+            }
+        }
+
+        for e in errors {
+            self.handle_error(e.into());
+        }
+    }
+
+    // NOTE: This defers control of the [WindowManager] to the user's mouse-binding action
+    //       which can lead to arbitrary calls to public methods on the [WindowManager]
+    //       including mutable methods.
+    #[tracing::instrument(level = "debug", skip(self, e, bindings), fields(?e.state, ?e.kind))]
+    fn run_mouse_binding(&mut self, e: MouseEvent, bindings: &mut MouseBindings<X>) {
+        if let Some(action) = bindings.get_mut(&(e.kind, e.state.clone())) {
+            // ignoring Child handlers and SIGCHILD
+            if let Err(e) = action(self, &e) {
+                self.handle_error(e);
+            }
+        } else if e.kind == MouseEventKind::Press && self.run_pointer_binding(&e) {
+            // handled
+        } else if e.kind != MouseEventKind::Motion
+            && (e.id == self.conn.root() || self.hot_corners.is_input_window(e.id))
+        {
+            // edges without a binding should behave in the same way as the rest of the desktop
+            self.handle_root_click(e);
+        } else if e.kind != MouseEventKind::Motion && !self.clients.is_known(e.id) {
+            self.handle_window_click(e);
+        }
+    }
+
+    // Run the pointer binding for a press on the desktop or a screen edge, returning whether or
+    // not there was one.
+    fn run_pointer_binding(&mut self, e: &MouseEvent) -> bool {
+        let target = match self.hot_corners.edge_for(e.id) {
+            Some(corner) => PointerTarget::Edge(corner),
+            None if e.id == self.conn.root() && self.client_at_point(e.rpt).is_none() => {
+                PointerTarget::Desktop
+            }
+            None => return false,
+        };
+
+        // As with hot corners, bindings set by the handler replace the ones that were taken
+        let mut bindings = self.hot_corners.take_pointer_bindings();
+        let ran = match bindings.get_mut(&(target, e.state.clone())) {
+            Some(action) => {
+                if let Err(e) = action(self) {
+                    self.handle_error(e);
+                }
+                true
+            }
+            None => false,
+        };
+
+        if self.hot_corners.restore_pointer_bindings(bindings) {
+            if let Err(e) = self.hot_corners.create_windows(&self.conn, &self.screens) {
+                self.handle_error(e);
+            }
+        }
+
+        ran
+    }
+
+    // Presses on the root window that are not bindings are clicks on the desktop unless they
+    // were passed up to the root window from a client that doesn't handle button presses itself.
+    fn handle_root_click(&mut self, e: MouseEvent) {
+        if self.client_at_point(e.rpt).is_some() {
+            return;
+        }
+
+        match e.kind {
+            MouseEventKind::Press => {
+                self.run_hook(HookName::RootWindowClicked(e.state.button, e.rpt))
+            }
+            MouseEventKind::Release => {
+                self.run_hook(HookName::RootWindowReleased(e.state.button, e.rpt))
+            }
+            MouseEventKind::Motion => (),
+        }
+    }
+
+    // Button events are only selected on windows other than the root and clients by hooks that
+    // draw windows of their own, such as the status bar and tab bar
+    fn handle_window_click(&mut self, e: MouseEvent) {
+        match e.kind {
+            MouseEventKind::Press => {
+                self.run_hook(HookName::WindowClicked(e.id, e.state.button, e.rpt))
+            }
+            MouseEventKind::Release => {
+                self.run_hook(HookName::WindowReleased(e.id, e.state.button, e.rpt))
+            }
+            MouseEventKind::Motion => (),
+        }
+    }
+
+    fn run_gesture_binding(&mut self, g: Gesture) {
+        // Handlers need mutable access to the WindowManager so the bindings are moved out while
+        // one of them is running. Any bindings set by the handler are merged back in afterwards.
+        let mut bindings = std::mem::take(&mut self.gesture_bindings);
+        if let Some(action) = bindings.get_mut(&g) {
+            if let Err(e) = action(self) {
+                self.handle_error(e);
+            }
+        }
+        let set_by_handler = std::mem::replace(&mut self.gesture_bindings, bindings);
+        self.gesture_bindings.extend(set_by_handler);
+    }
+
+    fn run_hot_corner(&mut self, corner: HotCorner) {
+        if let Some(mut hot_corners) = self.hot_corners.take_hot_corners() {
+            if let Err(e) = hot_corners.run(self, corner) {
+                self.handle_error(e);
+            }
+            self.hot_corners.restore_hot_corners(hot_corners);
+        }
+    }
+
+    fn set_active_client(&mut self, id: Xid) -> Result<()> {
+        if matches!(self.clients.get(id), Some(c) if c.minimized) {
+            return self.restore_minimized(id);
+        }
+
+        self.focus_client(&Selector::WinId(id))
+            .map_err(|_| PenroseError::UnknownClient(id))
+            .map(|_| ())
+    }
+
+    // Set the active [Screen] based on an (x, y) [Point]. If point is None then we set
+    // based on the current cursor position instead.
+    fn set_screen_from_point(&mut self, point: Option<Point>) -> Result<()> {
+        let point = match point {
+            Some(p) => p,
+            None => self.conn.cursor_position()?,
+        };
+
+        self.set_active_screen(&Selector::Condition(&|s: &Screen| s.contains(point)));
+        Ok(())
+    }
+
+    // An X window has taken input focus: make sure that the screen showing it is marked as
+    // active so that subsequent actions are applied to the correct screen.
+    fn handle_focus_in(&mut self, id: Xid) -> Result<()> {
+        self.clients.focus_in(id, &self.conn)?;
+        if let Some(wix) = self.clients.workspace_index_for_client(id) {
+            self.set_active_screen(&Selector::Condition(&|s| s.wix == wix));
+        }
+
+        Ok(())
+    }
+
+    // Toggle the given client fullscreen. This has knock on effects for other windows and can
+    // be triggered by user key bindings as well as applications requesting full screen as well.
+    // TODO: should something going fullscreen also hide unmaged windows?
     fn set_fullscreen(&mut self, id: Xid, should_fullscreen: bool) -> Result<()> {
         let (currently_fullscreen, wix) = self
             .clients
@@ -690,7 +1627,103 @@ impl<X: XConn> WindowManager<X> {
             .clients
             .toggle_fullscreen(id, wix, &client_ids, r, &self.conn)?;
 
-        self.handle_event_actions(actions)
+        self.handle_event_actions(actions)?;
+
+        // Floating clients are not repositioned by their layout so we need to put them back
+        // where they were if they were made fullscreen automatically.
+        if !should_fullscreen {
+            let floating = matches!(self.clients.get(id), Some(c) if c.floating);
+            match self.clients.take_auto_fullscreen(id) {
+                Some(prev) if floating => {
+                    let bpx = self.border_px_for(id);
+                    self.conn.position_client(id, prev, bpx, true)?;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_sticky(&mut self, id: Xid, sticky: bool) -> Result<()> {
+        let wix = match self.clients.get(id) {
+            Some(c) if c.wm_managed && c.sticky != sticky => c.workspace(),
+            _ => return Ok(()),
+        };
+
+        // Sticky clients float so that they keep their position when carried between workspaces
+        self.clients.modify(id, |c| {
+            c.sticky = sticky;
+            if sticky {
+                c.set_floating(true);
+            }
+        });
+
+        if sticky {
+            self.conn.set_client_on_all_workspaces(id)?;
+        } else {
+            self.conn.set_client_workspace(id, wix)?;
+        }
+
+        self.clients.update_states(id, &self.conn)?;
+
+        self.apply_layout(wix)
+    }
+
+    // Stretch a floating client to fill the width and / or height of its screen, returning it to
+    // the geometry it had before once it is no longer maximized along either axis. Tiled clients
+    // are positioned by their layout so requests from them are ignored.
+    fn set_maximized(&mut self, id: Xid, horz: bool, vert: bool) -> Result<()> {
+        let wix = match self.clients.get(id) {
+            Some(c) if c.maximized_horz == horz && c.maximized_vert == vert => return Ok(()),
+            Some(c) if c.wm_managed && c.floating && !c.fullscreen => c.workspace(),
+            _ => return Ok(()),
+        };
+
+        let screen = match self.screen(&Selector::Condition(&|s| s.wix == wix)) {
+            Some(s) => s.region(true),
+            None => return Ok(()),
+        };
+
+        let r = if horz || vert {
+            let prev = self.conn.client_geometry(id)?;
+            let prev = self.clients.mark_unmaximized(id, prev);
+            util::maximized_region(prev, screen, self.border_px_for(id), horz, vert)
+        } else {
+            match self.clients.take_unmaximized(id) {
+                Some(prev) => prev,
+                None => self.conn.client_geometry(id)?,
+            }
+        };
+
+        self.clients.modify(id, |c| {
+            c.maximized_horz = horz;
+            c.maximized_vert = vert;
+        });
+        self.clients.update_states(id, &self.conn)?;
+
+        self.position_client(id, r, true)
+    }
+
+    // Move the sticky clients on each 'from' workspace to the matching 'to' workspace so that they
+    // stay on the same screen when the workspaces being shown change
+    fn carry_sticky_clients(&mut self, moves: &[(usize, usize)]) -> Result<()> {
+        let mut to_move = vec![];
+        for &(from, to) in moves {
+            for id in self.workspaces.client_ids(from)? {
+                if matches!(self.clients.get(id), Some(c) if c.sticky) {
+                    to_move.push((id, from, to));
+                }
+            }
+        }
+
+        for (id, from, to) in to_move {
+            self.workspaces.remove_client(from, id);
+            self.add_client_to_workspace(to, id)?;
+            self.conn.set_client_on_all_workspaces(id)?;
+        }
+
+        Ok(())
     }
 
     /*
@@ -699,26 +1732,105 @@ impl<X: XConn> WindowManager<X> {
 
     #[tracing::instrument(level = "debug", err, skip(self))]
     fn apply_layout(&mut self, wix: usize) -> Result<()> {
-        let (i, s) = match self.screens.indexed_screen_for_workspace(wix) {
-            Some(index_and_screen) => index_and_screen,
+        let i = match self.screens.indexed_screen_for_workspace(wix) {
+            Some((i, _)) => i,
+            // in tag mode the workspace may be being viewed alongside another one
+            None if self.config.tag_mode => match self.screens.screen_viewing_tag(wix) {
+                Some(s) => return self.apply_layout(s.wix),
+                None => return Ok(()),
+            },
             None => return Ok(()), // workspace is not currently visible
         };
 
-        let region = s.region(self.config.show_bar);
-        let clients = self.clients.clients_for_workspace(wix);
-        let (lc, arrange_actions) = self.workspaces.get_arrange_actions(wix, region, &clients)?;
-        self.clients.apply_arrange_actions(
-            arrange_actions,
-            &lc,
-            self.config.border_px,
-            self.config.gap_px,
-            &self.conn,
-        )?;
+        let ids: Vec<Xid> = self
+            .clients_for_screen(wix, i)
+            .iter()
+            .map(|c| c.id())
+            .collect();
+        self.run_deferred(&ids)?;
+
+        let s = &self.screens.inner[i];
+        let region = s.region(true);
+        let clients = self.clients_for_screen(wix, i);
+        let has_fullscreen = clients.iter().any(|c| c.fullscreen);
+        let spacing = self.spacing(s);
+        let (_, arrange_actions) = self
+            .workspaces
+            .get_arrange_actions(wix, region, &spacing, &clients)?;
+        self.clients
+            .apply_arrange_actions(arrange_actions, spacing.border_px, &self.conn)?;
+
+        if !has_fullscreen {
+            self.hot_corners.raise_windows(&self.conn, i)?;
+        }
 
         self.run_hook(HookName::LayoutApplied(wix, i));
         Ok(())
     }
 
+    // The clients laid out on screen 'i' while it is showing workspace 'wix'
+    fn clients_for_screen(&self, wix: usize, i: usize) -> Vec<&Client> {
+        if self.config.tag_mode {
+            tags::clients_for_screen(&self.workspaces, &self.clients, &self.screens, i)
+        } else {
+            self.clients.clients_for_workspace(wix)
+        }
+    }
+
+    // Map or unmap workspace clients based on whether or not any of their tags are currently
+    // being viewed before re-applying the layout on each screen
+    fn sync_tag_visibility(&mut self) -> Result<()> {
+        let ids: Vec<Xid> = (0..self.screens.n_screens())
+            .flat_map(|i| {
+                tags::clients_for_screen(&self.workspaces, &self.clients, &self.screens, i)
+            })
+            .map(|c| c.id())
+            .collect();
+        self.run_deferred(&ids)?;
+
+        tags::sync_visibility(
+            &self.conn,
+            &self.workspaces,
+            &mut self.clients,
+            &self.screens,
+        )?;
+        self.layout_visible()
+    }
+
+    // Windows being mapped, unmapped and moved under the pointer generate enter events that
+    // would otherwise shift focus to whichever client happens to pass under the pointer. Event
+    // masks are always restored, even if 'f' fails.
+    fn without_crossing_events<F>(&mut self, ids: &[Xid], f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        self.conn
+            .set_clients_attributes(ids, &[ClientAttr::ClientEventMaskNoCrossing])?;
+        let res = f(self);
+        self.conn
+            .set_clients_attributes(ids, &[ClientAttr::ClientEventMask])?;
+
+        res
+    }
+
+    // Border and gap sizes for clients shown on the given screen
+    fn spacing(&self, s: &Screen) -> Spacing {
+        Spacing::new(s.scale(self.config.border_px), s.scale(self.config.gap_px))
+    }
+
+    // The border width of a client, scaled for the screen showing its workspace (if any)
+    fn border_px_for(&self, id: Xid) -> u32 {
+        let screen = self
+            .clients
+            .workspace_index_for_client(id)
+            .and_then(|wix| self.screens.indexed_screen_for_workspace(wix));
+
+        match screen {
+            Some((_, s)) => s.scale(self.config.border_px),
+            None => self.config.border_px,
+        }
+    }
+
     fn update_x_workspace_details(&mut self) -> Result<()> {
         let names = self.workspaces.workspace_names();
         self.conn.update_desktops(&names)?;
@@ -730,15 +1842,75 @@ impl<X: XConn> WindowManager<X> {
         Ok(())
     }
 
+    // Label each workspace with its most common client class when auto_name_workspaces is set,
+    // updating the X desktop names if any of the labels have changed.
+    fn update_workspace_labels(&mut self) -> Result<()> {
+        if !self.config.auto_name_workspaces {
+            return Ok(());
+        }
+
+        let mut changed = false;
+        for wix in 0..self.workspaces.len() {
+            let label = self.dominant_class(wix).map(|class| {
+                match self.config.workspace_class_names.get(&class) {
+                    Some(label) => label.clone(),
+                    None => class,
+                }
+            });
+            changed |= self.workspaces.set_workspace_label(wix, label);
+        }
+
+        if changed {
+            self.update_x_workspace_details()?;
+        }
+
+        Ok(())
+    }
+
+    // The most common WM_CLASS of the clients on a workspace, with ties going to the client that
+    // is highest in the stack
+    fn dominant_class(&self, wix: usize) -> Option<String> {
+        let classes: Vec<&str> = self
+            .workspaces
+            .client_ids(wix)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|id| self.clients.get(id))
+            .map(|c| c.wm_class())
+            .collect();
+
+        classes
+            .iter()
+            .rev()
+            .max_by_key(|&class| classes.iter().filter(|&c| c == class).count())
+            .map(|class| class.to_string())
+    }
+
+    // Update all internal references to workspace indices after workspaces have been added or
+    // removed, along with the _NET_WM_DESKTOP property of any clients that have moved.
+    fn reindex_workspaces(&mut self, f: impl Fn(usize) -> usize) -> Result<()> {
+        for (id, wix) in self.clients.reindex_workspaces(&f) {
+            self.conn.set_client_workspace(id, wix)?;
+        }
+        self.screens.reindex_workspaces(&f);
+        self.previous_workspace = f(self.previous_workspace);
+        self.config.overflow_workspace = self.config.overflow_workspace.map(&f);
+
+        Ok(())
+    }
+
     fn update_known_x_clients(&self) -> Result<()> {
         let ids = self.clients.all_known_ids();
         Ok(self.conn.update_known_clients(&ids)?)
     }
 
-    fn focus_screen(&mut self, sel: &Selector<'_, Screen>) -> &Screen {
+    fn set_active_screen(&mut self, sel: &Selector<'_, Screen>) -> &Screen {
         let actions = self.screens.focus_screen(sel);
         if let Err(e) = self.handle_event_actions(actions) {
-            (self.error_handler)(e);
+            self.handle_error(e);
+        }
+        if let Err(e) = self.update_root_background() {
+            self.handle_error(e);
         }
 
         self.screens.focused()
@@ -752,7 +1924,7 @@ impl<X: XConn> WindowManager<X> {
             self.handle_event_action(action, None, None)?;
         }
 
-        Ok(())
+        self.update_workspace_labels()
     }
 
     /*
@@ -790,9 +1962,67 @@ impl<X: XConn> WindowManager<X> {
     }
 
     /// Cycle between known [screens][Screen]. Does not wrap from first to last
+    ///
+    /// The pointer is moved as set by [warp_on_screen_change][1].
+    ///
+    /// [1]: crate::core::config::Config::warp_on_screen_change
     pub fn cycle_screen(&mut self, direction: Direction) -> Result<()> {
-        let actions = self.screens.cycle_screen(direction, &self.conn)?;
-        self.handle_event_actions(actions)
+        let actions = self.screens.cycle_screen(direction);
+        self.change_screen(actions)
+    }
+
+    /// Move focus to the next [Screen] in 'direction', wrapping from the last screen back to the
+    /// first (and vice versa). The pointer is moved as set by [warp_on_screen_change][1].
+    ///
+    /// [1]: crate::core::config::Config::warp_on_screen_change
+    pub fn focus_screen(&mut self, direction: Direction) -> Result<()> {
+        let actions = self.screens.focus_in_direction(direction);
+        self.change_screen(actions)
+    }
+
+    fn change_screen(&mut self, actions: Vec<EventAction>) -> Result<()> {
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        self.handle_event_actions(actions)?;
+        self.warp_pointer(self.config.warp_on_screen_change)
+    }
+
+    // Set the root window to the background color of the focused workspace if it has one. There
+    // is only one root window so the workspaces shown on other screens have no effect. Images are
+    // drawn by the Wallpapers hook as they need a Draw.
+    fn update_root_background(&mut self) -> Result<()> {
+        let background = self
+            .workspaces
+            .get(self.screens.active_ws_index())
+            .and_then(|ws| ws.background());
+
+        match background {
+            Some(&Background::Color(color)) if self.root_background != Some(color) => {
+                self.conn.set_window_background(self.conn.root(), color)?;
+                self.root_background = Some(color);
+            }
+            Some(Background::Image(_)) => self.root_background = None,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    // Move the pointer following a change of focus made using the keyboard
+    fn warp_pointer(&self, warp: PointerWarp) -> Result<()> {
+        let screen = self.screens.focused();
+        let id = match warp {
+            PointerWarp::Never => return Ok(()),
+            PointerWarp::ToScreen => None,
+            PointerWarp::ToClient => self
+                .workspaces
+                .get(self.screens.active_ws_index())
+                .and_then(|ws| ws.focused_client()),
+        };
+
+        Ok(self.conn.warp_cursor(id, screen)?)
     }
 
     /// Cycle between [workspaces][1] on the current [screen][2].
@@ -816,20 +2046,58 @@ impl<X: XConn> WindowManager<X> {
 
     /// Cycle focus between [clients][1] for the active [Workspace]
     ///
+    /// When running in [tag mode][2], focus cycles between all of the clients shown on the active
+    /// [Screen] for the tags that it is viewing.
+    ///
     /// [1]: Client
+    /// [2]: crate::core::config::Config::tag_mode
     pub fn cycle_client(&mut self, direction: Direction) -> Result<()> {
+        if self.config.tag_mode {
+            return self.cycle_tagged_client(direction);
+        }
+
         let wix = self.screens.active_ws_index();
         let res = self.workspaces.cycle_client(wix, direction);
         if let Some((prev, new)) = res {
             self.clients.client_lost_focus(prev, &self.conn);
             self.update_focus(new)?;
-            let screen = self.screens.focused();
-            self.conn.warp_cursor(Some(new), screen)?;
+            self.warp_pointer(self.config.warp_on_client_change)?;
         }
 
         Ok(())
     }
 
+    fn cycle_tagged_client(&mut self, direction: Direction) -> Result<()> {
+        let i = self.screens.active_screen_index();
+        let shown = tags::clients_for_screen(&self.workspaces, &self.clients, &self.screens, i);
+        let ids: Vec<Xid> = shown.iter().map(|c| c.id()).collect();
+        let focused = self.focused_client_id();
+        let current = match ids.iter().position(|&id| Some(id) == focused) {
+            Some(ix) => ix,
+            None => return Ok(()),
+        };
+        if ids.len() < 2 {
+            return Ok(());
+        }
+
+        let next = match direction {
+            Direction::Forward => (current + 1) % ids.len(),
+            Direction::Backward => (current + ids.len() - 1) % ids.len(),
+        };
+        if let Some(prev) = focused {
+            self.clients.client_lost_focus(prev, &self.conn);
+        }
+        self.update_focus(ids[next])?;
+        match self.config.warp_on_client_change {
+            // The client may be shown through a tag rather than being on the active workspace
+            PointerWarp::ToClient => {
+                let screen = self.screens.focused();
+                Ok(self.conn.warp_cursor(Some(ids[next]), screen)?)
+            }
+            warp => self.warp_pointer(warp),
+        }
+    }
+
     /// Focus the [Client] matching the given [Selector]
     pub fn focus_client(&mut self, selector: &Selector<'_, Client>) -> Result<Xid> {
         let id = match self.client(selector) {
@@ -837,35 +2105,259 @@ impl<X: XConn> WindowManager<X> {
             None => return Err(PenroseError::NoMatchingElement),
         };
         self.update_focus(id)?;
-        let screen = self.screens.focused();
-        self.conn.warp_cursor(Some(id), screen)?;
+        self.warp_pointer(self.config.warp_on_client_change)?;
         Ok(id)
     }
 
-    /// Rotate the [Client] stack on the active [Workspace].
-    ///
-    /// This maintains the current window layout but permutes the positions of each window within
-    /// that layout.
-    pub fn rotate_clients(&mut self, direction: Direction) -> Result<()> {
-        let wix = self.screens.active_ws_index();
-        self.workspaces.rotate_clients(wix, direction);
-        self.apply_layout(wix)
+    /// The IDs of all [clients][Client] ordered from most to least recently focused.
+    pub fn focus_history(&self) -> Vec<Xid> {
+        self.clients.focus_history().to_vec()
     }
 
-    /// Move the focused [Client] through the stack of clients on the active [Workspace].
-    pub fn drag_client(&mut self, direction: Direction) -> Result<()> {
-        if let Some(id) = self.clients.focused_client_id() {
+    /// Step through recently focused clients in the style of alt-tab.
+    ///
+    /// When bound to a key binding that uses modifiers (e.g. `A-Tab`), the first call starts a
+    /// cycle and each repeated press steps one client further back through the focus history.
+    /// The focus history itself is only updated once all modifiers other than shift have been
+    /// released, so that holding the modifier and pressing the key once always flips between the
+    /// two most recently focused clients. If 'all_workspaces' is false then only clients on the
+    /// active [Workspace] are included, otherwise workspaces are switched as needed.
+    pub fn cycle_focus_mru(&mut self, direction: Direction, all_workspaces: bool) -> Result<()> {
+        if self.mru_cycle.is_none() {
+            let history = self.clients.focus_history().to_vec();
             let wix = self.screens.active_ws_index();
-            self.workspaces.drag_client(wix, direction);
-            self.apply_layout(wix)?;
-            self.update_focus(id)?;
-            self.conn.warp_cursor(Some(id), self.screens.focused())?;
+            let candidates: Vec<Xid> = history
+                .iter()
+                .copied()
+                .filter(|&id| {
+                    all_workspaces
+                        || matches!(self.clients.get(id), Some(c) if c.workspace() == wix)
+                })
+                .collect();
+
+            if candidates.len() < 2 {
+                return Ok(());
+            }
+
+            let mask = self.running_key.map(|k| k.mask & !SHIFT_MASK).unwrap_or(0);
+            if mask != 0 {
+                // needed to see the modifiers being released
+                self.conn.grab_keyboard()?;
+            }
+
+            self.mru_cycle = Some(MruCycle {
+                history,
+                candidates,
+                index: 0,
+                mask,
+            });
+        }
+
+        let (id, mask) = match self.mru_cycle.as_mut() {
+            Some(cycle) => {
+                let n = cycle.candidates.len();
+                cycle.index = match direction {
+                    Direction::Forward => (cycle.index + 1) % n,
+                    Direction::Backward => (cycle.index + n - 1) % n,
+                };
+                (cycle.candidates[cycle.index], cycle.mask)
+            }
+            None => return Ok(()),
+        };
+
+        if let Some(wix) = self.clients.get(id).map(|c| c.workspace()) {
+            if self.screens.indexed_screen_for_workspace(wix).is_none() {
+                self.focus_workspace(&Selector::Index(wix))?;
+            }
+            self.focus_client(&Selector::WinId(id))?;
+        }
+
+        if mask == 0 {
+            self.end_mru_cycle()?;
         }
 
         Ok(())
     }
 
-    /// Cycle between [layouts][1] for the active [Workspace]
+    fn handle_key_release(&mut self) -> Result<()> {
+        let mask = match &self.mru_cycle {
+            Some(cycle) => cycle.mask,
+            None => return Ok(()),
+        };
+
+        if self.conn.modifier_state()? & mask == 0 {
+            self.end_mru_cycle()?;
+        }
+
+        Ok(())
+    }
+
+    fn end_mru_cycle(&mut self) -> Result<()> {
+        if let Some(cycle) = self.mru_cycle.take() {
+            self.clients.restore_focus_history(cycle.history);
+            if cycle.mask != 0 {
+                self.conn.ungrab_keyboard()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rotate the [Client] stack on the active [Workspace].
+    ///
+    /// This maintains the current window layout but permutes the positions of each window within
+    /// that layout.
+    pub fn rotate_clients(&mut self, direction: Direction) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        self.workspaces.rotate_clients(wix, direction);
+        self.apply_layout(wix)
+    }
+
+    /// Move the focused [Client] through the stack of clients on the active [Workspace].
+    pub fn drag_client(&mut self, direction: Direction) -> Result<()> {
+        if let Some(id) = self.clients.focused_client_id() {
+            let wix = self.screens.active_ws_index();
+            self.workspaces.drag_client(wix, direction);
+            self.apply_layout(wix)?;
+            self.update_focus(id)?;
+            self.warp_pointer(self.config.warp_on_client_change)?;
+        }
+
+        Ok(())
+    }
+
+    /// Swap the focused [Client] with its neighbour in the stack of clients on the active
+    /// [Workspace], retaining focus on the swapped client.
+    pub fn swap_client(&mut self, direction: Direction) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        if let Some(id) = self.workspaces.swap_client(wix, direction) {
+            self.apply_layout(wix)?;
+            self.update_focus(id)?;
+            self.warp_pointer(self.config.warp_on_client_change)?;
+        }
+
+        Ok(())
+    }
+
+    /// Move the focused [Client] to the head of the stack on the active [Workspace] so that it
+    /// is placed in the main area of the current layout. If the focused client is already at the
+    /// head of the stack then it is swapped with the next client in the stack.
+    pub fn promote_focused(&mut self) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        if let Some(id) = self.workspaces.promote_focused(wix) {
+            self.apply_layout(wix)?;
+            self.update_focus(id)?;
+            self.warp_pointer(self.config.warp_on_client_change)?;
+        }
+
+        Ok(())
+    }
+
+    /// Swap the positions of two [Client]s in the stack of the same [Workspace], retaining focus
+    /// on the first client.
+    ///
+    /// This is a no-op if the clients are not both on the same workspace.
+    pub fn swap_clients(&mut self, a: Xid, b: Xid) -> Result<()> {
+        let wix = match (
+            self.clients.workspace_index_for_client(a),
+            self.clients.workspace_index_for_client(b),
+        ) {
+            (Some(i), Some(j)) if i == j => i,
+            _ => return Ok(()),
+        };
+
+        if self.workspaces.swap_clients(wix, a, b) {
+            self.apply_layout(wix)?;
+            self.update_focus(a)?;
+        }
+
+        Ok(())
+    }
+
+    /// Move focus to the tiled [Client] next to the focused client on screen in the given
+    /// direction.
+    ///
+    /// Neighbours are found using where each client was placed the last time that the layout for
+    /// the active [Workspace] was applied. Floating clients are ignored.
+    pub fn focus_in_direction(&mut self, direction: RelativePosition) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let ws = self.workspaces.get_workspace(wix)?;
+        let id = match ws.focused_client() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let candidates = ws.client_ids();
+        if let Some(other) = self.clients.tiled_neighbour(id, &candidates, direction) {
+            self.focus_client(&Selector::WinId(other))?;
+        }
+
+        Ok(())
+    }
+
+    /// Swap the focused [Client] with the tiled client next to it on screen in the given
+    /// direction, retaining focus on the focused client.
+    ///
+    /// Neighbours are found using where each client was placed the last time that the layout for
+    /// the active [Workspace] was applied. Floating clients are ignored.
+    pub fn swap_in_direction(&mut self, direction: RelativePosition) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let ws = self.workspaces.get_workspace(wix)?;
+        let id = match ws.focused_client() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let candidates = ws.client_ids();
+        if let Some(other) = self.clients.tiled_neighbour(id, &candidates, direction) {
+            self.swap_clients(id, other)?;
+            self.warp_pointer(self.config.warp_on_client_change)?;
+        }
+
+        Ok(())
+    }
+
+    /// Grow or shrink the focused [Client] by roughly `px` pixels, taking space from (or giving
+    /// space to) the tiled clients that share its column or row.
+    ///
+    /// This sets the [weight][Client::weight] of the focused client, so it only has an effect on
+    /// layouts that size clients by weight such as [side_stack][1] and [bottom_stack][2]. Columns
+    /// and rows are found using where each client was placed the last time that the layout for
+    /// the active [Workspace] was applied. Use [reset_client_weights][3] to size clients evenly
+    /// again.
+    ///
+    /// [1]: crate::core::layout::side_stack
+    /// [2]: crate::core::layout::bottom_stack
+    /// [3]: WindowManager::reset_client_weights
+    pub fn grow_focused(&mut self, change: Change, px: u32) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let ws = self.workspaces.get_workspace(wix)?;
+        let id = match ws.focused_client() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let candidates = ws.client_ids();
+        if self.clients.resize_tiled(id, &candidates, change, px) {
+            self.apply_layout(wix)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reset the [weight][Client::weight] of every [Client] on the active [Workspace], undoing
+    /// any changes made using [grow_focused][WindowManager::grow_focused].
+    pub fn reset_client_weights(&mut self) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        for id in self.workspaces.get_workspace(wix)?.client_ids() {
+            self.clients
+                .modify(id, |c| c.set_weight(DEFAULT_CLIENT_WEIGHT));
+        }
+
+        self.apply_layout(wix)
+    }
+
+    /// Cycle between [layouts][1] for the active [Workspace]
     ///
     /// [1]: crate::core::layout::Layout
     pub fn cycle_layout(&mut self, direction: Direction) -> Result<()> {
@@ -894,9 +2386,101 @@ impl<X: XConn> WindowManager<X> {
     ///
     /// [1]: crate::core::layout::Layout
     pub fn update_main_ratio(&mut self, change: Change) -> Result<()> {
+        self.update_main_ratio_by(change, self.workspaces.main_ratio_step())
+    }
+
+    /// Increase or decrease the current [layout][1] main_ratio by `step` rather than the
+    /// configured `main_ratio_step`.
+    ///
+    /// This is useful for finer grained control such as resizing with the scroll wheel: see
+    /// [scroll_main_ratio][2].
+    ///
+    /// [1]: crate::core::layout::Layout
+    /// [2]: crate::contrib::actions::scroll_main_ratio
+    pub fn update_main_ratio_by(&mut self, change: Change, step: f32) -> Result<()> {
         let wix = self.screens.active_ws_index();
-        self.workspaces.update_main_ratio(wix, change);
-        self.apply_layout(wix)
+        self.workspaces.update_main_ratio_by(wix, change, step);
+        self.apply_layout(wix)?;
+        self.run_hook(HookName::MainRatioChanged(wix));
+
+        Ok(())
+    }
+
+    /// Wake up the main event loop within `delay`, running [event_handled][1] hooks even if no
+    /// [XEvents][2] have arrived in the meantime.
+    ///
+    /// This allows hooks to carry out time based actions such as hiding a window after a timeout.
+    ///
+    /// [1]: crate::core::hooks::Hook::event_handled
+    /// [2]: crate::core::xconnection::XEvent
+    pub fn wake_after(&mut self, delay: Duration) {
+        let at = Instant::now() + delay;
+        self.wakeup = Some(match self.wakeup {
+            Some(current) if current < at => current,
+            _ => at,
+        });
+    }
+
+    /// Show or hide the status bars, laying out the visible workspaces again so that clients
+    /// make use of the space that was reserved for the bars while they are hidden.
+    ///
+    /// Hooks are notified of the change through [bar_visibility_changed][1] so that bars can
+    /// show or hide their windows to match.
+    ///
+    /// [1]: crate::core::hooks::Hook::bar_visibility_changed
+    pub fn set_bar_visible(&mut self, visible: bool) -> Result<()> {
+        if self.screens.bars_visible() == visible {
+            return Ok(());
+        }
+
+        info!(visible, "setting status bar visibility");
+        if self.screens.set_bars_visible(visible) {
+            self.layout_visible()?;
+        }
+        self.run_hook(HookName::BarVisibilityChanged(visible));
+
+        Ok(())
+    }
+
+    /// Show the status bars if they are hidden, or hide them if they are shown.
+    pub fn toggle_bar(&mut self) -> Result<()> {
+        self.set_bar_visible(!self.screens.bars_visible())
+    }
+
+    /// Whether or not the status bars are currently shown
+    pub fn bar_visible(&self) -> bool {
+        self.screens.bars_visible()
+    }
+
+    /// Toggle game mode, having penrose get out of the way of games and other programs that need
+    /// full control of user input.
+    ///
+    /// While game mode is active:
+    ///   - All key and mouse bindings are released other than the key binding used to enable game
+    ///     mode (if it was enabled from a key binding) so that it can be used to disable it again.
+    ///   - Focus no longer follows the mouse.
+    ///   - Clients requesting focus are marked as urgent rather than being focused.
+    ///   - Hooks can check [game_mode][WindowManager::game_mode] to pause their own behaviour: the
+    ///     [Compositor][1] extension stops the compositor for example.
+    ///
+    /// Disabling game mode restores all bindings and the behaviour above.
+    ///
+    /// [1]: crate::contrib::extensions::Compositor
+    pub fn toggle_game_mode(&mut self) -> Result<()> {
+        self.game_mode = !self.game_mode;
+        self.game_mode_key = if self.game_mode {
+            self.running_key
+        } else {
+            None
+        };
+        info!(game_mode = self.game_mode, "toggled game mode");
+
+        Ok(())
+    }
+
+    /// Whether or not game mode is currently active
+    pub fn game_mode(&self) -> bool {
+        self.game_mode
     }
 
     /// Shut down the WindowManager, running any required cleanup and exiting penrose
@@ -904,6 +2488,13 @@ impl<X: XConn> WindowManager<X> {
     /// **NOTE**: any registered hooks on the `WindowManager` will still run following calling this
     /// method, with the actual exit condition being checked and handled at the end.
     pub fn exit(&mut self) -> Result<()> {
+        for mut hook in std::mem::take(&mut self.shutdown_hooks) {
+            if let Err(e) = hook(self) {
+                self.handle_error(e);
+            }
+        }
+        self.stop_autostarted();
+
         self.conn.cleanup()?;
         self.conn.flush();
         self.running = false;
@@ -911,6 +2502,159 @@ impl<X: XConn> WindowManager<X> {
         Ok(())
     }
 
+    /// Register an action to run once when penrose starts up, after all [Hook][1]s have run
+    /// their `startup` trigger but before the main event loop begins.
+    ///
+    /// This is the place to launch programs that need the window manager to be running, such as
+    /// compositors, status bars and wallpaper setters. Errors are passed to the [ErrorHandler]
+    /// without preventing penrose from starting.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use penrose::__test_helpers::*;
+    /// use penrose::core::process::spawn;
+    ///
+    /// # fn example<X: XConn + 'static>(mut wm: WindowManager<X>) {
+    /// wm.startup_hook(|_| spawn("feh --bg-fill /home/me/wallpaper.png"));
+    /// wm.startup_hook(|wm| wm.set_root_window_name("penrose"));
+    /// # }
+    /// ```
+    ///
+    /// [1]: crate::core::hooks::Hook
+    pub fn startup_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&mut WindowManager<X>) -> Result<()> + 'static,
+    {
+        self.startup_hooks.push(Box::new(hook));
+    }
+
+    /// Register an action to run once when [exit][WindowManager::exit] is called, before any
+    /// [autostarted][WindowManager::autostart] programs are stopped.
+    ///
+    /// Errors are passed to the [ErrorHandler] without preventing penrose from exiting.
+    pub fn shutdown_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&mut WindowManager<X>) -> Result<()> + 'static,
+    {
+        self.shutdown_hooks.push(Box::new(hook));
+    }
+
+    /// Start 'program' after all [startup hooks][WindowManager::startup_hook] have run and stop
+    /// it again when penrose exits.
+    ///
+    /// Programs from the XDG autostart directories are started after these if
+    /// [xdg_autostart][Config::xdg_autostart] is enabled.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use penrose::__test_helpers::*;
+    /// use penrose::core::process::Spawn;
+    ///
+    /// # fn example<X: XConn + 'static>(mut wm: WindowManager<X>) {
+    /// wm.autostart(Spawn::new("picom").arg("--experimental-backends"));
+    /// wm.autostart(Spawn::new("polybar").arg("main"));
+    /// # }
+    /// ```
+    pub fn autostart(&mut self, program: Spawn) {
+        self.autostart.push(program);
+    }
+
+    /// Run an external command as a detached process, splitting 'cmd' on whitespace to get the
+    /// program and its arguments.
+    ///
+    /// Unlike [spawn][process::spawn], the process is started with environment variables
+    /// describing the active workspace and screen: see [spawn_env][WindowManager::spawn_env].
+    pub fn spawn(&self, cmd: impl Into<String>) -> Result<()> {
+        let s = cmd.into();
+        let mut parts = s.split_whitespace();
+        let prog = parts.next().unwrap_or_default();
+
+        self.spawn_in_context(Spawn::new(prog).args(parts))
+    }
+
+    /// Start 'program' as a detached process with environment variables describing the active
+    /// workspace and screen added to any that it already sets.
+    pub fn spawn_in_context(&self, program: Spawn) -> Result<()> {
+        self.spawn_env()
+            .into_iter()
+            .fold(program, |p, (k, v)| p.env(k, v))
+            .spawn()
+    }
+
+    /// The environment variables set for programs started using [spawn][WindowManager::spawn]:
+    /// the name ([ENV_WORKSPACE][1]) and index ([ENV_WORKSPACE_INDEX][2]) of the active workspace
+    /// and the index of the active screen ([ENV_SCREEN][3]).
+    ///
+    /// [1]: process::ENV_WORKSPACE
+    /// [2]: process::ENV_WORKSPACE_INDEX
+    /// [3]: process::ENV_SCREEN
+    pub fn spawn_env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                process::ENV_WORKSPACE,
+                self.active_workspace().name().to_string(),
+            ),
+            (
+                process::ENV_WORKSPACE_INDEX,
+                self.screens.active_ws_index().to_string(),
+            ),
+            (process::ENV_SCREEN, self.active_screen_index().to_string()),
+        ]
+    }
+
+    fn run_startup_hooks(&mut self) {
+        for mut hook in std::mem::take(&mut self.startup_hooks) {
+            if let Err(e) = hook(self) {
+                self.handle_error(e);
+            }
+        }
+
+        let mut autostart = std::mem::take(&mut self.autostart);
+        if self.config.xdg_autostart {
+            autostart.extend(xdg::autostart_entries().iter().filter_map(|e| e.spawn()));
+        }
+
+        for program in autostart {
+            match program.spawn_attached() {
+                Ok(child) => self.autostarted.push(child),
+                Err(e) => self.handle_error(e),
+            }
+        }
+    }
+
+    // Programs that exit on their own (including those that fork and exit in order to daemonize)
+    // need to be waited on as soon as possible so that they are not left as zombies until penrose
+    // itself exits.
+    fn reap_autostarted(&mut self) {
+        self.autostarted.retain_mut(|child| match child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                debug!(pid = child.id(), %status, "autostarted process exited");
+                false
+            }
+            Err(e) => {
+                warn!(
+                    pid = child.id(),
+                    "unable to check autostarted process: {}", e
+                );
+                false
+            }
+        });
+    }
+
+    fn stop_autostarted(&mut self) {
+        for mut child in self.autostarted.drain(..) {
+            // the child may have already exited but still needs to be reaped
+            let _ = child.kill();
+            if let Err(e) = child.wait() {
+                warn!(
+                    pid = child.id(),
+                    "unable to wait for autostarted process: {}", e
+                );
+            }
+        }
+    }
+
     /// The layout symbol for the [layout][1] currently being used on the
     /// active workspace
     ///
@@ -925,6 +2669,107 @@ impl<X: XConn> WindowManager<X> {
         Ok(self.conn.set_root_window_name(s.as_ref())?)
     }
 
+    /// Make the bindings of 'chord' available for the next key press, cancelling any chord that
+    /// is currently active.
+    ///
+    /// This is normally called by the [handler][KeyChord::handler] of the chord rather than
+    /// directly.
+    pub fn enter_key_chord(&mut self, chord: KeyChord<X>) -> Result<()> {
+        let deadline = chord.get_timeout().map(|t| Instant::now() + t);
+        let hints = chord.get_hints().to_vec();
+        self.key_chord = Some((chord, deadline));
+        self.key_chord_changed = true;
+        self.run_hook(HookName::KeyChordChanged(hints));
+
+        Ok(())
+    }
+
+    /// Whether or not there is a key chord waiting for its next key press
+    pub fn key_chord_active(&self) -> bool {
+        self.key_chord.is_some()
+    }
+
+    fn end_key_chord(&mut self) {
+        if self.key_chord.take().is_some() {
+            self.key_chord_changed = true;
+            self.run_hook(HookName::KeyChordChanged(vec![]));
+        }
+    }
+
+    /// Set the actions to run when the pointer rests in a corner or along an edge of a screen,
+    /// replacing any existing hot corners.
+    pub fn set_hot_corners(&mut self, hot_corners: HotCorners<X>) -> Result<()> {
+        self.hot_corners.set_hot_corners(hot_corners);
+        self.hot_corners.create_windows(&self.conn, &self.screens)
+    }
+
+    /// Set the actions to run in response to touchpad swipe gestures, replacing any existing
+    /// gesture bindings.
+    ///
+    /// When called from a running gesture binding, the new bindings are instead merged with the
+    /// existing ones (replacing any for the same gesture) once the binding has finished.
+    ///
+    /// Gestures are delivered to penrose as `_PENROSE_GESTURE` client messages sent to the root
+    /// window: see [LibinputGestures][1] for a helper that reads them from `libinput`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # #[macro_use] extern crate penrose;
+    /// # use penrose::__test_helpers::*;
+    /// # fn example<X: XConn + 'static>(mut wm: WindowManager<X>) -> penrose::Result<()> {
+    /// wm.set_gesture_bindings(gen_gesturebindings! {
+    ///     3 Left => |wm: &mut WindowManager<X>| wm.cycle_workspace(Forward),
+    ///     3 Right => |wm: &mut WindowManager<X>| wm.cycle_workspace(Backward),
+    ///     4 Up => |wm: &mut WindowManager<X>| wm.toggle_workspace()
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [1]: crate::contrib::extensions::LibinputGestures
+    pub fn set_gesture_bindings(&mut self, bindings: GestureBindings<X>) {
+        self.gesture_bindings = bindings;
+    }
+
+    /// Set the actions to run when a mouse button is pressed (or the scroll wheel is used) over
+    /// the desktop or along the edge of a screen, replacing any existing pointer bindings.
+    ///
+    /// Unlike [mouse bindings][1], these do not need a modifier to be held as they are only
+    /// triggered when the pointer is not over a client. Presses that do not match a binding are
+    /// passed on to the [root_window_clicked][2] hook as usual. This can be called from a
+    /// running pointer binding to add or remove bindings, including the one that is running.
+    ///
+    /// Setting any `Desktop` bindings selects button presses on the root window, which prevents
+    /// other programs (such as a desktop manager) from handling them.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # #[macro_use] extern crate penrose;
+    /// # use penrose::__test_helpers::*;
+    /// # fn example<X: XConn + 'static>(mut wm: WindowManager<X>) -> penrose::Result<()> {
+    /// wm.set_pointer_bindings(gen_pointerbindings! {
+    ///     Desktop ScrollUp + [] => run_internal!(cycle_workspace, Backward),
+    ///     Desktop ScrollDown + [] => run_internal!(cycle_workspace, Forward),
+    ///     Edge(Right) ScrollUp + [] => run_internal!(cycle_client, Backward),
+    ///     Edge(Right) ScrollDown + [] => run_internal!(cycle_client, Forward)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [1]: crate::gen_mousebindings
+    /// [2]: crate::core::hooks::Hook::root_window_clicked
+    pub fn set_pointer_bindings(&mut self, bindings: PointerBindings<X>) -> Result<()> {
+        if bindings.keys().any(|(t, _)| *t == PointerTarget::Desktop) {
+            let root = self.conn.root();
+            self.conn
+                .set_client_attributes(root, &[ClientAttr::RootEventMaskWithClicks])?;
+        }
+
+        self.hot_corners.set_pointer_bindings(bindings);
+        self.hot_corners.create_windows(&self.conn, &self.screens)
+    }
+
     /// Set the insert point for new clients. Default is to insert at index 0.
     pub fn set_client_insert_point(&mut self, cip: InsertPoint) -> Result<()> {
         self.workspaces.set_client_insert_point(cip);
@@ -946,64 +2791,146 @@ impl<X: XConn> WindowManager<X> {
             return Ok(());
         }
 
-        if let Some(index) = self.workspaces.index(selector) {
-            let active = self.screens.active_ws_index();
-            self.previous_workspace = active;
-
-            for i in 0..self.screens.n_screens() {
-                if self.screens.inner[i].wix == index {
-                    // The workspace we want is currently displayed on another screen so
-                    // pull the target workspace to the focused screen, and place the
-                    // workspace we had on the screen where the target was
-                    self.screens.inner[i].wix = self.screens.focused().wix;
-                    self.screens.focused_mut().wix = index;
-
-                    // re-apply layouts as screen dimensions may differ
-                    self.apply_layout(active)?;
-                    self.apply_layout(index)?;
-
-                    let ws = self.workspaces.get_workspace(index)?;
-                    if let Some(id) = ws.focused_client() {
-                        self.update_focus(id)?;
-                    };
+        let index = match self.workspaces.index(selector) {
+            Some(index) => index,
+            None => return Err(PenroseError::UnknownWorkspace(format!("{:?}", selector))),
+        };
 
-                    self.workspaces.focus(&Selector::Index(index));
-                    self.run_hook(HookName::WorkspaceChange(active, index));
-                    return Ok(());
+        let active = self.screens.active_ws_index();
+        let active_screen = self.screens.active_screen_index();
+        self.previous_workspace = active;
+        if self.config.tag_mode {
+            self.screens.set_extra_tags(active_screen, vec![]);
+        }
+        self.screens.push_history(active_screen, active);
+
+        for i in 0..self.screens.n_screens() {
+            if self.screens.inner[i].wix == index {
+                self.screens.push_history(i, index);
+                self.carry_sticky_clients(&[(active, index), (index, active)])?;
+                // The workspace we want is currently displayed on another screen so
+                // pull the target workspace to the focused screen, and place the
+                // workspace we had on the screen where the target was
+                self.screens.inner[i].wix = self.screens.focused().wix;
+                self.screens.focused_mut().wix = index;
+
+                // re-apply layouts as screen dimensions may differ
+                let mut ids = self.workspaces.client_ids(active)?;
+                ids.extend(self.workspaces.client_ids(index)?);
+                self.without_crossing_events(&ids, |wm| {
+                    wm.apply_layout(active)?;
+                    wm.apply_layout(index)
+                })?;
+
+                let ws = self.workspaces.get_workspace(index)?;
+                if let Some(id) = ws.focused_client() {
+                    self.update_focus(id)?;
+                };
+
+                self.workspaces.focus(&Selector::Index(index));
+                if self.config.tag_mode {
+                    self.sync_tag_visibility()?;
                 }
+                self.warp_pointer(self.config.warp_on_workspace_change)?;
+                self.update_root_background()?;
+                self.run_hook(HookName::WorkspaceChange(active, index));
+                return Ok(());
+            }
+        }
+
+        // target not currently displayed so map, position and raise the target workspace
+        // before unmapping what we currently have displayed so that the root window is
+        // never left exposed and the old clients are never drawn over the new ones part
+        // way through the switch.
+        self.carry_sticky_clients(&[(active, index)])?;
+        let old_ids = self.workspaces.get_workspace(active)?.client_ids();
+        let new_ids = self.workspaces.get_workspace(index)?.client_ids();
+        let ids: Vec<Xid> = old_ids.iter().chain(new_ids.iter()).copied().collect();
+
+        self.without_crossing_events(&ids, |wm| {
+            // clients are placed before being mapped if they were set up while hidden
+            wm.screens.focused_mut().wix = index;
+            wm.run_deferred(&new_ids)?;
+            for id in new_ids.iter() {
+                wm.clients.map_if_needed(*id, &wm.conn)?;
             }
 
-            // target not currently displayed so unmap what we currently have
-            // displayed and replace it with the target workspace
-            let ws = self.workspaces.get_workspace(active)?;
-            for id in ws.client_ids().iter() {
-                self.clients.unmap_if_needed(*id, &self.conn)?;
+            wm.apply_layout(index)?;
+
+            // floating clients are raised last so that they stay above the tiled ones
+            let (floating, tiled): (Vec<Xid>, Vec<Xid>) = new_ids
+                .iter()
+                .partition(|&&id| matches!(wm.clients.get(id), Some(c) if c.floating));
+            for &id in tiled.iter().chain(floating.iter()) {
+                wm.conn.raise_client(id)?;
             }
 
-            let ws = self.workspaces.get_workspace(index)?;
-            for id in ws.client_ids().iter() {
-                self.clients.map_if_needed(*id, &self.conn)?;
+            for id in old_ids.iter() {
+                wm.clients.unmap_if_needed(*id, &wm.conn)?;
             }
 
-            self.screens.focused_mut().wix = index;
-            self.apply_layout(index)?;
-            self.conn.set_current_workspace(index)?;
+            Ok(())
+        })?;
+        self.conn.set_current_workspace(index)?;
 
-            let ws = self.workspaces.get_workspace(index)?;
-            if let Some(id) = ws.focused_client() {
-                self.update_focus(id)?;
-            };
+        let ws = self.workspaces.get_workspace(index)?;
+        if let Some(id) = ws.focused_client() {
+            self.update_focus(id)?;
+        };
 
-            self.workspaces.focus(&Selector::Index(index));
-            self.run_hook(HookName::WorkspaceChange(active, index));
+        self.workspaces.focus(&Selector::Index(index));
+        if self.config.tag_mode {
+            self.sync_tag_visibility()?;
         }
+        self.warp_pointer(self.config.warp_on_workspace_change)?;
+        self.update_root_background()?;
+        self.run_hook(HookName::WorkspaceChange(active, index));
 
         Ok(())
     }
 
-    /// Switch focus back to the last workspace that had focus.
+    /// Switch focus back to the last workspace that was shown on the focused [Screen].
+    ///
+    /// Calling this repeatedly flips between the current and previous workspace.
     pub fn toggle_workspace(&mut self) -> Result<()> {
-        self.focus_workspace(&Selector::Index(self.previous_workspace))
+        let wix = self
+            .workspace_history(self.screens.active_screen_index())
+            .first()
+            .copied()
+            .unwrap_or(self.previous_workspace);
+
+        self.focus_workspace(&Selector::Index(wix))
+    }
+
+    /// The indices of the workspaces previously shown on the [Screen] at 'screen_index', most
+    /// recently shown first.
+    ///
+    /// The workspace currently shown on the screen is not included.
+    pub fn workspace_history(&self, screen_index: usize) -> Vec<usize> {
+        self.screens.workspace_history(screen_index)
+    }
+
+    /// Focus the next [Workspace] in 'direction' that has at least one client, skipping over
+    /// any that are empty.
+    ///
+    /// Does nothing if all other workspaces are empty.
+    pub fn cycle_non_empty_workspace(&mut self, direction: Direction) -> Result<()> {
+        let n = self.workspaces.len();
+        let current = self.screens.active_ws_index();
+        let step = |i: usize| match direction {
+            Direction::Forward => (i + 1) % n,
+            Direction::Backward => (i + n - 1) % n,
+        };
+
+        let mut wix = step(current);
+        while wix != current {
+            if !self.workspaces[wix].is_empty() {
+                return self.focus_workspace(&Selector::Index(wix));
+            }
+            wix = step(wix);
+        }
+
+        Ok(())
     }
 
     /// Move the focused client to the workspace matching 'selector'.
@@ -1029,27 +2956,205 @@ impl<X: XConn> WindowManager<X> {
         self.client_to_workspace(&Selector::Index(i))
     }
 
-    /// Toggle the fullscreen state of the [Client] matching the given [Selector]
-    pub fn toggle_client_fullscreen(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
-        let (id, client_is_fullscreen) = match self.client(selector) {
+    /// Toggle whether or not the [Client] matching the given [Selector] is sticky.
+    ///
+    /// Sticky clients are floated and remain visible in the same position on their screen when
+    /// the workspace being shown on that screen changes. Clients can also request this
+    /// themselves by setting `_NET_WM_STATE_STICKY`.
+    pub fn toggle_sticky(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        let (id, sticky) = match self.client(selector) {
             None => return Ok(()), // unknown client
-            Some(c) => (c.id(), c.fullscreen),
+            Some(c) => (c.id(), c.sticky),
+        };
+        self.set_sticky(id, !sticky)
+    }
+
+    /// Toggle whether or not the floating [Client] matching the given [Selector] fills the width
+    /// of its screen.
+    ///
+    /// Clients keep their height and vertical position while maximized horizontally and are
+    /// returned to their previous geometry once they are no longer maximized along either axis.
+    /// Clients can also request this themselves by setting `_NET_WM_STATE_MAXIMIZED_HORZ`.
+    pub fn toggle_maximized_horz(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        let (id, horz, vert) = match self.client(selector) {
+            None => return Ok(()), // unknown client
+            Some(c) => (c.id(), c.maximized_horz, c.maximized_vert),
+        };
+        self.set_maximized(id, !horz, vert)
+    }
+
+    /// Toggle whether or not the floating [Client] matching the given [Selector] fills the height
+    /// of its screen.
+    ///
+    /// This is the vertical counterpart of [toggle_maximized_horz][1]. Clients can also request
+    /// this themselves by setting `_NET_WM_STATE_MAXIMIZED_VERT`.
+    ///
+    /// [1]: WindowManager::toggle_maximized_horz
+    pub fn toggle_maximized_vert(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        let (id, horz, vert) = match self.client(selector) {
+            None => return Ok(()), // unknown client
+            Some(c) => (c.id(), c.maximized_horz, c.maximized_vert),
+        };
+        self.set_maximized(id, horz, !vert)
+    }
+
+    /// Toggle the fullscreen state of the [Client] matching the given [Selector]
+    pub fn toggle_client_fullscreen(&mut self, selector: &Selector<'_, Client>) -> Result<()> {
+        let (id, client_is_fullscreen) = match self.client(selector) {
+            None => return Ok(()), // unknown client
+            Some(c) => (c.id(), c.fullscreen),
         };
         self.set_fullscreen(id, !client_is_fullscreen)
     }
 
     /// Kill the focused client window.
+    ///
+    /// Clients that list `WM_DELETE_WINDOW` in their `WM_PROTOCOLS` are asked to close
+    /// themselves, all other clients are forcibly killed. Internal state for the client is only
+    /// removed once the X server notifies us that the window has been destroyed.
     #[tracing::instrument(level = "debug", err, skip(self))]
     pub fn kill_client(&mut self) -> Result<()> {
-        if let Some(id) = self.clients.focused_client_id() {
-            let msg = ClientMessageKind::DeleteWindow(id).as_message(&self.conn)?;
-            self.conn.send_client_event(msg)?;
-            self.conn.flush();
+        let id = match self.clients.focused_client_id() {
+            Some(id) => id,
+            None => return Err(PenroseError::NoFocusedClient),
+        };
+
+        let supports_delete = match self.conn.client_accepts_close_request(id) {
+            Ok(supported) => supported,
+            Err(e) => {
+                warn!(
+                    id,
+                    "unable to check WM_PROTOCOLS, using cached value: {}", e
+                );
+                matches!(self.clients.get(id), Some(c) if c.accepts_close_request())
+            }
+        };
+
+        if supports_delete {
+            self.conn.request_client_close(id)?;
+        } else {
+            debug!(id, "client does not support WM_DELETE_WINDOW: killing");
+            self.conn.kill_client(id)?;
+        }
+        self.conn.flush();
+
+        Ok(())
+    }
+
+    /// Minimize the focused [Client], unmapping it and placing it on top of the stack of
+    /// minimized clients for the active [Workspace].
+    ///
+    /// Minimized clients are left out of the layout and focus cycle for their workspace until
+    /// they are brought back using [restore_last_minimized][1] or [restore_minimized][2]. A
+    /// minimized client that asks to be mapped or activated is restored automatically.
+    ///
+    /// [1]: WindowManager::restore_last_minimized
+    /// [2]: WindowManager::restore_minimized
+    pub fn minimize_focused(&mut self) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        let id = match self.workspaces.get_workspace(wix)?.focused_client() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        if !self.workspaces.minimize_client(wix, id) {
+            return Ok(());
+        }
+        self.clients.modify(id, |c| c.minimized = true);
+        self.clients.unmap_if_needed(id, &self.conn)?;
+        self.conn.set_client_state(id, WindowState::Iconic)?;
+        self.apply_layout(wix)?;
+
+        match self.active_workspace().focused_client() {
+            Some(now_focused) => self.update_focus(now_focused),
+            None => {
+                self.clients.clear_focused();
+                Ok(())
+            }
+        }
+    }
+
+    /// Restore the most recently minimized [Client] on the active [Workspace].
+    ///
+    /// This is a no-op if there are no minimized clients on the active workspace.
+    pub fn restore_last_minimized(&mut self) -> Result<()> {
+        let wix = self.screens.active_ws_index();
+        match self
+            .workspaces
+            .get_workspace(wix)?
+            .minimized_clients()
+            .last()
+        {
+            Some(&id) => self.restore_minimized(id),
+            None => Ok(()),
+        }
+    }
+
+    /// Restore the minimized [Client] with ID 'id' to the [Workspace] it was minimized on,
+    /// focusing it if that workspace is currently visible.
+    ///
+    /// This is a no-op if the client is not minimized.
+    pub fn restore_minimized(&mut self, id: Xid) -> Result<()> {
+        let wix = match self.clients.get(id) {
+            Some(c) if c.minimized => c.workspace(),
+            _ => return Ok(()),
+        };
+
+        if !self.workspaces.restore_minimized(wix, id) {
+            return Ok(());
         }
+        self.clients.modify(id, |c| c.minimized = false);
+        self.conn.set_client_state(id, WindowState::Normal)?;
+
+        if tags::client_is_viewed(&self.clients, &self.screens, id) {
+            self.clients.map_if_needed(id, &self.conn)?;
+            self.apply_layout(wix)?;
+            self.focus_client(&Selector::WinId(id))?;
+        }
+
+        Ok(())
+    }
+
+    /// The IDs of the [clients][Client] that have been minimized on the [Workspace] with index
+    /// 'wix', most recently minimized last.
+    pub fn minimized_clients(&self, wix: usize) -> Vec<Xid> {
+        self.workspaces
+            .get_workspace(wix)
+            .map(|ws| ws.minimized_clients().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Move focus to the most recently urgent [Client], switching to its [Workspace] if needed.
+    ///
+    /// This is a no-op if there are currently no urgent clients.
+    pub fn focus_urgent(&mut self) -> Result<()> {
+        let (id, wix) = match self.clients.most_recent_urgent() {
+            Some(id) => match self.clients.workspace_index_for_client(id) {
+                Some(wix) => (id, wix),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
 
+        self.focus_workspace(&Selector::Index(wix))?;
+        self.focus_client(&Selector::WinId(id))?;
         Ok(())
     }
 
+    /// The indices of each [Workspace] that currently contains an urgent [Client]
+    pub fn urgent_workspaces(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .clients
+            .matching_clients(&Selector::Condition(&|c: &Client| c.urgent))
+            .iter()
+            .map(|c| c.workspace())
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        indices
+    }
+
     /// Get a reference to the first Screen satisfying 'selector'. Xid selectors will return
     /// the screen containing that Client if the client is known.
     /// NOTE: It is not possible to get a mutable reference to a Screen.
@@ -1069,6 +3174,11 @@ impl<X: XConn> WindowManager<X> {
             .expect("no active workspace")
     }
 
+    /// The index of the currently active [Workspace]
+    pub fn active_workspace_index(&self) -> usize {
+        self.screens.active_ws_index()
+    }
+
     /// A mutable reference to the current active [Workspace]
     pub fn active_workspace_mut(&mut self) -> &mut Workspace {
         self.workspaces
@@ -1081,9 +3191,157 @@ impl<X: XConn> WindowManager<X> {
         self.screens.visible_workspaces()
     }
 
+    /// Take a [snapshot][State] of the screens, workspaces and clients currently being managed.
+    ///
+    /// Client geometries are fetched from the X server: clients whose geometry can not be
+    /// fetched are included with no geometry.
+    pub fn snapshot(&self) -> State {
+        let screens = (0..self.screens.n_screens())
+            .filter_map(|i| self.screens.get(i).map(|s| (i, s)))
+            .map(|(index, s)| ScreenState {
+                index,
+                region: s.region(false),
+                effective_region: s.region(true),
+                workspace: s.wix,
+            })
+            .collect();
+
+        let workspaces: Vec<WorkspaceState> = (0..self.workspaces.len())
+            .filter_map(|i| self.workspaces.get_workspace(i).ok().map(|ws| (i, ws)))
+            .map(|(index, ws)| WorkspaceState {
+                index,
+                name: ws.name().to_string(),
+                layout: ws.layout_symbol().to_string(),
+                clients: ws.client_ids(),
+                focused_client: ws.focused_client(),
+            })
+            .collect();
+
+        let clients = workspaces
+            .iter()
+            .flat_map(|ws| ws.clients.iter())
+            .filter_map(|&id| self.clients.get(id))
+            .map(|c| ClientState {
+                id: c.id(),
+                class: c.wm_class().to_string(),
+                name: c.wm_name().to_string(),
+                workspace: c.workspace(),
+                floating: c.floating,
+                fullscreen: c.is_fullscreen(),
+                urgent: c.is_urgent(),
+                geometry: self.conn.client_geometry(c.id()).ok(),
+            })
+            .collect();
+
+        State {
+            screens,
+            workspaces,
+            clients,
+            focused_screen: self.screens.active_screen_index(),
+            focused_workspace: self.screens.active_ws_index(),
+            focused_client: self.focused_client_id(),
+            key_bindings: self.key_binding_report.clone(),
+        }
+    }
+
+    /// Store the [KeyBindingReport] produced when parsing your key bindings so that it is
+    /// included in [snapshots][WindowManager::snapshot] for inspection by external programs.
+    ///
+    /// Any issues in the report are logged as warnings.
+    pub fn set_key_binding_report(&mut self, report: KeyBindingReport) {
+        report.log_issues();
+        self.key_binding_report = Some(report);
+    }
+
+    /// The [KeyBindingReport] passed to [set_key_binding_report][1], if there is one
+    ///
+    /// [1]: WindowManager::set_key_binding_report
+    pub fn key_binding_report(&self) -> Option<&KeyBindingReport> {
+        self.key_binding_report.as_ref()
+    }
+
+    /// The tags being viewed on the focused [Screen] when running in [tag mode][1], starting with
+    /// the index of the workspace that it is showing.
+    ///
+    /// Outside of tag mode this is only the index of the active workspace.
+    ///
+    /// [1]: crate::core::config::Config::tag_mode
+    pub fn viewed_tags(&self) -> Vec<usize> {
+        self.screens.viewed_tags(self.screens.active_screen_index())
+    }
+
+    /// View the given tags on the focused [Screen] when running in [tag mode][1].
+    ///
+    /// The first tag is shown as the workspace for the screen (as if it had been selected using
+    /// [focus_workspace][WindowManager::focus_workspace]) and its layout is used to position every
+    /// client carrying any of the tags.
+    ///
+    /// [1]: crate::core::config::Config::tag_mode
+    pub fn view_tags(&mut self, tags: &[usize]) -> Result<()> {
+        if !self.config.tag_mode {
+            return Err(perror!("view_tags requires tag_mode to be enabled"));
+        }
+        if let Some(t) = tags.iter().find(|&&t| t >= self.workspaces.len()) {
+            return Err(perror!("invalid tag: {}", t));
+        }
+        let (&first, rest) = match tags.split_first() {
+            Some(split) => split,
+            None => return Err(perror!("at least one tag must be viewed")),
+        };
+
+        self.focus_workspace(&Selector::Index(first))?;
+        let i = self.screens.active_screen_index();
+        self.screens.set_extra_tags(i, rest.to_vec());
+        self.sync_tag_visibility()
+    }
+
+    /// Add or remove a tag for a [Client] when running in [tag mode][1].
+    ///
+    /// Clients always carry at least one tag: removing the tag for the workspace that a client is
+    /// on moves it to the next tag that it carries and attempting to remove its only tag does
+    /// nothing.
+    ///
+    /// [1]: crate::core::config::Config::tag_mode
+    pub fn toggle_tag_on_client(&mut self, id: Xid, tag: usize) -> Result<()> {
+        if !self.config.tag_mode {
+            return Err(perror!(
+                "toggle_tag_on_client requires tag_mode to be enabled"
+            ));
+        }
+        if tag >= self.workspaces.len() {
+            return Err(perror!("invalid tag: {}", tag));
+        }
+        let (wix, extra_tags) = match self.clients.get(id) {
+            Some(c) if c.wm_managed => (c.workspace(), c.extra_tags.clone()),
+            _ => return Err(PenroseError::UnknownClient(id)),
+        };
+
+        if tag == wix {
+            match extra_tags.first() {
+                Some(&next) => self.move_client_to_workspace(id, next)?,
+                None => return Ok(()),
+            }
+        } else if extra_tags.contains(&tag) {
+            self.clients
+                .modify(id, |c| c.extra_tags.retain(|&t| t != tag));
+        } else {
+            self.clients.modify(id, |c| c.extra_tags.push(tag));
+        }
+
+        self.sync_tag_visibility()
+    }
+
     /// Add a new workspace at `index`, shifting all workspaces with indices greater to the right.
+    ///
+    /// Clients on the shifted workspaces remain where they are: only the index used to refer to
+    /// their workspace changes.
     pub fn add_workspace(&mut self, index: usize, ws: Workspace) -> Result<()> {
+        if index > self.workspaces.len() {
+            return Err(perror!("invalid workspace index: {}", index));
+        }
+
         self.workspaces.add_workspace(index, ws);
+        self.reindex_workspaces(|ix| if ix >= index { ix + 1 } else { ix })?;
         self.update_x_workspace_details()
     }
 
@@ -1094,22 +3352,79 @@ impl<X: XConn> WindowManager<X> {
     }
 
     /// Remove a Workspace from the WindowManager. All clients that were present on the removed
-    /// workspace will be destroyed. Xid selectors will be ignored.
+    /// workspace are moved to the workspace before it (or the one after it if it was the first
+    /// workspace). Xid selectors will be ignored.
+    ///
+    /// If the removed workspace was visible, the screen it was shown on will show the workspace
+    /// that its clients were moved to instead (or the first hidden workspace if that is already
+    /// visible on another screen).
     pub fn remove_workspace(
         &mut self,
         selector: &Selector<'_, Workspace>,
     ) -> Result<Option<Workspace>> {
-        if self.workspaces.len() == self.screens.n_screens() {
+        if self.workspaces.len() <= self.screens.n_screens() {
             return Err(perror!("must have at least one workspace per screen"));
         }
 
-        let ws = self.workspaces.remove_workspace(&selector)?;
-        ws.iter().try_for_each(|c| self.remove_client(*c))?;
+        let wix = self
+            .workspaces
+            .index(selector)
+            .ok_or_else(|| perror!("unknown workspace"))?;
+        let target = if wix == 0 { 1 } else { wix - 1 };
+        let target_visible = self.screens.visible_workspaces().contains(&target);
+        let removed_screen = self
+            .screens
+            .indexed_screen_for_workspace(wix)
+            .map(|(i, _)| i);
+
+        // Moved in reverse so that the default insert point retains their stacking order
+        for id in self.workspaces.client_ids(wix)?.into_iter().rev() {
+            self.workspaces.remove_client(wix, id);
+            self.add_client_to_workspace(target, id)?;
+        }
+
+        let ws = self.workspaces.remove_workspace(&Selector::Index(wix))?;
+        if self.config.overflow_workspace == Some(wix) {
+            self.config.overflow_workspace = None;
+        }
+        let new_target = if wix == 0 { 0 } else { target };
+        self.reindex_workspaces(|ix| match ix {
+            _ if ix == wix => new_target,
+            _ if ix > wix => ix - 1,
+            _ => ix,
+        })?;
+
+        // The screen that was showing the removed workspace now points at the target workspace,
+        // which may already be visible on another screen.
+        if let (Some(i), true) = (removed_screen, target_visible) {
+            let visible = self.screens.visible_workspaces();
+            let hidden = (0..self.workspaces.len()).find(|ix| !visible.contains(ix));
+            if let (Some(s), Some(hidden)) = (self.screens.inner.get_mut(i), hidden) {
+                s.wix = hidden;
+            }
+        }
+
+        // Which workspaces are visible may have changed so clients are mapped or unmapped to
+        // match where they now live
+        let visible = self.screens.visible_workspaces();
+        for wix in 0..self.workspaces.len() {
+            for id in self.workspaces.client_ids(wix)? {
+                let floating = matches!(self.clients.get(id), Some(c) if c.floating);
+                if !visible.contains(&wix) {
+                    self.clients.unmap_if_needed(id, &self.conn)?;
+                } else if floating {
+                    self.clients.map_if_needed(id, &self.conn)?;
+                }
+            }
+        }
 
-        // Focus the workspace before the one we just removed. There is always at least one
-        // workspace before this one due to the guard above.
-        let ix = self.screens.focused().wix.saturating_sub(1);
-        self.focus_workspace(&Selector::Index(ix))?;
+        self.layout_visible()?;
+        if let Some(id) = self
+            .workspaces
+            .focused_client(self.screens.active_ws_index())
+        {
+            self.update_focus(id)?;
+        }
         self.update_x_workspace_details()?;
 
         Ok(Some(ws))
@@ -1150,6 +3465,36 @@ impl<X: XConn> WindowManager<X> {
         self.update_x_workspace_details()
     }
 
+    /// Set the background of the root window while the selected Workspace is shown, or `None` to
+    /// leave the root window as it is when showing it.
+    ///
+    /// The initial backgrounds are taken from
+    /// [workspace_backgrounds][crate::core::config::Config::workspace_backgrounds]. There is
+    /// only a single root window so a background color covers every screen and is taken from
+    /// the workspace on the focused screen. Images are drawn on the screen showing their
+    /// workspace by the [Wallpapers][crate::draw::Wallpapers] hook.
+    pub fn set_workspace_background(
+        &mut self,
+        background: Option<Background>,
+        selector: &Selector<'_, Workspace>,
+    ) -> Result<()> {
+        match self.workspaces.workspace_mut(selector) {
+            Some(ws) => ws.set_background(background),
+            None => return Err(PenroseError::UnknownWorkspace(format!("{:?}", selector))),
+        }
+
+        self.update_root_background()
+    }
+
+    /// Let the WindowManager know that the root window background has been replaced by
+    /// something other than [set_workspace_background][1], such as a drawn wallpaper, so that
+    /// background colors are set again the next time that they are needed.
+    ///
+    /// [1]: WindowManager::set_workspace_background
+    pub fn invalidate_root_background(&mut self) {
+        self.root_background = None;
+    }
+
     /// Take a reference to the first Client found matching 'selector'
     pub fn client(&self, selector: &Selector<'_, Client>) -> Option<&Client> {
         match selector {
@@ -1223,20 +3568,221 @@ impl<X: XConn> WindowManager<X> {
     }
 
     /// The current effective screen size of the target screen. Effective screen size is the
-    /// physical screen size minus any space reserved for a status bar.
+    /// physical screen size minus any space reserved for a status bar or dock windows.
     pub fn screen_size(&self, index: usize) -> Option<Region> {
-        self.screens.screen_size(index, self.config.show_bar)
+        self.screens.screen_size(index, true)
     }
 
     /// Position an individual client on the display. (x,y) coordinates are absolute (i.e. relative
     /// to the root window not any individual screen).
     pub fn position_client(&self, id: Xid, region: Region, stack_above: bool) -> Result<()> {
-        let bpx = self.config.border_px;
+        let bpx = self.border_px_for(id);
         self.conn
             .position_client(id, region, bpx, stack_above)
             .map_err(|e| e.into())
     }
 
+    /// Make the given [Client] floating (if it is not already) and move it to 'region'. (x,y)
+    /// coordinates are absolute.
+    ///
+    /// This can also be requested by external programs: see [RemoteCommand][1].
+    ///
+    /// [1]: crate::contrib::extensions::RemoteCommand
+    pub fn float_client_at(&mut self, id: Xid, region: Region) -> Result<()> {
+        let wix = match self.clients.get(id) {
+            Some(c) if c.wm_managed => c.workspace(),
+            _ => return Ok(()),
+        };
+
+        self.clients.modify(id, |c| c.set_floating(true));
+        self.apply_layout(wix)?;
+        self.position_client(id, region, true)
+    }
+
+    /// Return a floating [Client] to the tiled layout of its [Workspace].
+    ///
+    /// This can also be requested by external programs: see [RemoteCommand][1].
+    ///
+    /// [1]: crate::contrib::extensions::RemoteCommand
+    pub fn tile_client(&mut self, id: Xid) -> Result<()> {
+        let wix = match self.clients.get(id) {
+            Some(c) if c.wm_managed && c.floating => c.workspace(),
+            _ => return Ok(()),
+        };
+
+        // Being maximized only applies to floating clients
+        self.clients.take_unmaximized(id);
+        self.clients.modify(id, |c| {
+            c.set_floating(false);
+            c.maximized_horz = false;
+            c.maximized_vert = false;
+        });
+        self.clients.update_states(id, &self.conn)?;
+        self.apply_layout(wix)
+    }
+
+    /// The ID of the tiled [Client] whose layout position contains the given absolute [Point],
+    /// if there is one.
+    ///
+    /// Floating clients and clients on workspaces that are not currently visible are ignored.
+    pub fn tiled_client_at_point(&self, p: Point) -> Option<Xid> {
+        let s = self.screen(&Selector::Condition(&|s: &Screen| s.contains(p)))?;
+        let clients = self.clients.clients_for_workspace(s.wix);
+        let (_, arrange_actions) = self
+            .workspaces
+            .get_arrange_actions(s.wix, s.region(true), &self.spacing(s), &clients)
+            .ok()?;
+
+        arrange_actions
+            .actions
+            .into_iter()
+            .find(|(_, r)| matches!(r, Some(r) if r.contains_point(&p)))
+            .map(|(id, _)| id)
+    }
+
+    /// The ID of the [Client] under the given absolute [Point], if there is one.
+    ///
+    /// Floating clients are stacked above the tiled layout so they are checked first.
+    pub fn client_at_point(&self, p: Point) -> Option<Xid> {
+        let s = self.screen(&Selector::Condition(&|s: &Screen| s.contains(p)))?;
+        let floating = self
+            .clients
+            .clients_for_workspace(s.wix)
+            .into_iter()
+            .filter(|c| c.floating)
+            .map(|c| c.id())
+            .find(|&id| matches!(self.conn.client_geometry(id), Ok(r) if r.contains_point(&p)));
+
+        floating.or_else(|| self.tiled_client_at_point(p))
+    }
+
+    /// Change the cursor to a crosshair and wait for the user to click on a window, in the style
+    /// of `xkill`.
+    ///
+    /// Returns the ID of the [Client] that was clicked with the left mouse button, or `None` if
+    /// the click was not on a client or any other button was used or Escape was pressed to cancel
+    /// the selection. Other events continue to be handled while waiting for the click but key and
+    /// mouse bindings are not run.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use penrose::core::{bindings::KeyEventHandler, manager::WindowManager, ring::Selector, xconnection::XConn};
+    /// # fn example<X: XConn>() -> KeyEventHandler<X> {
+    /// // Kill the next window that is clicked on
+    /// Box::new(|wm: &mut WindowManager<X>| {
+    ///     if let Some(id) = wm.select_window_interactive()? {
+    ///         wm.focus_client(&Selector::WinId(id))?;
+    ///         wm.kill_client()?;
+    ///     }
+    ///     Ok(())
+    /// })
+    /// # }
+    /// ```
+    pub fn select_window_interactive(&mut self) -> Result<Option<Xid>> {
+        Ok(self
+            .select_point_interactive()?
+            .and_then(|p| self.client_at_point(p)))
+    }
+
+    /// Change the cursor to a crosshair and wait for the user to click anywhere on the screen.
+    ///
+    /// Returns the absolute position of the pointer when the left mouse button was clicked, or
+    /// `None` if any other button was used to cancel the selection. As with
+    /// [select_window_interactive][WindowManager::select_window_interactive], other events
+    /// continue to be handled while waiting for the click.
+    pub fn select_point_interactive(&mut self) -> Result<Option<Point>> {
+        match self.select_interactive(&[])? {
+            Some(InteractiveSelection::Clicked(p)) => Ok(Some(p)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Change the cursor to a crosshair and wait for the user to either click anywhere on the
+    /// screen or press one of the keys named in 'keys' (using the names from `xmodmap`, such as
+    /// "a" or "Return").
+    ///
+    /// Returns `None` if any mouse button other than the left button is clicked or Escape is
+    /// pressed, while any other keys are ignored. As with
+    /// [select_window_interactive][WindowManager::select_window_interactive], other events
+    /// continue to be handled while waiting.
+    pub fn select_interactive(&mut self, keys: &[&str]) -> Result<Option<InteractiveSelection>> {
+        self.conn.grab_pointer()?;
+        // Key bindings are not run while selecting so the keyboard is grabbed to see Escape
+        if let Err(e) = self.conn.grab_keyboard() {
+            self.conn.ungrab_pointer()?;
+            return Err(e.into());
+        }
+        let selected = self.wait_for_selection(keys);
+        self.conn.ungrab_keyboard()?;
+        self.conn.ungrab_pointer()?;
+
+        selected
+    }
+
+    fn wait_for_selection(&mut self, keys: &[&str]) -> Result<Option<InteractiveSelection>> {
+        let codes = self.conn.keycodes()?;
+        let escape = codes.get("Escape").copied();
+        let names: HashMap<u8, &str> = keys
+            .iter()
+            .filter_map(|&name| codes.get(name).map(|&code| (code, name)))
+            .collect();
+
+        loop {
+            match self.conn.wait_for_event()? {
+                XEvent::MouseEvent(e) if e.kind == MouseEventKind::Press => {
+                    return Ok(Some(InteractiveSelection::Clicked(e.rpt))
+                        .filter(|_| e.state.button == MouseButton::Left));
+                }
+                XEvent::KeyPress(k) if Some(k.code) == escape => return Ok(None),
+                XEvent::KeyPress(k) if names.contains_key(&k.code) => {
+                    let name = names[&k.code].to_string();
+                    return Ok(Some(InteractiveSelection::KeyPressed(name)));
+                }
+                XEvent::MouseEvent(_) | XEvent::KeyPress(_) => (),
+                event => {
+                    let actions = process_next_event(event, WmState::new(self));
+                    for action in actions {
+                        if let Err(e) = self.handle_event_action(action, None, None) {
+                            self.handle_error(e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The number of tiled clients on the workspace at index `wix` that are not being shown by
+    /// its current layout, such as the unfocused clients when using [monocle][1].
+    ///
+    /// Workspaces that are not currently visible have no hidden clients.
+    ///
+    /// [1]: crate::core::layout::monocle
+    pub fn hidden_client_count(&self, wix: usize) -> usize {
+        let s = match self.screen(&Selector::Condition(&|s: &Screen| s.wix == wix)) {
+            Some(s) => s,
+            None => return 0,
+        };
+        let clients = self.clients.clients_for_workspace(wix);
+
+        match self
+            .workspaces
+            .get_arrange_actions(wix, s.region(true), &self.spacing(s), &clients)
+        {
+            Ok((_, arrange_actions)) => arrange_actions
+                .actions
+                .iter()
+                .filter(|(_, r)| r.is_none())
+                .count(),
+            Err(_) => 0,
+        }
+    }
+
+    /// Set the border color of a [Client] back to the color matching its current focus and
+    /// urgency state, removing any highlighting applied directly through the [XConn].
+    pub fn reset_client_border(&self, id: Xid) -> Result<()> {
+        self.clients.reset_border(id, &self.conn)
+    }
+
     /// Make the Client with ID 'id' visible at its last known position.
     pub fn show_client(&mut self, id: Xid) -> Result<()> {
         self.clients.map_if_needed(id, &self.conn)?;
@@ -1271,85 +3817,1820 @@ mod tests {
     use super::*;
     use crate::{
         __test_helpers::{
-            n_clients, test_key_bindings, test_mouse_bindings, test_windowmanager, RecordedCall,
-            RecordingXConn,
+            n_clients, test_key_bindings, test_mouse_bindings, test_windowmanager,
+            ConfigurableXConn, RecordedCall, RecordingXConn,
         },
+        core::bindings::{HotCornerHandler, MouseState},
+        core::client::InputModel,
         core::{
             data_types::*,
             helpers::logging_error_handler,
+            input::{InputDevice, KeyboardConfig, PointerConfig},
             layout::*,
+            power::PowerEvent,
             ring::Direction::*,
+            rules::{Matcher, Rule},
             screen::*,
-            xconnection::{MockXConn, Prop, XEvent},
+            xconnection::{
+                Atom, ClientEventMask, ClientMessage, ClientMessageKind, ConfigureEvent, MockXConn,
+                PointerChange, Prop, PropertyEvent, WmHints, XAtomQuerier, XEvent, XState,
+            },
         },
-        draw::Color,
+        draw::{Color, Wallpaper, WallpaperMode},
     };
 
-    use std::{cell::Cell, collections::HashMap, convert::TryFrom};
+    use std::{cell::Cell, collections::HashMap, convert::TryFrom, rc::Rc};
+
+    fn wm_with_mock_conn(events: Vec<XEvent>, unmanaged_ids: Vec<Xid>) -> WindowManager<MockXConn> {
+        let conn = MockXConn::new(test_screens(), events, unmanaged_ids);
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        wm
+    }
+
+    fn focus_test_layouts(follow_focus: bool) -> Vec<Layout> {
+        let conf = LayoutConf {
+            follow_focus,
+            ..Default::default()
+        };
+        vec![Layout::new("t", conf, mock_layout, 1, 0.6)]
+    }
+
+    fn test_screens() -> Vec<Screen> {
+        vec![
+            Screen::new(Region::new(0, 0, 1366, 768), 0),
+            Screen::new(Region::new(1366, 0, 1366, 768), 0),
+        ]
+    }
+
+    fn add_n_clients<X: XConn>(wm: &mut WindowManager<X>, n: usize, offset: usize) {
+        for i in 0..n {
+            wm.handle_map_request(10 * (i + offset + 1) as u32).unwrap();
+        }
+    }
+
+    #[test]
+    fn workspace_switching_with_active_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+
+        // add clients to the first workspace: final client should have focus
+        add_n_clients(&mut wm, 3, 0);
+        assert_eq!(wm.workspaces[0].len(), 3);
+        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
+
+        // switch and add to the second workspace: final client should have focus
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        add_n_clients(&mut wm, 2, 3);
+        assert_eq!(wm.workspaces[1].len(), 2);
+        assert_eq!(wm.workspaces[1].focused_client(), Some(50));
+
+        // switch back: clients should be the same, same client should have focus
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(wm.workspaces[0].len(), 3);
+        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
+    }
+
+    #[test]
+    fn new_clients_overflow_once_a_workspace_is_full() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.config.max_clients_per_workspace = Some(2);
+        wm.config.overflow_workspace = Some(5);
+
+        add_n_clients(&mut wm, 4, 0);
+        assert_eq!(wm.workspaces[0].iter().collect::<Vec<_>>(), vec![&20, &10]);
+        assert_eq!(wm.workspaces[5].iter().collect::<Vec<_>>(), vec![&40, &30]);
+        assert_eq!(wm.clients.get(30).map(|c| c.workspace()), Some(5));
+        assert_eq!(wm.focused_client_id(), Some(20));
+
+        // the overflow workspace itself is never capped and clients can still be moved manually
+        wm.focus_workspace(&Selector::Index(5)).unwrap();
+        add_n_clients(&mut wm, 1, 4);
+        assert_eq!(wm.workspaces[5].len(), 3);
+        wm.move_client_to_workspace(50, 0).unwrap();
+        assert_eq!(wm.workspaces[0].len(), 3);
+    }
+
+    #[test]
+    fn actions_with_nothing_to_act_on_are_errors() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+
+        assert!(matches!(
+            wm.kill_client(),
+            Err(PenroseError::NoFocusedClient)
+        ));
+        assert!(matches!(
+            wm.focus_workspace(&Selector::Index(42)),
+            Err(PenroseError::UnknownWorkspace(_))
+        ));
+    }
+
+    #[test]
+    fn killing_a_client_does_not_remove_it_from_the_workspace() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+        // Should trigger the kill but we wait for DestroyNotify before removing the
+        // client state
+        wm.kill_client().unwrap();
+
+        assert_eq!(wm.workspaces[0].len(), 1);
+    }
+
+    // RecordingXConn fails all property reads so the protocols cached at map time are used
+    test_cases! {
+        kill_client;
+        args: (protocols: Vec<String>, expected: &str);
+
+        case: supports_delete_window => (vec!["WM_DELETE_WINDOW".into()], "send_client_event");
+        case: no_protocols => (vec![], "kill_client");
+
+        body: {
+            let mut wm = WindowManager::new(
+                Config::default(),
+                RecordingXConn::init(),
+                vec![],
+                logging_error_handler(),
+            );
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            wm.clients.modify(10, |c| c.wm_protocols = protocols.clone());
+            wm.conn().clear();
+            wm.kill_client().unwrap();
+
+            let calls: Vec<String> = wm.conn().calls().into_iter().map(|(m, _)| m).collect();
+            assert!(calls.contains(&expected.to_string()));
+            assert!(wm.clients.is_known(10));
+        }
+    }
+
+    // The protocols reported by the X server take precedence over those cached at map time
+    test_cases! {
+        kill_client_checks_current_protocols;
+        args: (current: Vec<String>, cached: Vec<String>, expected: &str);
+
+        case: supports_delete_window => (
+            vec!["WM_TAKE_FOCUS".into(), "WM_DELETE_WINDOW".into()],
+            vec![],
+            "send_client_event"
+        );
+        case: no_longer_supports_delete_window => (
+            vec!["WM_TAKE_FOCUS".into()],
+            vec!["WM_DELETE_WINDOW".into()],
+            "kill_client"
+        );
+
+        body: {
+            let protocols = Prop::Atom(current);
+            let conn = ConfigurableXConn::default().with_prop(10, Atom::WmProtocols, protocols);
+            let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            wm.clients.modify(10, |c| c.wm_protocols = cached.clone());
+            wm.conn().clear();
+            wm.kill_client().unwrap();
+
+            assert_eq!(wm.conn().methods(), vec![expected]);
+            assert!(wm.clients.is_known(10));
+        }
+    }
+
+    test_cases! {
+        remap_request;
+        args: (wix: usize, should_map: bool);
+
+        case: visible_workspace => (0, true);
+        case: hidden_workspace => (5, false);
+
+        body: {
+            let mut wm = WindowManager::new(
+                Config::default(),
+                RecordingXConn::init(),
+                vec![],
+                logging_error_handler(),
+            );
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            wm.move_client_to_workspace(10, wix).unwrap();
+            wm.conn().clear();
+            wm.handle_remap_request(10).unwrap();
+
+            let calls: Vec<String> = wm.conn().calls().into_iter().map(|(m, _)| m).collect();
+            assert_eq!(calls.contains(&"map_client".to_string()), should_map);
+        }
+    }
+
+    // The state and placement calls made for a client after it has been created
+    fn setup_calls(wm: &WindowManager<RecordingXConn>) -> Vec<String> {
+        wm.conn()
+            .calls()
+            .into_iter()
+            .filter_map(|(m, args)| match m.as_str() {
+                "get_prop" if args == strings!(10, Atom::NetWmState.as_ref()) => Some(m),
+                "position_client" | "client_name" => Some(m),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn wm_with_floating_rule(wix: usize) -> WindowManager<RecordingXConn> {
+        let conf = Config {
+            rules: vec![Rule::new(
+                vec![],
+                vec![RuleAction::ToWorkspace(wix), RuleAction::Float],
+            )],
+            // RecordingXConn reports an empty geometry for all clients
+            border_px: 0,
+            ..Default::default()
+        };
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        wm
+    }
+
+    test_cases! {
+        clients_on_hidden_workspaces_are_set_up_when_shown;
+        args: (wix: usize, set_up_when_mapped: bool);
+
+        case: visible_workspace => (0, true);
+        case: hidden_workspace => (5, false);
+
+        body: {
+            let mut wm = wm_with_floating_rule(wix);
+            wm.handle_map_request(10).unwrap();
+            let calls = setup_calls(&wm);
+            assert_eq!(calls.contains(&"get_prop".to_string()), set_up_when_mapped);
+            assert_eq!(calls.contains(&"position_client".to_string()), set_up_when_mapped);
+
+            wm.focus_workspace(&Selector::Index(wix)).unwrap();
+            let calls = setup_calls(&wm);
+            assert_eq!(calls.contains(&"get_prop".to_string()), !set_up_when_mapped);
+            assert_eq!(calls.contains(&"position_client".to_string()), !set_up_when_mapped);
+        }
+    }
+
+    #[test]
+    fn hidden_clients_are_only_renamed_and_moved_when_shown() {
+        let mut wm = wm_with_floating_rule(5);
+        wm.handle_map_request(10).unwrap();
+        wm.conn().clear();
+
+        let first = Region::new(0, 0, 100, 100);
+        let r = Region::new(10, 20, 300, 400);
+        wm.handle_event_actions(vec![
+            EventAction::ClientNameChanged(10, false),
+            EventAction::MoveClientIfFloating(10, first),
+            EventAction::MoveClientIfFloating(10, r),
+        ])
+        .unwrap();
+        assert!(setup_calls(&wm).is_empty());
+
+        wm.focus_workspace(&Selector::Index(5)).unwrap();
+        let calls: Vec<RecordedCall> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "client_name" || m == "position_client")
+            .collect();
+        assert!(calls.contains(&("client_name".into(), strings!(10))));
+        assert!(calls.contains(&("position_client".into(), strings!(10, r, 0, true))));
+        assert!(!calls.contains(&("position_client".into(), strings!(10, first, 0, true))));
+    }
+
+    #[test]
+    fn switching_workspace_maps_new_clients_before_unmapping_old_ones() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        wm.move_client_to_workspace(20, 5).unwrap();
+        wm.conn().clear();
+        wm.focus_workspace(&Selector::Index(5)).unwrap();
+
+        let calls: Vec<String> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .flat_map(|(m, args)| match m.as_str() {
+                "set_client_attributes" | "raise_client" => Some(format!("{} {:?}", m, args)),
+                "map_client_if_needed" | "unmap_client_if_needed" => Some(m),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            calls,
+            vec![
+                r#"set_client_attributes ["10", "[ClientEventMaskNoCrossing]"]"#,
+                r#"set_client_attributes ["20", "[ClientEventMaskNoCrossing]"]"#,
+                "map_client_if_needed",
+                "map_client_if_needed", // from applying the layout
+                r#"raise_client ["20"]"#,
+                "unmap_client_if_needed",
+                r#"set_client_attributes ["10", "[ClientEventMask]"]"#,
+                r#"set_client_attributes ["20", "[ClientEventMask]"]"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn mru_cycling_without_modifiers_commits_immediately() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 3, 0);
+        wm.move_client_to_workspace(10, 5).unwrap();
+        assert_eq!(wm.focus_history(), vec![30, 20, 10]);
+
+        wm.cycle_focus_mru(Forward, false).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(20));
+        assert_eq!(wm.focus_history(), vec![20, 30, 10]);
+
+        wm.cycle_focus_mru(Forward, false).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(30));
+
+        wm.cycle_focus_mru(Backward, true).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(10));
+        assert_eq!(wm.active_workspace().name(), "6");
+        assert_eq!(wm.focus_history(), vec![10, 30, 20]);
+    }
+
+    #[test]
+    fn mru_cycling_with_a_modifier_held_commits_on_release() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        wm.conn().clear();
+
+        // Alt + Tab
+        wm.running_key = Some(KeyCode { mask: 8, code: 23 });
+        wm.cycle_focus_mru(Forward, false).unwrap();
+        wm.cycle_focus_mru(Forward, false).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(10));
+
+        // focus changes from the pointer are ignored until the cycle is complete
+        wm.handle_event_actions(vec![EventAction::ClientFocusGained(20)])
+            .unwrap();
+        assert_eq!(wm.focused_client_id(), Some(10));
+
+        wm.handle_event_actions(vec![EventAction::KeyReleased(KeyCode {
+            mask: 8,
+            code: 64,
+        })])
+        .unwrap();
+        assert!(wm.mru_cycle.is_none());
+        assert_eq!(wm.focus_history(), vec![10, 30, 20]);
+
+        let keyboard_grabs: Vec<String> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .map(|(m, _)| m)
+            .filter(|m| m.ends_with("grab_keyboard"))
+            .collect();
+        assert_eq!(keyboard_grabs, vec!["grab_keyboard", "ungrab_keyboard"]);
+    }
+
+    #[test]
+    fn unmaps_requested_by_penrose_do_not_remove_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.move_client_to_workspace(10, 5).unwrap();
+        wm.handle_unmap_notify(10).unwrap();
+
+        assert!(wm.clients.is_known(10));
+        assert_eq!(wm.workspaces[5].iter().collect::<Vec<_>>(), vec![&10]);
+    }
+
+    #[test]
+    fn client_initiated_unmaps_remove_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.move_client_to_workspace(10, 5).unwrap();
+        wm.handle_unmap_notify(10).unwrap();
+        wm.handle_unmap_notify(10).unwrap();
+        wm.handle_unmap_notify(20).unwrap();
+
+        assert!(!wm.clients.is_known(10));
+        assert!(!wm.clients.is_known(20));
+        assert!(wm.workspaces[0].is_empty());
+        assert!(wm.workspaces[5].is_empty());
+    }
+
+    #[test]
+    fn game_mode_only_grabs_the_binding_that_enabled_it() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+
+        let toggle = KeyCode { mask: 0, code: 1 };
+        let mut bindings: KeyBindings<RecordingXConn> = HashMap::new();
+        bindings.insert(
+            toggle,
+            Box::new(|wm: &mut WindowManager<_>| wm.toggle_game_mode()),
+        );
+        bindings.insert(
+            KeyCode { mask: 0, code: 2 },
+            Box::new(|_: &mut WindowManager<_>| Ok(())),
+        );
+        let mouse_bindings = HashMap::new();
+
+        let mut grabs = vec![];
+        for _ in 0..2 {
+            wm.run_key_binding(toggle, &mut bindings);
+            wm.conn().clear();
+            wm.update_grabs(&bindings, &mouse_bindings).unwrap();
+            grabs.push(wm.conn().calls());
+        }
+
+        let expected = |codes: &[&str]| -> Vec<RecordedCall> {
+            vec![
+                ("ungrab_keys".into(), vec![]),
+                (
+                    "grab_keys".into(),
+                    codes.iter().map(|s| s.to_string()).collect(),
+                ),
+            ]
+        };
+
+        assert!(!wm.game_mode());
+        assert_eq!(grabs[0], expected(&["1", "0 mouse bindings"]));
+        assert_eq!(grabs[1], expected(&["1", "2", "0 mouse bindings"]));
+    }
+
+    #[test]
+    fn keyboard_mapping_changes_refresh_the_keymap_and_regrab_bindings() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.conn().clear();
+
+        let mut bindings: KeyBindings<RecordingXConn> = HashMap::new();
+        bindings.insert(
+            KeyCode { mask: 0, code: 1 },
+            Box::new(|_: &mut WindowManager<_>| Ok(())),
+        );
+        let mut mouse_bindings = HashMap::new();
+
+        wm.handle_event_action(
+            EventAction::KeyboardMappingChanged,
+            Some(&mut bindings),
+            Some(&mut mouse_bindings),
+        )
+        .unwrap();
+
+        assert_eq!(
+            wm.conn().calls(),
+            vec![
+                ("refresh_keymap".into(), vec![]),
+                ("ungrab_keys".into(), vec![]),
+                (
+                    "grab_keys".into(),
+                    vec!["1".into(), "0 mouse bindings".into()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_chords_take_priority_until_a_key_is_pressed() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+
+        let (root_code, chord_code) = (KeyCode { mask: 0, code: 1 }, KeyCode { mask: 0, code: 2 });
+        let mut bindings: KeyBindings<RecordingXConn> = HashMap::new();
+        bindings.insert(
+            root_code,
+            Box::new(|wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(1))),
+        );
+        let mut chord_bindings: KeyBindings<RecordingXConn> = HashMap::new();
+        chord_bindings.insert(
+            chord_code,
+            Box::new(|wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(3))),
+        );
+        let chord = KeyChord::new(chord_bindings);
+        let mut mouse_bindings = HashMap::new();
+
+        let mut press = |wm: &mut WindowManager<_>, k: KeyCode| {
+            wm.handle_event_action(
+                EventAction::RunKeyBinding(k),
+                Some(&mut bindings),
+                Some(&mut mouse_bindings),
+            )
+            .unwrap();
+        };
+
+        // pressing a key bound in the chord runs the chord binding and ends the chord
+        wm.enter_key_chord(chord.clone()).unwrap();
+        assert!(wm.key_chord_active());
+        press(&mut wm, chord_code);
+        assert!(!wm.key_chord_active());
+        assert_eq!(wm.active_workspace_index(), 3);
+
+        // top level bindings cancel the chord and run as normal
+        wm.enter_key_chord(chord.clone()).unwrap();
+        press(&mut wm, root_code);
+        assert!(!wm.key_chord_active());
+        assert_eq!(wm.active_workspace_index(), 1);
+
+        // once the chord has timed out its bindings are no longer run
+        wm.enter_key_chord(chord.timeout(Duration::from_millis(0)))
+            .unwrap();
+        press(&mut wm, chord_code);
+        assert!(!wm.key_chord_active());
+        assert_eq!(wm.active_workspace_index(), 1);
+    }
+
+    #[test]
+    fn toggling_the_bar_gives_its_space_to_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        let with_bar = wm.screen_size(0).unwrap();
+        assert!(wm.bar_visible());
+
+        wm.toggle_bar().unwrap();
+        assert!(!wm.bar_visible());
+        assert_eq!(wm.screen_size(0), Some(Region::new(0, 0, 1366, 768)));
+
+        wm.toggle_bar().unwrap();
+        assert_eq!(wm.screen_size(0), Some(with_bar));
+    }
+
+    #[test]
+    fn wake_after_sets_the_earliest_deadline_until_it_has_passed() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        assert_eq!(wm.next_deadline(), None);
+
+        wm.wake_after(Duration::from_secs(60));
+        let later = wm.next_deadline().unwrap();
+        wm.wake_after(Duration::from_millis(0));
+        let sooner = wm.next_deadline().unwrap();
+        assert!(sooner < later);
+
+        wm.wake_after(Duration::from_secs(60));
+        assert_eq!(wm.next_deadline(), Some(sooner));
+
+        wm.handle_timeouts();
+        assert_eq!(wm.next_deadline(), None);
+    }
+
+    #[test]
+    fn hot_corners_run_their_action_when_entered() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.conn().clear();
+
+        let mut bindings: HashMap<HotCorner, HotCornerHandler<RecordingXConn>> = HashMap::new();
+        bindings.insert(
+            HotCorner::TopLeft,
+            Box::new(|wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(2))),
+        );
+        wm.set_hot_corners(HotCorners::new(bindings).delay(Duration::from_millis(0)))
+            .unwrap();
+
+        let created: Vec<_> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "create_input_window")
+            .collect();
+        assert_eq!(created.len(), wm.n_screens());
+        assert_eq!(wm.hot_corners.windows().len(), wm.n_screens());
+
+        let id = *wm.hot_corners.windows().keys().next().unwrap();
+        let p = PointerChange {
+            id,
+            abs: Point::new(0, 0),
+            relative: Point::new(0, 0),
+        };
+
+        let actions = process_next_event(XEvent::Enter(p), WmState::new(&wm));
+        assert_eq!(
+            actions,
+            vec![EventAction::HotCornerEntered(HotCorner::TopLeft)]
+        );
+        for action in actions {
+            wm.handle_event_action(action, None, None).unwrap();
+        }
+
+        assert_eq!(wm.active_workspace_index(), 2);
+    }
+
+    #[test]
+    fn leaving_a_hot_corner_cancels_its_action() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+
+        let mut bindings: HashMap<HotCorner, HotCornerHandler<RecordingXConn>> = HashMap::new();
+        bindings.insert(
+            HotCorner::Right,
+            Box::new(|wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(2))),
+        );
+        wm.set_hot_corners(HotCorners::new(bindings)).unwrap();
+
+        wm.handle_event_action(EventAction::HotCornerEntered(HotCorner::Right), None, None)
+            .unwrap();
+        assert!(wm.next_deadline().is_some());
+
+        wm.handle_event_action(EventAction::HotCornerLeft, None, None)
+            .unwrap();
+        assert!(wm.next_deadline().is_none());
+        assert_eq!(wm.active_workspace_index(), 0);
+    }
+
+    #[test]
+    fn desktop_pointer_bindings_run_for_their_button() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.conn().clear();
+        wm.set_pointer_bindings(gen_pointerbindings! {
+            Desktop ScrollDown + [] => |wm: &mut WindowManager<_>| wm.cycle_workspace(Forward)
+        })
+        .unwrap();
+
+        let root = wm.conn().root();
+        let press = |button| {
+            MouseEvent::new(
+                root,
+                10,
+                10,
+                10,
+                10,
+                MouseState::new(button, vec![]),
+                MouseEventKind::Press,
+            )
+        };
+
+        assert!(wm.conn().calls().contains(&(
+            "set_client_attributes".to_string(),
+            strings!(root, vec![ClientAttr::RootEventMaskWithClicks])
+        )));
+
+        wm.run_mouse_binding(press(MouseButton::ScrollDown), &mut HashMap::new());
+        assert_eq!(wm.active_workspace_index(), 1);
+
+        wm.run_mouse_binding(press(MouseButton::ScrollUp), &mut HashMap::new());
+        assert_eq!(wm.active_workspace_index(), 1);
+    }
+
+    #[test]
+    fn edge_pointer_bindings_are_given_input_windows() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.set_pointer_bindings(gen_pointerbindings! {
+            Edge(Left) Left + [] => |wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(3))
+        })
+        .unwrap();
+
+        assert_eq!(wm.hot_corners.windows().len(), wm.n_screens());
+        let (&id, _) = wm
+            .hot_corners
+            .windows()
+            .iter()
+            .find(|(_, &(i, _))| i == 0)
+            .unwrap();
+        let press = MouseEvent::new(
+            id,
+            0,
+            100,
+            0,
+            100,
+            MouseState::new(MouseButton::Left, vec![]),
+            MouseEventKind::Press,
+        );
+
+        wm.run_mouse_binding(press, &mut HashMap::new());
+        assert_eq!(wm.active_workspace_index(), 3);
+    }
+
+    test_cases! {
+        pointer_warp;
+        args: (warp: PointerWarp, target_ws: usize, expected: Vec<Vec<String>>);
+
+        case: never => (PointerWarp::Never, 0, vec![]);
+        case: to_screen => (PointerWarp::ToScreen, 0, vec![strings!(None::<Xid>)]);
+        case: to_client => (PointerWarp::ToClient, 0, vec![strings!(Some(20))]);
+        case: to_client_on_empty_workspace => (PointerWarp::ToClient, 2, vec![strings!(None::<Xid>)]);
+
+        body: {
+            let config = Config {
+                warp_on_workspace_change: warp,
+                ..Default::default()
+            };
+            let mut wm = WindowManager::new(
+                config,
+                RecordingXConn::init(),
+                vec![],
+                logging_error_handler(),
+            );
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 2, 0);
+            wm.focus_workspace(&Selector::Index(3)).unwrap();
+            wm.conn().clear();
+
+            wm.focus_workspace(&Selector::Index(target_ws)).unwrap();
+            let warps: Vec<Vec<String>> = wm
+                .conn()
+                .calls()
+                .into_iter()
+                .filter(|(m, _)| m == "warp_cursor")
+                .map(|(_, args)| args)
+                .collect();
+
+            assert_eq!(warps, expected);
+        }
+    }
+
+    #[test]
+    fn remote_commands_float_and_tile_clients() {
+        use crate::contrib::extensions::RemoteCommand;
+
+        let mut wm = WindowManager::new(
+            Config::default()
+                .builder()
+                .remote_commands(vec!["move-window", "tile-window"])
+                .build()
+                .unwrap(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        wm.conn().clear();
+
+        let r = Region::new(100, 200, 300, 400);
+        let send = |wm: &mut WindowManager<RecordingXConn>, cmd: RemoteCommand| {
+            let msg = XEvent::ClientMessage(cmd.as_client_message(0));
+            let actions = process_next_event(msg, WmState::new(wm));
+            wm.handle_event_actions(actions).unwrap();
+        };
+
+        send(&mut wm, RemoteCommand::MoveWindow(20, r));
+        assert!(wm.clients.get(20).unwrap().floating);
+        let positions: Vec<Vec<String>> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, args)| m == "position_client" && args[0] == "20")
+            .map(|(_, args)| args)
+            .collect();
+        assert_eq!(positions, vec![strings!(20, r, 2, true)]);
+
+        send(&mut wm, RemoteCommand::TileWindow(20));
+        assert!(!wm.clients.get(20).unwrap().floating);
+    }
+
+    #[test]
+    fn remote_commands_not_enabled_in_the_config_are_ignored() {
+        use crate::contrib::extensions::RemoteCommand;
+
+        let config = Config::default()
+            .builder()
+            .remote_commands(vec!["tile-window"])
+            .build()
+            .unwrap();
+        let mut wm = WindowManager::new(
+            config,
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+
+        let cmd = RemoteCommand::MoveWindow(20, Region::new(100, 200, 300, 400));
+        let msg = XEvent::ClientMessage(cmd.as_client_message(0));
+        let actions = process_next_event(msg, WmState::new(&wm));
+
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn gesture_and_power_messages_are_ignored_unless_enabled() {
+        let wm = wm_with_mock_conn(vec![], vec![]);
+        let gesture = Gesture::new(3, crate::core::bindings::SwipeDirection::Left);
+        let messages = vec![
+            (Atom::PenroseGesture, gesture.as_data()),
+            (Atom::PenrosePowerEvent, PowerEvent::LidClosed.as_data()),
+        ];
+
+        for (atom, data) in messages {
+            let msg = ClientMessage::new(
+                0,
+                ClientEventMask::SubstructureNotify,
+                atom.as_ref(),
+                data.into(),
+            );
+            let actions = process_next_event(XEvent::ClientMessage(msg), WmState::new(&wm));
+            assert_eq!(actions, vec![]);
+        }
+    }
+
+    #[test]
+    fn gesture_client_messages_run_the_matching_gesture_binding() {
+        let mut wm = WindowManager::new(
+            Config::default()
+                .builder()
+                .remote_commands(vec!["gesture"])
+                .build()
+                .unwrap(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.set_gesture_bindings(gen_gesturebindings! {
+            3 Left => |wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(2)),
+            3 Right => |wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(4))
+        });
+
+        let gesture = Gesture::new(3, crate::core::bindings::SwipeDirection::Left);
+        let msg = ClientMessage::new(
+            0,
+            ClientEventMask::SubstructureNotify,
+            Atom::PenroseGesture.as_ref(),
+            gesture.as_data().into(),
+        );
+
+        let actions = process_next_event(XEvent::ClientMessage(msg), WmState::new(&wm));
+        assert_eq!(actions, vec![EventAction::RunGestureBinding(gesture)]);
+        for action in actions {
+            wm.handle_event_action(action, None, None).unwrap();
+        }
+
+        assert_eq!(wm.active_workspace_index(), 2);
+    }
+
+    #[test]
+    fn power_events_are_passed_to_hooks() {
+        let mut wm = WindowManager::new(
+            Config::default()
+                .builder()
+                .remote_commands(vec!["power-event"])
+                .build()
+                .unwrap(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        let actions_for = |wm: &WindowManager<_>, event: PowerEvent| {
+            let msg = ClientMessage::new(
+                0,
+                ClientEventMask::SubstructureNotify,
+                Atom::PenrosePowerEvent.as_ref(),
+                event.as_data().into(),
+            );
+            process_next_event(XEvent::ClientMessage(msg), WmState::new(wm))
+        };
+
+        assert_eq!(
+            actions_for(&wm, PowerEvent::LidClosed),
+            vec![EventAction::RunHook(HookName::LidStateChanged(true))]
+        );
+        assert_eq!(
+            actions_for(&wm, PowerEvent::PrepareForSleep),
+            vec![EventAction::Suspending]
+        );
+
+        // a resume is only handled if we have seen the system going to sleep and have not already
+        // spotted it from the system clock
+        assert_eq!(actions_for(&wm, PowerEvent::Resumed), vec![]);
+        wm.handle_event_action(EventAction::Suspending, None, None)
+            .unwrap();
+        assert_eq!(
+            actions_for(&wm, PowerEvent::Resumed),
+            vec![EventAction::Resumed]
+        );
+        wm.handle_event_action(EventAction::Resumed, None, None)
+            .unwrap();
+        assert_eq!(actions_for(&wm, PowerEvent::Resumed), vec![]);
+    }
+
+    #[test]
+    fn gesture_bindings_set_by_a_gesture_binding_are_merged_with_the_existing_ones() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.set_gesture_bindings(gen_gesturebindings! {
+            3 Left => |wm: &mut WindowManager<_>| {
+                wm.set_gesture_bindings(gen_gesturebindings! {
+                    4 Up => |wm: &mut WindowManager<_>| wm.focus_workspace(&Selector::Index(4))
+                });
+                Ok(())
+            }
+        });
+
+        let left = Gesture::new(3, crate::core::bindings::SwipeDirection::Left);
+        let up = Gesture::new(4, crate::core::bindings::SwipeDirection::Up);
+        wm.run_gesture_binding(left);
+
+        assert!(wm.gesture_bindings.contains_key(&left));
+        assert!(wm.gesture_bindings.contains_key(&up));
+    }
+
+    #[test]
+    fn pointer_config_is_applied_to_matching_devices_on_hotplug() {
+        let conf = Config {
+            pointer_config: vec![
+                PointerConfig::new("TouchPad").natural_scrolling(true),
+                PointerConfig::new("Trackball").accel_speed(0.5),
+            ],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(
+            conf,
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.conn().clear();
+
+        wm.handle_event_action(EventAction::InputDevicesChanged, None, None)
+            .unwrap();
+
+        let configured: Vec<RecordedCall> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(method, _)| method == "configure_pointer_device")
+            .collect();
+
+        assert_eq!(
+            configured,
+            vec![("configure_pointer_device".into(), vec!["11".into()])]
+        );
+    }
+
+    #[test]
+    fn failing_to_configure_one_device_does_not_stop_the_others() {
+        let devices = vec![
+            InputDevice {
+                id: 1,
+                name: "Broken TouchPad".into(),
+            },
+            InputDevice {
+                id: 2,
+                name: "Other TouchPad".into(),
+            },
+        ];
+        let conn = ConfigurableXConn::default().with_pointer_devices(devices, vec![1]);
+        let conf = Config {
+            pointer_config: vec![PointerConfig::new("TouchPad").natural_scrolling(true)],
+            ..Default::default()
+        };
+        let errors = Rc::new(Cell::new(0));
+        let e = Rc::clone(&errors);
+        let handler: ErrorHandler = Box::new(move |_| e.set(e.get() + 1));
+        let mut wm = WindowManager::new(conf, conn, vec![], handler);
+        wm.init().unwrap();
+
+        wm.handle_event_action(EventAction::InputDevicesChanged, None, None)
+            .unwrap();
+
+        let configured: Vec<_> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "configure_pointer_device")
+            .collect();
+        assert_eq!(
+            configured,
+            vec![("configure_pointer_device".into(), strings!(2))]
+        );
+        assert_eq!(errors.get(), 1);
+    }
+
+    #[test]
+    fn keyboard_config_is_only_reapplied_when_a_keyboard_is_added() {
+        let keyboard = |id: u16| InputDevice {
+            id,
+            name: format!("keyboard {}", id),
+        };
+        let conf = Config {
+            keyboard_config: Some(KeyboardConfig::new().layout("gb")),
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(
+            conf,
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.conn().set_keyboards(vec![keyboard(3)]);
+
+        assert!(wm.detect_new_keyboards());
+        assert!(!wm.detect_new_keyboards());
+
+        wm.conn().set_keyboards(vec![keyboard(3), keyboard(4)]);
+        assert!(wm.detect_new_keyboards());
+
+        wm.conn().set_keyboards(vec![keyboard(4)]);
+        assert!(!wm.detect_new_keyboards());
+
+        wm.conn().set_keyboards(vec![keyboard(3), keyboard(4)]);
+        assert!(wm.detect_new_keyboards());
+
+        wm.conn().clear();
+        wm.handle_event_action(EventAction::InputDevicesChanged, None, None)
+            .unwrap();
+        let calls: Vec<String> = wm.conn().calls().into_iter().map(|(m, _)| m).collect();
+        assert!(calls.contains(&"keyboard_devices".to_string()));
+    }
+
+    #[test]
+    fn resuming_from_suspend_detects_screens_and_grabs_bindings_again() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        wm.conn().clear();
+
+        let (mut key_bindings, mut mouse_bindings) = (HashMap::new(), HashMap::new());
+        wm.handle_event_action(
+            EventAction::Resumed,
+            Some(&mut key_bindings),
+            Some(&mut mouse_bindings),
+        )
+        .unwrap();
+
+        let calls: Vec<String> = wm.conn().calls().into_iter().map(|(m, _)| m).collect();
+        assert!(calls.contains(&"current_screens".to_string()));
+        assert!(calls.contains(&"grab_keys".to_string()));
+    }
+
+    #[test]
+    fn game_mode_disables_focus_follows_mouse_and_focus_stealing() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.toggle_game_mode().unwrap();
+
+        wm.handle_event_action(EventAction::ClientFocusGained(10), None, None)
+            .unwrap();
+        wm.handle_event_action(EventAction::SetActiveClient(10), None, None)
+            .unwrap();
+
+        assert_eq!(wm.focused_client_id(), Some(20));
+        assert_eq!(wm.urgent_workspaces(), vec![0]);
+
+        wm.toggle_game_mode().unwrap();
+        wm.handle_event_action(EventAction::ClientFocusGained(10), None, None)
+            .unwrap();
+
+        assert_eq!(wm.focused_client_id(), Some(10));
+    }
+
+    #[test]
+    fn swapping_in_a_direction_uses_the_last_applied_layout() {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: vec![Layout::new(
+                "[side]",
+                LayoutConf::default(),
+                side_stack,
+                1,
+                0.6,
+            )],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 20, 10]);
+
+        wm.swap_in_direction(RelativePosition::Right).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![20, 30, 10]);
+        assert_eq!(wm.focused_client_id(), Some(30));
+
+        wm.swap_in_direction(RelativePosition::Below).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![20, 10, 30]);
+
+        wm.swap_in_direction(RelativePosition::Left).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 10, 20]);
+
+        // nothing above the main client
+        wm.swap_in_direction(RelativePosition::Above).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 10, 20]);
+        assert_eq!(wm.focused_client_id(), Some(30));
+    }
+
+    #[test]
+    fn swapping_in_a_direction_moves_the_client_into_the_nearest_slot() {
+        let conf = Config {
+            layouts: vec![Layout::new(
+                "[side]",
+                LayoutConf::default(),
+                side_stack,
+                1,
+                0.6,
+            )],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(
+            conf,
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 4, 0);
+        wm.focus_client(&Selector::WinId(30)).unwrap();
+
+        let positions = |wm: &WindowManager<RecordingXConn>| -> HashMap<String, String> {
+            wm.conn()
+                .calls()
+                .into_iter()
+                .filter(|(m, _)| m == "position_client")
+                .map(|(_, args)| (args[0].clone(), args[1].clone()))
+                .collect()
+        };
+
+        // 40 is the main client with 30, 20 and 10 stacked top to bottom to its right
+        wm.apply_layout(0).unwrap();
+        let before = positions(&wm);
+        wm.conn().clear();
+
+        // 20 is directly below 30 so it is picked over 10, and the layout is re-applied
+        wm.swap_in_direction(RelativePosition::Below).unwrap();
+        let after = positions(&wm);
+
+        assert_eq!(wm.active_workspace().client_ids(), vec![40, 20, 30, 10]);
+        assert_eq!(wm.focused_client_id(), Some(30));
+        assert_eq!(after["30"], before["20"]);
+        assert_eq!(after["20"], before["30"]);
+        assert_eq!(after["40"], before["40"]);
+        assert_eq!(after["10"], before["10"]);
+    }
+
+    #[test]
+    fn minimized_clients_are_restored_most_recent_first() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 3, 0);
+        assert_eq!(wm.focused_client_id(), Some(30));
+
+        wm.minimize_focused().unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![20, 10]);
+        assert_eq!(wm.focused_client_id(), Some(20));
+
+        wm.minimize_focused().unwrap();
+        assert_eq!(wm.minimized_clients(0), vec![30, 20]);
+        assert!(wm.client(&Selector::WinId(20)).unwrap().is_minimized());
+        assert!(!wm.client(&Selector::WinId(20)).unwrap().mapped);
+
+        wm.restore_last_minimized().unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![20, 10]);
+        assert_eq!(wm.focused_client_id(), Some(20));
+        assert_eq!(wm.minimized_clients(0), vec![30]);
+        assert!(!wm.client(&Selector::WinId(20)).unwrap().is_minimized());
+    }
+
+    #[test]
+    fn minimized_clients_are_dropped_when_removed() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.minimize_focused().unwrap();
+        assert_eq!(wm.minimized_clients(0), vec![20]);
+
+        wm.remove_client(20).unwrap();
+        assert_eq!(wm.minimized_clients(0), vec![]);
+        assert_eq!(wm.active_workspace().client_ids(), vec![10]);
+    }
+
+    #[test]
+    fn growing_the_focused_client_only_changes_its_weight() {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: vec![Layout::new(
+                "[side]",
+                LayoutConf::default(),
+                side_stack,
+                1,
+                0.6,
+            )],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        let weight = |wm: &WindowManager<_>, id| wm.client(&Selector::WinId(id)).unwrap().weight();
+
+        // the main client has nothing sharing its column or row
+        wm.grow_focused(Change::More, 50).unwrap();
+        assert_eq!(weight(&wm, 30), DEFAULT_CLIENT_WEIGHT);
+
+        wm.focus_client(&Selector::WinId(20)).unwrap();
+        wm.grow_focused(Change::More, 50).unwrap();
+        assert!(weight(&wm, 20) > DEFAULT_CLIENT_WEIGHT);
+        assert_eq!(weight(&wm, 10), DEFAULT_CLIENT_WEIGHT);
+
+        wm.grow_focused(Change::Less, 100).unwrap();
+        assert!(weight(&wm, 20) < DEFAULT_CLIENT_WEIGHT);
+
+        wm.reset_client_weights().unwrap();
+        assert_eq!(weight(&wm, 20), DEFAULT_CLIENT_WEIGHT);
+    }
+
+    #[test]
+    fn focusing_in_a_direction_uses_the_last_applied_layout() {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: vec![Layout::new(
+                "[side]",
+                LayoutConf::default(),
+                side_stack,
+                1,
+                0.6,
+            )],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 20, 10]);
+        assert_eq!(wm.focused_client_id(), Some(30));
+
+        wm.focus_in_direction(RelativePosition::Right).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(20));
+
+        wm.focus_in_direction(RelativePosition::Below).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(10));
+
+        // nothing below the bottom of the stack
+        wm.focus_in_direction(RelativePosition::Below).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(10));
+
+        wm.focus_in_direction(RelativePosition::Left).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(30));
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 20, 10]);
+    }
+
+    #[test]
+    fn swapping_clients_by_position() {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: vec![Layout::new(
+                "[side]",
+                LayoutConf::default(),
+                side_stack,
+                1,
+                0.6,
+            )],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+
+        let main = Point::new(100, 100);
+        let stack = Point::new(1000, 700);
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 20, 10]);
+        assert_eq!(wm.tiled_client_at_point(main), Some(30));
+        assert_eq!(wm.tiled_client_at_point(stack), Some(10));
+        assert_eq!(wm.tiled_client_at_point(Point::new(2000, 100)), None);
+
+        wm.swap_clients(10, 30).unwrap();
+
+        assert_eq!(wm.active_workspace().client_ids(), vec![10, 20, 30]);
+        assert_eq!(wm.tiled_client_at_point(main), Some(10));
+        assert_eq!(wm.tiled_client_at_point(stack), Some(30));
+        assert_eq!(wm.focused_client_id(), Some(10));
+    }
+
+    #[test]
+    fn selecting_a_window_by_clicking_on_it() {
+        let click = |button, x, y| {
+            let state = MouseState::new(button, vec![]);
+            XEvent::MouseEvent(MouseEvent::new(0, x, y, x, y, state, MouseEventKind::Press))
+        };
+        let events = vec![
+            click(MouseButton::Left, 100, 100),
+            XEvent::Destroy(30),
+            click(MouseButton::Left, 100, 100),
+            click(MouseButton::Right, 100, 100),
+            click(MouseButton::Left, 2000, 100),
+        ];
+        let conn = MockXConn::new(test_screens(), events, vec![]);
+        let conf = Config {
+            layouts: vec![Layout::new(
+                "[side]",
+                LayoutConf::default(),
+                side_stack,
+                1,
+                0.6,
+            )],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 3, 0);
+
+        assert_eq!(wm.select_window_interactive().unwrap(), Some(30));
+        // other events are still handled while waiting for a click
+        assert_eq!(wm.select_window_interactive().unwrap(), Some(20));
+        assert!(wm.client(&Selector::WinId(30)).is_none());
+        // any button other than left cancels the selection
+        assert_eq!(wm.select_window_interactive().unwrap(), None);
+        assert_eq!(wm.select_window_interactive().unwrap(), None);
+    }
+
+    #[test]
+    fn pressing_escape_cancels_selecting_a_window() {
+        let key = |code| XEvent::KeyPress(KeyCode { mask: 0, code });
+        let state = MouseState::new(MouseButton::Left, vec![]);
+        let click = MouseEvent::new(0, 100, 50, 100, 50, state, MouseEventKind::Press);
+        let conn = ConfigurableXConn::default().with_events(vec![
+            key(9),
+            key(38),
+            XEvent::MouseEvent(click),
+        ]);
+        conn.set_keycodes(vec![("Escape".to_string(), 9)].into_iter().collect());
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+
+        assert_eq!(wm.select_window_interactive().unwrap(), None);
+        // other keys are ignored
+        assert_eq!(wm.select_window_interactive().unwrap(), Some(10));
+    }
+
+    #[test]
+    fn selecting_with_a_key_press() {
+        let key = |code| XEvent::KeyPress(KeyCode { mask: 0, code });
+        let conn =
+            ConfigurableXConn::default().with_events(vec![key(40), key(38), key(39), key(9)]);
+        conn.set_keycodes(
+            vec![("Escape", 9), ("a", 38), ("s", 39), ("d", 40)]
+                .into_iter()
+                .map(|(name, code)| (name.to_string(), code))
+                .collect(),
+        );
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        let pressed = |k: &str| Some(InteractiveSelection::KeyPressed(k.to_string()));
+
+        // keys that were not asked for are ignored
+        assert_eq!(wm.select_interactive(&["a", "s"]).unwrap(), pressed("a"));
+        assert_eq!(wm.select_interactive(&["a", "s"]).unwrap(), pressed("s"));
+        assert_eq!(wm.select_interactive(&["a", "s"]).unwrap(), None);
+    }
+
+    #[test]
+    fn snapshot_captures_screens_workspaces_and_clients() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 3, 0);
+        wm.move_client_to_workspace(10, 1).unwrap();
+        wm.cycle_screen(Forward).unwrap();
+
+        let state = wm.snapshot();
+
+        assert_eq!(state.visible_workspaces(), vec![0, 1]);
+        assert_eq!(state.screens[1].region, Region::new(1366, 0, 1366, 768));
+        assert_eq!(state.focused_screen, 1);
+        assert_eq!(state.focused_workspace, 1);
+        assert_eq!(state.workspaces.len(), 9);
+        assert_eq!(state.workspaces[0].clients, vec![30, 20]);
+        assert_eq!(state.workspaces[1].clients, vec![10]);
+
+        let ids: Vec<Xid> = state.clients.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![30, 20, 10]);
+        let on_first: Vec<Xid> = state.clients_on_workspace(0).map(|c| c.id).collect();
+        assert_eq!(on_first, vec![30, 20]);
+        assert_eq!(state.client(10).map(|c| c.workspace), Some(1));
+        assert_eq!(state.client(40), None);
+    }
+
+    #[test]
+    fn update_main_ratio_by_uses_the_given_step() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        let ratio =
+            |wm: &WindowManager<MockXConn>| wm.workspaces.get_workspace(0).unwrap().main_ratio();
+        let initial = ratio(&wm);
+
+        wm.update_main_ratio_by(Change::More, 0.01).unwrap();
+        assert!((ratio(&wm) - (initial + 0.01)).abs() < 0.0001);
+
+        wm.update_main_ratio_by(Change::Less, 0.03).unwrap();
+        assert!((ratio(&wm) - (initial - 0.02)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn sticky_clients_follow_their_screen_across_workspaces() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.toggle_sticky(&Selector::WinId(10)).unwrap();
+
+        let client = wm.client(&Selector::WinId(10)).unwrap();
+        assert!(client.is_sticky());
+        assert!(client.floating);
+
+        // a hidden workspace being shown on the active screen
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert_eq!(wm.workspaces[2].client_ids(), vec![10]);
+        assert_eq!(wm.workspaces[0].client_ids(), vec![20]);
+        assert_eq!(wm.clients.get(10).map(|c| c.mapped), Some(true));
+        assert_eq!(wm.clients.get(20).map(|c| c.mapped), Some(false));
+
+        // swapping with the workspace shown on the other screen
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.workspaces[1].client_ids(), vec![10]);
+        assert!(wm.workspaces[2].is_empty());
+        assert!(wm.client(&Selector::WinId(10)).unwrap().floating);
+
+        // unsticking leaves the client where it is but it stays floating
+        wm.handle_event_action(EventAction::SetClientSticky(10, false), None, None)
+            .unwrap();
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(wm.workspaces[1].client_ids(), vec![10]);
+        assert!(!wm.client(&Selector::WinId(10)).unwrap().is_sticky());
+    }
+
+    // The most recent value written to _NET_WM_STATE for a client
+    fn last_net_wm_state(conn: &ConfigurableXConn, id: Xid) -> Option<String> {
+        let target = strings!(id, Atom::NetWmState.as_ref());
+        conn.calls()
+            .into_iter()
+            .rev()
+            .find(|(m, args)| m == "change_prop" && args[0..2] == target[..])
+            .map(|(_, args)| args[2].clone())
+    }
+
+    #[test]
+    fn net_wm_state_keeps_all_tracked_states() {
+        let conn = ConfigurableXConn::default();
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        let state = |atoms: &[Atom]| {
+            let atoms = atoms.iter().map(|a| a.as_ref().to_string()).collect();
+            Some(strings!(Prop::Atom(atoms)).remove(0))
+        };
+
+        wm.set_urgent(10, true).unwrap();
+        wm.conn().clear();
+        wm.set_sticky(10, true).unwrap();
+        assert_eq!(
+            last_net_wm_state(wm.conn(), 10),
+            state(&[Atom::NetWmStateDemandsAttention, Atom::NetWmStateSticky])
+        );
+
+        wm.set_fullscreen(10, true).unwrap();
+        assert_eq!(
+            last_net_wm_state(wm.conn(), 10),
+            state(&[
+                Atom::NetWmStateDemandsAttention,
+                Atom::NetWmStateFullscreen,
+                Atom::NetWmStateSticky
+            ])
+        );
+
+        wm.set_fullscreen(10, false).unwrap();
+        wm.set_urgent(10, false).unwrap();
+        assert_eq!(
+            last_net_wm_state(wm.conn(), 10),
+            state(&[Atom::NetWmStateSticky])
+        );
+    }
+
+    #[test]
+    fn clients_can_be_sticky_when_mapped() {
+        let sticky = Prop::Atom(vec![Atom::NetWmStateSticky.as_ref().to_string()]);
+        let conn = ConfigurableXConn::default().with_prop(10, Atom::NetWmState, sticky);
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.handle_map_request(10).unwrap();
+
+        let client = wm.client(&Selector::WinId(10)).unwrap();
+        assert!(client.is_sticky());
+        assert!(client.floating);
+    }
+
+    #[test]
+    fn net_wm_state_messages_can_change_two_states_at_once() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            ConfigurableXConn::default(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+
+        let fullscreen = wm
+            .conn()
+            .atom_id(Atom::NetWmStateFullscreen.as_ref())
+            .unwrap();
+        let sticky = wm.conn().atom_id(Atom::NetWmStateSticky.as_ref()).unwrap();
+        let msg = ClientMessage::new(
+            10,
+            ClientEventMask::SubstructureNotify,
+            Atom::NetWmState.as_ref(),
+            [1, fullscreen, sticky, 0, 0].into(),
+        );
+
+        let actions = process_next_event(XEvent::ClientMessage(msg), WmState::new(&wm));
+        assert_eq!(
+            actions,
+            vec![
+                EventAction::ToggleClientFullScreen(10, true),
+                EventAction::SetClientSticky(10, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn urgency_changes_are_handled_before_other_state_changes() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            ConfigurableXConn::default(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+
+        let fullscreen = wm
+            .conn()
+            .atom_id(Atom::NetWmStateFullscreen.as_ref())
+            .unwrap();
+        let attention = wm
+            .conn()
+            .atom_id(Atom::NetWmStateDemandsAttention.as_ref())
+            .unwrap();
+        let msg = ClientMessage::new(
+            10,
+            ClientEventMask::SubstructureNotify,
+            Atom::NetWmState.as_ref(),
+            [1, fullscreen, attention, 0, 0].into(),
+        );
+
+        let actions = process_next_event(XEvent::ClientMessage(msg), WmState::new(&wm));
+        assert_eq!(
+            actions,
+            vec![
+                EventAction::SetClientUrgent(10, true),
+                EventAction::ToggleClientFullScreen(10, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn spawned_programs_are_told_the_active_workspace_and_screen() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        let ws = wm.active_workspace().name().to_string();
+        let screen = wm.active_screen_index().to_string();
+
+        assert_eq!(
+            wm.spawn_env(),
+            vec![
+                (process::ENV_WORKSPACE, ws),
+                (process::ENV_WORKSPACE_INDEX, "2".to_string()),
+                (process::ENV_SCREEN, screen),
+            ]
+        );
+    }
+
+    #[test]
+    fn floating_clients_can_be_maximized_along_each_axis() {
+        let conf = Config {
+            show_bar: false,
+            border_px: 2,
+            ..Default::default()
+        };
+        let conn = ConfigurableXConn::default().with_geometry(10, Region::new(100, 50, 200, 100));
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        let last_position = |wm: &WindowManager<ConfigurableXConn>| {
+            wm.conn()
+                .calls()
+                .into_iter()
+                .rev()
+                .find(|(m, args)| m == "position_client" && args[0] == "10")
+                .map(|(_, args)| args)
+        };
+
+        // tiled clients are left to their layout
+        wm.conn().clear();
+        wm.toggle_maximized_horz(&Selector::WinId(10)).unwrap();
+        assert!(!wm.client(&Selector::WinId(10)).unwrap().is_maximized_horz());
+        assert_eq!(last_position(&wm), None);
+
+        wm.clients.modify(10, |c| c.set_floating(true));
+        wm.toggle_maximized_horz(&Selector::WinId(10)).unwrap();
+        assert_eq!(
+            last_position(&wm),
+            Some(strings!(10, Region::new(0, 50, 996, 100), 2, true))
+        );
+
+        wm.toggle_maximized_vert(&Selector::WinId(10)).unwrap();
+        let client = wm.client(&Selector::WinId(10)).unwrap();
+        assert!(client.is_maximized_horz() && client.is_maximized_vert());
+        assert_eq!(
+            last_position(&wm),
+            Some(strings!(10, Region::new(0, 0, 996, 796), 2, true))
+        );
+
+        // the original geometry is restored once neither axis is maximized
+        wm.toggle_maximized_horz(&Selector::WinId(10)).unwrap();
+        wm.toggle_maximized_vert(&Selector::WinId(10)).unwrap();
+        assert_eq!(
+            last_position(&wm),
+            Some(strings!(10, Region::new(100, 50, 200, 100), 2, true))
+        );
+        let client = wm.client(&Selector::WinId(10)).unwrap();
+        assert!(!client.is_maximized_horz() && !client.is_maximized_vert());
+    }
 
-    fn wm_with_mock_conn(events: Vec<XEvent>, unmanaged_ids: Vec<Xid>) -> WindowManager<MockXConn> {
-        let conn = MockXConn::new(test_screens(), events, unmanaged_ids);
-        let conf = Config {
-            layouts: focus_test_layouts(false),
-            ..Default::default()
-        };
-        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+    #[test]
+    fn maximizing_both_axes_is_a_single_action() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            ConfigurableXConn::default(),
+            vec![],
+            logging_error_handler(),
+        );
         wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
 
-        wm
+        let horz = Atom::NetWmStateMaximizedHorz.as_ref();
+        let vert = Atom::NetWmStateMaximizedVert.as_ref();
+        let (horz, vert) = (
+            wm.conn().atom_id(horz).unwrap(),
+            wm.conn().atom_id(vert).unwrap(),
+        );
+        let msg = ClientMessage::new(
+            10,
+            ClientEventMask::SubstructureNotify,
+            Atom::NetWmState.as_ref(),
+            [2, horz, vert, 0, 0].into(),
+        );
+
+        let actions = process_next_event(XEvent::ClientMessage(msg), WmState::new(&wm));
+        assert_eq!(
+            actions,
+            vec![EventAction::SetClientMaximized(10, true, true)]
+        );
     }
 
-    fn focus_test_layouts(follow_focus: bool) -> Vec<Layout> {
-        let conf = LayoutConf {
-            follow_focus,
+    #[test]
+    fn hidden_client_count_tracks_monocle_layout() {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: vec![Layout::new(
+                "[mono]",
+                LayoutConf::default(),
+                monocle,
+                1,
+                0.6,
+            )],
             ..Default::default()
         };
-        vec![Layout::new("t", conf, mock_layout, 1, 0.6)]
-    }
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
 
-    fn test_screens() -> Vec<Screen> {
-        vec![
-            Screen::new(Region::new(0, 0, 1366, 768), 0),
-            Screen::new(Region::new(1366, 0, 1366, 768), 0),
-        ]
-    }
+        assert_eq!(wm.hidden_client_count(0), 0);
+        add_n_clients(&mut wm, 3, 0);
+        assert_eq!(wm.hidden_client_count(0), 2);
 
-    fn add_n_clients<X: XConn>(wm: &mut WindowManager<X>, n: usize, offset: usize) {
-        for i in 0..n {
-            wm.handle_map_request(10 * (i + offset + 1) as u32).unwrap();
-        }
+        wm.client_to_workspace(&Selector::Index(5)).unwrap();
+        assert_eq!(wm.hidden_client_count(0), 1);
+
+        // Workspaces that are not visible have nothing to hide
+        wm.client_to_workspace(&Selector::Index(5)).unwrap();
+        assert_eq!(wm.hidden_client_count(0), 0);
+        assert_eq!(wm.hidden_client_count(5), 0);
     }
 
     #[test]
-    fn workspace_switching_with_active_clients() {
+    fn adding_workspaces_shifts_client_and_screen_indices() {
         let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+        let ws = || Workspace::new("new", focus_test_layouts(false));
+        wm.add_workspace(0, ws()).unwrap();
 
-        // add clients to the first workspace: final client should have focus
-        add_n_clients(&mut wm, 3, 0);
-        assert_eq!(wm.workspaces[0].len(), 3);
-        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
-
-        // switch and add to the second workspace: final client should have focus
-        wm.focus_workspace(&Selector::Index(1)).unwrap();
-        add_n_clients(&mut wm, 2, 3);
-        assert_eq!(wm.workspaces[1].len(), 2);
-        assert_eq!(wm.workspaces[1].focused_client(), Some(50));
+        assert_eq!(wm.workspace(&Selector::Index(0)).unwrap().name(), "new");
+        assert_eq!(wm.client(&Selector::WinId(10)).unwrap().workspace(), 1);
+        assert_eq!(wm.focused_workspaces(), vec![1, 2]);
+        assert!(wm.add_workspace(42, ws()).is_err());
+    }
 
-        // switch back: clients should be the same, same client should have focus
+    #[test]
+    fn removing_workspaces_merges_clients_into_a_neighbour() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 2, 0);
+        wm.focus_workspace(&Selector::Index(4)).unwrap();
+        add_n_clients(&mut wm, 1, 2);
         wm.focus_workspace(&Selector::Index(0)).unwrap();
-        assert_eq!(wm.workspaces[0].len(), 3);
-        assert_eq!(wm.workspaces[0].focused_client(), Some(30));
+
+        // hidden workspace: later indices shift down
+        wm.remove_workspace(&Selector::Index(2)).unwrap();
+        assert_eq!(wm.client(&Selector::WinId(30)).unwrap().workspace(), 3);
+        assert_eq!(wm.workspaces[3].client_ids(), vec![30]);
+
+        // visible workspace whose neighbour is visible on the other screen
+        wm.remove_workspace(&Selector::Index(0)).unwrap();
+        assert_eq!(wm.workspaces.len(), 7);
+        assert_eq!(wm.workspaces[0].name(), "2");
+        assert_eq!(wm.workspaces[0].client_ids(), vec![20, 10]);
+        assert_eq!(wm.client(&Selector::WinId(10)).unwrap().workspace(), 0);
+        assert_eq!(wm.focused_workspaces(), vec![1, 0]);
     }
 
     #[test]
-    fn killing_a_client_does_not_remove_it_from_the_workspace() {
+    fn removing_workspaces_maps_clients_that_become_visible() {
         let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 1, 0);
-        // Should trigger the kill but we wait for DestroyNotify before removing the
-        // client state
-        wm.kill_client().unwrap();
+        let add_floating = |wm: &mut WindowManager<MockXConn>, wix: usize, id: Xid| {
+            wm.focus_workspace(&Selector::Index(wix)).unwrap();
+            wm.handle_map_request(id).unwrap();
+            wm.clients.modify(id, |c| c.set_floating(true));
+        };
+        let mapped = |wm: &WindowManager<MockXConn>, id: Xid| wm.clients.get(id).unwrap().mapped;
+
+        add_floating(&mut wm, 3, 10);
+        add_floating(&mut wm, 2, 20);
+        assert!(!mapped(&wm, 10));
+
+        // hidden workspace merged into one that is visible
+        wm.remove_workspace(&Selector::Index(3)).unwrap();
+        assert_eq!(wm.workspaces[2].client_ids(), vec![10, 20]);
+        assert!(mapped(&wm, 10));
+
+        // visible workspace merged into one that was hidden
+        add_floating(&mut wm, 4, 30);
+        wm.focus_workspace(&Selector::Index(5)).unwrap();
+        assert!(!mapped(&wm, 30));
+        wm.remove_workspace(&Selector::Index(5)).unwrap();
+        assert_eq!(wm.focused_workspaces(), vec![4, 1]);
+        assert!(mapped(&wm, 30));
+        assert!(!mapped(&wm, 10));
+    }
 
-        assert_eq!(wm.workspaces[0].len(), 1);
+    #[test]
+    fn the_overflow_workspace_follows_added_and_removed_workspaces() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.config.overflow_workspace = Some(5);
+        let ws = || Workspace::new("new", focus_test_layouts(false));
+
+        wm.add_workspace(0, ws()).unwrap();
+        assert_eq!(wm.config.overflow_workspace, Some(6));
+        wm.remove_workspace(&Selector::Index(3)).unwrap();
+        assert_eq!(wm.config.overflow_workspace, Some(5));
+        wm.remove_workspace(&Selector::Index(5)).unwrap();
+        assert_eq!(wm.config.overflow_workspace, None);
     }
 
     #[test]
@@ -1415,6 +5696,27 @@ mod tests {
         assert_eq!(wm.workspaces[0].focused_client(), Some(10));
     }
 
+    #[test]
+    fn focus_urgent_jumps_to_most_recent_urgent_client() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 3, 0); // focus on last client: 30
+        wm.client_to_workspace(&Selector::Index(3)).unwrap();
+        wm.handle_event_action(EventAction::SetClientUrgent(10, true), None, None)
+            .unwrap();
+        wm.handle_event_action(EventAction::SetClientUrgent(30, true), None, None)
+            .unwrap();
+
+        assert_eq!(wm.urgent_workspaces(), vec![0, 3]);
+        wm.focus_urgent().unwrap();
+        assert_eq!(wm.focused_client_id(), Some(30));
+        assert_eq!(wm.active_workspace().name(), "4");
+        assert_eq!(wm.urgent_workspaces(), vec![0]);
+
+        wm.focus_urgent().unwrap();
+        assert_eq!(wm.focused_client_id(), Some(10));
+        assert!(wm.urgent_workspaces().is_empty());
+    }
+
     #[test]
     fn focus_workspace_sets_focus_in_ring() {
         let mut wm = wm_with_mock_conn(vec![], vec![]);
@@ -1494,33 +5796,468 @@ mod tests {
         );
     }
 
-    #[test]
-    fn selector_client() {
-        let mut wm = wm_with_mock_conn(vec![], vec![]);
-        add_n_clients(&mut wm, 4, 0);
+    #[test]
+    fn selector_client() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 4, 0);
+
+        assert_eq!(wm.client(&Selector::Focused), wm.clients.get(40));
+        assert_eq!(wm.client(&Selector::Index(2)), wm.clients.get(20));
+        assert_eq!(wm.client(&Selector::WinId(30)), wm.clients.get(30));
+        assert_eq!(
+            wm.client(&Selector::Condition(&|c| c.id() == 10)),
+            wm.clients.get(10)
+        );
+    }
+
+    #[test]
+    fn unmanaged_window_types_are_not_added_to_workspaces() {
+        // Setting the unmanaged window IDs here sets the return of
+        // MockXConn.is_managed_window to false for those IDs
+        let mut wm = wm_with_mock_conn(vec![], vec![10]);
+
+        wm.handle_map_request(10).unwrap(); // should not be tiled
+        assert!(wm.clients.get(10).is_some());
+        assert!(wm.workspaces[0].is_empty());
+
+        wm.handle_map_request(20).unwrap(); // should be tiled
+        assert!(wm.clients.get(20).is_some());
+        assert!(wm.workspaces[0].len() == 1);
+    }
+
+    fn wm_with_rules(rules: Vec<Rule>) -> WindowManager<MockXConn> {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            rules,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        wm
+    }
+
+    #[test]
+    fn rules_can_send_clients_to_other_workspaces() {
+        let mut wm = wm_with_rules(vec![Rule::new(
+            vec![],
+            vec![RuleAction::ToWorkspace(3), RuleAction::Float],
+        )]);
+
+        wm.handle_map_request(10).unwrap();
+        assert!(wm.workspaces[0].is_empty());
+        assert_eq!(wm.workspaces[3].client_ids(), vec![10]);
+        assert!(wm.clients.get(10).unwrap().floating);
+        assert_eq!(wm.focused_client_id(), None);
+        assert_eq!(wm.screens.active_ws_index(), 0);
+    }
+
+    #[test]
+    fn rules_targeting_invalid_workspaces_are_skipped() {
+        let mut wm = wm_with_rules(vec![Rule::new(vec![], vec![RuleAction::ToWorkspace(42)])]);
+
+        wm.handle_map_request(10).unwrap();
+        assert_eq!(wm.workspaces[0].client_ids(), vec![10]);
+    }
+
+    #[test]
+    fn clients_that_are_urgent_when_mapped_are_marked_as_urgent() {
+        let conf = Config {
+            rules: vec![Rule::new(vec![], vec![RuleAction::ToWorkspace(3)])],
+            ..Default::default()
+        };
+        let hints = WmHints::new(
+            WmHintsFlags::URGENCY_HINT,
+            true,
+            WindowState::Normal,
+            0,
+            0,
+            Point::new(0, 0),
+            0,
+            0,
+        );
+        let conn = ConfigurableXConn::default().with_prop(10, Atom::WmHints, Prop::WmHints(hints));
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.handle_map_request(10).unwrap();
+
+        assert!(wm.clients.get(10).unwrap().is_urgent());
+        assert_eq!(wm.urgent_workspaces(), vec![3]);
+    }
+
+    #[test]
+    fn ignored_clients_are_not_managed() {
+        let mut wm = wm_with_rules(vec![Rule::new(vec![], vec![RuleAction::Ignore])]);
+
+        wm.handle_map_request(10).unwrap();
+        assert!(wm.clients.get(10).is_none());
+        assert!(wm.workspaces[0].is_empty());
+    }
+
+    fn wm_with_auto_fullscreen(auto_fullscreen: bool) -> WindowManager<MockXConn> {
+        let conn = MockXConn::new(test_screens(), vec![], vec![]);
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            auto_fullscreen,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        wm
+    }
+
+    #[test]
+    fn rules_set_the_border_for_a_client_when_it_is_mapped() {
+        let blue = Color::try_from("#0000ff").unwrap();
+        let rule_actions = vec![RuleAction::ToWorkspace(5), RuleAction::Border(blue)];
+        let conf = Config {
+            rules: vec![Rule::new(vec![], rule_actions)],
+            ..Default::default()
+        };
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.conn().clear();
+
+        add_n_clients(&mut wm, 1, 0);
+
+        let borders: Vec<Vec<String>> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "set_client_border_color")
+            .map(|(_, args)| args)
+            .collect();
+        assert_eq!(borders, vec![strings!(10, blue)]);
+    }
+
+    #[test]
+    fn the_root_background_follows_the_focused_workspace() {
+        let red = Color::from(0xff0000);
+        let blue = Color::from(0x0000ff);
+        let green = Color::from(0x00ff00);
+        let conf = Config {
+            workspace_backgrounds: vec![red.into(), blue.into()],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(
+            conf,
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        wm.focus_workspace(&Selector::Index(2)).unwrap(); // no background so left as blue
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        wm.set_workspace_background(Some(green.into()), &Selector::Index(0))
+            .unwrap();
+        wm.set_workspace_background(Some(red.into()), &Selector::Index(1))
+            .unwrap(); // not focused so not shown
+
+        let backgrounds: Vec<Vec<String>> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "set_window_background")
+            .map(|(_, args)| args)
+            .collect();
+        let expected: Vec<Vec<String>> = vec![red, blue, red, green]
+            .into_iter()
+            .map(|c| strings!(42, c))
+            .collect();
+
+        assert_eq!(backgrounds, expected);
+    }
+
+    #[test]
+    fn background_colors_are_set_again_after_the_root_background_is_replaced() {
+        let red = Color::from(0xff0000);
+        let image = Wallpaper::new("image.png", WallpaperMode::Fill);
+        let conf = Config {
+            workspace_backgrounds: vec![red.into(), image.into()],
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(
+            conf,
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+
+        // showing an image means the root window no longer has the previous color
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        // as does drawing a wallpaper over it
+        wm.invalidate_root_background();
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+
+        let n_backgrounds = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "set_window_background")
+            .count();
+
+        assert_eq!(n_backgrounds, 3);
+    }
+
+    test_cases! {
+        two_tone_borders_are_redrawn_on_resize;
+        args: (inner_border_px: u32, expected: Vec<RecordedCall>);
+
+        case: disabled => (0, vec![]);
+        case: enabled => (1, vec![(
+            "set_client_border_colors".into(),
+            strings!(10, Color::from(0xff0000), Color::from(0x0000ff), 1)
+        )]);
+
+        body: {
+            let conf = Config {
+                unfocused_border: 0xff0000.into(),
+                inner_border: 0x0000ff.into(),
+                inner_border_px,
+                ..Default::default()
+            };
+            let conn = RecordingXConn::init();
+            let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+            wm.init().unwrap();
+            add_n_clients(&mut wm, 1, 0);
+            wm.clients.client_lost_focus(10, &wm.conn);
+            wm.conn().clear();
+
+            let evt = XEvent::ConfigureNotify(ConfigureEvent {
+                id: 10,
+                r: Region::new(0, 0, 100, 100),
+                is_root: false,
+            });
+            let actions = process_next_event(evt, WmState::new(&wm));
+            wm.handle_event_actions(actions).unwrap();
+
+            assert_eq!(wm.conn().calls(), expected);
+        }
+    }
+
+    #[test]
+    fn floating_clients_requesting_the_full_screen_are_made_fullscreen() {
+        let mut wm = wm_with_auto_fullscreen(true);
+        add_n_clients(&mut wm, 2, 0);
+        wm.clients.modify(20, |c| c.floating = true);
+        let screen = wm.screens.get(0).unwrap().region(false);
+
+        wm.handle_move_if_floating(10, screen).unwrap();
+        assert!(!wm.clients.get(10).unwrap().fullscreen);
+
+        wm.handle_move_if_floating(20, screen).unwrap();
+        assert!(wm.clients.get(20).unwrap().fullscreen);
+        assert!(wm.clients.is_auto_fullscreen(20));
+
+        wm.handle_move_if_floating(20, Region::new(10, 10, 200, 200))
+            .unwrap();
+        assert!(!wm.clients.get(20).unwrap().fullscreen);
+        assert!(!wm.clients.is_auto_fullscreen(20));
+    }
+
+    #[test]
+    fn explicitly_fullscreen_clients_are_not_restored_automatically() {
+        let mut wm = wm_with_auto_fullscreen(true);
+        add_n_clients(&mut wm, 1, 0);
+        wm.clients.modify(10, |c| c.floating = true);
+
+        wm.set_fullscreen(10, true).unwrap();
+        wm.handle_move_if_floating(10, Region::new(10, 10, 200, 200))
+            .unwrap();
+        assert!(wm.clients.get(10).unwrap().fullscreen);
+    }
+
+    #[test]
+    fn auto_fullscreen_is_disabled_by_default() {
+        assert!(!Config::default().auto_fullscreen);
+
+        let mut wm = wm_with_auto_fullscreen(false);
+        add_n_clients(&mut wm, 1, 0);
+        wm.clients.modify(10, |c| c.floating = true);
+
+        let screen = wm.screens.get(0).unwrap().region(false);
+        wm.handle_move_if_floating(10, screen).unwrap();
+        assert!(!wm.clients.get(10).unwrap().fullscreen);
+    }
+
+    #[test]
+    fn dock_struts_are_updated_while_the_dock_is_mapped() {
+        let dock = Prop::Atom(vec![Atom::NetWindowTypeDock.as_ref().to_string()]);
+        let conn = ConfigurableXConn::default()
+            .with_prop(99, Atom::NetWmWindowType, dock)
+            .with_prop(99, Atom::NetWmStrut, Prop::CardinalList(vec![0, 0, 30, 0]));
+        let conf = Config {
+            show_bar: false,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        assert_eq!(wm.screen_size(0).map(|r| r.y), Some(0));
+
+        wm.handle_map_request(99).unwrap();
+        assert_eq!(wm.screen_size(0).map(|r| r.y), Some(30));
+
+        wm.conn()
+            .set_prop(99, Atom::NetWmStrut, Prop::CardinalList(vec![0, 0, 45, 0]));
+        let evt = XEvent::PropertyNotify(PropertyEvent {
+            id: 99,
+            atom: Atom::NetWmStrut.as_ref().to_string(),
+            is_root: false,
+        });
+        let actions = process_next_event(evt, WmState::new(&wm));
+        assert_eq!(actions, vec![EventAction::ClientStrutChanged(99)]);
+        wm.handle_event_actions(actions).unwrap();
+        assert_eq!(wm.screen_size(0).map(|r| r.y), Some(45));
+
+        wm.handle_event_actions(vec![EventAction::DestroyClient(99)])
+            .unwrap();
+        assert_eq!(wm.screen_size(0).map(|r| r.y), Some(0));
+    }
+
+    fn swallow_test_conn(terminal_pid: u32) -> ConfigurableXConn {
+        let class = |c: &str| Prop::UTF8String(vec![c.into(), c.into()]);
+        let pid = std::process::id();
+
+        ConfigurableXConn::default()
+            .with_prop(10, Atom::WmClass, class("st"))
+            .with_prop(10, Atom::NetWmPid, Prop::Cardinal(terminal_pid))
+            .with_prop(20, Atom::WmClass, class("mpv"))
+            .with_prop(20, Atom::NetWmPid, Prop::Cardinal(pid))
+            .with_prop(40, Atom::WmClass, class("feh"))
+            .with_prop(40, Atom::NetWmPid, Prop::Cardinal(pid))
+    }
+
+    #[test]
+    fn terminals_are_swallowed_by_the_clients_they_start() {
+        // the test process stands in for a program started from the terminal
+        let terminal_pid = process::parent_pid(std::process::id()).unwrap();
+        let conf = Config {
+            show_bar: false,
+            rules: vec![
+                Rule::new(
+                    vec![Matcher::Class("st".into())],
+                    vec![RuleAction::Terminal],
+                ),
+                Rule::new(
+                    vec![Matcher::Class("feh".into())],
+                    vec![RuleAction::NoSwallow],
+                ),
+            ],
+            ..Default::default()
+        };
+        let conn = swallow_test_conn(terminal_pid);
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        wm.handle_map_request(10).unwrap();
+        wm.handle_map_request(30).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 10]);
+
+        // the child takes the place of the terminal, which is hidden
+        wm.handle_map_request(20).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 20]);
+        assert!(!wm.clients.is_known(10));
+        assert_eq!(wm.focused_client_id(), Some(20));
+
+        // unmapping the terminal ourselves is not mistaken for it withdrawing
+        wm.handle_event_actions(vec![EventAction::Unmap(10)])
+            .unwrap();
+
+        // once the child exits the terminal is restored in its place
+        wm.handle_event_actions(vec![EventAction::DestroyClient(20)])
+            .unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![30, 10]);
+        assert!(wm.clients.get(10).unwrap().mapped);
+        assert_eq!(wm.focused_client_id(), Some(10));
+
+        // clients matched by a NoSwallow rule are added as normal
+        wm.handle_map_request(40).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![40, 30, 10]);
+        assert!(wm.clients.get(10).unwrap().mapped);
+    }
+
+    #[test]
+    fn workspace_labels_follow_swallowed_terminals() {
+        let terminal_pid = process::parent_pid(std::process::id()).unwrap();
+        let conf = Config {
+            show_bar: false,
+            auto_name_workspaces: true,
+            rules: vec![Rule::new(
+                vec![Matcher::Class("st".into())],
+                vec![RuleAction::Terminal],
+            )],
+            ..Default::default()
+        };
+        let conn = swallow_test_conn(terminal_pid);
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        wm.handle_map_request(10).unwrap();
+        assert_eq!(wm.workspaces.workspace_names()[0], "1 st");
 
-        assert_eq!(wm.client(&Selector::Focused), wm.clients.get(40));
-        assert_eq!(wm.client(&Selector::Index(2)), wm.clients.get(20));
-        assert_eq!(wm.client(&Selector::WinId(30)), wm.clients.get(30));
-        assert_eq!(
-            wm.client(&Selector::Condition(&|c| c.id() == 10)),
-            wm.clients.get(10)
-        );
+        wm.handle_map_request(20).unwrap();
+        assert_eq!(wm.workspaces.workspace_names()[0], "1 mpv");
+
+        wm.handle_event_actions(vec![EventAction::DestroyClient(20)])
+            .unwrap();
+        assert_eq!(wm.workspaces.workspace_names()[0], "1 st");
     }
 
     #[test]
-    fn unmanaged_window_types_are_not_added_to_workspaces() {
-        // Setting the unmanaged window IDs here sets the return of
-        // MockXConn.is_managed_window to false for those IDs
-        let mut wm = wm_with_mock_conn(vec![], vec![10]);
+    fn transient_clients_float_over_and_follow_their_parent() {
+        let conf = Config {
+            show_bar: false,
+            border_px: 2,
+            ..Default::default()
+        };
+        let conn = ConfigurableXConn::default()
+            .with_prop(20, Atom::WmTransientFor, Prop::Window(vec![10]))
+            .with_geometry(10, Region::new(100, 100, 600, 400))
+            .with_geometry(20, Region::new(0, 0, 200, 100));
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
 
-        wm.handle_map_request(10).unwrap(); // should not be tiled
-        assert!(wm.clients.get(10).is_some());
-        assert!(wm.workspaces[0].is_empty());
+        wm.handle_map_request(10).unwrap();
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
 
-        wm.handle_map_request(20).unwrap(); // should be tiled
-        assert!(wm.clients.get(20).is_some());
-        assert!(wm.workspaces[0].len() == 1);
+        // the dialog opens floating on its parent's workspace, centred over the parent once
+        // that workspace is shown
+        wm.handle_map_request(20).unwrap();
+        let dialog = wm.clients.get(20).unwrap();
+        assert!(dialog.floating);
+        assert_eq!(dialog.transient_for(), Some(10));
+        assert_eq!(dialog.workspace(), 0);
+        wm.conn().clear();
+        wm.focus_workspace(&Selector::Index(0)).unwrap();
+        let centred = strings!(20, Region::new(302, 252, 196, 96), 2, false);
+        assert!(wm
+            .conn()
+            .calls()
+            .contains(&("position_client".into(), centred)));
+
+        // moving the parent takes the dialog with it
+        wm.move_client_to_workspace(10, 2).unwrap();
+        assert_eq!(wm.clients.workspace_index_for_client(20), Some(2));
+
+        // and focusing the parent raises the dialog above it
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        wm.conn().clear();
+        wm.update_focus(10).unwrap();
+        let raised: Vec<_> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "raise_client")
+            .collect();
+        assert_eq!(raised, vec![("raise_client".into(), strings!(20))]);
     }
 
     struct ScreenChangingXConn {
@@ -1597,6 +6334,116 @@ mod tests {
         assert_eq!(wm.screens.get(0).unwrap().wix, 3);
     }
 
+    struct KeyboardGrabFailingXConn {
+        pointer_grabbed: Cell<bool>,
+    }
+
+    __impl_stub_xcon! {
+        for KeyboardGrabFailingXConn;
+
+        atom_queries: {}
+        client_properties: {}
+        client_handler: {}
+        client_config: {}
+        event_handler: {}
+        state: {
+            fn mock_current_screens(&self) -> crate::core::xconnection::Result<Vec<Screen>> {
+                Ok(vec![Screen::new(Region::new(0, 0, 800, 600), 0)])
+            }
+        }
+        conn: {
+            fn mock_grab_keyboard(&self) -> crate::core::xconnection::Result<()> {
+                Err(crate::core::xconnection::XError::Raw(
+                    "keyboard already grabbed".into(),
+                ))
+            }
+
+            fn mock_grab_pointer(&self) -> crate::core::xconnection::Result<()> {
+                self.pointer_grabbed.set(true);
+                Ok(())
+            }
+
+            fn mock_ungrab_pointer(&self) -> crate::core::xconnection::Result<()> {
+                self.pointer_grabbed.set(false);
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn failing_to_grab_the_keyboard_releases_the_pointer() {
+        let conn = KeyboardGrabFailingXConn {
+            pointer_grabbed: Cell::new(false),
+        };
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        assert!(wm.select_window_interactive().is_err());
+        assert!(!wm.conn().pointer_grabbed.get());
+    }
+
+    struct ClickRecorder(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+    impl<X: XConn> crate::core::Hook<X> for ClickRecorder {
+        fn root_window_clicked(
+            &mut self,
+            _: &mut WindowManager<X>,
+            button: MouseButton,
+            _: Point,
+        ) -> Result<()> {
+            self.0.borrow_mut().push(format!("root {:?}", button));
+            Ok(())
+        }
+
+        fn window_clicked(
+            &mut self,
+            _: &mut WindowManager<X>,
+            id: Xid,
+            button: MouseButton,
+            _: Point,
+        ) -> Result<()> {
+            self.0.borrow_mut().push(format!("{} {:?}", id, button));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn clicks_on_other_windows_are_not_reported_as_root_clicks() {
+        let clicks = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let hooks: Vec<Box<dyn crate::core::Hook<RecordingXConn>>> =
+            vec![Box::new(ClickRecorder(clicks.clone()))];
+        let mut wm = WindowManager::new(
+            Config::default(),
+            RecordingXConn::init(),
+            hooks,
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 1, 0);
+        let root = wm.conn().root();
+        let press = |id, button| {
+            MouseEvent::new(
+                id,
+                10,
+                10,
+                10,
+                10,
+                MouseState::new(button, vec![]),
+                MouseEventKind::Press,
+            )
+        };
+
+        // 500 is a window drawn by a hook (such as a tab bar) and 10 is a client
+        wm.run_mouse_binding(press(500, MouseButton::Right), &mut HashMap::new());
+        wm.run_mouse_binding(press(10, MouseButton::Left), &mut HashMap::new());
+        wm.run_mouse_binding(press(root, MouseButton::Middle), &mut HashMap::new());
+
+        assert_eq!(
+            *clicks.borrow(),
+            vec!["500 Right".to_string(), "root Middle".to_string()]
+        );
+    }
+
     // Check that workspace layout is triggered correctly from public methods
 
     macro_rules! layout_trigger_test {
@@ -1643,6 +6490,8 @@ mod tests {
     layout_trigger_test!(focus_client; false; &Selector::Any);
     layout_trigger_test!(rotate_clients; true; Forward);
     layout_trigger_test!(drag_client; true; Forward);
+    layout_trigger_test!(swap_client; true; Forward);
+    layout_trigger_test!(promote_focused; true;);
     layout_trigger_test!(cycle_layout; true; Forward);
     layout_trigger_test!(update_max_main; true; Change::More);
     layout_trigger_test!(update_main_ratio; true; Change::More);
@@ -1699,7 +6548,7 @@ mod tests {
         update_focus;
         args: (
             target: Xid,
-            accepts_focus: bool,
+            model: InputModel,
             current: Option<Xid>,
             n_clients: usize,
             follow_focus: bool,
@@ -1709,44 +6558,57 @@ mod tests {
 
         // We should still run focusing logic when the requested target is our current focus
         case: client_is_current_focus => (
-            10, true, Some(10), 3, false,
+            10, InputModel::Passive, Some(10), 3, false,
             Some(10), vec![_focus(10), _active(10), _border(10, true)]
         );
 
         // We should remove the focused border from the current client first
         case: client_is_not_current_focus => (
-            20, true, Some(10), 3, false,
+            20, InputModel::Passive, Some(10), 3, false,
             Some(20), vec![_border(10, false), _focus(20), _active(20), _border(20, true)]
         );
 
         // Focus should default to the focused client on the active workspace if the given client
         // is not in the client_map
         case: client_is_unknown_workspace_populated => (
-            999, true, Some(10), 3, false,
+            999, InputModel::Passive, Some(10), 3, false,
             Some(30), vec![_border(10, false), _focus(30), _active(30), _border(30, true)]
         );
 
         // If the client is unknown and the workspace is empty, focus should revert to root
         case: client_is_unknown_workspace_empty => (
-            999, true, None, 0, false,
+            999, InputModel::Passive, None, 0, false,
             None, vec![_focus(42), _remove_active()]
         );
 
-        // If the client doesn't accept focus then we should still mark it as focused in the
-        // internal state, but a TakeFocus client message should be sent instead of forcing
-        // focus.
-        case: client_does_not_accept_focus_different => (
-            20, false, Some(10), 3, false,
+        // Clients that don't accept input are still marked as focused in the internal state but
+        // X focus is left alone
+        case: no_input_different => (
+            20, InputModel::NoInput, Some(10), 3, false,
+            Some(20), vec![_border(10, false), _active(20), _border(20, true)]
+        );
+
+        case: no_input_same => (
+            20, InputModel::NoInput, Some(20), 3, false,
+            Some(20), vec![_active(20), _border(20, true)]
+        );
+
+        // Clients supporting WM_TAKE_FOCUS are sent a TakeFocus client message along with having
+        // X focus set if they accept input
+        case: locally_active => (
+            20, InputModel::LocallyActive, Some(10), 3, false,
             Some(20), vec![
-                _border(10, false), _id(Atom::WmTakeFocus), _take_focus(20)
+                _border(10, false), _focus(20), _id(Atom::WmTakeFocus), _take_focus(20),
+                _active(20), _border(20, true)
             ]
         );
 
-        // If the client doesn't accept focus, and it is the current focus then we should just
-        // set the border and send the TakeFocus event
-        case: client_does_not_accept_focus_same => (
-            20, false, Some(20), 3, false,
-            Some(20), vec![_id(Atom::WmTakeFocus), _take_focus(20)]
+        case: globally_active => (
+            20, InputModel::GloballyActive, Some(10), 3, false,
+            Some(20), vec![
+                _border(10, false), _id(Atom::WmTakeFocus), _take_focus(20), _active(20),
+                _border(20, true)
+            ]
         );
 
         // TODO: add test cases for follow_focus layout triggering
@@ -1765,7 +6627,12 @@ mod tests {
             if let Some(id) = current {
                 wm.clients.set_focused(id, &wm.conn);
             }
-            wm.clients.modify(target, |c| c.accepts_focus = accepts_focus);
+            wm.clients.modify(target, |c| {
+                c.accepts_focus = model.accepts_input();
+                if model.takes_focus() {
+                    c.wm_protocols = vec![Atom::WmTakeFocus.as_ref().to_string()];
+                }
+            });
             wm.conn().clear();
 
             wm.update_focus(target).unwrap();
@@ -1775,6 +6642,250 @@ mod tests {
         }
     }
 
+    #[test]
+    fn key_bindings_follow_their_key_names_when_the_keymap_changes() {
+        let keymap = |y: u8, z: u8| -> HashMap<String, KeyCodeValue> {
+            vec![("y".to_string(), y), ("z".to_string(), z)]
+                .into_iter()
+                .collect()
+        };
+        let conn = ConfigurableXConn::default();
+        conn.set_keycodes(keymap(29, 52));
+        let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        wm.original_keycodes = wm.conn.keycodes().unwrap();
+
+        let presses = Rc::new(Cell::new(0));
+        let p = Rc::clone(&presses);
+        let y = KeyCode { mask: 0, code: 29 };
+        let mut key_bindings: KeyBindings<ConfigurableXConn> = HashMap::new();
+        key_bindings.insert(
+            y,
+            Box::new(move |_: &mut WindowManager<ConfigurableXConn>| {
+                p.set(p.get() + 1);
+                Ok(())
+            }),
+        );
+        let mut mouse_bindings = HashMap::new();
+        let mut run = |wm: &mut WindowManager<ConfigurableXConn>, action| {
+            wm.handle_event_action(action, Some(&mut key_bindings), Some(&mut mouse_bindings))
+                .unwrap()
+        };
+
+        // 'y' and 'z' swap places (e.g. switching from a QWERTY to a QWERTZ layout)
+        wm.conn().set_keycodes(keymap(52, 29));
+        run(&mut wm, EventAction::KeyboardMappingChanged);
+
+        run(&mut wm, EventAction::RunKeyBinding(y));
+        assert_eq!(presses.get(), 0);
+        run(
+            &mut wm,
+            EventAction::RunKeyBinding(KeyCode { mask: 0, code: 52 }),
+        );
+        assert_eq!(presses.get(), 1);
+    }
+
+    #[test]
+    fn rules_can_set_the_focused_border_for_a_client() {
+        let green = Color::try_from("#00ff00").unwrap();
+        let red = Color::try_from("#ff0000").unwrap();
+        let blue = Color::try_from("#0000ff").unwrap();
+        let conf = Config {
+            focused_border: green,
+            unfocused_border: red,
+            rules: vec![Rule::new(vec![], vec![RuleAction::FocusedBorder(blue)])],
+            ..Default::default()
+        };
+        let conn = RecordingXConn::init();
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+        add_n_clients(&mut wm, 2, 0);
+        wm.conn().clear();
+
+        wm.update_focus(10).unwrap();
+
+        let borders: Vec<Vec<String>> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "set_client_border_color")
+            .map(|(_, args)| args)
+            .collect();
+        assert_eq!(borders, vec![strings!(20, red), strings!(10, blue)]);
+    }
+
+    #[test]
+    fn clients_can_be_viewed_by_tag_in_tag_mode() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        wm.config.tag_mode = true;
+        add_n_clients(&mut wm, 4, 0);
+        wm.move_client_to_workspace(30, 2).unwrap();
+        wm.move_client_to_workspace(40, 3).unwrap();
+
+        let mapped = |wm: &WindowManager<MockXConn>| -> Vec<Xid> {
+            vec![10, 20, 30, 40]
+                .into_iter()
+                .filter(|&id| wm.clients.get(id).map(|c| c.mapped) == Some(true))
+                .collect()
+        };
+        assert_eq!(mapped(&wm), vec![10, 20]);
+
+        // tagging a hidden client with a visible workspace shows it
+        wm.toggle_tag_on_client(30, 0).unwrap();
+        assert_eq!(wm.clients.get(30).unwrap().tags(), vec![2, 0]);
+        assert_eq!(mapped(&wm), vec![10, 20, 30]);
+
+        // viewing multiple tags shows every client carrying any of them
+        wm.view_tags(&[0, 3]).unwrap();
+        assert_eq!(wm.viewed_tags(), vec![0, 3]);
+        assert_eq!(mapped(&wm), vec![10, 20, 30, 40]);
+
+        // focusing a workspace drops the additional tags being viewed
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        assert_eq!(wm.viewed_tags(), vec![2]);
+        assert_eq!(mapped(&wm), vec![30]);
+
+        // removing the primary tag of a client moves it to the next tag it carries
+        wm.toggle_tag_on_client(30, 2).unwrap();
+        assert_eq!(wm.clients.get(30).unwrap().tags(), vec![0]);
+        assert_eq!(mapped(&wm), vec![]);
+
+        // clients always keep at least one tag
+        wm.toggle_tag_on_client(30, 0).unwrap();
+        assert_eq!(wm.clients.get(30).unwrap().tags(), vec![0]);
+    }
+
+    fn tag_mode_wm() -> WindowManager<RecordingXConn> {
+        let conf = Config {
+            layouts: focus_test_layouts(false),
+            tag_mode: true,
+            ..Default::default()
+        };
+        let mut wm = WindowManager::new(
+            conf,
+            RecordingXConn::init(),
+            vec![],
+            logging_error_handler(),
+        );
+        wm.init().unwrap();
+
+        wm
+    }
+
+    #[test]
+    fn clients_viewed_on_multiple_screens_are_only_positioned_once() {
+        let mut wm = tag_mode_wm();
+        add_n_clients(&mut wm, 2, 0);
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        add_n_clients(&mut wm, 1, 2);
+        wm.toggle_tag_on_client(30, 0).unwrap();
+        wm.conn().clear();
+
+        wm.layout_visible().unwrap();
+        let positioned: Vec<String> = wm
+            .conn()
+            .calls()
+            .into_iter()
+            .filter(|(m, _)| m == "position_client")
+            .map(|(_, args)| args[0].clone())
+            .collect();
+
+        // 30 is shown on the screen displaying its own workspace
+        assert_eq!(positioned.iter().filter(|id| *id == "30").count(), 1);
+        assert_eq!(positioned.len(), 3);
+    }
+
+    #[test]
+    fn cycle_client_in_tag_mode_covers_every_viewed_tag() {
+        let mut wm = tag_mode_wm();
+        add_n_clients(&mut wm, 2, 0);
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        add_n_clients(&mut wm, 1, 2);
+        wm.view_tags(&[0, 2]).unwrap();
+        wm.focus_client(&Selector::WinId(20)).unwrap();
+
+        let mut seen = vec![];
+        for _ in 0..3 {
+            wm.cycle_client(Direction::Forward).unwrap();
+            seen.push(wm.focused_client_id().unwrap());
+        }
+        assert_eq!(seen, vec![10, 30, 20]);
+
+        wm.cycle_client(Direction::Backward).unwrap();
+        assert_eq!(wm.focused_client_id(), Some(30));
+    }
+
+    #[test]
+    fn cycle_client_in_tag_mode_follows_the_pointer_warp_config() {
+        let mut wm = tag_mode_wm();
+        add_n_clients(&mut wm, 2, 0);
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        add_n_clients(&mut wm, 1, 2);
+        wm.view_tags(&[0, 2]).unwrap();
+        wm.focus_client(&Selector::WinId(10)).unwrap();
+        let warps = |wm: &WindowManager<RecordingXConn>| -> Vec<Vec<String>> {
+            wm.conn()
+                .calls()
+                .into_iter()
+                .filter(|(m, _)| m == "warp_cursor")
+                .map(|(_, args)| args)
+                .collect()
+        };
+
+        // 30 is only on the screen through the tag being viewed
+        wm.conn().clear();
+        wm.cycle_client(Direction::Forward).unwrap();
+        assert_eq!(warps(&wm), vec![strings!(Some(30))]);
+
+        wm.config.warp_on_client_change = PointerWarp::Never;
+        wm.conn().clear();
+        wm.cycle_client(Direction::Forward).unwrap();
+        assert!(warps(&wm).is_empty());
+    }
+
+    #[test]
+    fn tag_methods_require_tag_mode() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+
+        assert!(wm.view_tags(&[0, 1]).is_err());
+        assert!(wm.toggle_tag_on_client(10, 1).is_err());
+        assert_eq!(wm.viewed_tags(), vec![0]);
+    }
+
+    #[test]
+    fn workspaces_can_be_named_after_their_dominant_client_class() {
+        let mut workspace_class_names = HashMap::new();
+        workspace_class_names.insert("st".to_string(), "term".to_string());
+        let conf = Config {
+            show_bar: false,
+            auto_name_workspaces: true,
+            workspace_class_names,
+            ..Default::default()
+        };
+        let class = |c: &str| Prop::UTF8String(vec![c.into(), c.into()]);
+        let conn = ConfigurableXConn::default()
+            .with_prop(10, Atom::WmClass, class("st"))
+            .with_prop(20, Atom::WmClass, class("st"))
+            .with_prop(30, Atom::WmClass, class("firefox"));
+        let mut wm = WindowManager::new(conf, conn, vec![], logging_error_handler());
+        wm.init().unwrap();
+
+        add_n_clients(&mut wm, 3, 0);
+        assert_eq!(&wm.workspaces.workspace_names()[0..2], &["1 term", "2"]);
+
+        wm.move_client_to_workspace(10, 1).unwrap();
+        wm.move_client_to_workspace(20, 1).unwrap();
+        assert_eq!(
+            &wm.workspaces.workspace_names()[0..2],
+            &["1 firefox", "2 term"]
+        );
+
+        wm.remove_client(30).unwrap();
+        assert_eq!(&wm.workspaces.workspace_names()[0..2], &["1", "2 term"]);
+        assert_eq!(wm.workspaces.get(1).map(|ws| ws.name()), Some("2"));
+    }
+
     #[test]
     fn cycle_screen_updates_active() {
         let mut wm = test_windowmanager(2, vec![]);
@@ -1786,6 +6897,30 @@ mod tests {
         assert_eq!(wm.active_screen_index(), 1);
     }
 
+    #[test]
+    fn focus_screen_wraps() {
+        let mut wm = test_windowmanager(2, vec![]);
+
+        assert_eq!(wm.active_screen_index(), 0);
+        wm.focus_screen(Backward).unwrap();
+        assert_eq!(wm.active_screen_index(), 1);
+        assert_eq!(wm.active_workspace().name(), "2");
+        wm.focus_screen(Forward).unwrap();
+        assert_eq!(wm.active_screen_index(), 0);
+    }
+
+    #[test]
+    fn focus_in_sets_active_screen() {
+        let mut wm = wm_with_mock_conn(vec![], vec![]);
+        add_n_clients(&mut wm, 1, 0);
+        wm.client_to_screen(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.active_screen_index(), 0);
+
+        wm.handle_event_action(EventAction::FocusIn(10), None, None)
+            .unwrap();
+        assert_eq!(wm.active_screen_index(), 1);
+    }
+
     #[test]
     fn cycle_workspace_updates_focused() {
         let mut wm = test_windowmanager(1, vec![]);
@@ -1807,6 +6942,88 @@ mod tests {
         assert_eq!(wm.focused_workspaces(), vec![1, 0]);
     }
 
+    #[test]
+    fn startup_and_shutdown_hooks_run_around_the_event_loop() {
+        let calls = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut wm = test_windowmanager(1, vec![]);
+
+        let c = calls.clone();
+        wm.startup_hook(move |wm| {
+            c.borrow_mut()
+                .push(format!("startup running={}", wm.running));
+            Ok(())
+        });
+        let c = calls.clone();
+        wm.shutdown_hook(move |wm| {
+            c.borrow_mut()
+                .push(format!("shutdown running={}", wm.running));
+            Ok(())
+        });
+
+        wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
+            .unwrap();
+
+        assert_eq!(
+            *calls.borrow(),
+            vec!["startup running=false", "shutdown running=true"]
+        );
+    }
+
+    #[test]
+    fn autostarted_programs_are_stopped_on_exit() {
+        let pid = std::rc::Rc::new(std::cell::Cell::new(None));
+        let mut wm = test_windowmanager(1, vec![]);
+        wm.autostart(Spawn::new("sleep").arg("30"));
+
+        let p = pid.clone();
+        wm.shutdown_hook(move |wm| {
+            p.set(wm.autostarted.first().map(|c| c.id()));
+            Ok(())
+        });
+
+        wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
+            .unwrap();
+
+        let pid = pid.get().expect("sleep was not started");
+        assert!(wm.autostarted.is_empty());
+        assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+    }
+
+    #[test]
+    fn autostarted_programs_are_reaped_when_they_exit() {
+        let mut wm = test_windowmanager(1, vec![]);
+        wm.autostart(Spawn::new("true"));
+        wm.autostart(Spawn::new("sleep").arg("30"));
+        wm.run_startup_hooks();
+
+        let pid = wm.autostarted[0].id();
+        let path = format!("/proc/{}/stat", pid);
+        let is_zombie = || {
+            std::fs::read_to_string(&path)
+                .map(|s| {
+                    s.rsplit(')')
+                        .next()
+                        .unwrap_or_default()
+                        .trim_start()
+                        .starts_with('Z')
+                })
+                .unwrap_or(false)
+        };
+        for _ in 0..100 {
+            if is_zombie() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(is_zombie());
+
+        wm.reap_autostarted();
+
+        assert_eq!(wm.autostarted.len(), 1);
+        assert!(!std::path::Path::new(&path).exists());
+        wm.stop_autostarted();
+    }
+
     #[test]
     fn cycle_client_updates_focus() {
         let mut wm = test_windowmanager(1, n_clients(3));
@@ -1874,6 +7091,36 @@ mod tests {
         assert_eq!(wm.active_workspace().client_ids(), vec![2, 1, 3, 0]);
     }
 
+    #[test]
+    fn swap_client() {
+        let mut wm = test_windowmanager(1, n_clients(4));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        assert_eq!(wm.active_workspace().client_ids(), vec![3, 2, 1, 0]);
+        wm.swap_client(Backward).unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![0, 2, 1, 3]);
+        assert_eq!(wm.focused_client_id(), Some(3));
+    }
+
+    #[test]
+    fn promote_focused() {
+        let mut wm = test_windowmanager(1, n_clients(4));
+        wm.init().unwrap();
+        wm.grab_keys_and_run(test_key_bindings(), test_mouse_bindings())
+            .unwrap();
+
+        wm.focus_client(&Selector::WinId(1)).unwrap();
+        wm.promote_focused().unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![1, 3, 2, 0]);
+        assert_eq!(wm.focused_client_id(), Some(1));
+
+        wm.promote_focused().unwrap();
+        assert_eq!(wm.active_workspace().client_ids(), vec![3, 1, 2, 0]);
+        assert_eq!(wm.focused_client_id(), Some(3));
+    }
+
     #[test]
     fn cycle_layout() {
         let mut wm = test_windowmanager(1, vec![]);
@@ -1885,6 +7132,20 @@ mod tests {
         assert_eq!(wm.current_layout_symbol(), "first");
     }
 
+    #[test]
+    fn layout_state_is_tracked_per_workspace_and_layout() {
+        let mut wm = test_windowmanager(1, vec![]);
+
+        wm.update_max_main(Change::More).unwrap();
+        wm.cycle_layout(Forward).unwrap();
+        assert_eq!(wm.active_workspace().max_main(), 1);
+        wm.cycle_layout(Forward).unwrap();
+        assert_eq!(wm.active_workspace().max_main(), 2);
+
+        wm.focus_workspace(&Selector::Index(1)).unwrap();
+        assert_eq!(wm.active_workspace().max_main(), 1);
+    }
+
     #[test]
     fn focus_workspace() {
         let mut wm = test_windowmanager(1, vec![]);
@@ -1909,6 +7170,48 @@ mod tests {
         assert_eq!(wm.active_workspace().name(), "2");
     }
 
+    #[test]
+    fn toggle_workspace_uses_the_history_of_the_focused_screen() {
+        let mut wm = test_windowmanager(2, vec![]);
+
+        wm.focus_workspace(&Selector::Index(2)).unwrap();
+        wm.focus_workspace(&Selector::Index(3)).unwrap();
+        assert_eq!(wm.workspace_history(0), vec![2, 0]);
+
+        wm.cycle_screen(Forward).unwrap();
+        wm.focus_workspace(&Selector::Index(4)).unwrap();
+        wm.toggle_workspace().unwrap();
+        assert_eq!(wm.screens.active_ws_index(), 1);
+        assert_eq!(wm.workspace_history(1), vec![4]);
+
+        wm.cycle_screen(Backward).unwrap();
+        wm.toggle_workspace().unwrap();
+        assert_eq!(wm.screens.active_ws_index(), 2);
+        assert_eq!(wm.workspace_history(0), vec![3, 0]);
+    }
+
+    test_cases! {
+        cycle_non_empty_workspace;
+        args: (occupied: &[usize], direction: Direction, expected: usize);
+
+        case: forward_skips_empty => (&[0, 3, 6], Forward, 3);
+        case: backward_skips_empty => (&[0, 3, 6], Backward, 6);
+        case: wraps_around => (&[0, 2], Backward, 2);
+        case: all_others_empty => (&[0], Forward, 0);
+
+        body: {
+            let mut wm = wm_with_mock_conn(vec![], vec![]);
+            for (i, &wix) in occupied.iter().enumerate() {
+                let id = 10 * (i as Xid + 1);
+                wm.handle_map_request(id).unwrap();
+                wm.move_client_to_workspace(id, wix).unwrap();
+            }
+
+            wm.cycle_non_empty_workspace(direction).unwrap();
+            assert_eq!(wm.screens.active_ws_index(), expected);
+        }
+    }
+
     #[test]
     fn client_to_workspace() {
         let mut wm = test_windowmanager(1, n_clients(3));
@@ -1,34 +1,80 @@
 //! State and management of screens being layed out by Penrose.
 use crate::{
     core::{
+        config::BarConfig,
         data_types::Region,
         hooks::HookName,
         manager::event::EventAction,
         ring::{Direction, Ring, Selector},
-        screen::Screen,
-        xconnection::XState,
+        screen::{Screen, Strut, DEFAULT_DPI},
+        xconnection::{XState, Xid},
     },
+    draw::Position,
     Result,
 };
 use tracing::{debug, info, trace};
 
+use std::collections::HashMap;
+
+const MAX_WORKSPACE_HISTORY: usize = 32;
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(super) struct Screens {
     pub(super) inner: Ring<Screen>,
-    bar_height: u32,
-    top_bar: bool,
+    #[cfg_attr(feature = "serde", serde(default = "default_bars"))]
+    bars: Vec<BarConfig>,
+    #[cfg_attr(feature = "serde", serde(default = "default_bars_visible"))]
+    bars_visible: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    scale_by_dpi: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    struts: HashMap<Xid, Strut>,
+    // previously shown workspaces for each screen index, most recent last
+    #[cfg_attr(feature = "serde", serde(default))]
+    history: HashMap<usize, Vec<usize>>,
+    // tags being viewed on each screen index in addition to the workspace it is showing
+    #[cfg_attr(feature = "serde", serde(default))]
+    extra_tags: HashMap<usize, Vec<usize>>,
+}
+
+// State serialized before multiple bars were supported only knew about a single bar, so fall back
+// to the one given by the default Config
+#[cfg(feature = "serde")]
+fn default_bars() -> Vec<BarConfig> {
+    crate::core::config::Config::default().bar_configs()
+}
+
+// Bars could not be hidden before toggle_bar was added
+#[cfg(feature = "serde")]
+fn default_bars_visible() -> bool {
+    true
 }
 
 impl Screens {
-    pub fn new(bar_height: u32, top_bar: bool) -> Self {
+    pub fn new(bars: Vec<BarConfig>, scale_by_dpi: bool) -> Self {
         Self {
             inner: Ring::default(),
-            bar_height,
-            top_bar,
+            bars,
+            bars_visible: true,
+            scale_by_dpi,
+            struts: HashMap::new(),
+            history: HashMap::new(),
+            extra_tags: HashMap::new(),
         }
     }
 
+    // The space reserved at the top and bottom of the given screen for the internal status bars
+    fn bar_space_for(&self, index: usize, s: &Screen) -> (u32, u32) {
+        self.bars
+            .iter()
+            .filter(|b| self.bars_visible && b.is_shown_on(index))
+            .fold((0, 0), |(top, bottom), b| match b.position() {
+                Position::Top => (top + s.scale(b.height()), bottom),
+                Position::Bottom => (top, bottom + s.scale(b.height())),
+            })
+    }
+
     pub fn indexed_screen_for_workspace(&self, wix: usize) -> Option<(usize, &Screen)> {
         self.inner
             .indexed_element(&Selector::Condition(&|s| s.wix == wix))
@@ -56,6 +102,84 @@ impl Screens {
         self.inner.vec_map(|s| s.wix)
     }
 
+    // The tags viewed on the screen at 'index': the workspace it is showing followed by any
+    // additional tags
+    pub fn viewed_tags(&self, index: usize) -> Vec<usize> {
+        let mut tags: Vec<usize> = self.inner.get(index).map(|s| s.wix).into_iter().collect();
+        if let Some(extra) = self.extra_tags.get(&index) {
+            for &t in extra {
+                if !tags.contains(&t) {
+                    tags.push(t);
+                }
+            }
+        }
+
+        tags
+    }
+
+    // Every tag being viewed on any screen
+    pub fn all_viewed_tags(&self) -> Vec<usize> {
+        (0..self.inner.len())
+            .flat_map(|i| self.viewed_tags(i))
+            .collect()
+    }
+
+    // The first screen viewing the given tag (if there is one)
+    pub fn screen_viewing_tag(&self, tag: usize) -> Option<&Screen> {
+        (0..self.inner.len())
+            .find(|&i| self.viewed_tags(i).contains(&tag))
+            .and_then(|i| self.inner.get(i))
+    }
+
+    pub fn set_extra_tags(&mut self, index: usize, tags: Vec<usize>) {
+        if tags.is_empty() {
+            self.extra_tags.remove(&index);
+        } else {
+            self.extra_tags.insert(index, tags);
+        }
+    }
+
+    // Update the workspace shown on each screen after workspaces have been added or removed
+    pub fn reindex_workspaces(&mut self, f: impl Fn(usize) -> usize) {
+        self.inner.iter_mut().for_each(|s| s.wix = f(s.wix));
+        for tags in self.extra_tags.values_mut() {
+            tags.iter_mut().for_each(|t| *t = f(*t));
+        }
+        for h in self.history.values_mut() {
+            let mut reindexed: Vec<usize> = Vec::with_capacity(h.len());
+            for wix in h.iter().map(|&w| f(w)) {
+                reindexed.retain(|&w| w != wix);
+                reindexed.push(wix);
+            }
+            *h = reindexed;
+        }
+    }
+
+    // Record that the screen at 'index' is no longer showing the workspace 'wix'
+    pub fn push_history(&mut self, index: usize, wix: usize) {
+        let h = self.history.entry(index).or_default();
+        h.retain(|&w| w != wix);
+        h.push(wix);
+        if h.len() > MAX_WORKSPACE_HISTORY {
+            h.remove(0);
+        }
+    }
+
+    // Previously shown workspaces for the screen at 'index', most recent first and not
+    // including the workspace currently shown
+    pub fn workspace_history(&self, index: usize) -> Vec<usize> {
+        let current = self.inner.get(index).map(|s| s.wix);
+        match self.history.get(&index) {
+            Some(h) => h
+                .iter()
+                .rev()
+                .filter(|&&w| Some(w) != current)
+                .copied()
+                .collect(),
+            None => vec![],
+        }
+    }
+
     pub fn screen(&self, selector: &Selector<'_, Screen>) -> Option<&Screen> {
         self.inner.element(selector)
     }
@@ -93,18 +217,35 @@ impl Screens {
 
         debug!(?workspace_ordering, "current workspace ordering");
 
-        let detected: Vec<Screen> = state
-            .current_screens()?
+        let screens = state.current_screens()?;
+        let root = root_region(&screens);
+        let struts: Vec<Strut> = self.struts.values().copied().collect();
+
+        let detected: Vec<Screen> = screens
             .into_iter()
             .zip(workspace_ordering)
             .enumerate()
-            .map(|(ix, (mut s, wix))| {
-                s.update_effective_region(self.bar_height, self.top_bar);
+            .map(|(ix, (s, wix))| {
+                // Screens are always reported with their DPI so we need to drop it if we are
+                // not scaling by it
+                let mut s = if self.scale_by_dpi {
+                    s
+                } else {
+                    s.with_dpi(DEFAULT_DPI)
+                };
+                let (top, bottom) = self.bar_space_for(ix, &s);
+                s.update_effective_region_with_bars(top, bottom, &struts, &root);
                 trace!(screen = ix, workspace = wix, "setting workspace for screen");
                 s.wix = wix;
 
                 let r = s.region(false);
-                info!(index = ix, w = r.w, h = r.h, "screen detected");
+                info!(
+                    index = ix,
+                    w = r.w,
+                    h = r.h,
+                    dpi = s.dpi(),
+                    "screen detected"
+                );
                 s
             })
             .collect();
@@ -120,6 +261,57 @@ impl Screens {
         })
     }
 
+    pub fn bars_visible(&self) -> bool {
+        self.bars_visible
+    }
+
+    /// Show or hide the internal status bars, reclaiming the space reserved for them while they
+    /// are hidden. Returns true if the effective region of any screen has changed as a result.
+    pub fn set_bars_visible(&mut self, visible: bool) -> bool {
+        if self.bars_visible == visible {
+            return false;
+        }
+        self.bars_visible = visible;
+        self.update_effective_regions()
+    }
+
+    /// Record the space reserved by a dock window, replacing any previous reservation it held.
+    /// Returns true if the effective region of any screen has changed as a result.
+    pub fn set_strut(&mut self, id: Xid, strut: Strut) -> bool {
+        if self.struts.insert(id, strut) == Some(strut) {
+            return false;
+        }
+        self.update_effective_regions()
+    }
+
+    /// Drop the space reserved by a dock window (if it had any). Returns true if the effective
+    /// region of any screen has changed as a result.
+    pub fn remove_strut(&mut self, id: Xid) -> bool {
+        if self.struts.remove(&id).is_none() {
+            return false;
+        }
+        self.update_effective_regions()
+    }
+
+    // Recompute the effective region of every screen from the current set of dock struts
+    fn update_effective_regions(&mut self) -> bool {
+        let screens = self.inner.as_vec();
+        let root = root_region(&screens);
+        let struts: Vec<Strut> = self.struts.values().copied().collect();
+        let bar_space: Vec<(u32, u32)> = screens
+            .iter()
+            .enumerate()
+            .map(|(i, s)| self.bar_space_for(i, s))
+            .collect();
+
+        self.inner
+            .iter_mut()
+            .zip(bar_space)
+            .for_each(|(s, (t, b))| s.update_effective_region_with_bars(t, b, &struts, &root));
+
+        self.inner.as_vec() != screens
+    }
+
     pub fn focus_screen(&mut self, sel: &Selector<'_, Screen>) -> Vec<EventAction> {
         match self.inner.focus(sel) {
             Some((true, focused)) => vec![
@@ -130,23 +322,38 @@ impl Screens {
         }
     }
 
-    pub fn cycle_screen<S>(&mut self, direction: Direction, state: &S) -> Result<Vec<EventAction>>
-    where
-        S: XState,
-    {
+    pub fn cycle_screen(&mut self, direction: Direction) -> Vec<EventAction> {
         if !self.inner.would_wrap(direction) {
-            self.inner.cycle_focus(direction);
-            let focused = self.inner.focused_unchecked();
-            state.warp_cursor(None, focused)?;
-
-            Ok(vec![
-                EventAction::SetActiveWorkspace(focused.wix),
-                EventAction::RunHook(HookName::ScreenChange),
-            ])
+            self.focus_in_direction(direction)
         } else {
-            Ok(vec![])
+            vec![]
         }
     }
+
+    // Unlike cycle_screen, this will wrap from the last screen back to the first (and vice versa)
+    pub fn focus_in_direction(&mut self, direction: Direction) -> Vec<EventAction> {
+        if self.inner.len() < 2 {
+            return vec![];
+        }
+
+        self.inner.cycle_focus(direction);
+        let focused = self.inner.focused_unchecked();
+
+        vec![
+            EventAction::SetActiveWorkspace(focused.wix),
+            EventAction::RunHook(HookName::ScreenChange),
+        ]
+    }
+}
+
+// The region covered by the root window: the bounding box of all connected screens
+fn root_region(screens: &[Screen]) -> Region {
+    let (w, h) = screens.iter().fold((0, 0), |(w, h), s| {
+        let (x, y, sw, sh) = s.region(false).values();
+        (w.max(x + sw), h.max(y + sh))
+    });
+
+    Region::new(0, 0, w, h)
 }
 
 #[cfg(test)]
@@ -154,6 +361,10 @@ mod tests {
     use super::*;
     use crate::core::xconnection::*;
 
+    fn bar_at_top(h: u32) -> Vec<BarConfig> {
+        vec![BarConfig::new(h, Position::Top)]
+    }
+
     fn raw_screens() -> Vec<Screen> {
         vec![
             Screen::new(Region::new(0, 0, 1366, 768), 0),
@@ -163,7 +374,7 @@ mod tests {
 
     #[test]
     fn update_known_screens_generates_events_when_there_is_a_change() {
-        let mut s = Screens::new(10, true);
+        let mut s = Screens::new(bar_at_top(10), false);
         let conn = MockXConn::new(raw_screens(), vec![], vec![]);
         let events = s.update_known_screens(&conn, 10).unwrap();
 
@@ -178,7 +389,7 @@ mod tests {
 
     #[test]
     fn update_known_screens_doesnt_generates_events_when_screens_are_unchanged() {
-        let mut s = Screens::new(10, true);
+        let mut s = Screens::new(bar_at_top(10), false);
         let conn = MockXConn::new(raw_screens(), vec![], vec![]);
         s.update_known_screens(&conn, 10).unwrap();
         let events = s.update_known_screens(&conn, 10).unwrap();
@@ -186,9 +397,126 @@ mod tests {
         assert!(events.is_empty());
     }
 
+    #[test]
+    fn struts_from_multiple_docks_are_reconciled() {
+        let mut s = Screens::new(bar_at_top(10), false);
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+
+        let bar = Strut::from_raw(&[0, 0, 20, 0]).unwrap();
+        let dock = Strut::from_raw(&[0, 0, 40, 0, 0, 0, 0, 0, 0, 1365, 0, 0]).unwrap();
+
+        assert!(s.set_strut(1, bar));
+        assert!(s.set_strut(2, dock));
+        assert!(!s.set_strut(2, dock));
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 40, 1366, 728)));
+        assert_eq!(
+            s.screen_size(1, true),
+            Some(Region::new(1366, 20, 1366, 748))
+        );
+
+        assert!(s.remove_strut(2));
+        assert!(!s.remove_strut(2));
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 20, 1366, 748)));
+
+        assert!(s.remove_strut(1));
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 10, 1366, 758)));
+    }
+
+    test_cases! {
+        bar_space_is_scaled_by_dpi;
+        args: (scale_by_dpi: bool, expected_dpi: u32, expected: Region);
+
+        case: scaling => (true, 192, Region::new(1366, 20, 1366, 748));
+        case: not_scaling => (false, 96, Region::new(1366, 10, 1366, 758));
+
+        body: {
+            let mut screens = raw_screens();
+            screens[1] = screens[1].with_dpi(192);
+            let mut s = Screens::new(bar_at_top(10), scale_by_dpi);
+            let conn = MockXConn::new(screens, vec![], vec![]);
+            s.update_known_screens(&conn, 10).unwrap();
+
+            assert_eq!(s.screen_size(0, true), Some(Region::new(0, 10, 1366, 758)));
+            assert_eq!(s.get(1).map(|s| s.dpi()), Some(expected_dpi));
+            assert_eq!(s.screen_size(1, true), Some(expected));
+        }
+    }
+
+    #[test]
+    fn space_is_reserved_for_each_bar_independently() {
+        let bars = vec![
+            BarConfig::new(10, Position::Top),
+            BarConfig::new(20, Position::Bottom),
+            BarConfig::new(5, Position::Top).screens(vec![0]),
+        ];
+        let mut s = Screens::new(bars, false);
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 15, 1366, 733)));
+        assert_eq!(
+            s.screen_size(1, true),
+            Some(Region::new(1366, 10, 1366, 738))
+        );
+    }
+
+    #[test]
+    fn hiding_the_bars_reclaims_their_space() {
+        let mut s = Screens::new(bar_at_top(10), false);
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+
+        assert!(s.set_bars_visible(false));
+        assert!(!s.set_bars_visible(false));
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 0, 1366, 768)));
+
+        assert!(s.set_bars_visible(true));
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 10, 1366, 758)));
+    }
+
+    #[test]
+    fn bar_space_is_only_reserved_on_screens_showing_the_bar() {
+        let mut s = Screens::new(
+            vec![BarConfig::new(10, Position::Top).hidden_screens(vec![1])],
+            false,
+        );
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 10, 1366, 758)));
+        assert_eq!(
+            s.screen_size(1, true),
+            Some(Region::new(1366, 0, 1366, 768))
+        );
+
+        // An external bar on the hidden screen is still honoured
+        let external = Strut::from_raw(&[0, 0, 24, 0, 0, 0, 0, 0, 1366, 2731, 0, 0]).unwrap();
+        assert!(s.set_strut(1, external));
+        assert_eq!(s.screen_size(0, true), Some(Region::new(0, 10, 1366, 758)));
+        assert_eq!(
+            s.screen_size(1, true),
+            Some(Region::new(1366, 24, 1366, 744))
+        );
+    }
+
+    #[test]
+    fn struts_are_applied_to_newly_detected_screens() {
+        let mut s = Screens::new(bar_at_top(10), false);
+        s.set_strut(1, Strut::from_raw(&[0, 0, 0, 30]).unwrap());
+
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+
+        assert_eq!(
+            s.screen_size(1, true),
+            Some(Region::new(1366, 10, 1366, 728))
+        );
+    }
+
     #[test]
     fn changing_focus_generates_event_actions() {
-        let mut s = Screens::new(10, true);
+        let mut s = Screens::new(bar_at_top(10), false);
         let conn = MockXConn::new(raw_screens(), vec![], vec![]);
         s.update_known_screens(&conn, 10).unwrap();
         let events = s.focus_screen(&Selector::Index(1));
@@ -204,7 +532,7 @@ mod tests {
 
     #[test]
     fn changing_focus_only_generates_event_actions_on_change() {
-        let mut s = Screens::new(10, true);
+        let mut s = Screens::new(bar_at_top(10), false);
         let conn = MockXConn::new(raw_screens(), vec![], vec![]);
         s.update_known_screens(&conn, 10).unwrap();
         let events = s.focus_screen(&Selector::Index(0));
@@ -214,10 +542,10 @@ mod tests {
 
     #[test]
     fn cycle_screen_generates_event_actions() {
-        let mut s = Screens::new(10, true);
+        let mut s = Screens::new(bar_at_top(10), false);
         let conn = MockXConn::new(raw_screens(), vec![], vec![]);
         s.update_known_screens(&conn, 10).unwrap();
-        let events = s.cycle_screen(Direction::Forward, &conn).unwrap();
+        let events = s.cycle_screen(Direction::Forward);
 
         assert_eq!(
             events,
@@ -230,12 +558,29 @@ mod tests {
 
     #[test]
     fn cycle_screen_does_not_generate_event_actions_when_unable_to_cycle() {
-        let mut s = Screens::new(10, true);
+        let mut s = Screens::new(bar_at_top(10), false);
         let conn = MockXConn::new(raw_screens(), vec![], vec![]);
         s.update_known_screens(&conn, 10).unwrap();
-        let events = s.cycle_screen(Direction::Backward, &conn);
+        let events = s.cycle_screen(Direction::Backward);
 
-        assert!(events.unwrap().is_empty())
+        assert!(events.is_empty())
+    }
+
+    #[test]
+    fn focus_in_direction_wraps() {
+        let mut s = Screens::new(bar_at_top(10), false);
+        let conn = MockXConn::new(raw_screens(), vec![], vec![]);
+        s.update_known_screens(&conn, 10).unwrap();
+        let events = s.focus_in_direction(Direction::Backward);
+
+        assert_eq!(s.focused_index(), 1);
+        assert_eq!(
+            events,
+            vec![
+                EventAction::SetActiveWorkspace(1),
+                EventAction::RunHook(HookName::ScreenChange)
+            ]
+        )
     }
 
     fn test_screens(h: u32, top_bar: bool) -> Vec<Screen> {
@@ -284,8 +629,12 @@ mod tests {
                         Screen::new(Region::new(0, 0, 0, 0), wix)
                     ).collect()
                 ),
-                bar_height,
-                top_bar
+                bars: vec![BarConfig::new(bar_height, Position::Top)],
+                bars_visible: true,
+                scale_by_dpi: false,
+                struts: HashMap::new(),
+                history: HashMap::new(),
+                extra_tags: HashMap::new(),
             };
 
             s.update_known_screens(&conn, n_workspaces).unwrap();
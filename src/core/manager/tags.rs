@@ -0,0 +1,161 @@
+//! Selection of the clients to show on each screen when running in tag mode.
+use crate::{
+    core::{
+        client::Client,
+        manager::{clients::Clients, screens::Screens, workspaces::Workspaces},
+        xconnection::{XConn, Xid},
+    },
+    Result,
+};
+
+// Whether or not any of the tags carried by a client are currently being viewed. Outside of tag
+// mode this is the same as the client's workspace being visible.
+pub(super) fn client_is_viewed(clients: &Clients, screens: &Screens, id: Xid) -> bool {
+    match clients.get(id) {
+        Some(c) => {
+            let viewed = screens.all_viewed_tags();
+            c.tags().iter().any(|t| viewed.contains(t))
+        }
+        None => true,
+    }
+}
+
+// The clients carrying any of the given tags, ordered by the workspace that they are on (in
+// the order the tags were given) and then by their position within that workspace
+pub(super) fn clients_for_tags<'a>(
+    workspaces: &Workspaces,
+    clients: &'a Clients,
+    tags: &[usize],
+) -> Vec<&'a Client> {
+    let others = (0..workspaces.len()).filter(|wix| !tags.contains(wix));
+
+    tags.iter()
+        .copied()
+        .chain(others)
+        .flat_map(|wix| workspaces.client_ids(wix).unwrap_or_default())
+        .filter_map(|id| clients.get(id))
+        .filter(|c| tags.iter().any(|&t| c.has_tag(t)))
+        .collect()
+}
+
+// The clients laid out on screen 'i'. A client carrying tags that are viewed on more than one
+// screen is only shown on one of them: the screen showing its workspace if there is one,
+// otherwise the first screen viewing any of its tags.
+pub(super) fn clients_for_screen<'a>(
+    workspaces: &Workspaces,
+    clients: &'a Clients,
+    screens: &Screens,
+    i: usize,
+) -> Vec<&'a Client> {
+    let n = screens.n_screens();
+    let owner = |c: &Client| {
+        (0..n)
+            .find(|&j| screens.viewed_tags(j).first() == Some(&c.workspace()))
+            .or_else(|| (0..n).find(|&j| screens.viewed_tags(j).iter().any(|&t| c.has_tag(t))))
+    };
+
+    clients_for_tags(workspaces, clients, &screens.viewed_tags(i))
+        .into_iter()
+        .filter(|c| owner(c) == Some(i))
+        .collect()
+}
+
+// Unmap workspace clients that have none of their tags viewed and map floating clients that do.
+// Tiled clients are mapped when they are positioned by their layout.
+pub(super) fn sync_visibility<X>(
+    conn: &X,
+    workspaces: &Workspaces,
+    clients: &mut Clients,
+    screens: &Screens,
+) -> Result<()>
+where
+    X: XConn,
+{
+    let viewed = screens.all_viewed_tags();
+    let ids: Vec<Xid> = (0..workspaces.len())
+        .flat_map(|wix| workspaces.client_ids(wix).unwrap_or_default())
+        .collect();
+
+    for id in ids {
+        let (visible, floating) = match clients.get(id) {
+            Some(c) => (c.tags().iter().any(|t| viewed.contains(t)), c.floating),
+            None => continue,
+        };
+
+        if !visible {
+            clients.unmap_if_needed(id, conn)?;
+        } else if floating {
+            clients.map_if_needed(id, conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        __test_helpers::*,
+        core::{config::BarConfig, xconnection::MockXConn},
+        draw::Position,
+    };
+
+    // Three workspaces with clients 1 and 2 on the first, 3 on the second and 4 on the third.
+    // Client 4 also carries the tag for the first workspace.
+    fn tagged() -> (Workspaces, Clients) {
+        let conn = MockXConn::new(vec![], vec![], vec![]);
+        let mut workspaces = Workspaces::new(
+            (0..3)
+                .map(|ix| test_workspace(format!("{}", ix), 0))
+                .collect(),
+            0.1,
+        );
+        let mut clients = Clients::new(0xffffff, 0x000000, 0xff0000);
+
+        for (id, wix) in [(1, 0), (2, 0), (3, 1), (4, 2)] {
+            workspaces.add_client(wix, id).unwrap();
+            clients.insert(id, Client::new(&conn, id, wix, &[]));
+        }
+        clients.modify(4, |c| c.extra_tags.push(0));
+
+        (workspaces, clients)
+    }
+
+    fn ids(cs: Vec<&Client>) -> Vec<Xid> {
+        cs.iter().map(|c| c.id()).collect()
+    }
+
+    #[test]
+    fn clients_for_tags_are_ordered_by_the_tags_given() {
+        let (workspaces, clients) = tagged();
+        let mut expected = workspaces.client_ids(1).unwrap();
+        expected.extend(workspaces.client_ids(0).unwrap());
+        expected.push(4);
+
+        assert_eq!(
+            ids(clients_for_tags(&workspaces, &clients, &[1, 0])),
+            expected
+        );
+    }
+
+    #[test]
+    fn clients_are_only_shown_on_one_screen() {
+        let (workspaces, clients) = tagged();
+        let conn = MockXConn::new(test_screens(2), vec![], vec![]);
+        let mut screens = Screens::new(vec![BarConfig::new(10, Position::Top)], false);
+        screens.update_known_screens(&conn, 3).unwrap();
+        screens.set_extra_tags(1, vec![0]);
+
+        // The first tag is viewed on both screens but only shown on the one showing it as its
+        // workspace
+        assert_eq!(
+            ids(clients_for_screen(&workspaces, &clients, &screens, 0)),
+            vec![2, 1, 4]
+        );
+        assert_eq!(
+            ids(clients_for_screen(&workspaces, &clients, &screens, 1)),
+            vec![3]
+        );
+    }
+}
@@ -6,19 +6,14 @@ use crate::{
     Result,
 };
 
+use std::time::{Duration, Instant, SystemTime};
+
 #[cfg(feature = "serde")]
 use crate::{
     core::{manager::WindowManager, xconnection::XConn},
     PenroseError,
 };
 
-pub(super) fn pad_region(region: &Region, gapless: bool, gap_px: u32, border_px: u32) -> Region {
-    let gpx = if gapless { 0 } else { gap_px };
-    let padding = 2 * (border_px + gpx);
-    let (x, y, w, h) = region.values();
-    Region::new(x + gpx, y + gpx, w - padding, h - padding)
-}
-
 pub(super) fn position_floating_client<X>(
     conn: &X,
     id: Xid,
@@ -43,6 +38,70 @@ where
     Ok(conn.position_client(id, reg, border_px, false)?)
 }
 
+// Place a transient client (e.g. a dialog) centred over its parent, keeping it on the screen
+pub(super) fn position_transient_client<X>(
+    conn: &X,
+    id: Xid,
+    parent: Xid,
+    screen_region: Region,
+    border_px: u32,
+) -> Result<()>
+where
+    X: XClientConfig + XState,
+{
+    let r = conn.client_geometry(id)?;
+    let parent_region = conn.client_geometry(parent)?;
+    let (x, y, w, h) = centred_over(r, parent_region, screen_region).values();
+    let reg = Region::new(
+        x + border_px,
+        y + border_px,
+        w.saturating_sub(2 * border_px),
+        h.saturating_sub(2 * border_px),
+    );
+
+    Ok(conn.position_client(id, reg, border_px, false)?)
+}
+
+// The position of 'r' when centred over 'parent', moved back onto 'screen' if that would
+// overflow any of its edges
+pub(super) fn centred_over(r: Region, parent: Region, screen: Region) -> Region {
+    let (_, _, w, h) = r.values();
+    let (px, py, pw, ph) = parent.values();
+    let (sx, sy, sw, sh) = screen.values();
+
+    let clamp = |centre: i64, len: u32, start: u32, screen_len: u32| -> u32 {
+        let max = (start + screen_len).saturating_sub(len).max(start) as i64;
+        (centre - len as i64 / 2).max(start as i64).min(max) as u32
+    };
+    let x = clamp(px as i64 + pw as i64 / 2, w, sx, sw);
+    let y = clamp(py as i64 + ph as i64 / 2, h, sy, sh);
+
+    Region::new(x, y, w, h)
+}
+
+// The position of a client with geometry 'r' when it is stretched to fill the width and / or
+// height of 'screen', leaving room for its border
+pub(super) fn maximized_region(
+    r: Region,
+    screen: Region,
+    border_px: u32,
+    horz: bool,
+    vert: bool,
+) -> Region {
+    let (mut x, mut y, mut w, mut h) = r.values();
+    let (sx, sy, sw, sh) = screen.values();
+    if horz {
+        x = sx;
+        w = sw.saturating_sub(2 * border_px);
+    }
+    if vert {
+        y = sy;
+        h = sh.saturating_sub(2 * border_px);
+    }
+
+    Region::new(x, y, w, h)
+}
+
 #[cfg(feature = "serde")]
 pub(super) fn validate_hydrated_wm_state<X>(wm: &mut WindowManager<X>) -> Result<()>
 where
@@ -86,16 +145,64 @@ where
     Ok(())
 }
 
+// The amount of time that the system spent suspended between two readings of the monotonic and
+// wall clocks: the monotonic clock stops while the system is suspended but the wall clock does not.
+pub(super) fn time_suspended(last: (Instant, SystemTime), now: (Instant, SystemTime)) -> Duration {
+    let wall = now.1.duration_since(last.1).unwrap_or_default();
+
+    wall.saturating_sub(now.0.duration_since(last.0))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn pad_region_centered() {
-        let r = Region::new(0, 0, 200, 100);
-        let g = 10;
-        let b = 3;
-        assert_eq!(pad_region(&r, false, g, b), Region::new(10, 10, 174, 74));
-        assert_eq!(pad_region(&r, true, g, b), Region::new(0, 0, 194, 94));
+    test_cases! {
+        centred_over;
+        args: (parent: Region, expected: Region);
+
+        case: centred_on_parent => (Region::new(100, 100, 600, 400), Region::new(300, 250, 200, 100));
+        case: overflows_left_and_top => (Region::new(0, 0, 100, 50), Region::new(0, 0, 200, 100));
+        case: overflows_right_and_bottom => (Region::new(900, 750, 100, 50), Region::new(800, 700, 200, 100));
+
+        body: {
+            let screen = Region::new(0, 0, 1000, 800);
+            let r = Region::new(0, 0, 200, 100);
+            assert_eq!(centred_over(r, parent, screen), expected);
+        }
+    }
+
+    test_cases! {
+        maximized_region;
+        args: (horz: bool, vert: bool, expected: Region);
+
+        case: neither => (false, false, Region::new(100, 50, 200, 100));
+        case: horizontal => (true, false, Region::new(0, 50, 996, 100));
+        case: vertical => (false, true, Region::new(100, 20, 200, 776));
+        case: both => (true, true, Region::new(0, 20, 996, 776));
+
+        body: {
+            let screen = Region::new(0, 20, 1000, 780);
+            let r = Region::new(100, 50, 200, 100);
+            assert_eq!(maximized_region(r, screen, 2, horz, vert), expected);
+        }
+    }
+
+    test_cases! {
+        time_suspended;
+        args: (monotonic: u64, wall: u64, expected: u64);
+
+        case: clocks_agree => (60, 60, 0);
+        case: wall_clock_ahead => (60, 3660, 3600);
+        case: wall_clock_behind => (60, 30, 0);
+
+        body: {
+            let last = (Instant::now(), SystemTime::now());
+            let now = (
+                last.0 + Duration::from_secs(monotonic),
+                last.1 + Duration::from_secs(wall),
+            );
+            assert_eq!(time_suspended(last, now), Duration::from_secs(expected));
+        }
     }
 }
@@ -4,7 +4,7 @@ use crate::{
         client::Client,
         data_types::{Change, Region},
         hooks::HookName,
-        layout::LayoutConf,
+        layout::{LayoutConf, Spacing},
         manager::EventAction,
         ring::{Direction, InsertPoint, Ring, Selector},
         workspace::{ArrangeActions, Workspace},
@@ -111,7 +111,15 @@ impl Workspaces {
     }
 
     pub fn workspace_names(&self) -> Vec<String> {
-        self.inner.iter().map(|ws| ws.name().to_string()).collect()
+        self.inner.iter().map(|ws| ws.display_name()).collect()
+    }
+
+    // Returns true if the label was changed
+    pub fn set_workspace_label(&mut self, wix: usize, label: Option<String>) -> bool {
+        match self.inner.get_mut(wix) {
+            Some(ws) => ws.set_label(label),
+            None => false,
+        }
     }
 
     pub fn set_workspace_name(
@@ -165,9 +173,10 @@ impl Workspaces {
     }
 
     pub fn get_arrange_actions(
-        &mut self,
+        &self,
         wix: usize,
         region: Region,
+        spacing: &Spacing,
         clients: &[&Client],
     ) -> Result<(LayoutConf, ArrangeActions)> {
         let ws = self
@@ -177,7 +186,7 @@ impl Workspaces {
 
         let lc = ws.layout_conf();
         if !lc.floating {
-            Ok((lc, ws.arrange(region, clients)))
+            Ok((lc, ws.arrange(region, spacing, clients)))
         } else {
             Ok((
                 lc,
@@ -206,6 +215,45 @@ impl Workspaces {
         });
     }
 
+    pub fn swap_client(&mut self, wix: usize, direction: Direction) -> Option<Xid> {
+        self.inner
+            .get_mut(wix)
+            .and_then(|ws| ws.swap_client(direction))
+    }
+
+    pub fn swap_clients(&mut self, wix: usize, a: Xid, b: Xid) -> bool {
+        match self.inner.get_mut(wix) {
+            Some(ws) => ws.swap_clients(a, b),
+            None => false,
+        }
+    }
+
+    pub fn replace_client(&mut self, wix: usize, old: Xid, new: Xid) -> bool {
+        match self.inner.get_mut(wix) {
+            Some(ws) => ws.replace_client(old, new),
+            None => false,
+        }
+    }
+
+    pub fn minimize_client(&mut self, wix: usize, id: Xid) -> bool {
+        match self.inner.get_mut(wix) {
+            Some(ws) => ws.minimize_client(id),
+            None => false,
+        }
+    }
+
+    pub fn restore_minimized(&mut self, wix: usize, id: Xid) -> bool {
+        let ip = self.client_insert_point;
+        match self.inner.get_mut(wix) {
+            Some(ws) => ws.restore_minimized(id, &ip),
+            None => false,
+        }
+    }
+
+    pub fn promote_focused(&mut self, wix: usize) -> Option<Xid> {
+        self.inner.get_mut(wix).and_then(|ws| ws.promote_focused())
+    }
+
     pub fn rotate_clients(&mut self, wix: usize, direction: Direction) {
         self.inner.apply_to(&Selector::Index(wix), |ws| {
             ws.rotate_clients(direction);
@@ -224,8 +272,11 @@ impl Workspaces {
         });
     }
 
-    pub fn update_main_ratio(&mut self, wix: usize, change: Change) {
-        let step = self.main_ratio_step;
+    pub fn main_ratio_step(&self) -> f32 {
+        self.main_ratio_step
+    }
+
+    pub fn update_main_ratio_by(&mut self, wix: usize, change: Change, step: f32) {
         self.inner.apply_to(&Selector::Index(wix), |ws| {
             ws.update_main_ratio(change, step);
         });
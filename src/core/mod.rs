@@ -22,6 +22,17 @@
 //! the hook system for listening to internal `WindowManager` events, but these are not required in
 //! any way.
 //!
+//! Only the `WindowManager` communicates with the X server (through its `XConn`). The data
+//! structures that it uses to track state, such as [Workspaces][11], [Layouts][12] and the
+//! [Clients][13] themselves, do not depend on X, so layout functions and workspace logic can be
+//! reused and tested without a display server by using [detached][14] clients. The
+//! `WindowManager` and its hooks are generic over the `XConn` in use and ask it for what they
+//! need in window management terms (such as [the space reserved by a dock][15] or [asking a
+//! client to close][16]): the atoms and properties used to do this with X are implementation
+//! details of the `XConn` default methods. The one remaining X specific part of the
+//! `WindowManager` is the translation of incoming `XEvent`s into the actions that it takes in
+//! response to them.
+//!
 //! [1]: crate::core::xconnection::XEvent
 //! [2]: crate::core::manager::WindowManager
 //! [3]: crate::core::xconnection::XConn
@@ -32,6 +43,12 @@
 //! [8]: crate::gen_mousebindings
 //! [9]: crate::core::hooks
 //! [10]: crate::draw::bar
+//! [11]: crate::core::workspace::Workspace
+//! [12]: crate::core::layout::Layout
+//! [13]: crate::core::client::Client
+//! [14]: crate::core::client::Client::detached
+//! [15]: crate::core::xconnection::XClientProperties::client_strut
+//! [16]: crate::core::xconnection::XConn::request_client_close
 #[macro_use]
 pub mod macros;
 
@@ -41,12 +58,18 @@ pub mod config;
 pub mod data_types;
 pub mod helpers;
 pub mod hooks;
+pub mod input;
 pub mod layout;
 pub mod manager;
+pub mod power;
+pub mod process;
 pub mod ring;
+pub mod rules;
 pub mod screen;
+pub mod state;
 pub mod workspace;
 pub mod xconnection;
+pub mod xdg;
 
 #[doc(inline)]
 pub use bindings::{KeyEventHandler, MouseEventHandler};
@@ -0,0 +1,59 @@
+//! Lid, sleep and resume events from the system power manager
+//!
+//! Penrose only receives events from the X server, so changes in power state are passed to it as
+//! a `_PENROSE_POWER_EVENT` client message sent to the root window. The [Logind][1] extension
+//! watches systemd-logind and sends these messages for you but any other program can send them
+//! using the data generated by [PowerEvent::as_data].
+//!
+//! Each event is passed on to the matching [Hook][2] methods:
+//!
+//!   - [LidClosed][PowerEvent::LidClosed] and [LidOpened][PowerEvent::LidOpened] trigger
+//!     [lid_state_changed][3]
+//!   - [PrepareForSleep][PowerEvent::PrepareForSleep] triggers [suspending][4]
+//!   - [Resumed][PowerEvent::Resumed] triggers [resumed][5] after screens and bindings have been
+//!     checked, unless the resume has already been detected from the system clock
+//!
+//! [1]: crate::contrib::extensions::Logind
+//! [2]: crate::core::hooks::Hook
+//! [3]: crate::core::hooks::Hook::lid_state_changed
+//! [4]: crate::core::hooks::Hook::suspending
+//! [5]: crate::core::hooks::Hook::resumed
+
+/// A change in the power state of the system
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PowerEvent {
+    /// The laptop lid has been closed
+    LidClosed,
+    /// The laptop lid has been opened
+    LidOpened,
+    /// The system is about to be suspended or hibernated
+    PrepareForSleep,
+    /// The system has woken up from being suspended or hibernated
+    Resumed,
+}
+
+impl PowerEvent {
+    /// The data sent in a `_PENROSE_POWER_EVENT` client message for this event
+    pub fn as_data(&self) -> [u32; 5] {
+        let kind = match self {
+            Self::LidClosed => 0,
+            Self::LidOpened => 1,
+            Self::PrepareForSleep => 2,
+            Self::Resumed => 3,
+        };
+
+        [kind, 0, 0, 0, 0]
+    }
+
+    /// Parse the data from a `_PENROSE_POWER_EVENT` client message
+    pub fn from_data(data: &[u32]) -> Option<Self> {
+        match data.first()? {
+            0 => Some(Self::LidClosed),
+            1 => Some(Self::LidOpened),
+            2 => Some(Self::PrepareForSleep),
+            3 => Some(Self::Resumed),
+            _ => None,
+        }
+    }
+}
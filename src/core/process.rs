@@ -0,0 +1,312 @@
+//! Spawning external programs from key bindings and hooks
+//!
+//! Processes started through this module are fully detached from penrose: they are double forked
+//! so that they are re-parented to init rather than being left as zombies when they exit, placed
+//! in their own session so that they do not receive signals sent to penrose and have their
+//! `SIGCHLD` handling reset to the default in case it was being ignored when penrose was started.
+//! Output from the spawned process is sent to `/dev/null` and failures to start the process are
+//! logged before being returned.
+//!
+//! Penrose does not ignore `SIGCHLD` itself (doing so would break waiting on the output of
+//! processes run by [spawn_for_output][crate::core::helpers::spawn_for_output]), so if you start
+//! processes using [std::process::Command] directly you are responsible for waiting on them.
+//! Programs started with [WindowManager::autostart][crate::core::manager::WindowManager::autostart]
+//! are checked after each event and reaped as soon as they exit.
+//!
+//! The [spawn] and [spawn_with_args] functions (and the [spawn][crate::spawn] macro that wraps
+//! them) cover most use cases. If you need to set environment variables or run a program on a
+//! specific X display, use [Spawn] directly.
+//!
+//! Programs started from key bindings using [run_external][crate::run_external] (or
+//! [WindowManager::spawn][crate::core::manager::WindowManager::spawn]) additionally have
+//! [ENV_WORKSPACE], [ENV_WORKSPACE_INDEX] and [ENV_SCREEN] set to describe where they were
+//! launched from, so that scripts and terminals can adapt to the workspace they are opened on.
+//!
+//! # Example
+//! ```no_run
+//! use penrose::core::process::{spawn, spawn_with_args, Spawn};
+//!
+//! # fn example() -> penrose::Result<()> {
+//! spawn("dmenu_run -p run")?;
+//! spawn_with_args("notify-send", &["penrose", "hello from penrose"])?;
+//! Spawn::new("alacritty").env("WINIT_X11_SCALE_FACTOR", "1").spawn()?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{PenroseError, Result};
+
+use nix::{
+    libc,
+    sys::signal::{signal, SigHandler, Signal},
+    unistd::{fork, setsid, ForkResult},
+};
+
+use std::{
+    env, fs, io,
+    os::unix::process::CommandExt,
+    process::{Child, Command, Stdio},
+};
+
+/// The X display used for spawned processes if `DISPLAY` is not set for penrose itself
+pub const DEFAULT_DISPLAY: &str = ":0";
+
+/// Set to the name of the active workspace for programs spawned by the
+/// [WindowManager][crate::core::manager::WindowManager]
+pub const ENV_WORKSPACE: &str = "PENROSE_WS";
+
+/// Set to the index of the active workspace for programs spawned by the
+/// [WindowManager][crate::core::manager::WindowManager]
+pub const ENV_WORKSPACE_INDEX: &str = "PENROSE_WS_INDEX";
+
+/// Set to the index of the active screen for programs spawned by the
+/// [WindowManager][crate::core::manager::WindowManager]
+pub const ENV_SCREEN: &str = "PENROSE_SCREEN";
+
+/// Run an external command, splitting 'cmd' on whitespace to get the program and its arguments.
+///
+/// The process is detached from penrose and its stdout and stderr are sent to `/dev/null`.
+pub fn spawn<S: Into<String>>(cmd: S) -> Result<()> {
+    let s = cmd.into();
+    let mut parts = s.split_whitespace();
+    let prog = parts.next().unwrap_or_default();
+
+    Spawn::new(prog).args(parts).spawn()
+}
+
+/// Run an external command with the specified command line arguments
+///
+/// The process is detached from penrose and its stdout and stderr are sent to `/dev/null`.
+pub fn spawn_with_args<S: Into<String>>(cmd: S, args: &[&str]) -> Result<()> {
+    Spawn::new(cmd).args(args.iter().copied()).spawn()
+}
+
+/// A detached external process to be run by penrose
+///
+/// `DISPLAY` is always set for the new process: either to the value explicitly given using
+/// [display][Spawn::display], the value set for penrose itself or [DEFAULT_DISPLAY].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Spawn {
+    prog: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    display: Option<String>,
+}
+
+impl Spawn {
+    /// Create a new Spawn for running 'prog' with no arguments
+    pub fn new(prog: impl Into<String>) -> Self {
+        Self {
+            prog: prog.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a single command line argument
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Add multiple command line arguments
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(|a| a.into()));
+        self
+    }
+
+    /// Set an environment variable for the spawned process
+    pub fn env(mut self, key: impl Into<String>, val: impl Into<String>) -> Self {
+        self.env.push((key.into(), val.into()));
+        self
+    }
+
+    /// Run the process on the given X display rather than the one penrose is using
+    pub fn display(mut self, display: impl Into<String>) -> Self {
+        self.display = Some(display.into());
+        self
+    }
+
+    /// The X display that will be set for the spawned process
+    pub fn get_display(&self) -> String {
+        self.display
+            .clone()
+            .or_else(|| env::var("DISPLAY").ok())
+            .unwrap_or_else(|| DEFAULT_DISPLAY.to_string())
+    }
+
+    /// Start the process, returning an error if it could not be run.
+    ///
+    /// This does not wait for the process itself to exit.
+    pub fn spawn(&self) -> Result<()> {
+        let (prog, args) = (&self.prog, &self.args);
+        debug!(?prog, ?args, "spawning detached process");
+
+        let mut cmd = self.command();
+
+        // SAFETY: only async-signal-safe functions are called between fork and exec
+        unsafe { cmd.pre_exec(detach) };
+
+        // The child we spawn exits as soon as it has forked the real process so this does not
+        // block for long and leaves nothing behind to be reaped.
+        match cmd.spawn().and_then(|mut child| child.wait()) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!(?prog, ?args, "unable to spawn process: {}", e);
+                Err(PenroseError::SpawnFailed(prog.clone(), e))
+            }
+        }
+    }
+
+    /// Start the process without detaching it from penrose, returning the [Child] so that it can
+    /// be stopped later.
+    ///
+    /// The caller is responsible for waiting on the child once it has exited.
+    pub fn spawn_attached(&self) -> Result<Child> {
+        let (prog, args) = (&self.prog, &self.args);
+        debug!(?prog, ?args, "spawning attached process");
+
+        self.command().spawn().map_err(|e| {
+            error!(?prog, ?args, "unable to spawn process: {}", e);
+            PenroseError::SpawnFailed(prog.clone(), e)
+        })
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.prog);
+        cmd.args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .env("DISPLAY", self.get_display())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        cmd
+    }
+}
+
+/// The ID of the parent of the process with the given ID, read from `/proc`.
+///
+/// Returns `None` if the process does not exist (or has already exited) and for init, which has
+/// no parent.
+pub fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The process name is wrapped in parens and may itself contain spaces or parens so the
+    // remaining fields are taken from after the final ')': "pid (name) state ppid ..."
+    let fields = &stat[stat.rfind(')')? + 1..];
+
+    match fields.split_whitespace().nth(1)?.parse() {
+        Ok(0) | Err(_) => None,
+        Ok(ppid) => Some(ppid),
+    }
+}
+
+/// Whether or not the process with ID 'pid' was started (directly or indirectly) by the process
+/// with ID 'ancestor'.
+///
+/// A process is not considered to be a descendant of itself.
+pub fn is_descendant_of(pid: u32, ancestor: u32) -> bool {
+    let mut current = pid;
+    while let Some(parent) = parent_pid(current) {
+        if parent == ancestor {
+            return true;
+        }
+        current = parent;
+    }
+
+    false
+}
+
+// Run in the child process after it has been forked: fork a second time and exit so that the
+// process that calls exec is orphaned and will be reaped by init.
+fn detach() -> io::Result<()> {
+    // Ignored signals remain ignored across exec
+    unsafe { signal(Signal::SIGCHLD, SigHandler::SigDfl) }.map_err(to_io_error)?;
+
+    match fork().map_err(to_io_error)? {
+        ForkResult::Parent { .. } => unsafe { libc::_exit(0) },
+        ForkResult::Child => setsid().map(|_| ()).map_err(to_io_error),
+    }
+}
+
+fn to_io_error(e: nix::Error) -> io::Error {
+    match e.as_errno() {
+        Some(errno) => io::Error::from_raw_os_error(errno as i32),
+        None => io::ErrorKind::Other.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{path::PathBuf, thread, time::Duration};
+
+    fn read_when_written(path: &PathBuf) -> String {
+        for _ in 0..100 {
+            if let Ok(s) = fs::read_to_string(path) {
+                if s.ends_with('\n') {
+                    let _ = fs::remove_file(path);
+                    return s;
+                }
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        panic!("spawned process did not write to {:?}", path)
+    }
+
+    fn output_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("penrose-process-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn spawning_a_missing_program_is_an_error() {
+        let res = spawn("penrose-this-program-does-not-exist");
+        assert!(
+            matches!(res, Err(PenroseError::SpawnFailed(ref p, _)) if p == "penrose-this-program-does-not-exist")
+        );
+    }
+
+    #[test]
+    fn spawned_processes_are_detached() {
+        let path = output_path("detached");
+        let script = format!("echo $PPID $$ $(ps -o sid= -p $$) > {}", path.display());
+        spawn_with_args("sh", &["-c", &script]).unwrap();
+
+        let output = read_when_written(&path);
+        let ids: Vec<&str> = output.split_whitespace().collect();
+
+        // re-parented away from us and the leader of its own session
+        assert_ne!(ids[0], std::process::id().to_string());
+        if let Some(&sid) = ids.get(2) {
+            assert_eq!(sid, ids[1]);
+        }
+    }
+
+    #[test]
+    fn display_and_env_are_set() {
+        let path = output_path("env");
+        let script = format!("echo $DISPLAY $PENROSE_TEST > {}", path.display());
+        Spawn::new("sh")
+            .args(vec!["-c", &script])
+            .env("PENROSE_TEST", "set")
+            .display(":42")
+            .spawn()
+            .unwrap();
+
+        assert_eq!(read_when_written(&path), ":42 set\n");
+    }
+
+    #[test]
+    fn process_ancestry() {
+        let pid = std::process::id();
+        let parent = parent_pid(pid).expect("test process has a parent");
+
+        assert!(is_descendant_of(pid, parent));
+        assert!(!is_descendant_of(parent, pid));
+        assert!(!is_descendant_of(pid, pid));
+    }
+}
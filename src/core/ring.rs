@@ -183,6 +183,33 @@ impl<T> Ring<T> {
         self.cycle_focus(direction)
     }
 
+    pub fn swap_focused(&mut self, direction: Direction) -> Option<&T> {
+        if self.elements.len() < 2 {
+            return None;
+        }
+        let other = self.next_index(direction);
+        self.elements.swap(self.focused, other);
+        self.focused = other;
+        self.focused()
+    }
+
+    pub fn swap(&mut self, i: usize, j: usize) {
+        self.elements.swap(i, j)
+    }
+
+    pub fn promote_focused(&mut self) -> Option<&T> {
+        match self.focused {
+            _ if self.elements.len() < 2 => return None,
+            0 => self.elements.swap(0, 1),
+            ix => {
+                let elem = self.elements.remove(ix)?;
+                self.elements.push_front(elem);
+            }
+        }
+        self.focused = 0;
+        self.focused()
+    }
+
     pub fn len(&self) -> usize {
         self.elements.len()
     }
@@ -485,6 +512,45 @@ mod tests {
         assert_eq!(r.focused(), Some(&1));
     }
 
+    #[test]
+    fn swapping_an_element_wraps_without_rotating() {
+        let mut r = Ring::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(r.swap_focused(Direction::Forward), Some(&1));
+        assert_eq!(r.elements, vec![2, 1, 3, 4]);
+
+        r.focused = 3;
+        assert_eq!(r.swap_focused(Direction::Forward), Some(&4));
+        assert_eq!(r.elements, vec![4, 1, 3, 2]);
+        assert_eq!(r.focused_index(), 0);
+
+        assert_eq!(r.swap_focused(Direction::Backward), Some(&4));
+        assert_eq!(r.elements, vec![2, 1, 3, 4]);
+        assert_eq!(r.focused_index(), 3);
+    }
+
+    #[test]
+    fn promoting_an_element() {
+        let mut r = Ring::new(vec![1, 2, 3, 4]);
+        r.focused = 2;
+
+        assert_eq!(r.promote_focused(), Some(&3));
+        assert_eq!(r.elements, vec![3, 1, 2, 4]);
+        assert_eq!(r.focused_index(), 0);
+
+        // promoting the head swaps it with the next element
+        assert_eq!(r.promote_focused(), Some(&1));
+        assert_eq!(r.elements, vec![1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn swap_and_promote_need_two_elements() {
+        let mut r = Ring::new(vec![1]);
+        assert_eq!(r.swap_focused(Direction::Forward), None);
+        assert_eq!(r.promote_focused(), None);
+        assert_eq!(r.elements, vec![1]);
+    }
+
     #[test]
     fn remove_focused() {
         let mut r = Ring::new(vec![1, 2, 3]);
@@ -0,0 +1,186 @@
+//! Rules for modifying how newly mapped clients are handled
+//!
+//! A [Rule] pairs a set of [Matcher]s with a set of [RuleAction]s that are applied to any new
+//! client window for which all of the matchers succeed. Rules are evaluated in the order that
+//! they are given in your [Config][crate::core::config::Config] before the client is placed on
+//! a workspace, and the actions of every matching rule are applied.
+//!
+//! # Example
+//! ```
+//! use penrose::{
+//!     core::rules::{Matcher, Rule, RuleAction},
+//!     Config,
+//! };
+//!
+//! let rules = vec![
+//!     Rule::new(
+//!         vec![Matcher::Class("firefox".into())],
+//!         vec![RuleAction::ToWorkspace(1), RuleAction::FocusedBorder(0x98971a.into())],
+//!     ),
+//!     Rule::new(
+//!         vec![Matcher::Class("Alacritty".into())],
+//!         vec![RuleAction::FocusedBorder(0x458588.into())],
+//!     ),
+//!     Rule::new(
+//!         vec![Matcher::Role("pop-up".into())],
+//!         vec![RuleAction::Float],
+//!     ),
+//! ];
+//!
+//! let config = Config::default().builder().rules(rules).build().unwrap();
+//! ```
+//!
+//! # Window swallowing
+//! Clients matched by a rule using [RuleAction::Terminal] are swallowed by any graphical program
+//! that they start: the terminal is hidden and the new client takes its place in the layout until
+//! it exits, at which point the terminal is shown again. Programs are matched to the terminal that
+//! started them using the `_NET_WM_PID` property of both windows, so this only works for clients
+//! that set it and are running on the same machine as penrose. Use [RuleAction::NoSwallow] for
+//! programs that should never replace their terminal.
+use crate::{core::client::Client, draw::Color};
+
+/// A check against the properties of a newly mapped [Client]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Matcher {
+    /// The WM_CLASS of the client is exactly this string
+    Class(String),
+    /// The WM_NAME (or _NET_WM_NAME) of the client is exactly this string
+    Name(String),
+    /// The WM_NAME (or _NET_WM_NAME) of the client contains this string
+    NameContains(String),
+    /// The WM_WINDOW_ROLE of the client is exactly this string
+    Role(String),
+}
+
+impl Matcher {
+    /// Check whether or not this Matcher matches the given [Client]
+    pub fn matches(&self, client: &Client) -> bool {
+        match self {
+            Matcher::Class(class) => client.wm_class.iter().any(|c| c == class),
+            Matcher::Name(name) => client.wm_name() == name,
+            Matcher::NameContains(s) => client.wm_name().contains(s.as_str()),
+            Matcher::Role(role) => client.wm_role() == Some(role.as_str()),
+        }
+    }
+}
+
+/// An action to take for a newly mapped [Client] that has been matched by a [Rule]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleAction {
+    /// Place the client on the workspace with this index rather than the active workspace
+    ToWorkspace(usize),
+    /// Float the client rather than tiling it
+    Float,
+    /// Make the client fullscreen once it has been mapped
+    Fullscreen,
+    /// Map the client without managing it (as is done for docks and notifications)
+    Ignore,
+    /// Use this border color for the client when it does not have focus
+    Border(Color),
+    /// Use this border color for the client when it has focus. Matching on [Matcher::Class]
+    /// allows groups of similar programs (terminals, browsers...) to share a focus color.
+    FocusedBorder(Color),
+    /// The client is a terminal: graphical programs started from it will take its place in the
+    /// layout (swallowing it) until they exit
+    Terminal,
+    /// Never let the client swallow the terminal that started it
+    NoSwallow,
+}
+
+/// A set of [Matcher]s and the [RuleAction]s to apply to clients that satisfy all of them
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    matchers: Vec<Matcher>,
+    actions: Vec<RuleAction>,
+}
+
+impl Rule {
+    /// Create a new Rule. A Rule with no matchers will match every client.
+    pub fn new(matchers: Vec<Matcher>, actions: Vec<RuleAction>) -> Self {
+        Self { matchers, actions }
+    }
+
+    /// Check whether or not all of the matchers for this Rule match the given [Client]
+    pub fn matches(&self, client: &Client) -> bool {
+        self.matchers.iter().all(|m| m.matches(client))
+    }
+
+    /// The actions that will be applied to clients matched by this Rule
+    pub fn actions(&self) -> &[RuleAction] {
+        &self.actions
+    }
+}
+
+// The actions of every rule that matches the given client, in the order that the rules were given
+pub(crate) fn matching_actions(rules: &[Rule], client: &Client) -> Vec<RuleAction> {
+    rules
+        .iter()
+        .filter(|r| r.matches(client))
+        .flat_map(|r| r.actions.iter().cloned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::xconnection::MockXConn;
+
+    fn client(class: &str, name: &str, role: Option<&str>) -> Client {
+        let conn = MockXConn::new(vec![], vec![], vec![]);
+        let mut c = Client::new(&conn, 1, 0, &[]);
+        c.wm_class = vec![class.into(), class.into()];
+        c.set_name(name);
+        c.wm_role = role.map(|r| r.into());
+        c
+    }
+
+    test_cases! {
+        matcher;
+        args: (m: Matcher, expected: bool);
+
+        case: class => (Matcher::Class("firefox".into()), true);
+        case: class_mismatch => (Matcher::Class("chromium".into()), false);
+        case: name => (Matcher::Name("Mozilla Firefox".into()), true);
+        case: name_contains => (Matcher::NameContains("Fire".into()), true);
+        case: name_partial_mismatch => (Matcher::Name("Firefox".into()), false);
+        case: role => (Matcher::Role("browser".into()), true);
+        case: role_mismatch => (Matcher::Role("pop-up".into()), false);
+
+        body: {
+            let c = client("firefox", "Mozilla Firefox", Some("browser"));
+            assert_eq!(m.matches(&c), expected);
+        }
+    }
+
+    #[test]
+    fn all_matchers_must_match() {
+        let c = client("firefox", "Mozilla Firefox", None);
+        let rule = Rule::new(
+            vec![
+                Matcher::Class("firefox".into()),
+                Matcher::Role("pop-up".into()),
+            ],
+            vec![RuleAction::Float],
+        );
+
+        assert!(!rule.matches(&c));
+    }
+
+    #[test]
+    fn actions_from_all_matching_rules_are_returned_in_order() {
+        let c = client("mpv", "video.mkv", None);
+        let rules = vec![
+            Rule::new(vec![Matcher::Class("mpv".into())], vec![RuleAction::Float]),
+            Rule::new(vec![Matcher::Class("st".into())], vec![RuleAction::Ignore]),
+            Rule::new(vec![], vec![RuleAction::ToWorkspace(3)]),
+        ];
+
+        assert_eq!(
+            matching_actions(&rules, &c),
+            vec![RuleAction::Float, RuleAction::ToWorkspace(3)]
+        );
+    }
+}
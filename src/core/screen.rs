@@ -1,6 +1,15 @@
 //! Information on connected displays
 use crate::core::data_types::{Point, Region};
 
+/// The DPI that sizes given in pixels are assumed to be for when scaling them for a [Screen]
+pub const DEFAULT_DPI: u32 = 96;
+
+// Screens serialized before DPI was tracked are treated as having the default DPI
+#[cfg(feature = "serde")]
+fn default_dpi() -> u32 {
+    DEFAULT_DPI
+}
+
 /// Display information for a connected screen
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -9,6 +18,8 @@ pub struct Screen {
     pub wix: usize,
     true_region: Region,
     effective_region: Region,
+    #[cfg_attr(feature = "serde", serde(default = "default_dpi"))]
+    dpi: u32,
 }
 
 impl Screen {
@@ -18,18 +29,97 @@ impl Screen {
             true_region: region,
             effective_region: region,
             wix,
+            dpi: DEFAULT_DPI,
         }
     }
 
+    /// Set the DPI of this screen, used to [scale][Screen::scale] sizes given in pixels.
+    /// A DPI of `0` is treated as [DEFAULT_DPI].
+    pub fn with_dpi(mut self, dpi: u32) -> Screen {
+        self.dpi = if dpi == 0 { DEFAULT_DPI } else { dpi };
+        self
+    }
+
+    /// The DPI of this screen
+    pub fn dpi(&self) -> u32 {
+        self.dpi
+    }
+
+    /// The factor that sizes given for a [DEFAULT_DPI] screen need to be multiplied by in order
+    /// to appear the same size on this screen
+    pub fn scale_factor(&self) -> f64 {
+        self.dpi as f64 / DEFAULT_DPI as f64
+    }
+
+    /// Scale a size in pixels given for a [DEFAULT_DPI] screen to this screen, rounding to the
+    /// nearest whole pixel.
+    ///
+    /// ```
+    /// # use penrose::core::{data_types::Region, screen::Screen};
+    /// let s = Screen::new(Region::new(0, 0, 2560, 1440), 0).with_dpi(192);
+    /// assert_eq!(s.scale(18), 36);
+    /// ```
+    pub fn scale(&self, px: u32) -> u32 {
+        (px as f64 * self.scale_factor()).round() as u32
+    }
+
     /// Cache the current effective region of this screen based on whether or not a bar is
     /// displayed and if that bar is positioned at the top or bottom of the screen.
     pub fn update_effective_region(&mut self, bar_height: u32, top_bar: bool) {
-        let (x, y, w, h) = self.true_region.values();
-        self.effective_region = if top_bar {
-            Region::new(x, y + bar_height, w, h - bar_height)
+        let root = self.true_region;
+        self.update_effective_region_with_struts(bar_height, top_bar, &[], &root);
+    }
+
+    /// Cache the current effective region of this screen, reserving space for a bar as in
+    /// [update_effective_region][Screen::update_effective_region] along with any space reserved
+    /// by dock windows through their [Strut]s. `root` is the region covered by the root window.
+    ///
+    /// When more than one reservation applies to the same edge of the screen the largest of
+    /// them is used: struts are measured from the edge of the root window so a dock stacked
+    /// beneath a bar will already include the space taken up by the bar.
+    pub fn update_effective_region_with_struts(
+        &mut self,
+        bar_height: u32,
+        top_bar: bool,
+        struts: &[Strut],
+        root: &Region,
+    ) {
+        let (top, bottom) = if top_bar {
+            (bar_height, 0)
         } else {
-            Region::new(x, y, w, h - bar_height)
+            (0, bar_height)
+        };
+        self.update_effective_region_with_bars(top, bottom, struts, root);
+    }
+
+    /// Cache the current effective region of this screen, reserving `top` and `bottom` pixels
+    /// for bars at the top and bottom of the screen along with any space reserved by dock windows
+    /// as in [update_effective_region_with_struts][Screen::update_effective_region_with_struts].
+    pub fn update_effective_region_with_bars(
+        &mut self,
+        top: u32,
+        bottom: u32,
+        struts: &[Strut],
+        root: &Region,
+    ) {
+        let (x, y, w, h) = self.true_region.values();
+        let (mut top, mut bottom) = (top, bottom);
+        let (mut left, mut right) = (0, 0);
+
+        for strut in struts {
+            let (t, b, l, r) = strut.reserved_on(&self.true_region, root);
+            top = top.max(t);
+            bottom = bottom.max(b);
+            left = left.max(l);
+            right = right.max(r);
         }
+
+        let top = top.min(h);
+        let bottom = bottom.min(h - top);
+        let left = left.min(w);
+        let right = right.min(w - left);
+
+        self.effective_region = Region::new(x + left, y + top, w - left - right, h - top - bottom);
     }
 
     /// The available space for displaying clients on this screen. If 'effective_only' then the
@@ -48,3 +138,223 @@ impl Screen {
         self.true_region.contains_point(&p)
     }
 }
+
+/// The DPI of a screen from its size in pixels and its physical size in millimetres (as reported
+/// by RandR). The larger of the two dimensions is used so that rotated screens are handled
+/// correctly.
+///
+/// Returns `None` if the physical size is unknown, which many projectors and virtual displays
+/// report as being 0.
+pub fn dpi_from_physical_size(px: (u32, u32), mm: (u32, u32)) -> Option<u32> {
+    let (px, mm) = (px.0.max(px.1), mm.0.max(mm.1));
+    if px == 0 || mm == 0 {
+        return None;
+    }
+
+    Some((px as f64 * 25.4 / mm as f64).round() as u32)
+}
+
+/// Parse the value of `Xft.dpi` from the X resource database (the contents of the
+/// `RESOURCE_MANAGER` property on the root window).
+///
+/// ```
+/// # use penrose::core::screen::parse_xft_dpi;
+/// assert_eq!(parse_xft_dpi("Xft.antialias:\t1\nXft.dpi:\t192\n"), Some(192));
+/// assert_eq!(parse_xft_dpi("Xft.antialias:\t1\n"), None);
+/// ```
+pub fn parse_xft_dpi(resources: &str) -> Option<u32> {
+    resources.lines().find_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        match (parts.next().map(str::trim), parts.next()) {
+            (Some("Xft.dpi"), Some(val)) => val.trim().parse::<f64>().ok().map(|d| d as u32),
+            _ => None,
+        }
+    })
+}
+
+/// Space reserved at the edges of the root window by a dock or panel through the
+/// `_NET_WM_STRUT_PARTIAL` or `_NET_WM_STRUT` properties.
+///
+/// Each reservation is measured from the corresponding edge of the root window rather than from
+/// the edge of an individual [Screen], and applies along the given inclusive (start, end) range
+/// of the perpendicular axis.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Strut {
+    /// Space reserved at the left edge of the root window
+    pub left: u32,
+    /// Space reserved at the right edge of the root window
+    pub right: u32,
+    /// Space reserved at the top edge of the root window
+    pub top: u32,
+    /// Space reserved at the bottom edge of the root window
+    pub bottom: u32,
+    /// The y-coordinates covered by the left reservation
+    pub left_range: (u32, u32),
+    /// The y-coordinates covered by the right reservation
+    pub right_range: (u32, u32),
+    /// The x-coordinates covered by the top reservation
+    pub top_range: (u32, u32),
+    /// The x-coordinates covered by the bottom reservation
+    pub bottom_range: (u32, u32),
+}
+
+impl Strut {
+    /// Parse the raw values of either a `_NET_WM_STRUT_PARTIAL` (12 values) or `_NET_WM_STRUT`
+    /// (4 values) property. `_NET_WM_STRUT` reservations span the full length of their edge.
+    ///
+    /// Returns `None` if there are too few values or no space is actually being reserved.
+    ///
+    /// ```
+    /// # use penrose::core::screen::Strut;
+    /// let strut = Strut::from_raw(&[0, 0, 20, 0]).unwrap();
+    /// assert_eq!(strut.top, 20);
+    /// assert_eq!(strut.top_range, (0, u32::MAX));
+    ///
+    /// assert_eq!(Strut::from_raw(&[0, 0, 0, 0]), None);
+    /// ```
+    pub fn from_raw(raw: &[u32]) -> Option<Self> {
+        let full = (0, u32::MAX);
+        let strut = match *raw {
+            [left, right, top, bottom, ly0, ly1, ry0, ry1, tx0, tx1, bx0, bx1, ..] => Self {
+                left,
+                right,
+                top,
+                bottom,
+                left_range: (ly0, ly1),
+                right_range: (ry0, ry1),
+                top_range: (tx0, tx1),
+                bottom_range: (bx0, bx1),
+            },
+            [left, right, top, bottom, ..] => Self {
+                left,
+                right,
+                top,
+                bottom,
+                left_range: full,
+                right_range: full,
+                top_range: full,
+                bottom_range: full,
+            },
+            _ => return None,
+        };
+
+        if [strut.left, strut.right, strut.top, strut.bottom] == [0; 4] {
+            None
+        } else {
+            Some(strut)
+        }
+    }
+
+    /// The (top, bottom, left, right) space that this strut reserves on a screen occupying
+    /// `screen` within a root window covering `root`.
+    pub fn reserved_on(&self, screen: &Region, root: &Region) -> (u32, u32, u32, u32) {
+        let (sx, sy, sw, sh) = screen.values();
+        let (rx, ry, rw, rh) = root.values();
+
+        // Only the part of a reservation that extends past the distance between the edge of
+        // the root window and the edge of this screen applies to this screen.
+        let reserve = |size: u32, dist: u32, (start, end): (u32, u32), lo: u32, len: u32| {
+            if size > dist && start < lo + len && end >= lo {
+                size - dist
+            } else {
+                0
+            }
+        };
+
+        (
+            reserve(self.top, sy.saturating_sub(ry), self.top_range, sx, sw),
+            reserve(
+                self.bottom,
+                (ry + rh).saturating_sub(sy + sh),
+                self.bottom_range,
+                sx,
+                sw,
+            ),
+            reserve(self.left, sx.saturating_sub(rx), self.left_range, sy, sh),
+            reserve(
+                self.right,
+                (rx + rw).saturating_sub(sx + sw),
+                self.right_range,
+                sy,
+                sh,
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(left: u32, right: u32, top: u32, bottom: u32, range: (u32, u32)) -> Strut {
+        Strut {
+            left,
+            right,
+            top,
+            bottom,
+            left_range: range,
+            right_range: range,
+            top_range: range,
+            bottom_range: range,
+        }
+    }
+
+    test_cases! {
+        effective_region;
+        args: (struts: &[Strut], expected: Region);
+
+        case: no_struts => (&[], Region::new(1000, 10, 1000, 590));
+        case: full_width_top => (&[Strut::from_raw(&[0, 0, 30, 0]).unwrap()], Region::new(1000, 30, 1000, 570));
+        case: largest_reservation_wins => (
+            &[partial(0, 0, 20, 0, (0, 1999)), partial(0, 0, 40, 0, (1200, 1400))],
+            Region::new(1000, 40, 1000, 560)
+        );
+        case: other_screen_ignored => (&[partial(0, 0, 30, 0, (0, 999))], Region::new(1000, 10, 1000, 590));
+        case: shorter_screen_bottom => (&[partial(0, 0, 0, 220, (1000, 1999))], Region::new(1000, 10, 1000, 570));
+        case: right_edge => (&[partial(0, 50, 0, 0, (0, 599))], Region::new(1000, 10, 950, 590));
+        case: left_edge_other_screen => (&[partial(50, 0, 0, 0, (0, 799))], Region::new(1000, 10, 1000, 590));
+        body: {
+            // second of two screens: 1000x800 on the left and 1000x600 on the right
+            let root = Region::new(0, 0, 2000, 800);
+            let mut s = Screen::new(Region::new(1000, 0, 1000, 600), 1);
+            s.update_effective_region_with_struts(10, true, struts, &root);
+
+            assert_eq!(s.region(true), expected);
+        }
+    }
+
+    #[test]
+    fn from_raw_needs_at_least_four_values() {
+        assert_eq!(Strut::from_raw(&[0, 0, 10]), None);
+    }
+
+    test_cases! {
+        dpi_from_physical_size;
+        args: (px: (u32, u32), mm: (u32, u32), expected: Option<u32>);
+
+        case: standard_1080p => ((1920, 1080), (508, 286), Some(96));
+        case: hidpi_laptop => ((2880, 1800), (286, 179), Some(256));
+        case: rotated => ((1080, 1920), (286, 508), Some(96));
+        case: unknown_size => ((1920, 1080), (0, 0), None);
+
+        body: {
+            assert_eq!(dpi_from_physical_size(px, mm), expected);
+        }
+    }
+
+    test_cases! {
+        parse_xft_dpi;
+        args: (resources: &str, expected: Option<u32>);
+
+        case: tab_separated => ("Xft.dpi:\t144\n", Some(144));
+        case: among_other_resources => ("*.foreground: #ebdbb2\nXft.dpi: 120\nXft.hinting: 1", Some(120));
+        case: fractional => ("Xft.dpi:\t96.5", Some(96));
+        case: missing => ("Xft.hinting: 1", None);
+        case: invalid => ("Xft.dpi: lots", None);
+
+        body: {
+            assert_eq!(parse_xft_dpi(resources), expected);
+        }
+    }
+}
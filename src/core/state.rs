@@ -0,0 +1,122 @@
+//! Point in time snapshots of [WindowManager][1] state
+//!
+//! A [State] is a plain data copy of what the window manager is currently managing: the screens
+//! that are connected, the workspaces they are showing and the clients on each workspace along
+//! with where they are positioned. Snapshots are taken using [WindowManager::snapshot][2] and
+//! (with the `serde` feature enabled) can be serialized directly, making them suitable for
+//! sending to external programs such as bar scripts or for debugging.
+//!
+//! Unlike serializing the [WindowManager][1] itself, a snapshot can not be used to restore the
+//! window manager: it only describes what is being shown.
+//!
+//! # Example
+//! ```
+//! # use penrose::__test_helpers::*;
+//! # fn example(wm: &TestWM) -> penrose::Result<()> {
+//! let state = wm.snapshot();
+//!
+//! assert_eq!(state.focused_workspace, 0);
+//! assert_eq!(state.visible_workspaces(), vec![0]);
+//! assert!(state.clients_on_workspace(0).all(|c| c.workspace == 0));
+//! # Ok(())
+//! # }
+//! # example(&test_windowmanager(1, vec![])).unwrap();
+//! ```
+//!
+//! [1]: crate::core::manager::WindowManager
+//! [2]: crate::core::manager::WindowManager::snapshot
+use crate::core::{bindings::KeyBindingReport, data_types::Region, xconnection::Xid};
+
+/// A snapshot of the screens, workspaces and clients being managed by a [WindowManager][1].
+///
+/// [1]: crate::core::manager::WindowManager
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    /// The connected screens in index order
+    pub screens: Vec<ScreenState>,
+    /// The known workspaces in index order
+    pub workspaces: Vec<WorkspaceState>,
+    /// Clients managed on a workspace, in workspace order
+    pub clients: Vec<ClientState>,
+    /// The index of the screen that currently has focus
+    pub focused_screen: usize,
+    /// The index of the workspace shown on the focused screen
+    pub focused_workspace: usize,
+    /// The client that currently has focus (if there is one)
+    pub focused_client: Option<Xid>,
+    /// The parsed key bindings and any problems found with them, if a report was given to
+    /// [set_key_binding_report][1]
+    ///
+    /// [1]: crate::core::manager::WindowManager::set_key_binding_report
+    pub key_bindings: Option<KeyBindingReport>,
+}
+
+impl State {
+    /// The state of the client with the given ID if it is being managed
+    pub fn client(&self, id: Xid) -> Option<&ClientState> {
+        self.clients.iter().find(|c| c.id == id)
+    }
+
+    /// The clients on the workspace with the given index, in the order that they are stacked
+    pub fn clients_on_workspace(&self, wix: usize) -> impl Iterator<Item = &ClientState> {
+        self.clients.iter().filter(move |c| c.workspace == wix)
+    }
+
+    /// The indices of the workspaces currently being shown on each screen
+    pub fn visible_workspaces(&self) -> Vec<usize> {
+        self.screens.iter().map(|s| s.workspace).collect()
+    }
+}
+
+/// A snapshot of a single screen
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenState {
+    /// The index of this screen
+    pub index: usize,
+    /// The full size of this screen
+    pub region: Region,
+    /// The size of this screen minus any space reserved for bars or docks
+    pub effective_region: Region,
+    /// The index of the workspace being shown on this screen
+    pub workspace: usize,
+}
+
+/// A snapshot of a single workspace
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceState {
+    /// The index of this workspace
+    pub index: usize,
+    /// The name of this workspace
+    pub name: String,
+    /// The symbol of the active layout
+    pub layout: String,
+    /// The IDs of the clients on this workspace in the order that they are stacked
+    pub clients: Vec<Xid>,
+    /// The focused client within this workspace (if there is one)
+    pub focused_client: Option<Xid>,
+}
+
+/// A snapshot of a single client
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientState {
+    /// The X window ID of this client
+    pub id: Xid,
+    /// The WM_CLASS of this client
+    pub class: String,
+    /// The WM_NAME of this client
+    pub name: String,
+    /// The index of the workspace this client is on
+    pub workspace: usize,
+    /// Whether or not this client is floating
+    pub floating: bool,
+    /// Whether or not this client is fullscreen
+    pub fullscreen: bool,
+    /// Whether or not this client has set its urgency hint
+    pub urgent: bool,
+    /// The current position of this client if it could be fetched from the X server
+    pub geometry: Option<Region>,
+}
@@ -11,10 +11,11 @@ use crate::{
     core::{
         client::Client,
         data_types::{Change, Region, ResizeAction},
-        layout::{Layout, LayoutConf},
+        layout::{Layout, LayoutConf, Spacing},
         ring::{Direction, InsertPoint, Ring, Selector},
         xconnection::Xid,
     },
+    draw::Background,
     Result,
 };
 
@@ -43,6 +44,12 @@ pub struct Workspace {
     name: String,
     clients: Ring<Xid>,
     layouts: Ring<Layout>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    label: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    minimized: Vec<Xid>, // most recently minimized last
+    #[cfg_attr(feature = "serde", serde(default))]
+    background: Option<Background>,
 }
 
 impl Workspace {
@@ -58,6 +65,9 @@ impl Workspace {
             name: name.into(),
             clients: Ring::new(Vec::new()),
             layouts: Ring::new(layouts),
+            label: None,
+            minimized: vec![],
+            background: None,
         }
     }
 
@@ -70,6 +80,52 @@ impl Workspace {
         self.name = name.into();
     }
 
+    /// The label generated for this workspace from its content when
+    /// [auto_name_workspaces][crate::core::config::Config::auto_name_workspaces] is set.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The name of this workspace followed by its label (if it has one)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(workspace: Workspace) -> Result<()> {
+    /// assert_eq!(workspace.label(), None);
+    /// assert_eq!(workspace.display_name(), "example");
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 5)).unwrap();
+    /// ```
+    pub fn display_name(&self) -> String {
+        match self.label {
+            Some(ref label) => format!("{} {}", self.name, label),
+            None => self.name.clone(),
+        }
+    }
+
+    // Returns true if the label was changed
+    pub(crate) fn set_label(&mut self, label: Option<String>) -> bool {
+        let changed = self.label != label;
+        self.label = label;
+        changed
+    }
+
+    /// The background of the root window while this workspace is shown (if one has been set).
+    ///
+    /// See [set_workspace_background][1] for details.
+    ///
+    /// [1]: crate::core::manager::WindowManager::set_workspace_background
+    pub fn background(&self) -> Option<&Background> {
+        self.background.as_ref()
+    }
+
+    pub(crate) fn set_background(&mut self, background: Option<Background>) {
+        self.background = background;
+    }
+
     #[cfg(feature = "serde")]
     pub(crate) fn restore_layout_functions(
         &mut self,
@@ -225,9 +281,78 @@ impl Workspace {
     /// # example(test_workspace("example", 5)).unwrap();
     /// ```
     pub fn remove_client(&mut self, id: Xid) -> Option<Xid> {
+        self.minimized.retain(|&m| m != id);
         self.clients.remove(&Selector::Condition(&|c| *c == id))
     }
 
+    /// The clients that have been minimized on this workspace, most recently minimized last.
+    pub fn minimized_clients(&self) -> &[Xid] {
+        &self.minimized
+    }
+
+    /// Remove a client from the stack, placing it on top of the stack of minimized clients for
+    /// this workspace. Returns false if the client was not in the stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert!(workspace.minimize_client(1));
+    /// assert!(workspace.minimize_client(3));
+    /// assert_eq!(workspace.client_ids(), vec![0, 2]);
+    /// assert_eq!(workspace.minimized_clients(), &[1, 3]);
+    ///
+    /// assert!(!workspace.minimize_client(42));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn minimize_client(&mut self, id: Xid) -> bool {
+        if self
+            .clients
+            .remove(&Selector::Condition(&|c| *c == id))
+            .is_none()
+        {
+            return false;
+        }
+        self.minimized.push(id);
+
+        true
+    }
+
+    /// Return a minimized client to the stack at the given [InsertPoint], focusing it. Returns
+    /// false if the client was not minimized on this workspace.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// workspace.minimize_client(1);
+    /// workspace.minimize_client(3);
+    ///
+    /// assert!(workspace.restore_minimized(1, &InsertPoint::First));
+    /// assert_eq!(workspace.client_ids(), vec![1, 0, 2]);
+    /// assert_eq!(workspace.focused_client(), Some(1));
+    /// assert_eq!(workspace.minimized_clients(), &[3]);
+    ///
+    /// assert!(!workspace.restore_minimized(1, &InsertPoint::First));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn restore_minimized(&mut self, id: Xid, ip: &InsertPoint) -> bool {
+        if !self.minimized.contains(&id) {
+            return false;
+        }
+        self.minimized.retain(|&m| m != id);
+        self.clients.insert_at(ip, id);
+        self.focus_client(id);
+
+        true
+    }
+
     /// Remove the currently focused client, keeping focus at the same position in the stack.
     /// Returns the removed client if there was one to remove.
     ///
@@ -256,13 +381,26 @@ impl Workspace {
     pub(crate) fn arrange(
         &self,
         screen_region: Region,
+        spacing: &Spacing,
         managed_workspace_clients: &[&Client],
     ) -> ArrangeActions {
-        if self.clients.len() > 0 {
+        // In tag mode clients from other workspaces may be shown even if this one is empty
+        let has_tiled = managed_workspace_clients.iter().any(|c| !c.floating);
+        if self.clients.len() > 0 || has_tiled {
             let layout = self.layouts.focused_unchecked();
-            let (floating, tiled): (Vec<&Client>, Vec<&Client>) =
+            let (floating, mut tiled): (Vec<&Client>, Vec<&Client>) =
                 managed_workspace_clients.iter().partition(|c| c.floating);
 
+            // Layouts position clients in the order they are given so they need to follow the
+            // order of the client stack rather than the order they were passed in. Clients from
+            // other workspaces follow those from this one in the order that they were given.
+            let ids = self.client_ids();
+            tiled.sort_by_key(|c| {
+                ids.iter()
+                    .position(|&id| id == c.id())
+                    .unwrap_or(usize::MAX)
+            });
+
             debug!(
                 layout = ?layout.symbol,
                 n_clients = tiled.len(),
@@ -271,7 +409,7 @@ impl Workspace {
             );
 
             ArrangeActions {
-                actions: layout.arrange(&tiled, self.focused_client(), &screen_region),
+                actions: layout.arrange(&tiled, self.focused_client(), &screen_region, spacing),
                 floating: floating.iter().map(|c| c.id()).collect(),
             }
         } else {
@@ -359,6 +497,54 @@ impl Workspace {
         self.layouts.focused_unchecked().conf
     }
 
+    /// The maximum number of clients in the main area of the current Layout.
+    ///
+    /// Each Layout on a workspace tracks its own main area state so changes made while one
+    /// layout is active are retained when cycling to another layout and back again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// workspace.update_max_main(More);
+    /// assert_eq!(workspace.max_main(), 2);
+    ///
+    /// workspace.cycle_layout(Forward);
+    /// assert_eq!(workspace.max_main(), 1);
+    ///
+    /// workspace.cycle_layout(Forward);
+    /// assert_eq!(workspace.max_main(), 2);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 2)).unwrap();
+    /// ```
+    pub fn max_main(&self) -> u32 {
+        self.layouts.focused_unchecked().max_main()
+    }
+
+    /// The proportion of the screen taken up by the main area of the current Layout.
+    ///
+    /// As with [max_main][Workspace::max_main], this is tracked separately for each Layout.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// workspace.update_main_ratio(More, 0.1);
+    /// assert!((workspace.main_ratio() - 0.7).abs() < f32::EPSILON);
+    ///
+    /// workspace.cycle_layout(Forward);
+    /// assert!((workspace.main_ratio() - 0.6).abs() < f32::EPSILON);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 2)).unwrap();
+    /// ```
+    pub fn main_ratio(&self) -> f32 {
+        self.layouts.focused_unchecked().ratio()
+    }
+
     /// Cycle focus through the clients on this workspace, returning the previous and new focused
     /// client ids.
     ///
@@ -418,6 +604,129 @@ impl Workspace {
         self.clients.drag_focused(direction).copied()
     }
 
+    /// Swap the focused client with its neighbour in the given direction, retaining focus.
+    ///
+    /// Unlike [drag_client][Workspace::drag_client], swapping past either end of the stack
+    /// exchanges the first and last clients rather than rotating the whole stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert_eq!(workspace.client_ids(), vec![0, 1, 2]);
+    ///
+    /// assert_eq!(workspace.swap_client(Backward), Some(0));
+    /// assert_eq!(workspace.client_ids(), vec![2, 1, 0]);
+    /// assert_eq!(workspace.focused_client(), Some(0));
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 3)).unwrap();
+    /// ```
+    pub fn swap_client(&mut self, direction: Direction) -> Option<Xid> {
+        if !self.layout_conf().allow_wrapping && self.clients.would_wrap(direction) {
+            return None;
+        }
+        self.clients.swap_focused(direction).copied()
+    }
+
+    /// Swap the positions of two clients in the stack, leaving focus on the same position.
+    ///
+    /// Returns false (leaving the stack unchanged) if either client is not on this workspace.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert_eq!(workspace.client_ids(), vec![0, 1, 2, 3]);
+    ///
+    /// assert!(workspace.swap_clients(0, 2));
+    /// assert_eq!(workspace.client_ids(), vec![2, 1, 0, 3]);
+    ///
+    /// assert!(!workspace.swap_clients(1, 42));
+    /// assert_eq!(workspace.client_ids(), vec![2, 1, 0, 3]);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn swap_clients(&mut self, a: Xid, b: Xid) -> bool {
+        let ix_a = self.clients.index(&Selector::Condition(&|c| *c == a));
+        let ix_b = self.clients.index(&Selector::Condition(&|c| *c == b));
+
+        match (ix_a, ix_b) {
+            (Some(i), Some(j)) => {
+                self.clients.swap(i, j);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replace a client in the stack with a new one, keeping its position and focus.
+    ///
+    /// Returns false (leaving the stack unchanged) if 'old' is not on this workspace or 'new'
+    /// already is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// assert_eq!(workspace.client_ids(), vec![0, 1, 2]);
+    ///
+    /// assert!(workspace.replace_client(1, 42));
+    /// assert_eq!(workspace.client_ids(), vec![0, 42, 2]);
+    ///
+    /// assert!(!workspace.replace_client(1, 43));
+    /// assert!(!workspace.replace_client(0, 2));
+    /// assert_eq!(workspace.client_ids(), vec![0, 42, 2]);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 3)).unwrap();
+    /// ```
+    pub fn replace_client(&mut self, old: Xid, new: Xid) -> bool {
+        if self.clients.iter().any(|&c| c == new) {
+            return false;
+        }
+
+        match self
+            .clients
+            .element_mut(&Selector::Condition(&|c| *c == old))
+        {
+            Some(c) => {
+                *c = new;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the focused client to the head of the stack (the main area for most layouts). If
+    /// it is already at the head of the stack it is swapped with the next client instead.
+    ///
+    /// Returns the client that now holds focus at the head of the stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use penrose::__test_helpers::*;
+    /// # fn example(mut workspace: Workspace) -> Result<()> {
+    /// workspace.focus_client(2);
+    ///
+    /// assert_eq!(workspace.promote_focused(), Some(2));
+    /// assert_eq!(workspace.client_ids(), vec![2, 0, 1, 3]);
+    ///
+    /// assert_eq!(workspace.promote_focused(), Some(0));
+    /// assert_eq!(workspace.client_ids(), vec![0, 2, 1, 3]);
+    /// # Ok(())
+    /// # }
+    /// # example(test_workspace("example", 4)).unwrap();
+    /// ```
+    pub fn promote_focused(&mut self) -> Option<Xid> {
+        self.clients.promote_focused().copied()
+    }
+
     /// Rotate the client stack in the given direction
     ///
     /// # Example
@@ -461,7 +770,7 @@ impl Workspace {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::{layout::*, ring::Direction, xconnection::MockXConn};
+    use crate::core::{layout::*, ring::Direction};
 
     fn test_layouts() -> Vec<Layout> {
         vec![Layout::new("t", LayoutConf::default(), mock_layout, 1, 0.6)]
@@ -524,15 +833,15 @@ mod tests {
     #[test]
     fn applying_a_layout_gives_one_action_per_client() {
         let mut ws = Workspace::new("test", test_layouts());
-        let conn = MockXConn::new(vec![], vec![], vec![]);
         ws.clients = Ring::new(vec![1, 2, 3]);
         let clients = vec![
-            Client::new(&conn, 1, 0, &[]),
-            Client::new(&conn, 2, 0, &[]),
-            Client::new(&conn, 3, 0, &[]),
+            Client::detached(1, 0),
+            Client::detached(2, 0),
+            Client::detached(3, 0),
         ];
         let refs: Vec<&Client> = clients.iter().collect();
-        let res = ws.arrange(Region::new(0, 0, 2000, 1000), &refs[..]);
+        let spacing = Spacing::default();
+        let res = ws.arrange(Region::new(0, 0, 2000, 1000), &spacing, &refs[..]);
         assert_eq!(res.actions.len(), 3, "actions are not 1-1 for clients")
     }
 
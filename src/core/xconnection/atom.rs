@@ -53,6 +53,9 @@ pub enum Atom {
     /// WM_TAKE_FOCUS
     #[strum(serialize = "WM_TAKE_FOCUS")]
     WmTakeFocus,
+    /// WM_WINDOW_ROLE
+    #[strum(serialize = "WM_WINDOW_ROLE")]
+    WmWindowRole,
     /// _NET_ACTIVE_WINDOW
     #[strum(serialize = "_NET_ACTIVE_WINDOW")]
     NetActiveWindow,
@@ -92,24 +95,66 @@ pub enum Atom {
     /// _NET_WM_DESKTOP
     #[strum(serialize = "_NET_WM_DESKTOP")]
     NetWmDesktop,
+    /// _NET_WM_BYPASS_COMPOSITOR
+    #[strum(serialize = "_NET_WM_BYPASS_COMPOSITOR")]
+    NetWmBypassCompositor,
     /// _NET_WM_NAME
     #[strum(serialize = "_NET_WM_NAME")]
     NetWmName,
+    /// _NET_WM_PID
+    #[strum(serialize = "_NET_WM_PID")]
+    NetWmPid,
     /// _NET_WM_STATE
     #[strum(serialize = "_NET_WM_STATE")]
     NetWmState,
+    /// _NET_WM_STATE_DEMANDS_ATTENTION
+    #[strum(serialize = "_NET_WM_STATE_DEMANDS_ATTENTION")]
+    NetWmStateDemandsAttention,
     /// _NET_WM_STATE_FULLSCREEN
     #[strum(serialize = "_NET_WM_STATE_FULLSCREEN")]
     NetWmStateFullscreen,
+    /// _NET_WM_STATE_MAXIMIZED_HORZ
+    #[strum(serialize = "_NET_WM_STATE_MAXIMIZED_HORZ")]
+    NetWmStateMaximizedHorz,
+    /// _NET_WM_STATE_MAXIMIZED_VERT
+    #[strum(serialize = "_NET_WM_STATE_MAXIMIZED_VERT")]
+    NetWmStateMaximizedVert,
+    /// _NET_WM_STATE_STICKY
+    #[strum(serialize = "_NET_WM_STATE_STICKY")]
+    NetWmStateSticky,
+    /// _NET_WM_STRUT
+    #[strum(serialize = "_NET_WM_STRUT")]
+    NetWmStrut,
+    /// _NET_WM_STRUT_PARTIAL
+    #[strum(serialize = "_NET_WM_STRUT_PARTIAL")]
+    NetWmStrutPartial,
     /// _NET_WM_WINDOW_TYPE
     #[strum(serialize = "_NET_WM_WINDOW_TYPE")]
     NetWmWindowType,
+    /// _NET_WM_WINDOW_OPACITY
+    #[strum(serialize = "_NET_WM_WINDOW_OPACITY")]
+    NetWmWindowOpacity,
     /// _XEMBED
     #[strum(serialize = "_XEMBED")]
     XEmbed,
     /// _XEMBED_INFO
     #[strum(serialize = "_XEMBED_INFO")]
     XEmbedInfo,
+    /// _PENROSE_DBUS_CALL
+    #[strum(serialize = "_PENROSE_DBUS_CALL")]
+    PenroseDbusCall,
+    /// _PENROSE_GESTURE
+    #[strum(serialize = "_PENROSE_GESTURE")]
+    PenroseGesture,
+    /// _PENROSE_MOVE_WINDOW
+    #[strum(serialize = "_PENROSE_MOVE_WINDOW")]
+    PenroseMoveWindow,
+    /// _PENROSE_POWER_EVENT
+    #[strum(serialize = "_PENROSE_POWER_EVENT")]
+    PenrosePowerEvent,
+    /// _PENROSE_TILE_WINDOW
+    #[strum(serialize = "_PENROSE_TILE_WINDOW")]
+    PenroseTileWindow,
 
     // Window Types
     /// _NET_WM_WINDOW_TYPE_DESKTOP
@@ -191,6 +236,12 @@ pub const EWMH_SUPPORTED_ATOMS: &[Atom] = &[
     // Atom::NetSystemTrayOrientationHorz,
     Atom::NetWmName,
     Atom::NetWmState,
+    Atom::NetWmStateDemandsAttention,
     Atom::NetWmStateFullscreen,
+    Atom::NetWmStateMaximizedHorz,
+    Atom::NetWmStateMaximizedVert,
+    Atom::NetWmStateSticky,
+    Atom::NetWmStrut,
+    Atom::NetWmStrutPartial,
     Atom::NetWmWindowType,
 ];
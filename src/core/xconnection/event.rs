@@ -2,6 +2,7 @@
 use crate::core::{
     bindings::{KeyCode, MouseEvent},
     data_types::{Point, Region},
+    input::LockState,
     xconnection::{Atom, Result, XAtomQuerier, Xid},
 };
 
@@ -29,10 +30,18 @@ pub enum XEvent {
     FocusIn(Xid),
     /// A client window has been closed
     Destroy(Xid),
+    /// An input device (keyboard, mouse etc) has been added, removed, enabled or disabled
+    InputDevicesChanged,
     /// A grabbed key combination has been entered by the user
     KeyPress(KeyCode),
+    /// A key has been released while the keyboard is grabbed
+    KeyRelease(KeyCode),
+    /// The keyboard mapping has changed (e.g. after running `setxkbmap`)
+    KeyboardMappingChanged,
     /// The mouse pointer has left the current client window
     Leave(PointerChange),
+    /// The Caps Lock or Num Lock state of the keyboard has changed
+    LockStateChanged(LockState),
     /// A client window is requesting to be positioned and rendered on the screen.
     MapRequest(Xid, bool),
     /// The mouse has moved or a mouse button has been pressed
@@ -57,8 +66,12 @@ impl std::fmt::Display for XEvent {
             XEvent::Expose(_) => write!(f, "Expose"),
             XEvent::FocusIn(_) => write!(f, "FocusIn"),
             XEvent::Destroy(_) => write!(f, "Destroy"),
+            XEvent::InputDevicesChanged => write!(f, "InputDevicesChanged"),
             XEvent::KeyPress(_) => write!(f, "KeyPress"),
+            XEvent::KeyRelease(_) => write!(f, "KeyRelease"),
+            XEvent::KeyboardMappingChanged => write!(f, "KeyboardMappingChanged"),
             XEvent::Leave(_) => write!(f, "Leave"),
+            XEvent::LockStateChanged(_) => write!(f, "LockStateChanged"),
             XEvent::MapRequest(_, _) => write!(f, "MapRequest"),
             XEvent::MouseEvent(_) => write!(f, "MouseEvent"),
             XEvent::PropertyNotify(_) => write!(f, "PropertyNotify"),
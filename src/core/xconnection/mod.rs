@@ -9,16 +9,22 @@
 //! [1]: crate::core::manager::WindowManager
 use crate::{
     core::{
-        bindings::{KeyBindings, KeyPress, MouseBindings},
+        bindings::{KeyBindings, KeyCodeValue, KeyPress, MouseBindings},
         client::Client,
         data_types::{Point, Region},
-        screen::Screen,
+        input::{InputDevice, LockState, PointerConfig},
+        screen::{Screen, Strut},
     },
     draw::Color,
 };
 
 use penrose_proc::stubbed_companion_trait;
 
+use std::{collections::HashMap, time::Duration};
+
+#[cfg(any(feature = "xcb", feature = "x11rb"))]
+use std::os::unix::io::RawFd;
+
 pub mod atom;
 pub mod event;
 pub mod property;
@@ -31,13 +37,69 @@ pub use event::{
     ExposeEvent, PointerChange, PropertyEvent, XEvent,
 };
 pub use property::{
-    MapState, Prop, WindowAttributes, WindowClass, WindowState, WmHints, WmNormalHints,
-    WmNormalHintsFlags,
+    MapState, Prop, WindowAttributes, WindowClass, WindowState, WmHints, WmHintsFlags,
+    WmNormalHints, WmNormalHintsFlags,
 };
 
 /// An X resource ID
 pub type Xid = u32;
 
+// _NET_WM_DESKTOP value for clients that are shown on all desktops
+const ALL_DESKTOPS: u32 = 0xFFFFFFFF;
+
+// Block until there is data to read from the X server connection 'fd' or 'timeout' passes,
+// returning whether or not there is data available.
+#[cfg(any(feature = "xcb", feature = "x11rb"))]
+pub(crate) fn wait_until_readable(fd: RawFd, timeout: Duration) -> Result<bool> {
+    use nix::{
+        errno::Errno,
+        poll::{poll, PollFd, PollFlags},
+    };
+
+    // Round up so that we never return early and end up spinning on a sub-millisecond timeout
+    let millis = timeout.as_micros().div_ceil(1000);
+    let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+
+    match poll(&mut fds, millis.min(i32::MAX as u128) as i32) {
+        Ok(n) => Ok(n > 0),
+        Err(e) if e.as_errno() == Some(Errno::EINTR) => Ok(false),
+        Err(e) => Err(XError::Raw(format!(
+            "unable to poll the X server connection: {}",
+            e
+        ))),
+    }
+}
+
+// The regions of a border pixmap for a 'w' x 'h' window with a 'border_px' border that need to be
+// filled with the inner color of a two-tone border, the rest of the pixmap being the outer color.
+//
+// X tiles the border pixmap from the origin of the window itself so pixmaps are (w + 2b) x (h + 2b)
+// with the right and bottom borders directly after the window contents and the left and top
+// borders wrapping around to the end of the pixmap.
+#[cfg(any(feature = "xcb", feature = "x11rb", test))]
+pub(crate) fn inner_border_regions(w: u32, h: u32, border_px: u32, inner_px: u32) -> Vec<Region> {
+    let i = inner_px.min(border_px);
+    if i == 0 {
+        return vec![];
+    }
+
+    let (pw, ph) = (w + 2 * border_px, h + 2 * border_px);
+    let rows = [(0, h + i), (ph - i, i)];
+    let cols = [(0, w + i), (pw - i, i)];
+    let mut regions = Vec::with_capacity(8);
+
+    for &(y, rh) in rows.iter() {
+        regions.push(Region::new(w, y, i, rh));
+        regions.push(Region::new(pw - i, y, i, rh));
+    }
+    for &(x, rw) in cols.iter() {
+        regions.push(Region::new(x, h, rw, i));
+        regions.push(Region::new(x, ph - i, rw, i));
+    }
+
+    regions
+}
+
 const WM_NAME: &str = "penrose";
 
 /// Enum to store the various ways that operations can fail in X traits
@@ -90,6 +152,13 @@ pub enum XError {
     #[cfg(feature = "x11rb")]
     #[error(transparent)]
     X11rb(#[from] crate::x11rb::X11rbError),
+
+    /// Something went wrong using the [wayland][crate::wayland] module.
+    ///
+    /// See [WaylandError][crate::wayland::WaylandError] for variants.
+    #[cfg(feature = "wayland")]
+    #[error(transparent)]
+    Wayland(#[from] crate::wayland::WaylandError),
 }
 
 /// Result type for errors raised by X traits
@@ -113,10 +182,25 @@ pub enum ClientConfig {
 pub enum ClientAttr {
     /// Border color as an argb hex value
     BorderColor(u32),
+    /// Background color as an argb hex value
+    BackgroundColor(u32),
     /// Set the pre-defined client event mask
     ClientEventMask,
+    /// Set the pre-defined client event mask without enter and leave events
+    ClientEventMaskNoCrossing,
     /// Set the pre-defined root event mask
     RootEventMask,
+    /// Set the pre-defined root event mask along with button presses and releases on the root
+    /// window.
+    ///
+    /// Only one X client can select button presses on a given window so this is opt-in, allowing
+    /// other programs (such as a desktop manager) to handle clicks on the root window instead.
+    RootEventMaskWithClicks,
+    /// Select button presses and releases on a window created by penrose itself (such as the
+    /// window of a status bar or tab bar) so that clicks on it are passed to the
+    /// [window_clicked][crate::core::hooks::Hook::window_clicked] and
+    /// [window_released][crate::core::hooks::Hook::window_released] hooks.
+    ButtonEventMask,
 }
 
 /// An [XEvent] parsed into a [KeyPress] if possible, otherwise the original `XEvent`
@@ -155,6 +239,12 @@ pub trait XState: XAtomQuerier {
     #[stub(Ok(Point::default()))]
     fn cursor_position(&self) -> Result<Point>;
 
+    /// The modifier keys that are currently held down, as a [KeyCode][1] style mask
+    ///
+    /// [1]: crate::core::bindings::KeyCode
+    #[stub(Ok(0))]
+    fn modifier_state(&self) -> Result<u16>;
+
     /// Warp the cursor to be within the specified window. If id == None then behaviour is
     /// definined by the implementor (e.g. warp cursor to active window, warp to center of screen)
     #[stub(Ok(()))]
@@ -184,6 +274,16 @@ pub trait XEventHandler {
     #[stub(Err(XError::Raw("mocked".into())))]
     fn wait_for_event(&self) -> Result<XEvent>;
 
+    /// Return the next event from the X server as an [XEvent] if there is one available, without
+    /// blocking
+    #[stub(Ok(None))]
+    fn poll_for_event(&self) -> Result<Option<XEvent>>;
+
+    /// Wait for up to 'timeout' for the next event from the X server, returning None if no event
+    /// arrived in time.
+    #[stub(Ok(None))]
+    fn wait_for_event_timeout(&self, timeout: Duration) -> Result<Option<XEvent>>;
+
     /// Send an X event to the target client
     ///
     /// The `msg` being sent can be composed by hand or, for known common message types, generated
@@ -217,6 +317,13 @@ pub trait XClientHandler {
     #[stub(Ok(()))]
     fn kill_client(&self, id: Xid) -> Result<()>;
 
+    /// Create a new unmanaged, input only window that is sent pointer enter and leave events
+    /// along with button presses and releases.
+    ///
+    /// The window is not mapped.
+    #[stub(Err(XError::Raw("mocked".into())))]
+    fn create_input_window(&self, r: Region) -> Result<Xid>;
+
     /// Mark the given client as having focus
     #[stub(Ok(()))]
     fn focus_client(&self, id: Xid) -> Result<()>;
@@ -301,13 +408,18 @@ pub trait XClientProperties {
         }
     }
 
-    /// Toggle the fullscreen state of the given client ID with the X server
+    /// Toggle the fullscreen state of the given client ID with the X server, leaving any other
+    /// states in _NET_WM_STATE as they are
     fn toggle_client_fullscreen(&self, id: Xid, client_is_fullscreen: bool) -> Result<()> {
-        let data = if client_is_fullscreen {
-            vec![]
-        } else {
-            vec![Atom::NetWmStateFullscreen.as_ref().to_string()]
+        let fullscreen = Atom::NetWmStateFullscreen.as_ref();
+        let mut data = match self.get_prop(id, Atom::NetWmState.as_ref()) {
+            Ok(Prop::Atom(states)) => states,
+            _ => vec![],
         };
+        data.retain(|s| s != fullscreen);
+        if !client_is_fullscreen {
+            data.push(fullscreen.to_string());
+        }
 
         self.change_prop(id, Atom::NetWmState.as_ref(), Prop::Atom(data))
     }
@@ -349,6 +461,90 @@ pub trait XClientProperties {
             false
         }
     }
+
+    /// Fetch the ICCCM hints for a client, if it has set any
+    fn client_hints(&self, id: Xid) -> Option<WmHints> {
+        match self.get_prop(id, Atom::WmHints.as_ref()) {
+            Ok(Prop::WmHints(hints)) => Some(hints),
+            _ => None,
+        }
+    }
+
+    /// Fetch the names of the protocols that a client supports
+    fn client_protocols(&self, id: Xid) -> Vec<String> {
+        match self.get_prop(id, Atom::WmProtocols.as_ref()) {
+            Ok(Prop::Atom(protocols)) => protocols,
+            _ => vec![],
+        }
+    }
+
+    /// Check to see if a client can be asked to close itself rather than being killed
+    fn client_accepts_close_request(&self, id: Xid) -> Result<bool> {
+        self.client_supports_protocol(id, Atom::WmDeleteWindow.as_ref())
+    }
+
+    /// Check to see if a client wants to be told when it is given focus so that it can decide
+    /// which of its windows should take it
+    fn client_takes_focus(&self, id: Xid) -> bool {
+        let supported = self.client_supports_protocol(id, Atom::WmTakeFocus.as_ref());
+        supported.unwrap_or(false)
+    }
+
+    /// Check to see if the given EWMH state is currently set for a client
+    fn client_has_state(&self, id: Xid, state: Atom) -> bool {
+        let state = state.as_ref();
+        match self.get_prop(id, Atom::NetWmState.as_ref()) {
+            Ok(Prop::Atom(states)) => states.iter().any(|s| s == state),
+            _ => false,
+        }
+    }
+
+    /// Check to see if a client has asked to be shown on all workspaces
+    fn client_requests_sticky(&self, id: Xid) -> bool {
+        self.client_has_state(id, Atom::NetWmStateSticky)
+    }
+
+    /// Check to see if a client has asked to be maximized horizontally and / or vertically
+    fn client_requests_maximized(&self, id: Xid) -> (bool, bool) {
+        let horz = self.client_has_state(id, Atom::NetWmStateMaximizedHorz);
+        let vert = self.client_has_state(id, Atom::NetWmStateMaximizedVert);
+
+        (horz, vert)
+    }
+
+    /// Check to see if a client has asked for the compositor to be bypassed while it is shown.
+    ///
+    /// Clients doing this are almost always games or video players that want to be shown without
+    /// any decoration.
+    fn client_requests_compositor_bypass(&self, id: Xid) -> bool {
+        match self.get_prop(id, Atom::NetWmBypassCompositor.as_ref()) {
+            Ok(Prop::Cardinal(bypass)) => bypass == 1,
+            _ => false,
+        }
+    }
+
+    /// The space that a dock client has reserved at the edges of the root window, if any.
+    ///
+    /// `_NET_WM_STRUT_PARTIAL` takes precedence over `_NET_WM_STRUT` if both are set.
+    fn client_strut(&self, id: Xid) -> Option<Strut> {
+        for atom in &[Atom::NetWmStrutPartial, Atom::NetWmStrut] {
+            if let Ok(Prop::CardinalList(raw)) = self.get_prop(id, atom.as_ref()) {
+                if let Some(strut) = Strut::from_raw(&raw) {
+                    return Some(strut);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Update the fullscreen, maximized, sticky and urgency state of a client with the X server.
+    ///
+    /// All of them are written together so that updating one does not clear the others.
+    fn update_client_states(&self, c: &Client) -> Result<()> {
+        let data = Prop::Atom(c.net_wm_state());
+        self.change_prop(c.id(), Atom::NetWmState.as_ref(), data)
+    }
 }
 
 /// Modifying X client config and attributes
@@ -387,10 +583,47 @@ pub trait XClientConfig {
         self.configure_client(id, &[ClientConfig::StackAbove])
     }
 
+    /// Set the same attributes on each of the given clients.
+    ///
+    /// Implementations should send all of the requests before waiting on any replies so that
+    /// this costs a single round trip to the X server.
+    fn set_clients_attributes(&self, ids: &[Xid], data: &[ClientAttr]) -> Result<()> {
+        for &id in ids {
+            self.set_client_attributes(id, data)?;
+        }
+
+        Ok(())
+    }
+
     /// Change the border color for the given client
     fn set_client_border_color(&self, id: Xid, color: Color) -> Result<()> {
         self.set_client_attributes(id, &[ClientAttr::BorderColor(color.rgb_u32())])
     }
+
+    /// Set the background color of the given window.
+    ///
+    /// Implementations should repaint the window so that the new background is shown immediately
+    /// rather than the next time that the window is exposed.
+    fn set_window_background(&self, id: Xid, color: Color) -> Result<()> {
+        self.set_client_attributes(id, &[ClientAttr::BackgroundColor(color.rgb_u32())])
+    }
+
+    /// Draw the border of the given client using two colors: an `inner` line `inner_px` wide
+    /// running directly alongside the client window with the `outer` color filling the rest of
+    /// the border.
+    ///
+    /// The border needs to be redrawn each time that the client is resized. Implementations that
+    /// are unable to draw two-tone borders should fall back to a solid `outer` border.
+    fn set_client_border_colors(
+        &self,
+        id: Xid,
+        outer: Color,
+        inner: Color,
+        inner_px: u32,
+    ) -> Result<()> {
+        let _ = (inner, inner_px);
+        self.set_client_border_color(id, outer)
+    }
 }
 
 /// Keyboard input for created clients
@@ -467,6 +700,57 @@ pub trait XConn:
         mouse_bindings: &MouseBindings<Self>,
     ) -> Result<()>;
 
+    /// Release all key and mouse grabs so that the corresponding events are passed through to the
+    /// underlying applications.
+    #[stub(Ok(()))]
+    fn ungrab_keys(&self) -> Result<()>;
+
+    /// Grab all keyboard input so that every key press and release is sent through to the
+    /// WindowManager rather than only those for grabbed key bindings.
+    #[stub(Ok(()))]
+    fn grab_keyboard(&self) -> Result<()>;
+
+    /// Release a grab made using [grab_keyboard][XConn::grab_keyboard]
+    #[stub(Ok(()))]
+    fn ungrab_keyboard(&self) -> Result<()>;
+
+    /// Grab the pointer, showing a crosshair cursor, so that the next button press anywhere on
+    /// the screen is sent through to the WindowManager rather than to the client under it.
+    #[stub(Ok(()))]
+    fn grab_pointer(&self) -> Result<()>;
+
+    /// Release a grab made using [grab_pointer][XConn::grab_pointer]
+    #[stub(Ok(()))]
+    fn ungrab_pointer(&self) -> Result<()>;
+
+    /// Refresh any cached mapping between key codes and key names after the keyboard mapping has
+    /// changed.
+    #[stub(Ok(()))]
+    fn refresh_keymap(&self) -> Result<()>;
+
+    /// The key code currently holding each key name.
+    ///
+    /// This is used to move key bindings to the keys holding the key names they were bound to
+    /// after the keyboard mapping has changed.
+    #[stub(Ok(HashMap::new()))]
+    fn keycodes(&self) -> Result<HashMap<String, KeyCodeValue>>;
+
+    /// The pointer devices currently attached to the X server
+    #[stub(Ok(vec![]))]
+    fn pointer_devices(&self) -> Result<Vec<InputDevice>>;
+
+    /// The keyboard devices currently attached to the X server
+    #[stub(Ok(vec![]))]
+    fn keyboard_devices(&self) -> Result<Vec<InputDevice>>;
+
+    /// Apply the settings in a [PointerConfig] to the given pointer device
+    #[stub(Ok(()))]
+    fn configure_pointer_device(&self, device: &InputDevice, conf: &PointerConfig) -> Result<()>;
+
+    /// The current state of the Caps Lock and Num Lock keyboard indicators
+    #[stub(Ok(LockState::default()))]
+    fn lock_state(&self) -> Result<LockState>;
+
     /*
      *  The following default implementations should used if possible.
      *
@@ -566,6 +850,40 @@ pub trait XConn:
         self.change_prop(id, Atom::NetWmDesktop.as_ref(), Prop::Cardinal(wix as u32))
     }
 
+    /// Mark a client as being shown on all desktops
+    fn set_client_on_all_workspaces(&self, id: Xid) -> Result<()> {
+        self.change_prop(
+            id,
+            Atom::NetWmDesktop.as_ref(),
+            Prop::Cardinal(ALL_DESKTOPS),
+        )
+    }
+
+    /// Update which client is currently active, clearing it if `id` is None
+    fn set_active_client(&self, id: Option<Xid>) -> Result<()> {
+        let active_window = Atom::NetActiveWindow.as_ref();
+        match id {
+            Some(id) => self.change_prop(self.root(), active_window, Prop::Window(vec![id])),
+            None => self.delete_prop(self.root(), active_window),
+        }
+    }
+
+    /// Let a client that [takes focus][1] know that it has been given focus
+    ///
+    /// [1]: XClientProperties::client_takes_focus
+    fn send_take_focus(&self, id: Xid) -> Result<()> {
+        let msg = ClientMessageKind::TakeFocus(id).as_message(self)?;
+        self.send_client_event(msg)
+    }
+
+    /// Ask a client that [accepts close requests][1] to close itself
+    ///
+    /// [1]: XClientProperties::client_accepts_close_request
+    fn request_client_close(&self, id: Xid) -> Result<()> {
+        let msg = ClientMessageKind::DeleteWindow(id).as_message(self)?;
+        self.send_client_event(msg)
+    }
+
     /// Check to see if this client is one that we should be handling or not
     #[tracing::instrument(level = "trace", skip(self))]
     fn is_managed_client(&self, c: &Client) -> bool {
@@ -606,14 +924,60 @@ pub trait XConn:
     }
 }
 
-#[cfg(test)]
 pub use mock_conn::MockXConn;
 
-#[cfg(test)]
 mod mock_conn {
     use super::*;
     use std::{cell::Cell, fmt};
 
+    /// A stub [XConn] for tests that replays a fixed set of [XEvent]s against known [Screen]s
+    ///
+    /// No X server is needed to use a MockXConn so it can be used to unit test [Hook][1]s, key
+    /// bindings and other logic that drives a [WindowManager][2]. Each call to
+    /// [wait_for_event][XEventHandler::wait_for_event] returns the next of the events that it was
+    /// created with, after which the connection reports that it has been closed. All other X server
+    /// interactions are stubbed out.
+    ///
+    /// ```
+    /// use penrose::{
+    ///     core::{
+    ///         bindings::{KeyBindings, KeyCode},
+    ///         config::Config,
+    ///         data_types::Region,
+    ///         manager::WindowManager,
+    ///         screen::Screen,
+    ///         xconnection::{MockXConn, XEvent},
+    ///     },
+    ///     logging_error_handler,
+    /// };
+    /// use std::collections::HashMap;
+    ///
+    /// # fn example() -> penrose::Result<()> {
+    /// // The event loop runs until something calls exit so bind it to a key and press it last
+    /// let exit = KeyCode { mask: 0, code: 0 };
+    /// let mut key_bindings: KeyBindings<MockXConn> = HashMap::new();
+    /// key_bindings.insert(exit, Box::new(|wm| wm.exit()));
+    ///
+    /// let screens = vec![Screen::new(Region::new(0, 0, 1920, 1080), 0)];
+    /// let events = vec![
+    ///     XEvent::MapRequest(1, false),
+    ///     XEvent::MapRequest(2, false),
+    ///     XEvent::KeyPress(exit),
+    /// ];
+    /// let conn = MockXConn::new(screens, events, vec![]);
+    ///
+    /// let mut wm = WindowManager::new(Config::default(), conn, vec![], logging_error_handler());
+    /// wm.init()?;
+    /// wm.grab_keys_and_run(key_bindings, HashMap::new())?;
+    ///
+    /// assert_eq!(wm.active_workspace().client_ids(), vec![2, 1]);
+    /// # Ok(())
+    /// # }
+    /// # example().unwrap();
+    /// ```
+    ///
+    /// [1]: crate::core::hooks::Hook
+    /// [2]: crate::core::manager::WindowManager
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct MockXConn {
         screens: Vec<Screen>,
@@ -711,6 +1075,7 @@ mod mock_conn {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::__test_helpers::ConfigurableXConn;
 
     use std::str::FromStr;
 
@@ -754,4 +1119,70 @@ mod tests {
             assert_eq!(&conn.client_name(42).unwrap(), expected);
         }
     }
+
+    // Which color each pixel of the border pixmap ends up after tiling it from the window
+    // origin: 'o'uter, 'i'nner or '.' for the window itself
+    fn rendered_border(w: u32, h: u32, b: u32, i: u32) -> Vec<String> {
+        let (pw, ph) = (w + 2 * b, h + 2 * b);
+        let regions = inner_border_regions(w, h, b, i);
+
+        (0..ph)
+            .map(|sy| {
+                (0..pw)
+                    .map(|sx| {
+                        // screen coordinates are relative to the outer corner of the border
+                        let (x, y) = (sx as i32 - b as i32, sy as i32 - b as i32);
+                        if x >= 0 && y >= 0 && x < w as i32 && y < h as i32 {
+                            return '.';
+                        }
+                        let p = Point::new(
+                            x.rem_euclid(pw as i32) as u32,
+                            y.rem_euclid(ph as i32) as u32,
+                        );
+                        if regions.iter().any(|r| r.contains_point(&p)) {
+                            'i'
+                        } else {
+                            'o'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    test_cases! {
+        inner_border_regions;
+        args: (b: u32, i: u32, expected: &[&str]);
+
+        case: no_inner => (2, 0, &["oooooo", "oooooo", "oo..oo", "oooooo", "oooooo"]);
+        case: one_px_inner => (2, 1, &["oooooo", "oiiiio", "oi..io", "oiiiio", "oooooo"]);
+        case: inner_clamped_to_border => (1, 3, &["iiii", "i..i", "iiii"]);
+
+        body: {
+            let rendered = rendered_border(2, 1, b, i);
+            assert_eq!(rendered, expected);
+        }
+    }
+
+    test_cases! {
+        client_strut;
+        args: (partial: Option<Vec<u32>>, full: Option<Vec<u32>>, expected_top: Option<u32>);
+
+        case: neither => (None, None, None);
+        case: full_only => (None, Some(vec![0, 0, 20, 0]), Some(20));
+        case: partial_preferred => (Some(vec![0, 0, 30, 0]), Some(vec![0, 0, 20, 0]), Some(30));
+        case: empty_partial_ignored => (Some(vec![]), Some(vec![0, 0, 20, 0]), Some(20));
+
+        body: {
+            let mut conn = ConfigurableXConn::default();
+            if let Some(raw) = partial {
+                conn = conn.with_prop(1, Atom::NetWmStrutPartial, Prop::CardinalList(raw));
+            }
+            if let Some(raw) = full {
+                conn = conn.with_prop(1, Atom::NetWmStrut, Prop::CardinalList(raw));
+            }
+
+            assert_eq!(conn.client_strut(1).map(|s| s.top), expected_top);
+        }
+    }
 }
@@ -18,6 +18,8 @@ pub enum Prop {
     Bytes(Vec<u32>),
     /// A cardinal number
     Cardinal(u32),
+    /// A list of cardinal numbers (single values are returned as [Prop::Cardinal])
+    CardinalList(Vec<u32>),
     /// UTF-8 encoded string data
     UTF8String(Vec<String>),
     /// An X window IDs
@@ -189,7 +191,7 @@ impl WmHints {
             )));
         }
 
-        let flags = WmHintsFlags::from_bits(raw[0]).unwrap();
+        let flags = WmHintsFlags::from_bits_truncate(raw[0]);
         let accepts_input = !flags.contains(WmHintsFlags::INPUT_HINT) || raw[1] > 0;
         let initial_state = match (flags.contains(WmHintsFlags::STATE_HINT), raw[2]) {
             (true, 0) => WindowState::Withdrawn,
@@ -283,7 +285,7 @@ impl WmNormalHints {
             )));
         }
 
-        let flags = WmNormalHintsFlags::from_bits(raw[0]).unwrap();
+        let flags = WmNormalHintsFlags::from_bits_truncate(raw[0]);
 
         // These properties are marked as obsolete but some clients still set them
         // so it they are useful as fallbacks
@@ -0,0 +1,604 @@
+//! Support for freedesktop.org desktop entries and the XDG autostart directories
+//!
+//! Desktop environments start the programs described by the `.desktop` files found in the
+//! [XDG autostart][1] directories when a session begins. Enabling
+//! [xdg_autostart][crate::core::config::Config::xdg_autostart] has penrose do the same, using
+//! [autostart_entries] to find the programs that should be started.
+//!
+//! The installed applications that should be listed in menus are found using
+//! [application_entries], which is what [launch_desktop_entry_with_pmenu][2] shows.
+//!
+//! [1]: https://specifications.freedesktop.org/autostart-spec/latest/
+//! [2]: crate::contrib::actions::launch_desktop_entry_with_pmenu
+use crate::core::process::Spawn;
+
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// The desktop name that is matched against `OnlyShowIn` and `NotShowIn` if
+/// `XDG_CURRENT_DESKTOP` is not set.
+pub const DEFAULT_DESKTOP_NAME: &str = "Penrose";
+
+/// The terminal used to run applications with `Terminal=true` if `TERMINAL` is not set
+pub const DEFAULT_TERMINAL: &str = "xterm";
+
+/// The parts of a `.desktop` file that penrose makes use of.
+///
+/// Only the `[Desktop Entry]` group is read and localised keys (such as `Name[de]`) are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DesktopEntry {
+    /// The desktop file ID of the entry (such as `org.gnome.Nautilus.desktop`), which is empty
+    /// unless the entry was found in one of the XDG directories.
+    pub id: String,
+    /// The file that the entry was read from, if any
+    pub path: Option<PathBuf>,
+    /// The `Type` of the entry: only `Application` entries can be launched
+    pub entry_type: String,
+    /// The name of the application
+    pub name: String,
+    /// A generic description of the application, such as "Web Browser"
+    pub generic_name: Option<String>,
+    /// The name of the icon (or path to an icon file) for the application
+    pub icon: Option<String>,
+    /// The command line used to start the application
+    pub exec: Option<String>,
+    /// A program that must exist for the entry to be used
+    pub try_exec: Option<String>,
+    /// Whether the entry has been deleted by the user
+    pub hidden: bool,
+    /// Whether the entry should be left out of menus
+    pub no_display: bool,
+    /// Whether the application needs to run in a terminal
+    pub terminal: bool,
+    /// The only desktops that the entry should be used in (all if empty)
+    pub only_show_in: Vec<String>,
+    /// Desktops that the entry should not be used in
+    pub not_show_in: Vec<String>,
+    /// Additional search terms for the entry
+    pub keywords: Vec<String>,
+}
+
+impl DesktopEntry {
+    /// Parse the contents of a `.desktop` file, returning None if there is no
+    /// `[Desktop Entry]` group.
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut entry = Self::default();
+        let mut in_group = false;
+        let mut found = false;
+
+        for line in contents.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_group = line == "[Desktop Entry]";
+                found |= in_group;
+                continue;
+            }
+            if !in_group {
+                continue;
+            }
+
+            let (key, val) = match line.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => continue,
+            };
+            let list = || -> Vec<String> {
+                val.split(';')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string())
+                    .collect()
+            };
+
+            match key {
+                "Type" => entry.entry_type = val.to_string(),
+                "Name" => entry.name = val.to_string(),
+                "GenericName" => entry.generic_name = Some(val.to_string()),
+                "Icon" => entry.icon = Some(val.to_string()),
+                "Exec" => entry.exec = Some(val.to_string()),
+                "TryExec" => entry.try_exec = Some(val.to_string()),
+                "Hidden" => entry.hidden = val == "true",
+                "NoDisplay" => entry.no_display = val == "true",
+                "Terminal" => entry.terminal = val == "true",
+                "OnlyShowIn" => entry.only_show_in = list(),
+                "NotShowIn" => entry.not_show_in = list(),
+                "Keywords" => entry.keywords = list(),
+                _ => (),
+            }
+        }
+
+        if found {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Read and parse the desktop entry at 'path'
+    pub fn from_file(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let mut entry = Self::parse(&fs::read_to_string(path).ok()?)?;
+        entry.path = Some(path.to_path_buf());
+
+        Some(entry)
+    }
+
+    /// Whether or not this entry should be used when running under any of 'desktops', taking
+    /// into account its `Hidden`, `OnlyShowIn` and `NotShowIn` keys.
+    pub fn is_shown_in(&self, desktops: &[String]) -> bool {
+        let matches = |names: &[String]| names.iter().any(|n| desktops.contains(n));
+
+        !self.hidden
+            && (self.only_show_in.is_empty() || matches(&self.only_show_in))
+            && !matches(&self.not_show_in)
+    }
+
+    /// The program and arguments to run for this entry, or None if it is not an application
+    /// that can be launched.
+    ///
+    /// The `%i`, `%c` and `%k` field codes are expanded to the icon, name and location of the
+    /// entry. As there are never any files or URLs to open, `%f`, `%F`, `%u` and `%U` are
+    /// removed along with any deprecated field codes.
+    pub fn command(&self) -> Option<Vec<String>> {
+        if self.entry_type != "Application" {
+            return None;
+        }
+
+        let parts: Vec<String> = split_exec(self.exec.as_deref()?)
+            .into_iter()
+            .flat_map(|arg| self.expand_field_codes(&arg))
+            .collect();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts)
+        }
+    }
+
+    /// A [Spawn] for running this entry, or None if it is not an application that can be
+    /// launched.
+    ///
+    /// Applications that need a terminal are run using `$TERMINAL -e`, falling back to
+    /// [DEFAULT_TERMINAL] if `TERMINAL` is not set.
+    pub fn spawn(&self) -> Option<Spawn> {
+        let mut parts = self.command()?.into_iter();
+
+        if self.terminal {
+            let term = env::var("TERMINAL").unwrap_or_else(|_| DEFAULT_TERMINAL.to_string());
+            return Some(Spawn::new(term).arg("-e").args(parts));
+        }
+
+        let prog = parts.next()?;
+        Some(Spawn::new(prog).args(parts))
+    }
+
+    /// The text used for this entry in menus: its name followed by its generic name if it has
+    /// one that is different.
+    pub fn label(&self) -> String {
+        match self.generic_name {
+            Some(ref g) if !g.is_empty() && g != &self.name => format!("{} ({})", self.name, g),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+// Split an Exec value into its arguments, honouring double quotes and backslash escapes
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let (mut quoted, mut escaped, mut started) = (false, false, false);
+
+    for c in exec.chars() {
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started || !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+                started = false;
+            }
+            c => current.push(c),
+        }
+    }
+
+    if started || !current.is_empty() {
+        args.push(current);
+    }
+
+    args
+}
+
+impl DesktopEntry {
+    // Expand the field codes in a single argument. An argument that is only a field code with
+    // nothing to expand to is dropped completely and '%i' becomes two arguments.
+    fn expand_field_codes(&self, arg: &str) -> Vec<String> {
+        if arg == "%i" {
+            return match self.icon {
+                Some(ref icon) => vec!["--icon".to_string(), icon.clone()],
+                None => vec![],
+            };
+        }
+
+        let mut s = String::new();
+        let mut chars = arg.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                s.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => s.push('%'),
+                Some('c') => s.push_str(&self.name),
+                Some('k') => {
+                    if let Some(ref path) = self.path {
+                        s.push_str(&path.to_string_lossy());
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if s.is_empty() && !arg.is_empty() {
+            vec![]
+        } else {
+            vec![s]
+        }
+    }
+}
+
+/// The names of the current desktop from `XDG_CURRENT_DESKTOP`, falling back to
+/// [DEFAULT_DESKTOP_NAME] if it is not set.
+pub fn current_desktops() -> Vec<String> {
+    match env::var("XDG_CURRENT_DESKTOP") {
+        Ok(s) if !s.is_empty() => s.split(':').map(|d| d.to_string()).collect(),
+        _ => vec![DEFAULT_DESKTOP_NAME.to_string()],
+    }
+}
+
+// $XDG_CONFIG_HOME or $XDG_DATA_HOME, falling back to the given path relative to $HOME
+fn xdg_home(var: &str, default: &str) -> Option<PathBuf> {
+    match env::var(var) {
+        Ok(s) if !s.is_empty() => Some(PathBuf::from(s)),
+        _ => env::var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join(default)),
+    }
+}
+
+// $XDG_CONFIG_DIRS or $XDG_DATA_DIRS, falling back to the given default list of directories
+fn xdg_dirs(var: &str, default: &str) -> Vec<PathBuf> {
+    match env::var(var) {
+        Ok(s) if !s.is_empty() => s.split(':').map(PathBuf::from).collect(),
+        _ => default.split(':').map(PathBuf::from).collect(),
+    }
+}
+
+/// The base directory for user specific data files: `$XDG_DATA_HOME` or `~/.local/share` if that
+/// is not set.
+pub fn data_home() -> Option<PathBuf> {
+    xdg_home("XDG_DATA_HOME", ".local/share")
+}
+
+/// The XDG autostart directories in order of preference: `$XDG_CONFIG_HOME/autostart`
+/// followed by `autostart` in each of `$XDG_CONFIG_DIRS`.
+pub fn autostart_dirs() -> Vec<PathBuf> {
+    xdg_home("XDG_CONFIG_HOME", ".config")
+        .into_iter()
+        .chain(xdg_dirs("XDG_CONFIG_DIRS", "/etc/xdg"))
+        .map(|d| d.join("autostart"))
+        .collect()
+}
+
+/// The directories containing the desktop entries of installed applications in order of
+/// preference: `applications` in [data_home] followed by `applications` in each of
+/// `$XDG_DATA_DIRS`.
+pub fn application_dirs() -> Vec<PathBuf> {
+    data_home()
+        .into_iter()
+        .chain(xdg_dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share"))
+        .map(|d| d.join("applications"))
+        .collect()
+}
+
+// The desktop file IDs and paths of the '.desktop' files in 'dirs', with files in earlier
+// directories replacing those with the same ID in later ones. If 'recursive' is true then
+// subdirectories are searched as well, with their names becoming a prefix of the ID.
+fn desktop_files_in(dirs: &[PathBuf], recursive: bool) -> Vec<(String, PathBuf)> {
+    fn walk(dir: &Path, prefix: &str, recursive: bool, files: &mut Vec<(String, PathBuf)>) {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(iter) => iter.flatten().map(|e| e.path()).collect(),
+            Err(_) => return,
+        };
+        paths.sort();
+
+        for path in paths {
+            let name = match path.file_name() {
+                Some(name) => format!("{}{}", prefix, name.to_string_lossy()),
+                None => continue,
+            };
+            if path.is_dir() {
+                if recursive {
+                    walk(&path, &format!("{}-", name), recursive, files);
+                }
+            } else if matches!(path.extension(), Some(ext) if ext == "desktop") {
+                files.push((name, path));
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut files = vec![];
+
+    for dir in dirs {
+        let mut found = vec![];
+        walk(dir, "", recursive, &mut found);
+        files.extend(found.into_iter().filter(|(id, _)| seen.insert(id.clone())));
+    }
+
+    files
+}
+
+/// The entries in 'dirs' that should be started under any of 'desktops'.
+///
+/// When the same file name is found in more than one directory, only the one from the earliest
+/// directory is considered so that users are able to override or hide (using `Hidden=true`)
+/// entries installed by the system.
+pub fn autostart_entries_in(dirs: &[PathBuf], desktops: &[String]) -> Vec<DesktopEntry> {
+    entries_in(dirs, false, "autostart", |e| {
+        e.is_shown_in(desktops) && e.try_exec_found()
+    })
+}
+
+/// The entries in the XDG [autostart directories][autostart_dirs] that should be started for the
+/// [current desktop][current_desktops].
+pub fn autostart_entries() -> Vec<DesktopEntry> {
+    autostart_entries_in(&autostart_dirs(), &current_desktops())
+}
+
+/// The applications in 'dirs' that should be shown in menus under any of 'desktops'.
+///
+/// Entries are skipped if they can not be launched or are marked as `NoDisplay`, in addition to
+/// the keys checked by [is_shown_in][DesktopEntry::is_shown_in]. As with
+/// [autostart_entries_in], entries in earlier directories replace those with the same desktop
+/// file ID in later ones.
+pub fn application_entries_in(dirs: &[PathBuf], desktops: &[String]) -> Vec<DesktopEntry> {
+    entries_in(dirs, true, "application", |e| {
+        !e.no_display && e.is_shown_in(desktops) && e.try_exec_found() && e.command().is_some()
+    })
+}
+
+/// The applications in the XDG [application directories][application_dirs] that should be shown
+/// in menus for the [current desktop][current_desktops].
+pub fn application_entries() -> Vec<DesktopEntry> {
+    application_entries_in(&application_dirs(), &current_desktops())
+}
+
+fn entries_in<F>(dirs: &[PathBuf], recursive: bool, kind: &str, keep: F) -> Vec<DesktopEntry>
+where
+    F: Fn(&DesktopEntry) -> bool,
+{
+    desktop_files_in(dirs, recursive)
+        .into_iter()
+        .filter_map(|(id, path)| match DesktopEntry::from_file(&path) {
+            Some(entry) if keep(&entry) => Some(DesktopEntry { id, ..entry }),
+            Some(_) => {
+                debug!(?path, "skipping {} entry", kind);
+                None
+            }
+            None => {
+                warn!(?path, "unable to parse {} entry", kind);
+                None
+            }
+        })
+        .collect()
+}
+
+impl DesktopEntry {
+    // Entries with a TryExec are ignored if the program it names can not be found
+    fn try_exec_found(&self) -> bool {
+        let prog = match self.try_exec {
+            Some(ref prog) => prog,
+            None => return true,
+        };
+
+        if prog.contains('/') {
+            return Path::new(prog).exists();
+        }
+
+        env::var("PATH")
+            .unwrap_or_default()
+            .split(':')
+            .any(|dir| Path::new(dir).join(prog).exists())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY: &str = "\
+# a comment
+[Desktop Entry]
+Type=Application
+Name=Network Manager Applet
+Exec=nm-applet --indicator %U
+OnlyShowIn=GNOME;Penrose;
+NotShowIn=KDE;
+Keywords=network;wifi;
+
+[Desktop Action new-window]
+Name=Should be ignored
+";
+
+    fn desktops(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn desktop_entries_are_parsed() {
+        let entry = DesktopEntry::parse(ENTRY).unwrap();
+
+        assert_eq!(entry.name, "Network Manager Applet");
+        assert_eq!(entry.only_show_in, desktops(&["GNOME", "Penrose"]));
+        assert_eq!(entry.keywords, desktops(&["network", "wifi"]));
+        assert_eq!(
+            entry.command(),
+            Some(desktops(&["nm-applet", "--indicator"]))
+        );
+    }
+
+    #[test]
+    fn files_without_a_desktop_entry_group_are_rejected() {
+        assert_eq!(DesktopEntry::parse("[Something Else]\nName=foo"), None);
+    }
+
+    test_cases! {
+        is_shown_in;
+        args: (extra: &str, current: &[&str], expected: bool);
+
+        case: listed_in_only_show_in => ("", &["Penrose"], true);
+        case: not_listed_in_only_show_in => ("", &["XFCE"], false);
+        case: any_current_desktop_can_match => ("", &["XFCE", "GNOME"], true);
+        case: listed_in_not_show_in => ("", &["KDE", "GNOME"], false);
+        case: hidden => ("Hidden=true", &["Penrose"], false);
+
+        body: {
+            let entry = DesktopEntry::parse(&format!("{}{}\n", ENTRY.replace("[Desktop Action new-window]\nName=Should be ignored\n", ""), extra)).unwrap();
+            assert_eq!(entry.is_shown_in(&desktops(current)), expected);
+        }
+    }
+
+    test_cases! {
+        command;
+        args: (exec: &str, expected: Option<&[&str]>);
+
+        case: plain => ("foo --bar", Some(&["foo", "--bar"]));
+        case: field_codes_are_dropped => ("foo %f %U --bar", Some(&["foo", "--bar"]));
+        case: embedded_field_codes_are_removed => ("foo --file=%f", Some(&["foo", "--file="]));
+        case: escaped_percent => ("foo 100%%", Some(&["foo", "100%"]));
+        case: quoted_args => (r#""my prog" "a b" c"#, Some(&["my prog", "a b", "c"]));
+        case: empty_quoted_arg => (r#"foo "" bar"#, Some(&["foo", "", "bar"]));
+        case: only_field_codes => ("%U", None);
+
+        body: {
+            let entry = DesktopEntry {
+                entry_type: "Application".into(),
+                exec: Some(exec.into()),
+                ..Default::default()
+            };
+            assert_eq!(entry.command(), expected.map(desktops));
+        }
+    }
+
+    #[test]
+    fn entry_details_are_substituted_for_field_codes() {
+        let entry = DesktopEntry {
+            entry_type: "Application".into(),
+            name: "Foo".into(),
+            icon: Some("foo-icon".into()),
+            path: Some("/apps/foo.desktop".into()),
+            exec: Some("foo %i --title=%c %k %u".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            entry.command(),
+            Some(desktops(&[
+                "foo",
+                "--icon",
+                "foo-icon",
+                "--title=Foo",
+                "/apps/foo.desktop"
+            ]))
+        );
+    }
+
+    #[test]
+    fn only_applications_can_be_run() {
+        let entry = DesktopEntry {
+            entry_type: "Link".into(),
+            exec: Some("foo".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(entry.command(), None);
+    }
+
+    #[test]
+    fn earlier_autostart_dirs_override_later_ones() {
+        let root = env::temp_dir().join(format!("penrose-xdg-autostart-{}", std::process::id()));
+        let (user, system) = (root.join("user"), root.join("system"));
+        fs::create_dir_all(&user).unwrap();
+        fs::create_dir_all(&system).unwrap();
+
+        let app = |name: &str, extra: &str| {
+            format!(
+                "[Desktop Entry]\nType=Application\nName={}\nExec={}\n{}",
+                name, name, extra
+            )
+        };
+        fs::write(system.join("a.desktop"), app("a", "")).unwrap();
+        fs::write(system.join("b.desktop"), app("b", "")).unwrap();
+        fs::write(system.join("c.desktop"), app("c", "")).unwrap();
+        fs::write(system.join("notes.txt"), app("d", "")).unwrap();
+        fs::write(user.join("b.desktop"), app("b", "Hidden=true")).unwrap();
+        fs::write(user.join("c.desktop"), app("user-c", "")).unwrap();
+
+        let entries = autostart_entries_in(&[user, system], &desktops(&["Penrose"]));
+        fs::remove_dir_all(&root).unwrap();
+
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["user-c", "a"]);
+    }
+
+    #[test]
+    fn application_ids_include_subdirectories() {
+        let root = env::temp_dir().join(format!("penrose-xdg-apps-{}", std::process::id()));
+        let (user, system) = (root.join("user"), root.join("system"));
+        fs::create_dir_all(&user).unwrap();
+        fs::create_dir_all(system.join("kde")).unwrap();
+
+        let app = |name: &str, extra: &str| {
+            format!(
+                "[Desktop Entry]\nType=Application\nName={}\nExec={}\n{}",
+                name, name, extra
+            )
+        };
+        fs::write(system.join("kde/konsole.desktop"), app("konsole", "")).unwrap();
+        fs::write(
+            system.join("mimeinfo.desktop"),
+            app("mime", "NoDisplay=true"),
+        )
+        .unwrap();
+        fs::write(system.join("kde-dolphin.desktop"), app("dolphin", "")).unwrap();
+        fs::write(user.join("kde-konsole.desktop"), app("my-konsole", "")).unwrap();
+
+        let entries = application_entries_in(&[user, system], &desktops(&["Penrose"]));
+        fs::remove_dir_all(&root).unwrap();
+
+        let ids: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.id.as_str(), e.name.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("kde-konsole.desktop", "my-konsole"),
+                ("kde-dolphin.desktop", "dolphin")
+            ]
+        );
+    }
+}
@@ -48,19 +48,29 @@
 //! ```
 use crate::{
     core::{
-        data_types::{Region, WinType},
+        bindings::{ClickKind, MouseButton},
+        config::BarConfig,
+        data_types::{Point, Region, WinType},
         hooks::Hook,
+        input::LockState,
         manager::WindowManager,
-        xconnection::{Atom, Prop, XConn, Xid},
+        ring::Selector,
+        screen::DEFAULT_DPI,
+        xconnection::{Atom, ClientAttr, Prop, XConn, Xid},
     },
-    draw::{Color, Draw, DrawContext, HookableWidget, Result, TextStyle},
+    draw::{Color, Draw, DrawContext, HookableWidget, Result, TextStyle, Widget},
 };
 
-use std::fmt;
+use std::{
+    f64::consts::PI,
+    fmt,
+    time::{Duration, Instant},
+};
 
 use crate::draw::widget::{ActiveWindowName, CurrentLayout, RootWindowName, Workspaces};
 
 const MAX_ACTIVE_WINDOW_CHARS: usize = 80;
+const DEFAULT_MAX_FPS: u32 = 30;
 
 /// Create a default dwm style status bar that displays content pulled from the
 /// WM_NAME property of the root window.
@@ -112,6 +122,7 @@ where
 }
 
 /// The position of a status bar
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum Position {
     /// Top of the screen
@@ -120,7 +131,155 @@ pub enum Position {
     Bottom,
 }
 
+/// The shape of the separators that a [StatusBar] draws between neighbouring widgets
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Separator {
+    /// A powerline style arrow pointing from the left widget into the right
+    Angled,
+    /// A half circle bulging from the left widget into the right
+    Round,
+}
+
+impl Separator {
+    // The number of straight lines used to approximate a round separator
+    const ROUND_STEPS: usize = 16;
+
+    // Separators are half as wide as the bar is tall
+    fn width(&self, h: f64) -> f64 {
+        (h / 2.0).round()
+    }
+
+    // The outline of the part of the separator that is filled with the left hand background
+    fn points(&self, h: f64) -> Vec<(f64, f64)> {
+        let w = self.width(h);
+        match self {
+            Self::Angled => vec![(0.0, 0.0), (w, h / 2.0), (0.0, h)],
+            Self::Round => (0..=Self::ROUND_STEPS)
+                .map(|i| {
+                    let t = PI * i as f64 / Self::ROUND_STEPS as f64;
+                    (w * t.sin(), h / 2.0 * (1.0 - t.cos()))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// How quickly clicks on a [StatusBar] need to follow one another to count as a double click and
+/// how long a button needs to be held down to count as press-and-hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ClickTiming {
+    /// The longest gap between releasing the first click and pressing the second
+    pub double_click: Duration,
+    /// The shortest time between pressing and releasing a button that counts as holding it
+    pub hold: Duration,
+}
+
+impl Default for ClickTiming {
+    fn default() -> Self {
+        Self {
+            double_click: Duration::from_millis(400),
+            hold: Duration::from_millis(500),
+        }
+    }
+}
+
+/// When and how an auto hiding [StatusBar] is revealed.
+///
+/// While auto hiding, the bar is hidden (and its space given back to clients) unless the pointer
+/// is at the edge of a screen that the bar is shown on, or the modifiers in `modifiers` are
+/// held. Once revealed, the bar stays visible for as long as the pointer is over it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AutoHide {
+    /// How close to the edge of the screen (in pixels) the pointer needs to be to reveal the bar
+    pub edge_px: u32,
+    /// A [KeyCode][crate::core::bindings::KeyCode] style modifier mask that reveals the bar
+    /// while any of its modifiers are held, or None to only reveal the bar using the pointer
+    pub modifiers: Option<u16>,
+    /// How often to check the position of the pointer and the held modifiers
+    pub poll_interval: Duration,
+}
+
+impl Default for AutoHide {
+    fn default() -> Self {
+        Self {
+            edge_px: 2,
+            modifiers: None,
+            poll_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+// Presses and recent clicks on the bar that are needed to classify the next click
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ClickState {
+    pressed: Option<(MouseButton, Point, Instant)>,
+    last_single: Option<(MouseButton, usize, Instant)>, // widget index and time of release
+}
+
+impl ClickState {
+    fn press(&mut self, button: MouseButton, p: Point, now: Instant) {
+        self.pressed = Some((button, p, now));
+    }
+
+    // The widget that 'button' was pressed over, the position of the press relative to that
+    // widget and the kind of click that releasing the button at 'now' completes.
+    fn release(
+        &mut self,
+        button: MouseButton,
+        now: Instant,
+        timing: &ClickTiming,
+        regions: &[(usize, Region)],
+    ) -> Option<(usize, Point, ClickKind)> {
+        let (b, p, pressed_at) = self.pressed.take()?;
+        if b != button {
+            return None;
+        }
+
+        let (ix, rel) = widget_at(regions, p)?;
+        let is_scroll = matches!(button, MouseButton::ScrollUp | MouseButton::ScrollDown);
+        let kind = match self.last_single {
+            _ if is_scroll => ClickKind::Single,
+            _ if now.duration_since(pressed_at) >= timing.hold => ClickKind::Hold,
+            Some((lb, lix, at))
+                if lb == button
+                    && lix == ix
+                    && pressed_at.saturating_duration_since(at) <= timing.double_click =>
+            {
+                ClickKind::Double
+            }
+            _ => ClickKind::Single,
+        };
+
+        self.last_single = match kind {
+            ClickKind::Single if !is_scroll => Some((button, ix, now)),
+            _ => None,
+        };
+
+        Some((ix, rel, kind))
+    }
+}
+
 /// A simple status bar that works via hooks
+///
+/// Redraws are rate limited (to 30 frames per second by default, see
+/// [set_max_fps][StatusBar::set_max_fps]) so that updates from several widgets arriving close
+/// together are drawn as a single frame. Nothing is drawn while the system is suspending or on
+/// screens that are covered by a fullscreen client: those screens are brought up to date once
+/// they are visible again.
+///
+/// Widgets with a [poll_interval][Widget::poll_interval] are refreshed at least that often: the
+/// bar asks the [WindowManager] to [wake up][WindowManager::wake_after] its event loop when the
+/// next widget is due so that they update even when no X events are arriving.
+///
+/// Clicks on the bar are classified as single clicks, double clicks or press-and-hold using its
+/// [ClickTiming] when the button is released, and passed to [bar_clicked][Hook::bar_clicked] for
+/// the widget that the button was pressed over, with the point given relative to the top left
+/// corner of that widget. Scrolling always counts as a single click.
+///
+/// If a [Separator] has been set, one is drawn between each pair of neighbouring widgets with
+/// different [backgrounds][crate::draw::Widget::background], blending from the background of the
+/// left widget into that of the right. Widgets without a background of their own are treated as
+/// having the background of the bar.
 pub struct StatusBar<C, D, X>
 where
     C: DrawContext,
@@ -131,11 +290,28 @@ where
     position: Position,
     /// The widgets contained within this status bar
     pub widgets: Vec<Box<dyn HookableWidget<X>>>,
-    screens: Vec<(Xid, f64)>, // window and width
+    screens: Vec<(usize, Xid, Region, f64)>, // screen index, window, window region and scale factor
+    click_windows: Vec<Xid>,                 // windows that button events have been selected on
+    widget_regions: Vec<(usize, Region)>,    // widget index and where it was last drawn
+    hidden_screens: Vec<usize>,
+    shown_screens: Option<Vec<usize>>,
+    config: Option<BarConfig>,
+    scale_by_dpi: bool,
     hpx: usize,
     h: f64,
     bg: Color,
+    separator: Option<Separator>,
     active_screen: usize,
+    click_timing: ClickTiming,
+    clicks: ClickState,
+    poll_due: Vec<Option<Instant>>, // when each widget next needs the event loop to wake up
+    frame_interval: Option<Duration>,
+    last_frame: Option<Instant>,
+    covered_screens: Vec<usize>,
+    suspended: bool,
+    dirty: bool,
+    visible: bool,
+    autohide: Option<AutoHide>,
 }
 
 impl<C, D, X> fmt::Debug for StatusBar<C, D, X>
@@ -150,9 +326,26 @@ where
             .field("position", &self.position)
             .field("widgets", &stringify!(self.widgets))
             .field("screens", &self.screens)
+            .field("click_windows", &self.click_windows)
+            .field("widget_regions", &self.widget_regions)
+            .field("hidden_screens", &self.hidden_screens)
+            .field("shown_screens", &self.shown_screens)
+            .field("config", &self.config)
+            .field("scale_by_dpi", &self.scale_by_dpi)
             .field("hpx", &self.hpx)
             .field("bg", &self.bg)
+            .field("separator", &self.separator)
             .field("active_screen", &self.active_screen)
+            .field("click_timing", &self.click_timing)
+            .field("clicks", &self.clicks)
+            .field("poll_due", &self.poll_due)
+            .field("frame_interval", &self.frame_interval)
+            .field("last_frame", &self.last_frame)
+            .field("covered_screens", &self.covered_screens)
+            .field("suspended", &self.suspended)
+            .field("dirty", &self.dirty)
+            .field("visible", &self.visible)
+            .field("autohide", &self.autohide)
             .finish()
     }
 }
@@ -172,37 +365,195 @@ where
         fonts: &[&str],
         widgets: Vec<Box<dyn HookableWidget<X>>>,
     ) -> Result<Self> {
-        let mut bar = Self {
+        let mut bar = Self::without_windows(drw, position, h, bg, widgets);
+        bar.init_for_screens()?;
+        fonts.iter().for_each(|f| bar.drw.register_font(f));
+
+        Ok(bar)
+    }
+
+    /// Try to initialise a new empty status bar with the height, position and screens given in
+    /// 'config', which should also be one of the [bars][crate::core::config::Config::bars] in
+    /// your [Config][crate::core::config::Config] so that space is reserved for it.
+    pub fn try_new_from_config(
+        drw: D,
+        config: &BarConfig,
+        bg: impl Into<Color>,
+        fonts: &[&str],
+        widgets: Vec<Box<dyn HookableWidget<X>>>,
+    ) -> Result<Self> {
+        let h = config.height() as usize;
+        let mut bar = Self::without_windows(drw, config.position(), h, bg, widgets);
+        bar.config = Some(config.clone());
+        bar.init_for_screens()?;
+        fonts.iter().for_each(|f| bar.drw.register_font(f));
+
+        Ok(bar)
+    }
+
+    fn without_windows(
+        drw: D,
+        position: Position,
+        h: usize,
+        bg: impl Into<Color>,
+        widgets: Vec<Box<dyn HookableWidget<X>>>,
+    ) -> Self {
+        Self {
             drw,
             position,
             widgets,
             screens: vec![],
+            click_windows: vec![],
+            widget_regions: vec![],
+            hidden_screens: vec![],
+            shown_screens: None,
+            config: None,
+            scale_by_dpi: false,
             hpx: h,
             h: h as f64,
             bg: bg.into(),
+            separator: None,
             active_screen: 0,
-        };
-        bar.init_for_screens()?;
-        fonts.iter().for_each(|f| bar.drw.register_font(f));
+            click_timing: ClickTiming::default(),
+            clicks: ClickState::default(),
+            poll_due: vec![],
+            frame_interval: frame_interval(Some(DEFAULT_MAX_FPS)),
+            last_frame: None,
+            covered_screens: vec![],
+            suspended: false,
+            dirty: false,
+            visible: true,
+            autohide: None,
+        }
+    }
 
-        Ok(bar)
+    /// Stop displaying this bar on the screens with the given indices, leaving them free for an
+    /// external bar or for no bar at all.
+    ///
+    /// This should be paired with setting `bar_hidden_screens` in your
+    /// [Config][crate::core::config::Config] so that space is no longer reserved for this bar on
+    /// those screens.
+    pub fn set_hidden_screens(&mut self, screens: &[usize]) -> Result<()> {
+        self.hidden_screens = screens.to_vec();
+        self.rebuild_windows()
+    }
+
+    /// Only display this bar on the screens with the given indices, or on all screens that have
+    /// not been [hidden][StatusBar::set_hidden_screens] if None.
+    ///
+    /// This allows for showing different bars on different screens, and should be paired with
+    /// giving the same screens to the matching [BarConfig] in your
+    /// [Config][crate::core::config::Config].
+    pub fn set_screens(&mut self, screens: Option<&[usize]>) -> Result<()> {
+        self.shown_screens = screens.map(|s| s.to_vec());
+        self.rebuild_windows()
+    }
+
+    /// Set whether or not the height of this bar and the size of its fonts should be scaled by
+    /// the DPI of each screen, treating the height and point sizes that were given as being for
+    /// a 96 DPI screen.
+    ///
+    /// This should be paired with setting `scale_by_dpi` in your
+    /// [Config][crate::core::config::Config] so that the space reserved for this bar matches.
+    pub fn set_scale_by_dpi(&mut self, scale_by_dpi: bool) -> Result<()> {
+        self.scale_by_dpi = scale_by_dpi;
+        self.rebuild_windows()
+    }
+
+    /// Set the shape of the separators drawn between widgets with different backgrounds, or
+    /// None to draw the widgets directly next to one another.
+    pub fn set_separator(&mut self, separator: Option<Separator>) -> Result<()> {
+        self.separator = separator;
+        self.redraw()
+    }
+
+    /// Set the maximum number of times per second that this bar will redraw itself, or None to
+    /// redraw after every event that changes a widget.
+    pub fn set_max_fps(&mut self, fps: Option<u32>) {
+        self.frame_interval = frame_interval(fps);
+    }
+
+    /// Set how clicks on this bar are told apart from one another
+    pub fn set_click_timing(&mut self, timing: ClickTiming) {
+        self.click_timing = timing;
+    }
+
+    /// Hide this bar unless the pointer is at the edge of the screen or the given modifiers are
+    /// held, or None to always show it. Hiding the bar uses [WindowManager::set_bar_visible]
+    /// so that clients are able to make use of the space while it is hidden.
+    ///
+    /// Only one bar should auto hide as the space reserved for all bars is shown and hidden
+    /// together.
+    pub fn set_autohide(&mut self, autohide: Option<AutoHide>) {
+        self.autohide = autohide;
+    }
+
+    fn shows_screen(&self, i: usize) -> bool {
+        !self.hidden_screens.contains(&i)
+            && self.shown_screens.iter().all(|s| s.contains(&i))
+            && self.config.iter().all(|c| c.is_shown_on(i))
+    }
+
+    fn set_visible(&mut self, visible: bool) -> Result<()> {
+        if self.visible == visible {
+            return Ok(());
+        }
+
+        self.visible = visible;
+        if visible {
+            self.init_for_screens()?;
+            self.redraw()
+        } else {
+            self.destroy_windows()
+        }
+    }
+
+    fn rebuild_windows(&mut self) -> Result<()> {
+        self.destroy_windows()?;
+        if self.visible {
+            self.init_for_screens()?;
+        }
+
+        Ok(())
+    }
+
+    fn destroy_windows(&mut self) -> Result<()> {
+        for (_, id, _, _) in self.screens.drain(..) {
+            self.drw.destroy_client(id)?;
+        }
+
+        Ok(())
     }
 
     fn init_for_screens(&mut self) -> Result<()> {
         let screen_sizes = self.drw.screen_sizes()?;
+        let scale_factors: Vec<f64> = if self.scale_by_dpi {
+            let dpis = self.drw.screen_dpis()?;
+            dpis.iter()
+                .map(|&d| d as f64 / DEFAULT_DPI as f64)
+                .collect()
+        } else {
+            vec![1.0; screen_sizes.len()]
+        };
+        let shown: Vec<usize> = (0..screen_sizes.len())
+            .filter(|&i| self.shows_screen(i))
+            .collect();
         self.screens = screen_sizes
             .iter()
-            .map(|r| {
+            .zip(scale_factors)
+            .enumerate()
+            .filter(|(i, _)| shown.contains(i))
+            .map(|(i, (r, scale))| {
                 let (sx, sy, sw, sh) = r.values();
+                let hpx = (self.hpx as f64 * scale).round() as usize;
                 let y = match self.position {
                     Position::Top => sy as usize,
-                    Position::Bottom => sh as usize - self.hpx,
+                    Position::Bottom => (sy + sh) as usize - hpx,
                 };
-                let id = self.drw.new_window(
-                    WinType::InputOutput(Atom::NetWindowTypeDock),
-                    Region::new(sx, y as u32, sw, self.hpx as u32),
-                    false,
-                )?;
+                let r = Region::new(sx, y as u32, sw, hpx as u32);
+                let id =
+                    self.drw
+                        .new_window(WinType::InputOutput(Atom::NetWindowTypeDock), r, false)?;
 
                 let p = Prop::UTF8String(vec!["penrose-statusbar".to_string()]);
                 for atom in &[Atom::NetWmName, Atom::WmName, Atom::WmClass] {
@@ -210,75 +561,314 @@ where
                 }
 
                 self.drw.flush(id)?;
-                Ok((id, sw as f64))
+                Ok((i, id, r, scale))
             })
-            .collect::<Result<Vec<(u32, f64)>>>()?;
+            .collect::<Result<Vec<(usize, Xid, Region, f64)>>>()?;
 
         Ok(())
     }
 
     /// Re-render all widgets in this status bar
     pub fn redraw(&mut self) -> Result<()> {
-        for (i, &(id, w)) in self.screens.clone().iter().enumerate() {
+        self.widget_regions.clear();
+
+        for &(i, id, r, scale) in self.screens.clone().iter() {
+            if self.covered_screens.contains(&i) {
+                continue;
+            }
+
+            let w = r.w as f64;
             let screen_has_focus = self.active_screen == i;
+            let h = (self.h * scale).round();
             let mut ctx = self.drw.context_for(id)?;
+            ctx.set_font_scale(scale);
 
             ctx.clear();
 
             ctx.color(&self.bg);
-            ctx.rectangle(0.0, 0.0, w, self.h as f64);
+            ctx.rectangle(0.0, 0.0, w, h);
 
-            let extents = self.layout(&mut ctx, w)?;
+            let extents = self.layout(&mut ctx, w, h)?;
+            let separators = self.separator_colors();
             let mut x = 0.0;
-            for (wd, (w, _)) in self.widgets.iter_mut().zip(extents) {
-                wd.draw(&mut ctx, self.active_screen, screen_has_focus, w, self.h)?;
+            for (ix, (wd, (w, _))) in self.widgets.iter_mut().zip(extents).enumerate() {
+                wd.draw(&mut ctx, self.active_screen, screen_has_focus, w, h)?;
+                let wr = Region::new(r.x + x as u32, r.y, w as u32, r.h);
+                self.widget_regions.push((ix, wr));
                 x += w;
                 ctx.flush();
                 ctx.set_x_offset(x);
+
+                if let (Some(sep), Some((left, right))) = (self.separator, separators[ix]) {
+                    ctx.color(&right);
+                    ctx.rectangle(0.0, 0.0, sep.width(h), h);
+                    ctx.color(&left);
+                    ctx.polygon(&sep.points(h));
+                    x += sep.width(h);
+                    ctx.flush();
+                    ctx.set_x_offset(x);
+                }
             }
 
             self.drw.flush(id)?;
         }
 
+        self.last_frame = Some(Instant::now());
+        self.dirty = false;
+
         Ok(())
     }
 
-    fn layout(&mut self, ctx: &mut C, w: f64) -> Result<Vec<(f64, f64)>> {
-        let mut extents = Vec::with_capacity(self.widgets.len());
-        let mut greedy_indices = vec![];
+    fn layout(&mut self, ctx: &mut C, w: f64, h: f64) -> Result<Vec<(f64, f64)>> {
+        let reserved = self.separators_width(h);
+        layout_widgets(&mut self.widgets, ctx, w, h, reserved)
+    }
 
-        for (i, w) in self.widgets.iter_mut().enumerate() {
-            extents.push(w.current_extent(ctx, self.h)?);
-            if w.is_greedy() {
-                greedy_indices.push(i)
-            }
+    // The background colors either side of each gap between widgets that needs a separator
+    fn separator_colors(&self) -> Vec<Option<(Color, Color)>> {
+        if self.separator.is_none() {
+            return vec![None; self.widgets.len()];
         }
 
-        let total = extents.iter().map(|(w, _)| w).sum::<f64>();
-        let n_greedy = greedy_indices.len();
+        let backgrounds: Vec<Option<Color>> = self.widgets.iter().map(|w| w.background()).collect();
+        separator_colors(&backgrounds, self.bg)
+    }
 
-        if total < w && n_greedy > 0 {
-            let per_greedy = (w - total) / n_greedy as f64;
-            for i in greedy_indices.iter() {
-                let (w, h) = extents[*i];
-                extents[*i] = (w + per_greedy, h);
+    fn separators_width(&self, h: f64) -> f64 {
+        match self.separator {
+            Some(sep) => {
+                let n = self.separator_colors().iter().flatten().count();
+                n as f64 * sep.width(h)
             }
+            None => 0.0,
         }
+    }
+
+    // Make sure that the event loop wakes up in time for the next widget that needs polling
+    fn schedule_polls(&mut self, wm: &mut WindowManager<X>) {
+        let intervals: Vec<Option<Duration>> =
+            self.widgets.iter().map(|w| w.poll_interval()).collect();
+        let now = Instant::now();
 
-        // Allowing overflow to happen
-        Ok(extents)
+        if let Some(next) = next_poll(&mut self.poll_due, &intervals, now) {
+            wm.wake_after(next.saturating_duration_since(now));
+        }
     }
 
-    fn redraw_if_needed(&mut self) -> Result<()> {
-        if self.widgets.iter().any(|w| w.require_draw()) {
-            self.redraw()?;
-            for (id, _) in self.screens.iter() {
-                self.drw.flush(*id)?;
+    // The bar windows are created using our Draw connection so button events need selecting on
+    // each new window using the connection of the WindowManager in order for clicks to be
+    // reported to hooks.
+    fn select_clicks(&mut self, wm: &WindowManager<X>) -> crate::Result<()> {
+        for &(_, id, _, _) in self.screens.iter() {
+            if !self.click_windows.contains(&id) {
+                wm.conn()
+                    .set_client_attributes(id, &[ClientAttr::ButtonEventMask])?;
             }
         }
+        self.click_windows = self.screens.iter().map(|&(_, id, _, _)| id).collect();
 
         Ok(())
     }
+
+    fn is_bar_window(&self, id: Xid) -> bool {
+        self.screens.iter().any(|&(_, w, _, _)| w == id)
+    }
+
+    // Screens showing a fullscreen client can't see the bar so there is no need to draw it there
+    fn update_covered_screens(&mut self, wm: &WindowManager<X>) {
+        let has_fullscreen_client = |wix: usize| match wm.workspace(&Selector::Index(wix)) {
+            Some(ws) => ws
+                .client_ids()
+                .iter()
+                .any(|&id| matches!(wm.client(&Selector::WinId(id)), Some(c) if c.is_fullscreen())),
+            None => false,
+        };
+
+        let covered: Vec<usize> = wm
+            .focused_workspaces()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, wix)| has_fullscreen_client(wix))
+            .map(|(i, _)| i)
+            .collect();
+
+        if covered != self.covered_screens {
+            // anything that changed while a screen was covered still needs drawing
+            self.dirty |= self.covered_screens.iter().any(|i| !covered.contains(i));
+            self.covered_screens = covered;
+        }
+    }
+
+    // Reveal or hide the bar based on the pointer position and held modifiers, checking again
+    // after the poll interval as pointer motion over the root window does not generate events
+    fn update_autohide(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        let autohide = match self.autohide {
+            Some(autohide) => autohide,
+            None => return Ok(()),
+        };
+        wm.wake_after(autohide.poll_interval);
+
+        let held = match autohide.modifiers {
+            Some(mask) => wm.conn().modifier_state()? & mask != 0,
+            None => false,
+        };
+        let p = wm.conn().cursor_position()?;
+        let reveal = held
+            || (0..wm.n_screens())
+                .filter(|&i| self.shows_screen(i))
+                .any(|i| {
+                    // the whole bar counts as the edge once it has been revealed
+                    let zone = match self.screens.iter().find(|(ix, _, _, _)| *ix == i) {
+                        Some((_, _, r, _)) => r.h,
+                        None => autohide.edge_px,
+                    };
+                    match wm.screen(&Selector::Index(i)) {
+                        Some(s) => reveals_bar(p, s.region(false), self.position, zone),
+                        None => false,
+                    }
+                });
+
+        wm.set_bar_visible(reveal)?;
+        // hooks are not re-run while we are running as one so we update ourselves directly
+        Ok(self.set_visible(reveal)?)
+    }
+
+    fn redraw_if_needed(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        let all_covered = self
+            .screens
+            .iter()
+            .all(|(i, _, _, _)| self.covered_screens.contains(i));
+        let needed = self.dirty || self.widgets.iter().any(|w| w.require_draw());
+
+        if self.suspended || all_covered || !needed {
+            return Ok(());
+        }
+
+        if let Some(wait) = frame_wait(self.last_frame, self.frame_interval, Instant::now()) {
+            // picked up by the event_handled hook once the event loop wakes up again
+            wm.wake_after(wait);
+            return Ok(());
+        }
+
+        self.redraw()?;
+        for (_, id, _, _) in self.screens.iter() {
+            self.drw.flush(*id)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Whether or not 'p' is within 'zone' pixels of the edge of 'screen' that a bar at 'position' sits on
+fn reveals_bar(p: Point, screen: Region, position: Position, zone: u32) -> bool {
+    if !screen.contains_point(&p) {
+        return false;
+    }
+
+    let (_, sy, _, sh) = screen.values();
+    match position {
+        Position::Top => p.y < sy + zone,
+        Position::Bottom => p.y + zone >= sy + sh,
+    }
+}
+
+// The width and height of each widget when drawn in a bar of size (w, h). Greedy widgets share
+// whatever space is left after all widgets and 'reserved' (e.g. separators) have been placed.
+fn layout_widgets<W>(
+    widgets: &mut [Box<W>],
+    ctx: &mut dyn DrawContext,
+    w: f64,
+    h: f64,
+    reserved: f64,
+) -> Result<Vec<(f64, f64)>>
+where
+    W: Widget + ?Sized,
+{
+    let mut extents = Vec::with_capacity(widgets.len());
+    let mut greedy_indices = vec![];
+
+    for (i, w) in widgets.iter_mut().enumerate() {
+        extents.push(w.current_extent(ctx, h)?);
+        if w.is_greedy() {
+            greedy_indices.push(i)
+        }
+    }
+
+    let total = extents.iter().map(|(w, _)| w).sum::<f64>() + reserved;
+    let n_greedy = greedy_indices.len();
+
+    if total < w && n_greedy > 0 {
+        let per_greedy = (w - total) / n_greedy as f64;
+        for i in greedy_indices.iter() {
+            let (w, h) = extents[*i];
+            extents[*i] = (w + per_greedy, h);
+        }
+    }
+
+    // Allowing overflow to happen
+    Ok(extents)
+}
+
+// For each widget, the backgrounds either side of the separator following it if one is needed.
+// Separators are only needed between neighbouring widgets whose backgrounds differ.
+fn separator_colors(backgrounds: &[Option<Color>], bar_bg: Color) -> Vec<Option<(Color, Color)>> {
+    let bgs: Vec<Color> = backgrounds.iter().map(|b| b.unwrap_or(bar_bg)).collect();
+
+    (0..bgs.len())
+        .map(|i| match bgs.get(i + 1) {
+            Some(&right) if right != bgs[i] => Some((bgs[i], right)),
+            _ => None,
+        })
+        .collect()
+}
+
+// The shortest time between frames when drawing at most 'fps' frames per second
+fn frame_interval(fps: Option<u32>) -> Option<Duration> {
+    fps.filter(|&f| f > 0).map(|f| Duration::from_secs(1) / f)
+}
+
+// How much longer we need to wait before drawing the next frame, if at all
+fn frame_wait(
+    last_frame: Option<Instant>,
+    frame_interval: Option<Duration>,
+    now: Instant,
+) -> Option<Duration> {
+    let next = last_frame? + frame_interval?;
+    if next > now {
+        Some(next - now)
+    } else {
+        None
+    }
+}
+
+// Move each poll in 'due' that has passed on to one 'interval' from now, returning the earliest
+// point at which a widget next needs to be polled
+fn next_poll(
+    due: &mut Vec<Option<Instant>>,
+    intervals: &[Option<Duration>],
+    now: Instant,
+) -> Option<Instant> {
+    due.resize(intervals.len(), None);
+
+    due.iter_mut()
+        .zip(intervals)
+        .filter_map(|(d, interval)| {
+            *d = match (*d, interval) {
+                (_, None) => None,
+                (Some(t), Some(_)) if t > now => Some(t),
+                (_, Some(i)) => Some(now + *i),
+            };
+            *d
+        })
+        .min()
+}
+
+// The widget drawn at 'p' along with 'p' relative to the top left corner of that widget
+fn widget_at(regions: &[(usize, Region)], p: Point) -> Option<(usize, Point)> {
+    regions
+        .iter()
+        .find(|(_, r)| r.contains_point(&p))
+        .map(|(ix, r)| (*ix, Point::new(p.x - r.x, p.y - r.y)))
 }
 
 macro_rules! __impl_status_bar_as_hook {
@@ -306,14 +896,11 @@ macro_rules! __impl_status_bar_as_hook {
 
 
             fn screens_updated(&mut self, wm: &mut WindowManager<X>, dimensions: &[Region]) -> crate::Result<()> {
-                for (id, _) in self.screens.iter() {
-                    self.drw.destroy_client(*id)?;
-                }
-
-                if let Err(e) = self.init_for_screens() {
+                if let Err(e) = self.rebuild_windows() {
                     error!("error removing old status bar windows: {}", e)
                 }
 
+                self.select_clicks(wm)?;
                 self.widgets
                     .iter_mut()
                     .try_for_each(|w| w.screens_updated(wm, dimensions))?;
@@ -323,11 +910,67 @@ macro_rules! __impl_status_bar_as_hook {
 
             fn event_handled(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
                 self.widgets.iter_mut().try_for_each(|w| w.event_handled(wm))?;
-                Ok(self.redraw_if_needed()?)
+                self.update_autohide(wm)?;
+                self.select_clicks(wm)?;
+                self.schedule_polls(wm);
+                self.update_covered_screens(wm);
+                Ok(self.redraw_if_needed(wm)?)
             }
 
             fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()>  {
+                self.select_clicks(wm)?;
                 self.widgets.iter_mut().try_for_each(|w| w.startup(wm))?;
+                self.update_autohide(wm)?;
+                self.schedule_polls(wm);
+                Ok(self.redraw()?)
+            }
+
+            fn bar_visibility_changed(&mut self, wm: &mut WindowManager<X>, visible: bool) -> crate::Result<()> {
+                self.set_visible(visible)?;
+                self.select_clicks(wm)?;
+                self.widgets
+                    .iter_mut()
+                    .try_for_each(|w| w.bar_visibility_changed(wm, visible))
+            }
+
+            fn window_clicked(
+                &mut self,
+                _: &mut WindowManager<X>,
+                id: Xid,
+                button: MouseButton,
+                point: Point,
+            ) -> crate::Result<()> {
+                if self.is_bar_window(id) {
+                    self.clicks.press(button, point, Instant::now());
+                }
+                Ok(())
+            }
+
+            fn window_released(
+                &mut self,
+                wm: &mut WindowManager<X>,
+                id: Xid,
+                button: MouseButton,
+                _: Point,
+            ) -> crate::Result<()> {
+                if !self.is_bar_window(id) {
+                    return Ok(());
+                }
+                let now = Instant::now();
+                match self.clicks.release(button, now, &self.click_timing, &self.widget_regions) {
+                    Some((ix, p, kind)) => self.widgets[ix].bar_clicked(wm, button, kind, p),
+                    None => Ok(()),
+                }
+            }
+
+            fn suspending(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()>  {
+                self.suspended = true;
+                self.widgets.iter_mut().try_for_each(|w| w.suspending(wm))
+            }
+
+            fn resumed(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()>  {
+                self.suspended = false;
+                self.widgets.iter_mut().try_for_each(|w| w.resumed(wm))?;
                 Ok(self.redraw()?)
             }
         }
@@ -337,12 +980,246 @@ macro_rules! __impl_status_bar_as_hook {
 __impl_status_bar_as_hook! {
     client_name_updated => id: Xid, name: &str, is_root: bool;
     client_added_to_workspace => id: Xid, wix: usize;
+    client_urgency_changed => id: Xid, urgent: bool;
+    error_raised => msg: &str;
     focus_change => id: Xid;
+    input_devices_changed => ;
+    key_chord_changed => hints: &[&str];
     layout_applied => workspace_index: usize, screen_index: usize;
     layout_change => workspace_index: usize, screen_index: usize;
+    lid_state_changed => closed: bool;
+    lock_state_changed => state: LockState;
+    main_ratio_changed => workspace_index: usize, ratio: f32;
     new_client => id: Xid;
     randr_notify => ;
     remove_client => id: Xid;
     workspace_change => prev: usize, new: usize;
+    workspace_overflow => id: Xid, full: usize, overflow: usize;
     workspaces_updated => names: &[&str], active: usize;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw::{widget::Text, MockDrawContext};
+    use ClickKind::*;
+    use MouseButton::*;
+
+    const BAR: u32 = 0x282828;
+    const BLUE: u32 = 0x458588;
+    const GREY: u32 = 0x3c3836;
+
+    test_cases! {
+        separator_colors;
+        args: (backgrounds: &[Option<u32>], expected: &[Option<(u32, u32)>]);
+
+        case: no_widgets => (&[], &[]);
+        case: single_widget => (&[Some(BLUE)], &[None]);
+        case: same_background => (&[Some(BLUE), Some(BLUE)], &[None, None]);
+        case: different_backgrounds => (&[Some(BLUE), Some(GREY)], &[Some((BLUE, GREY)), None]);
+        case: transparent_uses_bar => (
+            &[None, Some(BLUE), None],
+            &[Some((BAR, BLUE)), Some((BLUE, BAR)), None]
+        );
+        case: transparent_next_to_bar_color => (&[None, Some(BAR)], &[None, None]);
+
+        body: {
+            let backgrounds: Vec<Option<Color>> = backgrounds.iter().map(|b| b.map(Color::from)).collect();
+            let expected: Vec<Option<(Color, Color)>> = expected
+                .iter()
+                .map(|e| e.map(|(l, r)| (Color::from(l), Color::from(r))))
+                .collect();
+
+            assert_eq!(separator_colors(&backgrounds, BAR.into()), expected);
+        }
+    }
+
+    #[test]
+    fn separators_span_the_height_of_the_bar() {
+        for sep in &[Separator::Angled, Separator::Round] {
+            let points = sep.points(20.0);
+            let ys: Vec<f64> = points.iter().map(|&(_, y)| y).collect();
+            let max_x = points.iter().map(|&(x, _)| x).fold(0.0, f64::max);
+
+            assert_eq!(points[0], (0.0, 0.0));
+            assert!((points[points.len() - 1].1 - 20.0).abs() < 1e-9);
+            assert!(ys.windows(2).all(|w| w[0] <= w[1]));
+            assert!((max_x - sep.width(20.0)).abs() < 1e-9);
+        }
+    }
+
+    test_cases! {
+        widget_at;
+        args: (p: Point, expected: Option<(usize, Point)>);
+
+        case: first_widget => (Point::new(10, 5), Some((0, Point::new(10, 5))));
+        case: second_widget => (Point::new(150, 5), Some((1, Point::new(50, 5))));
+        case: second_screen => (Point::new(1010, 5), Some((0, Point::new(10, 5))));
+        case: below_bar => (Point::new(10, 50), None);
+
+        body: {
+            let regions = vec![
+                (0, Region::new(0, 0, 100, 20)),
+                (1, Region::new(100, 0, 200, 20)),
+                (0, Region::new(1000, 0, 100, 20)),
+                (1, Region::new(1100, 0, 200, 20)),
+            ];
+
+            assert_eq!(widget_at(&regions, p), expected);
+        }
+    }
+
+    test_cases! {
+        click_kinds;
+        args: (clicks: &[(MouseButton, u32, u64, u64)], expected: &[Option<ClickKind>]);
+
+        case: single => (&[(Left, 10, 0, 50)], &[Some(Single)]);
+        case: hold => (&[(Left, 10, 0, 600)], &[Some(Hold)]);
+        case: double => (&[(Left, 10, 0, 50), (Left, 10, 200, 250)], &[Some(Single), Some(Double)]);
+        case: slow_second_click => (
+            &[(Left, 10, 0, 50), (Left, 10, 500, 550)],
+            &[Some(Single), Some(Single)]
+        );
+        case: second_click_on_other_widget => (
+            &[(Left, 10, 0, 50), (Left, 150, 200, 250)],
+            &[Some(Single), Some(Single)]
+        );
+        case: second_click_with_other_button => (
+            &[(Left, 10, 0, 50), (Right, 10, 200, 250)],
+            &[Some(Single), Some(Single)]
+        );
+        case: triple_click => (
+            &[(Left, 10, 0, 50), (Left, 10, 100, 150), (Left, 10, 200, 250)],
+            &[Some(Single), Some(Double), Some(Single)]
+        );
+        case: scrolling_is_never_double => (
+            &[(ScrollUp, 10, 0, 0), (ScrollUp, 10, 10, 10)],
+            &[Some(Single), Some(Single)]
+        );
+        case: off_the_bar => (&[(Left, 10, 0, 50), (Left, 500, 100, 150)], &[Some(Single), None]);
+
+        body: {
+            let regions = vec![
+                (0, Region::new(0, 0, 100, 20)),
+                (1, Region::new(100, 0, 200, 20)),
+            ];
+            let timing = ClickTiming::default();
+            let start = Instant::now();
+            let at = |ms| start + Duration::from_millis(ms);
+            let mut state = ClickState::default();
+
+            let kinds: Vec<Option<ClickKind>> = clicks
+                .iter()
+                .map(|&(button, x, pressed, released)| {
+                    state.press(button, Point::new(x, 5), at(pressed));
+                    state
+                        .release(button, at(released), &timing, &regions)
+                        .map(|(_, _, kind)| kind)
+                })
+                .collect();
+
+            assert_eq!(kinds, expected);
+        }
+    }
+
+    test_cases! {
+        layout_widgets;
+        args: (widgets: &[(&str, bool)], reserved: f64, expected: &[f64]);
+
+        case: no_greedy_widgets => (&[("ab", false), ("cde", false)], 0.0, &[20.0, 30.0]);
+        case: single_greedy_widget => (&[("ab", true), ("cde", false)], 0.0, &[70.0, 30.0]);
+        case: greedy_widgets_share_space => (&[("ab", true), ("cde", true)], 0.0, &[45.0, 55.0]);
+        case: reserved_space_is_left_free => (&[("ab", true), ("cde", false)], 10.0, &[60.0, 30.0]);
+        case: overflow_is_not_shrunk => (
+            &[("abcdefgh", true), ("abcdefgh", false)],
+            0.0,
+            &[80.0, 80.0]
+        );
+
+        body: {
+            let style = TextStyle {
+                font: "mono".into(),
+                point_size: 12,
+                fg: 0xffffff.into(),
+                bg: None,
+                padding: (0.0, 0.0),
+            };
+            let mut ctx = MockDrawContext::new(10.0, 10.0);
+            let mut widgets: Vec<Box<Text>> = widgets
+                .iter()
+                .map(|&(txt, greedy)| Box::new(Text::new(txt, &style, greedy, false)))
+                .collect();
+
+            let widths: Vec<f64> = layout_widgets(&mut widgets, &mut ctx, 100.0, 20.0, reserved)
+                .unwrap()
+                .into_iter()
+                .map(|(w, _)| w)
+                .collect();
+
+            assert_eq!(widths, expected);
+        }
+    }
+
+    #[test]
+    fn polls_are_scheduled_for_the_earliest_widget() {
+        let now = Instant::now();
+        let secs = Duration::from_secs;
+        let intervals = vec![Some(secs(5)), None, Some(secs(2))];
+        let mut due = vec![];
+
+        assert_eq!(next_poll(&mut due, &intervals, now), Some(now + secs(2)));
+        assert_eq!(due, vec![Some(now + secs(5)), None, Some(now + secs(2))]);
+
+        // polls that haven't passed yet are left alone
+        assert_eq!(
+            next_poll(&mut due, &intervals, now + secs(1)),
+            Some(now + secs(2))
+        );
+
+        // and those that have are moved on by their interval
+        let later = now + secs(2);
+        assert_eq!(next_poll(&mut due, &intervals, later), Some(now + secs(4)));
+        assert_eq!(due, vec![Some(now + secs(5)), None, Some(now + secs(4))]);
+    }
+
+    test_cases! {
+        frame_wait;
+        args: (last_ms: Option<u64>, fps: Option<u32>, now_ms: u64, expected_ms: Option<u64>);
+
+        case: first_frame => (None, Some(30), 0, None);
+        case: uncapped => (Some(0), None, 1, None);
+        case: too_soon => (Some(0), Some(50), 5, Some(15));
+        case: after_the_interval => (Some(0), Some(50), 20, None);
+        case: zero_fps_is_uncapped => (Some(0), Some(0), 1, None);
+
+        body: {
+            let start = Instant::now();
+            let at = |ms| start + Duration::from_millis(ms);
+
+            assert_eq!(
+                frame_wait(last_ms.map(at), frame_interval(fps), at(now_ms)),
+                expected_ms.map(Duration::from_millis)
+            );
+        }
+    }
+
+    test_cases! {
+        reveals_bar;
+        args: (p: (u32, u32), position: Position, zone: u32, expected: bool);
+
+        case: top_edge => ((100, 0), Position::Top, 2, true);
+        case: inside_top_zone => ((100, 1), Position::Top, 2, true);
+        case: below_top_zone => ((100, 2), Position::Top, 2, false);
+        case: bottom_edge => ((100, 767), Position::Bottom, 2, true);
+        case: above_bottom_zone => ((100, 765), Position::Bottom, 2, false);
+        case: other_screen => ((1500, 0), Position::Top, 2, false);
+        case: over_revealed_bar => ((100, 15), Position::Top, 18, true);
+
+        body: {
+            let screen = Region::new(0, 0, 1366, 768);
+            let p = Point::new(p.0, p.1);
+
+            assert_eq!(reveals_bar(p, screen, position, zone), expected);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Keyboard hints for jumping straight to a visible window
+//!
+//! Example
+//! ```
+//! use penrose::{
+//!     contrib::actions::goto_window_overlay,
+//!     core::{bindings::KeyEventHandler, xconnection::XConn},
+//!     draw::{Color, KeyPressDraw, TextStyle, WindowHints},
+//! };
+//!
+//! use std::convert::TryFrom;
+//!
+//! # fn example<D: KeyPressDraw + 'static, X: XConn>(drw: D) -> penrose::Result<KeyEventHandler<X>> {
+//! let style = TextStyle {
+//!     font: "mono".to_string(),
+//!     point_size: 28,
+//!     fg: Color::try_from("#282828")?,
+//!     bg: Some(Color::try_from("#fabd2f")?),
+//!     padding: (12.0, 6.0),
+//! };
+//!
+//! let goto_window = goto_window_overlay(WindowHints::new(drw, &style));
+//! # Ok(goto_window)
+//! # }
+//! ```
+use crate::{
+    core::{
+        bindings::KeyPress,
+        data_types::{Region, WinType},
+        xconnection::{Atom, KeyPressParseAttempt, Prop, Xid},
+    },
+    draw::{DrawContext, KeyPressDraw, Result, TextStyle},
+};
+
+use std::fmt;
+
+const DEFAULT_HINT_KEYS: &str = "asdfghjklqwertyuiopzxcvbnm1234567890";
+
+/// Draws a badge labelled with a single key in the middle of each of a set of windows and waits
+/// for one of those keys to be pressed, in the style of easymotion or ace-jump.
+///
+/// The badges are drawn using the font, colors and padding from the `style` that the hints were
+/// created with so a large point size is recommended. By default the badges are labelled using
+/// the home row first, followed by the rest of the letters and then the digits: see
+/// [with_keys][WindowHints::with_keys] to change this. Windows beyond the number of available
+/// keys are not given a badge.
+///
+/// See [goto_window_overlay][1] for a ready made key binding. The badges can also be shown
+/// without waiting for a key press using [show][WindowHints::show], which is how [expose][2]
+/// labels each client with its title.
+///
+/// [1]: crate::contrib::actions::goto_window_overlay
+/// [2]: crate::contrib::actions::expose
+pub struct WindowHints<D: KeyPressDraw> {
+    drw: D,
+    style: TextStyle,
+    keys: Vec<char>,
+    badges: Vec<Xid>,
+}
+
+impl<D: KeyPressDraw> fmt::Debug for WindowHints<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindowHints")
+            .field("drw", &stringify!(self.drw))
+            .field("style", &self.style)
+            .field("keys", &self.keys)
+            .field("badges", &self.badges)
+            .finish()
+    }
+}
+
+impl<D: KeyPressDraw> WindowHints<D> {
+    /// Create a new WindowHints that draws its badges using `style`
+    pub fn new(mut drw: D, style: &TextStyle) -> Self {
+        drw.register_font(&style.font);
+
+        Self {
+            drw,
+            style: style.clone(),
+            keys: DEFAULT_HINT_KEYS.chars().collect(),
+            badges: vec![],
+        }
+    }
+
+    /// Label badges using the characters of `keys` in order, skipping any repeats
+    pub fn with_keys(mut self, keys: &str) -> Self {
+        self.keys = vec![];
+        for c in keys.chars() {
+            if !self.keys.contains(&c) {
+                self.keys.push(c);
+            }
+        }
+
+        self
+    }
+
+    /// The keys used to label badges, in the order that they are given out
+    pub fn keys(&self) -> &[char] {
+        &self.keys
+    }
+
+    /// Show a badge over each of `targets` and block until a key is pressed, returning the
+    /// window whose badge matches that key.
+    ///
+    /// Pressing Escape or any key without a badge returns `None`. The keyboard is grabbed for as
+    /// long as the badges are shown.
+    pub fn select(&mut self, targets: &[(Xid, Region)]) -> Result<Option<Xid>> {
+        let targets = &targets[..targets.len().min(self.keys.len())];
+        if targets.is_empty() {
+            return Ok(None);
+        }
+
+        self.drw.grab_keyboard()?;
+        let res = self.show(targets, &[]).and_then(|_| self.next_key());
+        self.drw.ungrab_keyboard()?;
+        self.hide()?;
+
+        Ok(selected(&self.keys, targets, &res?))
+    }
+
+    /// Show a badge over each of `targets` without waiting for a key press, with the badge for
+    /// each target labelled using the matching entry of `titles` (if there is one) after its key.
+    ///
+    /// The badges stay visible until [hide][WindowHints::hide] is called.
+    pub fn show(&mut self, targets: &[(Xid, Region)], titles: &[String]) -> Result<()> {
+        let (px, py) = self.style.padding;
+        let labels: Vec<String> = self
+            .keys
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let key: String = c.to_uppercase().collect();
+                match titles.get(i) {
+                    Some(title) => format!("{}  {}", key, title),
+                    None => key,
+                }
+            })
+            .collect();
+
+        for (i, (&(_, client), label)) in targets.iter().zip(labels.iter()).enumerate() {
+            let (tw, th) = self.text_extent(label)?;
+            let (w, h) = ((tw + 2.0 * px).ceil() as u32, (th + 2.0 * py).ceil() as u32);
+            let id = self.badge_window(i, badge_region(client, w, h))?;
+
+            let mut ctx = self.drw.context_for(id)?;
+            ctx.clear();
+            ctx.color(&self.style.bg.unwrap_or_else(|| 0x000000.into()));
+            ctx.rectangle(0.0, 0.0, w as f64, h as f64);
+            ctx.font(&self.style.font, self.style.point_size)?;
+            ctx.color(&self.style.fg);
+            ctx.text(label, 0.0, (px, py))?;
+            ctx.flush();
+            self.drw.flush(id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Hide any badges that are currently being shown
+    pub fn hide(&mut self) -> Result<()> {
+        for &id in self.badges.iter() {
+            self.drw.unmap_client(id)?;
+        }
+
+        Ok(())
+    }
+
+    fn next_key(&self) -> Result<KeyPress> {
+        loop {
+            if let KeyPressParseAttempt::KeyPress(k) = self.drw.next_keypress_blocking()? {
+                return Ok(k);
+            }
+        }
+    }
+
+    fn text_extent(&self, s: &str) -> Result<(f64, f64)> {
+        let mut ctx = self.drw.temp_context(1, 1)?;
+        ctx.font(&self.style.font, self.style.point_size)?;
+        ctx.text_extent(s)
+    }
+
+    // Badge windows are created as they are first needed and then reused for later selections
+    fn badge_window(&mut self, i: usize, r: Region) -> Result<Xid> {
+        if let Some(&id) = self.badges.get(i) {
+            self.drw.position_window(id, r)?;
+            return Ok(id);
+        }
+
+        let ty = WinType::InputOutput(Atom::NetWindowTypeNotification);
+        let id = self.drw.new_window(ty, r, false)?;
+        let p = Prop::UTF8String(vec!["penrose-window-hint".to_string()]);
+        for atom in &[Atom::NetWmName, Atom::WmName, Atom::WmClass] {
+            self.drw.change_prop(id, atom.as_ref(), p.clone())?;
+        }
+        self.badges.push(id);
+
+        Ok(id)
+    }
+}
+
+// A badge of size 'w' x 'h' centered on the client, shrunk to fit if the client is smaller
+fn badge_region(client: Region, w: u32, h: u32) -> Region {
+    let (cx, cy, cw, ch) = client.values();
+    let (w, h) = (w.min(cw), h.min(ch));
+
+    Region::new(cx + (cw - w) / 2, cy + (ch - h) / 2, w, h)
+}
+
+// The target labelled with the key that was pressed. Labels are shown in upper case but matched
+// without needing shift to be held.
+fn selected(keys: &[char], targets: &[(Xid, Region)], k: &KeyPress) -> Option<Xid> {
+    let pressed = match k {
+        KeyPress::Utf8(s) => s.to_lowercase(),
+        _ => return None,
+    };
+
+    keys.iter()
+        .zip(targets)
+        .find(|(c, _)| c.to_lowercase().collect::<String>() == pressed)
+        .map(|(_, &(id, _))| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        badge_region;
+        args: (client: Region, expected: Region);
+
+        case: centered => (Region::new(100, 50, 400, 300), Region::new(280, 180, 40, 40));
+        case: small_client => (Region::new(0, 0, 20, 100), Region::new(0, 30, 20, 40));
+
+        body: {
+            assert_eq!(badge_region(client, 40, 40), expected);
+        }
+    }
+
+    test_cases! {
+        selected;
+        args: (k: KeyPress, expected: Option<Xid>);
+
+        case: first => (KeyPress::Utf8("a".into()), Some(10));
+        case: last => (KeyPress::Utf8("d".into()), Some(30));
+        case: shifted => (KeyPress::Utf8("S".into()), Some(20));
+        case: key_without_a_badge => (KeyPress::Utf8("f".into()), None);
+        case: escape => (KeyPress::Escape, None);
+
+        body: {
+            let r = Region::new(0, 0, 10, 10);
+            let targets = vec![(10, r), (20, r), (30, r)];
+            let keys: Vec<char> = "asdf".chars().collect();
+
+            assert_eq!(selected(&keys, &targets, &k), expected);
+        }
+    }
+}
@@ -0,0 +1,585 @@
+//! Pop up menus for launching programs with the mouse and picking items with the keyboard
+//!
+//! Example
+//! ```
+//! use penrose::{
+//!     core::{hooks::Hooks, xconnection::XConn},
+//!     draw::{Color, Draw, MenuEntry, RootMenu, TextStyle},
+//! };
+//!
+//! use std::convert::TryFrom;
+//!
+//! # fn example<D: Draw + 'static, X: XConn>(drw: D) -> penrose::Result<Hooks<X>> {
+//! let style = TextStyle {
+//!     font: "mono".to_string(),
+//!     point_size: 11,
+//!     fg: Color::try_from("#ebdbb2")?,
+//!     bg: Some(Color::try_from("#282828")?),
+//!     padding: (6.0, 4.0),
+//! };
+//!
+//! let entries = vec![
+//!     MenuEntry::command("Terminal", "st"),
+//!     MenuEntry::command("Browser", "firefox"),
+//!     MenuEntry::submenu(
+//!         "Graphics",
+//!         vec![
+//!             MenuEntry::command("GIMP", "gimp"),
+//!             MenuEntry::command("Inkscape", "inkscape"),
+//!         ],
+//!     ),
+//! ];
+//!
+//! let hooks: Hooks<X> = vec![Box::new(RootMenu::new(drw, &style, entries))];
+//! # Ok(hooks)
+//! # }
+//! ```
+use crate::{
+    core::{
+        bindings::{KeyPress, MouseButton},
+        data_types::{Point, Region},
+        helpers::spawn,
+        hooks::Hook,
+        manager::WindowManager,
+        ring::Selector,
+        screen::Screen,
+        xconnection::{Atom, ClientAttr, KeyPressParseAttempt, XConn},
+    },
+    draw::{
+        overlay::OverlayWindow, Color, Draw, DrawContext, DrawError, KeyPressDraw, Result,
+        TextStyle,
+    },
+};
+
+use std::fmt;
+
+const SUBMENU_MARKER: &str = " >";
+
+/// A single entry in a [RootMenu]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MenuEntry {
+    /// A label and the command that is run when it is selected
+    Command(String, String),
+    /// A label and the entries of a nested menu that is opened when it is selected
+    Submenu(String, Vec<MenuEntry>),
+}
+
+impl MenuEntry {
+    /// Create an entry that runs `cmd` when it is selected
+    pub fn command(label: impl Into<String>, cmd: impl Into<String>) -> Self {
+        Self::Command(label.into(), cmd.into())
+    }
+
+    /// Create an entry that opens a nested menu containing `entries` when it is selected
+    pub fn submenu(label: impl Into<String>, entries: Vec<MenuEntry>) -> Self {
+        Self::Submenu(label.into(), entries)
+    }
+
+    fn display_label(&self) -> String {
+        match self {
+            Self::Command(label, _) => label.clone(),
+            Self::Submenu(label, _) => format!("{}{}", label, SUBMENU_MARKER),
+        }
+    }
+}
+
+/// A menu of programs to launch that is shown when right clicking on the desktop.
+///
+/// The menu is opened at the pointer and an entry is selected by left clicking on it, which will
+/// either run its command or open its nested menu alongside it. Clicking anywhere outside of the
+/// menu, clicking with any other button or pressing Escape closes the menu without running
+/// anything. As with [select_point_interactive][1] the cursor is shown as a crosshair while the
+/// menu is open.
+///
+/// [1]: crate::core::manager::WindowManager::select_point_interactive
+pub struct RootMenu<D: Draw> {
+    overlay: OverlayWindow<D>,
+    entries: Vec<MenuEntry>,
+}
+
+impl<D: Draw> fmt::Debug for RootMenu<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RootMenu")
+            .field("overlay", &self.overlay)
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+impl<D: Draw> RootMenu<D> {
+    /// Create a new RootMenu showing `entries` drawn using `style`
+    pub fn new(drw: D, style: &TextStyle, entries: Vec<MenuEntry>) -> Self {
+        Self {
+            overlay: OverlayWindow::new(
+                drw,
+                style,
+                "penrose-root-menu",
+                Atom::NetWindowTypePopupMenu,
+            ),
+            entries,
+        }
+    }
+
+    fn run<X: XConn>(&mut self, wm: &mut WindowManager<X>, p: Point) -> crate::Result<()> {
+        let mut entries = self.entries.clone();
+        let mut at = p;
+
+        loop {
+            let screen = match wm.screen(&Selector::Condition(&|s: &Screen| s.contains(at))) {
+                Some(s) => s.region(false),
+                None => return Ok(()),
+            };
+            let labels: Vec<String> = entries.iter().map(|e| e.display_label()).collect();
+            let (region, line_height) = self.show(screen, at, &labels)?;
+
+            let clicked = wm.select_point_interactive();
+            self.overlay.hide()?;
+
+            let selected = clicked?
+                .and_then(|p| entry_at(region, line_height, p))
+                .and_then(|ix| entries.get(ix).map(|e| (ix, e.clone())));
+
+            match selected {
+                Some((_, MenuEntry::Command(_, cmd))) => return spawn(cmd),
+                Some((ix, MenuEntry::Submenu(_, nested))) => {
+                    let (x, y, w, _) = region.values();
+                    at = Point::new(x + w, y + ix as u32 * line_height);
+                    entries = nested;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn show(&mut self, screen: Region, at: Point, labels: &[String]) -> Result<(Region, u32)> {
+        let (px, py) = self.overlay.style().padding;
+
+        // measure the labels before positioning a window of the right size
+        let labels: Vec<&str> = labels.iter().map(|l| l.as_str()).collect();
+        let (max_w, max_h) = self
+            .overlay
+            .text_extents(&labels)?
+            .into_iter()
+            .fold((0.0f64, 0.0f64), |(mw, mh), (w, h)| (mw.max(w), mh.max(h)));
+        let line_height = (max_h + 2.0 * py).ceil() as u32;
+        let w = (max_w + 2.0 * px).ceil() as u32;
+        let h = line_height * labels.len() as u32;
+
+        let region = menu_region(screen, at, w, h);
+        let ctx = self.overlay.show_at(region)?;
+        for (i, label) in labels.iter().enumerate() {
+            ctx.set_y_offset((i as u32 * line_height) as f64);
+            ctx.text(label, 0.0, (px, py))?;
+        }
+
+        ctx.flush();
+        self.overlay.flush()?;
+
+        Ok((region, line_height))
+    }
+}
+
+// Open the menu with its top left corner at the pointer, moving it back onto the screen if it
+// would otherwise overflow the right or bottom edge.
+fn menu_region(screen: Region, at: Point, w: u32, h: u32) -> Region {
+    let (sx, sy, sw, sh) = screen.values();
+    let x = at.x.min((sx + sw).saturating_sub(w)).max(sx);
+    let y = at.y.min((sy + sh).saturating_sub(h)).max(sy);
+
+    Region::new(x, y, w, h)
+}
+
+// The index of the menu entry under 'p', if there is one
+fn entry_at(region: Region, line_height: u32, p: Point) -> Option<usize> {
+    if line_height == 0 || !region.contains_point(&p) {
+        return None;
+    }
+
+    Some(((p.y - region.y) / line_height) as usize)
+}
+
+impl<D, X> Hook<X> for RootMenu<D>
+where
+    D: Draw,
+    X: XConn,
+{
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        let root = wm.conn().root();
+        wm.conn()
+            .set_client_attributes(root, &[ClientAttr::RootEventMaskWithClicks])?;
+
+        Ok(())
+    }
+
+    fn root_window_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        point: Point,
+    ) -> crate::Result<()> {
+        if button != MouseButton::Right || self.entries.is_empty() {
+            return Ok(());
+        }
+
+        self.run(wm, point)
+    }
+}
+
+/// The result of picking an item from a [PMenu]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PMenuMatch {
+    /// The selected item along with its index in the items given to the menu
+    Line(usize, String),
+    /// Nothing matched and this was the user's input when they hit Return
+    UserInput(String),
+    /// The user hit Delete to ask for the selected item (and its index) to be removed
+    Delete(usize, String),
+    /// The user closed the menu or hit Return with nothing typed
+    NoMatch,
+}
+
+/// A keyboard driven menu in the style of [dmenu][1] that is drawn by penrose itself.
+///
+/// The menu is shown across the top of the screen with the prompt and the user's input on the
+/// first line and the items matching that input below it. Items are matched fuzzily: an item
+/// matches if it contains each of the typed characters in order, ignoring case, with items that
+/// start with or contain the input exactly being listed first. Up and Down (or Tab) move the
+/// selection, Return picks the selected item and Escape closes the menu. Delete also closes the
+/// menu, returning the selected item as a [PMenuMatch::Delete] so that the caller can remove it
+/// from wherever the items came from (such as a [Frecency][4] history).
+///
+/// See [launch_with_pmenu][2] and [switch_client_with_pmenu][3] for ready made key bindings.
+///
+/// # Example
+/// ```
+/// use penrose::draw::{Color, KeyPressDraw, PMenu, PMenuMatch, TextStyle};
+/// use std::convert::TryFrom;
+///
+/// # fn example<D: KeyPressDraw>(drw: D) -> penrose::Result<()> {
+/// let style = TextStyle {
+///     font: "mono".to_string(),
+///     point_size: 11,
+///     fg: Color::try_from("#ebdbb2")?,
+///     bg: Some(Color::try_from("#282828")?),
+///     padding: (6.0, 4.0),
+/// };
+///
+/// let mut menu = PMenu::new(drw, &style, Color::try_from("#458588")?, 10);
+/// let items = vec!["lock".to_string(), "logout".to_string(), "shutdown".to_string()];
+/// if let PMenuMatch::Line(_, choice) = menu.get_selection_from_input(">>>", items, 0)? {
+///     println!("picked {}", choice);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [1]: https://tools.suckless.org/dmenu/
+/// [2]: crate::contrib::actions::launch_with_pmenu
+/// [3]: crate::contrib::actions::switch_client_with_pmenu
+/// [4]: crate::contrib::frecency::Frecency
+pub struct PMenu<D: KeyPressDraw> {
+    overlay: OverlayWindow<D>,
+    highlight: Color,
+    n_lines: usize,
+}
+
+impl<D: KeyPressDraw> fmt::Debug for PMenu<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PMenu")
+            .field("overlay", &self.overlay)
+            .field("highlight", &self.highlight)
+            .field("n_lines", &self.n_lines)
+            .finish()
+    }
+}
+
+impl<D: KeyPressDraw> PMenu<D> {
+    /// Create a new PMenu drawn using `style` that shows at most `n_lines` matching items at a
+    /// time, with the selected item drawn on a `highlight` background.
+    pub fn new(drw: D, style: &TextStyle, highlight: impl Into<Color>, n_lines: usize) -> Self {
+        Self {
+            overlay: OverlayWindow::new(drw, style, "penrose-menu", Atom::NetWindowTypeDialog),
+            highlight: highlight.into(),
+            n_lines: n_lines.max(1),
+        }
+    }
+
+    /// Show `items` on the screen with index `screen_index` and block until the user picks one
+    /// of them or closes the menu.
+    ///
+    /// The keyboard is grabbed for as long as the menu is open.
+    pub fn get_selection_from_input(
+        &mut self,
+        prompt: impl Into<String>,
+        items: Vec<String>,
+        screen_index: usize,
+    ) -> Result<PMenuMatch> {
+        let screen = match self.overlay.drw().screen_sizes()?.get(screen_index) {
+            Some(&r) => r,
+            None => return Err(DrawError::Raw(format!("unknown screen: {}", screen_index))),
+        };
+
+        self.overlay.drw().grab_keyboard()?;
+        let res = self.run(&prompt.into(), &items, screen);
+        self.overlay.drw().ungrab_keyboard()?;
+        self.overlay.hide()?;
+        self.overlay.flush()?;
+
+        res
+    }
+
+    fn run(&mut self, prompt: &str, items: &[String], screen: Region) -> Result<PMenuMatch> {
+        let mut state = MenuState::new(items);
+
+        loop {
+            self.show(prompt, &state, screen)?;
+
+            if let KeyPressParseAttempt::KeyPress(k) =
+                self.overlay.drw().next_keypress_blocking()?
+            {
+                if let Some(m) = state.handle_keypress(k) {
+                    return Ok(m);
+                }
+            }
+        }
+    }
+
+    fn show(&mut self, prompt: &str, state: &MenuState<'_>, screen: Region) -> Result<()> {
+        let style = self.overlay.style().clone();
+        let (px, py) = style.padding;
+        let (_, th) = self.overlay.text_extents(&[prompt])?[0];
+        let line_height = (th + 2.0 * py).ceil() as u32;
+
+        let (x, y, w, _) = screen.values();
+        let visible = state.visible(self.n_lines);
+        let h = line_height * (1 + self.n_lines.min(state.items.len())) as u32;
+
+        let mut ctx = self.overlay.show_at(Region::new(x, y, w, h))?;
+        ctx.text(&format!("{} {}", prompt, state.input), 0.0, (px, py))?;
+
+        for (row, &ix) in visible.iter().enumerate() {
+            ctx.set_y_offset(((row + 1) as u32 * line_height) as f64);
+            if Some(&ix) == state.matches.get(state.selected) {
+                ctx.color(&self.highlight);
+                ctx.rectangle(0.0, 0.0, w as f64, line_height as f64);
+                ctx.color(&style.fg);
+            }
+            ctx.text(&state.items[ix], 0.0, (px, py))?;
+        }
+
+        ctx.flush();
+        self.overlay.flush()
+    }
+}
+
+// The user's input and the items matching it while a PMenu is open
+struct MenuState<'a> {
+    items: &'a [String],
+    input: String,
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl<'a> MenuState<'a> {
+    fn new(items: &'a [String]) -> Self {
+        Self {
+            items,
+            input: String::new(),
+            matches: fuzzy_filter(items, ""),
+            selected: 0,
+        }
+    }
+
+    // Returns the result of the menu once the user has made a choice
+    fn handle_keypress(&mut self, k: KeyPress) -> Option<PMenuMatch> {
+        match k {
+            KeyPress::Escape => return Some(PMenuMatch::NoMatch),
+            KeyPress::Return => return Some(self.current_match()),
+            KeyPress::Delete => {
+                if let Some(&ix) = self.matches.get(self.selected) {
+                    return Some(PMenuMatch::Delete(ix, self.items[ix].clone()));
+                }
+            }
+            KeyPress::Up => self.selected = self.selected.saturating_sub(1),
+            KeyPress::Down | KeyPress::Tab if self.selected + 1 < self.matches.len() => {
+                self.selected += 1
+            }
+            KeyPress::Backspace => {
+                self.input.pop();
+                self.refilter();
+            }
+            KeyPress::Utf8(c) => {
+                self.input.push_str(&c);
+                self.refilter();
+            }
+            _ => (),
+        }
+
+        None
+    }
+
+    fn refilter(&mut self) {
+        self.matches = fuzzy_filter(self.items, &self.input);
+        self.selected = 0;
+    }
+
+    fn current_match(&self) -> PMenuMatch {
+        match self.matches.get(self.selected) {
+            Some(&ix) => PMenuMatch::Line(ix, self.items[ix].clone()),
+            None if self.input.is_empty() => PMenuMatch::NoMatch,
+            None => PMenuMatch::UserInput(self.input.clone()),
+        }
+    }
+
+    // The page of at most 'n_lines' matches that contains the current selection
+    fn visible(&self, n_lines: usize) -> &[usize] {
+        let start = (self.selected / n_lines) * n_lines;
+        let end = (start + n_lines).min(self.matches.len());
+
+        &self.matches[start..end]
+    }
+}
+
+// The indices of the items matching 'pattern', best matches first: items starting with the
+// pattern, then those containing it and finally those containing its characters in order.
+fn fuzzy_filter(items: &[String], pattern: &str) -> Vec<usize> {
+    let pattern = pattern.to_lowercase();
+    let mut ranked: Vec<(u8, usize)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| {
+            let item = item.to_lowercase();
+            let rank = if item.starts_with(&pattern) {
+                0
+            } else if item.contains(&pattern) {
+                1
+            } else if is_subsequence(&pattern, &item) {
+                2
+            } else {
+                return None;
+            };
+
+            Some((rank, i))
+        })
+        .collect();
+
+    ranked.sort_unstable();
+    ranked.into_iter().map(|(_, i)| i).collect()
+}
+
+fn is_subsequence(pattern: &str, s: &str) -> bool {
+    let mut chars = s.chars();
+    pattern.chars().all(|p| chars.any(|c| c == p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        menu_region;
+        args: (at: Point, expected: Region);
+
+        case: fits_at_pointer => (Point::new(100, 100), Region::new(100, 100, 200, 300));
+        case: overflows_right => (Point::new(900, 100), Region::new(800, 100, 200, 300));
+        case: overflows_bottom => (Point::new(100, 700), Region::new(100, 500, 200, 300));
+
+        body: {
+            let screen = Region::new(0, 0, 1000, 800);
+            assert_eq!(menu_region(screen, at, 200, 300), expected);
+        }
+    }
+
+    test_cases! {
+        entry_at;
+        args: (p: Point, expected: Option<usize>);
+
+        case: first_entry => (Point::new(110, 105), Some(0));
+        case: last_entry => (Point::new(110, 199), Some(3));
+        case: outside_menu => (Point::new(50, 105), None);
+
+        body: {
+            let region = Region::new(100, 100, 200, 100);
+            assert_eq!(entry_at(region, 25, p), expected);
+        }
+    }
+
+    test_cases! {
+        fuzzy_filter;
+        args: (pattern: &str, expected: &[usize]);
+
+        case: empty_matches_everything => ("", &[0, 1, 2, 3]);
+        case: prefix_before_substring => ("fox", &[1, 0]);
+        case: ignores_case => ("FIRE", &[0]);
+        case: characters_in_order => ("ffx", &[0]);
+        case: no_match => ("zzz", &[]);
+
+        body: {
+            let items: Vec<String> = vec!["firefox", "foxit", "st", "alacritty"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+
+            assert_eq!(fuzzy_filter(&items, pattern), expected);
+        }
+    }
+
+    test_cases! {
+        menu_state;
+        args: (keys: Vec<KeyPress>, expected: PMenuMatch);
+
+        case: first_item_by_default => (vec![KeyPress::Return], PMenuMatch::Line(0, "firefox".into()));
+        case: move_down => (
+            vec![KeyPress::Down, KeyPress::Down, KeyPress::Return],
+            PMenuMatch::Line(2, "st".into())
+        );
+        case: up_stops_at_first_item => (
+            vec![KeyPress::Up, KeyPress::Return],
+            PMenuMatch::Line(0, "firefox".into())
+        );
+        case: typing_filters => (
+            vec![KeyPress::Utf8("s".into()), KeyPress::Return],
+            PMenuMatch::Line(2, "st".into())
+        );
+        case: backspace_refilters => (
+            vec![KeyPress::Utf8("x".into()), KeyPress::Backspace, KeyPress::Return],
+            PMenuMatch::Line(0, "firefox".into())
+        );
+        case: unmatched_input => (
+            vec![KeyPress::Utf8("q".into()), KeyPress::Return],
+            PMenuMatch::UserInput("q".into())
+        );
+        case: escape => (vec![KeyPress::Utf8("s".into()), KeyPress::Escape], PMenuMatch::NoMatch);
+        case: delete_selected => (
+            vec![KeyPress::Down, KeyPress::Delete],
+            PMenuMatch::Delete(1, "foxit".into())
+        );
+        case: delete_needs_a_selection => (
+            vec![KeyPress::Utf8("q".into()), KeyPress::Delete, KeyPress::Escape],
+            PMenuMatch::NoMatch
+        );
+
+        body: {
+            let items: Vec<String> = vec!["firefox", "foxit", "st"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+            let mut state = MenuState::new(&items);
+
+            let res = keys.into_iter().find_map(|k| state.handle_keypress(k));
+            assert_eq!(res, Some(expected));
+        }
+    }
+
+    #[test]
+    fn visible_matches_page_with_the_selection() {
+        let items: Vec<String> = (0..5).map(|i| i.to_string()).collect();
+        let mut state = MenuState::new(&items);
+
+        assert_eq!(state.visible(2), &[0, 1]);
+        state.selected = 3;
+        assert_eq!(state.visible(2), &[2, 3]);
+        state.selected = 4;
+        assert_eq!(state.visible(2), &[4]);
+    }
+}
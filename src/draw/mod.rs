@@ -5,23 +5,52 @@
 //! for writing a full GUI application, the [Draw] and [DrawContext] traits are enough for setting
 //! up simple text based UI elements such as status bars and menus.
 pub mod bar;
+pub mod hints;
+pub mod menu;
+pub mod overlay;
+pub mod tabs;
+pub mod wallpaper;
 pub mod widget;
 
 #[doc(inline)]
 pub use bar::*;
 
+#[doc(inline)]
+pub use hints::*;
+
+#[doc(inline)]
+pub use menu::*;
+
+#[doc(inline)]
+pub use overlay::*;
+
+#[doc(inline)]
+pub use tabs::*;
+
+#[doc(inline)]
+pub use wallpaper::*;
+
 #[doc(inline)]
 pub use widget::{HookableWidget, KeyboardControlled, Widget};
 
 use crate::core::{
     data_types::{Region, WinType},
+    screen::DEFAULT_DPI,
     xconnection::{XClientHandler, XClientProperties, XKeyboardHandler, Xid},
 };
 
 #[cfg(feature = "xcb")]
 use crate::xcb::XcbError;
 
-use std::{convert::TryFrom, convert::TryInto};
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap},
+    convert::TryFrom,
+    convert::TryInto,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    rc::Rc,
+};
 
 /// Enum to store the various ways that operations can fail when rendering windows
 #[derive(thiserror::Error, Debug)]
@@ -43,6 +72,14 @@ pub enum DrawError {
     #[error("'{0}' is has not been registered as a font")]
     UnknownFont(String),
 
+    /// A wallpaper image could not be loaded
+    #[error("unable to load wallpaper '{0}': {1}")]
+    InvalidWallpaper(PathBuf, String),
+
+    /// The [Draw] implementation being used does not support the requested operation
+    #[error("{0} is not supported by this Draw implementation")]
+    Unsupported(&'static str),
+
     /// Wrapper around XCB implementation errors for [draw][crate::draw] traits
     #[cfg(feature = "xcb")]
     #[error(transparent)]
@@ -176,6 +213,98 @@ impl TryFrom<&str> for Color {
     }
 }
 
+/// Wrap `s` in Unicode directional isolates if it contains any right-to-left text.
+///
+/// Each piece of text is laid out in the direction of the first strongly directional character
+/// it contains, so a right-to-left window title or device name that is formatted into a larger
+/// string can reverse the order of the text around it. Isolating it keeps the surrounding text
+/// in place while `s` itself is still drawn right-to-left.
+pub fn isolate_bidi(s: &str) -> String {
+    if s.chars().any(is_rtl) {
+        format!("\u{2068}{}\u{2069}", s)
+    } else {
+        s.to_string()
+    }
+}
+
+// Characters from the Hebrew, Arabic and other right-to-left blocks
+fn is_rtl(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF
+    )
+}
+
+/// The number of entries a [TextExtentCache] can hold before it is emptied
+pub const TEXT_EXTENT_CACHE_SIZE: usize = 512;
+
+/// A cache of measured text extents that can be shared between the [DrawContext]s of a [Draw].
+///
+/// Extents are keyed by font name, the point size the font was set to (after any scaling) and a
+/// hash of the text, so strings that are rendered repeatedly such as workspace labels are only
+/// measured once for each font and size. Only the hash of each string is stored and the cache is
+/// emptied once it holds [TEXT_EXTENT_CACHE_SIZE] entries, so frequently changing text (a clock
+/// for example) can not grow it without bound.
+#[derive(Clone, Debug, Default)]
+pub struct TextExtentCache {
+    extents: Rc<RefCell<HashMap<ExtentKey, (f64, f64)>>>,
+}
+
+// font name, point size and hash of the text
+type ExtentKey = (String, i32, u64);
+
+impl TextExtentCache {
+    /// Create a new, empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The extent of 's' when rendered using 'font' at 'point_size', calling 'measure' to find it
+    /// if it has not been seen before.
+    pub fn get_or_measure<F>(
+        &self,
+        font: &str,
+        point_size: i32,
+        s: &str,
+        measure: F,
+    ) -> Result<(f64, f64)>
+    where
+        F: FnOnce() -> Result<(f64, f64)>,
+    {
+        let mut hasher = DefaultHasher::new();
+        s.hash(&mut hasher);
+        let key = (font.to_string(), point_size, hasher.finish());
+
+        if let Some(&extent) = self.extents.borrow().get(&key) {
+            return Ok(extent);
+        }
+
+        let extent = measure()?;
+        let mut extents = self.extents.borrow_mut();
+        if extents.len() >= TEXT_EXTENT_CACHE_SIZE {
+            extents.clear();
+        }
+        extents.insert(key, extent);
+
+        Ok(extent)
+    }
+
+    /// Remove all cached extents
+    pub fn clear(&self) {
+        self.extents.borrow_mut().clear();
+    }
+
+    /// The number of extents currently held in the cache
+    pub fn len(&self) -> usize {
+        self.extents.borrow().len()
+    }
+
+    /// Whether or not the cache is currently empty
+    pub fn is_empty(&self) -> bool {
+        self.extents.borrow().is_empty()
+    }
+}
+
 /// A simple drawing abstraction
 ///
 /// `Draw` is not intended for use in writing full GUI interfaces, rather it is a simple
@@ -189,8 +318,22 @@ pub trait Draw: XClientHandler + XClientProperties {
 
     /// Create a new client window with a canvas for drawing
     fn new_window(&mut self, ty: WinType, r: Region, managed: bool) -> Result<Xid>;
+    /// Move and resize a window created using [new_window][Draw::new_window] (along with its
+    /// canvas), raising it above its peers
+    ///
+    /// By default this is [unsupported][DrawError::Unsupported].
+    #[allow(unused_variables)]
+    fn position_window(&mut self, id: Xid, r: Region) -> Result<()> {
+        Err(DrawError::Unsupported("positioning windows"))
+    }
     /// Get the size of the target screen in pixels
     fn screen_sizes(&self) -> Result<Vec<Region>>;
+    /// Get the DPI of each screen, in the same order as [screen_sizes][Draw::screen_sizes].
+    ///
+    /// By default every screen is assumed to have the [DEFAULT_DPI].
+    fn screen_dpis(&self) -> Result<Vec<u32>> {
+        Ok(vec![DEFAULT_DPI; self.screen_sizes()?.len()])
+    }
     /// Register a font by name for later use
     fn register_font(&mut self, font_name: &str);
     /// Get a new [DrawContext] for the target window
@@ -199,6 +342,14 @@ pub trait Draw: XClientHandler + XClientProperties {
     fn temp_context(&self, w: u32, h: u32) -> Result<Self::Ctx>;
     /// Flush pending actions
     fn flush(&self, id: Xid) -> Result<()>;
+    /// Draw each [Wallpaper] so that it covers its paired region of the root window and set the
+    /// result as the root window background.
+    ///
+    /// By default this is [unsupported][DrawError::Unsupported].
+    #[allow(unused_variables)]
+    fn set_wallpapers(&mut self, wallpapers: &[(Region, Wallpaper)]) -> Result<()> {
+        Err(DrawError::Unsupported("setting wallpapers"))
+    }
 }
 
 /// A [Draw] that can return the [KeyPress][1] events from the user for its windows
@@ -212,6 +363,17 @@ impl<T> KeyPressDraw for T where T: Draw + XKeyboardHandler {}
 pub trait DrawContext {
     /// Set the active font, must have been registered on the partent Draw
     fn font(&mut self, font_name: &str, point_size: i32) -> Result<()>;
+    /// Scale the point size of fonts set from now on by 'factor' (e.g. for HiDPI screens).
+    ///
+    /// By default fonts are not scaled.
+    #[allow(unused_variables)]
+    fn set_font_scale(&mut self, factor: f64) {}
+    /// The factor that font point sizes are currently being scaled by.
+    ///
+    /// Widgets that cache the size of their text should measure it again when this changes.
+    fn font_scale(&self) -> f64 {
+        1.0
+    }
     /// Set the color used for subsequent drawing operations
     fn color(&mut self, color: &Color);
     /// Clears the context
@@ -224,8 +386,16 @@ pub trait DrawContext {
     fn set_y_offset(&self, y: f64);
     /// Draw a filled rectangle using the current color
     fn rectangle(&self, x: f64, y: f64, w: f64, h: f64);
+    /// Draw a filled polygon with the given vertices using the current color.
+    ///
+    /// This is used for drawing shapes such as powerline separators, which can not be built from
+    /// rectangles, so every backend needs to implement it.
+    fn polygon(&self, points: &[(f64, f64)]);
     /// Render 's' using the current font with the supplied padding. returns the extent taken
     /// up by the rendered text
+    ///
+    /// Implementations are expected to shape the text and to lay it out in the direction of its
+    /// content, so that right-to-left and mixed direction strings are drawn correctly.
     fn text(&self, s: &str, h_offset: f64, padding: (f64, f64)) -> Result<(f64, f64)>;
     /// Determine the pixel width of a given piece of text using the current font
     fn text_extent(&self, s: &str) -> Result<(f64, f64)>;
@@ -233,11 +403,282 @@ pub trait DrawContext {
     fn flush(&self);
 }
 
+pub use mock_context::{DrawCall, MockDrawContext};
+
+mod mock_context {
+    use super::*;
+    use std::{cell::Cell, fmt};
+
+    /// A single call made against a [MockDrawContext].
+    ///
+    /// Positions are recorded exactly as they were passed to the context: see
+    /// [MockDrawContext::drawn_text] for where text ended up once offsets are taken into account.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum DrawCall {
+        /// [DrawContext::font] was called with the given font name and point size
+        Font(String, i32),
+        /// [DrawContext::color] was called with the given color
+        Color(Color),
+        /// [DrawContext::clear] was called
+        Clear,
+        /// [DrawContext::translate] was called with (dx, dy)
+        Translate(f64, f64),
+        /// [DrawContext::set_x_offset] was called with the given offset
+        SetXOffset(f64),
+        /// [DrawContext::set_y_offset] was called with the given offset
+        SetYOffset(f64),
+        /// [DrawContext::rectangle] was called with (x, y, w, h)
+        Rectangle(f64, f64, f64, f64),
+        /// [DrawContext::polygon] was called with the given points
+        Polygon(Vec<(f64, f64)>),
+        /// [DrawContext::text] was called with the given string, h_offset and padding
+        Text(String, f64, (f64, f64)),
+        /// [DrawContext::flush] was called
+        Flush,
+    }
+
+    /// A [DrawContext] that records the calls made against it rather than rendering anything.
+    ///
+    /// Text is measured as a fixed width per character and a fixed height, both multiplied by
+    /// the current font scale, unless a specific extent has been set for a given string using
+    /// [with_extent][MockDrawContext::with_extent]. This allows for checking how widgets lay
+    /// themselves out without needing a running X server or a cairo surface.
+    ///
+    /// # Example
+    /// ```
+    /// use penrose::draw::{DrawCall, MockDrawContext, TextStyle, Widget, widget::Text};
+    ///
+    /// let style = TextStyle {
+    ///     font: "mono".to_string(),
+    ///     point_size: 10,
+    ///     fg: 0xebdbb2.into(),
+    ///     bg: None,
+    ///     padding: (2.0, 3.0),
+    /// };
+    ///
+    /// let mut ctx = MockDrawContext::new(10.0, 12.0).with_extent("wide", (100.0, 12.0));
+    /// let mut short = Text::new("hi", &style, false, false);
+    /// let mut wide = Text::new("wide", &style, false, false);
+    ///
+    /// // extents include the padding of the text
+    /// assert_eq!(short.current_extent(&mut ctx, 20.0).unwrap(), (25.0, 12.0));
+    /// assert_eq!(wide.current_extent(&mut ctx, 20.0).unwrap(), (105.0, 12.0));
+    ///
+    /// short.draw(&mut ctx, 0, true, 25.0, 20.0).unwrap();
+    /// assert!(ctx.calls().contains(&DrawCall::Text("hi".into(), 8.0, (2.0, 3.0))));
+    /// ```
+    pub struct MockDrawContext {
+        char_width: f64,
+        height: f64,
+        extents: HashMap<String, (f64, f64)>,
+        scale: f64,
+        offset: Cell<(f64, f64)>,
+        calls: RefCell<Vec<DrawCall>>,
+        drawn_text: RefCell<Vec<(String, f64, f64)>>,
+        measured: Cell<usize>,
+    }
+
+    impl fmt::Debug for MockDrawContext {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("MockDrawContext")
+                .field("char_width", &self.char_width)
+                .field("height", &self.height)
+                .field("scale", &self.scale)
+                .field("calls", &self.calls.borrow().len())
+                .finish()
+        }
+    }
+
+    impl MockDrawContext {
+        /// Create a new context that measures text as 'char_width' pixels per character and
+        /// 'height' pixels high.
+        pub fn new(char_width: f64, height: f64) -> Self {
+            Self {
+                char_width,
+                height,
+                extents: HashMap::new(),
+                scale: 1.0,
+                offset: Cell::new((0.0, 0.0)),
+                calls: RefCell::new(vec![]),
+                drawn_text: RefCell::new(vec![]),
+                measured: Cell::new(0),
+            }
+        }
+
+        /// Measure 's' as having the given (unscaled) extent rather than using the per character
+        /// width.
+        pub fn with_extent(mut self, s: impl Into<String>, extent: (f64, f64)) -> Self {
+            self.extents.insert(s.into(), extent);
+            self
+        }
+
+        /// The calls that have been made against this context so far, in order.
+        pub fn calls(&self) -> Vec<DrawCall> {
+            self.calls.borrow().clone()
+        }
+
+        /// Each piece of text that has been drawn along with the (x, y) position of the start
+        /// of the text itself: offsets, translations and left padding are all applied.
+        pub fn drawn_text(&self) -> Vec<(String, f64, f64)> {
+            self.drawn_text.borrow().clone()
+        }
+
+        /// The number of times that text has been measured using
+        /// [text_extent][DrawContext::text_extent].
+        pub fn times_measured(&self) -> usize {
+            self.measured.get()
+        }
+
+        /// Forget all calls that have been recorded so far.
+        pub fn clear_calls(&self) {
+            self.calls.borrow_mut().clear();
+            self.drawn_text.borrow_mut().clear();
+            self.measured.set(0);
+        }
+
+        fn record(&self, call: DrawCall) {
+            self.calls.borrow_mut().push(call);
+        }
+
+        fn extent(&self, s: &str) -> (f64, f64) {
+            let (w, h) = match self.extents.get(s) {
+                Some(&extent) => extent,
+                None => (s.chars().count() as f64 * self.char_width, self.height),
+            };
+
+            (w * self.scale, h * self.scale)
+        }
+    }
+
+    impl DrawContext for MockDrawContext {
+        fn font(&mut self, font_name: &str, point_size: i32) -> Result<()> {
+            self.record(DrawCall::Font(font_name.into(), point_size));
+            Ok(())
+        }
+
+        fn set_font_scale(&mut self, factor: f64) {
+            self.scale = factor;
+        }
+
+        fn font_scale(&self) -> f64 {
+            self.scale
+        }
+
+        fn color(&mut self, color: &Color) {
+            self.record(DrawCall::Color(*color));
+        }
+
+        fn clear(&mut self) {
+            self.record(DrawCall::Clear);
+        }
+
+        fn translate(&self, dx: f64, dy: f64) {
+            let (x, y) = self.offset.get();
+            self.offset.set((x + dx, y + dy));
+            self.record(DrawCall::Translate(dx, dy));
+        }
+
+        fn set_x_offset(&self, x: f64) {
+            let (_, y) = self.offset.get();
+            self.offset.set((x, y));
+            self.record(DrawCall::SetXOffset(x));
+        }
+
+        fn set_y_offset(&self, y: f64) {
+            let (x, _) = self.offset.get();
+            self.offset.set((x, y));
+            self.record(DrawCall::SetYOffset(y));
+        }
+
+        fn rectangle(&self, x: f64, y: f64, w: f64, h: f64) {
+            self.record(DrawCall::Rectangle(x, y, w, h));
+        }
+
+        fn polygon(&self, points: &[(f64, f64)]) {
+            self.record(DrawCall::Polygon(points.to_vec()));
+        }
+
+        fn text(&self, s: &str, h_offset: f64, padding: (f64, f64)) -> Result<(f64, f64)> {
+            let (x, y) = self.offset.get();
+            let (l, r) = padding;
+            let (w, h) = self.extent(s);
+            self.record(DrawCall::Text(s.into(), h_offset, padding));
+            self.drawn_text
+                .borrow_mut()
+                .push((s.into(), x + l, y + h_offset));
+
+            Ok((w + l + r, h))
+        }
+
+        fn text_extent(&self, s: &str) -> Result<(f64, f64)> {
+            self.measured.set(self.measured.get() + 1);
+            Ok(self.extent(s))
+        }
+
+        fn flush(&self) {
+            self.record(DrawCall::Flush);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::convert::TryFrom;
 
+    test_cases! {
+        isolate_bidi;
+        args: (s: &str, expected: &str);
+
+        case: empty => ("", "");
+        case: left_to_right => ("Firefox", "Firefox");
+        case: hebrew => ("שלום", "\u{2068}שלום\u{2069}");
+        case: arabic => ("مرحبا", "\u{2068}مرحبا\u{2069}");
+        case: mixed => ("שלום - Firefox", "\u{2068}שלום - Firefox\u{2069}");
+
+        body: {
+            assert_eq!(isolate_bidi(s), expected);
+        }
+    }
+
+    #[test]
+    fn text_extents_are_only_measured_once() {
+        let cache = TextExtentCache::new();
+        let measured = RefCell::new(0);
+        let measure = || {
+            *measured.borrow_mut() += 1;
+            Ok((10.0, 5.0))
+        };
+
+        assert_eq!(
+            cache.get_or_measure("mono", 12, "1", measure).unwrap(),
+            (10.0, 5.0)
+        );
+        assert_eq!(
+            cache.get_or_measure("mono", 12, "1", measure).unwrap(),
+            (10.0, 5.0)
+        );
+        assert_eq!(*measured.borrow(), 1);
+
+        cache.get_or_measure("mono", 24, "1", measure).unwrap();
+        cache.get_or_measure("sans", 12, "1", measure).unwrap();
+        cache.get_or_measure("mono", 12, "2", measure).unwrap();
+        assert_eq!(*measured.borrow(), 4);
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[test]
+    fn text_extent_cache_is_bounded() {
+        let cache = TextExtentCache::new();
+        for i in 0..=TEXT_EXTENT_CACHE_SIZE {
+            cache
+                .get_or_measure("mono", 12, &i.to_string(), || Ok((1.0, 1.0)))
+                .unwrap();
+        }
+
+        assert_eq!(cache.len(), 1);
+    }
+
     test_cases! {
         color_from_hex_rgba;
         args: (hex: u32, floats: (f64, f64, f64, f64));
@@ -0,0 +1,523 @@
+//! Short lived windows giving visual feedback on window manager actions
+//!
+//! Overlays are [Hook]s that draw an unmanaged window on top of the clients being managed in
+//! response to a trigger and hide it again once it is no longer needed.
+//!
+//! Example
+//! ```
+//! use penrose::{
+//!     core::{hooks::Hooks, xconnection::XConn},
+//!     draw::{Color, Draw, ErrorOverlay, RatioOverlay, TextStyle},
+//! };
+//!
+//! use std::{convert::TryFrom, time::Duration};
+//!
+//! # fn example<D: Draw + 'static, X: XConn>(drw: D, err_drw: D) -> penrose::Result<Hooks<X>> {
+//! let style = TextStyle {
+//!     font: "mono".to_string(),
+//!     point_size: 14,
+//!     fg: Color::try_from("#ebdbb2")?,
+//!     bg: Some(Color::try_from("#282828")?),
+//!     padding: (2.0, 2.0),
+//! };
+//! let highlight = Color::try_from("#458588")?;
+//!
+//! let hooks: Hooks<X> = vec![
+//!     Box::new(RatioOverlay::new(drw, &style, highlight, Duration::from_millis(750))),
+//!     Box::new(ErrorOverlay::new(err_drw, &style, Duration::from_secs(3))),
+//! ];
+//! # Ok(hooks)
+//! # }
+//! ```
+use crate::{
+    core::{
+        data_types::{Region, WinType},
+        hooks::Hook,
+        manager::WindowManager,
+        ring::Selector,
+        screen::Screen,
+        xconnection::{Atom, Prop, XConn, Xid},
+    },
+    draw::{Color, Draw, DrawContext, Result, TextStyle},
+};
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+const RATIO_OVERLAY_WIDTH: u32 = 240;
+const RATIO_OVERLAY_HEIGHT: u32 = 100;
+const RATIO_OVERLAY_PADDING: f64 = 12.0;
+const BADGE_PADDING: f64 = 4.0;
+const ERROR_OVERLAY_PADDING: f64 = 8.0;
+const ERROR_OVERLAY_MARGIN: u32 = 10;
+
+// An unmanaged window drawn on top of the clients being managed. The X window is created the first
+// time that it is shown and is then moved, resized and mapped again each time it is needed rather
+// than being recreated.
+pub(crate) struct OverlayWindow<D: Draw> {
+    drw: D,
+    style: TextStyle,
+    name: &'static str,
+    win_type: Atom,
+    win: Option<Xid>,
+}
+
+impl<D: Draw> fmt::Debug for OverlayWindow<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverlayWindow")
+            .field("drw", &stringify!(self.drw))
+            .field("style", &self.style)
+            .field("name", &self.name)
+            .field("win_type", &self.win_type)
+            .field("win", &self.win)
+            .finish()
+    }
+}
+
+impl<D: Draw> OverlayWindow<D> {
+    // 'name' is set as the window name and class so that the window can be matched by
+    // compositor rules
+    pub(crate) fn new(mut drw: D, style: &TextStyle, name: &'static str, win_type: Atom) -> Self {
+        drw.register_font(&style.font);
+
+        Self {
+            drw,
+            style: style.clone(),
+            name,
+            win_type,
+            win: None,
+        }
+    }
+
+    pub(crate) fn style(&self) -> &TextStyle {
+        &self.style
+    }
+
+    pub(crate) fn drw(&self) -> &D {
+        &self.drw
+    }
+
+    // The ID of the X window, if it has been created yet
+    pub(crate) fn win(&self) -> Option<Xid> {
+        self.win
+    }
+
+    // The size of each of 'labels' when drawn in the overlay font
+    pub(crate) fn text_extents(&self, labels: &[&str]) -> Result<Vec<(f64, f64)>> {
+        let mut ctx = self.drw.temp_context(1, 1)?;
+        ctx.font(&self.style.font, self.style.point_size)?;
+
+        labels.iter().map(|l| ctx.text_extent(l)).collect()
+    }
+
+    // Move the window to 'r' and return a cleared context for drawing to it, creating the window
+    // if needed. The window is shown once 'flush' is called.
+    pub(crate) fn show_at(&mut self, r: Region) -> Result<D::Ctx> {
+        let id = match self.win {
+            Some(id) => {
+                self.drw.position_window(id, r)?;
+                id
+            }
+            None => {
+                let ty = WinType::InputOutput(self.win_type);
+                let id = self.drw.new_window(ty, r, false)?;
+                let p = Prop::UTF8String(vec![self.name.to_string()]);
+                for atom in &[Atom::NetWmName, Atom::WmName, Atom::WmClass] {
+                    self.drw.change_prop(id, atom.as_ref(), p.clone())?;
+                }
+                self.win = Some(id);
+                id
+            }
+        };
+
+        let mut ctx = self.drw.context_for(id)?;
+        ctx.clear();
+        ctx.color(&self.style.bg.unwrap_or_else(|| 0x000000.into()));
+        let (_, _, w, h) = r.values();
+        ctx.rectangle(0.0, 0.0, w as f64, h as f64);
+        ctx.font(&self.style.font, self.style.point_size)?;
+        ctx.color(&self.style.fg);
+
+        Ok(ctx)
+    }
+
+    pub(crate) fn flush(&self) -> Result<()> {
+        match self.win {
+            Some(id) => self.drw.flush(id),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn hide(&mut self) -> Result<()> {
+        if let Some(id) = self.win {
+            self.drw.unmap_client(id)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Briefly show the main ratio of the active layout each time that it is changed.
+///
+/// The overlay is centered on the screen showing the workspace that was modified and contains the
+/// new ratio as a percentage above a diagram of the split between the main and secondary areas.
+/// It is hidden again once `timeout` has passed without any further changes.
+pub struct RatioOverlay<D: Draw> {
+    overlay: OverlayWindow<D>,
+    highlight: Color,
+    timeout: Duration,
+    hide_at: Option<Instant>,
+}
+
+impl<D: Draw> fmt::Debug for RatioOverlay<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RatioOverlay")
+            .field("overlay", &self.overlay)
+            .field("highlight", &self.highlight)
+            .field("timeout", &self.timeout)
+            .field("hide_at", &self.hide_at)
+            .finish()
+    }
+}
+
+impl<D: Draw> RatioOverlay<D> {
+    /// Create a new RatioOverlay that is hidden `timeout` after the ratio was last changed
+    pub fn new(drw: D, style: &TextStyle, highlight: impl Into<Color>, timeout: Duration) -> Self {
+        Self {
+            overlay: OverlayWindow::new(
+                drw,
+                style,
+                "penrose-ratio-overlay",
+                Atom::NetWindowTypeNotification,
+            ),
+            highlight: highlight.into(),
+            timeout,
+            hide_at: None,
+        }
+    }
+
+    fn show(&mut self, screen_region: Region, ratio: f32) -> Result<()> {
+        let (sx, sy, sw, sh) = screen_region.values();
+        let (w, h) = (RATIO_OVERLAY_WIDTH, RATIO_OVERLAY_HEIGHT);
+        let x = sx + sw.saturating_sub(w) / 2;
+        let y = sy + sh.saturating_sub(h) / 2;
+
+        let mut ctx = self.overlay.show_at(Region::new(x, y, w, h))?;
+        let (w, h) = (w as f64, h as f64);
+        let pad = RATIO_OVERLAY_PADDING;
+        let style = self.overlay.style();
+        let bg = style.bg.unwrap_or_else(|| 0x000000.into());
+
+        let label = format!("{:.0}%", ratio * 100.0);
+        let (tw, _) = ctx.text_extent(&label)?;
+        let text_offset = ((w - tw) / 2.0).max(0.0);
+        ctx.translate(text_offset, pad);
+        ctx.text(&label, 0.0, (0.0, 0.0))?;
+        ctx.translate(-text_offset, -pad);
+
+        // The main and secondary areas with the split line between them
+        let (dx, dy, dw, dh) = (pad, h / 2.0, w - 2.0 * pad, h / 2.0 - pad);
+        let split = dw * ratio as f64;
+        ctx.color(&style.fg);
+        ctx.rectangle(dx, dy, dw, dh);
+        ctx.color(&self.highlight);
+        ctx.rectangle(dx, dy, split, dh);
+        ctx.color(&bg);
+        ctx.rectangle(dx + split - 1.0, dy, 2.0, dh);
+
+        ctx.flush();
+        self.overlay.flush()
+    }
+
+    fn hide(&mut self) -> Result<()> {
+        self.hide_at = None;
+        self.overlay.hide()
+    }
+}
+
+impl<D, X> Hook<X> for RatioOverlay<D>
+where
+    D: Draw,
+    X: XConn,
+{
+    fn main_ratio_changed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        ratio: f32,
+    ) -> crate::Result<()> {
+        let region = match wm.screen(&Selector::Condition(&|s: &Screen| s.wix == workspace_index)) {
+            Some(s) => s.region(true),
+            None => return Ok(()),
+        };
+
+        self.show(region, ratio)?;
+        self.hide_at = Some(Instant::now() + self.timeout);
+        wm.wake_after(self.timeout);
+
+        Ok(())
+    }
+
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        if matches!(self.hide_at, Some(t) if t <= Instant::now()) {
+            self.hide()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Show a count badge on the focused client while the current layout is hiding clients.
+///
+/// When a layout such as [monocle][1] only shows some of the tiled clients on the active
+/// workspace, the badge is drawn in the top right corner of the focused client as the position
+/// of that client followed by the total number of tiled clients (e.g. `2/5`) so that it is clear
+/// that there are more clients behind it. The badge is removed as soon as all clients are being
+/// shown again.
+///
+/// See [CurrentLayout][2] for showing the number of hidden clients in a status bar instead.
+///
+/// [1]: crate::core::layout::monocle
+/// [2]: crate::draw::widget::CurrentLayout
+pub struct HiddenClientsBadge<D: Draw> {
+    overlay: OverlayWindow<D>,
+    shown: Option<(Xid, String, Region)>,
+}
+
+impl<D: Draw> fmt::Debug for HiddenClientsBadge<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HiddenClientsBadge")
+            .field("overlay", &self.overlay)
+            .field("shown", &self.shown)
+            .finish()
+    }
+}
+
+impl<D: Draw> HiddenClientsBadge<D> {
+    /// Create a new HiddenClientsBadge drawn using `style`
+    pub fn new(drw: D, style: &TextStyle) -> Self {
+        Self {
+            overlay: OverlayWindow::new(
+                drw,
+                style,
+                "penrose-hidden-clients-badge",
+                Atom::NetWindowTypeNotification,
+            ),
+            shown: None,
+        }
+    }
+
+    fn update<X: XConn>(&mut self, wm: &WindowManager<X>) -> Result<()> {
+        let wix = wm.active_workspace_index();
+        let badge = match (wm.hidden_client_count(wix), wm.focused_client_id()) {
+            (0, _) | (_, None) => None,
+            (_, Some(id)) => match badge_text(wm, wix, id) {
+                Some(txt) => Some((id, txt, wm.conn().client_geometry(id)?)),
+                None => None,
+            },
+        };
+
+        // The badge also needs moving if the focused client has been resized or moved
+        if badge == self.shown {
+            return Ok(());
+        }
+
+        match badge {
+            Some((_, ref txt, region)) => self.show(region, txt)?,
+            None => self.overlay.hide()?,
+        }
+        self.shown = badge;
+
+        Ok(())
+    }
+
+    fn show(&mut self, client_region: Region, txt: &str) -> Result<()> {
+        let (tw, th) = self.overlay.text_extents(&[txt])?[0];
+        let (w, h) = (tw + 2.0 * BADGE_PADDING, th + 2.0 * BADGE_PADDING);
+
+        let (cx, cy, cw, _) = client_region.values();
+        let x = cx + cw.saturating_sub(w as u32);
+        let r = Region::new(x, cy, w as u32, h as u32);
+        let ctx = self.overlay.show_at(r)?;
+        ctx.text(txt, 0.0, (BADGE_PADDING, BADGE_PADDING))?;
+
+        ctx.flush();
+        self.overlay.flush()
+    }
+}
+
+// "position/total" for the focused client among the tiled clients on a workspace
+fn badge_text<X: XConn>(wm: &WindowManager<X>, wix: usize, focused: Xid) -> Option<String> {
+    let tiled: Vec<Xid> = wm
+        .workspace(&Selector::Index(wix))?
+        .client_ids()
+        .into_iter()
+        .filter(|&id| matches!(wm.client(&Selector::WinId(id)), Some(c) if !c.floating))
+        .collect();
+    let position = tiled.iter().position(|&id| id == focused)?;
+
+    Some(format!("{}/{}", position + 1, tiled.len()))
+}
+
+impl<D, X> Hook<X> for HiddenClientsBadge<D>
+where
+    D: Draw,
+    X: XConn,
+{
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: usize,
+        _: usize,
+    ) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+
+    fn focus_change(&mut self, wm: &mut WindowManager<X>, _: Xid) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: usize,
+        _: usize,
+    ) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+
+    fn remove_client(&mut self, wm: &mut WindowManager<X>, _: Xid) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+}
+
+/// Briefly show the error message each time that an action fails.
+///
+/// Errors returned from key bindings and other actions are normally only passed to the
+/// [ErrorHandler][crate::ErrorHandler], which makes problems such as a key binding spawning a
+/// program that is not installed easy to miss. This overlay shows the error message along the
+/// top of the focused screen so that they can be spotted, and hides it again once `timeout` has
+/// passed without any further errors.
+pub struct ErrorOverlay<D: Draw> {
+    overlay: OverlayWindow<D>,
+    timeout: Duration,
+    hide_at: Option<Instant>,
+}
+
+impl<D: Draw> fmt::Debug for ErrorOverlay<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorOverlay")
+            .field("overlay", &self.overlay)
+            .field("timeout", &self.timeout)
+            .field("hide_at", &self.hide_at)
+            .finish()
+    }
+}
+
+impl<D: Draw> ErrorOverlay<D> {
+    /// Create a new ErrorOverlay that is hidden `timeout` after the last error was raised
+    pub fn new(drw: D, style: &TextStyle, timeout: Duration) -> Self {
+        Self {
+            overlay: OverlayWindow::new(
+                drw,
+                style,
+                "penrose-error-overlay",
+                Atom::NetWindowTypeNotification,
+            ),
+            timeout,
+            hide_at: None,
+        }
+    }
+
+    fn show(&mut self, screen_region: Region, msg: &str) -> Result<()> {
+        let (tw, th) = self.overlay.text_extents(&[msg])?[0];
+        let pad = ERROR_OVERLAY_PADDING;
+
+        let (sx, sy, sw, _) = screen_region.values();
+        let max_w = sw.saturating_sub(2 * ERROR_OVERLAY_MARGIN);
+        let w = ((tw + 2.0 * pad).ceil() as u32).min(max_w);
+        let h = (th + 2.0 * pad).ceil() as u32;
+        let x = sx + sw.saturating_sub(w) / 2;
+
+        let r = Region::new(x, sy + ERROR_OVERLAY_MARGIN, w, h);
+        let ctx = self.overlay.show_at(r)?;
+        ctx.text(msg, 0.0, (pad, pad))?;
+
+        ctx.flush();
+        self.overlay.flush()
+    }
+
+    fn hide(&mut self) -> Result<()> {
+        self.hide_at = None;
+        self.overlay.hide()
+    }
+}
+
+impl<D, X> Hook<X> for ErrorOverlay<D>
+where
+    D: Draw,
+    X: XConn,
+{
+    fn error_raised(&mut self, wm: &mut WindowManager<X>, msg: &str) -> crate::Result<()> {
+        let region = match wm.screen(&Selector::Focused) {
+            Some(s) => s.region(true),
+            None => return Ok(()),
+        };
+
+        // The overlay is a single line so multi-line errors are flattened
+        let msg = msg.lines().collect::<Vec<_>>().join(" ");
+        self.show(region, &msg)?;
+        self.hide_at = Some(Instant::now() + self.timeout);
+        wm.wake_after(self.timeout);
+
+        Ok(())
+    }
+
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        if matches!(self.hide_at, Some(t) if t <= Instant::now()) {
+            self.hide()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{__test_helpers::*, core::xconnection::XEvent};
+    use std::collections::HashMap;
+
+    // Clients 0, 1 and 2 all on the first workspace in the order [2, 1, 0]
+    fn wm_with_three_clients() -> TestWM {
+        let mut events = n_clients(3);
+        events.push(XEvent::KeyPress(EXIT_CODE));
+        let mut wm = test_windowmanager(1, events);
+        wm.grab_keys_and_run(test_key_bindings(), HashMap::new())
+            .unwrap();
+
+        wm
+    }
+
+    test_cases! {
+        badge_text;
+        args: (floating: &[Xid], focused: Xid, expected: Option<&str>);
+
+        case: first_client => (&[], 2, Some("1/3"));
+        case: last_client => (&[], 0, Some("3/3"));
+        case: floating_clients_are_not_counted => (&[1], 0, Some("2/2"));
+        case: floating_focused_client => (&[1], 1, None);
+        case: unknown_client => (&[], 42, None);
+
+        body: {
+            let mut wm = wm_with_three_clients();
+            for &id in floating {
+                wm.client_mut(&Selector::WinId(id)).unwrap().set_floating(true);
+            }
+
+            assert_eq!(badge_text(&wm, 0, focused).as_deref(), expected);
+        }
+    }
+}
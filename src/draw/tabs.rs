@@ -0,0 +1,275 @@
+//! A tab bar for tabbed layouts
+//!
+//! Example
+//! ```
+//! use penrose::{
+//!     contrib::layouts::tabbed,
+//!     core::{
+//!         hooks::Hooks,
+//!         layout::{Layout, LayoutConf},
+//!         xconnection::XConn,
+//!     },
+//!     draw::{Color, Draw, TabBar, TextStyle},
+//! };
+//!
+//! use std::convert::TryFrom;
+//!
+//! # fn example<D: Draw + 'static, X: XConn>(drw: D) -> penrose::Result<(Layout, Hooks<X>)> {
+//! let style = TextStyle {
+//!     font: "mono".to_string(),
+//!     point_size: 10,
+//!     fg: Color::try_from("#ebdbb2")?,
+//!     bg: Some(Color::try_from("#282828")?),
+//!     padding: (6.0, 4.0),
+//! };
+//!
+//! // the layout needs to be re-applied when focus changes to show the newly focused client
+//! let conf = LayoutConf {
+//!     follow_focus: true,
+//!     ..Default::default()
+//! };
+//! let layout = Layout::new("[tabs]", conf, tabbed, 1, 0.6);
+//!
+//! let highlight = Color::try_from("#458588")?;
+//! let hooks: Hooks<X> = vec![Box::new(TabBar::new(drw, &style, highlight, "[tabs]"))];
+//! # Ok((layout, hooks))
+//! # }
+//! ```
+use crate::{
+    core::{
+        bindings::MouseButton,
+        data_types::{Point, Region},
+        hooks::Hook,
+        manager::WindowManager,
+        ring::Selector,
+        xconnection::{Atom, ClientAttr, XConn, Xid},
+    },
+    draw::{overlay::OverlayWindow, Color, Draw, DrawContext, Result, TextStyle},
+};
+
+use std::fmt;
+
+/// The height in pixels of the tab bar drawn by a [TabBar]. The [tabbed][1] layout leaves this
+/// much space at the top of the screen for it.
+///
+/// [1]: crate::contrib::layouts::tabbed
+pub const TAB_BAR_HEIGHT: u32 = 22;
+
+/// Draws a strip of tabs, one for each tiled client, above workspaces using the [tabbed][1]
+/// layout.
+///
+/// Each tab shows the title of its client with the focused client's tab highlighted. Left
+/// clicking on a tab focuses its client and the tabs follow the focus bindings you already have
+/// (such as [cycle_client][2]) so long as the layout has
+/// [follow_focus][crate::core::layout::LayoutConf::follow_focus] set. Workspaces are identified
+/// as being tabbed by the symbol of their current layout.
+///
+/// Only one tab bar is shown at a time: if tabbed workspaces are visible on more than one screen
+/// then the bar is drawn on the screen that was most recently laid out.
+///
+/// [1]: crate::contrib::layouts::tabbed
+/// [2]: crate::core::manager::WindowManager::cycle_client
+pub struct TabBar<D: Draw> {
+    overlay: OverlayWindow<D>,
+    highlight: Color,
+    symbol: String,
+    shown: Option<(usize, usize)>, // workspace and screen index
+    tabs: Vec<(Xid, Region)>,
+    click_window: Option<Xid>, // the window that button events have been selected on
+}
+
+impl<D: Draw> fmt::Debug for TabBar<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TabBar")
+            .field("overlay", &self.overlay)
+            .field("highlight", &self.highlight)
+            .field("symbol", &self.symbol)
+            .field("shown", &self.shown)
+            .field("tabs", &self.tabs)
+            .field("click_window", &self.click_window)
+            .finish()
+    }
+}
+
+impl<D: Draw> TabBar<D> {
+    /// Create a new TabBar drawn using `style` that is shown for workspaces whose layout has the
+    /// symbol `layout_symbol`. The tab of the focused client is drawn with a `highlight`
+    /// background.
+    pub fn new(
+        drw: D,
+        style: &TextStyle,
+        highlight: impl Into<Color>,
+        layout_symbol: impl Into<String>,
+    ) -> Self {
+        Self {
+            overlay: OverlayWindow::new(drw, style, "penrose-tab-bar", Atom::NetWindowTypeToolbar),
+            highlight: highlight.into(),
+            symbol: layout_symbol.into(),
+            shown: None,
+            tabs: vec![],
+            click_window: None,
+        }
+    }
+
+    fn update<X: XConn>(&mut self, wm: &WindowManager<X>, wix: usize, i: usize) -> Result<()> {
+        let ws = match wm.workspace(&Selector::Index(wix)) {
+            Some(ws) if ws.layout_symbol() == self.symbol => ws,
+            _ => return self.hide_on(i),
+        };
+        let screen = match wm.screen(&Selector::Index(i)) {
+            Some(s) => s.region(true),
+            None => return self.hide_on(i),
+        };
+
+        let titles: Vec<(Xid, String)> = ws
+            .client_ids()
+            .into_iter()
+            .filter_map(|id| wm.client(&Selector::WinId(id)))
+            .filter(|c| !c.floating)
+            .map(|c| (c.id(), c.wm_name().to_string()))
+            .collect();
+        if titles.is_empty() {
+            return self.hide_on(i);
+        }
+
+        self.shown = Some((wix, i));
+        self.draw(tab_bar_region(screen), &titles, ws.focused_client())
+    }
+
+    fn draw(&mut self, bar: Region, titles: &[(Xid, String)], focused: Option<Xid>) -> Result<()> {
+        let regions = bar.as_columns(titles.len() as u32);
+        let style = self.overlay.style().clone();
+        let bg = style.bg.unwrap_or_else(|| 0x000000.into());
+        let (_, th) = self.overlay.text_extents(&["|"])?[0];
+        let h_offset = ((bar.h as f64 - th) / 2.0).max(0.0);
+
+        let mut ctx = self.overlay.show_at(bar)?;
+        for ((id, title), r) in titles.iter().zip(regions.iter()) {
+            ctx.set_x_offset((r.x - bar.x) as f64);
+            ctx.color(if Some(*id) == focused {
+                &self.highlight
+            } else {
+                &bg
+            });
+            ctx.rectangle(0.0, 0.0, r.w as f64, r.h as f64);
+            ctx.color(&style.fg);
+            ctx.text(title, h_offset, style.padding)?;
+        }
+
+        ctx.flush();
+        self.overlay.flush()?;
+        self.tabs = titles.iter().map(|(id, _)| *id).zip(regions).collect();
+
+        Ok(())
+    }
+
+    // Ask for button events on the tab bar window once it has been created
+    fn select_clicks<X: XConn>(&mut self, wm: &WindowManager<X>) -> crate::Result<()> {
+        let win = self.overlay.win();
+        if let Some(id) = win.filter(|&id| self.click_window != Some(id)) {
+            wm.conn()
+                .set_client_attributes(id, &[ClientAttr::ButtonEventMask])?;
+            self.click_window = win;
+        }
+
+        Ok(())
+    }
+
+    // Hide the bar if it is currently being shown on screen 'i'
+    fn hide_on(&mut self, i: usize) -> Result<()> {
+        if matches!(self.shown, Some((_, s)) if s == i) {
+            self.shown = None;
+            self.tabs.clear();
+            self.overlay.hide()?;
+        }
+
+        Ok(())
+    }
+}
+
+// The strip along the top of the screen that the tabbed layout leaves free
+fn tab_bar_region(screen: Region) -> Region {
+    let (x, y, w, h) = screen.values();
+    Region::new(x, y, w, TAB_BAR_HEIGHT.min(h))
+}
+
+// The client whose tab is under 'p', if there is one
+fn tab_at(tabs: &[(Xid, Region)], p: Point) -> Option<Xid> {
+    tabs.iter()
+        .find(|(_, r)| r.contains_point(&p))
+        .map(|(id, _)| *id)
+}
+
+impl<D, X> Hook<X> for TabBar<D>
+where
+    D: Draw,
+    X: XConn,
+{
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        screen_index: usize,
+    ) -> crate::Result<()> {
+        self.update(wm, workspace_index, screen_index)?;
+        self.select_clicks(wm)
+    }
+
+    fn client_name_updated(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        _: &str,
+        _: bool,
+    ) -> crate::Result<()> {
+        match self.shown {
+            Some((wix, i)) if self.tabs.iter().any(|(t, _)| *t == id) => {
+                Ok(self.update(wm, wix, i)?)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn window_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        button: MouseButton,
+        point: Point,
+    ) -> crate::Result<()> {
+        if button != MouseButton::Left || self.click_window != Some(id) {
+            return Ok(());
+        }
+
+        if let Some(id) = tab_at(&self.tabs, point) {
+            wm.focus_client(&Selector::WinId(id))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        tab_at;
+        args: (p: Point, expected: Option<Xid>);
+
+        case: first_tab => (Point::new(10, 5), Some(1));
+        case: last_tab => (Point::new(250, 5), Some(3));
+        case: below_tabs => (Point::new(10, 50), None);
+
+        body: {
+            let tabs: Vec<(Xid, Region)> = Region::new(0, 0, 300, TAB_BAR_HEIGHT)
+                .as_columns(3)
+                .into_iter()
+                .enumerate()
+                .map(|(i, r)| (i as Xid + 1, r))
+                .collect();
+
+            assert_eq!(tab_at(&tabs, p), expected);
+        }
+    }
+}
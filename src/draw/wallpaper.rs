@@ -0,0 +1,324 @@
+//! Wallpapers drawn onto the root window by penrose
+//!
+//! Workspaces can be given a [Background] using [workspace_backgrounds][1] or
+//! [set_workspace_background][2]. Background colors are set by the [WindowManager] itself but
+//! images need to be drawn, which is done by the [Wallpapers] [Hook]: it shows the image of the
+//! workspace on each screen, falling back to a (possibly different) wallpaper for each screen,
+//! and redraws them whenever the connected screens change or a different workspace is shown.
+//!
+//! Example
+//! ```
+//! use penrose::{
+//!     core::{config::Config, hooks::Hooks, xconnection::XConn},
+//!     draw::{Background, Color, Draw, Wallpaper, WallpaperMode, Wallpapers},
+//! };
+//!
+//! # fn example<D: Draw + 'static, X: XConn>(drw: D) -> (Config, Hooks<X>) {
+//! // The first workspace shows its own image on whichever screen it is on and the second sets a
+//! // solid color. All other workspaces show the wallpaper of their screen.
+//! let config = Config::default()
+//!     .builder()
+//!     .workspace_backgrounds(vec![
+//!         Background::from(Wallpaper::new("/home/me/wallpapers/code.png", WallpaperMode::Fill)),
+//!         Background::from(Color::from(0x282828)),
+//!     ])
+//!     .build()
+//!     .unwrap();
+//!
+//! let wallpapers = Wallpapers::new(
+//!     drw,
+//!     vec![
+//!         Wallpaper::new("/home/me/wallpapers/left.png", WallpaperMode::Fill),
+//!         Wallpaper::new("/home/me/wallpapers/right.png", WallpaperMode::Fit),
+//!     ],
+//! );
+//! let hooks: Hooks<X> = vec![Box::new(wallpapers)];
+//! # (config, hooks)
+//! # }
+//! ```
+//!
+//! [1]: crate::core::config::Config::workspace_backgrounds
+//! [2]: crate::core::manager::WindowManager::set_workspace_background
+use crate::{
+    core::{
+        data_types::Region, hooks::Hook, manager::WindowManager, ring::Selector, xconnection::XConn,
+    },
+    draw::{Color, Draw, Result},
+};
+
+use std::{fmt, path::PathBuf};
+
+/// How a [Wallpaper] image is sized to cover the screen it is shown on
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WallpaperMode {
+    /// Scale the image to cover the whole screen, cropping it if the aspect ratios differ
+    Fill,
+    /// Scale the image to fit within the screen, leaving borders if the aspect ratios differ
+    Fit,
+    /// Stretch the image to the size of the screen, ignoring its aspect ratio
+    Stretch,
+    /// Show the image at its original size in the center of the screen
+    Center,
+    /// Repeat the image at its original size starting from the top left of the screen
+    Tile,
+}
+
+impl WallpaperMode {
+    /// The (x, y) position and (x, y) scale factors that an image of size (w, h) should be
+    /// drawn with so that it covers 'screen' using this mode.
+    ///
+    /// For [Tile][WallpaperMode::Tile] this is the position of the first copy of the image.
+    pub fn placement(&self, w: u32, h: u32, screen: Region) -> (f64, f64, f64, f64) {
+        let (sx, sy, sw, sh) = screen.values();
+        let (sx, sy, sw, sh) = (sx as f64, sy as f64, sw as f64, sh as f64);
+        let (w, h) = (w.max(1) as f64, h.max(1) as f64);
+
+        let centered = |scale: f64| {
+            let x = sx + (sw - w * scale) / 2.0;
+            let y = sy + (sh - h * scale) / 2.0;
+            (x, y, scale, scale)
+        };
+
+        match self {
+            Self::Fill => centered((sw / w).max(sh / h)),
+            Self::Fit => centered((sw / w).min(sh / h)),
+            Self::Stretch => (sx, sy, sw / w, sh / h),
+            Self::Center => centered(1.0),
+            Self::Tile => (sx, sy, 1.0, 1.0),
+        }
+    }
+}
+
+/// An image to use as the wallpaper of a screen, along with how it should be sized to cover it.
+///
+/// Only PNG images are currently supported by [XcbDraw][1].
+///
+/// [1]: crate::xcb::XcbDraw
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Wallpaper {
+    /// The path to the image file
+    pub path: PathBuf,
+    /// How the image is sized to cover the screen
+    pub mode: WallpaperMode,
+}
+
+impl Wallpaper {
+    /// Create a new Wallpaper using the image at 'path'
+    pub fn new(path: impl Into<PathBuf>, mode: WallpaperMode) -> Self {
+        Self {
+            path: path.into(),
+            mode,
+        }
+    }
+}
+
+/// The background of the root window while a [Workspace][1] is shown.
+///
+/// See [set_workspace_background][2] for details.
+///
+/// [1]: crate::core::workspace::Workspace
+/// [2]: crate::core::manager::WindowManager::set_workspace_background
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+    /// Fill the root window with a solid color
+    Color(Color),
+    /// Draw an image on the screen showing the workspace using the [Wallpapers] hook
+    Image(Wallpaper),
+}
+
+impl From<Color> for Background {
+    fn from(c: Color) -> Self {
+        Self::Color(c)
+    }
+}
+
+impl From<Wallpaper> for Background {
+    fn from(w: Wallpaper) -> Self {
+        Self::Image(w)
+    }
+}
+
+/// Draw a wallpaper on each screen, in place of an external program such as feh or nitrogen.
+///
+/// Screens show the image [background][1] of the workspace they are showing if it has one,
+/// otherwise they are given the wallpaper at their index, with any additional screens using the
+/// last wallpaper in the list.
+///
+/// Wallpapers are redrawn whenever screens are added, removed or resized (such as following a
+/// RandR change), whenever a different workspace is shown and whenever workspaces are added or
+/// removed. While the focused workspace has a background color that color is shown instead.
+///
+/// [1]: crate::core::config::Config::workspace_backgrounds
+pub struct Wallpapers<D: Draw> {
+    drw: D,
+    screens: Vec<Wallpaper>,
+    current: Vec<(Region, Wallpaper)>,
+}
+
+impl<D: Draw> fmt::Debug for Wallpapers<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wallpapers")
+            .field("drw", &stringify!(self.drw))
+            .field("screens", &self.screens)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl<D: Draw> Wallpapers<D> {
+    /// Create a new Wallpapers hook showing 'screens' on the screen with the matching index
+    pub fn new(drw: D, screens: Vec<Wallpaper>) -> Self {
+        Self {
+            drw,
+            screens,
+            current: vec![],
+        }
+    }
+
+    fn update<X: XConn>(&mut self, wm: &mut WindowManager<X>) -> Result<()> {
+        let shown: Vec<(Region, Option<Background>)> = wm
+            .focused_workspaces()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, wix)| {
+                let background = wm
+                    .workspace(&Selector::Index(wix))
+                    .and_then(|ws| ws.background().cloned());
+                wm.screen(&Selector::Index(i))
+                    .map(|s| (s.region(false), background))
+            })
+            .collect();
+
+        // A background color covers the whole root window so the wallpapers will need to be
+        // drawn again when a different workspace is focused
+        let focused = shown.get(wm.active_screen_index());
+        if let Some((_, Some(Background::Color(_)))) = focused {
+            self.current.clear();
+            return Ok(());
+        }
+
+        let wallpapers = wallpapers_for(&shown, &self.screens);
+        if wallpapers.is_empty() || wallpapers == self.current {
+            return Ok(());
+        }
+
+        self.drw.set_wallpapers(&wallpapers)?;
+        self.current = wallpapers;
+        wm.invalidate_root_background();
+
+        Ok(())
+    }
+}
+
+impl<D: Draw, X: XConn> Hook<X> for Wallpapers<D> {
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+
+    fn screens_updated(&mut self, wm: &mut WindowManager<X>, _: &[Region]) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: usize,
+        _: usize,
+    ) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+
+    // Backgrounds are held by the workspaces themselves so they follow them as workspaces are
+    // added and removed, but the workspaces being shown may have changed
+    fn workspaces_updated(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: &[&str],
+        _: usize,
+    ) -> crate::Result<()> {
+        Ok(self.update(wm)?)
+    }
+}
+
+// The wallpaper to draw on each screen given the region of the screen and the background of the
+// workspace it shows
+fn wallpapers_for(
+    shown: &[(Region, Option<Background>)],
+    screens: &[Wallpaper],
+) -> Vec<(Region, Wallpaper)> {
+    shown
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (r, background))| {
+            let image = match background {
+                Some(Background::Image(w)) => Some(w),
+                _ => None,
+            };
+
+            image
+                .or_else(|| screens.get(i))
+                .or_else(|| screens.last())
+                .map(|w| (*r, w.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        placement;
+        args: (mode: WallpaperMode, img: (u32, u32), expected: (f64, f64, f64, f64));
+
+        case: fill_crops_the_longer_side => (WallpaperMode::Fill, (100, 100), (0.0, -100.0, 4.0, 4.0));
+        case: fit_leaves_borders => (WallpaperMode::Fit, (100, 100), (100.0, 0.0, 2.0, 2.0));
+        case: stretch_ignores_aspect_ratio => (WallpaperMode::Stretch, (100, 100), (0.0, 0.0, 4.0, 2.0));
+        case: center_keeps_the_original_size => (WallpaperMode::Center, (100, 100), (150.0, 50.0, 1.0, 1.0));
+        case: tile_starts_at_the_screen_origin => (WallpaperMode::Tile, (100, 100), (0.0, 0.0, 1.0, 1.0));
+
+        body: {
+            let screen = Region::new(0, 0, 400, 200);
+            assert_eq!(mode.placement(img.0, img.1, screen), expected);
+        }
+    }
+
+    #[test]
+    fn placement_is_relative_to_the_screen_position() {
+        let screen = Region::new(1366, 0, 400, 200);
+        let placement = WallpaperMode::Center.placement(100, 100, screen);
+
+        assert_eq!(placement, (1516.0, 50.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn workspace_images_take_priority_over_screen_wallpapers() {
+        let (a, b, c) = (
+            Wallpaper::new("a.png", WallpaperMode::Fill),
+            Wallpaper::new("b.png", WallpaperMode::Fill),
+            Wallpaper::new("c.png", WallpaperMode::Fit),
+        );
+        let r = |i: u32| Region::new(i * 100, 0, 100, 100);
+        let shown = vec![
+            (r(0), None),
+            (r(1), Some(c.clone().into())),
+            (r(2), Some(Color::from(0xff0000).into())),
+        ];
+
+        let wallpapers = wallpapers_for(&shown, &[a.clone(), b.clone()]);
+
+        // the final screen has no wallpaper of its own so it reuses the last one
+        assert_eq!(wallpapers, vec![(r(0), a), (r(1), c), (r(2), b)]);
+    }
+
+    #[test]
+    fn screens_are_left_alone_without_any_wallpapers() {
+        let shown = vec![(Region::new(0, 0, 100, 100), None)];
+
+        assert!(wallpapers_for(&shown, &[]).is_empty());
+    }
+}
@@ -1,21 +1,29 @@
 //! Widgets intended for use in statusbars
 use crate::{
     core::{
-        data_types::Region,
+        bindings::{ClickKind, MouseButton},
+        data_types::{Point, Region},
         hooks::Hook,
+        input::LockState,
         manager::WindowManager,
-        ring::Selector,
+        ring::{Direction, Selector},
         xconnection::{XConn, Xid},
     },
-    draw::{widget::Text, Color, DrawContext, Result, TextStyle, Widget},
+    draw::{
+        widget::{Segment, SegmentedText, Text},
+        Color, DrawContext, Result, TextStyle, Widget,
+    },
 };
 
+use std::{collections::HashMap, fmt};
+
 const PADDING: f64 = 3.0;
 
 #[derive(Clone, Debug, PartialEq)]
 struct WSMeta {
     name: String,
     occupied: bool,
+    urgent: bool,
     extent: (f64, f64),
 }
 
@@ -25,12 +33,26 @@ fn meta_from_names(names: &[String]) -> Vec<WSMeta> {
         .map(|s| WSMeta {
             name: s.clone(),
             occupied: false,
+            urgent: false,
             extent: (0.0, 0.0),
         })
         .collect()
 }
 
+/// An action taken by a [Workspaces] widget when one of its workspaces is clicked on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WorkspaceClickAction {
+    /// Focus the workspace on the active screen
+    Focus,
+    /// Move the focused client to the workspace
+    MoveFocusedClient,
+}
+
 /// A simple workspace indicator for a status bar
+///
+/// Left clicking on a workspace focuses it and pressing and holding the left button over a
+/// workspace moves the focused client there. The action taken for each [ClickKind] can be
+/// changed using [set_click_action][Workspaces::set_click_action].
 #[derive(Clone, Debug, PartialEq)]
 pub struct Workspaces {
     workspaces: Vec<WSMeta>,
@@ -39,10 +61,12 @@ pub struct Workspaces {
     focused_ws: Vec<usize>, // focused ws per screen
     require_draw: bool,
     extent: Option<(f64, f64)>,
+    extent_scale: f64,
     fg_1: Color,
     fg_2: Color,
     bg_1: Color,
     bg_2: Color,
+    click_actions: HashMap<ClickKind, WorkspaceClickAction>,
 }
 
 impl Workspaces {
@@ -60,13 +84,42 @@ impl Workspaces {
             focused_ws: vec![], // set in startup hook
             require_draw: false,
             extent: None,
+            extent_scale: 1.0,
             fg_1: style.fg,
             fg_2: empty_fg.into(),
             bg_1: highlight.into(),
             bg_2: style.bg.unwrap_or_else(|| 0x000000.into()),
+            click_actions: vec![
+                (ClickKind::Single, WorkspaceClickAction::Focus),
+                (ClickKind::Hold, WorkspaceClickAction::MoveFocusedClient),
+            ]
+            .into_iter()
+            .collect(),
         }
     }
 
+    /// Set the action taken when a workspace is left clicked in the given way, or None to
+    /// ignore clicks of that kind.
+    pub fn set_click_action(&mut self, kind: ClickKind, action: Option<WorkspaceClickAction>) {
+        match action {
+            Some(action) => self.click_actions.insert(kind, action),
+            None => self.click_actions.remove(&kind),
+        };
+    }
+
+    // The index of the workspace drawn at 'x' pixels from the left of the widget
+    fn workspace_at(&self, x: f64) -> Option<usize> {
+        let mut start = PADDING;
+        for (i, ws) in self.workspaces.iter().enumerate() {
+            if x >= start && x < start + ws.extent.0 {
+                return Some(i);
+            }
+            start += ws.extent.0;
+        }
+
+        None
+    }
+
     fn names(&self) -> Vec<&str> {
         self.workspaces.iter().map(|w| w.name.as_ref()).collect()
     }
@@ -87,13 +140,29 @@ impl Workspaces {
         }
     }
 
+    fn update_workspace_urgent<X: XConn>(&mut self, wm: &mut WindowManager<X>) {
+        let urgent = wm.urgent_workspaces();
+        for (i, ws) in self.workspaces.iter_mut().enumerate() {
+            let now_urgent = urgent.contains(&i);
+            if ws.urgent != now_urgent {
+                self.require_draw = true;
+                ws.urgent = now_urgent;
+            }
+        }
+    }
+
     fn ws_colors(
         &self,
         ix: usize,
         screen: usize,
         screen_has_focus: bool,
         occupied: bool,
+        urgent: bool,
     ) -> (&Color, Option<&Color>) {
+        if urgent {
+            return (&self.bg_2, Some(&self.fg_1));
+        }
+
         let focused_here = match self.focused_ws.get(screen) {
             Some(&ws) => ix == ws,
             None => false,
@@ -123,8 +192,12 @@ where
     X: XConn,
 {
     fn new_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> crate::Result<()> {
-        let c = wm.client(&Selector::WinId(id)).unwrap();
-        if let Some(ws) = self.workspaces.get_mut(c.workspace()) {
+        let wix = match wm.client(&Selector::WinId(id)) {
+            Some(c) => c.workspace(),
+            None => return Ok(()), // removed by an earlier hook
+        };
+
+        if let Some(ws) = self.workspaces.get_mut(wix) {
             self.require_draw = !ws.occupied;
             ws.occupied = true;
         }
@@ -134,6 +207,7 @@ where
 
     fn remove_client(&mut self, wm: &mut WindowManager<X>, _: Xid) -> crate::Result<()> {
         self.update_workspace_occupied(wm);
+        self.update_workspace_urgent(wm);
 
         Ok(())
     }
@@ -145,6 +219,7 @@ where
         _: usize,
     ) -> crate::Result<()> {
         self.update_workspace_occupied(wm);
+        self.update_workspace_urgent(wm);
 
         Ok(())
     }
@@ -197,6 +272,17 @@ where
         Ok(())
     }
 
+    fn client_urgency_changed(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: Xid,
+        _: bool,
+    ) -> crate::Result<()> {
+        self.update_workspace_urgent(wm);
+
+        Ok(())
+    }
+
     fn screens_updated(&mut self, wm: &mut WindowManager<X>, _: &[Region]) -> crate::Result<()> {
         self.focused_ws = wm.focused_workspaces();
         self.update_workspace_occupied(wm);
@@ -212,6 +298,29 @@ where
 
         Ok(())
     }
+
+    fn bar_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        point: Point,
+    ) -> crate::Result<()> {
+        if button != MouseButton::Left {
+            return Ok(());
+        }
+
+        let sel = match self.workspace_at(point.x as f64) {
+            Some(ix) => Selector::Index(ix),
+            None => return Ok(()),
+        };
+
+        match self.click_actions.get(&kind) {
+            Some(WorkspaceClickAction::Focus) => wm.focus_workspace(&sel),
+            Some(WorkspaceClickAction::MoveFocusedClient) => wm.client_to_workspace(&sel),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Widget for Workspaces {
@@ -227,10 +336,10 @@ impl Widget for Workspaces {
         ctx.rectangle(0.0, 0.0, w, h);
         ctx.font(&self.font, self.point_size)?;
         ctx.translate(PADDING, 0.0);
-        let (_, eh) = self.extent.unwrap();
+        let (_, eh) = self.current_extent(ctx, h)?;
 
         for (i, ws) in self.workspaces.iter().enumerate() {
-            let (fg, bg) = self.ws_colors(i, screen, screen_has_focus, ws.occupied);
+            let (fg, bg) = self.ws_colors(i, screen, screen_has_focus, ws.occupied, ws.urgent);
             if let Some(c) = bg {
                 ctx.color(c);
                 ctx.rectangle(0.0, 0.0, ws.extent.0, h);
@@ -247,8 +356,8 @@ impl Widget for Workspaces {
 
     fn current_extent(&mut self, ctx: &mut dyn DrawContext, _h: f64) -> Result<(f64, f64)> {
         match self.extent {
-            Some(extent) => Ok(extent),
-            None => {
+            Some(extent) if self.extent_scale == ctx.font_scale() => Ok(extent),
+            _ => {
                 let mut total = 0.0;
                 let mut h_max = 0.0;
                 for ws in self.workspaces.iter_mut() {
@@ -261,6 +370,7 @@ impl Widget for Workspaces {
 
                 let ext = (total + PADDING, h_max);
                 self.extent = Some(ext);
+                self.extent_scale = ctx.font_scale();
                 Ok(ext)
             }
         }
@@ -273,6 +383,10 @@ impl Widget for Workspaces {
     fn is_greedy(&self) -> bool {
         false
     }
+
+    fn background(&self) -> Option<Color> {
+        Some(self.bg_2)
+    }
 }
 
 /// A text widget that is set via updating the root window name a la dwm
@@ -325,6 +439,10 @@ impl Widget for RootWindowName {
     fn is_greedy(&self) -> bool {
         self.txt.is_greedy()
     }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
 }
 
 /// A text widget that is set via updating the root window name a la dwm
@@ -349,12 +467,17 @@ impl ActiveWindowName {
     }
 
     fn set_text(&mut self, txt: &str) {
-        if txt.chars().count() <= self.max_chars {
-            self.txt.set_text(txt);
-        } else {
-            let s: String = txt.chars().take(self.max_chars - 3).collect();
-            self.txt.set_text(format!("{}...", s));
-        }
+        self.txt.set_text(truncate(txt, self.max_chars));
+    }
+}
+
+// 'txt' cut down to at most 'max_chars' characters, marking where it was cut with an ellipsis
+fn truncate(txt: &str, max_chars: usize) -> String {
+    if txt.chars().count() <= max_chars {
+        txt.to_string()
+    } else {
+        let s: String = txt.chars().take(max_chars.saturating_sub(3)).collect();
+        format!("{}...", s)
     }
 }
 
@@ -425,9 +548,17 @@ impl Widget for ActiveWindowName {
     fn is_greedy(&self) -> bool {
         self.txt.is_greedy()
     }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
 }
 
 /// A simple widget that displays the active layout symbol
+///
+/// If the active layout is hiding any clients (as [monocle][crate::core::layout::monocle] does)
+/// then the number of hidden clients is shown after the symbol, e.g. `[mono] +2`. See
+/// [HiddenClientsBadge][crate::draw::HiddenClientsBadge] for showing this on the focused client.
 #[derive(Clone, Debug, PartialEq)]
 pub struct CurrentLayout {
     txt: Text,
@@ -440,6 +571,14 @@ impl CurrentLayout {
             txt: Text::new("", style, false, false),
         }
     }
+
+    fn update_text<X: XConn>(&mut self, wm: &WindowManager<X>) {
+        let symbol = wm.current_layout_symbol();
+        match wm.hidden_client_count(wm.active_workspace_index()) {
+            0 => self.txt.set_text(symbol),
+            n => self.txt.set_text(format!("{} +{}", symbol, n)),
+        }
+    }
 }
 
 impl<X> Hook<X> for CurrentLayout
@@ -447,7 +586,7 @@ where
     X: XConn,
 {
     fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
-        self.txt.set_text(wm.current_layout_symbol());
+        self.update_text(wm);
         Ok(())
     }
 
@@ -457,7 +596,7 @@ where
         _: usize,
         _: usize,
     ) -> crate::Result<()> {
-        self.txt.set_text(wm.current_layout_symbol());
+        self.update_text(wm);
         Ok(())
     }
 
@@ -467,12 +606,24 @@ where
         _: usize,
         _: usize,
     ) -> crate::Result<()> {
-        self.txt.set_text(wm.current_layout_symbol());
+        self.update_text(wm);
+        Ok(())
+    }
+
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        workspace_index: usize,
+        _: usize,
+    ) -> crate::Result<()> {
+        if workspace_index == wm.active_workspace_index() {
+            self.update_text(wm);
+        }
         Ok(())
     }
 
     fn screen_change(&mut self, wm: &mut WindowManager<X>, _: usize) -> crate::Result<()> {
-        self.txt.set_text(wm.current_layout_symbol());
+        self.update_text(wm);
         Ok(())
     }
 }
@@ -493,4 +644,475 @@ impl Widget for CurrentLayout {
     fn is_greedy(&self) -> bool {
         false
     }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
+}
+
+/// A simple widget that shows which of Caps Lock and Num Lock are currently active.
+///
+/// While Caps Lock is active the widget is drawn using the `highlight` color as its background.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockIndicator {
+    txt: Text,
+    bg: Option<Color>,
+    highlight: Color,
+}
+
+impl LockIndicator {
+    /// Create a new LockIndicator widget
+    pub fn new(style: &TextStyle, highlight: impl Into<Color>) -> Self {
+        Self {
+            txt: Text::new("", style, false, false),
+            bg: style.bg,
+            highlight: highlight.into(),
+        }
+    }
+
+    fn update(&mut self, state: LockState) {
+        let labels: Vec<&str> = vec![(state.caps_lock, "CAPS"), (state.num_lock, "NUM")]
+            .into_iter()
+            .filter(|&(active, _)| active)
+            .map(|(_, label)| label)
+            .collect();
+
+        self.txt.set_text(labels.join(" "));
+        self.txt.set_bg(if state.caps_lock {
+            Some(self.highlight)
+        } else {
+            self.bg
+        });
+    }
+}
+
+impl<X> Hook<X> for LockIndicator
+where
+    X: XConn,
+{
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        let state = wm.conn().lock_state()?;
+        self.update(state);
+        Ok(())
+    }
+
+    fn lock_state_changed(
+        &mut self,
+        _: &mut WindowManager<X>,
+        state: LockState,
+    ) -> crate::Result<()> {
+        self.update(state);
+        Ok(())
+    }
+}
+
+impl Widget for LockIndicator {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
+}
+
+/// A simple widget that shows how many clients have been
+/// [minimized][crate::core::manager::WindowManager::minimize_focused] on the active workspace.
+///
+/// The widget is empty while there are no minimized clients. Left clicking on it restores the
+/// most recently minimized client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MinimizedClients {
+    txt: Text,
+    icon: String,
+}
+
+impl MinimizedClients {
+    /// Create a new MinimizedClients widget that shows 'icon' followed by the number of
+    /// minimized clients.
+    pub fn new(style: &TextStyle, icon: impl Into<String>) -> Self {
+        Self {
+            txt: Text::new("", style, false, false),
+            icon: icon.into(),
+        }
+    }
+
+    fn update_text<X: XConn>(&mut self, wm: &WindowManager<X>) {
+        match wm.minimized_clients(wm.active_workspace_index()).len() {
+            0 => self.txt.set_text(""),
+            n => self.txt.set_text(format!("{} {}", self.icon, n)),
+        }
+    }
+}
+
+impl<X> Hook<X> for MinimizedClients
+where
+    X: XConn,
+{
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        self.update_text(wm);
+        Ok(())
+    }
+
+    fn layout_applied(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: usize,
+        _: usize,
+    ) -> crate::Result<()> {
+        self.update_text(wm);
+        Ok(())
+    }
+
+    fn remove_client(&mut self, wm: &mut WindowManager<X>, _: Xid) -> crate::Result<()> {
+        self.update_text(wm);
+        Ok(())
+    }
+
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _: usize,
+        _: usize,
+    ) -> crate::Result<()> {
+        self.update_text(wm);
+        Ok(())
+    }
+
+    fn screen_change(&mut self, wm: &mut WindowManager<X>, _: usize) -> crate::Result<()> {
+        self.update_text(wm);
+        Ok(())
+    }
+
+    fn bar_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        _: Point,
+    ) -> crate::Result<()> {
+        if button == MouseButton::Left && kind == ClickKind::Single {
+            wm.restore_last_minimized()?;
+            self.update_text(wm);
+        }
+
+        Ok(())
+    }
+}
+
+impl Widget for MinimizedClients {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
+}
+
+/// A simple widget that shows the hints for the available continuations of the active
+/// [KeyChord][crate::core::bindings::KeyChord].
+///
+/// The widget is empty while there is no active chord.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyChordHint {
+    txt: Text,
+    separator: String,
+}
+
+impl KeyChordHint {
+    /// Create a new KeyChordHint widget that shows hints with 'separator' between each of them
+    pub fn new(style: &TextStyle, separator: impl Into<String>) -> Self {
+        Self {
+            txt: Text::new("", style, false, false),
+            separator: separator.into(),
+        }
+    }
+}
+
+impl<X> Hook<X> for KeyChordHint
+where
+    X: XConn,
+{
+    fn key_chord_changed(&mut self, _: &mut WindowManager<X>, hints: &[&str]) -> crate::Result<()> {
+        self.txt.set_text(hints.join(&self.separator));
+        Ok(())
+    }
+}
+
+impl Widget for KeyChordHint {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
+}
+
+/// A compact list of the titles of the most recently focused clients, most recent first.
+///
+/// Left clicking on a title focuses that client, switching workspace if needed, and scrolling
+/// over the widget steps focus through the list. The order of the list is held while scrolling
+/// so that each step moves one client further through it, and is picked up again from the
+/// [focus history][WindowManager::focus_history] once focus changes in any other way.
+pub struct RecentClients<X: XConn> {
+    txt: SegmentedText<X>,
+    ids: Vec<Xid>, // the clients being shown, most recently focused first
+    scroll_target: Option<Xid>,
+    n_clients: usize,
+    max_chars: usize,
+    fg: Color,
+    highlight: Color,
+}
+
+impl<X: XConn> fmt::Debug for RecentClients<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecentClients")
+            .field("txt", &self.txt)
+            .field("ids", &self.ids)
+            .field("scroll_target", &self.scroll_target)
+            .field("n_clients", &self.n_clients)
+            .field("max_chars", &self.max_chars)
+            .field("fg", &self.fg)
+            .field("highlight", &self.highlight)
+            .finish()
+    }
+}
+
+impl<X: XConn> RecentClients<X> {
+    /// Create a new RecentClients widget showing up to 'n_clients' titles, each cut down to at
+    /// most 'max_chars' characters. The focused client is drawn with 'highlight' as its
+    /// background.
+    pub fn new(
+        style: &TextStyle,
+        highlight: impl Into<Color>,
+        n_clients: usize,
+        max_chars: usize,
+    ) -> Self {
+        Self {
+            txt: SegmentedText::new(vec![], style, false),
+            ids: vec![],
+            scroll_target: None,
+            n_clients,
+            max_chars,
+            fg: style.fg,
+            highlight: highlight.into(),
+        }
+    }
+
+    fn update(&mut self, wm: &WindowManager<X>) {
+        let history = wm.focus_history();
+        let holding_order = self.scroll_target.is_some()
+            && wm.focused_client_id() == self.scroll_target
+            && self.ids.iter().all(|id| history.contains(id));
+
+        if !holding_order {
+            self.scroll_target = None;
+            self.ids = history.into_iter().take(self.n_clients).collect();
+        }
+
+        let focused = wm.focused_client_id();
+        let segments = self
+            .ids
+            .iter()
+            .filter_map(|&id| wm.client(&Selector::WinId(id)).map(|c| (id, c.wm_name())))
+            .map(|(id, name)| {
+                let seg = Segment::new(truncate(name, self.max_chars), self.fg);
+                if Some(id) == focused {
+                    seg.bg(self.highlight)
+                } else {
+                    seg
+                }
+            })
+            .collect();
+
+        self.txt.set_segments(segments);
+    }
+
+    fn focus(&mut self, wm: &mut WindowManager<X>, id: Xid) -> crate::Result<()> {
+        if let Some(wix) = wm.client(&Selector::WinId(id)).map(|c| c.workspace()) {
+            if !wm.focused_workspaces().contains(&wix) {
+                wm.focus_workspace(&Selector::Index(wix))?;
+            }
+            wm.focus_client(&Selector::WinId(id))?;
+        }
+
+        Ok(())
+    }
+}
+
+// The client to focus next when scrolling through 'ids' in 'direction' from 'focused'
+fn next_recent_client(ids: &[Xid], focused: Option<Xid>, direction: Direction) -> Option<Xid> {
+    if ids.is_empty() {
+        return None;
+    }
+
+    let n = ids.len();
+    let ix = match focused.and_then(|id| ids.iter().position(|&i| i == id)) {
+        Some(ix) => match direction {
+            Direction::Forward => (ix + 1) % n,
+            Direction::Backward => (ix + n - 1) % n,
+        },
+        None => 0,
+    };
+
+    Some(ids[ix])
+}
+
+impl<X: XConn> Hook<X> for RecentClients<X> {
+    fn new_client(&mut self, wm: &mut WindowManager<X>, _: Xid) -> crate::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn remove_client(&mut self, wm: &mut WindowManager<X>, _: Xid) -> crate::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn focus_change(&mut self, wm: &mut WindowManager<X>, _: Xid) -> crate::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn client_name_updated(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        _: &str,
+        root: bool,
+    ) -> crate::Result<()> {
+        if !root && self.ids.contains(&id) {
+            self.update(wm);
+        }
+
+        Ok(())
+    }
+
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> crate::Result<()> {
+        self.update(wm);
+        Ok(())
+    }
+
+    fn bar_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        point: Point,
+    ) -> crate::Result<()> {
+        if kind != ClickKind::Single {
+            return Ok(());
+        }
+
+        let direction = match button {
+            MouseButton::ScrollDown => Direction::Forward,
+            MouseButton::ScrollUp => Direction::Backward,
+            MouseButton::Left => {
+                let ix = self.txt.segment_at(point.x as f64);
+                let id = ix.and_then(|ix| self.ids.get(ix).copied());
+                return match id {
+                    Some(id) => self.focus(wm, id),
+                    None => Ok(()),
+                };
+            }
+            _ => return Ok(()),
+        };
+
+        if let Some(id) = next_recent_client(&self.ids, wm.focused_client_id(), direction) {
+            self.scroll_target = Some(id);
+            self.focus(wm, id)?;
+            self.update(wm);
+        }
+
+        Ok(())
+    }
+}
+
+impl<X: XConn> Widget for RecentClients<X> {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    test_cases! {
+        next_recent_client;
+        args: (focused: Option<Xid>, direction: Direction, expected: Option<Xid>);
+
+        case: forward_from_most_recent => (Some(10), Direction::Forward, Some(20));
+        case: forward_wraps => (Some(30), Direction::Forward, Some(10));
+        case: backward_wraps => (Some(10), Direction::Backward, Some(30));
+        case: unknown_focus_starts_at_most_recent => (Some(99), Direction::Forward, Some(10));
+        case: nothing_focused => (None, Direction::Backward, Some(10));
+
+        body: {
+            assert_eq!(next_recent_client(&[10, 20, 30], focused, direction), expected);
+        }
+    }
+
+    test_cases! {
+        truncate;
+        args: (txt: &str, expected: &str);
+
+        case: short => ("abc", "abc");
+        case: exact => ("abcdef", "abcdef");
+        case: long => ("abcdefgh", "abc...");
+
+        body: {
+            assert_eq!(truncate(txt, 6), expected);
+        }
+    }
 }
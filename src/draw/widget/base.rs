@@ -1,9 +1,22 @@
 //! Base widgets for building more complex structures
 use crate::{
-    core::{bindings::KeyPress, hooks::Hook, xconnection::XConn},
-    draw::{Color, DrawContext, DrawError, KeyboardControlled, Result, TextStyle, Widget},
+    core::{
+        bindings::{ClickKind, KeyPress, MouseButton},
+        data_types::Point,
+        hooks::Hook,
+        manager::WindowManager,
+        xconnection::XConn,
+    },
+    draw::{
+        widget::sys::{on_battery, RefreshInterval},
+        Color, DrawContext, DrawError, KeyboardControlled, Result, TextStyle, Widget,
+    },
 };
 
+use std::{collections::VecDeque, fmt, time::Duration};
+
+const UNDERLINE_PX: f64 = 2.0;
+
 /// A simple piece of static text with an optional background color.
 ///
 /// Can be used as a simple static element in a status bar or as an inner element for rendering
@@ -19,6 +32,7 @@ pub struct Text {
     is_greedy: bool,
     right_justified: bool,
     extent: Option<(f64, f64)>,
+    extent_scale: f64,
     require_draw: bool,
 }
 
@@ -40,6 +54,7 @@ impl Text {
             is_greedy,
             right_justified,
             extent: None,
+            extent_scale: 1.0,
             require_draw: false,
         }
     }
@@ -49,8 +64,10 @@ impl Text {
         &self.txt
     }
 
-    /// Mutably borrow the current contents of the widget.
+    /// Mutably borrow the current contents of the widget, triggering a redraw.
     pub fn get_text_mut(&mut self) -> &mut String {
+        self.extent = None;
+        self.require_draw = true;
         &mut self.txt
     }
 
@@ -64,6 +81,34 @@ impl Text {
         }
     }
 
+    /// Set the font and point size used to render the text and trigger a redraw
+    pub fn set_font(&mut self, font: impl Into<String>, point_size: i32) {
+        let font = font.into();
+        if self.font != font || self.point_size != point_size {
+            self.font = font;
+            self.point_size = point_size;
+            self.extent = None;
+            self.require_draw = true;
+        }
+    }
+
+    /// Set the foreground color used to render the text and trigger a redraw
+    pub fn set_fg(&mut self, fg: impl Into<Color>) {
+        let fg = fg.into();
+        if self.fg != fg {
+            self.fg = fg;
+            self.require_draw = true;
+        }
+    }
+
+    /// Set the background color (if any) used behind the text and trigger a redraw
+    pub fn set_bg(&mut self, bg: Option<Color>) {
+        if self.bg != bg {
+            self.bg = bg;
+            self.require_draw = true;
+        }
+    }
+
     /// Force this text widget to redraw on the next render request.
     /// Mostly used when being wrapped by another widget.
     pub fn force_draw(&mut self) {
@@ -99,14 +144,16 @@ impl Widget for Text {
     }
 
     fn current_extent(&mut self, ctx: &mut dyn DrawContext, _h: f64) -> Result<(f64, f64)> {
+        // status bars scale fonts per screen so the cached extent is only valid at one scale
         match self.extent {
-            Some(extent) => Ok(extent),
-            None => {
+            Some(extent) if self.extent_scale == ctx.font_scale() => Ok(extent),
+            _ => {
                 let (l, r) = self.padding;
                 ctx.font(&self.font, self.point_size)?;
                 let (w, h) = ctx.text_extent(&self.txt)?;
                 let extent = (w + l + r, h);
                 self.extent = Some(extent);
+                self.extent_scale = ctx.font_scale();
                 Ok(extent)
             }
         }
@@ -119,6 +166,10 @@ impl Widget for Text {
     fn is_greedy(&self) -> bool {
         self.is_greedy
     }
+
+    fn background(&self) -> Option<Color> {
+        self.bg
+    }
 }
 
 /// A set of lines that highlights the currently selected line.
@@ -384,3 +435,931 @@ impl KeyboardControlled for InputBox {
         Ok(None)
     }
 }
+
+/// A handler called with the button that was used to click on a [Segment]
+pub type SegmentClickHandler<X> =
+    Box<dyn FnMut(&mut WindowManager<X>, MouseButton) -> crate::Result<()>>;
+
+/// A piece of text with its own colors within a [SegmentedText]
+pub struct Segment<X: XConn> {
+    txt: String,
+    fg: Color,
+    bg: Option<Color>,
+    underline: Option<Color>,
+    on_click: Option<SegmentClickHandler<X>>,
+    on_double_click: Option<SegmentClickHandler<X>>,
+    on_hold: Option<SegmentClickHandler<X>>,
+}
+
+impl<X: XConn> fmt::Debug for Segment<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Segment")
+            .field("txt", &self.txt)
+            .field("fg", &self.fg)
+            .field("bg", &self.bg)
+            .field("underline", &self.underline)
+            .field("on_click", &self.on_click.as_ref().map(|_| "<handler>"))
+            .field(
+                "on_double_click",
+                &self.on_double_click.as_ref().map(|_| "<handler>"),
+            )
+            .field("on_hold", &self.on_hold.as_ref().map(|_| "<handler>"))
+            .finish()
+    }
+}
+
+impl<X: XConn> Segment<X> {
+    /// Create a new segment rendering 'txt' in 'fg' with no background
+    pub fn new(txt: impl Into<String>, fg: impl Into<Color>) -> Self {
+        Self {
+            txt: txt.into(),
+            fg: fg.into(),
+            bg: None,
+            underline: None,
+            on_click: None,
+            on_double_click: None,
+            on_hold: None,
+        }
+    }
+
+    /// Fill the background of this segment with 'bg'
+    pub fn bg(mut self, bg: impl Into<Color>) -> Self {
+        self.bg = Some(bg.into());
+        self
+    }
+
+    /// Draw a line along the bottom of this segment in 'color'
+    pub fn underline(mut self, color: impl Into<Color>) -> Self {
+        self.underline = Some(color.into());
+        self
+    }
+
+    /// Run 'handler' when this segment is clicked on in a [StatusBar][crate::draw::StatusBar]
+    pub fn on_click<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&mut WindowManager<X>, MouseButton) -> crate::Result<()> + 'static,
+    {
+        self.on_click = Some(Box::new(handler));
+        self
+    }
+
+    /// Run 'handler' when this segment is double clicked in a [StatusBar][crate::draw::StatusBar].
+    ///
+    /// The first click of the pair still runs the handler set using [on_click][Segment::on_click].
+    pub fn on_double_click<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&mut WindowManager<X>, MouseButton) -> crate::Result<()> + 'static,
+    {
+        self.on_double_click = Some(Box::new(handler));
+        self
+    }
+
+    /// Run 'handler' when a mouse button is held down over this segment in a
+    /// [StatusBar][crate::draw::StatusBar] and then released.
+    pub fn on_hold<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&mut WindowManager<X>, MouseButton) -> crate::Result<()> + 'static,
+    {
+        self.on_hold = Some(Box::new(handler));
+        self
+    }
+
+    /// The text rendered by this segment
+    pub fn text(&self) -> &str {
+        &self.txt
+    }
+}
+
+/// A line of text made up of [segments][Segment], each with their own colors and (optionally)
+/// an underline and a handler that is run when the segment is clicked.
+///
+/// All segments share the same font and padding. Useful as a building block for workspace
+/// indicators, breadcrumbs and powerline style sections of a status bar.
+pub struct SegmentedText<X: XConn> {
+    segments: Vec<Segment<X>>,
+    font: String,
+    point_size: i32,
+    padding: (f64, f64),
+    is_greedy: bool,
+    extents: Option<Vec<(f64, f64)>>,
+    extent_scale: f64,
+    require_draw: bool,
+}
+
+impl<X: XConn> fmt::Debug for SegmentedText<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SegmentedText")
+            .field("segments", &self.segments)
+            .field("font", &self.font)
+            .field("point_size", &self.point_size)
+            .field("padding", &self.padding)
+            .field("is_greedy", &self.is_greedy)
+            .field("extents", &self.extents)
+            .field("require_draw", &self.require_draw)
+            .finish()
+    }
+}
+
+impl<X: XConn> SegmentedText<X> {
+    /// Construct a new [SegmentedText] using the font and padding from 'style' for each segment
+    pub fn new(segments: Vec<Segment<X>>, style: &TextStyle, is_greedy: bool) -> Self {
+        Self {
+            segments,
+            font: style.font.clone(),
+            point_size: style.point_size,
+            padding: style.padding,
+            is_greedy,
+            extents: None,
+            extent_scale: 1.0,
+            require_draw: false,
+        }
+    }
+
+    /// Borrow the current segments of the widget.
+    pub fn segments(&self) -> &[Segment<X>] {
+        &self.segments
+    }
+
+    /// Replace all of the segments and trigger a redraw
+    pub fn set_segments(&mut self, segments: Vec<Segment<X>>) {
+        self.segments = segments;
+        self.extents = None;
+        self.require_draw = true;
+    }
+
+    /// Set the text of the segment at 'index' and trigger a redraw
+    pub fn set_segment_text(&mut self, index: usize, txt: impl Into<String>) -> Result<()> {
+        let n = self.segments.len();
+        let seg = self
+            .segments
+            .get_mut(index)
+            .ok_or_else(|| DrawError::Raw(format!("index out of bounds: {} >= {}", index, n)))?;
+
+        let txt = txt.into();
+        if seg.txt != txt {
+            seg.txt = txt;
+            self.extents = None;
+            self.require_draw = true;
+        }
+
+        Ok(())
+    }
+}
+
+// The index of the segment containing 'x' given the width of each segment in order
+fn segment_at(widths: &[f64], x: f64) -> Option<usize> {
+    let mut start = 0.0;
+    for (i, w) in widths.iter().enumerate() {
+        if x >= start && x < start + w {
+            return Some(i);
+        }
+        start += w;
+    }
+
+    None
+}
+
+impl<X: XConn> SegmentedText<X> {
+    /// The index of the segment that was last drawn at 'x' pixels from the left of the widget
+    pub fn segment_at(&self, x: f64) -> Option<usize> {
+        let widths: Vec<f64> = self.extents.as_ref()?.iter().map(|(w, _)| *w).collect();
+        segment_at(&widths, x)
+    }
+
+    // The handler for 'kind' on the segment at 'point', if one has been set
+    fn handler_at(&mut self, point: Point, kind: ClickKind) -> Option<&mut SegmentClickHandler<X>> {
+        let ix = self.segment_at(point.x as f64)?;
+        let seg = &mut self.segments[ix];
+
+        match kind {
+            ClickKind::Single => seg.on_click.as_mut(),
+            ClickKind::Double => seg.on_double_click.as_mut(),
+            ClickKind::Hold => seg.on_hold.as_mut(),
+        }
+    }
+}
+
+impl<X: XConn> Hook<X> for SegmentedText<X> {
+    fn bar_clicked(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        point: Point,
+    ) -> crate::Result<()> {
+        match self.handler_at(point, kind) {
+            Some(handler) => handler(wm, button),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<X: XConn> Widget for SegmentedText<X> {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, _: usize, _: bool, _: f64, h: f64) -> Result<()> {
+        let (_, eh) = self.current_extent(ctx, h)?;
+        let extents = self.extents.clone().unwrap_or_default();
+        ctx.font(&self.font, self.point_size)?;
+
+        for (seg, (w, _)) in self.segments.iter().zip(extents) {
+            if let Some(color) = seg.bg {
+                ctx.color(&color);
+                ctx.rectangle(0.0, 0.0, w, h);
+            }
+            if let Some(color) = seg.underline {
+                ctx.color(&color);
+                ctx.rectangle(0.0, h - UNDERLINE_PX, w, UNDERLINE_PX);
+            }
+
+            ctx.color(&seg.fg);
+            ctx.text(&seg.txt, h - eh, self.padding)?;
+            ctx.translate(w, 0.0);
+        }
+
+        self.require_draw = false;
+        Ok(())
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, _h: f64) -> Result<(f64, f64)> {
+        let extents = match self.extents {
+            Some(ref extents) if self.extent_scale == ctx.font_scale() => extents.clone(),
+            _ => {
+                let (l, r) = self.padding;
+                ctx.font(&self.font, self.point_size)?;
+                let extents = self
+                    .segments
+                    .iter()
+                    .map(|seg| ctx.text_extent(&seg.txt).map(|(w, h)| (w + l + r, h)))
+                    .collect::<Result<Vec<_>>>()?;
+                self.extents = Some(extents.clone());
+                self.extent_scale = ctx.font_scale();
+                extents
+            }
+        };
+
+        Ok(extents
+            .iter()
+            .fold((0.0, 0.0), |(w, h), &(sw, sh)| (w + sw, f64::max(h, sh))))
+    }
+
+    fn require_draw(&self) -> bool {
+        self.require_draw
+    }
+
+    fn is_greedy(&self) -> bool {
+        self.is_greedy
+    }
+}
+
+/// A closure called by a [LineGraph] to take its next reading. Returning `None` skips a reading.
+pub type GraphSampler = Box<dyn FnMut() -> Option<f64>>;
+
+/// A sparkline of the most recent readings returned by a sampling closure.
+///
+/// The closure is called from the event loop at most once per refresh interval, so it should
+/// return quickly, and the most recent `n_samples` readings are kept with the newest drawn on the
+/// right. Each reading is `sample_width` pixels wide and the graph is scaled so that `max` reaches
+/// the top of the bar, or so that the largest reading being shown does if no max is given.
+/// Readings can also be added directly using [push][LineGraph::push].
+pub struct LineGraph {
+    samples: VecDeque<f64>,
+    n_samples: usize,
+    sample_width: f64,
+    max: Option<f64>,
+    fg: Color,
+    bg: Option<Color>,
+    sampler: GraphSampler,
+    interval: RefreshInterval,
+    require_draw: bool,
+}
+
+impl fmt::Debug for LineGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineGraph")
+            .field("samples", &self.samples)
+            .field("n_samples", &self.n_samples)
+            .field("sample_width", &self.sample_width)
+            .field("max", &self.max)
+            .field("fg", &self.fg)
+            .field("bg", &self.bg)
+            .field("interval", &self.interval)
+            .field("require_draw", &self.require_draw)
+            .finish()
+    }
+}
+
+impl LineGraph {
+    /// Construct a new [LineGraph] that calls `sampler` for a new reading every `interval`
+    pub fn new<F>(
+        n_samples: usize,
+        sample_width: f64,
+        max: Option<f64>,
+        fg: impl Into<Color>,
+        bg: Option<Color>,
+        interval: Duration,
+        sampler: F,
+    ) -> Self
+    where
+        F: FnMut() -> Option<f64> + 'static,
+    {
+        Self {
+            samples: VecDeque::with_capacity(n_samples),
+            n_samples,
+            sample_width,
+            max,
+            fg: fg.into(),
+            bg,
+            sampler: Box::new(sampler),
+            interval: RefreshInterval::new(interval),
+            require_draw: false,
+        }
+    }
+
+    /// The readings currently being shown, oldest first
+    pub fn samples(&self) -> &VecDeque<f64> {
+        &self.samples
+    }
+
+    /// Add a reading, dropping the oldest one if the graph is full, and trigger a redraw
+    pub fn push(&mut self, value: f64) {
+        self.samples.push_back(value);
+        while self.samples.len() > self.n_samples {
+            self.samples.pop_front();
+        }
+        self.require_draw = true;
+    }
+
+    fn sample(&mut self) {
+        if !self.interval.is_due() {
+            return;
+        }
+
+        if let Some(value) = (self.sampler)() {
+            self.push(value);
+        }
+    }
+}
+
+// The vertices of the area under a graph of 'samples' that is 'h' pixels high. Each sample is
+// plotted at the middle of its column with the line carried out to the outer edges of the first
+// and last columns, and the newest sample is always in the right most column.
+fn graph_points(
+    samples: &VecDeque<f64>,
+    n_samples: usize,
+    sample_width: f64,
+    max: Option<f64>,
+    h: f64,
+) -> Vec<(f64, f64)> {
+    if samples.is_empty() {
+        return vec![];
+    }
+
+    let max = max.unwrap_or_else(|| samples.iter().cloned().fold(0.0, f64::max));
+    let y = |v: f64| {
+        if max > 0.0 {
+            h - (v / max).clamp(0.0, 1.0) * h
+        } else {
+            h
+        }
+    };
+
+    let start = n_samples.saturating_sub(samples.len()) as f64 * sample_width;
+    let end = n_samples.max(samples.len()) as f64 * sample_width;
+    let first = samples.front().cloned().unwrap_or_default();
+    let last = samples.back().cloned().unwrap_or_default();
+
+    let mut points = vec![(start, h), (start, y(first))];
+    points.extend(
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (start + (i as f64 + 0.5) * sample_width, y(v))),
+    );
+    points.extend(vec![(end, y(last)), (end, h)]);
+
+    points
+}
+
+impl<X> Hook<X> for LineGraph
+where
+    X: XConn,
+{
+    fn startup(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.sample();
+        Ok(())
+    }
+
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.sample();
+        Ok(())
+    }
+}
+
+impl Widget for LineGraph {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, _: usize, _: bool, w: f64, h: f64) -> Result<()> {
+        if let Some(color) = self.bg {
+            ctx.color(&color);
+            ctx.rectangle(0.0, 0.0, w, h);
+        }
+
+        let points = graph_points(
+            &self.samples,
+            self.n_samples,
+            self.sample_width,
+            self.max,
+            h,
+        );
+        if !points.is_empty() {
+            ctx.color(&self.fg);
+            ctx.polygon(&points);
+        }
+
+        self.require_draw = false;
+        Ok(())
+    }
+
+    fn current_extent(&mut self, _: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        Ok((self.n_samples as f64 * self.sample_width, h))
+    }
+
+    fn require_draw(&self) -> bool {
+        self.require_draw
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.bg
+    }
+
+    fn poll_interval(&self) -> Option<Duration> {
+        Some(self.interval.period())
+    }
+}
+
+/// The direction that a [ProgressBar] fills in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// Fill from left to right
+    Horizontal,
+    /// Fill from bottom to top
+    Vertical,
+}
+
+/// A bar that is filled in proportion to a value between 0.0 and 1.0, with an optional label
+/// drawn to its right.
+///
+/// Useful as the visual part of volume, brightness and battery widgets: `Volume` (with the
+/// `volume` feature enabled) and the usage widgets in [stats][1] can be shown this way using
+/// their `set_bar` method. A horizontal bar is `length` pixels long and `thickness` pixels high,
+/// while a vertical bar is `thickness` pixels wide and `length` pixels high (or the height of the
+/// bar if that is smaller). The bar is filled with the foreground color of the style it was
+/// created with and the rest of it is drawn in `track`. If an animation step has been set,
+/// changes in value are shown by moving the fill that fraction of the bar towards the new value
+/// each time the widget is drawn. Animations are skipped while running on battery so that a
+/// change in value only needs a single redraw.
+///
+/// [1]: crate::draw::widget::stats
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressBar {
+    value: f64,
+    shown: f64,
+    length: f64,
+    thickness: f64,
+    orientation: Orientation,
+    fg: Color,
+    bg: Option<Color>,
+    track: Color,
+    padding: (f64, f64),
+    label: Option<Text>,
+    style: TextStyle,
+    animation_step: Option<f64>,
+    require_draw: bool,
+}
+
+impl ProgressBar {
+    /// Construct a new, empty [ProgressBar] using the colors and padding from 'style'
+    pub fn new(
+        style: &TextStyle,
+        length: f64,
+        thickness: f64,
+        track: impl Into<Color>,
+        orientation: Orientation,
+    ) -> Self {
+        Self {
+            value: 0.0,
+            shown: 0.0,
+            length,
+            thickness,
+            orientation,
+            fg: style.fg,
+            bg: style.bg,
+            track: track.into(),
+            padding: style.padding,
+            label: None,
+            style: style.clone(),
+            animation_step: None,
+            require_draw: false,
+        }
+    }
+
+    /// The value currently being shown by this bar
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Set the value shown by this bar, clamped to between 0.0 and 1.0, and trigger a redraw
+    pub fn set_value(&mut self, value: f64) {
+        let value = value.clamp(0.0, 1.0);
+        if (self.value - value).abs() > f64::EPSILON {
+            self.value = value;
+            self.require_draw = true;
+        }
+    }
+
+    /// Set the text shown to the right of the bar, or None to only show the bar itself
+    pub fn set_label(&mut self, label: Option<impl Into<String>>) {
+        match (label, self.label.as_mut()) {
+            (Some(s), Some(txt)) => txt.set_text(s),
+            (Some(s), None) => {
+                self.label = Some(Text::new(s, &self.style, false, false));
+                self.require_draw = true;
+            }
+            (None, Some(_)) => {
+                self.label = None;
+                self.require_draw = true;
+            }
+            (None, None) => (),
+        }
+    }
+
+    /// Set the color used to fill the bar and trigger a redraw
+    pub fn set_fg(&mut self, fg: impl Into<Color>) {
+        let fg = fg.into();
+        if self.fg != fg {
+            self.fg = fg;
+            self.require_draw = true;
+        }
+    }
+
+    /// Set the fraction of the bar that the fill moves by each time the widget is drawn when the
+    /// value changes, or None to jump straight to the new value.
+    pub fn set_animation_step(&mut self, step: Option<f64>) {
+        self.animation_step = step.filter(|&s| s > 0.0);
+        self.shown = self.value;
+    }
+
+    // Move the fill one animation step towards the current value, or straight to it if we are
+    // not animating
+    fn advance(&mut self, on_battery: bool) {
+        let step = if on_battery {
+            None
+        } else {
+            self.animation_step
+        };
+        self.shown = animate_towards(self.shown, self.value, step);
+        self.require_draw = self.shown != self.value;
+    }
+
+    fn bar_extent(&self) -> f64 {
+        let (l, r) = self.padding;
+        let w = match self.orientation {
+            Orientation::Horizontal => self.length,
+            Orientation::Vertical => self.thickness,
+        };
+
+        // the label has padding of its own
+        match self.label {
+            Some(_) => l + w,
+            None => l + w + r,
+        }
+    }
+}
+
+// The fill level to draw next when moving from 'shown' towards 'target' by at most 'step'
+fn animate_towards(shown: f64, target: f64, step: Option<f64>) -> f64 {
+    match step {
+        Some(step) if (target - shown).abs() > step => shown + step.copysign(target - shown),
+        _ => target,
+    }
+}
+
+// An area to fill as (x, y, w, h)
+type Rect = (f64, f64, f64, f64);
+
+// The track and filled portion of a bar 'length' by 'thickness' pixels that is centered
+// vertically within 'h' pixels and filled to 'fraction'
+fn progress_bar_regions(
+    fraction: f64,
+    orientation: Orientation,
+    length: f64,
+    thickness: f64,
+    h: f64,
+) -> (Rect, Rect) {
+    let fraction = fraction.clamp(0.0, 1.0);
+
+    match orientation {
+        Orientation::Horizontal => {
+            let bh = thickness.min(h);
+            let y = ((h - bh) / 2.0).floor();
+            ((0.0, y, length, bh), (0.0, y, length * fraction, bh))
+        }
+        Orientation::Vertical => {
+            let bh = length.min(h);
+            let y = ((h - bh) / 2.0).floor();
+            let filled = bh * fraction;
+            (
+                (0.0, y, thickness, bh),
+                (0.0, y + bh - filled, thickness, filled),
+            )
+        }
+    }
+}
+
+impl<X> Hook<X> for ProgressBar where X: XConn {}
+
+impl Widget for ProgressBar {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        if let Some(color) = self.bg {
+            ctx.color(&color);
+            ctx.rectangle(0.0, 0.0, w, h);
+        }
+
+        self.advance(self.animation_step.is_some() && on_battery());
+        let (track, fill) =
+            progress_bar_regions(self.shown, self.orientation, self.length, self.thickness, h);
+
+        let offset = self.padding.0;
+        ctx.translate(offset, 0.0);
+        ctx.color(&self.track);
+        ctx.rectangle(track.0, track.1, track.2, track.3);
+        ctx.color(&self.fg);
+        ctx.rectangle(fill.0, fill.1, fill.2, fill.3);
+        ctx.translate(-offset, 0.0);
+
+        let bw = self.bar_extent();
+        if let Some(ref mut label) = self.label {
+            ctx.translate(bw, 0.0);
+            label.draw(ctx, s, f, w - bw, h)?;
+            ctx.translate(-bw, 0.0);
+        }
+
+        Ok(())
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        let bw = self.bar_extent();
+        match self.label {
+            Some(ref mut label) => {
+                let (lw, lh) = label.current_extent(ctx, h)?;
+                Ok((bw + lw, lh))
+            }
+            None => Ok((bw, h)),
+        }
+    }
+
+    fn require_draw(&self) -> bool {
+        self.require_draw || matches!(&self.label, Some(l) if l.require_draw())
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.bg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw::{DrawCall, MockDrawContext};
+
+    fn text(txt: &str) -> Text {
+        let style = TextStyle {
+            font: "mono".into(),
+            point_size: 12,
+            fg: 0xffffff.into(),
+            bg: None,
+            padding: (0.0, 0.0),
+        };
+
+        Text::new(txt, &style, false, false)
+    }
+
+    #[test]
+    fn text_extent_is_cached_until_the_text_changes() {
+        let mut ctx = MockDrawContext::new(10.0, 10.0);
+        let mut t = text("1");
+
+        assert_eq!(t.current_extent(&mut ctx, 0.0).unwrap(), (10.0, 10.0));
+        assert_eq!(t.current_extent(&mut ctx, 0.0).unwrap(), (10.0, 10.0));
+        assert_eq!(ctx.times_measured(), 1);
+
+        t.get_text_mut().push('2');
+        assert_eq!(t.current_extent(&mut ctx, 0.0).unwrap(), (20.0, 10.0));
+
+        t.set_font("sans", 14);
+        t.current_extent(&mut ctx, 0.0).unwrap();
+        assert_eq!(ctx.times_measured(), 3);
+    }
+
+    test_cases! {
+        text_setters_only_redraw_on_change;
+        args: (update: fn(&mut Text), require_draw: bool, times_measured: usize);
+
+        case: set_text => (|t| t.set_text("2"), true, 2);
+        case: set_text_unchanged => (|t| t.set_text("1"), false, 1);
+        case: set_fg => (|t| t.set_fg(Color::from(0xff0000)), true, 1);
+        case: set_fg_unchanged => (|t| t.set_fg(Color::from(0xffffff)), false, 1);
+        case: set_bg => (|t| t.set_bg(Some(Color::from(0xff0000))), true, 1);
+        case: set_bg_unchanged => (|t| t.set_bg(None), false, 1);
+
+        body: {
+            let mut ctx = MockDrawContext::new(10.0, 10.0);
+            let mut t = text("1");
+            t.draw(&mut ctx, 0, false, 100.0, 10.0).unwrap();
+            assert!(!t.require_draw());
+
+            update(&mut t);
+            assert_eq!(t.require_draw(), require_draw);
+
+            // only changing the text needs it to be measured again
+            t.current_extent(&mut ctx, 10.0).unwrap();
+            assert_eq!(ctx.times_measured(), times_measured);
+        }
+    }
+
+    test_cases! {
+        segment_at;
+        args: (x: f64, expected: Option<usize>);
+
+        case: first => (0.0, Some(0));
+        case: boundary => (10.0, Some(1));
+        case: last => (34.0, Some(2));
+        case: past_the_end => (35.0, None);
+
+        body: {
+            assert_eq!(segment_at(&[10.0, 20.0, 5.0], x), expected);
+        }
+    }
+
+    #[test]
+    fn text_extent_is_remeasured_when_the_font_scale_changes() {
+        let mut ctx = MockDrawContext::new(10.0, 10.0);
+        let mut t = text("1");
+
+        assert_eq!(t.current_extent(&mut ctx, 0.0).unwrap(), (10.0, 10.0));
+        ctx.set_font_scale(2.0);
+        assert_eq!(t.current_extent(&mut ctx, 0.0).unwrap(), (20.0, 20.0));
+    }
+
+    test_cases! {
+        text_placement;
+        args: (greedy: bool, right_justified: bool, expected_x: f64);
+
+        case: left_aligned => (false, false, 2.0);
+        case: right_justified_but_not_greedy => (false, true, 2.0);
+        case: greedy_but_left_aligned => (true, false, 2.0);
+        case: greedy_and_right_justified => (true, true, 72.0);
+
+        body: {
+            let style = TextStyle {
+                font: "mono".into(),
+                point_size: 12,
+                fg: 0xffffff.into(),
+                bg: None,
+                padding: (2.0, 3.0),
+            };
+            let mut ctx = MockDrawContext::new(10.0, 10.0);
+            let mut t = Text::new("ab", &style, greedy, right_justified);
+
+            assert_eq!(t.current_extent(&mut ctx, 20.0).unwrap(), (25.0, 10.0));
+            t.draw(&mut ctx, 0, true, 95.0, 20.0).unwrap();
+            assert_eq!(ctx.drawn_text(), vec![("ab".into(), expected_x, 10.0)]);
+        }
+    }
+
+    #[test]
+    fn graph_points_are_right_aligned_and_scaled_to_max() {
+        let samples: VecDeque<f64> = vec![5.0, 10.0].into();
+        let points = graph_points(&samples, 4, 2.0, Some(10.0), 20.0);
+
+        assert_eq!(
+            points,
+            vec![
+                (4.0, 20.0),
+                (4.0, 10.0),
+                (5.0, 10.0),
+                (7.0, 0.0),
+                (8.0, 0.0),
+                (8.0, 20.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_points_scale_to_the_largest_sample_without_a_max() {
+        let samples: VecDeque<f64> = vec![2.0, 4.0].into();
+        let points = graph_points(&samples, 2, 1.0, None, 10.0);
+
+        assert_eq!(points[2], (0.5, 5.0));
+        assert_eq!(points[3], (1.5, 0.0));
+    }
+
+    #[test]
+    fn line_graph_keeps_the_most_recent_samples() {
+        let mut ctx = MockDrawContext::new(10.0, 10.0);
+        let mut g = LineGraph::new(3, 2.0, None, 0xffffff, None, Duration::from_secs(1), || {
+            None
+        });
+        for v in 1..=5 {
+            g.push(v as f64);
+        }
+
+        assert_eq!(g.samples(), &VecDeque::from(vec![3.0, 4.0, 5.0]));
+        assert!(g.require_draw());
+        assert_eq!(g.current_extent(&mut ctx, 20.0).unwrap(), (6.0, 20.0));
+
+        g.draw(&mut ctx, 0, true, 6.0, 20.0).unwrap();
+        assert!(!g.require_draw());
+        assert!(matches!(ctx.calls().last(), Some(DrawCall::Polygon(_))));
+    }
+
+    #[test]
+    fn text_background_fills_the_space_it_is_given() {
+        let style = TextStyle {
+            font: "mono".into(),
+            point_size: 12,
+            fg: 0xffffff.into(),
+            bg: Some(0x282828.into()),
+            padding: (0.0, 0.0),
+        };
+        let mut ctx = MockDrawContext::new(10.0, 10.0);
+        let mut t = Text::new("ab", &style, true, false);
+
+        t.draw(&mut ctx, 0, true, 50.0, 20.0).unwrap();
+        let calls = ctx.calls();
+        assert_eq!(calls[0], DrawCall::Color(0x282828.into()));
+        assert_eq!(calls[1], DrawCall::Rectangle(0.0, 0.0, 50.0, 20.0));
+    }
+
+    test_cases! {
+        animate_towards;
+        args: (shown: f64, target: f64, step: Option<f64>, expected: f64);
+
+        case: no_animation => (0.0, 0.8, None, 0.8);
+        case: step_up => (0.0, 0.8, Some(0.25), 0.25);
+        case: step_down => (0.8, 0.0, Some(0.25), 0.55);
+        case: final_step_lands_on_target => (0.7, 0.8, Some(0.25), 0.8);
+
+        body: {
+            assert!((animate_towards(shown, target, step) - expected).abs() < 1e-9);
+        }
+    }
+
+    test_cases! {
+        progress_bar_regions;
+        args: (orientation: Orientation, expected: (Rect, Rect));
+
+        case: horizontal => (
+            Orientation::Horizontal,
+            ((0.0, 6.0, 40.0, 8.0), (0.0, 6.0, 10.0, 8.0))
+        );
+        case: vertical_is_limited_to_bar_height => (
+            Orientation::Vertical,
+            ((0.0, 0.0, 8.0, 20.0), (0.0, 15.0, 8.0, 5.0))
+        );
+
+        body: {
+            assert_eq!(progress_bar_regions(0.25, orientation, 40.0, 8.0, 20.0), expected);
+        }
+    }
+
+    #[test]
+    fn progress_bar_keeps_redrawing_until_the_animation_finishes() {
+        let style = TextStyle {
+            font: "mono".into(),
+            point_size: 12,
+            fg: 0xffffff.into(),
+            bg: None,
+            padding: (0.0, 0.0),
+        };
+        let mut bar = ProgressBar::new(&style, 40.0, 8.0, 0x000000, Orientation::Horizontal);
+        bar.set_animation_step(Some(0.5));
+        bar.set_value(1.0);
+
+        bar.advance(false);
+        assert!(bar.require_draw());
+        bar.advance(false);
+        assert!(!bar.require_draw());
+
+        // on battery the fill jumps straight to the new value
+        bar.set_value(0.0);
+        bar.advance(true);
+        assert_eq!(bar.shown, 0.0);
+        assert!(!bar.require_draw());
+    }
+}
@@ -6,16 +6,29 @@
 //! [1]: crate::draw::Draw
 use crate::{
     core::{bindings::KeyPress, hooks::Hook, xconnection::XConn},
-    draw::{DrawContext, Result},
+    draw::{Color, DrawContext, Result},
 };
 
+use std::time::Duration;
+
 pub mod bar;
 pub mod base;
+pub mod stats;
+pub mod sys;
+#[cfg(feature = "volume")]
+pub mod volume;
 
 #[doc(inline)]
 pub use bar::*;
 #[doc(inline)]
 pub use base::*;
+#[doc(inline)]
+pub use stats::*;
+#[doc(inline)]
+pub use sys::*;
+#[cfg(feature = "volume")]
+#[doc(inline)]
+pub use volume::*;
 
 /// A status bar widget that can be rendered using a [DrawContext]
 pub trait Widget {
@@ -41,6 +54,26 @@ pub trait Widget {
      * space will be split evenly between all widgets.
      */
     fn is_greedy(&self) -> bool;
+
+    /// The color that this widget fills its background with, if it always fills it with a
+    /// single color.
+    ///
+    /// Used by [StatusBar][crate::draw::StatusBar] to pick the colors of the separators drawn
+    /// between widgets. Widgets that leave the background of the bar showing should return None.
+    fn background(&self) -> Option<Color> {
+        None
+    }
+
+    /// How often this widget needs to refresh its content when nothing else is happening.
+    ///
+    /// A [StatusBar][crate::draw::StatusBar] wakes the event loop at least this often so that
+    /// time based widgets can update from their [event_handled][1] hook without waiting for the
+    /// next X event. Widgets that only change in response to other hooks should return None.
+    ///
+    /// [1]: crate::core::hooks::Hook::event_handled
+    fn poll_interval(&self) -> Option<Duration> {
+        None
+    }
 }
 
 /**
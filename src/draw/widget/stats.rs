@@ -0,0 +1,687 @@
+//! Widgets that display CPU, memory and network usage read from `/proc`
+//!
+//! The files under `/proc` are cheap to read so, like [VpnStatus][1], these widgets refresh
+//! directly from the event loop at most once per refresh interval rather than polling on a
+//! background thread. Readings can be shown either as a compact figure or as a tiny inline graph
+//! of recent readings and can optionally be colored based on [Thresholds]. Each widget can also
+//! show its latest reading as the fill level of a [ProgressBar] via `set_bar`.
+//!
+//! [1]: crate::draw::widget::VpnStatus
+use crate::{
+    core::{hooks::Hook, manager::WindowManager, xconnection::XConn},
+    draw::{
+        widget::{sys::RefreshInterval, ProgressBar, Text},
+        Color, DrawContext, Result, TextStyle, Widget,
+    },
+};
+
+use std::{
+    collections::VecDeque,
+    fs,
+    time::{Duration, Instant},
+};
+
+const PROC_STAT: &str = "/proc/stat";
+const PROC_MEMINFO: &str = "/proc/meminfo";
+const PROC_NET_DEV: &str = "/proc/net/dev";
+const GRAPH_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How a usage widget shows its readings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UsageDisplay {
+    /// The most recent reading as a number
+    Figure,
+    /// A graph of the given number of most recent readings, one character per reading
+    Graph(usize),
+}
+
+/// Colors to use for a usage widget depending on how high its most recent reading is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thresholds {
+    /// Readings at or above this value are shown using `warning_color`
+    pub warning: f64,
+    /// Readings at or above this value are shown using `critical_color`
+    pub critical: f64,
+    /// The color used for readings below `warning`
+    pub normal_color: Color,
+    /// The color used for readings between `warning` and `critical`
+    pub warning_color: Color,
+    /// The color used for readings at or above `critical`
+    pub critical_color: Color,
+}
+
+impl Thresholds {
+    /// The color that a reading of `value` should be shown in
+    pub fn color_for(&self, value: f64) -> Color {
+        if value >= self.critical {
+            self.critical_color
+        } else if value >= self.warning {
+            self.warning_color
+        } else {
+            self.normal_color
+        }
+    }
+}
+
+// The text, display mode and reading history shared by each of the usage widgets
+#[derive(Clone, Debug, PartialEq)]
+struct UsageText {
+    txt: Text,
+    bar: Option<ProgressBar>,
+    peak: f64,
+    prefix: String,
+    display: UsageDisplay,
+    thresholds: Option<Thresholds>,
+    history: VecDeque<f64>,
+    interval: RefreshInterval,
+}
+
+impl UsageText {
+    fn new(
+        style: &TextStyle,
+        prefix: impl Into<String>,
+        display: UsageDisplay,
+        thresholds: Option<Thresholds>,
+        interval: Duration,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        Self {
+            txt: Text::new("", style, is_greedy, right_justified),
+            bar: None,
+            peak: 0.0,
+            prefix: prefix.into(),
+            display,
+            thresholds,
+            history: VecDeque::new(),
+            interval: RefreshInterval::new(interval),
+        }
+    }
+
+    // Record a new reading, rendering it using 'figure' if the widget is showing figures. Graphs
+    // are scaled so that 'max' fills a full character cell, with None scaling to the largest
+    // reading currently being shown. Bars are scaled in the same way but with None scaling to the
+    // largest reading seen so far.
+    fn push(&mut self, value: f64, figure: String, max: Option<f64>) {
+        if let Some(t) = self.thresholds {
+            self.txt.set_fg(t.color_for(value));
+        }
+
+        self.peak = self.peak.max(value);
+        let scale = max.unwrap_or(self.peak);
+        if let Some(bar) = self.bar.as_mut() {
+            bar.set_value(if scale > 0.0 { value / scale } else { 0.0 });
+            if let Some(t) = self.thresholds {
+                bar.set_fg(t.color_for(value));
+            }
+        }
+
+        let content = match self.display {
+            UsageDisplay::Figure => figure,
+            UsageDisplay::Graph(n) => {
+                self.history.push_back(value);
+                while self.history.len() > n {
+                    self.history.pop_front();
+                }
+                let max = max.unwrap_or_else(|| self.history.iter().cloned().fold(0.0, f64::max));
+                graph(self.history.iter().cloned(), max)
+            }
+        };
+
+        self.set_text(format!("{}{}", self.prefix, content));
+    }
+
+    fn unavailable(&mut self) {
+        if let Some(bar) = self.bar.as_mut() {
+            bar.set_value(0.0);
+        }
+        self.set_text(format!("{}?", self.prefix));
+    }
+
+    fn set_text(&mut self, txt: String) {
+        if let Some(bar) = self.bar.as_mut() {
+            bar.set_label(Some(txt.clone()));
+        }
+        self.txt.set_text(txt);
+    }
+
+    fn set_bar(&mut self, bar: Option<ProgressBar>) {
+        self.bar = bar;
+        let label = self.txt.get_text().clone();
+        if let Some(bar) = self.bar.as_mut() {
+            bar.set_label(Some(label));
+        }
+    }
+
+    // The widget actually being shown
+    fn shown(&mut self) -> &mut dyn Widget {
+        match self.bar.as_mut() {
+            Some(bar) => bar,
+            None => &mut self.txt,
+        }
+    }
+
+    fn shown_ref(&self) -> &dyn Widget {
+        match self.bar.as_ref() {
+            Some(bar) => bar,
+            None => &self.txt,
+        }
+    }
+}
+
+macro_rules! __usage_widget {
+    ($name:ident) => {
+        impl $name {
+            /// Show the latest reading as the fill level of `bar` with the usual text as its
+            /// label, or pass None to go back to only showing text.
+            pub fn set_bar(&mut self, bar: Option<ProgressBar>) {
+                self.usage.set_bar(bar);
+            }
+        }
+
+        impl<X> Hook<X> for $name
+        where
+            X: XConn,
+        {
+            fn startup(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+                self.refresh();
+                Ok(())
+            }
+
+            fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+                self.refresh();
+                Ok(())
+            }
+        }
+
+        impl Widget for $name {
+            fn draw(
+                &mut self,
+                ctx: &mut dyn DrawContext,
+                s: usize,
+                f: bool,
+                w: f64,
+                h: f64,
+            ) -> Result<()> {
+                self.usage.shown().draw(ctx, s, f, w, h)
+            }
+
+            fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+                self.usage.shown().current_extent(ctx, h)
+            }
+
+            fn require_draw(&self) -> bool {
+                self.usage.shown_ref().require_draw()
+            }
+
+            fn is_greedy(&self) -> bool {
+                self.usage.shown_ref().is_greedy()
+            }
+
+            fn background(&self) -> Option<Color> {
+                self.usage.shown_ref().background()
+            }
+
+            fn poll_interval(&self) -> Option<Duration> {
+                Some(self.usage.interval.period())
+            }
+        }
+    };
+}
+
+/// Display the percentage of CPU time spent busy across all cores, read from `/proc/stat`.
+///
+/// Usage is calculated between consecutive refreshes so nothing is shown until the second
+/// refresh. [Thresholds] are given as percentages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CpuUsage {
+    usage: UsageText,
+    previous: Option<CpuTimes>,
+}
+
+impl CpuUsage {
+    /// Create a new CpuUsage widget that takes a reading every `interval`
+    pub fn new(
+        style: &TextStyle,
+        display: UsageDisplay,
+        thresholds: Option<Thresholds>,
+        interval: Duration,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        Self {
+            usage: UsageText::new(
+                style,
+                "cpu ",
+                display,
+                thresholds,
+                interval,
+                is_greedy,
+                right_justified,
+            ),
+            previous: None,
+        }
+    }
+
+    fn refresh(&mut self) {
+        if !self.usage.interval.is_due() {
+            return;
+        }
+
+        let raw = fs::read_to_string(PROC_STAT).unwrap_or_default();
+        let current = match parse_cpu_times(&raw) {
+            Some(times) => times,
+            None => return self.usage.unavailable(),
+        };
+
+        if let Some(percent) = self.previous.and_then(|p| cpu_percent(p, current)) {
+            self.usage
+                .push(percent, format!("{:.0}%", percent), Some(100.0));
+        }
+        self.previous = Some(current);
+    }
+}
+
+__usage_widget!(CpuUsage);
+
+/// Display the percentage of memory in use, read from `/proc/meminfo`.
+///
+/// Memory that the kernel reports as being available (free memory along with caches that can be
+/// reclaimed) is not counted as being in use. [Thresholds] are given as percentages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryUsage {
+    usage: UsageText,
+}
+
+impl MemoryUsage {
+    /// Create a new MemoryUsage widget that takes a reading every `interval`
+    pub fn new(
+        style: &TextStyle,
+        display: UsageDisplay,
+        thresholds: Option<Thresholds>,
+        interval: Duration,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        Self {
+            usage: UsageText::new(
+                style,
+                "mem ",
+                display,
+                thresholds,
+                interval,
+                is_greedy,
+                right_justified,
+            ),
+        }
+    }
+
+    fn refresh(&mut self) {
+        if !self.usage.interval.is_due() {
+            return;
+        }
+
+        let raw = fs::read_to_string(PROC_MEMINFO).unwrap_or_default();
+        match parse_memory_percent(&raw) {
+            Some(percent) => self
+                .usage
+                .push(percent, format!("{:.0}%", percent), Some(100.0)),
+            None => self.usage.unavailable(),
+        }
+    }
+}
+
+__usage_widget!(MemoryUsage);
+
+/// Display the rate that data is being received and sent on a network interface, read from
+/// `/proc/net/dev`.
+///
+/// Rates are calculated between consecutive refreshes so nothing is shown until the second
+/// refresh. [Thresholds] are given in bytes per second of combined traffic and graphs are
+/// scaled to the busiest reading being shown.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkThroughput {
+    usage: UsageText,
+    interface: String,
+    previous: Option<(NetBytes, Instant)>,
+}
+
+impl NetworkThroughput {
+    /// Create a new NetworkThroughput widget for `interface` that takes a reading every
+    /// `interval`
+    pub fn new(
+        style: &TextStyle,
+        interface: impl Into<String>,
+        display: UsageDisplay,
+        thresholds: Option<Thresholds>,
+        interval: Duration,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        let interface = interface.into();
+
+        Self {
+            usage: UsageText::new(
+                style,
+                format!("{} ", interface),
+                display,
+                thresholds,
+                interval,
+                is_greedy,
+                right_justified,
+            ),
+            interface,
+            previous: None,
+        }
+    }
+
+    fn refresh(&mut self) {
+        if !self.usage.interval.is_due() {
+            return;
+        }
+
+        let raw = fs::read_to_string(PROC_NET_DEV).unwrap_or_default();
+        let current = match parse_net_dev(&raw, &self.interface) {
+            Some(bytes) => (bytes, Instant::now()),
+            None => return self.usage.unavailable(),
+        };
+
+        if let Some((prev, at)) = self.previous {
+            let secs = current.1.duration_since(at).as_secs_f64();
+            if secs > 0.0 {
+                let rx = current.0.rx.saturating_sub(prev.rx) as f64 / secs;
+                let tx = current.0.tx.saturating_sub(prev.tx) as f64 / secs;
+                let figure = format!("↓{} ↑{}", human_rate(rx), human_rate(tx));
+                self.usage.push(rx + tx, figure, None);
+            }
+        }
+        self.previous = Some(current);
+    }
+}
+
+__usage_widget!(NetworkThroughput);
+
+// Cumulative busy and total CPU time across all cores, in USER_HZ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+// The first line of /proc/stat is the total across all cores:
+//   cpu  user nice system idle iowait irq softirq steal guest guest_nice
+// guest time is already included in user and nice so it is ignored.
+fn parse_cpu_times(raw: &str) -> Option<CpuTimes> {
+    let line = raw.lines().find(|l| l.starts_with("cpu "))?;
+    let fields: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .take(8)
+        .map(|f| f.parse().ok())
+        .collect::<Option<_>>()?;
+
+    if fields.len() < 4 {
+        return None;
+    }
+
+    let total: u64 = fields.iter().sum();
+    let idle = fields[3] + fields.get(4).copied().unwrap_or(0);
+
+    Some(CpuTimes {
+        busy: total - idle,
+        total,
+    })
+}
+
+fn cpu_percent(prev: CpuTimes, current: CpuTimes) -> Option<f64> {
+    let total = current.total.checked_sub(prev.total)?;
+    let busy = current.busy.checked_sub(prev.busy)?;
+    if total == 0 {
+        return None;
+    }
+
+    Some(100.0 * busy as f64 / total as f64)
+}
+
+// Lines of /proc/meminfo look like "MemAvailable:    1234567 kB"
+fn parse_memory_percent(raw: &str) -> Option<f64> {
+    let field = |name: &str| -> Option<f64> {
+        raw.lines()
+            .find_map(|l| l.strip_prefix(name))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|v| v.parse().ok())
+    };
+
+    let total = field("MemTotal:")?;
+    let available = field("MemAvailable:")?;
+    if total <= 0.0 {
+        return None;
+    }
+
+    Some(100.0 * (total - available) / total)
+}
+
+// Cumulative bytes received and sent on an interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NetBytes {
+    rx: u64,
+    tx: u64,
+}
+
+// After two header lines, each line of /proc/net/dev is an interface name followed by 8 receive
+// and 8 transmit counters, with bytes being the first of each:
+//   wlan0: 1234 10 0 0 0 0 0 0 5678 20 0 0 0 0 0 0
+fn parse_net_dev(raw: &str, interface: &str) -> Option<NetBytes> {
+    let counters = raw.lines().find_map(|l| {
+        let (name, rest) = l.split_once(':')?;
+        if name.trim() == interface {
+            Some(rest)
+        } else {
+            None
+        }
+    })?;
+
+    let fields: Vec<u64> = counters
+        .split_whitespace()
+        .map(|f| f.parse().ok())
+        .collect::<Option<_>>()?;
+
+    Some(NetBytes {
+        rx: *fields.first()?,
+        tx: *fields.get(8)?,
+    })
+}
+
+// A compact rendering of a rate in bytes per second: "512B", "1.5K", "23M"
+fn human_rate(bytes_per_sec: f64) -> String {
+    let mut value = bytes_per_sec;
+    for unit in &["B", "K", "M", "G"] {
+        if value < 1024.0 || *unit == "G" {
+            return if *unit != "B" && value < 10.0 {
+                format!("{:.1}{}", value, unit)
+            } else {
+                format!("{:.0}{}", value, unit)
+            };
+        }
+        value /= 1024.0;
+    }
+
+    unreachable!("the loop always returns on the last unit")
+}
+
+// One block character per value with 'max' drawn as a full block
+fn graph(values: impl Iterator<Item = f64>, max: f64) -> String {
+    let top = (GRAPH_BARS.len() - 1) as f64;
+
+    values
+        .map(|v| {
+            let level = if max > 0.0 { v / max * top } else { 0.0 };
+            GRAPH_BARS[level.round().max(0.0).min(top) as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw::widget::Orientation;
+
+    const STAT: &str = "cpu  100 0 50 800 50 0 0 0 0 0
+cpu0 50 0 25 400 25 0 0 0 0 0
+intr 1234";
+
+    const MEMINFO: &str = "MemTotal:        8000000 kB
+MemFree:         1000000 kB
+MemAvailable:    6000000 kB
+Buffers:          100000 kB";
+
+    const NET_DEV: &str = "Inter-|   Receive                            |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    1000      10    0    0    0     0          0         0     1000      10    0    0    0     0       0          0
+ wlan0: 5000000    4000    0    0    0     0          0         0   250000    2000    0    0    0     0       0          0";
+
+    #[test]
+    fn parse_cpu_times_uses_the_total_line() {
+        assert_eq!(
+            parse_cpu_times(STAT),
+            Some(CpuTimes {
+                busy: 150,
+                total: 1000
+            })
+        );
+    }
+
+    test_cases! {
+        cpu_percent;
+        args: (busy: u64, total: u64, expected: Option<f64>);
+
+        case: half_busy => (250, 1200, Some(50.0));
+        case: idle => (150, 1100, Some(0.0));
+        case: no_time_passed => (150, 1000, None);
+        case: counters_reset => (10, 100, None);
+
+        body: {
+            let prev = CpuTimes { busy: 150, total: 1000 };
+            assert_eq!(cpu_percent(prev, CpuTimes { busy, total }), expected);
+        }
+    }
+
+    #[test]
+    fn parse_memory_percent_ignores_available_memory() {
+        assert_eq!(parse_memory_percent(MEMINFO), Some(25.0));
+        assert_eq!(parse_memory_percent("MemTotal: 100 kB"), None);
+    }
+
+    test_cases! {
+        parse_net_dev;
+        args: (interface: &str, expected: Option<NetBytes>);
+
+        case: loopback => ("lo", Some(NetBytes { rx: 1000, tx: 1000 }));
+        case: wireless => ("wlan0", Some(NetBytes { rx: 5000000, tx: 250000 }));
+        case: unknown => ("eth0", None);
+
+        body: {
+            assert_eq!(parse_net_dev(NET_DEV, interface), expected);
+        }
+    }
+
+    test_cases! {
+        human_rate;
+        args: (bytes_per_sec: f64, expected: &str);
+
+        case: bytes => (512.0, "512B");
+        case: small_kilobytes => (1536.0, "1.5K");
+        case: kilobytes => (23.0 * 1024.0, "23K");
+        case: megabytes => (5.0 * 1024.0 * 1024.0, "5.0M");
+        case: huge => (4096.0 * 1024.0 * 1024.0 * 1024.0, "4096G");
+
+        body: {
+            assert_eq!(human_rate(bytes_per_sec), expected);
+        }
+    }
+
+    test_cases! {
+        graph;
+        args: (values: &[f64], max: f64, expected: &str);
+
+        case: empty => (&[], 100.0, "");
+        case: scaled_to_max => (&[0.0, 50.0, 100.0], 100.0, "▁▅█");
+        case: above_max_is_clamped => (&[200.0], 100.0, "█");
+        case: zero_max => (&[0.0, 0.0], 0.0, "▁▁");
+
+        body: {
+            assert_eq!(graph(values.iter().cloned(), max), expected);
+        }
+    }
+
+    #[test]
+    fn thresholds_pick_the_highest_matching_color() {
+        let t = Thresholds {
+            warning: 50.0,
+            critical: 90.0,
+            normal_color: 0x00ff00.into(),
+            warning_color: 0xffff00.into(),
+            critical_color: 0xff0000.into(),
+        };
+
+        assert_eq!(t.color_for(10.0), Color::from(0x00ff00));
+        assert_eq!(t.color_for(50.0), Color::from(0xffff00));
+        assert_eq!(t.color_for(95.0), Color::from(0xff0000));
+    }
+
+    #[test]
+    fn graphs_only_keep_the_most_recent_readings() {
+        let style = TextStyle {
+            font: "mono".into(),
+            point_size: 10,
+            fg: 0xffffff.into(),
+            bg: None,
+            padding: (0.0, 0.0),
+        };
+        let mut usage = UsageText::new(
+            &style,
+            "cpu ",
+            UsageDisplay::Graph(2),
+            None,
+            Duration::from_secs(1),
+            false,
+            false,
+        );
+
+        for v in &[100.0, 0.0, 50.0] {
+            usage.push(*v, String::new(), Some(100.0));
+        }
+
+        assert_eq!(usage.history, vec![0.0, 50.0]);
+    }
+
+    #[test]
+    fn bars_without_a_max_are_scaled_to_the_largest_reading() {
+        let style = TextStyle {
+            font: "mono".into(),
+            point_size: 10,
+            fg: 0xffffff.into(),
+            bg: None,
+            padding: (0.0, 0.0),
+        };
+        let mut usage = UsageText::new(
+            &style,
+            "wlan0 ",
+            UsageDisplay::Figure,
+            None,
+            Duration::from_secs(1),
+            false,
+            false,
+        );
+        let bar = ProgressBar::new(&style, 40.0, 8.0, 0x000000, Orientation::Horizontal);
+        usage.set_bar(Some(bar));
+
+        usage.push(400.0, "400B/s".into(), None);
+        usage.push(100.0, "100B/s".into(), None);
+        assert_eq!(usage.bar.as_ref().map(|b| b.value()), Some(0.25));
+
+        usage.push(25.0, "25%".into(), Some(100.0));
+        assert_eq!(usage.bar.as_ref().map(|b| b.value()), Some(0.25));
+        assert_eq!(usage.txt.get_text(), "wlan0 25%");
+    }
+}
@@ -0,0 +1,928 @@
+//! Widgets that display system state by polling external programs
+//!
+//! Each of the widgets in this module re-checks the state it is displaying at most once per
+//! refresh interval. Widgets that need to run an external program do so on a background thread
+//! so that a slow program never blocks the [WindowManager] event loop. Results are picked up from
+//! the event loop so a widget will only update its content after the next X event has been
+//! processed.
+//!
+//! To reduce wakeups while running on battery, refresh intervals are multiplied by
+//! [BATTERY_INTERVAL_FACTOR] whenever `/sys/class/power_supply` reports that no external power
+//! is connected. The power state is checked by a single background thread (started by the first
+//! widget that needs it) so switching back to AC restores the normal intervals without any
+//! configuration.
+use crate::{
+    core::{
+        bindings::{ClickKind, KeyEventHandler, MouseButton},
+        data_types::Point,
+        helpers::{spawn_for_output, spawn_for_output_with_args},
+        hooks::Hook,
+        manager::WindowManager,
+        process::{spawn, spawn_with_args},
+        xconnection::XConn,
+    },
+    draw::{isolate_bidi, widget::Text, Color, DrawContext, Result, TextStyle, Widget},
+};
+
+use std::{
+    fmt, fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Once,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How many times longer widgets in this module wait between refreshes while on battery
+pub const BATTERY_INTERVAL_FACTOR: u32 = 4;
+
+const SYS_CLASS_POWER_SUPPLY: &str = "/sys/class/power_supply";
+const POWER_STATE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+static ON_BATTERY: AtomicBool = AtomicBool::new(false);
+static WATCH_POWER_STATE: Once = Once::new();
+
+// Whether the system is currently running on battery, as last seen by the power state watcher.
+// The watcher thread is started on the first call and runs for the lifetime of the process.
+pub(crate) fn on_battery() -> bool {
+    WATCH_POWER_STATE.call_once(|| {
+        let sys_class_power_supply = Path::new(SYS_CLASS_POWER_SUPPLY);
+        ON_BATTERY.store(read_on_battery(sys_class_power_supply), Ordering::Relaxed);
+
+        thread::spawn(move || loop {
+            thread::sleep(POWER_STATE_CHECK_INTERVAL);
+            let current = read_on_battery(sys_class_power_supply);
+            if ON_BATTERY.swap(current, Ordering::Relaxed) != current {
+                debug!(on_battery = current, "power state changed");
+            }
+        });
+    });
+
+    ON_BATTERY.load(Ordering::Relaxed)
+}
+
+// The period to wait before the next refresh given the current power state
+fn scaled_period(period: Duration, on_battery: bool) -> Duration {
+    if on_battery {
+        period * BATTERY_INTERVAL_FACTOR
+    } else {
+        period
+    }
+}
+
+fn read_on_battery(sys_class_power_supply: &Path) -> bool {
+    let supplies = match fs::read_dir(sys_class_power_supply) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| {
+                let read = |f: &str| fs::read_to_string(e.path().join(f)).unwrap_or_default();
+                (read("type"), read("online"))
+            })
+            .collect::<Vec<_>>(),
+        Err(_) => return false,
+    };
+
+    parse_on_battery(&supplies)
+}
+
+// Each supply is given as the contents of its 'type' and 'online' files. We are on battery if
+// there is a battery and none of the other supplies (mains, USB-C etc) are online: machines
+// without a battery are always treated as being on AC.
+fn parse_on_battery(supplies: &[(String, String)]) -> bool {
+    let has_battery = supplies.iter().any(|(kind, _)| kind.trim() == "Battery");
+    let external_power = supplies
+        .iter()
+        .any(|(kind, online)| kind.trim() != "Battery" && online.trim() == "1");
+
+    has_battery && !external_power
+}
+
+// Tracks when a polling widget last refreshed its state
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RefreshInterval {
+    period: Duration,
+    last: Option<Instant>,
+}
+
+impl RefreshInterval {
+    pub(crate) fn new(period: Duration) -> Self {
+        Self { period, last: None }
+    }
+
+    // The current time between refreshes, taking into account whether we are on battery
+    pub(crate) fn period(&self) -> Duration {
+        scaled_period(self.period, on_battery())
+    }
+
+    // Returns true (and resets the interval) if a refresh is now due
+    pub(crate) fn is_due(&mut self) -> bool {
+        let now = Instant::now();
+        match self.last {
+            Some(t) if now.duration_since(t) < scaled_period(self.period, on_battery()) => false,
+            _ => {
+                self.last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+type PollResult<T> = std::result::Result<T, String>;
+type PollQuery<T> = Arc<dyn Fn() -> crate::Result<T> + Send + Sync>;
+
+// Runs a blocking query on a background thread once per period, keeping the most recent result
+// so that it can be read from the event loop without waiting. The thread is started on the first
+// call to 'latest' and exits once the poller has been dropped.
+pub(crate) struct BackgroundPoll<T> {
+    period: Duration,
+    query: PollQuery<T>,
+    latest: Arc<Mutex<Option<PollResult<T>>>>,
+    started: bool,
+}
+
+impl<T> fmt::Debug for BackgroundPoll<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackgroundPoll")
+            .field("period", &self.period)
+            .field("started", &self.started)
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> Clone for BackgroundPoll<T> {
+    fn clone(&self) -> Self {
+        Self {
+            period: self.period,
+            query: Arc::clone(&self.query),
+            latest: Arc::new(Mutex::new(None)),
+            started: false,
+        }
+    }
+}
+
+impl<T> PartialEq for BackgroundPoll<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.period == other.period && Arc::ptr_eq(&self.latest, &other.latest)
+    }
+}
+
+impl<T: Send + 'static> BackgroundPoll<T> {
+    pub(crate) fn new<F>(period: Duration, query: F) -> Self
+    where
+        F: Fn() -> crate::Result<T> + Send + Sync + 'static,
+    {
+        Self {
+            period,
+            query: Arc::new(query),
+            latest: Arc::new(Mutex::new(None)),
+            started: false,
+        }
+    }
+
+    // The current time between queries, taking into account whether we are on battery
+    pub(crate) fn period(&self) -> Duration {
+        scaled_period(self.period, on_battery())
+    }
+
+    // Take the result of the most recent query if there has been one since the last call
+    pub(crate) fn latest(&mut self) -> Option<PollResult<T>> {
+        if !self.started {
+            self.started = true;
+            self.spawn();
+        }
+
+        self.latest.lock().ok()?.take()
+    }
+
+    fn spawn(&self) {
+        let (period, query) = (self.period, Arc::clone(&self.query));
+        let latest = Arc::downgrade(&self.latest);
+
+        thread::spawn(move || loop {
+            let result = query().map_err(|e| e.to_string());
+            match latest.upgrade() {
+                Some(shared) => match shared.lock() {
+                    Ok(mut guard) => *guard = Some(result),
+                    Err(_) => return,
+                },
+                None => return, // the widget has been dropped
+            }
+            thread::sleep(scaled_period(period, on_battery()));
+        });
+    }
+}
+
+/// The current state of the bluetooth adapter as reported by `bluetoothctl`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BluetoothState {
+    /// Whether or not the adapter is currently powered on
+    pub powered: bool,
+    /// The name of the first connected device (if there is one)
+    pub device: Option<String>,
+    /// The battery percentage of the connected device if it is reported
+    pub battery: Option<u8>,
+}
+
+impl BluetoothState {
+    /// Query the current bluetooth state using `bluetoothctl`.
+    ///
+    /// This blocks until `bluetoothctl` has exited so it should not be called from the event loop.
+    pub fn query() -> crate::Result<Self> {
+        let powered = parse_powered(&spawn_for_output("bluetoothctl show")?);
+        let address = if powered {
+            parse_connected_address(&spawn_for_output("bluetoothctl devices Connected")?)
+        } else {
+            None
+        };
+        let (device, battery) = match address {
+            Some(addr) => parse_device_info(&spawn_for_output_with_args(
+                "bluetoothctl",
+                &["info", &addr],
+            )?),
+            None => (None, None),
+        };
+
+        Ok(Self {
+            powered,
+            device,
+            battery,
+        })
+    }
+
+    fn render(&self, icon: &str) -> String {
+        match (self.powered, &self.device, self.battery) {
+            (false, _, _) => format!("{} off", icon),
+            (true, None, _) => format!("{} on", icon),
+            (true, Some(d), None) => format!("{} {}", icon, isolate_bidi(d)),
+            (true, Some(d), Some(b)) => format!("{} {} {}%", icon, isolate_bidi(d), b),
+        }
+    }
+}
+
+// Pull the power state out of the output of `bluetoothctl show`
+fn parse_powered(raw: &str) -> bool {
+    raw.lines()
+        .filter_map(|l| l.trim().strip_prefix("Powered:"))
+        .any(|v| v.trim() == "yes")
+}
+
+// Pull the address of the first connected device out of the output of
+// `bluetoothctl devices Connected`, lines of which look like "Device AA:BB:CC:DD:EE:FF name"
+fn parse_connected_address(raw: &str) -> Option<String> {
+    raw.lines()
+        .filter_map(|l| l.trim().strip_prefix("Device "))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .map(|addr| addr.to_string())
+        .next()
+}
+
+// Pull the connected device name and battery level out of the output of `bluetoothctl info`
+//
+// Battery is reported as "Battery Percentage: 0x55 (85)" so we take the bracketed decimal value.
+fn parse_device_info(raw: &str) -> (Option<String>, Option<u8>) {
+    let mut connected = false;
+    let mut name = None;
+    let mut battery = None;
+
+    for line in raw.lines().map(|l| l.trim()) {
+        if let Some(v) = line.strip_prefix("Connected:") {
+            connected = v.trim() == "yes";
+        } else if let Some(v) = line.strip_prefix("Alias:") {
+            name = Some(v.trim().to_string());
+        } else if let Some(v) = line.strip_prefix("Battery Percentage:") {
+            battery = v
+                .split(&['(', ')'][..])
+                .nth(1)
+                .and_then(|s| s.trim().parse().ok());
+        }
+    }
+
+    if connected {
+        (name, battery)
+    } else {
+        (None, None)
+    }
+}
+
+/// Display the power state of the bluetooth adapter along with the name and battery level of the
+/// connected device (if there is one).
+///
+/// State is read using `bluetoothctl` on a background thread so the `bluez-utils` package (or
+/// your distribution's equivalent) needs to be installed. The power state can be toggled by left
+/// clicking on the widget in a [StatusBar][crate::draw::StatusBar] or via the key binding action
+/// returned by [BluetoothStatus::toggle_power].
+#[derive(Clone, Debug)]
+pub struct BluetoothStatus {
+    txt: Text,
+    icon: String,
+    poll: BackgroundPoll<BluetoothState>,
+    // the power state from the most recent query, shared with the polling thread
+    powered: Arc<AtomicBool>,
+}
+
+impl PartialEq for BluetoothStatus {
+    fn eq(&self, other: &Self) -> bool {
+        self.txt == other.txt && self.icon == other.icon && self.poll == other.poll
+    }
+}
+
+impl BluetoothStatus {
+    /// Create a new BluetoothStatus widget that checks for changes every `interval`
+    pub fn new(
+        style: &TextStyle,
+        icon: impl Into<String>,
+        interval: Duration,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        let powered = Arc::new(AtomicBool::new(false));
+        let shared = Arc::clone(&powered);
+
+        Self {
+            txt: Text::new("", style, is_greedy, right_justified),
+            icon: icon.into(),
+            poll: BackgroundPoll::new(interval, move || {
+                let state = BluetoothState::query()?;
+                shared.store(state.powered, Ordering::Relaxed);
+                Ok(state)
+            }),
+            powered,
+        }
+    }
+
+    /// A key binding action that toggles the power state of the bluetooth adapter.
+    ///
+    /// The current power state is taken from the most recent background query rather than asking
+    /// `bluetoothctl` again so this needs to be created before the widget is added to a bar.
+    pub fn toggle_power<X: XConn>(&self) -> KeyEventHandler<X> {
+        let powered = Arc::clone(&self.powered);
+        Box::new(move |_: &mut WindowManager<X>| toggle_bluetooth_power(&powered))
+    }
+
+    fn refresh(&mut self) {
+        match self.poll.latest() {
+            None => (), // no change since we last checked
+            Some(Ok(state)) => self.txt.set_text(state.render(&self.icon)),
+            Some(Err(e)) => {
+                warn!("unable to query bluetooth state: {}", e);
+                self.txt.set_text(format!("{} ?", self.icon));
+            }
+        }
+    }
+}
+
+fn toggle_bluetooth_power(powered: &AtomicBool) -> crate::Result<()> {
+    // Flipped straight away so that repeated toggles work before the next query
+    let was_powered = powered.fetch_xor(true, Ordering::Relaxed);
+    let state = if was_powered { "off" } else { "on" };
+    spawn_with_args("bluetoothctl", &["power", state])
+}
+
+impl<X> Hook<X> for BluetoothStatus
+where
+    X: XConn,
+{
+    fn startup(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn bar_clicked(
+        &mut self,
+        _: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        _: Point,
+    ) -> crate::Result<()> {
+        if button == MouseButton::Left && kind == ClickKind::Single {
+            toggle_bluetooth_power(&self.powered)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Widget for BluetoothStatus {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        self.txt.is_greedy()
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
+
+    fn poll_interval(&self) -> Option<Duration> {
+        Some(self.poll.period())
+    }
+}
+
+/// Display which of a set of VPN interfaces (e.g. WireGuard tunnels) are currently up.
+///
+/// Interface state is read from `/sys/class/net` so no external programs are required. The text
+/// is rendered using `up_color` if at least one interface is up and `down_color` otherwise. Tunnels
+/// can be brought up and down via the key binding action returned by [VpnStatus::toggle_tunnel],
+/// or by left clicking on the widget in a [StatusBar][crate::draw::StatusBar] once a tunnel has
+/// been set using [VpnStatus::set_click_tunnel].
+#[derive(Clone, Debug, PartialEq)]
+pub struct VpnStatus {
+    txt: Text,
+    interfaces: Vec<String>,
+    up_color: Color,
+    down_color: Color,
+    interval: RefreshInterval,
+    click_tunnel: Option<Tunnel>,
+}
+
+// An interface along with the commands used to bring it up and down
+#[derive(Clone, Debug, PartialEq)]
+struct Tunnel {
+    interface: String,
+    up_cmd: String,
+    down_cmd: String,
+}
+
+impl Tunnel {
+    fn toggle(&self) -> crate::Result<()> {
+        if interface_is_up(Path::new(SYS_CLASS_NET), &self.interface) {
+            spawn(&self.down_cmd)
+        } else {
+            spawn(&self.up_cmd)
+        }
+    }
+}
+
+impl VpnStatus {
+    /// Create a new VpnStatus widget that checks the state of `interfaces` every `interval`
+    pub fn new(
+        style: &TextStyle,
+        interfaces: Vec<impl Into<String>>,
+        up_color: impl Into<Color>,
+        down_color: impl Into<Color>,
+        interval: Duration,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        Self {
+            txt: Text::new("", style, is_greedy, right_justified),
+            interfaces: interfaces.into_iter().map(|i| i.into()).collect(),
+            up_color: up_color.into(),
+            down_color: down_color.into(),
+            interval: RefreshInterval::new(interval),
+            click_tunnel: None,
+        }
+    }
+
+    /// Toggle `interface` in the same way as [toggle_tunnel][VpnStatus::toggle_tunnel] when the
+    /// widget is left clicked.
+    pub fn set_click_tunnel(
+        &mut self,
+        interface: impl Into<String>,
+        up_cmd: impl Into<String>,
+        down_cmd: impl Into<String>,
+    ) {
+        self.click_tunnel = Some(Tunnel {
+            interface: interface.into(),
+            up_cmd: up_cmd.into(),
+            down_cmd: down_cmd.into(),
+        });
+    }
+
+    /// A key binding action that runs `up_cmd` if `interface` is currently down and `down_cmd`
+    /// if it is currently up.
+    ///
+    /// ```no_run
+    /// # use penrose::{draw::widget::VpnStatus, __test_helpers::TestXConn};
+    /// let toggle = VpnStatus::toggle_tunnel::<TestXConn>(
+    ///     "wg0",
+    ///     "sudo wg-quick up wg0",
+    ///     "sudo wg-quick down wg0",
+    /// );
+    /// ```
+    pub fn toggle_tunnel<X: XConn>(
+        interface: impl Into<String>,
+        up_cmd: impl Into<String>,
+        down_cmd: impl Into<String>,
+    ) -> KeyEventHandler<X> {
+        let tunnel = Tunnel {
+            interface: interface.into(),
+            up_cmd: up_cmd.into(),
+            down_cmd: down_cmd.into(),
+        };
+        Box::new(move |_: &mut WindowManager<X>| tunnel.toggle())
+    }
+
+    fn refresh(&mut self) {
+        if !self.interval.is_due() {
+            return;
+        }
+
+        let up: Vec<&str> = self
+            .interfaces
+            .iter()
+            .filter(|i| interface_is_up(Path::new(SYS_CLASS_NET), i))
+            .map(|i| i.as_ref())
+            .collect();
+
+        if up.is_empty() {
+            self.txt.set_fg(self.down_color);
+            self.txt.set_text("vpn: down");
+        } else {
+            self.txt.set_fg(self.up_color);
+            self.txt.set_text(format!("vpn: {}", up.join(",")));
+        }
+    }
+}
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+
+fn interface_is_up(sys_class_net: &Path, interface: &str) -> bool {
+    match fs::read_to_string(sys_class_net.join(interface).join("operstate")) {
+        Ok(state) => parse_operstate(&state),
+        Err(_) => false,
+    }
+}
+
+// Interfaces that are up report "up" in their operstate. WireGuard (and other point-to-point)
+// interfaces report "unknown" as they have no carrier to detect, so we treat anything other
+// than "down" as being up so long as the interface exists.
+fn parse_operstate(raw: &str) -> bool {
+    raw.trim() != "down"
+}
+
+impl<X> Hook<X> for VpnStatus
+where
+    X: XConn,
+{
+    fn startup(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn bar_clicked(
+        &mut self,
+        _: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        _: Point,
+    ) -> crate::Result<()> {
+        match self.click_tunnel {
+            Some(ref tunnel) if button == MouseButton::Left && kind == ClickKind::Single => {
+                tunnel.toggle()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Widget for VpnStatus {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        self.txt.is_greedy()
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
+
+    fn poll_interval(&self) -> Option<Duration> {
+        Some(self.interval.period())
+    }
+}
+
+/// Display the state of a set of systemd user units, highlighting any that have failed.
+///
+/// Unit state is read on a background thread using `systemctl --user` which queries the systemd
+/// user manager over D-Bus on our behalf. The text is rendered using `ok_color` while all units
+/// are healthy and `failed_color` (along with the names of the failed units) otherwise. Failed
+/// units can be restarted via the key binding action returned by [SystemdUnits::restart_failed]
+/// or by left clicking on the widget in a [StatusBar][crate::draw::StatusBar].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemdUnits {
+    txt: Text,
+    units: Vec<String>,
+    ok_color: Color,
+    failed_color: Color,
+    poll: BackgroundPoll<Vec<String>>,
+}
+
+impl SystemdUnits {
+    /// Create a new SystemdUnits widget that checks the state of `units` every `interval`
+    pub fn new(
+        style: &TextStyle,
+        units: Vec<impl Into<String>>,
+        ok_color: impl Into<Color>,
+        failed_color: impl Into<Color>,
+        interval: Duration,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        let units: Vec<String> = units.into_iter().map(|u| u.into()).collect();
+        let polled = units.clone();
+
+        Self {
+            txt: Text::new("", style, is_greedy, right_justified),
+            units,
+            ok_color: ok_color.into(),
+            failed_color: failed_color.into(),
+            poll: BackgroundPoll::new(interval, move || failed_units(&polled)),
+        }
+    }
+
+    /// A key binding action that restarts any of `units` that are currently in a failed state.
+    ///
+    /// Unit state is checked on a background thread so that the window manager is not blocked
+    /// waiting on `systemctl`.
+    ///
+    /// ```no_run
+    /// # use penrose::{draw::widget::SystemdUnits, __test_helpers::TestXConn};
+    /// let restart = SystemdUnits::restart_failed::<TestXConn>(vec!["picom", "syncthing"]);
+    /// ```
+    pub fn restart_failed<X: XConn>(units: Vec<impl Into<String>>) -> KeyEventHandler<X> {
+        let units: Vec<String> = units.into_iter().map(|u| u.into()).collect();
+        Box::new(move |_: &mut WindowManager<X>| {
+            restart_failed_units(units.clone());
+            Ok(())
+        })
+    }
+
+    fn refresh(&mut self) {
+        match self.poll.latest() {
+            None => (), // no change since we last checked
+            Some(Ok(failed)) if failed.is_empty() => {
+                self.txt.set_fg(self.ok_color);
+                self.txt.set_text("units: ok");
+            }
+            Some(Ok(failed)) => {
+                self.txt.set_fg(self.failed_color);
+                self.txt.set_text(format!("failed: {}", failed.join(",")));
+            }
+            Some(Err(e)) => {
+                warn!("unable to query systemd unit state: {}", e);
+                self.txt.set_fg(self.failed_color);
+                self.txt.set_text("units: ?");
+            }
+        }
+    }
+}
+
+// Ask systemd for the state of each unit and return the names of those that have failed
+fn failed_units(units: &[String]) -> crate::Result<Vec<String>> {
+    if units.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut args = vec!["--user", "is-active"];
+    args.extend(units.iter().map(|u| u.as_str()));
+    let raw = spawn_for_output_with_args("systemctl", &args)?;
+
+    Ok(parse_failed_units(units, &raw))
+}
+
+// Restart any of `units` that have failed on a background thread so that we don't block waiting
+// on systemctl
+fn restart_failed_units(units: Vec<String>) {
+    thread::spawn(move || {
+        let failed = match failed_units(&units) {
+            Ok(failed) => failed,
+            Err(e) => return warn!("unable to query systemd unit state: {}", e),
+        };
+        for unit in failed {
+            if let Err(e) = spawn_with_args("systemctl", &["--user", "restart", &unit]) {
+                warn!(%unit, "unable to restart systemd unit: {}", e);
+            }
+        }
+    });
+}
+
+// `systemctl is-active` prints one state per line in the same order as the units it was given
+fn parse_failed_units(units: &[String], raw: &str) -> Vec<String> {
+    units
+        .iter()
+        .zip(raw.lines())
+        .filter(|(_, state)| state.trim() == "failed")
+        .map(|(unit, _)| unit.clone())
+        .collect()
+}
+
+impl<X> Hook<X> for SystemdUnits
+where
+    X: XConn,
+{
+    fn startup(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn bar_clicked(
+        &mut self,
+        _: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        _: Point,
+    ) -> crate::Result<()> {
+        if button == MouseButton::Left && kind == ClickKind::Single {
+            restart_failed_units(self.units.clone());
+        }
+        Ok(())
+    }
+}
+
+impl Widget for SystemdUnits {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.txt.draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.txt.current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.txt.require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        self.txt.is_greedy()
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.txt.background()
+    }
+
+    fn poll_interval(&self) -> Option<Duration> {
+        Some(self.poll.period())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHOW: &str = "Controller 00:11:22:33:44:55 (public)
+	Name: host
+	Alias: host
+	Powered: yes
+	Discoverable: no";
+
+    const INFO: &str = "Device AA:BB:CC:DD:EE:FF (public)
+	Name: WH-1000XM4
+	Alias: WH-1000XM4
+	Paired: yes
+	Connected: yes
+	Battery Percentage: 0x55 (85)";
+
+    #[test]
+    fn parse_powered_works() {
+        assert!(parse_powered(SHOW));
+        assert!(!parse_powered(&SHOW.replace("Powered: yes", "Powered: no")));
+    }
+
+    #[test]
+    fn parse_connected_address_takes_the_first_device() {
+        let raw = "Device AA:BB:CC:DD:EE:FF WH-1000XM4\nDevice 11:22:33:44:55:66 Keyboard\n";
+
+        assert_eq!(
+            parse_connected_address(raw),
+            Some("AA:BB:CC:DD:EE:FF".to_string())
+        );
+        assert_eq!(parse_connected_address(""), None);
+    }
+
+    #[test]
+    fn parse_device_info_works() {
+        assert_eq!(
+            parse_device_info(INFO),
+            (Some("WH-1000XM4".to_string()), Some(85))
+        );
+    }
+
+    #[test]
+    fn parse_device_info_ignores_disconnected_devices() {
+        let raw = INFO.replace("Connected: yes", "Connected: no");
+        assert_eq!(parse_device_info(&raw), (None, None));
+    }
+
+    test_cases! {
+        parse_operstate;
+        args: (raw: &str, expected: bool);
+
+        case: up => ("up\n", true);
+        case: down => ("down\n", false);
+        case: unknown => ("unknown\n", true);
+
+        body: {
+            assert_eq!(parse_operstate(raw), expected);
+        }
+    }
+
+    #[test]
+    fn missing_interfaces_are_down() {
+        assert!(!interface_is_up(Path::new("/this/does/not/exist"), "wg0"));
+    }
+
+    fn supplies(raw: &[(&str, &str)]) -> Vec<(String, String)> {
+        raw.iter()
+            .map(|(kind, online)| (format!("{}\n", kind), format!("{}\n", online)))
+            .collect()
+    }
+
+    test_cases! {
+        parse_on_battery;
+        args: (raw: &[(&str, &str)], expected: bool);
+
+        case: no_supplies => (&[], false);
+        case: mains_only => (&[("Mains", "1")], false);
+        case: battery_and_mains_online => (&[("Battery", ""), ("Mains", "1")], false);
+        case: battery_and_mains_offline => (&[("Battery", ""), ("Mains", "0")], true);
+        case: battery_and_usb_online => (&[("Battery", ""), ("Mains", "0"), ("USB", "1")], false);
+        case: battery_only => (&[("Battery", "")], true);
+
+        body: {
+            assert_eq!(parse_on_battery(&supplies(raw)), expected);
+        }
+    }
+
+    #[test]
+    fn missing_power_supplies_are_not_on_battery() {
+        assert!(!read_on_battery(Path::new("/this/does/not/exist")));
+    }
+
+    #[test]
+    fn periods_are_scaled_on_battery() {
+        let period = Duration::from_secs(5);
+
+        assert_eq!(scaled_period(period, false), period);
+        assert_eq!(
+            scaled_period(period, true),
+            period * BATTERY_INTERVAL_FACTOR
+        );
+    }
+
+    #[test]
+    fn parse_failed_units_works() {
+        let units: Vec<String> = vec!["picom".into(), "dunst".into(), "syncthing".into()];
+        let raw = "active\nfailed\ninactive\n";
+
+        assert_eq!(parse_failed_units(&units, raw), vec!["dunst".to_string()]);
+    }
+
+    #[test]
+    fn background_poll_results_are_taken_once() {
+        let mut poll = BackgroundPoll::new(Duration::from_secs(60), || Ok(42));
+        let start = Instant::now();
+        let mut result = poll.latest();
+        while result.is_none() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(1));
+            result = poll.latest();
+        }
+
+        assert_eq!(result, Some(Ok(42)));
+        assert_eq!(poll.latest(), None);
+    }
+
+    #[test]
+    fn refresh_interval_is_due_once_per_period() {
+        let mut interval = RefreshInterval::new(Duration::from_secs(60));
+        assert!(interval.is_due());
+        assert!(!interval.is_due());
+    }
+}
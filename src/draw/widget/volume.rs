@@ -0,0 +1,568 @@
+//! A widget showing the volume of the default audio output
+//!
+//! Rather than polling, the [Volume] widget runs a monitor program (`pactl subscribe` or
+//! `alsactl monitor`) on a background thread and only re-reads the volume when that program
+//! reports a change. As with the widgets in [sys][1], results are picked up from the event loop
+//! so the widget updates its content after the next X event has been processed.
+//!
+//! This module is only available with the `volume` feature enabled as it runs external programs
+//! for as long as the widget is alive.
+//!
+//! [1]: crate::draw::widget::sys
+use crate::{
+    core::{
+        bindings::{ClickKind, MouseButton},
+        data_types::Point,
+        helpers::spawn_for_output_with_args,
+        hooks::Hook,
+        manager::WindowManager,
+        process::spawn_with_args,
+        xconnection::XConn,
+    },
+    draw::{
+        widget::{ProgressBar, Text},
+        Color, DrawContext, Result, TextStyle, Widget,
+    },
+};
+
+use std::{
+    fmt,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+// How long to wait before restarting a monitor program that has exited (e.g. when the sound
+// server is restarted)
+const MONITOR_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+// How often to pick up changes seen by the monitor thread while there are no other events
+const CHANGE_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+type VolumeResult = std::result::Result<VolumeState, String>;
+
+/// The sound system used by a [Volume] widget to read and change the volume
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VolumeBackend {
+    /// The default sink of PulseAudio (or PipeWire via `pipewire-pulse`) using `pactl`
+    PulseAudio,
+    /// The named ALSA simple mixer control (e.g. "Master") using `amixer` and `alsactl`
+    Alsa(String),
+}
+
+impl VolumeBackend {
+    /// Query the current volume and mute state.
+    ///
+    /// This blocks until the backend program has exited so it should not be called from the
+    /// event loop.
+    pub fn query(&self) -> crate::Result<VolumeState> {
+        match self {
+            Self::PulseAudio => {
+                let volume =
+                    spawn_for_output_with_args("pactl", &["get-sink-volume", "@DEFAULT_SINK@"])?;
+                let mute =
+                    spawn_for_output_with_args("pactl", &["get-sink-mute", "@DEFAULT_SINK@"])?;
+
+                Ok(VolumeState {
+                    percent: parse_first_percent(&volume).unwrap_or_default(),
+                    muted: parse_pactl_mute(&mute),
+                })
+            }
+
+            Self::Alsa(control) => {
+                let raw = spawn_for_output_with_args("amixer", &["get", control])?;
+
+                Ok(VolumeState {
+                    percent: parse_first_percent(&raw).unwrap_or_default(),
+                    muted: parse_amixer_muted(&raw),
+                })
+            }
+        }
+    }
+
+    /// Raise the volume by `step` percent
+    pub fn raise(&self, step: u32) -> crate::Result<()> {
+        match self {
+            Self::PulseAudio => {
+                let change = format!("+{}%", step);
+                spawn_with_args("pactl", &["set-sink-volume", "@DEFAULT_SINK@", &change])
+            }
+            Self::Alsa(control) => {
+                let change = format!("{}%+", step);
+                spawn_with_args("amixer", &["-q", "set", control, &change])
+            }
+        }
+    }
+
+    /// Lower the volume by `step` percent
+    pub fn lower(&self, step: u32) -> crate::Result<()> {
+        match self {
+            Self::PulseAudio => {
+                let change = format!("-{}%", step);
+                spawn_with_args("pactl", &["set-sink-volume", "@DEFAULT_SINK@", &change])
+            }
+            Self::Alsa(control) => {
+                let change = format!("{}%-", step);
+                spawn_with_args("amixer", &["-q", "set", control, &change])
+            }
+        }
+    }
+
+    /// Toggle whether or not the output is muted
+    pub fn toggle_mute(&self) -> crate::Result<()> {
+        match self {
+            Self::PulseAudio => {
+                spawn_with_args("pactl", &["set-sink-mute", "@DEFAULT_SINK@", "toggle"])
+            }
+            Self::Alsa(control) => spawn_with_args("amixer", &["-q", "set", control, "toggle"]),
+        }
+    }
+
+    fn monitor_command(&self) -> Command {
+        let mut cmd = match self {
+            Self::PulseAudio => {
+                let mut cmd = Command::new("pactl");
+                cmd.arg("subscribe");
+                cmd
+            }
+            Self::Alsa(_) => {
+                let mut cmd = Command::new("alsactl");
+                cmd.arg("monitor");
+                cmd
+            }
+        };
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        cmd
+    }
+
+    // Whether a line of output from the monitor program means that the volume may have changed.
+    // `pactl subscribe` reports changes to every kind of object so we only care about sinks and
+    // the server (which is what changes when a new default sink is selected).
+    fn is_change(&self, line: &str) -> bool {
+        match self {
+            Self::PulseAudio => line.contains("on sink ") || line.contains("on server "),
+            Self::Alsa(_) => !line.trim().is_empty(),
+        }
+    }
+}
+
+/// The volume of the default audio output as reported by a [VolumeBackend]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VolumeState {
+    /// The volume as a percentage, which can be over 100 for PulseAudio
+    pub percent: u32,
+    /// Whether or not the output is muted
+    pub muted: bool,
+}
+
+impl VolumeState {
+    fn render(&self, icon: &str) -> String {
+        if self.muted {
+            format!("{} muted", icon)
+        } else {
+            format!("{} {}%", icon, self.percent)
+        }
+    }
+}
+
+// Both `pactl get-sink-volume` and `amixer get` report the volume of each channel in turn with
+// the percentage as a separate token ("65%" and "[65%]" respectively) so we take the first one
+fn parse_first_percent(raw: &str) -> Option<u32> {
+    raw.split_whitespace()
+        .map(|tok| tok.trim_matches(&['[', ']'][..]))
+        .filter_map(|tok| tok.strip_suffix('%'))
+        .find_map(|n| n.parse().ok())
+}
+
+// `pactl get-sink-mute` prints a single line of either "Mute: yes" or "Mute: no"
+fn parse_pactl_mute(raw: &str) -> bool {
+    raw.lines()
+        .filter_map(|l| l.trim().strip_prefix("Mute:"))
+        .any(|v| v.trim() == "yes")
+}
+
+// `amixer get` ends each playback channel line with "[on]" or "[off]". Controls without a
+// playback switch never report being muted.
+fn parse_amixer_muted(raw: &str) -> bool {
+    raw.lines()
+        .filter(|l| l.contains("Playback") && l.contains('%'))
+        .any(|l| l.trim_end().ends_with("[off]"))
+}
+
+// Runs the monitor program for a backend on a background thread, re-querying the volume each
+// time that it reports a change. The thread is started on the first call to 'latest' and is
+// stopped (along with the monitor program) when the watcher is dropped.
+struct VolumeWatch {
+    backend: VolumeBackend,
+    latest: Arc<Mutex<Option<VolumeResult>>>,
+    monitor: Arc<Mutex<Option<Child>>>,
+    stopped: Arc<AtomicBool>,
+    started: bool,
+}
+
+impl VolumeWatch {
+    fn new(backend: VolumeBackend) -> Self {
+        Self {
+            backend,
+            latest: Arc::new(Mutex::new(None)),
+            monitor: Arc::new(Mutex::new(None)),
+            stopped: Arc::new(AtomicBool::new(false)),
+            started: false,
+        }
+    }
+
+    // Take the most recent volume if it has been re-read since the last call
+    fn latest(&mut self) -> Option<VolumeResult> {
+        if !self.started {
+            self.started = true;
+            let (backend, latest) = (self.backend.clone(), Arc::downgrade(&self.latest));
+            let (monitor, stopped) = (Arc::clone(&self.monitor), Arc::clone(&self.stopped));
+            thread::spawn(move || watch(backend, latest, monitor, stopped));
+        }
+
+        self.latest.lock().ok()?.take()
+    }
+}
+
+impl Drop for VolumeWatch {
+    fn drop(&mut self) {
+        // Killing the monitor program ends its output, waking the thread so that it sees the flag
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(child) = self.monitor.lock().ok().and_then(|mut m| m.take()) {
+            stop(child);
+        }
+    }
+}
+
+fn stop(mut child: Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// Store the current volume for the widget to pick up, returning false if it has been dropped
+fn publish(backend: &VolumeBackend, latest: &Weak<Mutex<Option<VolumeResult>>>) -> bool {
+    let result = backend.query().map_err(|e| e.to_string());
+    match latest.upgrade() {
+        Some(shared) => match shared.lock() {
+            Ok(mut guard) => {
+                *guard = Some(result);
+                true
+            }
+            Err(_) => false,
+        },
+        None => false,
+    }
+}
+
+fn watch(
+    backend: VolumeBackend,
+    latest: Weak<Mutex<Option<VolumeResult>>>,
+    monitor: Arc<Mutex<Option<Child>>>,
+    stopped: Arc<AtomicBool>,
+) {
+    loop {
+        if stopped.load(Ordering::SeqCst) || !publish(&backend, &latest) {
+            return;
+        }
+
+        let mut child = match backend.monitor_command().spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                warn!(?backend, "unable to monitor volume changes: {}", e);
+                thread::sleep(MONITOR_RESTART_DELAY);
+                continue;
+            }
+        };
+
+        // The child is shared so that it can be killed if the watcher is dropped while we are
+        // blocked reading its output
+        let stdout = child.stdout.take();
+        match monitor.lock() {
+            Ok(mut guard) if !stopped.load(Ordering::SeqCst) => *guard = Some(child),
+            _ => return stop(child),
+        }
+
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if backend.is_change(&line) && !publish(&backend, &latest) {
+                    break;
+                }
+            }
+        }
+
+        // Already taken if the watcher was dropped
+        if let Some(child) = monitor.lock().ok().and_then(|mut m| m.take()) {
+            stop(child);
+        }
+        if stopped.load(Ordering::SeqCst) {
+            return;
+        }
+        thread::sleep(MONITOR_RESTART_DELAY);
+    }
+}
+
+/// Display the volume and mute state of the default audio output.
+///
+/// The volume is re-read on a background thread whenever the [VolumeBackend] reports a change,
+/// so `pactl` (for PulseAudio and PipeWire) or `amixer` and `alsactl` (for ALSA) need to be
+/// installed. When placed in a [StatusBar][crate::draw::StatusBar], scrolling up and down over
+/// the widget raises and lowers the volume by `step` percent and left clicking toggles mute.
+///
+/// By default the volume is shown as text but it can also be shown as a [ProgressBar] using
+/// [set_bar][Volume::set_bar].
+pub struct Volume {
+    txt: Text,
+    bar: Option<ProgressBar>,
+    icon: String,
+    step: u32,
+    watch: VolumeWatch,
+}
+
+impl fmt::Debug for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Volume")
+            .field("txt", &self.txt)
+            .field("bar", &self.bar)
+            .field("icon", &self.icon)
+            .field("step", &self.step)
+            .field("backend", &self.watch.backend)
+            .finish()
+    }
+}
+
+impl Volume {
+    /// Create a new Volume widget that reads and changes the volume using `backend`
+    pub fn new(
+        style: &TextStyle,
+        backend: VolumeBackend,
+        icon: impl Into<String>,
+        step: u32,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        Self {
+            txt: Text::new("", style, is_greedy, right_justified),
+            bar: None,
+            icon: icon.into(),
+            step,
+            watch: VolumeWatch::new(backend),
+        }
+    }
+
+    /// Show the volume as the fill level of `bar` with the usual text as its label, or pass
+    /// None to go back to only showing text.
+    pub fn set_bar(&mut self, bar: Option<ProgressBar>) {
+        self.bar = bar;
+        let label = self.txt.get_text().clone();
+        if let Some(bar) = self.bar.as_mut() {
+            bar.set_label(Some(label));
+        }
+    }
+
+    fn set_state(&mut self, label: String, percent: Option<u32>) {
+        if let Some(bar) = self.bar.as_mut() {
+            bar.set_value(percent.unwrap_or(0) as f64 / 100.0);
+            bar.set_label(Some(label.clone()));
+        }
+        self.txt.set_text(label);
+    }
+
+    fn refresh(&mut self) {
+        match self.watch.latest() {
+            None => (), // no change since we last checked
+            Some(Ok(state)) => {
+                let percent = if state.muted { 0 } else { state.percent };
+                self.set_state(state.render(&self.icon), Some(percent));
+            }
+            Some(Err(e)) => {
+                warn!("unable to query volume: {}", e);
+                self.set_state(format!("{} ?", self.icon), None);
+            }
+        }
+    }
+
+    // The widget actually being shown
+    fn shown(&mut self) -> &mut dyn Widget {
+        match self.bar.as_mut() {
+            Some(bar) => bar,
+            None => &mut self.txt,
+        }
+    }
+
+    fn shown_ref(&self) -> &dyn Widget {
+        match self.bar.as_ref() {
+            Some(bar) => bar,
+            None => &self.txt,
+        }
+    }
+}
+
+impl<X> Hook<X> for Volume
+where
+    X: XConn,
+{
+    fn startup(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn event_handled(&mut self, _: &mut WindowManager<X>) -> crate::Result<()> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn bar_clicked(
+        &mut self,
+        _: &mut WindowManager<X>,
+        button: MouseButton,
+        kind: ClickKind,
+        _: Point,
+    ) -> crate::Result<()> {
+        if kind != ClickKind::Single {
+            return Ok(());
+        }
+
+        let backend = &self.watch.backend;
+        match button {
+            MouseButton::ScrollUp => backend.raise(self.step),
+            MouseButton::ScrollDown => backend.lower(self.step),
+            MouseButton::Left => backend.toggle_mute(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Widget for Volume {
+    fn draw(&mut self, ctx: &mut dyn DrawContext, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        self.shown().draw(ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut dyn DrawContext, h: f64) -> Result<(f64, f64)> {
+        self.shown().current_extent(ctx, h)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.shown_ref().require_draw()
+    }
+
+    fn is_greedy(&self) -> bool {
+        self.shown_ref().is_greedy()
+    }
+
+    fn background(&self) -> Option<Color> {
+        self.shown_ref().background()
+    }
+
+    fn poll_interval(&self) -> Option<Duration> {
+        Some(CHANGE_CHECK_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::draw::widget::Orientation;
+
+    const PACTL_VOLUME: &str = "Volume: front-left: 42597 /  65% / -11.23 dB,   \
+        front-right: 42597 /  65% / -11.23 dB
+        balance 0.00";
+
+    const AMIXER: &str = "Simple mixer control 'Master',0
+  Capabilities: pvolume pswitch pswitch-joined
+  Playback channels: Front Left - Front Right
+  Limits: Playback 0 - 87
+  Mono:
+  Front Left: Playback 57 [66%] [-22.50dB] [off]
+  Front Right: Playback 57 [66%] [-22.50dB] [off]";
+
+    #[test]
+    fn parse_first_percent_works_for_pactl() {
+        assert_eq!(parse_first_percent(PACTL_VOLUME), Some(65));
+    }
+
+    #[test]
+    fn parse_first_percent_works_for_amixer() {
+        assert_eq!(parse_first_percent(AMIXER), Some(66));
+        assert!(parse_amixer_muted(AMIXER));
+        assert!(!parse_amixer_muted(&AMIXER.replace("[off]", "[on]")));
+    }
+
+    #[test]
+    fn parse_pactl_mute_works() {
+        assert!(parse_pactl_mute("Mute: yes\n"));
+        assert!(!parse_pactl_mute("Mute: no\n"));
+    }
+
+    #[test]
+    fn only_sink_and_server_events_are_changes() {
+        let backend = VolumeBackend::PulseAudio;
+        assert!(backend.is_change("Event 'change' on sink #0"));
+        assert!(backend.is_change("Event 'change' on server #0"));
+        assert!(!backend.is_change("Event 'change' on sink-input #12"));
+        assert!(!backend.is_change("Event 'new' on client #42"));
+    }
+
+    #[test]
+    fn volume_bars_are_filled_to_the_current_volume() {
+        let style = TextStyle {
+            font: "mono".into(),
+            point_size: 12,
+            fg: 0xffffff.into(),
+            bg: None,
+            padding: (0.0, 0.0),
+        };
+        let mut vol = Volume::new(&style, VolumeBackend::PulseAudio, "vol", 5, false, false);
+        let bar = ProgressBar::new(&style, 40.0, 8.0, 0x000000, Orientation::Horizontal);
+        vol.set_bar(Some(bar));
+        vol.set_state("vol 150%".into(), Some(150));
+
+        assert_eq!(vol.bar.as_ref().map(|b| b.value()), Some(1.0));
+        assert_eq!(vol.txt.get_text(), "vol 150%");
+        assert!(vol.require_draw());
+
+        vol.set_bar(None);
+        assert!(vol.bar.is_none());
+    }
+
+    #[test]
+    fn dropping_the_watcher_stops_the_monitor_program() {
+        let watch = VolumeWatch::new(VolumeBackend::PulseAudio);
+        let child = Command::new("sleep").arg("60").spawn().unwrap();
+        *watch.monitor.lock().unwrap() = Some(child);
+        let (monitor, stopped) = (Arc::clone(&watch.monitor), Arc::clone(&watch.stopped));
+
+        drop(watch);
+
+        assert!(stopped.load(Ordering::SeqCst));
+        assert!(monitor.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn muted_outputs_do_not_show_their_volume() {
+        let state = VolumeState {
+            percent: 40,
+            muted: false,
+        };
+        assert_eq!(state.render("vol"), "vol 40%");
+        assert_eq!(
+            VolumeState {
+                muted: true,
+                ..state
+            }
+            .render("vol"),
+            "vol muted"
+        );
+    }
+}
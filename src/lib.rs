@@ -155,6 +155,9 @@ pub mod xcb;
 #[cfg(feature = "x11rb")]
 pub mod x11rb;
 
+#[cfg(feature = "wayland")]
+pub mod wayland;
+
 #[doc(hidden)]
 pub mod __test_helpers;
 
@@ -185,6 +188,11 @@ pub enum PenroseError {
     #[error(transparent)]
     Draw(#[from] crate::draw::DrawError),
 
+    /// Something went wrong when talking to D-Bus
+    #[cfg(feature = "dbus")]
+    #[error(transparent)]
+    Dbus(#[from] zbus::Error),
+
     /// Something was inconsistant when attempting to re-create a serialised [WindowManager]
     #[error("unable to rehydrate from serialized state: {0}")]
     HydrationState(String),
@@ -209,6 +217,10 @@ pub enum PenroseError {
     #[error("Invalid window hints property: {0}")]
     InvalidHints(String),
 
+    /// An action acting on the focused client was run while no client had focus
+    #[error("there is no focused client")]
+    NoFocusedClient,
+
     /// No elements match the given selector
     #[error("No elements match the given selector")]
     NoMatchingElement,
@@ -222,6 +234,10 @@ pub enum PenroseError {
     #[error("Unhandled error: {0}")]
     Raw(String),
 
+    /// An external program could not be started (e.g. because it is not installed)
+    #[error("unable to run '{0}': {1}")]
+    SpawnFailed(String, #[source] std::io::Error),
+
     /// An attempt to spawn an external process failed
     #[error("unable to get stdout handle for child process: {0}")]
     SpawnProc(String),
@@ -240,6 +256,10 @@ pub enum PenroseError {
     #[error("Unknown modifier key: {0}")]
     UnknownModifier(String),
 
+    /// An attempt was made to reference a workspace that does not exist
+    #[error("no workspace matches {0}")]
+    UnknownWorkspace(String),
+
     /// Something went wrong using the [xcb] module.
     ///
     /// See [XcbError][crate::xcb::XcbError] for variants.
@@ -254,6 +274,13 @@ pub enum PenroseError {
     #[error(transparent)]
     X11rb(#[from] crate::x11rb::X11rbError),
 
+    /// Something went wrong using the [wayland] module.
+    ///
+    /// See [WaylandError][crate::wayland::WaylandError] for variants.
+    #[cfg(feature = "wayland")]
+    #[error(transparent)]
+    Wayland(#[from] crate::wayland::WaylandError),
+
     /// Something went wrong when communicating with the X server
     #[error(transparent)]
     X(#[from] crate::core::xconnection::XError),
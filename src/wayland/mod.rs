@@ -0,0 +1,109 @@
+//! Helpers and utilities for running penrose as an experimental Wayland compositor
+//!
+//! Wayland has no separate window manager: the compositor that displays clients is also the
+//! one that has to arrange them. This module uses [smithay][1] to provide that compositor and
+//! exposes it through the same [XConn][crate::core::xconnection::XConn] traits used by the X11
+//! back ends so that the [WindowManager] itself is unchanged.
+//!
+//! The compositor runs inside a [winit][2] window, either nested in an existing X11 or Wayland
+//! session or directly on a display server that winit supports. It implements enough of the
+//! core protocols for `xdg_shell` clients drawing into shared memory buffers:
+//!
+//!   - new toplevel windows are managed, positioned and stacked by the [WindowManager]
+//!   - window titles and app ids are exposed as the `_NET_WM_NAME` and `WM_CLASS` properties
+//!   - key bindings are intercepted before they reach the focused client
+//!   - pointer enter and leave events drive focus, mouse bindings and hot corners
+//!   - the window size is reported as a single screen that is updated when the window resizes
+//!
+//! This back end is experimental and a number of things are not supported yet:
+//!
+//!   - window borders and anything drawn by the [draw][crate::draw] module (such as the status
+//!     bar) are not rendered as the draw module talks directly to an X server
+//!   - XWayland is not started so X11 clients can not be run
+//!   - popups are shown but are not able to grab the pointer or keyboard
+//!   - hardware accelerated clients need to fall back to shared memory buffers
+//!   - penrose can not be restarted in place as clients do not outlive the compositor
+//!
+//! Key bindings are still given as X key codes so the helpers for parsing them from `xmodmap`
+//! need to be able to reach an X server. When running nested inside of an X session this
+//! happens automatically.
+//!
+//! [1]: https://github.com/Smithay/smithay
+//! [2]: https://github.com/rust-windowing/winit
+use crate::{
+    core::{config::Config, hooks::Hooks, manager::WindowManager, xconnection::XError},
+    ErrorHandler,
+};
+
+use smithay::{
+    backend::{renderer::gles2::Gles2Error, winit::Error as WinitError, SwapBuffersError},
+    wayland::seat::KeyboardError,
+};
+
+pub mod xconn;
+
+#[doc(inline)]
+pub use xconn::WaylandConnection;
+
+/// Result type for fallible methods using the Wayland back end
+pub type Result<T> = std::result::Result<T, WaylandError>;
+
+/// Helper type for when you are defining your [Hook][crate::core::hooks::Hook] vector in your
+/// main.rs when using the Wayland back end
+pub type WaylandHooks = Hooks<WaylandConnection>;
+
+/// Construct a penrose [WindowManager] that runs as a Wayland compositor.
+///
+/// The name of the Wayland socket that clients should connect to is set as `WAYLAND_DISPLAY`
+/// for any programs spawned by penrose.
+pub fn new_wayland_backed_window_manager(
+    config: Config,
+    hooks: WaylandHooks,
+    error_handler: ErrorHandler,
+) -> crate::Result<WindowManager<WaylandConnection>> {
+    let conn = WaylandConnection::new()?;
+    let mut wm = WindowManager::new(config, conn, hooks, error_handler);
+    wm.init()?;
+
+    Ok(wm)
+}
+
+/// Enum to store the various ways that operations can fail inside of the Wayland
+/// implementations of penrose traits.
+#[derive(thiserror::Error, Debug)]
+pub enum WaylandError {
+    /// Unable to create the window used to display the compositor
+    #[error(transparent)]
+    Init(#[from] WinitError),
+
+    /// The Wayland socket could not be created or clients could not be dispatched
+    #[error("Wayland display error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The keyboard could not be set up from the current xkb configuration
+    #[error(transparent)]
+    Keyboard(#[from] KeyboardError),
+
+    /// A client buffer could not be rendered
+    #[error(transparent)]
+    Render(#[from] Gles2Error),
+
+    /// The rendered frame could not be shown
+    #[error(transparent)]
+    SwapBuffers(#[from] SwapBuffersError),
+}
+
+macro_rules! from_error {
+    ($type:ident) => {
+        impl From<$type> for XError {
+            fn from(error: $type) -> Self {
+                WaylandError::from(error).into()
+            }
+        }
+    };
+}
+
+from_error!(WinitError);
+from_error!(KeyboardError);
+from_error!(Gles2Error);
+from_error!(SwapBuffersError);
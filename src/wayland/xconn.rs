@@ -0,0 +1,1392 @@
+//! A Wayland compositor exposed through the penrose XConn traits
+//!
+//! Toplevel `xdg_shell` surfaces are given an [Xid] when they are created and are then treated
+//! as if they were X client windows: creating one sends a [MapRequest][XEvent::MapRequest] to
+//! the [WindowManager][crate::core::manager::WindowManager], which positions, maps and focuses it
+//! using the [XClientConfig] and [XClientHandler] methods below. Properties are held in memory
+//! for each window, with the title, app id and parent of each toplevel kept in sync with the
+//! equivalent X properties.
+use crate::{
+    core::{
+        bindings::{
+            KeyBindings, KeyCode, KeyCodeMask, KeyCodeValue, ModifierKey, MouseBindings,
+            MouseButton, MouseEvent, MouseEventKind, MouseState,
+        },
+        data_types::{Point, Region},
+        helpers::try_keycodes_from_xmodmap,
+        input::{InputDevice, LockState, PointerConfig},
+        screen::Screen,
+        xconnection::{
+            Atom, ClientAttr, ClientConfig, ClientMessage, ClientMessageKind, MapState,
+            PointerChange, Prop, PropertyEvent, Result, WindowAttributes, WindowClass, WindowState,
+            XAtomQuerier, XClientConfig, XClientHandler, XClientProperties, XConn, XError, XEvent,
+            XEventHandler, XState, Xid,
+        },
+    },
+    wayland::WaylandError,
+};
+
+use smithay::{
+    backend::{
+        input::{
+            self, Axis, AxisSource, ButtonState, Event, InputBackend, InputEvent, KeyState,
+            KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent,
+        },
+        renderer::{
+            buffer_dimensions,
+            gles2::{Gles2Error, Gles2Frame, Gles2Renderer, Gles2Texture},
+            Frame, ImportShm, Transform,
+        },
+        winit::{self, WinitEvent, WinitGraphicsBackend, WinitInputBackend, WinitInputError},
+    },
+    reexports::{
+        wayland_protocols::xdg_shell::server::xdg_toplevel,
+        wayland_server::{
+            protocol::{wl_buffer::WlBuffer, wl_output, wl_pointer, wl_surface::WlSurface},
+            Display,
+        },
+    },
+    utils::{self, Buffer as BufferCoords, Logical, Rectangle},
+    wayland::{
+        compositor::{
+            compositor_init, with_states, with_surface_tree_downward, with_surface_tree_upward,
+            BufferAssignment, SubsurfaceCachedState, SurfaceAttributes, SurfaceData,
+            TraversalAction,
+        },
+        output::{Mode, Output, PhysicalProperties},
+        seat::{AxisFrame, KeyboardHandle, ModifiersState, PointerHandle, Seat, XkbConfig},
+        shell::xdg::{
+            xdg_shell_init, PopupSurface, ToplevelSurface, XdgPopupSurfaceRoleAttributes,
+            XdgRequest, XdgToplevelSurfaceRoleAttributes,
+        },
+        shm::init_shm_global,
+        SERIAL_COUNTER,
+    },
+};
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    env,
+    ffi::OsString,
+    fmt,
+    rc::Rc,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// How long to wait for clients on each pass of the event loop: roughly one frame at 60Hz
+const FRAME: Duration = Duration::from_millis(16);
+const REFRESH_MHZ: i32 = 60_000;
+const ROOT: Xid = 1;
+const CHECK_WIN: Xid = 2;
+const BACKGROUND: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+
+// Keyboard repeat delay and rate in ms and keys per second
+const REPEAT_DELAY: i32 = 200;
+const REPEAT_RATE: i32 = 25;
+
+// Linux input event codes for pointer buttons (see linux/input-event-codes.h)
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+// The X modifier masks used in the KeyCodes for key bindings
+const SHIFT_MASK: KeyCodeMask = 1 << 0;
+const CONTROL_MASK: KeyCodeMask = 1 << 2;
+const ALT_MASK: KeyCodeMask = 1 << 3;
+const SUPER_MASK: KeyCodeMask = 1 << 6;
+
+// X key codes are offset by 8 from the evdev key codes reported by the kernel
+const X_KEYCODE_OFFSET: u32 = 8;
+
+// The KeyCode that X would report for the evdev key code 'key' while holding 'mods'. Caps Lock
+// and Num Lock are left out of the mask so that they do not change which binding a key runs.
+fn key_code(mods: &ModifiersState, key: u32) -> KeyCode {
+    let held = [
+        (mods.shift, SHIFT_MASK),
+        (mods.ctrl, CONTROL_MASK),
+        (mods.alt, ALT_MASK),
+        (mods.logo, SUPER_MASK),
+    ];
+
+    KeyCode {
+        mask: held
+            .iter()
+            .filter(|(h, _)| *h)
+            .fold(0, |mask, (_, m)| mask | m),
+        code: (key + X_KEYCODE_OFFSET) as KeyCodeValue,
+    }
+}
+
+// The MouseState for 'button' being pressed while holding 'mods' if it is one penrose can bind
+fn mouse_state(mods: &ModifiersState, button: input::MouseButton) -> Option<MouseState> {
+    let button = match button {
+        input::MouseButton::Left => MouseButton::Left,
+        input::MouseButton::Middle => MouseButton::Middle,
+        input::MouseButton::Right => MouseButton::Right,
+        input::MouseButton::Other(_) => return None,
+    };
+    let held = [
+        (mods.ctrl, ModifierKey::Ctrl),
+        (mods.alt, ModifierKey::Alt),
+        (mods.shift, ModifierKey::Shift),
+        (mods.logo, ModifierKey::Meta),
+    ];
+
+    Some(MouseState::new(
+        button,
+        held.iter().filter(|(h, _)| *h).map(|&(_, m)| m).collect(),
+    ))
+}
+
+fn button_code(button: input::MouseButton) -> u32 {
+    match button {
+        input::MouseButton::Left => BTN_LEFT,
+        input::MouseButton::Right => BTN_RIGHT,
+        input::MouseButton::Middle => BTN_MIDDLE,
+        input::MouseButton::Other(b) => b as u32,
+    }
+}
+
+fn axis_source(source: AxisSource) -> wl_pointer::AxisSource {
+    match source {
+        AxisSource::Finger => wl_pointer::AxisSource::Finger,
+        AxisSource::Continuous => wl_pointer::AxisSource::Continuous,
+        AxisSource::Wheel => wl_pointer::AxisSource::Wheel,
+        AxisSource::WheelTilt => wl_pointer::AxisSource::WheelTilt,
+    }
+}
+
+fn rect(r: Region) -> Rectangle<i32, Logical> {
+    Rectangle::from_loc_and_size((r.x as i32, r.y as i32), (r.w as i32, r.h as i32))
+}
+
+fn point(p: utils::Point<f64, Logical>) -> Point {
+    Point::new(p.x.max(0.0) as u32, p.y.max(0.0) as u32)
+}
+
+// The buffer most recently committed to a surface and the texture it was last drawn from.
+// Buffers are imported when the next frame is rendered as the renderer is not available while
+// clients are being dispatched.
+#[derive(Default)]
+struct SurfaceTexture {
+    buffer: Option<WlBuffer>,
+    texture: Option<Gles2Texture>,
+}
+
+// Returns None if the surface already has data of the same type stored by something else
+fn with_surface_texture<T>(
+    states: &SurfaceData,
+    f: impl FnOnce(&mut SurfaceTexture) -> T,
+) -> Option<T> {
+    states
+        .data_map
+        .insert_if_missing(|| RefCell::new(SurfaceTexture::default()));
+
+    states
+        .data_map
+        .get::<RefCell<SurfaceTexture>>()
+        .map(|data| f(&mut data.borrow_mut()))
+}
+
+// Hold on to newly committed buffers until they are drawn, releasing any that are replaced
+// before that happens so that the client is able to reuse them.
+fn surface_committed(surface: &WlSurface) {
+    let _ = with_states(surface, |states| {
+        let assignment = states
+            .cached_state
+            .current::<SurfaceAttributes>()
+            .buffer
+            .take();
+
+        with_surface_texture(states, |data| match assignment {
+            Some(BufferAssignment::NewBuffer { buffer, .. }) => {
+                if let Some(old) = data.buffer.replace(buffer) {
+                    old.release();
+                }
+            }
+            Some(BufferAssignment::Removed) => {
+                if let Some(old) = data.buffer.take() {
+                    old.release();
+                }
+                data.texture = None;
+            }
+            None => (),
+        })
+    });
+}
+
+// Draw a surface and its subsurfaces with the top left corner of 'root' at 'location'
+fn draw_surface_tree(
+    renderer: &mut Gles2Renderer,
+    frame: &mut Gles2Frame,
+    root: &WlSurface,
+    location: utils::Point<i32, Logical>,
+    scale: f64,
+) -> std::result::Result<(), Gles2Error> {
+    let mut result = Ok(());
+
+    with_surface_tree_upward(
+        root,
+        location,
+        |_, states, &location| {
+            let mut location = location;
+            if states.role == Some("subsurface") {
+                location += states
+                    .cached_state
+                    .current::<SubsurfaceCachedState>()
+                    .location;
+            }
+
+            let texture = with_surface_texture(states, |data| {
+                if let Some(buffer) = data.buffer.take() {
+                    let damage: Vec<Rectangle<i32, BufferCoords>> = buffer_dimensions(&buffer)
+                        .map(|s| Rectangle::from_loc_and_size((0, 0), (s.w, s.h)))
+                        .into_iter()
+                        .collect();
+                    match renderer.import_shm_buffer(&buffer, Some(states), &damage) {
+                        Ok(texture) => data.texture = Some(texture),
+                        Err(e) => warn!(?e, "unable to import client buffer"),
+                    }
+                    buffer.release();
+                }
+                data.texture.clone()
+            })
+            .flatten();
+
+            if let Some(texture) = texture {
+                let buffer_scale = states
+                    .cached_state
+                    .current::<SurfaceAttributes>()
+                    .buffer_scale;
+                let pos = location.to_f64().to_physical(scale);
+                let res = frame.render_texture_at(
+                    &texture,
+                    pos,
+                    buffer_scale,
+                    scale,
+                    Transform::Normal,
+                    1.0,
+                );
+                if let Err(e) = res {
+                    result = Err(e);
+                    return TraversalAction::Break;
+                }
+            }
+
+            TraversalAction::DoChildren(location)
+        },
+        |_, _, _| (),
+        |_, _, _| true,
+    );
+
+    result
+}
+
+// Let the client know that its last frame has been shown so that it can draw the next one
+fn send_frames(surface: &WlSurface, time: u32) {
+    with_surface_tree_downward(
+        surface,
+        (),
+        |_, _, &()| TraversalAction::DoChildren(()),
+        |_, states, &()| {
+            let mut attrs = states.cached_state.current::<SurfaceAttributes>();
+            for callback in attrs.frame_callbacks.drain(..) {
+                callback.done(time);
+            }
+        },
+        |_, _, &()| true,
+    );
+}
+
+fn set_activated(toplevel: &ToplevelSurface, activated: bool) {
+    let changed = toplevel.with_pending_state(|s| {
+        if activated {
+            s.states.set(xdg_toplevel::State::Activated)
+        } else {
+            s.states.unset(xdg_toplevel::State::Activated)
+        }
+    });
+
+    if let Ok(true) = changed {
+        toplevel.send_configure();
+    }
+}
+
+// A toplevel surface being treated as an X client window
+struct Window {
+    toplevel: ToplevelSurface,
+    region: Region,
+    border: u32,
+    mapped: bool,
+    title: Option<String>,
+    app_id: Option<String>,
+    parent: Option<Xid>,
+}
+
+impl Window {
+    fn new(toplevel: ToplevelSurface) -> Self {
+        Self {
+            toplevel,
+            region: Region::new(0, 0, 0, 0),
+            border: 0,
+            mapped: false,
+            title: None,
+            app_id: None,
+            parent: None,
+        }
+    }
+
+    fn surface(&self) -> Option<&WlSurface> {
+        self.toplevel.get_surface()
+    }
+
+    // Clients are drawn inside of where their border would be under X
+    fn content_rect(&self) -> Rectangle<i32, Logical> {
+        let mut r = rect(self.region);
+        r.loc += (self.border as i32, self.border as i32).into();
+        r
+    }
+}
+
+// Everything that is modified while clients are being dispatched
+#[derive(Default)]
+struct State {
+    next_id: Xid,
+    windows: HashMap<Xid, Window>,
+    popups: Vec<PopupSurface>,
+    // all windows from bottom to top
+    stack: Vec<Xid>,
+    input_windows: HashMap<Xid, (Region, bool)>,
+    props: HashMap<Xid, HashMap<String, Prop>>,
+    atoms: Vec<String>,
+    events: VecDeque<XEvent>,
+    focused: Option<Xid>,
+    hovered: Option<Xid>,
+    mods: ModifiersState,
+    grabbed_keys: HashSet<KeyCode>,
+    grabbed_buttons: HashSet<MouseState>,
+    keyboard_grabbed: bool,
+    pointer_grabbed: bool,
+    // evdev codes of key presses sent to penrose: their releases are not passed on to clients
+    suppressed_keys: HashSet<u32>,
+    // the mouse binding that is being held down
+    held_button: Option<MouseState>,
+}
+
+impl State {
+    fn new_id(&mut self) -> Xid {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn set_prop(&mut self, id: Xid, name: &str, val: Prop) {
+        self.props
+            .entry(id)
+            .or_default()
+            .insert(name.to_string(), val);
+    }
+
+    fn handle_xdg_request(&mut self, req: XdgRequest) {
+        match req {
+            XdgRequest::NewToplevel { surface } => {
+                surface.send_configure();
+                let id = self.new_id();
+                self.windows.insert(id, Window::new(surface));
+                // every xdg toplevel can be asked to close
+                let protocols = Prop::Atom(vec![Atom::WmDeleteWindow.as_ref().to_string()]);
+                self.set_prop(id, Atom::WmProtocols.as_ref(), protocols);
+                self.stack.push(id);
+                self.events.push_back(XEvent::MapRequest(id, false));
+            }
+
+            XdgRequest::NewPopup { surface } => {
+                surface.send_configure();
+                self.popups.push(surface);
+            }
+
+            _ => (),
+        }
+    }
+
+    // Drop windows that have been closed and pick up changes to their title, app id and parent
+    fn refresh(&mut self) {
+        self.popups.retain(|p| p.alive());
+
+        let closed: Vec<Xid> = self
+            .windows
+            .iter()
+            .filter(|(_, w)| !w.toplevel.alive())
+            .map(|(&id, _)| id)
+            .collect();
+
+        for id in closed {
+            self.windows.remove(&id);
+            self.props.remove(&id);
+            self.stack.retain(|&w| w != id);
+            if self.focused == Some(id) {
+                self.focused = None;
+            }
+            if self.hovered == Some(id) {
+                self.hovered = None;
+            }
+            self.events.push_back(XEvent::Destroy(id));
+        }
+
+        let surface_ids: Vec<(WlSurface, Xid)> = self
+            .windows
+            .iter()
+            .filter_map(|(&id, w)| w.surface().map(|s| (s.clone(), id)))
+            .collect();
+        let id_for = |surface: WlSurface| {
+            surface_ids
+                .iter()
+                .find(|(s, _)| *s == surface)
+                .map(|&(_, id)| id)
+        };
+
+        let mut changes = vec![];
+        for (&id, w) in self.windows.iter_mut() {
+            let attrs = w.surface().and_then(|s| {
+                with_states(s, |states| {
+                    let attrs = states
+                        .data_map
+                        .get::<Mutex<XdgToplevelSurfaceRoleAttributes>>();
+                    // surfaces are skipped until their attributes can be read
+                    attrs.and_then(|a| {
+                        let a = a.lock().ok()?;
+                        Some((a.title.clone(), a.app_id.clone(), a.parent.clone()))
+                    })
+                })
+                .ok()
+                .flatten()
+            });
+            let (title, app_id, parent) = match attrs {
+                Some((t, a, p)) => (t, a, p.and_then(id_for)),
+                None => continue,
+            };
+
+            if title != w.title {
+                w.title = title.clone();
+                let prop = title.map(|t| Prop::UTF8String(vec![t]));
+                changes.push((id, Atom::WmName, prop.clone()));
+                changes.push((id, Atom::NetWmName, prop));
+            }
+            if app_id != w.app_id {
+                w.app_id = app_id.clone();
+                let prop = app_id.map(|a| Prop::UTF8String(vec![a.clone(), a]));
+                changes.push((id, Atom::WmClass, prop));
+            }
+            if parent != w.parent {
+                w.parent = parent;
+                let prop = parent.map(|p| Prop::Window(vec![p]));
+                changes.push((id, Atom::WmTransientFor, prop));
+            }
+        }
+
+        for (id, atom, prop) in changes {
+            match prop {
+                Some(prop) => self.set_prop(id, atom.as_ref(), prop),
+                None => {
+                    if let Some(props) = self.props.get_mut(&id) {
+                        props.remove(atom.as_ref());
+                    }
+                }
+            }
+
+            self.events.push_back(XEvent::PropertyNotify(PropertyEvent {
+                id,
+                atom: atom.as_ref().to_string(),
+                is_root: false,
+            }));
+        }
+    }
+
+    // The surfaces that are currently being shown from bottom to top, along with the window that
+    // they belong to and where they are on the screen
+    fn visible_surfaces(&self) -> Vec<(Xid, WlSurface, Rectangle<i32, Logical>)> {
+        let mut visible: Vec<_> = self
+            .stack
+            .iter()
+            .filter_map(|id| self.windows.get(id).map(|w| (*id, w)))
+            .filter(|(_, w)| w.mapped)
+            .filter_map(|(id, w)| w.surface().map(|s| (id, s.clone(), w.content_rect())))
+            .collect();
+
+        for popup in self.popups.iter() {
+            let (surface, parent) = match (popup.get_surface(), popup.get_parent_surface()) {
+                (Some(s), Some(p)) => (s.clone(), p),
+                _ => continue,
+            };
+            let parent = visible
+                .iter()
+                .find(|(_, s, _)| *s == parent)
+                .map(|(id, _, r)| (*id, r.loc));
+            let geometry = with_states(&surface, |states| {
+                let attrs = states
+                    .data_map
+                    .get::<Mutex<XdgPopupSurfaceRoleAttributes>>();
+                attrs.and_then(|a| a.lock().ok().map(|a| a.current.geometry))
+            });
+
+            if let (Some((id, loc)), Ok(Some(mut r))) = (parent, geometry) {
+                r.loc += loc;
+                visible.push((id, surface, r));
+            }
+        }
+
+        visible
+    }
+
+    fn surface_at(
+        &self,
+        p: utils::Point<f64, Logical>,
+    ) -> Option<(Xid, WlSurface, Rectangle<i32, Logical>)> {
+        self.visible_surfaces()
+            .into_iter()
+            .rev()
+            .find(|(_, _, r)| r.to_f64().contains(p))
+    }
+
+    // Input windows are always above clients, the same as the windows used for hot corners are
+    // kept raised under X
+    fn window_at(&self, p: utils::Point<f64, Logical>) -> Option<(Xid, Rectangle<i32, Logical>)> {
+        let input_window = self
+            .input_windows
+            .iter()
+            .filter(|(_, (_, mapped))| *mapped)
+            .map(|(&id, &(r, _))| (id, rect(r)))
+            .find(|(_, r)| r.to_f64().contains(p));
+
+        input_window.or_else(|| self.surface_at(p).map(|(id, _, r)| (id, r)))
+    }
+
+    fn pointer_change(&self, id: Xid, p: utils::Point<f64, Logical>) -> PointerChange {
+        let origin = match self.window_at(p) {
+            Some((w, r)) if w == id => point(r.loc.to_f64()),
+            _ => Point::new(0, 0),
+        };
+        let abs = point(p);
+
+        PointerChange {
+            id,
+            abs,
+            relative: Point::new(
+                abs.x.saturating_sub(origin.x),
+                abs.y.saturating_sub(origin.y),
+            ),
+        }
+    }
+
+    fn push_mouse_event(
+        &mut self,
+        p: utils::Point<f64, Logical>,
+        state: MouseState,
+        kind: MouseEventKind,
+    ) {
+        let (id, origin) = match self.window_at(p) {
+            Some((id, r)) => (id, r.loc),
+            None => (ROOT, (0, 0).into()),
+        };
+        let (x, y) = (p.x as i16, p.y as i16);
+        let evt = MouseEvent::new(
+            id,
+            x,
+            y,
+            x - origin.x as i16,
+            y - origin.y as i16,
+            state,
+            kind,
+        );
+
+        self.events.push_back(XEvent::MouseEvent(evt));
+    }
+}
+
+/// Handles communication with Wayland clients by running penrose as a Wayland compositor.
+///
+/// See the [module level docs][crate::wayland] for what is currently supported.
+pub struct WaylandConnection {
+    display: RefCell<Display>,
+    graphics: RefCell<WinitGraphicsBackend>,
+    input: RefCell<WinitInputBackend>,
+    keyboard: KeyboardHandle,
+    pointer: PointerHandle,
+    output: Output,
+    socket_name: OsString,
+    start: Instant,
+    last_frame: Cell<Option<Instant>>,
+    state: Rc<RefCell<State>>,
+}
+
+impl fmt::Debug for WaylandConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("WaylandConnection")
+            .field("socket_name", &self.socket_name)
+            .field("windows", &state.windows.keys().collect::<Vec<_>>())
+            .field("focused", &state.focused)
+            .finish()
+    }
+}
+
+impl WaylandConnection {
+    /// Open the window used to display the compositor and start listening for Wayland clients.
+    ///
+    /// `WAYLAND_DISPLAY` is set to the name of the new Wayland socket so that programs spawned
+    /// by penrose connect to it.
+    pub fn new() -> Result<Self> {
+        let (graphics, input) = winit::init(None)?;
+        let size = graphics.window_size().physical_size;
+
+        let mut display = Display::new();
+        let socket_name = display.add_socket_auto().map_err(WaylandError::from)?;
+        env::set_var("WAYLAND_DISPLAY", &socket_name);
+
+        let state = Rc::new(RefCell::new(State {
+            next_id: CHECK_WIN,
+            ..Default::default()
+        }));
+
+        compositor_init(&mut display, |surface, _| surface_committed(&surface), None);
+        init_shm_global(&mut display, vec![], None);
+        let shell_state = state.clone();
+        xdg_shell_init(
+            &mut display,
+            move |req, _| shell_state.borrow_mut().handle_xdg_request(req),
+            None,
+        );
+
+        let properties = PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: wl_output::Subpixel::Unknown,
+            make: "penrose".into(),
+            model: "winit".into(),
+        };
+        let (output, _) = Output::new(&mut display, "penrose".into(), properties, None);
+        let mode = Mode {
+            size,
+            refresh: REFRESH_MHZ,
+        };
+        output.change_current_state(Some(mode), None, None, Some((0, 0).into()));
+        output.set_preferred(mode);
+
+        let (mut seat, _) = Seat::new(&mut display, "penrose".into(), None);
+        let keyboard =
+            seat.add_keyboard(XkbConfig::default(), REPEAT_DELAY, REPEAT_RATE, |_, _| ())?;
+        let pointer = seat.add_pointer(|_| ());
+
+        Ok(Self {
+            display: RefCell::new(display),
+            graphics: RefCell::new(graphics),
+            input: RefCell::new(input),
+            keyboard,
+            pointer,
+            output,
+            socket_name,
+            start: Instant::now(),
+            last_frame: Cell::new(None),
+            state,
+        })
+    }
+
+    /// The name of the Wayland socket that clients connect to
+    pub fn socket_name(&self) -> &OsString {
+        &self.socket_name
+    }
+
+    fn logical_size(&self) -> utils::Size<i32, Logical> {
+        let size = self.graphics.borrow().window_size();
+        size.physical_size
+            .to_f64()
+            .to_logical(size.scale_factor)
+            .to_i32_round()
+    }
+
+    fn next_event(&self) -> Option<XEvent> {
+        self.state.borrow_mut().events.pop_front()
+    }
+
+    // Process any pending input and client requests, waiting for up to 'timeout' for clients
+    // if there are none, and then draw the next frame if one is due.
+    fn dispatch(&self, timeout: Duration) -> Result<()> {
+        let res = self
+            .input
+            .borrow_mut()
+            .dispatch_new_events(|event| self.handle_input(event));
+        if let Err(WinitInputError::WindowClosed) = res {
+            return Err(XError::ConnectionClosed);
+        }
+
+        self.display
+            .borrow_mut()
+            .dispatch(timeout, &mut ())
+            .map_err(WaylandError::from)?;
+        self.state.borrow_mut().refresh();
+        self.render()?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn handle_input(&self, event: InputEvent<WinitInputBackend>) {
+        match event {
+            InputEvent::Keyboard { event } => {
+                self.key_input(event.key_code(), event.state(), event.time())
+            }
+
+            InputEvent::PointerMotionAbsolute { event } => {
+                let p = event.position_transformed(self.logical_size());
+                self.pointer_moved(p, event.time());
+            }
+
+            InputEvent::PointerButton { event } => {
+                self.pointer_button(event.button(), event.state(), event.time())
+            }
+
+            InputEvent::PointerAxis { event } => self.pointer_axis(&event),
+
+            InputEvent::Special(WinitEvent::Resized { size, .. }) => {
+                let mode = Mode {
+                    size,
+                    refresh: REFRESH_MHZ,
+                };
+                self.output
+                    .change_current_state(Some(mode), None, None, None);
+                self.output.set_preferred(mode);
+                self.state
+                    .borrow_mut()
+                    .events
+                    .push_back(XEvent::RandrNotify);
+            }
+
+            _ => (),
+        }
+    }
+
+    // Key presses are sent to penrose instead of the focused client if they are bound or if
+    // the keyboard is grabbed, along with the matching release.
+    fn key_input(&self, key: u32, key_state: KeyState, time: u32) {
+        let serial = SERIAL_COUNTER.next_serial();
+
+        self.keyboard
+            .input(key, key_state, serial, time, |mods, _| {
+                let mut state = self.state.borrow_mut();
+                if (state.mods.caps_lock, state.mods.num_lock) != (mods.caps_lock, mods.num_lock) {
+                    state.events.push_back(XEvent::LockStateChanged(LockState {
+                        caps_lock: mods.caps_lock,
+                        num_lock: mods.num_lock,
+                    }));
+                }
+                state.mods = *mods;
+
+                let code = key_code(mods, key);
+                match key_state {
+                    KeyState::Pressed
+                        if state.keyboard_grabbed || state.grabbed_keys.contains(&code) =>
+                    {
+                        state.suppressed_keys.insert(key);
+                        state.events.push_back(XEvent::KeyPress(code));
+                        false
+                    }
+                    KeyState::Released if state.suppressed_keys.remove(&key) => {
+                        if state.keyboard_grabbed {
+                            state.events.push_back(XEvent::KeyRelease(code));
+                        }
+                        false
+                    }
+                    _ => true,
+                }
+            });
+    }
+
+    fn pointer_moved(&self, p: utils::Point<f64, Logical>, time: u32) {
+        let mut state = self.state.borrow_mut();
+
+        let hovered = state.window_at(p).map(|(id, _)| id);
+        if hovered != state.hovered {
+            if let Some(id) = state.hovered {
+                let change = state.pointer_change(id, p);
+                state.events.push_back(XEvent::Leave(change));
+            }
+            if let Some(id) = hovered {
+                let change = state.pointer_change(id, p);
+                state.events.push_back(XEvent::Enter(change));
+            }
+            state.hovered = hovered;
+        }
+
+        if let Some(held) = state.held_button.clone() {
+            state.push_mouse_event(p, held, MouseEventKind::Motion);
+            return;
+        }
+
+        let focus = state.surface_at(p).map(|(_, s, r)| (s, r.loc));
+        drop(state);
+        self.pointer
+            .motion(p, focus, SERIAL_COUNTER.next_serial(), time);
+    }
+
+    fn pointer_button(&self, button: input::MouseButton, button_state: ButtonState, time: u32) {
+        let p = self.pointer.current_location();
+        let mut state = self.state.borrow_mut();
+
+        let wl_state = match button_state {
+            ButtonState::Pressed => {
+                if let Some(ms) = mouse_state(&state.mods, button) {
+                    if state.pointer_grabbed || state.grabbed_buttons.contains(&ms) {
+                        state.push_mouse_event(p, ms.clone(), MouseEventKind::Press);
+                        state.held_button = Some(ms);
+                        return;
+                    }
+                }
+                wl_pointer::ButtonState::Pressed
+            }
+
+            ButtonState::Released => {
+                if let Some(held) = state.held_button.take() {
+                    state.push_mouse_event(p, held, MouseEventKind::Release);
+                    return;
+                }
+                wl_pointer::ButtonState::Released
+            }
+        };
+
+        drop(state);
+        self.pointer.button(
+            button_code(button),
+            wl_state,
+            SERIAL_COUNTER.next_serial(),
+            time,
+        );
+    }
+
+    fn pointer_axis<E>(&self, event: &E)
+    where
+        E: PointerAxisEvent<WinitInputBackend>,
+    {
+        let axes = [
+            (Axis::Horizontal, wl_pointer::Axis::HorizontalScroll),
+            (Axis::Vertical, wl_pointer::Axis::VerticalScroll),
+        ];
+        let mut frame = AxisFrame::new(event.time()).source(axis_source(event.source()));
+
+        for &(axis, wl_axis) in axes.iter() {
+            let steps = event.amount_discrete(axis);
+            // Wheels only report discrete steps so scroll by a few lines for each of them
+            let amount = event.amount(axis).or_else(|| steps.map(|s| s * 3.0));
+
+            if let Some(amount) = amount.filter(|&a| a != 0.0) {
+                frame = frame.value(wl_axis, amount);
+            }
+            if let Some(steps) = steps.filter(|&s| s != 0.0) {
+                frame = frame.discrete(wl_axis, steps as i32);
+            }
+        }
+
+        self.pointer.axis(frame);
+    }
+
+    fn render(&self) -> Result<()> {
+        let now = Instant::now();
+        if matches!(self.last_frame.get(), Some(t) if now.duration_since(t) < FRAME) {
+            return Ok(());
+        }
+        self.last_frame.set(Some(now));
+
+        let visible = self.state.borrow().visible_surfaces();
+        let mut graphics = self.graphics.borrow_mut();
+        let scale = graphics.window_size().scale_factor;
+
+        graphics
+            .render(|renderer, frame| {
+                frame.clear(BACKGROUND)?;
+                for (_, surface, r) in visible.iter() {
+                    draw_surface_tree(renderer, frame, surface, r.loc, scale)?;
+                }
+                Ok(())
+            })
+            .map_err(WaylandError::from)?
+            .map_err(|e: Gles2Error| WaylandError::from(e))?;
+
+        let time = self.start.elapsed().as_millis() as u32;
+        for (_, surface, _) in visible.iter() {
+            send_frames(surface, time);
+        }
+
+        Ok(())
+    }
+}
+
+impl XAtomQuerier for WaylandConnection {
+    fn atom_name(&self, atom: Xid) -> Result<String> {
+        let state = self.state.borrow();
+        atom.checked_sub(1)
+            .and_then(|ix| state.atoms.get(ix as usize))
+            .cloned()
+            .ok_or(XError::UnknownAtom(atom))
+    }
+
+    // There are no atoms under Wayland but each name is still given a unique id
+    fn atom_id(&self, name: &str) -> Result<Xid> {
+        let mut state = self.state.borrow_mut();
+        let ix = match state.atoms.iter().position(|a| a == name) {
+            Some(ix) => ix,
+            None => {
+                state.atoms.push(name.to_string());
+                state.atoms.len() - 1
+            }
+        };
+
+        Ok(ix as Xid + 1)
+    }
+}
+
+impl XClientConfig for WaylandConnection {
+    fn configure_client(&self, id: Xid, data: &[ClientConfig]) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+
+        if let Some((r, _)) = state.input_windows.get_mut(&id) {
+            for c in data {
+                if let ClientConfig::Position(new) = *c {
+                    *r = new;
+                }
+            }
+            return Ok(());
+        }
+
+        let w = state
+            .windows
+            .get_mut(&id)
+            .ok_or(XError::UnknownClient(id))?;
+        let mut raise = false;
+
+        for c in data {
+            match *c {
+                ClientConfig::Position(r) => {
+                    w.region = r;
+                    let size = (r.w as i32, r.h as i32).into();
+                    if w.toplevel
+                        .with_pending_state(|s| s.size = Some(size))
+                        .is_ok()
+                    {
+                        w.toplevel.send_configure();
+                    }
+                }
+                ClientConfig::BorderPx(px) => w.border = px,
+                ClientConfig::StackAbove => raise = true,
+            }
+        }
+
+        if raise {
+            state.stack.retain(|&s| s != id);
+            state.stack.push(id);
+        }
+
+        Ok(())
+    }
+
+    // Borders are not drawn and there are no event masks to select so there is nothing to set
+    fn set_client_attributes(&self, _: Xid, _: &[ClientAttr]) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_window_attributes(&self, id: Xid) -> Result<WindowAttributes> {
+        let state = self.state.borrow();
+        let (mapped, class) = match (state.windows.get(&id), state.input_windows.get(&id)) {
+            (Some(w), _) => (w.mapped, WindowClass::InputOutput),
+            (None, Some(&(_, mapped))) => (mapped, WindowClass::InputOnly),
+            (None, None) => return Err(XError::UnknownClient(id)),
+        };
+        let map_state = if mapped {
+            MapState::Viewable
+        } else {
+            MapState::Unmapped
+        };
+
+        Ok(WindowAttributes::new(false, map_state, class))
+    }
+}
+
+impl XClientHandler for WaylandConnection {
+    fn map_client(&self, id: Xid) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if let Some(w) = state.windows.get_mut(&id) {
+            w.mapped = true;
+        } else if let Some((_, mapped)) = state.input_windows.get_mut(&id) {
+            *mapped = true;
+        } else {
+            return Err(XError::UnknownClient(id));
+        }
+
+        Ok(())
+    }
+
+    fn unmap_client(&self, id: Xid) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if let Some(w) = state.windows.get_mut(&id) {
+            w.mapped = false;
+        } else if let Some((_, mapped)) = state.input_windows.get_mut(&id) {
+            *mapped = false;
+        } else {
+            return Err(XError::UnknownClient(id));
+        }
+
+        Ok(())
+    }
+
+    fn focus_client(&self, id: Xid) -> Result<()> {
+        let serial = SERIAL_COUNTER.next_serial();
+        let mut state = self.state.borrow_mut();
+
+        if let Some(w) = state.focused.and_then(|prev| state.windows.get(&prev)) {
+            set_activated(&w.toplevel, false);
+        }
+
+        state.focused = match state.windows.get(&id) {
+            Some(w) => {
+                set_activated(&w.toplevel, true);
+                self.keyboard.set_focus(w.surface(), serial);
+                Some(id)
+            }
+            None => {
+                self.keyboard.set_focus(None, serial);
+                None
+            }
+        };
+
+        Ok(())
+    }
+
+    fn destroy_client(&self, id: Xid) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if state.input_windows.remove(&id).is_some() {
+            return Ok(());
+        }
+
+        match state.windows.get(&id) {
+            Some(w) => {
+                w.toplevel.send_close();
+                Ok(())
+            }
+            None => Err(XError::UnknownClient(id)),
+        }
+    }
+
+    fn kill_client(&self, id: Xid) -> Result<()> {
+        let state = self.state.borrow();
+        let w = state.windows.get(&id).ok_or(XError::UnknownClient(id))?;
+        if let Some(client) = w.surface().and_then(|s| s.as_ref().client()) {
+            client.kill();
+        }
+
+        Ok(())
+    }
+
+    fn create_input_window(&self, r: Region) -> Result<Xid> {
+        let mut state = self.state.borrow_mut();
+        let id = state.new_id();
+        state.input_windows.insert(id, (r, false));
+
+        Ok(id)
+    }
+}
+
+impl XClientProperties for WaylandConnection {
+    fn list_props(&self, id: Xid) -> Result<Vec<String>> {
+        let state = self.state.borrow();
+        Ok(state
+            .props
+            .get(&id)
+            .map(|props| props.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn get_prop(&self, id: Xid, name: &str) -> Result<Prop> {
+        let state = self.state.borrow();
+        state
+            .props
+            .get(&id)
+            .and_then(|props| props.get(name))
+            .cloned()
+            .ok_or_else(|| XError::MissingProperty(name.into(), id))
+    }
+
+    fn delete_prop(&self, id: Xid, name: &str) -> Result<()> {
+        if let Some(props) = self.state.borrow_mut().props.get_mut(&id) {
+            props.remove(name);
+        }
+
+        Ok(())
+    }
+
+    fn change_prop(&self, id: Xid, name: &str, val: Prop) -> Result<()> {
+        self.state.borrow_mut().set_prop(id, name, val);
+        Ok(())
+    }
+
+    // Clients are unmapped by the compositor rather than being told to iconify themselves
+    fn set_client_state(&self, _: Xid, _: WindowState) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl XEventHandler for WaylandConnection {
+    fn flush(&self) -> bool {
+        self.display.borrow_mut().flush_clients(&mut ());
+        true
+    }
+
+    fn wait_for_event(&self) -> Result<XEvent> {
+        loop {
+            if let Some(event) = self.next_event() {
+                return Ok(event);
+            }
+            self.dispatch(FRAME)?;
+        }
+    }
+
+    fn poll_for_event(&self) -> Result<Option<XEvent>> {
+        if let Some(event) = self.next_event() {
+            return Ok(Some(event));
+        }
+        self.dispatch(Duration::from_millis(0))?;
+
+        Ok(self.next_event())
+    }
+
+    fn wait_for_event_timeout(&self, timeout: Duration) -> Result<Option<XEvent>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(event) = self.next_event() {
+                return Ok(Some(event));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_millis(0) {
+                return Ok(None);
+            }
+            self.dispatch(remaining.min(FRAME))?;
+        }
+    }
+
+    // Wayland clients do not receive X client messages. Those sent to the root window are passed
+    // back to penrose in the same way as they would be under X and WM_DELETE_WINDOW asks the
+    // toplevel to close: anything else is dropped.
+    fn send_client_event(&self, msg: ClientMessage) -> Result<()> {
+        if msg.id == ROOT {
+            let mut state = self.state.borrow_mut();
+            state.events.push_back(XEvent::ClientMessage(msg));
+            return Ok(());
+        }
+
+        let delete = self.atom_id(Atom::WmDeleteWindow.as_ref())?;
+        let is_delete = msg.data().as_u32().first() == Some(&delete);
+        if msg.dtype == Atom::WmProtocols.as_ref() && is_delete {
+            if let Some(w) = self.state.borrow().windows.get(&msg.id) {
+                w.toplevel.send_close();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_client_event(&self, kind: ClientMessageKind) -> Result<ClientMessage> {
+        kind.as_message(self)
+    }
+}
+
+impl XState for WaylandConnection {
+    fn root(&self) -> Xid {
+        ROOT
+    }
+
+    fn current_screens(&self) -> Result<Vec<Screen>> {
+        let size = self.logical_size();
+        let r = Region::new(0, 0, size.w as u32, size.h as u32);
+
+        Ok(vec![Screen::new(r, 0)])
+    }
+
+    fn cursor_position(&self) -> Result<Point> {
+        Ok(point(self.pointer.current_location()))
+    }
+
+    fn modifier_state(&self) -> Result<u16> {
+        Ok(key_code(&self.state.borrow().mods, 0).mask)
+    }
+
+    // The compositor is shown inside of a window so it is unable to move the cursor
+    fn warp_cursor(&self, _: Option<Xid>, _: &Screen) -> Result<()> {
+        Ok(())
+    }
+
+    fn client_geometry(&self, id: Xid) -> Result<Region> {
+        let state = self.state.borrow();
+        match (state.windows.get(&id), state.input_windows.get(&id)) {
+            (Some(w), _) => Ok(w.region),
+            (None, Some(&(r, _))) => Ok(r),
+            (None, None) => Err(XError::UnknownClient(id)),
+        }
+    }
+
+    fn active_clients(&self) -> Result<Vec<Xid>> {
+        Ok(self.state.borrow().stack.clone())
+    }
+
+    fn focused_client(&self) -> Result<Xid> {
+        Ok(self.state.borrow().focused.unwrap_or(ROOT))
+    }
+}
+
+impl XConn for WaylandConnection {
+    #[cfg(feature = "serde")]
+    fn hydrate(&mut self) -> Result<()> {
+        Err(XError::Raw(
+            "Wayland clients do not outlive the compositor so they can not be restored".into(),
+        ))
+    }
+
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn check_window(&self) -> Xid {
+        CHECK_WIN
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        for w in self.state.borrow().windows.values() {
+            w.toplevel.send_close();
+        }
+        self.flush();
+
+        Ok(())
+    }
+
+    fn grab_keys(
+        &self,
+        key_bindings: &KeyBindings<Self>,
+        mouse_bindings: &MouseBindings<Self>,
+    ) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.grabbed_keys = key_bindings.keys().copied().collect();
+        state.grabbed_buttons = mouse_bindings
+            .keys()
+            .map(|(_, mouse_state)| mouse_state.clone())
+            .collect();
+
+        Ok(())
+    }
+
+    fn ungrab_keys(&self) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.grabbed_keys.clear();
+        state.grabbed_buttons.clear();
+
+        Ok(())
+    }
+
+    fn grab_keyboard(&self) -> Result<()> {
+        self.state.borrow_mut().keyboard_grabbed = true;
+        Ok(())
+    }
+
+    fn ungrab_keyboard(&self) -> Result<()> {
+        self.state.borrow_mut().keyboard_grabbed = false;
+        Ok(())
+    }
+
+    fn grab_pointer(&self) -> Result<()> {
+        self.state.borrow_mut().pointer_grabbed = true;
+        Ok(())
+    }
+
+    fn ungrab_pointer(&self) -> Result<()> {
+        self.state.borrow_mut().pointer_grabbed = false;
+        Ok(())
+    }
+
+    fn refresh_keymap(&self) -> Result<()> {
+        // key presses are passed through as raw key codes so there is no keymap to refresh
+        Ok(())
+    }
+
+    fn keycodes(&self) -> Result<HashMap<String, KeyCodeValue>> {
+        try_keycodes_from_xmodmap().map_err(|e| XError::Raw(e.to_string()))
+    }
+
+    // winit reports all input as coming from a single virtual device which can not be configured
+    fn pointer_devices(&self) -> Result<Vec<InputDevice>> {
+        Ok(vec![])
+    }
+
+    fn keyboard_devices(&self) -> Result<Vec<InputDevice>> {
+        Ok(vec![])
+    }
+
+    fn configure_pointer_device(&self, _: &InputDevice, _: &PointerConfig) -> Result<()> {
+        Ok(())
+    }
+
+    fn lock_state(&self) -> Result<LockState> {
+        let mods = self.state.borrow().mods;
+        Ok(LockState {
+            caps_lock: mods.caps_lock,
+            num_lock: mods.num_lock,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mods(shift: bool, ctrl: bool, alt: bool, logo: bool) -> ModifiersState {
+        ModifiersState {
+            shift,
+            ctrl,
+            alt,
+            logo,
+            ..Default::default()
+        }
+    }
+
+    test_cases! {
+        key_code;
+        args: (mods: ModifiersState, key: u32, expected: KeyCode);
+
+        case: no_modifiers => (mods(false, false, false, false), 36, KeyCode { mask: 0, code: 44 });
+        case: super_key => (mods(false, false, false, true), 36, KeyCode { mask: 64, code: 44 });
+        case: all_modifiers => (mods(true, true, true, true), 36, KeyCode { mask: 77, code: 44 });
+        case: lock_keys_are_ignored => (
+            ModifiersState { caps_lock: true, num_lock: true, ..mods(false, true, false, false) },
+            36,
+            KeyCode { mask: 4, code: 44 }
+        );
+
+        body: {
+            assert_eq!(key_code(&mods, key), expected);
+        }
+    }
+
+    #[test]
+    fn mouse_state_includes_held_modifiers() {
+        let state = mouse_state(&mods(true, false, false, true), input::MouseButton::Left);
+        let expected = MouseState::new(
+            MouseButton::Left,
+            vec![ModifierKey::Meta, ModifierKey::Shift],
+        );
+
+        assert_eq!(state, Some(expected));
+        assert_eq!(
+            mouse_state(
+                &mods(false, false, false, false),
+                input::MouseButton::Other(8)
+            ),
+            None
+        );
+    }
+}
@@ -26,8 +26,8 @@ impl Atoms {
         Ok(Self { atoms })
     }
 
-    pub(crate) fn known_atom(&self, atom: Atom) -> u32 {
-        *self.atoms.get(&atom).unwrap()
+    pub(crate) fn known_atom(&self, atom: Atom) -> Option<u32> {
+        self.atoms.get(&atom).copied()
     }
 
     pub(crate) fn atom_name(&self, atom: u32) -> Option<Atom> {
@@ -4,7 +4,7 @@ use crate::{
         data_types::{Point, Region},
         xconnection::{
             event::ClientEventMask, ClientMessage, ClientMessageData, ConfigureEvent, ExposeEvent,
-            PointerChange, PropertyEvent, Result, XAtomQuerier, XError, XEvent,
+            PointerChange, PropertyEvent, Result, XAtomQuerier, XConn, XError, XEvent,
         },
     },
     x11rb::{xconn::X11rbConnection, X11rbError},
@@ -15,7 +15,8 @@ use strum::IntoEnumIterator;
 use x11rb::{
     connection::Connection,
     protocol::{
-        xproto::{ClientMessageEvent, ConnectionExt as _, ModMask},
+        xkb,
+        xproto::{ClientMessageEvent, ConnectionExt as _, Mapping, ModMask},
         Event,
     },
 };
@@ -27,6 +28,23 @@ pub(crate) fn convert_event<C: Connection>(
     match event {
         Event::RandrNotify(_) => Ok(Some(XEvent::RandrNotify)),
         Event::RandrScreenChangeNotify(_) => Ok(Some(XEvent::ScreenChange)),
+        Event::XinputDevicePresenceNotify(_) => Ok(Some(XEvent::InputDevicesChanged)),
+        Event::XkbIndicatorStateNotify(_) => Ok(Some(XEvent::LockStateChanged(conn.lock_state()?))),
+        Event::XkbMapNotify(_) | Event::XkbNewKeyboardNotify(_) => {
+            Ok(Some(XEvent::KeyboardMappingChanged))
+        }
+        // With XKB available the same change is also reported as an XkbMapNotify
+        Event::MappingNotify(event) if event.request != Mapping::POINTER => {
+            let has_xkb = conn
+                .connection()
+                .extension_information(xkb::X11_EXTENSION_NAME)?
+                .is_some();
+            Ok(if has_xkb {
+                None
+            } else {
+                Some(XEvent::KeyboardMappingChanged)
+            })
+        }
         Event::ButtonPress(event) => Ok(to_mouse_state(event.detail, event.state).map(|state| {
             XEvent::MouseEvent(MouseEvent::new(
                 event.event,
@@ -71,6 +89,16 @@ pub(crate) fn convert_event<C: Connection>(
                 code.ignoring_modifier(numlock.into()),
             )))
         }
+        Event::KeyRelease(event) => {
+            let code = KeyCode {
+                mask: event.state,
+                code: event.detail,
+            };
+            let numlock = ModMask::M2;
+            Ok(Some(XEvent::KeyRelease(
+                code.ignoring_modifier(numlock.into()),
+            )))
+        }
         Event::MapRequest(event) => {
             let attr = conn
                 .connection()
@@ -92,6 +120,10 @@ pub(crate) fn convert_event<C: Connection>(
             relative: Point::new(event.event_x as u32, event.event_y as u32),
         }))),
         Event::DestroyNotify(event) => Ok(Some(XEvent::Destroy(event.window))),
+        // Clients select StructureNotify so we see each unmap twice: only pass on the event
+        // reported to the parent window.
+        Event::UnmapNotify(event) if event.event == event.window => Ok(None),
+        Event::UnmapNotify(event) => Ok(Some(XEvent::UnmapNotify(event.window))),
         Event::ConfigureNotify(event) => Ok(Some(XEvent::ConfigureNotify(ConfigureEvent {
             id: event.window,
             r: Region::new(
@@ -135,9 +167,19 @@ pub(crate) fn convert_event<C: Connection>(
     }
 }
 
+/// The X11 modifier mask for a single [ModifierKey]
+pub(crate) fn modifier_mask(key: ModifierKey) -> u16 {
+    u16::from(match key {
+        ModifierKey::Ctrl => ModMask::CONTROL,
+        ModifierKey::Alt => ModMask::M1,
+        ModifierKey::Shift => ModMask::SHIFT,
+        ModifierKey::Meta => ModMask::M4,
+    })
+}
+
 fn to_mouse_state(detail: u8, state: u16) -> Option<MouseState> {
     fn is_held(key: &ModifierKey, mask: u16) -> bool {
-        mask & u16::from(*key) > 0
+        mask & modifier_mask(*key) > 0
     }
     let button = match detail {
         1 => MouseButton::Left,
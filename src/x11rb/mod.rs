@@ -26,6 +26,8 @@ use x11rb::{
     x11_utils::X11Error,
 };
 
+use std::os::unix::io::AsRawFd;
+
 pub(crate) mod atom;
 pub(crate) mod event;
 pub mod xconn;
@@ -48,7 +50,9 @@ pub fn new_x11rb_rust_backed_window_manager(
     error_handler: ErrorHandler,
 ) -> crate::Result<WindowManager<X11rbConnection<RustConnection>>> {
     let (conn, _) = RustConnection::connect(None).map_err(X11rbError::from)?;
-    new_x11rb_backed_window_manager(conn, config, hooks, error_handler)
+    let fd = conn.stream().as_raw_fd();
+    let conn = X11rbConnection::new_for_connection(conn)?.with_event_fd(fd);
+    init_window_manager(conn, config, hooks, error_handler)
 }
 
 /// Construct a penrose [WindowManager] backed by the [x11rb][crate::x11rb] backend using
@@ -60,7 +64,9 @@ pub fn new_x11rb_xcb_backed_window_manager(
     error_handler: ErrorHandler,
 ) -> crate::Result<WindowManager<X11rbConnection<XCBConnection>>> {
     let (conn, _) = XCBConnection::connect(None).map_err(X11rbError::from)?;
-    new_x11rb_backed_window_manager(conn, config, hooks, error_handler)
+    let fd = conn.as_raw_fd();
+    let conn = X11rbConnection::new_for_connection(conn)?.with_event_fd(fd);
+    init_window_manager(conn, config, hooks, error_handler)
 }
 
 /// Construct a penrose [WindowManager] backed by the [x11rb][crate::x11rb] backend using
@@ -72,6 +78,15 @@ pub fn new_x11rb_backed_window_manager<C: Connection>(
     error_handler: ErrorHandler,
 ) -> crate::Result<WindowManager<X11rbConnection<C>>> {
     let conn = X11rbConnection::new_for_connection(connection)?;
+    init_window_manager(conn, config, hooks, error_handler)
+}
+
+fn init_window_manager<C: Connection>(
+    conn: X11rbConnection<C>,
+    config: Config,
+    hooks: Vec<Box<dyn Hook<X11rbConnection<C>>>>,
+    error_handler: ErrorHandler,
+) -> crate::Result<WindowManager<X11rbConnection<C>>> {
     let mut wm = WindowManager::new(config, conn, hooks, error_handler);
     wm.init()?;
 
@@ -9,9 +9,14 @@
 
 use crate::{
     core::{
-        bindings::{KeyBindings, KeyCode, MouseBindings, MouseState},
+        bindings::{KeyBindings, KeyCode, KeyCodeValue, MouseBindings, MouseState},
         data_types::{Point, Region},
-        screen::Screen,
+        helpers::try_keycodes_from_xmodmap,
+        input::{
+            InputDevice, LockState, PointerConfig, ACCEL_SPEED_PROP, CAPS_LOCK_INDICATOR,
+            NATURAL_SCROLLING_PROP, NUM_LOCK_INDICATOR,
+        },
+        screen::{dpi_from_physical_size, parse_xft_dpi, Screen, DEFAULT_DPI},
         xconnection::{
             self, Atom, ClientAttr, ClientConfig, ClientEventMask, ClientMessage,
             ClientMessageKind, Prop, Result, WindowAttributes, WindowState, WmHints, WmNormalHints,
@@ -19,19 +24,30 @@ use crate::{
             XEventHandler, XState, Xid,
         },
     },
+    draw::Color,
     x11rb::{atom::Atoms, X11rbError},
 };
 
-use std::{convert::TryFrom, str::FromStr};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    os::unix::io::RawFd,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
 
 use x11rb::{
     connection::Connection,
     protocol::{
         randr::{self, ConnectionExt as _},
+        xinput::{self, ConnectionExt as _},
+        xkb::{self, ConnectionExt as _},
         xproto::{
-            AtomEnum, ButtonIndex, ChangeWindowAttributesAux, ClientMessageData,
-            ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, CreateWindowAux, EventMask,
-            Grab, GrabMode, InputFocus, MapState, ModMask, PropMode, StackMode, WindowClass,
+            AtomEnum, ButtonIndex, ChangeGCAux, ChangeWindowAttributesAux, ClientMessageData,
+            ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, CreateGCAux,
+            CreateWindowAux, EventMask, Grab, GrabMode, GrabStatus, InputFocus, MapState,
+            MappingStatus, ModMask, PropMode, Rectangle, StackMode, WindowClass,
             CLIENT_MESSAGE_EVENT,
         },
     },
@@ -40,6 +56,8 @@ use x11rb::{
 };
 
 const RANDR_VER: (u32, u32) = (1, 2);
+// The XInput event class for DevicePresenceNotify events (see DevicePresence in XInput.h)
+const DEVICE_PRESENCE_CLASS: u32 = 0x10000;
 
 /// Handles communication with an X server via the x11rb crate.
 #[derive(Debug)]
@@ -48,6 +66,7 @@ pub struct X11rbConnection<C: Connection> {
     root: Xid,
     check_win: Xid,
     atoms: Atoms,
+    event_fd: Option<RawFd>,
 }
 
 impl<C: Connection> X11rbConnection<C> {
@@ -78,6 +97,35 @@ impl<C: Connection> X11rbConnection<C> {
         let mask = NotifyMask::OUTPUT_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::SCREEN_CHANGE;
         conn.randr_select_input(root, mask)?;
 
+        // XInput is optional: without it we are unable to detect newly connected input devices
+        if conn
+            .extension_information(xinput::X11_EXTENSION_NAME)?
+            .is_some()
+        {
+            conn.xinput_select_extension_event(root, &[DEVICE_PRESENCE_CLASS])?;
+        }
+
+        // XKB is optional: without it we are unable to track the state of the lock keys
+        if conn
+            .extension_information(xkb::X11_EXTENSION_NAME)?
+            .is_some()
+            && conn.xkb_use_extension(1, 0)?.reply()?.supported
+        {
+            use xkb::{EventType, MapPart};
+            let events = EventType::INDICATOR_STATE_NOTIFY
+                | EventType::NEW_KEYBOARD_NOTIFY
+                | EventType::MAP_NOTIFY;
+            let map_parts = MapPart::KEY_SYMS | MapPart::MODIFIER_MAP;
+            conn.xkb_select_events(
+                xkb::ID::USE_CORE_KBD.into(),
+                0u16,      // clear
+                events,    // select_all
+                map_parts, // affect_map
+                map_parts, // map
+                &xkb::SelectEventsAux::new(),
+            )?;
+        }
+
         let check_win = conn.generate_id()?;
         conn.create_window(
             x11rb::COPY_DEPTH_FROM_PARENT,
@@ -98,9 +146,19 @@ impl<C: Connection> X11rbConnection<C> {
             root,
             check_win,
             atoms,
+            event_fd: None,
         })
     }
 
+    /// Set the file descriptor that the underlying connection reads events from.
+    ///
+    /// This allows penrose to sleep until an event arrives while waiting for a key chord or hot
+    /// corner to time out rather than repeatedly polling for new events.
+    pub fn with_event_fd(mut self, fd: RawFd) -> Self {
+        self.event_fd = Some(fd);
+        self
+    }
+
     /// The root window ID
     pub fn root(&self) -> Xid {
         self.root
@@ -126,8 +184,11 @@ impl<C: Connection> XAtomQuerier for X11rbConnection<C> {
     }
 
     fn atom_id(&self, name: &str) -> Result<Xid> {
-        if let Ok(known) = Atom::from_str(name) {
-            return Ok(self.atoms.known_atom(known));
+        if let Some(id) = Atom::from_str(name)
+            .ok()
+            .and_then(|known| self.atoms.known_atom(known))
+        {
+            return Ok(id);
         }
 
         Ok(self.conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
@@ -152,10 +213,9 @@ impl<C: Connection> XClientConfig for X11rbConnection<C> {
     }
 
     fn set_client_attributes(&self, id: Xid, data: &[ClientAttr]) -> Result<()> {
-        let client_event_mask = EventMask::ENTER_WINDOW
-            | EventMask::LEAVE_WINDOW
-            | EventMask::PROPERTY_CHANGE
-            | EventMask::STRUCTURE_NOTIFY;
+        let no_crossing_event_mask = EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY;
+        let client_event_mask =
+            EventMask::ENTER_WINDOW | EventMask::LEAVE_WINDOW | no_crossing_event_mask;
 
         let root_event_mask = EventMask::PROPERTY_CHANGE
             | EventMask::SUBSTRUCTURE_REDIRECT
@@ -166,8 +226,20 @@ impl<C: Connection> XClientConfig for X11rbConnection<C> {
         for conf in data.iter() {
             match conf {
                 ClientAttr::BorderColor(c) => aux = aux.border_pixel(*c),
+                ClientAttr::BackgroundColor(c) => aux = aux.background_pixel(*c),
                 ClientAttr::ClientEventMask => aux = aux.event_mask(client_event_mask),
+                ClientAttr::ClientEventMaskNoCrossing => {
+                    aux = aux.event_mask(no_crossing_event_mask)
+                }
                 ClientAttr::RootEventMask => aux = aux.event_mask(root_event_mask),
+                ClientAttr::RootEventMaskWithClicks => {
+                    aux = aux.event_mask(
+                        root_event_mask | EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE,
+                    )
+                }
+                ClientAttr::ButtonEventMask => {
+                    aux = aux.event_mask(EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE)
+                }
             }
         }
         self.conn.change_window_attributes(id, &aux)?;
@@ -206,6 +278,72 @@ impl<C: Connection> XClientConfig for X11rbConnection<C> {
             window_class,
         ))
     }
+
+    fn set_window_background(&self, id: Xid, color: Color) -> Result<()> {
+        let aux = ChangeWindowAttributesAux::new().background_pixel(color.rgb_u32());
+        self.conn.change_window_attributes(id, &aux)?;
+        self.conn.clear_area(false, id, 0, 0, 0, 0)?;
+        self.conn.flush()?;
+
+        Ok(())
+    }
+
+    fn set_client_border_colors(
+        &self,
+        id: Xid,
+        outer: Color,
+        inner: Color,
+        inner_px: u32,
+    ) -> Result<()> {
+        let geom = self.conn.get_geometry(id)?.reply()?;
+        let (w, h, b) = (
+            geom.width as u32,
+            geom.height as u32,
+            geom.border_width as u32,
+        );
+        let rects: Vec<Rectangle> = xconnection::inner_border_regions(w, h, b, inner_px)
+            .into_iter()
+            .map(|r| Rectangle {
+                x: r.x as i16,
+                y: r.y as i16,
+                width: r.w as u16,
+                height: r.h as u16,
+            })
+            .collect();
+
+        if b == 0 || rects.is_empty() {
+            return self.set_client_border_color(id, outer);
+        }
+
+        let pixmap = self.conn.generate_id()?;
+        let gc = self.conn.generate_id()?;
+        let (pw, ph) = ((w + 2 * b) as u16, (h + 2 * b) as u16);
+        let full = Rectangle {
+            x: 0,
+            y: 0,
+            width: pw,
+            height: ph,
+        };
+
+        self.conn.create_pixmap(geom.depth, pixmap, id, pw, ph)?;
+        self.conn
+            .create_gc(gc, pixmap, &CreateGCAux::new().foreground(outer.rgb_u32()))?;
+        self.conn.poly_fill_rectangle(pixmap, gc, &[full])?;
+        self.conn
+            .change_gc(gc, &ChangeGCAux::new().foreground(inner.rgb_u32()))?;
+        self.conn.poly_fill_rectangle(pixmap, gc, &rects)?;
+        self.conn.change_window_attributes(
+            id,
+            &ChangeWindowAttributesAux::new().border_pixmap(pixmap),
+        )?;
+
+        // The server keeps its own reference to the pixmap once it is set as the border
+        self.conn.free_gc(gc)?;
+        self.conn.free_pixmap(pixmap)?;
+        self.conn.flush()?;
+
+        Ok(())
+    }
 }
 
 impl<C: Connection> XClientHandler for X11rbConnection<C> {
@@ -239,6 +377,32 @@ impl<C: Connection> XClientHandler for X11rbConnection<C> {
         self.conn.kill_client(id)?;
         Ok(())
     }
+
+    fn create_input_window(&self, r: Region) -> Result<Xid> {
+        let (x, y, w, h) = r.values();
+        let id = self.conn.generate_id()?;
+        let aux = CreateWindowAux::new().override_redirect(1).event_mask(
+            EventMask::ENTER_WINDOW
+                | EventMask::LEAVE_WINDOW
+                | EventMask::BUTTON_PRESS
+                | EventMask::BUTTON_RELEASE,
+        );
+        self.conn.create_window(
+            0,
+            id,
+            self.root,
+            x as i16,
+            y as i16,
+            w as u16,
+            h as u16,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &aux,
+        )?;
+
+        Ok(id)
+    }
 }
 
 impl<C: Connection> XClientProperties for X11rbConnection<C> {
@@ -258,8 +422,23 @@ impl<C: Connection> XClientProperties for X11rbConnection<C> {
                     .collect::<Result<Vec<String>>>()?,
             ),
 
-            // This uses unwrap() for symmetry with penrose::xcb (which does value()[0] to "panic")
-            "CARDINAL" => Prop::Cardinal(r.value32().unwrap().next().unwrap()),
+            "CARDINAL" => {
+                let mut values: Vec<u32> = r
+                    .value32()
+                    .ok_or_else(|| X11rbError::InvalidPropertyData(prop_type.to_string()))?
+                    .collect();
+                match values.len() {
+                    0 => {
+                        return Err(X11rbError::InvalidPropertyData(format!(
+                            "empty CARDINAL property: {}",
+                            name
+                        ))
+                        .into())
+                    }
+                    1 => Prop::Cardinal(values.remove(0)),
+                    _ => Prop::CardinalList(values),
+                }
+            }
 
             "STRING" | "UTF8_STRING" => Prop::UTF8String(
                 // FIXME: I think this should check prop.format == 8, but penrose::xcb does not
@@ -298,9 +477,9 @@ impl<C: Connection> XClientProperties for X11rbConnection<C> {
             // Default to returning the raw bytes as u32s which the user can then
             // convert as needed if the prop type is not one we recognise
             _ => Prop::Bytes(match r.format {
-                8 => r.value8().unwrap().map(From::from).collect(),
-                16 => r.value16().unwrap().map(From::from).collect(),
-                32 => r.value32().unwrap().collect(),
+                8 => r.value8().into_iter().flatten().map(From::from).collect(),
+                16 => r.value16().into_iter().flatten().map(From::from).collect(),
+                32 => r.value32().into_iter().flatten().collect(),
                 _ => {
                     return Err(X11rbError::InvalidPropertyData(format!(
                         "prop type for {} was {} which claims to have a data format of {}",
@@ -358,6 +537,8 @@ impl<C: Connection> XClientProperties for X11rbConnection<C> {
 
             Prop::Cardinal(val) => (AtomEnum::CARDINAL, vec![val]),
 
+            Prop::CardinalList(vals) => (AtomEnum::CARDINAL, vals),
+
             Prop::Window(ids) => (AtomEnum::WINDOW, ids),
 
             // FIXME: handle changing WmHints and WmNormalHints correctly in change_prop
@@ -402,6 +583,41 @@ impl<C: Connection> XEventHandler for X11rbConnection<C> {
         }
     }
 
+    fn poll_for_event(&self) -> Result<Option<XEvent>> {
+        while let Some(event) = self.conn.poll_for_event()? {
+            if let Some(event) = super::event::convert_event(self, event)? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn wait_for_event_timeout(&self, timeout: Duration) -> Result<Option<XEvent>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(event) = self.poll_for_event()? {
+                return Ok(Some(event));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_millis(0) {
+                return Ok(None);
+            }
+
+            match self.event_fd {
+                Some(fd) => {
+                    if !xconnection::wait_until_readable(fd, remaining)? {
+                        return Ok(None);
+                    }
+                }
+                // Without access to the connection we have no choice but to poll
+                None => thread::sleep(remaining.min(Duration::from_millis(10))),
+            }
+        }
+    }
+
     fn send_client_event(&self, msg: ClientMessage) -> Result<()> {
         let type_ = self.atom_id(&msg.dtype)?;
         let data = match msg.data() {
@@ -450,6 +666,12 @@ impl<C: Connection> XState for X11rbConnection<C> {
             })
             .collect::<Result<Vec<_>>>()?;
 
+        let fallback_dpi = match self.get_prop(self.root, "RESOURCE_MANAGER") {
+            Ok(Prop::UTF8String(strs)) => parse_xft_dpi(&strs.join("\n")),
+            _ => None,
+        }
+        .unwrap_or(DEFAULT_DPI);
+
         // Get the replies and construct screens
         let screens = crtcs
             .into_iter()
@@ -457,13 +679,17 @@ impl<C: Connection> XState for X11rbConnection<C> {
             .enumerate()
             .filter(|(_, reply)| reply.width > 0)
             .map(|(i, reply)| {
-                let region = Region::new(
-                    reply.x as u32,
-                    reply.y as u32,
-                    reply.width as u32,
-                    reply.height as u32,
-                );
-                Screen::new(region, i)
+                let (w, h) = (reply.width as u32, reply.height as u32);
+                let region = Region::new(reply.x as u32, reply.y as u32, w, h);
+                let dpi = reply
+                    .outputs
+                    .first()
+                    .and_then(|o| self.conn.randr_get_output_info(*o, 0).ok())
+                    .and_then(|cookie| cookie.reply().ok())
+                    .and_then(|o| dpi_from_physical_size((w, h), (o.mm_width, o.mm_height)))
+                    .unwrap_or(fallback_dpi);
+
+                Screen::new(region, i).with_dpi(dpi)
             })
             .collect();
         Ok(screens)
@@ -474,6 +700,10 @@ impl<C: Connection> XState for X11rbConnection<C> {
         Ok(Point::new(reply.root_x as u32, reply.root_y as u32))
     }
 
+    fn modifier_state(&self) -> Result<u16> {
+        Ok(self.conn.query_pointer(self.root)?.reply()?.mask)
+    }
+
     fn warp_cursor(&self, win_id: Option<Xid>, screen: &Screen) -> Result<()> {
         let (x, y, id) = match win_id {
             Some(id) => {
@@ -527,9 +757,7 @@ impl<C: Connection> XConn for X11rbConnection<C> {
 
     fn cleanup(&self) -> Result<()> {
         self.conn.ungrab_keyboard(x11rb::CURRENT_TIME)?;
-        self.conn.ungrab_key(Grab::ANY, self.root, ModMask::ANY)?;
-        self.conn
-            .ungrab_button(ButtonIndex::ANY, self.root, ModMask::ANY)?;
+        self.ungrab_keys()?;
         self.conn.destroy_window(self.check_win)?;
         let net_name = Atom::NetActiveWindow.as_ref();
         self.conn
@@ -555,9 +783,218 @@ impl<C: Connection> XConn for X11rbConnection<C> {
 
         Ok(())
     }
+
+    fn ungrab_keys(&self) -> Result<()> {
+        self.conn.ungrab_key(Grab::ANY, self.root, ModMask::ANY)?;
+        self.conn
+            .ungrab_button(ButtonIndex::ANY, self.root, ModMask::ANY)?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn grab_keyboard(&self) -> Result<()> {
+        self.conn
+            .grab_keyboard(
+                true,
+                self.root,
+                x11rb::CURRENT_TIME,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?
+            .reply()?;
+
+        Ok(())
+    }
+
+    fn ungrab_keyboard(&self) -> Result<()> {
+        self.conn.ungrab_keyboard(x11rb::CURRENT_TIME)?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn grab_pointer(&self) -> Result<()> {
+        // XC_crosshair from X11/cursorfont.h: the mask glyph is always the following one
+        const XC_CROSSHAIR: u16 = 34;
+
+        let font = self.conn.generate_id()?;
+        self.conn.open_font(font, b"cursor")?;
+        let cursor = self.conn.generate_id()?;
+        self.conn.create_glyph_cursor(
+            cursor,
+            font,
+            font,
+            XC_CROSSHAIR,
+            XC_CROSSHAIR + 1,
+            0,
+            0,
+            0,
+            0xffff,
+            0xffff,
+            0xffff,
+        )?;
+
+        let reply = self
+            .conn
+            .grab_pointer(
+                false,
+                self.root,
+                u32::from(EventMask::BUTTON_PRESS) as u16,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                cursor,
+                x11rb::CURRENT_TIME,
+            )?
+            .reply()?;
+
+        // The server holds its own reference to the cursor for the duration of the grab
+        self.conn.free_cursor(cursor)?;
+        self.conn.close_font(font)?;
+
+        if reply.status != GrabStatus::SUCCESS {
+            return Err(XError::Raw("unable to grab the pointer".into()));
+        }
+
+        Ok(())
+    }
+
+    fn ungrab_pointer(&self) -> Result<()> {
+        self.conn.ungrab_pointer(x11rb::CURRENT_TIME)?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn pointer_devices(&self) -> Result<Vec<InputDevice>> {
+        self.input_devices(xinput::DeviceType::SLAVE_POINTER)
+    }
+
+    fn keyboard_devices(&self) -> Result<Vec<InputDevice>> {
+        self.input_devices(xinput::DeviceType::SLAVE_KEYBOARD)
+    }
+
+    fn configure_pointer_device(&self, device: &InputDevice, conf: &PointerConfig) -> Result<()> {
+        if let Some(speed) = conf.get_accel_speed() {
+            let float = self.atom_id("FLOAT")?;
+            let data = xinput::XIChangePropertyAux::Data32(vec![speed.to_bits()]);
+            self.change_device_prop(device.id, ACCEL_SPEED_PROP, float, data)?;
+        }
+
+        if let Some(enabled) = conf.get_natural_scrolling() {
+            let data = xinput::XIChangePropertyAux::Data8(vec![enabled as u8]);
+            let integer = AtomEnum::INTEGER.into();
+            self.change_device_prop(device.id, NATURAL_SCROLLING_PROP, integer, data)?;
+        }
+
+        if let Some(map) = conf.get_button_map() {
+            let reply = self
+                .conn
+                .xinput_set_device_button_mapping(device.id as u8, map)?
+                .reply()?;
+            if reply.status != MappingStatus::SUCCESS {
+                return Err(XError::Raw(format!(
+                    "unable to set button mapping for '{}': device busy",
+                    device.name
+                )));
+            }
+        }
+
+        self.flush();
+
+        Ok(())
+    }
+
+    fn refresh_keymap(&self) -> Result<()> {
+        // x11rb does not cache a keymap: key presses are passed through as raw key codes
+        Ok(())
+    }
+
+    fn keycodes(&self) -> Result<HashMap<String, KeyCodeValue>> {
+        try_keycodes_from_xmodmap().map_err(|e| XError::Raw(e.to_string()))
+    }
+
+    fn lock_state(&self) -> Result<LockState> {
+        if self
+            .conn
+            .extension_information(xkb::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Ok(LockState::default());
+        }
+
+        let indicator_on = |name: &str| -> Result<bool> {
+            let reply = self
+                .conn
+                .xkb_get_named_indicator(
+                    xkb::ID::USE_CORE_KBD.into(),
+                    xkb::LedClass::DFLT_XI_CLASS,
+                    xkb::ID::DFLT_XI_ID,
+                    self.atom_id(name)?,
+                )?
+                .reply()?;
+
+            Ok(reply.found && reply.on)
+        };
+
+        Ok(LockState {
+            caps_lock: indicator_on(CAPS_LOCK_INDICATOR)?,
+            num_lock: indicator_on(NUM_LOCK_INDICATOR)?,
+        })
+    }
 }
 
 impl<C: Connection> X11rbConnection<C> {
+    fn input_devices(&self, ty: xinput::DeviceType) -> Result<Vec<InputDevice>> {
+        if self
+            .conn
+            .extension_information(xinput::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Ok(vec![]);
+        }
+
+        // XI2 requests are only accepted once the client has announced which version it supports
+        self.conn.xinput_xi_query_version(2, 0)?.reply()?;
+        let reply = self
+            .conn
+            .xinput_xi_query_device(xinput::Device::ALL)?
+            .reply()?;
+
+        Ok(reply
+            .infos
+            .into_iter()
+            .filter(|info| info.type_ == ty)
+            .map(|info| InputDevice {
+                id: info.deviceid,
+                name: String::from_utf8_lossy(&info.name).into_owned(),
+            })
+            .collect())
+    }
+
+    fn change_device_prop(
+        &self,
+        device: u16,
+        name: &str,
+        ty: Xid,
+        data: xinput::XIChangePropertyAux,
+    ) -> Result<()> {
+        let prop = self.atom_id(name)?;
+        let n_items = match &data {
+            xinput::XIChangePropertyAux::Data8(d) => d.len(),
+            xinput::XIChangePropertyAux::Data16(d) => d.len(),
+            xinput::XIChangePropertyAux::Data32(d) => d.len(),
+            _ => 0,
+        } as u32;
+
+        self.conn
+            .xinput_xi_change_property(device, PropMode::REPLACE, prop, ty, n_items, &data)?
+            .check()?;
+
+        Ok(())
+    }
+
     fn grab_key_bindings(&self, keys: &[&KeyCode]) -> Result<()> {
         // We need to explicitly grab NumLock as an additional modifier and then drop it later on
         // when we are passing events through to the WindowManager as NumLock alters the modifier
@@ -589,21 +1026,26 @@ impl<C: Connection> X11rbConnection<C> {
         let modifiers = &[0, u16::from(ModMask::M2)];
         let mode = GrabMode::ASYNC;
         let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::BUTTON_MOTION;
-        let mask = u16::try_from(u32::from(mask)).unwrap();
+        let mask = u16::try_from(u32::from(mask))
+            .map_err(|e| X11rbError::InvalidPropertyData(format!("invalid event mask: {}", e)))?;
 
         for m in modifiers.iter() {
             for state in states.iter() {
-                let button = state.button().into();
+                let button = u8::from(state.button).into();
+                let state_mask = state
+                    .modifiers
+                    .iter()
+                    .fold(0, |acc, &k| acc | super::event::modifier_mask(k));
                 self.conn.grab_button(
-                    false,            // don't pass grabbed events through to the client
-                    self.root,        // the window to grab: in this case the root window
-                    mask,             // which events are reported to the client
-                    mode,             // don't lock pointer input while grabbing
-                    mode,             // don't lock keyboard input while grabbing
-                    x11rb::NONE,      // don't confine the cursor to a specific window
-                    x11rb::NONE,      // don't change the cursor type
-                    button,           // the button to grab
-                    state.mask() | m, // modifiers to grab
+                    false,          // don't pass grabbed events through to the client
+                    self.root,      // the window to grab: in this case the root window
+                    mask,           // which events are reported to the client
+                    mode,           // don't lock pointer input while grabbing
+                    mode,           // don't lock keyboard input while grabbing
+                    x11rb::NONE,    // don't confine the cursor to a specific window
+                    x11rb::NONE,    // don't change the cursor type
+                    button,         // the button to grab
+                    state_mask | m, // modifiers to grab
                 )?;
             }
         }
@@ -4,19 +4,31 @@ use crate::{
         bindings::{KeyCode, KeyCodeMask, KeyCodeValue, MouseEvent, MouseState},
         data_types::{Point, Region, WinType},
         helpers::spawn_for_output,
-        screen::Screen,
+        input::{InputDevice, LockState, PointerConfig, CAPS_LOCK_INDICATOR, NUM_LOCK_INDICATOR},
+        screen::{dpi_from_physical_size, parse_xft_dpi, Screen, DEFAULT_DPI},
         xconnection::{
-            Atom, ClientAttr, ClientConfig, ClientEventMask, ClientMessage, ClientMessageData,
-            ClientMessageKind, ConfigureEvent, ExposeEvent, MapState, PointerChange, Prop,
-            PropertyEvent, WindowAttributes, WindowClass, WindowState, WmHints, WmNormalHints,
-            XAtomQuerier, XEvent, Xid,
+            inner_border_regions, wait_until_readable, Atom, ClientAttr, ClientConfig,
+            ClientEventMask, ClientMessage, ClientMessageData, ClientMessageKind, ConfigureEvent,
+            ExposeEvent, MapState, PointerChange, Prop, PropertyEvent, WindowAttributes,
+            WindowClass, WindowState, WmHints, WmNormalHints, XAtomQuerier, XEvent, Xid,
         },
     },
     xcb::{Result, XErrorCode, XcbError, XcbGenericEvent},
 };
 use strum::*;
 
-use std::{collections::HashMap, convert::TryFrom, fmt, str::FromStr};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    os::unix::io::AsRawFd,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "keysyms")]
+use std::cell::RefCell;
 
 #[cfg(feature = "keysyms")]
 use crate::core::{bindings::KeyPress, xconnection::KeyPressParseAttempt};
@@ -29,19 +41,12 @@ pub type ReverseCodeMap = HashMap<(KeyCodeMask, KeyCodeValue), String>;
 const RANDR_MAJ: u32 = 1;
 const RANDR_MIN: u32 = 2;
 
-#[cfg(feature = "serde")]
-fn default_conn() -> xcb::Connection {
-    let (conn, _) = xcb::Connection::connect(None).expect("unable to connect using XCB");
-    conn
-}
-
 /**
  * Use `xmodmap -pke` to determine the user's current keymap to allow for mapping X KeySym values
  * to their string representation on the user's system.
  *
- * # Panics
- * This function will panic if it is unable to fetch keycodes using the xmodmap
- * binary on your system or if the output of `xmodmap -pke` is not valid
+ * Returns an error if `xmodmap` can not be run. Lines of output that are not in the expected
+ * format are skipped.
  */
 pub fn code_map_from_xmodmap() -> Result<ReverseCodeMap> {
     let output = match spawn_for_output("xmodmap -pke") {
@@ -52,12 +57,9 @@ pub fn code_map_from_xmodmap() -> Result<ReverseCodeMap> {
         .lines()
         .flat_map(|l| {
             let mut words = l.split_whitespace(); // keycode <code> = <names ...>
-            let key_code: u8 = match words.nth(1) {
-                Some(word) => match word.parse() {
-                    Ok(val) => val,
-                    Err(e) => panic!("{}", e),
-                },
-                None => panic!("unexpected output format from xmodmap -pke"),
+            let key_code: u8 = match words.nth(1).and_then(|word| word.parse().ok()) {
+                Some(code) => code,
+                None => return vec![].into_iter().flatten(),
             };
             vec![
                 words.nth(1).map(move |name| ((0, key_code), name.into())),
@@ -69,16 +71,61 @@ pub fn code_map_from_xmodmap() -> Result<ReverseCodeMap> {
         .collect::<HashMap<(u16, u8), String>>())
 }
 
+// The XInput bindings in xcb 0.9 do not build so input devices can not be listed or configured
+const NO_XINPUT: &str = "X Input device configuration is only supported by the x11rb backend";
+
 /// A connection to the X server using the XCB C API
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "ApiState")
+)]
 pub struct Api {
-    #[cfg_attr(feature = "serde", serde(skip, default = "default_conn"))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
     conn: xcb::Connection,
     root: Xid,
     randr_base: u8,
+    xkb_base: Option<u8>,
+    // XKB indicator indices for Caps Lock and Num Lock: used to read the state of the lock keys
+    // directly from IndicatorStateNotify events
+    lock_indicators: Cell<(Option<u8>, Option<u8>)>,
     atoms: HashMap<Atom, u32>,
     #[cfg(feature = "keysyms")]
-    code_map: ReverseCodeMap,
+    code_map: RefCell<ReverseCodeMap>,
+}
+
+// The serialized state of an Api: deserializing opens a new connection to the X server, failing
+// if we are unable to connect.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct ApiState {
+    root: Xid,
+    randr_base: u8,
+    xkb_base: Option<u8>,
+    lock_indicators: Cell<(Option<u8>, Option<u8>)>,
+    atoms: HashMap<Atom, u32>,
+    #[cfg(feature = "keysyms")]
+    code_map: RefCell<ReverseCodeMap>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ApiState> for Api {
+    type Error = XcbError;
+
+    fn try_from(state: ApiState) -> Result<Self> {
+        let (conn, _) = xcb::Connection::connect(None)?;
+
+        Ok(Self {
+            conn,
+            root: state.root,
+            randr_base: state.randr_base,
+            xkb_base: state.xkb_base,
+            lock_indicators: state.lock_indicators,
+            atoms: state.atoms,
+            #[cfg(feature = "keysyms")]
+            code_map: state.code_map,
+        })
+    }
 }
 
 impl fmt::Debug for Api {
@@ -86,6 +133,7 @@ impl fmt::Debug for Api {
         f.debug_struct("XCB Api")
             .field("root", &self.root)
             .field("randr_base", &self.randr_base)
+            .field("xkb_base", &self.xkb_base)
             .field("atoms", &self.atoms)
             .finish()
     }
@@ -120,9 +168,11 @@ impl Api {
             conn,
             root: 0,
             randr_base: 0,
+            xkb_base: None,
+            lock_indicators: Cell::new((None, None)),
             atoms: HashMap::new(),
             #[cfg(feature = "keysyms")]
-            code_map: code_map_from_xmodmap()?,
+            code_map: RefCell::new(code_map_from_xmodmap()?),
         };
         api.init()?;
 
@@ -140,6 +190,20 @@ impl Api {
             .ok_or_else(|| XcbError::Randr("unable to fetch extension data".into()))?
             .first_event();
 
+        // XKB is optional: without it we are unable to track the state of the lock keys
+        self.xkb_base = self
+            .conn
+            .get_extension_data(&mut xcb::xkb::id())
+            .filter(|data| data.present())
+            .map(|data| data.first_event());
+        if self.xkb_base.is_some() {
+            // XKB requests are only accepted once the client has announced which version it uses
+            let reply = xcb::xkb::use_extension(&self.conn, 1, 0).get_reply()?;
+            if !reply.supported() {
+                self.xkb_base = None;
+            }
+        }
+
         // Make sure we have new enough RandR so we can use 'get_screen_resources'
         // See https://github.com/sminez/penrose/issues/115 for more details
         let cookie = xcb::randr::query_version(&self.conn, RANDR_MAJ, RANDR_MIN);
@@ -158,6 +222,7 @@ impl Api {
                 Ok((atom, val))
             })
             .collect::<Result<HashMap<_, _>>>()?;
+        self.refresh_lock_indicators()?;
 
         Ok(())
     }
@@ -228,7 +293,12 @@ impl Api {
                     .collect::<Result<Vec<String>>>()?,
             ),
 
-            "CARDINAL" => Prop::Cardinal(r.value()[0]),
+            "CARDINAL" if r.value::<u32>().len() > 1 => {
+                Prop::CardinalList(r.value::<u32>().to_vec())
+            }
+            "CARDINAL" => Prop::Cardinal(*r.value().first().ok_or_else(|| {
+                XcbError::InvalidPropertyData(format!("empty CARDINAL property: {}", name))
+            })?),
 
             "STRING" => Prop::UTF8String(
                 String::from_utf8_lossy(r.value())
@@ -323,6 +393,62 @@ impl Api {
         Ok(())
     }
 
+    /// Grab the pointer using a crosshair cursor so that the next button press is reported to
+    /// penrose regardless of which window is under the cursor.
+    pub fn grab_pointer(&self) -> Result<()> {
+        // XC_crosshair from X11/cursorfont.h: the mask glyph is always the following one
+        const XC_CROSSHAIR: u16 = 34;
+
+        let font = self.conn.generate_id();
+        xcb::open_font_checked(&self.conn, font, "cursor").request_check()?;
+        let cursor = self.conn.generate_id();
+        xcb::create_glyph_cursor_checked(
+            &self.conn,
+            cursor,
+            font,
+            font,
+            XC_CROSSHAIR,
+            XC_CROSSHAIR + 1,
+            0,
+            0,
+            0,
+            0xffff,
+            0xffff,
+            0xffff,
+        )
+        .request_check()?;
+
+        let reply = xcb::grab_pointer(
+            &self.conn,
+            false,                               // don't pass events through to clients
+            self.root,                           // report events relative to the root window
+            xcb::EVENT_MASK_BUTTON_PRESS as u16, // only button presses are needed
+            xcb::GRAB_MODE_ASYNC as u8,          // don't freeze pointer input
+            xcb::GRAB_MODE_ASYNC as u8,          // don't freeze keyboard input
+            xcb::NONE,                           // don't confine the cursor
+            cursor,                              // show the crosshair while grabbed
+            xcb::CURRENT_TIME,
+        )
+        .get_reply();
+
+        // The server holds its own reference to the cursor for the duration of the grab
+        xcb::free_cursor(&self.conn, cursor);
+        xcb::close_font(&self.conn, font);
+
+        if reply?.status() != xcb::GRAB_STATUS_SUCCESS as u8 {
+            return Err(XcbError::QueryFailed("unable to grab the pointer"));
+        }
+
+        Ok(())
+    }
+
+    /// Release pointer input
+    pub fn ungrab_pointer(&self) -> Result<()> {
+        xcb::ungrab_pointer_checked(&self.conn, xcb::CURRENT_TIME).request_check()?;
+
+        Ok(())
+    }
+
     /// Poll for the next event from the underlying [XCB Connection][::xcb::Connection],
     /// returning it as an [XKeySym] if it was a user keypress, or an [XEvent] if not.
     ///
@@ -363,7 +489,7 @@ impl Api {
         event: XcbGenericEvent,
     ) -> Result<Option<KeyPressParseAttempt>> {
         if let Ok(k) = KeyCode::try_from(&event) {
-            if let Some(s) = self.code_map.get(&(k.mask, k.code)) {
+            if let Some(s) = self.code_map.borrow().get(&(k.mask, k.code)) {
                 if let Ok(k) = KeyPress::try_from(XKeySym::from_str(s)?) {
                     return Ok(Some(KeyPressParseAttempt::KeyPress(k)));
                 }
@@ -388,6 +514,20 @@ impl Api {
             return Ok(Some(XEvent::RandrNotify));
         } else if etype == self.randr_base + xcb::randr::SCREEN_CHANGE_NOTIFY {
             return Ok(Some(XEvent::ScreenChange));
+        } else if matches!(self.xkb_base, Some(base) if etype == base) {
+            // All XKB events share a single event code and are distinguished by their xkb_type
+            let e: &xcb::xkb::IndicatorStateNotifyEvent = unsafe { xcb::cast_event(&event) };
+            return Ok(match e.xkb_type() {
+                xcb::xkb::INDICATOR_STATE_NOTIFY => {
+                    Some(XEvent::LockStateChanged(self.lock_state_from(e.state())))
+                }
+                // Device hotplug is only reported through the X Input extension which is not
+                // available here: a new core keyboard may still change the keymap
+                xcb::xkb::NEW_KEYBOARD_NOTIFY | xcb::xkb::MAP_NOTIFY => {
+                    Some(XEvent::KeyboardMappingChanged)
+                }
+                _ => None,
+            });
         }
 
         Ok(match etype {
@@ -405,6 +545,13 @@ impl Api {
                 KeyCode::try_from(event)?.ignoring_modifier(numlock),
             )),
 
+            xcb::KEY_RELEASE => {
+                let e: &xcb::KeyReleaseEvent = unsafe { xcb::cast_event(&event) };
+                Some(XEvent::KeyRelease(
+                    KeyCode::from(e).ignoring_modifier(numlock),
+                ))
+            }
+
             xcb::MAP_REQUEST => {
                 let e: &xcb::MapRequestEvent = unsafe { xcb::cast_event(&event) };
                 let id = e.window();
@@ -486,7 +633,13 @@ impl Api {
 
             xcb::UNMAP_NOTIFY => {
                 let e: &xcb::UnmapNotifyEvent = unsafe { xcb::cast_event(&event) };
-                Some(XEvent::UnmapNotify(e.window()))
+                // Clients select StructureNotify so we see each unmap twice: only pass on the
+                // event reported to the parent window.
+                if e.event() == e.window() {
+                    None
+                } else {
+                    Some(XEvent::UnmapNotify(e.window()))
+                }
             }
 
             xcb::CLIENT_MESSAGE => {
@@ -528,6 +681,16 @@ impl Api {
                     })
             }
 
+            xcb::MAPPING_NOTIFY => {
+                let e: &xcb::MappingNotifyEvent = unsafe { xcb::cast_event(&event) };
+                match e.request() as u32 {
+                    xcb::MAPPING_KEYBOARD | xcb::MAPPING_MODIFIER => {
+                        Some(XEvent::KeyboardMappingChanged)
+                    }
+                    _ => None, // pointer button mappings don't affect our grabs
+                }
+            }
+
             0 => {
                 let e: &xcb::GenericError = unsafe { xcb::cast_event(&event) };
                 return Err(XcbError::from(e));
@@ -566,11 +729,10 @@ impl Api {
 
     /// Fetch the id value of a known [Atom] variant.
     ///
-    /// This operation is expected to always succeed as known atoms should
-    /// either be interned on init of the implementing struct or statically
-    /// assigned a value in the implementation.
-    pub fn known_atom(&self, atom: Atom) -> u32 {
-        *self.atoms.get(&atom).unwrap()
+    /// Known atoms are interned on init so this should only need to ask the X server for the
+    /// atom if it is called before then.
+    pub fn known_atom(&self, atom: Atom) -> Result<u32> {
+        self.atom(atom.as_ref())
     }
 
     /// Delete a known property from a window
@@ -603,6 +765,8 @@ impl Api {
 
             Prop::Cardinal(val) => (xcb::xproto::ATOM_CARDINAL, vec![val]),
 
+            Prop::CardinalList(vals) => (xcb::xproto::ATOM_CARDINAL, vals),
+
             Prop::UTF8String(strs) => {
                 return Ok(xcb::change_property_checked(
                     &self.conn,
@@ -632,7 +796,7 @@ impl Api {
     /// Set the target client's WM_STATE
     pub fn set_client_state(&self, id: Xid, wm_state: WindowState) -> Result<()> {
         let mode = xcb::PROP_MODE_REPLACE as u8;
-        let a = self.known_atom(Atom::WmState);
+        let a = self.known_atom(Atom::WmState)?;
         let state = match wm_state {
             WindowState::Withdrawn => 0,
             WindowState::Normal => 1,
@@ -737,6 +901,20 @@ impl Api {
         Ok(id)
     }
 
+    /// Create a new unmanaged, input only window that is sent pointer enter and leave events
+    /// along with button presses and releases
+    pub fn create_input_window(&self, r: Region) -> Result<Xid> {
+        let id = self.create_window(WinType::InputOnly, r, false)?;
+        let mask = xcb::EVENT_MASK_ENTER_WINDOW
+            | xcb::EVENT_MASK_LEAVE_WINDOW
+            | xcb::EVENT_MASK_BUTTON_PRESS
+            | xcb::EVENT_MASK_BUTTON_RELEASE;
+        xcb::change_window_attributes_checked(&self.conn, id, &[(xcb::CW_EVENT_MASK, mask)])
+            .request_check()?;
+
+        Ok(id)
+    }
+
     /// Apply a set of config options to a window
     pub fn configure_client(&self, id: Xid, conf: &[ClientConfig]) -> Result<()> {
         let data: Vec<(u16, u32)> = conf.iter().flat_map::<Vec<_>, _>(|c| c.into()).collect();
@@ -807,6 +985,129 @@ impl Api {
         Ok(xcb::change_window_attributes_checked(&self.conn, id, &data).request_check()?)
     }
 
+    /// Set the same attributes on each of the given windows, checking for errors only once all
+    /// of the requests have been sent
+    pub fn set_clients_attributes(&self, ids: &[Xid], attrs: &[ClientAttr]) -> Result<()> {
+        let data: Vec<(u32, u32)> = attrs.iter().flat_map::<Vec<_>, _>(|c| c.into()).collect();
+        let cookies: Vec<_> = ids
+            .iter()
+            .map(|&id| xcb::change_window_attributes_checked(&self.conn, id, &data))
+            .collect();
+
+        for cookie in cookies {
+            cookie.request_check()?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the background color of the target window and repaint it
+    pub fn set_window_background(&self, id: Xid, color: u32) -> Result<()> {
+        let data = [(xcb::CW_BACK_PIXEL, color)];
+        xcb::change_window_attributes_checked(&self.conn, id, &data).request_check()?;
+        xcb::clear_area(&self.conn, false, id, 0, 0, 0, 0);
+        self.flush();
+
+        Ok(())
+    }
+
+    /// The current width and height of the root window.
+    ///
+    /// Unlike the size given in the connection setup data, this follows the root window being
+    /// resized by RandR when monitors are added or removed.
+    pub fn root_size(&self) -> Result<(u16, u16)> {
+        let geom = xcb::get_geometry(&self.conn, self.root).get_reply()?;
+
+        Ok((geom.width(), geom.height()))
+    }
+
+    /// Create a new pixmap that has the given size and the same depth as the root window
+    pub fn create_root_pixmap(&self, w: u16, h: u16) -> Result<Xid> {
+        let screen = self.screen(0)?;
+        let pixmap = self.conn.generate_id();
+        xcb::create_pixmap_checked(&self.conn, screen.root_depth(), pixmap, self.root, w, h)
+            .request_check()?;
+
+        Ok(pixmap)
+    }
+
+    /// Free a pixmap that is no longer needed
+    pub fn free_pixmap(&self, pixmap: Xid) {
+        xcb::free_pixmap(&self.conn, pixmap);
+    }
+
+    /// Set 'pixmap' as the background of the root window and repaint it.
+    ///
+    /// The pixmap is also advertised through the `_XROOTPMAP_ID` and `ESETROOT_PMAP_ID`
+    /// properties so that compositors and pseudo-transparent programs are able to find it.
+    pub fn set_root_pixmap(&self, pixmap: Xid) -> Result<()> {
+        let data = [(xcb::CW_BACK_PIXMAP, pixmap)];
+        xcb::change_window_attributes_checked(&self.conn, self.root, &data).request_check()?;
+
+        let mode = xcb::PROP_MODE_REPLACE as u8;
+        for name in &["_XROOTPMAP_ID", "ESETROOT_PMAP_ID"] {
+            let a = self.atom(name)?;
+            let ty = xcb::xproto::ATOM_PIXMAP;
+            xcb::change_property_checked(&self.conn, mode, self.root, a, ty, 32, &[pixmap])
+                .request_check()?;
+        }
+
+        xcb::clear_area(&self.conn, false, self.root, 0, 0, 0, 0);
+        self.flush();
+
+        Ok(())
+    }
+
+    /// Draw a two-tone border for the target window by setting its border pixmap.
+    ///
+    /// The pixmap is sized to match the current geometry of the window so this needs to be called
+    /// again whenever the window is resized.
+    pub fn set_client_border_colors(
+        &self,
+        id: Xid,
+        outer: u32,
+        inner: u32,
+        inner_px: u32,
+    ) -> Result<()> {
+        let geom = xcb::get_geometry(&self.conn, id).get_reply()?;
+        let (w, h, b) = (
+            geom.width() as u32,
+            geom.height() as u32,
+            geom.border_width() as u32,
+        );
+        let rects: Vec<xcb::Rectangle> = inner_border_regions(w, h, b, inner_px)
+            .into_iter()
+            .map(|r| xcb::Rectangle::new(r.x as i16, r.y as i16, r.w as u16, r.h as u16))
+            .collect();
+
+        if b == 0 || rects.is_empty() {
+            let data = [(xcb::CW_BORDER_PIXEL, outer)];
+            return Ok(
+                xcb::change_window_attributes_checked(&self.conn, id, &data).request_check()?
+            );
+        }
+
+        let pixmap = self.conn.generate_id();
+        let gc = self.conn.generate_id();
+        let (pw, ph) = ((w + 2 * b) as u16, (h + 2 * b) as u16);
+
+        xcb::create_pixmap(&self.conn, geom.depth(), pixmap, id, pw, ph);
+        xcb::create_gc(&self.conn, gc, pixmap, &[(xcb::GC_FOREGROUND, outer)]);
+        xcb::poly_fill_rectangle(&self.conn, pixmap, gc, &[xcb::Rectangle::new(0, 0, pw, ph)]);
+        xcb::change_gc(&self.conn, gc, &[(xcb::GC_FOREGROUND, inner)]);
+        xcb::poly_fill_rectangle(&self.conn, pixmap, gc, &rects);
+
+        let data = [(xcb::CW_BORDER_PIXMAP, pixmap)];
+        let res = xcb::change_window_attributes_checked(&self.conn, id, &data).request_check();
+
+        // The server keeps its own reference to the pixmap once it is set as the border
+        xcb::free_gc(&self.conn, gc);
+        xcb::free_pixmap(&self.conn, pixmap);
+        self.flush();
+
+        Ok(res?)
+    }
+
     /// Find the current size and position of the target window
     pub fn client_geometry(&self, id: Xid) -> Result<Region> {
         let res = xcb::get_geometry(&self.conn, id).get_reply()?;
@@ -825,6 +1126,7 @@ impl Api {
         // xcb docs: https://www.mankier.com/3/xcb_randr_get_screen_resources
         let check_win = self.check_window();
         let resources = xcb::randr::get_screen_resources(&self.conn, check_win);
+        let fallback_dpi = self.xft_dpi().unwrap_or(DEFAULT_DPI);
 
         // xcb docs: https://www.mankier.com/3/xcb_randr_get_crtc_info
         let screens = resources
@@ -834,13 +1136,21 @@ impl Api {
             .flat_map(|c| xcb::randr::get_crtc_info(&self.conn, *c, 0).get_reply())
             .enumerate()
             .map(|(i, r)| {
-                let region = Region::new(
-                    r.x() as u32,
-                    r.y() as u32,
-                    r.width() as u32,
-                    r.height() as u32,
-                );
-                Screen::new(region, i)
+                let (w, h) = (r.width() as u32, r.height() as u32);
+                let region = Region::new(r.x() as u32, r.y() as u32, w, h);
+                // xcb docs: https://www.mankier.com/3/xcb_randr_get_output_info
+                let dpi = r
+                    .outputs()
+                    .first()
+                    .and_then(|o| {
+                        xcb::randr::get_output_info(&self.conn, *o, 0)
+                            .get_reply()
+                            .ok()
+                    })
+                    .and_then(|o| dpi_from_physical_size((w, h), (o.mm_width(), o.mm_height())))
+                    .unwrap_or(fallback_dpi);
+
+                Screen::new(region, i).with_dpi(dpi)
             })
             .filter(|s| {
                 let (_, _, w, _) = s.region(false).values();
@@ -852,6 +1162,14 @@ impl Api {
         Ok(screens)
     }
 
+    // The DPI set by the user through Xft.dpi in their X resources (if any)
+    fn xft_dpi(&self) -> Option<u32> {
+        match self.get_prop(self.root, "RESOURCE_MANAGER") {
+            Ok(Prop::UTF8String(strs)) => parse_xft_dpi(&strs.join("\n")),
+            _ => None,
+        }
+    }
+
     /// Query the randr API for current outputs and return the size of each screen
     pub fn screen_sizes(&self) -> Result<Vec<Region>> {
         self.current_screens()
@@ -872,6 +1190,13 @@ impl Api {
             .map(|reply| Point::new(reply.root_x() as u32, reply.root_y() as u32))?)
     }
 
+    /// The modifier keys that are currently held down
+    pub fn modifier_state(&self) -> Result<u16> {
+        Ok(xcb::query_pointer(&self.conn, self.root)
+            .get_reply()
+            .map(|reply| reply.mask())?)
+    }
+
     /// Flush pending actions to the X event loop
     pub fn flush(&self) -> bool {
         self.conn.flush()
@@ -982,6 +1307,126 @@ impl Api {
         Ok(())
     }
 
+    /// Request notifications when the keyboard indicators or keyboard mapping change (if XKB is
+    /// available)
+    pub fn set_xkb_notify_mask(&self) -> Result<()> {
+        if self.xkb_base.is_some() {
+            let events = (xcb::xkb::EVENT_TYPE_INDICATOR_STATE_NOTIFY
+                | xcb::xkb::EVENT_TYPE_NEW_KEYBOARD_NOTIFY
+                | xcb::xkb::EVENT_TYPE_MAP_NOTIFY) as u16;
+            let map_parts = (xcb::xkb::MAP_PART_KEY_SYMS | xcb::xkb::MAP_PART_MODIFIER_MAP) as u16;
+            xcb::xkb::select_events_checked(
+                &self.conn,
+                xcb::xkb::ID_USE_CORE_KBD as u16,
+                events,    // affect_which
+                0,         // clear
+                events,    // select_all
+                map_parts, // affect_map
+                map_parts, // map
+                None,
+            )
+            .request_check()?;
+            self.flush();
+        }
+
+        Ok(())
+    }
+
+    /// The current state of the Caps Lock and Num Lock indicators of the core keyboard.
+    ///
+    /// If XKB is not available then both are reported as inactive.
+    pub fn lock_state(&self) -> Result<LockState> {
+        if self.xkb_base.is_none() {
+            return Ok(LockState::default());
+        }
+
+        let indicator_on = |name: &str| -> Result<bool> {
+            let reply = self.named_indicator(name)?;
+            Ok(reply.found() && reply.on())
+        };
+
+        Ok(LockState {
+            caps_lock: indicator_on(CAPS_LOCK_INDICATOR)?,
+            num_lock: indicator_on(NUM_LOCK_INDICATOR)?,
+        })
+    }
+
+    fn named_indicator(&self, name: &str) -> Result<xcb::xkb::GetNamedIndicatorReply> {
+        Ok(xcb::xkb::get_named_indicator(
+            &self.conn,
+            xcb::xkb::ID_USE_CORE_KBD as u16,
+            xcb::xkb::LED_CLASS_DFLT_XI_CLASS as u16,
+            xcb::xkb::ID_DFLT_XI_ID as u16,
+            self.atom(name)?,
+        )
+        .get_reply()?)
+    }
+
+    // Indicator indices are part of the keymap so they need to be re-read when it changes
+    fn refresh_lock_indicators(&self) -> Result<()> {
+        if self.xkb_base.is_none() {
+            return Ok(());
+        }
+
+        let index_of = |name: &str| -> Result<Option<u8>> {
+            let reply = self.named_indicator(name)?;
+            Ok(if reply.found() {
+                Some(reply.ndx())
+            } else {
+                None
+            })
+        };
+        self.lock_indicators.set((
+            index_of(CAPS_LOCK_INDICATOR)?,
+            index_of(NUM_LOCK_INDICATOR)?,
+        ));
+
+        Ok(())
+    }
+
+    // The lock state given the bitmask of active indicators from an IndicatorStateNotify event
+    fn lock_state_from(&self, indicators: u32) -> LockState {
+        let (caps, num) = self.lock_indicators.get();
+        let on = |ndx: Option<u8>| matches!(ndx, Some(i) if indicators & (1 << i) != 0);
+
+        LockState {
+            caps_lock: on(caps),
+            num_lock: on(num),
+        }
+    }
+
+    /// Re-read the [ReverseCodeMap] used for parsing key presses and the XKB lock indicators
+    /// after the keyboard mapping has changed.
+    pub fn refresh_code_map(&self) -> Result<()> {
+        self.refresh_lock_indicators()?;
+
+        #[cfg(feature = "keysyms")]
+        {
+            *self.code_map.borrow_mut() = code_map_from_xmodmap()?;
+        }
+
+        Ok(())
+    }
+
+    /// Listing pointer devices requires the X Input extension: this always returns an error.
+    pub fn pointer_devices(&self) -> Result<Vec<InputDevice>> {
+        Err(XcbError::Raw(NO_XINPUT.into()))
+    }
+
+    /// Listing keyboard devices requires the X Input extension: this always returns an error.
+    pub fn keyboard_devices(&self) -> Result<Vec<InputDevice>> {
+        Err(XcbError::Raw(NO_XINPUT.into()))
+    }
+
+    /// Configuring pointer devices requires the X Input extension: this always returns an error.
+    pub fn configure_pointer_device(
+        &self,
+        _device: &InputDevice,
+        _conf: &PointerConfig,
+    ) -> Result<()> {
+        Err(XcbError::Raw(NO_XINPUT.into()))
+    }
+
     /// Drop all active intercepts for key combinations
     pub fn ungrab_keys(&self) -> Result<()> {
         Ok(xcb::ungrab_key_checked(
@@ -1037,6 +1482,34 @@ impl Api {
         }
     }
 
+    /// Wait for up to 'timeout' for the next event from the X event loop.
+    ///
+    /// Rather than repeatedly polling, this sleeps until there is data to read from the X server
+    /// connection, returning None if no event that penrose handles arrived in time.
+    pub fn wait_for_event_timeout(
+        &self,
+        timeout: Duration,
+    ) -> crate::core::xconnection::Result<Option<XEvent>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            // Events may already have been read from the socket and queued by xcb
+            while let Some(event) = self.conn.poll_for_event() {
+                if let Some(e) = self.generic_xcb_to_xevent(event)? {
+                    return Ok(Some(e));
+                }
+            }
+            self.conn.has_error().map_err(XcbError::from)?;
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_millis(0)
+                || !wait_until_readable(self.conn.as_raw_fd(), remaining)?
+            {
+                return Ok(None);
+            }
+        }
+    }
+
     /// Move the cursor to the given (x, y) position inside the specified window.
     pub fn warp_cursor(&self, id: Xid, x: usize, y: usize) -> Result<()> {
         Ok(
@@ -196,10 +196,10 @@ impl From<&ClientConfig> for Vec<(u16, u32)> {
 
 impl From<&ClientAttr> for Vec<(u32, u32)> {
     fn from(w: &ClientAttr) -> Vec<(u32, u32)> {
-        let client_event_mask = xcb::EVENT_MASK_ENTER_WINDOW
-            | xcb::EVENT_MASK_LEAVE_WINDOW
-            | xcb::EVENT_MASK_PROPERTY_CHANGE
-            | xcb::EVENT_MASK_STRUCTURE_NOTIFY;
+        let no_crossing_event_mask =
+            xcb::EVENT_MASK_PROPERTY_CHANGE | xcb::EVENT_MASK_STRUCTURE_NOTIFY;
+        let client_event_mask =
+            xcb::EVENT_MASK_ENTER_WINDOW | xcb::EVENT_MASK_LEAVE_WINDOW | no_crossing_event_mask;
 
         let root_event_mask = xcb::EVENT_MASK_PROPERTY_CHANGE
             | xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT
@@ -208,8 +208,20 @@ impl From<&ClientAttr> for Vec<(u32, u32)> {
 
         match w {
             ClientAttr::BorderColor(c) => vec![(xcb::CW_BORDER_PIXEL, *c)],
+            ClientAttr::BackgroundColor(c) => vec![(xcb::CW_BACK_PIXEL, *c)],
             ClientAttr::ClientEventMask => vec![(xcb::CW_EVENT_MASK, client_event_mask)],
+            ClientAttr::ClientEventMaskNoCrossing => {
+                vec![(xcb::CW_EVENT_MASK, no_crossing_event_mask)]
+            }
             ClientAttr::RootEventMask => vec![(xcb::CW_EVENT_MASK, root_event_mask)],
+            ClientAttr::RootEventMaskWithClicks => vec![(
+                xcb::CW_EVENT_MASK,
+                root_event_mask | xcb::EVENT_MASK_BUTTON_PRESS | xcb::EVENT_MASK_BUTTON_RELEASE,
+            )],
+            ClientAttr::ButtonEventMask => vec![(
+                xcb::CW_EVENT_MASK,
+                xcb::EVENT_MASK_BUTTON_PRESS | xcb::EVENT_MASK_BUTTON_RELEASE,
+            )],
         }
     }
 }
@@ -8,15 +8,17 @@
 use crate::{
     core::{
         data_types::{Region, WinType},
-        xconnection::{Prop, WindowState, XClientHandler, Xid},
+        xconnection::{ClientConfig, Prop, WindowState, XClientHandler, Xid},
+    },
+    draw::{
+        Color, Draw, DrawContext, DrawError, Result, TextExtentCache, Wallpaper, WallpaperMode,
     },
-    draw::{Color, Draw, DrawContext, DrawError, Result},
     xcb::{Api, XcbError},
 };
 
 use pangocairo::functions::{create_layout, show_layout};
 
-use std::collections::HashMap;
+use std::{collections::HashMap, fs::File, path::Path};
 
 #[cfg(feature = "keysyms")]
 use crate::core::xconnection::{KeyPressParseAttempt, XKeyboardHandler};
@@ -25,12 +27,21 @@ fn pango_layout(ctx: &cairo::Context) -> Result<pango::Layout> {
     Ok(create_layout(ctx).ok_or_else(|| XcbError::Pango("unable to create layout".into()))?)
 }
 
+fn load_png(path: &Path) -> Result<cairo::ImageSurface> {
+    let invalid = |e: String| DrawError::InvalidWallpaper(path.to_path_buf(), e);
+    let mut f = File::open(path).map_err(|e| invalid(e.to_string()))?;
+
+    cairo::ImageSurface::create_from_png(&mut f).map_err(|e| invalid(e.to_string()))
+}
+
 /// An XCB based [Draw] implementation backed by pango and cairo
 #[derive(Debug)]
 pub struct XcbDraw {
     api: Api,
     fonts: HashMap<String, pango::FontDescription>,
     surfaces: HashMap<Xid, cairo::XCBSurface>,
+    text_extents: TextExtentCache,
+    wallpaper: Option<Xid>, // the pixmap currently set as the root window background
 }
 
 crate::__xcb_impl_xclienthandler!(XcbDraw);
@@ -45,6 +56,8 @@ impl XcbDraw {
             api: Api::new()?,
             fonts: HashMap::new(),
             surfaces: HashMap::new(),
+            text_extents: TextExtentCache::new(),
+            wallpaper: None,
         })
     }
 
@@ -63,6 +76,67 @@ impl XcbDraw {
     pub fn api_mut(&mut self) -> &mut Api {
         &mut self.api
     }
+
+    // Paint each wallpaper into its region of 'pixmap', which is 'w' x 'h' and covers the root
+    // window, leaving anything not covered by a wallpaper black.
+    fn paint_wallpapers(
+        &self,
+        pixmap: Xid,
+        w: u16,
+        h: u16,
+        wallpapers: &[(Region, Wallpaper)],
+        images: Vec<cairo::ImageSurface>,
+    ) -> Result<()> {
+        let xcb_screen = self.api.screen(0)?;
+        let root_visual = xcb_screen.root_visual();
+        let mut visualtype = None;
+        for depth in xcb_screen.allowed_depths() {
+            if let Some(v) = depth.visuals().find(|v| v.visual_id() == root_visual) {
+                visualtype = Some(v);
+                break;
+            }
+        }
+        let mut visualtype = visualtype.ok_or(XcbError::QueryFailed("root visual type"))?;
+
+        let surface = unsafe {
+            let conn_ptr = self.api.conn().get_raw_conn() as *mut cairo_sys::xcb_connection_t;
+
+            cairo::XCBSurface::create(
+                &cairo::XCBConnection::from_raw_none(conn_ptr),
+                &cairo::XCBDrawable(pixmap),
+                &cairo::XCBVisualType::from_raw_none(
+                    &mut visualtype.base as *mut xcb::ffi::xcb_visualtype_t
+                        as *mut cairo_sys::xcb_visualtype_t,
+                ),
+                w as i32,
+                h as i32,
+            )?
+        };
+
+        let ctx = cairo::Context::new(&surface);
+        ctx.set_source_rgb(0.0, 0.0, 0.0);
+        ctx.paint();
+
+        for ((r, wallpaper), img) in wallpapers.iter().zip(images) {
+            let (iw, ih) = (img.get_width() as u32, img.get_height() as u32);
+            let (x, y, scale_x, scale_y) = wallpaper.mode.placement(iw, ih, *r);
+
+            ctx.save();
+            ctx.rectangle(r.x as f64, r.y as f64, r.w as f64, r.h as f64);
+            ctx.clip();
+            ctx.translate(x, y);
+            ctx.scale(scale_x, scale_y);
+            ctx.set_source_surface(&img, 0.0, 0.0);
+            if wallpaper.mode == WallpaperMode::Tile {
+                ctx.get_source().set_extend(cairo::Extend::Repeat);
+            }
+            ctx.paint();
+            ctx.restore();
+        }
+        surface.flush();
+
+        Ok(())
+    }
 }
 
 impl Draw for XcbDraw {
@@ -96,10 +170,32 @@ impl Draw for XcbDraw {
         Ok(id)
     }
 
+    fn position_window(&mut self, id: Xid, r: Region) -> Result<()> {
+        let (_, _, w, h) = r.values();
+        let surface = self
+            .surfaces
+            .get(&id)
+            .ok_or(XcbError::UnintialisedSurface(id))?;
+        surface.set_size(w as i32, h as i32)?;
+        self.api
+            .configure_client(id, &[ClientConfig::Position(r), ClientConfig::StackAbove])?;
+
+        Ok(())
+    }
+
     fn screen_sizes(&self) -> Result<Vec<Region>> {
         Ok(self.api.screen_sizes()?)
     }
 
+    fn screen_dpis(&self) -> Result<Vec<u32>> {
+        Ok(self
+            .api
+            .current_screens()?
+            .iter()
+            .map(|s| s.dpi())
+            .collect())
+    }
+
     fn register_font(&mut self, font_name: &str) {
         self.fonts.insert(
             font_name.into(),
@@ -117,7 +213,10 @@ impl Draw for XcbDraw {
         Ok(Self::Ctx {
             ctx,
             font: None,
+            font_key: None,
+            font_scale: 1.0,
             fonts: self.fonts.clone(),
+            text_extents: self.text_extents.clone(),
         })
     }
 
@@ -147,7 +246,10 @@ impl Draw for XcbDraw {
         Ok(Self::Ctx {
             ctx,
             font: None,
+            font_key: None,
+            font_scale: 1.0,
             fonts: self.fonts.clone(),
+            text_extents: self.text_extents.clone(),
         })
     }
 
@@ -159,6 +261,33 @@ impl Draw for XcbDraw {
         self.api.flush();
         Ok(())
     }
+
+    fn set_wallpapers(&mut self, wallpapers: &[(Region, Wallpaper)]) -> Result<()> {
+        // Load everything up front so that a bad path leaves the current wallpaper in place
+        let images = wallpapers
+            .iter()
+            .map(|(_, w)| load_png(&w.path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let (w, h) = self.api.root_size()?;
+        let pixmap = self.api.create_root_pixmap(w, h)?;
+        // The new pixmap is only kept once it has become the root window background
+        let res = self
+            .paint_wallpapers(pixmap, w, h, wallpapers, images)
+            .and_then(|_| Ok(self.api.set_root_pixmap(pixmap)?));
+        if let Err(e) = res {
+            self.api.free_pixmap(pixmap);
+            return Err(e);
+        }
+
+        // The pixmap is left alive while it is in use so that compositors can read it using
+        // _XROOTPMAP_ID, leaving only the one it is replacing to be freed
+        if let Some(old) = self.wallpaper.replace(pixmap) {
+            self.api.free_pixmap(old);
+        }
+
+        Ok(())
+    }
 }
 
 /// An XCB based drawing context using pango and cairo
@@ -166,7 +295,32 @@ impl Draw for XcbDraw {
 pub struct XcbDrawContext {
     ctx: cairo::Context,
     font: Option<pango::FontDescription>,
+    font_key: Option<(String, i32)>, // name and scaled size of the current font
+    font_scale: f64,
     fonts: HashMap<String, pango::FontDescription>,
+    text_extents: TextExtentCache,
+}
+
+impl XcbDrawContext {
+    // Pango shapes the text and resolves its bidi runs, taking the base direction of the
+    // paragraph from its first strongly directional character
+    fn text_layout(&self, s: &str) -> Result<pango::Layout> {
+        let layout = pango_layout(&self.ctx)?;
+        if let Some(ref font) = self.font {
+            layout.set_font_description(Some(font));
+        }
+        layout.set_auto_dir(true);
+        layout.set_text(s);
+
+        Ok(layout)
+    }
+
+    fn measure_text(&self, s: &str) -> Result<(f64, f64)> {
+        let layout = self.text_layout(s)?;
+        let (w, h) = layout.get_pixel_size();
+
+        Ok((w as f64, h as f64))
+    }
 }
 
 impl DrawContext for XcbDrawContext {
@@ -176,12 +330,22 @@ impl DrawContext for XcbDrawContext {
             .get_mut(font_name)
             .ok_or_else(|| DrawError::UnknownFont(font_name.into()))?
             .clone();
-        font.set_size(point_size * pango::SCALE);
+        let size = (point_size as f64 * self.font_scale * pango::SCALE as f64).round() as i32;
+        font.set_size(size);
         self.font = Some(font);
+        self.font_key = Some((font_name.into(), size));
 
         Ok(())
     }
 
+    fn set_font_scale(&mut self, factor: f64) {
+        self.font_scale = factor;
+    }
+
+    fn font_scale(&self) -> f64 {
+        self.font_scale
+    }
+
     fn color(&mut self, color: &Color) {
         let (r, g, b, a) = color.rgba();
         self.ctx.set_source_rgba(r, g, b, a);
@@ -215,13 +379,18 @@ impl DrawContext for XcbDrawContext {
         self.ctx.fill();
     }
 
-    fn text(&self, txt: &str, h_offset: f64, padding: (f64, f64)) -> Result<(f64, f64)> {
-        let layout = pango_layout(&self.ctx)?;
-        if let Some(ref font) = self.font {
-            layout.set_font_description(Some(font));
+    fn polygon(&self, points: &[(f64, f64)]) {
+        let mut points = points.iter();
+        if let Some(&(x, y)) = points.next() {
+            self.ctx.move_to(x, y);
+            points.for_each(|&(x, y)| self.ctx.line_to(x, y));
+            self.ctx.close_path();
+            self.ctx.fill();
         }
+    }
 
-        layout.set_text(txt);
+    fn text(&self, txt: &str, h_offset: f64, padding: (f64, f64)) -> Result<(f64, f64)> {
+        let layout = self.text_layout(txt)?;
         layout.set_ellipsize(pango::EllipsizeMode::End);
 
         let (w, h) = layout.get_pixel_size();
@@ -237,14 +406,12 @@ impl DrawContext for XcbDrawContext {
     }
 
     fn text_extent(&self, s: &str) -> Result<(f64, f64)> {
-        let layout = pango_layout(&self.ctx)?;
-        if let Some(ref font) = self.font {
-            layout.set_font_description(Some(font));
+        match self.font_key {
+            Some((ref name, size)) => self
+                .text_extents
+                .get_or_measure(name, size, s, || self.measure_text(s)),
+            None => self.measure_text(s),
         }
-        layout.set_text(&s);
-        let (w, h) = layout.get_pixel_size();
-
-        Ok((w as f64, h as f64))
     }
 
     fn flush(&self) {
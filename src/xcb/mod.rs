@@ -259,6 +259,10 @@ macro_rules! __xcb_impl_xstate {
                 Ok(self.api.cursor_position()?)
             }
 
+            fn modifier_state(&self) -> $crate::core::xconnection::Result<u16> {
+                Ok(self.api.modifier_state()?)
+            }
+
             fn warp_cursor(&self, win_id: Option<Xid>, screen: &Screen) -> $crate::core::xconnection::Result<()> {
                 let (x, y, id) = match win_id {
                     Some(id) => {
@@ -302,6 +306,17 @@ macro_rules! __xcb_impl_xeventhandler {
                 Ok(self.api.wait_for_event()?)
             }
 
+            fn poll_for_event(&self) -> $crate::core::xconnection::Result<Option<XEvent>> {
+                Ok(self.api.poll_for_event()?)
+            }
+
+            fn wait_for_event_timeout(
+                &self,
+                timeout: std::time::Duration,
+            ) -> $crate::core::xconnection::Result<Option<XEvent>> {
+                self.api.wait_for_event_timeout(timeout)
+            }
+
             fn send_client_event(&self, msg: ClientMessage) -> $crate::core::xconnection::Result<()> {
                 Ok(self.api.send_client_event(msg)?)
             }
@@ -337,6 +352,10 @@ macro_rules! __xcb_impl_xclienthandler {
             fn kill_client(&self, id: Xid) -> $crate::core::xconnection::Result<()> {
                 Ok(self.api.kill_client(id)?)
             }
+
+            fn create_input_window(&self, r: Region) -> $crate::core::xconnection::Result<Xid> {
+                Ok(self.api.create_input_window(r)?)
+            }
         }
     }
 }
@@ -387,9 +406,27 @@ macro_rules! __xcb_impl_xclientconfig {
                 Ok(self.api.set_client_attributes(id, data)?)
             }
 
+            fn set_clients_attributes(&self, ids: &[Xid], data: &[ClientAttr]) -> $crate::core::xconnection::Result<()> {
+                Ok(self.api.set_clients_attributes(ids, data)?)
+            }
+
             fn get_window_attributes(&self, id: Xid) -> $crate::core::xconnection::Result<$crate::core::xconnection::WindowAttributes> {
                 Ok(self.api.get_window_attributes(id)?)
             }
+
+            fn set_window_background(&self, id: Xid, color: $crate::draw::Color) -> $crate::core::xconnection::Result<()> {
+                Ok(self.api.set_window_background(id, color.rgb_u32())?)
+            }
+
+            fn set_client_border_colors(
+                &self,
+                id: Xid,
+                outer: $crate::draw::Color,
+                inner: $crate::draw::Color,
+                inner_px: u32,
+            ) -> $crate::core::xconnection::Result<()> {
+                Ok(self.api.set_client_border_colors(id, outer.rgb_u32(), inner.rgb_u32(), inner_px)?)
+            }
         }
     }
 }
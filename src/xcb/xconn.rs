@@ -13,8 +13,10 @@
  */
 use crate::{
     core::{
-        bindings::{KeyBindings, MouseBindings},
+        bindings::{KeyBindings, KeyCodeValue, MouseBindings},
         data_types::{Point, Region},
+        helpers::try_keycodes_from_xmodmap,
+        input::{InputDevice, LockState, PointerConfig},
         manager::WindowManager,
         screen::Screen,
         xconnection::{
@@ -46,6 +48,7 @@ impl XcbConnection {
         let api = Api::new()?;
         let check_win = api.check_window();
         api.set_randr_notify_mask()?;
+        api.set_xkb_notify_mask()?;
 
         Ok(Self { check_win, api })
     }
@@ -110,8 +113,7 @@ impl XConn for XcbConnection {
     }
 
     fn cleanup(&self) -> Result<()> {
-        self.api.ungrab_keys()?;
-        self.api.ungrab_mouse_buttons()?;
+        self.ungrab_keys()?;
         let net_name = Atom::NetActiveWindow.as_ref();
         self.api.delete_prop(self.api.root(), net_name)?;
         self.api.destroy_client(self.check_win)?;
@@ -137,4 +139,61 @@ impl XConn for XcbConnection {
 
         Ok(())
     }
+
+    fn ungrab_keys(&self) -> Result<()> {
+        self.api.ungrab_keys()?;
+        self.api.ungrab_mouse_buttons()?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn grab_keyboard(&self) -> Result<()> {
+        Ok(self.api.grab_keyboard()?)
+    }
+
+    fn ungrab_keyboard(&self) -> Result<()> {
+        self.api.ungrab_keyboard()?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn grab_pointer(&self) -> Result<()> {
+        Ok(self.api.grab_pointer()?)
+    }
+
+    fn ungrab_pointer(&self) -> Result<()> {
+        self.api.ungrab_pointer()?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn pointer_devices(&self) -> Result<Vec<InputDevice>> {
+        Ok(self.api.pointer_devices()?)
+    }
+
+    fn keyboard_devices(&self) -> Result<Vec<InputDevice>> {
+        Ok(self.api.keyboard_devices()?)
+    }
+
+    fn configure_pointer_device(&self, device: &InputDevice, conf: &PointerConfig) -> Result<()> {
+        self.api.configure_pointer_device(device, conf)?;
+        self.flush();
+
+        Ok(())
+    }
+
+    fn refresh_keymap(&self) -> Result<()> {
+        Ok(self.api.refresh_code_map()?)
+    }
+
+    fn keycodes(&self) -> Result<HashMap<String, KeyCodeValue>> {
+        Ok(try_keycodes_from_xmodmap().map_err(|e| XcbError::Raw(e.to_string()))?)
+    }
+
+    fn lock_state(&self) -> Result<LockState> {
+        Ok(self.api.lock_state()?)
+    }
 }
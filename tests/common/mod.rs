@@ -1,6 +1,6 @@
 use penrose::{
     core::{
-        bindings::{KeyBindings, KeyCode, KeyEventHandler},
+        bindings::{KeyBindings, KeyChord, KeyCode, KeyEventHandler},
         data_types::Region,
         layout::*,
         manager::WindowManager,
@@ -9,7 +9,7 @@ use penrose::{
         workspace::Workspace,
         xconnection::XConn,
     },
-    Forward,
+    Forward, More, PenroseError,
 };
 
 use std::collections::HashMap;
@@ -24,6 +24,9 @@ pub const FOCUS_CHANGE_CODE: KeyCode = KeyCode { mask: 0, code: 4 };
 pub const KILL_CLIENT_CODE: KeyCode = KeyCode { mask: 0, code: 5 };
 pub const ADD_WORKSPACE_CODE: KeyCode = KeyCode { mask: 0, code: 6 };
 pub const CLIENT_TO_WORKSPACE_CODE: KeyCode = KeyCode { mask: 0, code: 7 };
+pub const ERROR_CODE: KeyCode = KeyCode { mask: 0, code: 8 };
+pub const KEY_CHORD_CODE: KeyCode = KeyCode { mask: 0, code: 9 };
+pub const MAIN_RATIO_CODE: KeyCode = KeyCode { mask: 0, code: 10 };
 
 pub fn simple_screen(n: usize) -> Screen {
     Screen::new(
@@ -73,11 +76,25 @@ pub fn test_bindings<X: XConn>() -> KeyBindings<X> {
         KILL_CLIENT_CODE,
         Box::new(|wm: &mut WindowManager<X>| wm.kill_client()) as KeyEventHandler<X>,
     );
+    bindings.insert(
+        ERROR_CODE,
+        Box::new(|_: &mut WindowManager<X>| Err(PenroseError::Raw("test error".into())))
+            as KeyEventHandler<X>,
+    );
     bindings.insert(
         CLIENT_TO_WORKSPACE_CODE,
         Box::new(|wm: &mut WindowManager<X>| wm.client_to_workspace(&Selector::Index(1)))
             as KeyEventHandler<X>,
     );
+    bindings.insert(
+        KEY_CHORD_CODE,
+        Box::new(|wm: &mut WindowManager<X>| wm.enter_key_chord(KeyChord::new(HashMap::new())))
+            as KeyEventHandler<X>,
+    );
+    bindings.insert(
+        MAIN_RATIO_CODE,
+        Box::new(|wm: &mut WindowManager<X>| wm.update_main_ratio(More)) as KeyEventHandler<X>,
+    );
 
     bindings
 }
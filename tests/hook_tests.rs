@@ -4,91 +4,21 @@ extern crate penrose;
 
 use penrose::{
     core::{
-        client::Client,
+        bindings::{MouseButton, MouseEvent, MouseEventKind, MouseState},
         config::Config,
-        data_types::Region,
+        data_types::{Point, Region},
         hooks::{Hook, Hooks},
+        input::LockState,
         manager::WindowManager,
-        screen::Screen,
-        xconnection::{Atom, Prop, PropertyEvent, Result, XConn, XError, XEvent, Xid},
+        xconnection::{MockXConn, PropertyEvent, XConn, XEvent, Xid},
     },
     logging_error_handler,
 };
 
-use std::{
-    cell::{Cell, RefCell},
-    collections::HashMap,
-    rc::Rc,
-};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 mod common;
 
-pub struct TestXConn {
-    screens: Vec<Screen>,
-    events: Cell<Vec<XEvent>>,
-    focused: Cell<Xid>,
-    unmanaged_ids: Vec<Xid>,
-}
-
-impl TestXConn {
-    /// Set up a new [MockXConn] with pre-defined [Screen]s and an event stream to pull from
-    pub fn new(screens: Vec<Screen>, events: Vec<XEvent>, unmanaged_ids: Vec<Xid>) -> Self {
-        Self {
-            screens,
-            events: Cell::new(events),
-            focused: Cell::new(0),
-            unmanaged_ids,
-        }
-    }
-}
-
-__impl_stub_xcon! {
-    for TestXConn;
-
-    atom_queries: {}
-    client_properties: {
-        fn mock_get_prop(&self, id: Xid, name: &str) -> Result<Prop> {
-            if name == Atom::NetWmName.as_ref() {
-                Ok(Prop::UTF8String(vec!["mock name".into()]))
-            } else {
-                Err(XError::MissingProperty(name.into(), id))
-            }
-        }
-    }
-    client_handler: {
-        fn mock_focus_client(&self, id: Xid) -> Result<()> {
-            self.focused.replace(id);
-            Ok(())
-        }
-    }
-    client_config: {}
-    event_handler: {
-        fn mock_wait_for_event(&self) -> Result<XEvent> {
-            let mut remaining = self.events.replace(vec![]);
-            if remaining.is_empty() {
-                return Err(XError::ConnectionClosed);
-            }
-            let next = remaining.remove(0);
-            self.events.set(remaining);
-            Ok(next)
-        }
-    }
-    state: {
-        fn mock_current_screens(&self) -> Result<Vec<Screen>> {
-            Ok(self.screens.clone())
-        }
-
-        fn mock_focused_client(&self) -> Result<Xid> {
-            Ok(self.focused.get())
-        }
-    }
-    conn: {
-        fn mock_is_managed_client(&self, c: &Client) -> bool {
-            !self.unmanaged_ids.contains(&c.id())
-        }
-    }
-}
-
 struct TestHook {
     method: &'static str,
     calls: Rc<RefCell<Vec<String>>>,
@@ -123,18 +53,48 @@ macro_rules! __impl_test_hook {
 __impl_test_hook! {
     client_name_updated => Xid, &str, bool;
     client_added_to_workspace => Xid, usize;
+    error_raised => &str;
     event_handled => ;
     focus_change => Xid;
+    input_devices_changed => ;
+    key_chord_changed => &[&str];
     layout_applied => usize, usize;
     layout_change => usize, usize;
+    lock_state_changed => LockState;
+    main_ratio_changed => usize, f32;
     new_client => Xid;
     randr_notify => ;
     remove_client => Xid;
+    root_window_clicked => MouseButton, Point;
     screen_change => usize;
     screens_updated => &[Region];
     startup => ;
     workspace_change => usize, usize;
     workspaces_updated => &[&str], usize;
+    workspace_overflow => Xid, usize, usize;
+}
+
+// Run the window manager over 'events' and return the calls made to 'method'
+fn hook_calls(method: &'static str, events: Vec<XEvent>, config: Config) -> Vec<String> {
+    let calls = Rc::new(RefCell::new(vec![]));
+    let hooks: Hooks<MockXConn> = vec![Box::new(TestHook {
+        method,
+        calls: Rc::clone(&calls),
+    })];
+
+    let mut events = events;
+    events.push(XEvent::KeyPress(common::EXIT_CODE));
+
+    let screens = vec![common::simple_screen(0), common::simple_screen(1)];
+    let conn = MockXConn::new(screens, events, vec![]);
+    let mut wm = WindowManager::new(config, conn, hooks, logging_error_handler());
+
+    wm.init().unwrap();
+    wm.grab_keys_and_run(common::test_bindings(), HashMap::new())
+        .unwrap();
+    drop(wm);
+
+    Rc::try_unwrap(calls).unwrap().into_inner()
 }
 
 test_cases! {
@@ -149,14 +109,24 @@ test_cases! {
         XEvent::MapRequest(1, false),
         XEvent::KeyPress(common::CLIENT_TO_WORKSPACE_CODE)
     ]);
+    case: error_raised => ("error_raised", 1, vec![XEvent::KeyPress(common::ERROR_CODE)]);
     case: event_handled => ("event_handled", 2, vec![XEvent::ScreenChange]);
     case: focus_change => ("focus_change", 3, vec![
         XEvent::MapRequest(1, false),
         XEvent::MapRequest(2, false),
         XEvent::KeyPress(common::FOCUS_CHANGE_CODE)
     ]);
+    case: input_devices_changed => ("input_devices_changed", 1, vec![XEvent::InputDevicesChanged]);
+    case: key_chord_changed => ("key_chord_changed", 2, vec![XEvent::KeyPress(common::KEY_CHORD_CODE)]);
     case: layout_applied => ("layout_applied", 3, vec![XEvent::KeyPress(common::LAYOUT_CHANGE_CODE)]);
     case: layout_change => ("layout_change", 1, vec![XEvent::KeyPress(common::LAYOUT_CHANGE_CODE)]);
+    case: lock_state_changed => ("lock_state_changed", 1, vec![
+        XEvent::LockStateChanged(LockState { caps_lock: true, num_lock: false })
+    ]);
+    case: main_ratio_changed => ("main_ratio_changed", 2, vec![
+        XEvent::KeyPress(common::MAIN_RATIO_CODE),
+        XEvent::KeyPress(common::MAIN_RATIO_CODE),
+    ]);
     case: new_client => ("new_client", 1, vec![XEvent::MapRequest(1, false)]);
     case: randr_notify => ("randr_notify", 1, vec![XEvent::RandrNotify]);
     case: remove_client => ("remove_client", 1, vec![
@@ -164,6 +134,14 @@ test_cases! {
         XEvent::KeyPress(common::KILL_CLIENT_CODE),
         XEvent::Destroy(1),
     ]);
+    case: root_window_clicked => ("root_window_clicked", 1, vec![
+        XEvent::MouseEvent(MouseEvent::new(
+            42, 10, 10, 10, 10, MouseState::new(MouseButton::Right, vec![]), MouseEventKind::Press
+        )),
+        XEvent::MouseEvent(MouseEvent::new(
+            42, 10, 10, 10, 10, MouseState::new(MouseButton::Right, vec![]), MouseEventKind::Release
+        )),
+    ]);
     case: screen_change => ("screen_change", 1, vec![XEvent::KeyPress(common::SCREEN_CHANGE_CODE)]);
     case: screens_updated => ("screens_updated", 1, vec![XEvent::RandrNotify]);
     case: startup => ("startup", 1, vec![]);
@@ -171,24 +149,25 @@ test_cases! {
     case: workspaces_updated => ("workspaces_updated", 1, vec![XEvent::KeyPress(common::ADD_WORKSPACE_CODE)]);
 
     body: {
-        let calls = Rc::new(RefCell::new(vec![]));
-        let hooks: Hooks<TestXConn> = vec![Box::new(TestHook {
-            method,
-            calls: Rc::clone(&calls),
-        })];
-
-        let mut events = events;
-        events.push(XEvent::KeyPress(common::EXIT_CODE));
-
-        let screens = vec![common::simple_screen(0), common::simple_screen(1)];
-        let conn = TestXConn::new(screens, events, vec![]);
-        let mut wm = WindowManager::new(Config::default(), conn, hooks, logging_error_handler());
-
-        wm.init().unwrap();
-        wm.grab_keys_and_run(common::test_bindings(), HashMap::new()).unwrap();
-        drop(wm);
-
-        let actual_calls = Rc::try_unwrap(calls).unwrap().into_inner();
+        let actual_calls = hook_calls(method, events, Config::default());
         assert_eq!(actual_calls, [method].repeat(n_calls));
     }
 }
+
+#[test]
+fn workspace_overflow_triggers() {
+    let config = Config::default()
+        .builder()
+        .max_clients_per_workspace(Some(2))
+        .build()
+        .unwrap();
+    let events = vec![
+        XEvent::MapRequest(1, false),
+        XEvent::MapRequest(2, false),
+        XEvent::MapRequest(3, false),
+        XEvent::MapRequest(4, false),
+    ];
+
+    let actual_calls = hook_calls("workspace_overflow", events, config);
+    assert_eq!(actual_calls, ["workspace_overflow"].repeat(2));
+}
@@ -13,6 +13,7 @@ use penrose::{
         layout::{floating, side_stack, LayoutFunc},
         manager::WindowManager,
         screen::Screen,
+        state::State,
         xconnection::{Atom, Prop, Result, XError, XEvent, Xid},
     },
     logging_error_handler, PenroseError,
@@ -123,6 +124,20 @@ fn serde_windowmanager_can_be_serialized() {
     assert!(as_json.is_ok());
 }
 
+#[cfg(feature = "serde")]
+#[test]
+fn serde_snapshot_roundtrips_through_json() {
+    let mut wm = get_seeded_wm(true);
+    wm.grab_keys_and_run(common::test_bindings(), HashMap::new())
+        .unwrap();
+    let state = wm.snapshot();
+    let as_json = serde_json::to_string(&state).unwrap();
+    let restored: State = serde_json::from_str(&as_json).unwrap();
+
+    assert_eq!(restored, state);
+    assert_eq!(restored.client(2).map(|c| c.workspace), Some(1));
+}
+
 #[cfg(feature = "serde")]
 #[test]
 fn serde_windowmanager_can_be_deserialized() {
@@ -210,3 +225,62 @@ fn serde_running_after_hydration_works() {
         .grab_keys_and_run(common::test_bindings(), HashMap::new())
         .unwrap();
 }
+
+// Remove fields from the objects found at the given path, with "*" matching every entry of a map
+#[cfg(feature = "serde")]
+fn strip_fields(json: &mut serde_json::Value, path: &[&str], fields: &[&str]) {
+    match path.split_first() {
+        Some((&"*", rest)) => {
+            let entries = json.as_object_mut().unwrap();
+            assert!(!entries.is_empty(), "nothing to strip fields from");
+            for v in entries.values_mut() {
+                strip_fields(v, rest, fields);
+            }
+        }
+        Some((key, rest)) => strip_fields(&mut json[*key], rest, fields),
+        None => {
+            let obj = json.as_object_mut().unwrap();
+            for field in fields {
+                assert!(obj.remove(*field).is_some(), "{} was not serialized", field);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_state_from_before_newer_fields_can_be_deserialized() {
+    let mut wm = get_seeded_wm(true);
+    wm.grab_keys_and_run(common::test_bindings(), HashMap::new())
+        .unwrap();
+    let mut json = serde_json::to_value(&wm).unwrap();
+    strip_fields(&mut json, &["config"], &["urgent_border"]);
+    strip_fields(
+        &mut json,
+        &["clients"],
+        &["urgent_border", "urgent_clients"],
+    );
+    strip_fields(&mut json, &["screens"], &["struts"]);
+    strip_fields(&mut json, &["clients"], &["border_overrides"]);
+    strip_fields(&mut json, &["clients"], &["auto_fullscreen"]);
+    strip_fields(&mut json, &["clients"], &["expected_unmaps"]);
+    strip_fields(&mut json, &["screens"], &["history"]);
+    strip_fields(&mut json, &["clients"], &["focus_history"]);
+    strip_fields(&mut json, &["clients"], &["tiled_regions"]);
+    strip_fields(&mut json, &["clients"], &["swallowed"]);
+    strip_fields(
+        &mut json,
+        &["clients", "inner", "*"],
+        &["no_swallow", "terminal"],
+    );
+    strip_fields(&mut json, &["clients"], &["focused_border_overrides"]);
+    strip_fields(&mut json, &["screens"], &["extra_tags"]);
+    strip_fields(&mut json, &["clients", "inner", "*"], &["extra_tags"]);
+    strip_fields(&mut json, &["clients", "inner", "*"], &["sticky"]);
+    strip_fields(&mut json, &["screens"], &["scale_by_dpi"]);
+    strip_fields(&mut json, &["clients"], &["unmaximized"]);
+    strip_fields(&mut json, &["screens"], &["bars"]);
+    strip_fields(&mut json, &["screens"], &["bars_visible"]);
+    let res: std::result::Result<WindowManager<EarlyExitConn>, _> = serde_json::from_value(json);
+    assert!(res.is_ok());
+}